@@ -0,0 +1,495 @@
+//! # Animation
+//!
+//! Easing curves, animated values, and timelines for sequencing them.
+
+use crate::{AnimatePass, Rgba, Xy};
+
+
+
+/// A curve mapping linear progress (from `0.0` to `1.0`) to eased progress.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Easing {
+    #[default]
+    Linear,
+    /// Starts slowly and speeds up (cubic).
+    EaseIn,
+    /// Starts quickly and slows down (cubic).
+    EaseOut,
+    /// Starts and ends slowly (cubic).
+    EaseInOut,
+    /// A CSS-style cubic bezier curve from `(0, 0)` to `(1, 1)` with the
+    /// control points `(x1, y1)` and `(x2, y2)`.
+    CubicBezier(f32, f32, f32, f32),
+    /// A damped spring that overshoots the target before settling.
+    ///
+    /// `oscillations` is the number of times it swings past the target over the
+    /// course of the animation, and `damping` is how quickly those swings
+    /// decay.
+    Spring { oscillations: f32, damping: f32 },
+}
+
+impl Easing {
+    pub const EASE: Self = Self::CubicBezier(0.25, 0.1, 0.25, 1.0);
+
+    /// Apply the curve to the given progress, which is clamped to `0.0..=1.0`.
+    ///
+    /// The result is exactly `0.0` at the start and `1.0` at the end, but may
+    /// fall outside of that range in between.
+    pub fn apply(&self, t: f32) -> f32 {
+        let t = t.clamp(0.0, 1.0);
+        if t == 0.0 || t == 1.0 {
+            return t;
+        }
+
+        match *self {
+            Self::Linear => t,
+            Self::EaseIn => t * t * t,
+            Self::EaseOut => 1.0 - (1.0 - t).powi(3),
+            Self::EaseInOut => {
+                if t < 0.5 {
+                    4.0 * t * t * t
+                } else {
+                    1.0 - (-2.0 * t + 2.0).powi(3) / 2.0
+                }
+            }
+            Self::CubicBezier(x1, y1, x2, y2) => {
+                let s = solve_bezier(t, x1, x2);
+                bezier(s, y1, y2)
+            }
+            Self::Spring {
+                oscillations,
+                damping,
+            } => {
+                1.0 - (-damping * t).exp()
+                    * (oscillations * std::f32::consts::TAU * t).cos()
+                    * (1.0 - t)
+            }
+        }
+    }
+}
+
+/// One axis of a cubic bezier curve from 0 to 1 with the given control points.
+fn bezier(s: f32, p1: f32, p2: f32) -> f32 {
+    let inv = 1.0 - s;
+    3.0 * inv * inv * s * p1 + 3.0 * inv * s * s * p2 + s * s * s
+}
+
+/// Find the curve parameter whose x coordinate is `x`.
+fn solve_bezier(x: f32, x1: f32, x2: f32) -> f32 {
+    // Newton's method converges quickly for most curves...
+    let mut s = x;
+    for _ in 0..8 {
+        let error = bezier(s, x1, x2) - x;
+        if error.abs() < 1e-5 {
+            return s;
+        }
+        let inv = 1.0 - s;
+        let slope = 3.0 * inv * inv * x1 + 6.0 * inv * s * (x2 - x1) + 3.0 * s * s * (1.0 - x2);
+        if slope.abs() < 1e-6 {
+            break;
+        }
+        s = (s - error / slope).clamp(0.0, 1.0);
+    }
+
+    // ...but falls back to bisection for the ones with flat sections.
+    let (mut low, mut high) = (0.0, 1.0);
+    s = x;
+    for _ in 0..32 {
+        let value = bezier(s, x1, x2);
+        if (value - x).abs() < 1e-5 {
+            break;
+        }
+        if value < x {
+            low = s;
+        } else {
+            high = s;
+        }
+        s = (low + high) / 2.0;
+    }
+
+    s
+}
+
+
+
+/// A value that can be animated.
+pub trait Interpolate: Copy {
+    /// Get the value `t` of the way from `self` to `other`. `t` may be outside
+    /// of `0.0..=1.0` for easing curves that overshoot.
+    fn interpolate(&self, other: &Self, t: f32) -> Self;
+}
+
+impl Interpolate for f32 {
+    fn interpolate(&self, other: &Self, t: f32) -> Self {
+        self + (other - self) * t
+    }
+}
+
+impl Interpolate for Xy<f32> {
+    fn interpolate(&self, other: &Self, t: f32) -> Self {
+        Xy::new(
+            self.x.interpolate(&other.x, t),
+            self.y.interpolate(&other.y, t),
+        )
+    }
+}
+
+impl Interpolate for Rgba {
+    fn interpolate(&self, other: &Self, t: f32) -> Self {
+        let channel = |from: u8, to: u8| {
+            (from as f32)
+                .interpolate(&(to as f32), t)
+                .round()
+                .clamp(0.0, 255.0) as u8
+        };
+
+        Rgba::new(
+            channel(self.r, other.r),
+            channel(self.g, other.g),
+            channel(self.b, other.b),
+            channel(self.a, other.a),
+        )
+    }
+}
+
+
+
+/// A value that moves toward a target over time.
+#[derive(Clone, Debug)]
+pub struct Animated<T: Interpolate> {
+    start: T,
+    current: T,
+    target: T,
+    duration_ms: f32,
+    elapsed_ms: f32,
+    easing: Easing,
+}
+
+pub type AnimatedF32 = Animated<f32>;
+pub type AnimatedXy = Animated<Xy<f32>>;
+pub type AnimatedRgba = Animated<Rgba>;
+
+impl<T: Interpolate> Animated<T> {
+    pub const fn new(value: T) -> Self {
+        Self {
+            start: value,
+            current: value,
+            target: value,
+            duration_ms: 0.0,
+            elapsed_ms: 0.0,
+            easing: Easing::Linear,
+        }
+    }
+
+    pub fn with_easing(mut self, easing: Easing) -> Self {
+        self.easing = easing;
+        self
+    }
+
+    /// Set the curve used by this and future animations.
+    pub fn set_easing(&mut self, easing: Easing) {
+        self.easing = easing;
+    }
+
+    #[inline]
+    pub const fn get(&self) -> T {
+        self.current
+    }
+
+    #[inline]
+    pub const fn target(&self) -> T {
+        self.target
+    }
+
+    #[inline]
+    pub fn is_animating(&self) -> bool {
+        self.elapsed_ms < self.duration_ms
+    }
+
+    /// Start moving from the current value to `target`, arriving after
+    /// `time_ms`. Non-positive times jump straight to the target.
+    pub fn move_to(&mut self, target: T, time_ms: f32) {
+        self.start = self.current;
+        self.target = target;
+        self.elapsed_ms = 0.0;
+        if time_ms > 0.0 {
+            self.duration_ms = time_ms;
+        } else {
+            self.duration_ms = 0.0;
+            self.current = target;
+        }
+    }
+
+    /// Jump to a value, cancelling any animation in progress.
+    pub fn set(&mut self, value: T) {
+        self.move_to(value, 0.0);
+    }
+
+    /// Advance the animation, returning `true` if it has finished.
+    pub fn advance(&mut self, ms: f32) -> bool {
+        if !self.is_animating() {
+            self.current = self.target;
+            return true;
+        }
+
+        self.elapsed_ms = (self.elapsed_ms + ms).min(self.duration_ms);
+        if !self.is_animating() {
+            self.current = self.target;
+            return true;
+        }
+
+        let t = self.easing.apply(self.elapsed_ms / self.duration_ms);
+        self.current = self.start.interpolate(&self.target, t);
+
+        false
+    }
+}
+
+
+
+/// A handle to a track in a [`Timeline`].
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct TrackId(usize);
+
+#[derive(Clone, Debug)]
+struct Track {
+    start_ms: f32,
+    duration_ms: f32,
+    easing: Easing,
+}
+
+/// A set of tracks that play over a shared clock, used to sequence and stagger
+/// animations of several properties.
+///
+/// Each track only reports its progress, see [`Timeline::progress`] and
+/// [`Timeline::sample`].
+///
+/// ```
+/// # use abi::{Easing, Rgba, Timeline};
+/// let mut timeline = Timeline::new();
+/// let fade = timeline.then(200.0, Easing::EaseOut);
+/// let slide = timeline.with(300.0, Easing::EaseInOut);
+/// let items = timeline.stagger(3, 50.0, 100.0, Easing::Linear);
+///
+/// timeline.advance_by(200.0);
+/// assert_eq!(timeline.sample(fade, &Rgba::NONE, &Rgba::WHITE), Rgba::WHITE);
+/// assert!(timeline.progress(slide) < 1.0);
+/// assert_eq!(timeline.progress(items[0]), 0.0);
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct Timeline {
+    tracks: Vec<Track>,
+    elapsed_ms: f32,
+    /// The start of the most recently added track.
+    sequence_start_ms: f32,
+    /// The end of the most recently added track, or group of tracks added with
+    /// [`Timeline::with`] or [`Timeline::stagger`].
+    sequence_end_ms: f32,
+}
+
+impl Timeline {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a track that starts at the given time.
+    pub fn at(&mut self, start_ms: f32, duration_ms: f32, easing: Easing) -> TrackId {
+        let id = TrackId(self.tracks.len());
+        let duration_ms = duration_ms.max(0.0);
+        self.tracks.push(Track {
+            start_ms,
+            duration_ms,
+            easing,
+        });
+        self.sequence_start_ms = start_ms;
+        self.sequence_end_ms = start_ms + duration_ms;
+
+        id
+    }
+
+    /// Add a track that starts once the most recently added track ends.
+    pub fn then(&mut self, duration_ms: f32, easing: Easing) -> TrackId {
+        self.at(self.sequence_end_ms, duration_ms, easing)
+    }
+
+    /// Add a track that starts at the same time as the most recently added
+    /// track.
+    pub fn with(&mut self, duration_ms: f32, easing: Easing) -> TrackId {
+        let end_ms = self.sequence_end_ms;
+        let id = self.at(self.sequence_start_ms, duration_ms, easing);
+        self.sequence_end_ms = self.sequence_end_ms.max(end_ms);
+
+        id
+    }
+
+    /// Add `count` tracks, each starting `offset_ms` after the one before it,
+    /// with the first starting once the most recently added track ends.
+    pub fn stagger(
+        &mut self,
+        count: usize,
+        offset_ms: f32,
+        duration_ms: f32,
+        easing: Easing,
+    ) -> Vec<TrackId> {
+        let start_ms = self.sequence_end_ms;
+        let mut end_ms = start_ms;
+        let ids = (0..count)
+            .map(|index| {
+                let id = self.at(start_ms + offset_ms * index as f32, duration_ms, easing);
+                end_ms = end_ms.max(self.sequence_end_ms);
+                id
+            })
+            .collect();
+        self.sequence_end_ms = end_ms;
+
+        ids
+    }
+
+    /// The time at which every track has finished.
+    pub fn duration_ms(&self) -> f32 {
+        self.tracks
+            .iter()
+            .map(|track| track.start_ms + track.duration_ms)
+            .fold(0.0, f32::max)
+    }
+
+    #[inline]
+    pub fn elapsed_ms(&self) -> f32 {
+        self.elapsed_ms
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.elapsed_ms >= self.duration_ms()
+    }
+
+    /// Move the clock to the given time.
+    pub fn seek(&mut self, ms: f32) {
+        self.elapsed_ms = ms.clamp(0.0, self.duration_ms());
+    }
+
+    pub fn restart(&mut self) {
+        self.elapsed_ms = 0.0;
+    }
+
+    /// Get the eased progress of a track, which is `0.0` before it starts and
+    /// `1.0` after it ends.
+    pub fn progress(&self, id: TrackId) -> f32 {
+        let track = &self.tracks[id.0];
+        if track.duration_ms == 0.0 {
+            return if self.elapsed_ms >= track.start_ms {
+                1.0
+            } else {
+                0.0
+            };
+        }
+
+        track
+            .easing
+            .apply((self.elapsed_ms - track.start_ms) / track.duration_ms)
+    }
+
+    /// Get the value of a property animated by a track from `from` to `to`.
+    pub fn sample<T: Interpolate>(&self, id: TrackId, from: &T, to: &T) -> T {
+        from.interpolate(to, self.progress(id))
+    }
+
+    /// Advance the clock, returning `true` if every track has finished.
+    pub fn advance_by(&mut self, ms: f32) -> bool {
+        self.elapsed_ms = (self.elapsed_ms + ms).min(self.duration_ms());
+        self.is_finished()
+    }
+
+    /// Advance the clock by the time passed to [`crate::Element::animate`],
+    /// requesting another frame and a render until every track has finished.
+    pub fn advance(&mut self, pass: &mut AnimatePass<'_>, dt: f64) -> bool {
+        if self.is_finished() {
+            return true;
+        }
+
+        let finished = self.advance_by((dt * 1000.0) as f32);
+        pass.request_render();
+        if !finished {
+            pass.request_animate();
+        }
+
+        finished
+    }
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn easing_endpoints() {
+        for easing in [
+            Easing::Linear,
+            Easing::EaseIn,
+            Easing::EaseOut,
+            Easing::EaseInOut,
+            Easing::EASE,
+            Easing::Spring {
+                oscillations: 2.0,
+                damping: 4.0,
+            },
+        ] {
+            assert_eq!(easing.apply(0.0), 0.0);
+            assert_eq!(easing.apply(1.0), 1.0);
+            assert_eq!(easing.apply(-1.0), 0.0);
+            assert_eq!(easing.apply(2.0), 1.0);
+        }
+
+        assert!(Easing::EaseIn.apply(0.5) < 0.5);
+        assert!(Easing::EaseOut.apply(0.5) > 0.5);
+        assert!((Easing::CubicBezier(0.0, 0.0, 1.0, 1.0).apply(0.3) - 0.3).abs() < 1e-3);
+    }
+
+    #[test]
+    fn animated_values() {
+        let mut value = AnimatedF32::new(0.0).with_easing(Easing::EaseIn);
+        value.move_to(10.0, 100.0);
+        assert!(!value.advance(50.0));
+        assert!(value.get() < 5.0);
+        assert!(value.advance(50.0));
+        assert_eq!(value.get(), 10.0);
+
+        let mut color = AnimatedRgba::new(Rgba::BLACK);
+        color.move_to(Rgba::WHITE, 0.0);
+        assert_eq!(color.get(), Rgba::WHITE);
+        assert!(color.advance(16.0));
+
+        let mut position = AnimatedXy::new(Xy::ZERO);
+        position.move_to(Xy::new(10.0, 20.0), 100.0);
+        position.advance(25.0);
+        assert_eq!(position.get(), Xy::new(2.5, 5.0));
+    }
+
+    #[test]
+    fn timeline_sequencing() {
+        let mut timeline = Timeline::new();
+        let first = timeline.then(100.0, Easing::Linear);
+        let second = timeline.then(100.0, Easing::Linear);
+        let parallel = timeline.with(50.0, Easing::Linear);
+        let staggered = timeline.stagger(3, 10.0, 100.0, Easing::Linear);
+        let last = timeline.then(10.0, Easing::Linear);
+
+        assert_eq!(timeline.duration_ms(), 330.0);
+
+        timeline.advance_by(150.0);
+        assert_eq!(timeline.progress(first), 1.0);
+        assert_eq!(timeline.progress(second), 0.5);
+        assert_eq!(timeline.progress(parallel), 1.0);
+        assert_eq!(timeline.progress(staggered[0]), 0.0);
+
+        assert!(!timeline.advance_by(110.0));
+        assert_eq!(timeline.progress(staggered[0]), 0.6);
+        assert_eq!(timeline.progress(staggered[2]), 0.4);
+        assert_eq!(timeline.progress(last), 0.0);
+
+        assert!(timeline.advance_by(1000.0));
+        assert_eq!(timeline.elapsed_ms(), 330.0);
+        assert_eq!(timeline.progress(last), 1.0);
+    }
+}
@@ -0,0 +1,408 @@
+//! # Application Hosting
+//!
+//! See [`AppHost`] and [`AppRegions`] for more information.
+
+use std::{
+    collections::BTreeMap,
+    time::{Duration, Instant},
+};
+
+use crate::{
+    Aabb2D, AppViews, Column, DriverControl, DriverInput, DriverInputEvent, Label, Rgba, ViewId, Xy,
+};
+
+
+
+/// Identifies one of the applications in an [`AppHost`].
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+#[repr(transparent)]
+pub struct AppId(pub u64);
+
+/// The applications hosted by a single driver process, each with its own
+/// [`AppViews`].
+///
+/// Input for every application is multiplexed through the same
+/// [`DriverInput`], which is addressed to a view of one of them with its
+/// [`app`](DriverInput::app) and [`id`](DriverInput::id).
+pub struct AppHost {
+    apps: BTreeMap<AppId, AppViews>,
+    next_id: u64,
+}
+
+impl Default for AppHost {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl AppHost {
+    pub fn new() -> Self {
+        Self {
+            apps: BTreeMap::new(),
+            next_id: 1,
+        }
+    }
+
+    /// Start hosting an application's views.
+    pub fn load(&mut self, views: AppViews) -> AppId {
+        let id = AppId(self.next_id);
+        self.next_id += 1;
+        self.apps.insert(id, views);
+
+        id
+    }
+
+    /// Stop hosting the application, returning its views so that the host can
+    /// drop them before unloading it.
+    pub fn unload(&mut self, id: AppId) -> Option<AppViews> {
+        self.apps.remove(&id)
+    }
+
+    /// The ID of the first loaded application with the given
+    /// [`name`](crate::Manifest::name).
+    pub fn find(&self, name: &str) -> Option<AppId> {
+        self.apps
+            .iter()
+            .find(|(_, views)| views.manifest().name == name)
+            .map(|(id, _)| *id)
+    }
+
+    pub fn get(&self, id: AppId) -> Option<&AppViews> {
+        self.apps.get(&id)
+    }
+
+    pub fn get_mut(&mut self, id: AppId) -> Option<&mut AppViews> {
+        self.apps.get_mut(&id)
+    }
+
+    /// The IDs of every loaded application, in the order they were loaded.
+    pub fn ids(&self) -> impl Iterator<Item = AppId> + '_ {
+        self.apps.keys().copied()
+    }
+
+    /// Every loaded application, in the order they were loaded.
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = (AppId, &mut AppViews)> {
+        self.apps.iter_mut().map(|(id, views)| (*id, views))
+    }
+
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.apps.len()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.apps.is_empty()
+    }
+
+    /// Send the events that the driver received to the application with the
+    /// input's [`app`](DriverInput::app), see [`AppViews::handle_input`].
+    ///
+    /// Returns `false` if there's no such application or view, in which case
+    /// the events are discarded.
+    pub fn handle_input(
+        &mut self,
+        input: &mut DriverInput,
+        events: impl IntoIterator<Item = DriverInputEvent>,
+    ) -> bool {
+        let Some(views) = self.apps.get_mut(&AppId(input.app)) else {
+            events.into_iter().for_each(drop);
+            return false;
+        };
+
+        views.handle_input(input, events)
+    }
+
+    /// Queue the control messages for the view the input is addressed to, see
+    /// [`AppViews::write_controls`].
+    pub fn write_controls(&mut self, input: &mut DriverInput) {
+        match self.apps.get_mut(&AppId(input.app)) {
+            Some(views) => views.write_controls(input),
+            None => input.push_control(DriverControl::RequestClose),
+        }
+    }
+}
+
+
+
+/// The region of the screen occupied by one application view.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct AppRegion {
+    pub app: AppId,
+    pub view: ViewId,
+    pub bounds: Aabb2D,
+}
+
+/// The shell's record of which application view occupies each region of the
+/// screen, used to decide where pointer input goes.
+///
+/// Regions can overlap, in which case the one that was set or raised most
+/// recently is above the others.
+#[derive(Clone, Debug, Default)]
+pub struct AppRegions {
+    /// Every region, from the bottom of the stack to the top.
+    regions: Vec<AppRegion>,
+}
+
+impl AppRegions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the bounds of the view's region, adding it above the others if it's
+    /// new.
+    pub fn set(&mut self, app: AppId, view: ViewId, bounds: Aabb2D) {
+        match self.position(app, view) {
+            Some(index) => self.regions[index].bounds = bounds,
+            None => self.regions.push(AppRegion { app, view, bounds }),
+        }
+    }
+
+    /// Move the view's region above the others, returning `false` if it doesn't
+    /// exist.
+    pub fn raise(&mut self, app: AppId, view: ViewId) -> bool {
+        let Some(index) = self.position(app, view) else {
+            return false;
+        };
+        let region = self.regions.remove(index);
+        self.regions.push(region);
+
+        true
+    }
+
+    /// Remove the view's region, returning `false` if it doesn't exist.
+    pub fn remove(&mut self, app: AppId, view: ViewId) -> bool {
+        let Some(index) = self.position(app, view) else {
+            return false;
+        };
+        self.regions.remove(index);
+
+        true
+    }
+
+    /// Remove every region belonging to the application, such as once it has
+    /// been unloaded.
+    pub fn remove_app(&mut self, app: AppId) {
+        self.regions.retain(|region| region.app != app);
+    }
+
+    pub fn get(&self, app: AppId, view: ViewId) -> Option<&AppRegion> {
+        self.position(app, view).map(|index| &self.regions[index])
+    }
+
+    /// Every region, from the bottom of the stack to the top.
+    pub fn iter(&self) -> impl Iterator<Item = &AppRegion> {
+        self.regions.iter()
+    }
+
+    /// The topmost region containing the point.
+    pub fn hit_test(&self, point: Xy<f32>) -> Option<&AppRegion> {
+        self.regions
+            .iter()
+            .rev()
+            .find(|region| region.bounds.contains(point))
+    }
+
+    /// Address the input to the view at the point, returning `false` (and
+    /// leaving the input unchanged) if there's no view there.
+    pub fn route(&self, point: Xy<f32>, input: &mut DriverInput) -> bool {
+        let Some(region) = self.hit_test(point) else {
+            return false;
+        };
+        input.app = region.app.0;
+        input.id = region.view.0;
+
+        true
+    }
+
+    fn position(&self, app: AppId, view: ViewId) -> Option<usize> {
+        self.regions
+            .iter()
+            .position(|region| region.app == app && region.view == view)
+    }
+}
+
+
+
+/// How long a driver can go without advancing its
+/// [`heartbeat`](DriverInput::heartbeat) before it's considered dead.
+pub const DRIVER_HEARTBEAT_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// How many times a driver is restarted before the host gives up on it.
+pub const MAX_DRIVER_RESTARTS: u32 = 3;
+
+/// What a [`DriverWatchdog`] knows about its driver.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum DriverHealth {
+    Alive,
+    /// The driver hasn't advanced its heartbeat in time, and should be
+    /// restarted.
+    Dead,
+    /// The driver has died more than [`MAX_DRIVER_RESTARTS`] times, and should
+    /// be replaced with a [`crashed_placeholder`].
+    Crashed,
+}
+
+/// The host's side of a driver's [`heartbeat`](DriverInput::heartbeat), which
+/// detects when the driver has panicked or hung so that the host doesn't keep
+/// showing its last frame as if nothing had happened.
+///
+/// When the driver is [`Dead`](DriverHealth::Dead), the host restarts it with
+/// the state it last saved (see [`AppViews::save`]) and calls
+/// [`DriverWatchdog::restarted`].
+#[derive(Clone, Debug)]
+pub struct DriverWatchdog {
+    timeout: Duration,
+    last_heartbeat: u64,
+    last_change: Instant,
+    restarts: u32,
+}
+
+impl DriverWatchdog {
+    pub fn new(now: Instant) -> Self {
+        Self {
+            timeout: DRIVER_HEARTBEAT_TIMEOUT,
+            last_heartbeat: 0,
+            last_change: now,
+            restarts: 0,
+        }
+    }
+
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Check the driver's latest heartbeat.
+    pub fn check(&mut self, heartbeat: u64, now: Instant) -> DriverHealth {
+        if heartbeat != self.last_heartbeat {
+            self.last_heartbeat = heartbeat;
+            self.last_change = now;
+            return DriverHealth::Alive;
+        }
+        if now.saturating_duration_since(self.last_change) < self.timeout {
+            DriverHealth::Alive
+        } else if self.restarts < MAX_DRIVER_RESTARTS {
+            DriverHealth::Dead
+        } else {
+            DriverHealth::Crashed
+        }
+    }
+
+    /// Start watching a restarted driver, whose heartbeat starts again from
+    /// zero.
+    pub fn restarted(&mut self, now: Instant) {
+        self.restarts += 1;
+        self.last_heartbeat = 0;
+        self.last_change = now;
+    }
+
+    /// The number of times the driver has been restarted.
+    #[inline]
+    pub fn restarts(&self) -> u32 {
+        self.restarts
+    }
+}
+
+/// The element shown in place of an application whose driver has
+/// [`Crashed`](DriverHealth::Crashed).
+pub fn crashed_placeholder(app_name: &str) -> Column {
+    Column::new()
+        .with_padding(16.0)
+        .with_gap(8.0)
+        .with(Label::new(format!("{app_name} crashed")).with_font_size(20.0))
+        .with(
+            Label::new("It was restarted too many times, and won't be restarted again.")
+                .with_color(Rgba {
+                    r: 170,
+                    g: 170,
+                    b: 170,
+                    a: 255,
+                }),
+        )
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::{Column, ElementBuilder, MAIN_VIEW, Manifest, VERSION, testing::MockFonts},
+    };
+
+    fn manifest(name: &'static str) -> &'static Manifest {
+        Box::leak(Box::new(Manifest {
+            abi_version: VERSION,
+            name,
+            init: || ElementBuilder::new(Column::new()),
+            dependencies: &[],
+            capabilities: &[],
+            views: &[],
+        }))
+    }
+
+    #[test]
+    fn routes_input_to_apps() {
+        let mut host = AppHost::new();
+        let mut files = AppViews::new(manifest("files"), || Box::new(MockFonts));
+        let files_view = files.open(MAIN_VIEW, Xy::new(100.0, 100.0)).unwrap();
+        let files = host.load(files);
+        let mut notes = AppViews::new(manifest("notes"), || Box::new(MockFonts));
+        let notes_view = notes.open(MAIN_VIEW, Xy::new(100.0, 100.0)).unwrap();
+        let notes = host.load(notes);
+        assert_eq!(host.find("notes"), Some(notes));
+
+        let mut regions = AppRegions::new();
+        regions.set(files, files_view, Aabb2D::new(0.0, 0.0, 100.0, 100.0));
+        regions.set(notes, notes_view, Aabb2D::new(50.0, 50.0, 150.0, 150.0));
+
+        let mut input = DriverInput::new(Aabb2D::new(0.0, 0.0, 100.0, 100.0));
+        assert!(regions.route(Xy::new(75.0, 75.0), &mut input));
+        assert_eq!((input.app, input.id), (notes.0, notes_view.0));
+        regions.raise(files, files_view);
+        assert!(regions.route(Xy::new(75.0, 75.0), &mut input));
+        assert_eq!((input.app, input.id), (files.0, files_view.0));
+        assert!(!regions.route(Xy::new(200.0, 200.0), &mut input));
+
+        assert!(host.handle_input(&mut input, [DriverInputEvent::Close]));
+        assert!(host.get(files).unwrap().is_empty());
+        assert_eq!(host.get(notes).unwrap().len(), 1);
+
+        host.unload(notes);
+        regions.remove_app(notes);
+        input.app = notes.0;
+        assert!(!host.handle_input(&mut input, []));
+        host.write_controls(&mut input);
+        assert_eq!(
+            input.drain_controls().collect::<Vec<_>>(),
+            [DriverControl::RequestClose]
+        );
+        assert!(regions.hit_test(Xy::new(125.0, 125.0)).is_none());
+    }
+
+    #[test]
+    fn driver_watchdog() {
+        let start = Instant::now();
+        let mut watchdog = DriverWatchdog::new(start).with_timeout(Duration::from_secs(1));
+        let mut input = DriverInput::new(Aabb2D::new(0.0, 0.0, 100.0, 100.0));
+
+        input.beat();
+        let later = start + Duration::from_millis(1500);
+        assert_eq!(watchdog.check(input.heartbeat, later), DriverHealth::Alive);
+        assert_eq!(
+            watchdog.check(input.heartbeat, later + Duration::from_millis(500)),
+            DriverHealth::Alive,
+        );
+
+        let mut now = later + Duration::from_secs(1);
+        assert_eq!(watchdog.check(input.heartbeat, now), DriverHealth::Dead);
+        for _ in 0..MAX_DRIVER_RESTARTS {
+            watchdog.restarted(now);
+            assert_eq!(watchdog.check(0, now), DriverHealth::Alive);
+            now += Duration::from_secs(1);
+        }
+        assert_eq!(watchdog.check(0, now), DriverHealth::Crashed);
+        assert_eq!(watchdog.restarts(), MAX_DRIVER_RESTARTS);
+    }
+}
@@ -0,0 +1,587 @@
+//! # Bidirectional Text
+//!
+//! A simplified implementation of the [Unicode Bidirectional Algorithm][uax9]
+//! (without explicit embeddings or isolates), along with cluster segmentation
+//! for cursor movement in complex scripts.
+//!
+//! Text is measured in logical order, and drawn in visual order (see
+//! [`BidiParagraph::visual_clusters`]), one line at a time. Characters are
+//! drawn one at a time, so Arabic letters are shaped by replacing them with
+//! their contextual [presentation forms][forms] (see [`shaped_char_indices`])
+//! before they're measured or drawn.
+//!
+//! [uax9]: https://www.unicode.org/reports/tr9/
+//! [forms]: https://www.unicode.org/charts/PDF/UFE70.pdf
+
+use std::ops::Range;
+
+use crate::TextDirection;
+
+
+
+/// The (simplified) bidirectional character type of a character.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum BidiClass {
+    /// Strong left-to-right.
+    L,
+    /// Strong right-to-left.
+    R,
+    /// Strong right-to-left (Arabic letter).
+    AL,
+    /// European number.
+    EN,
+    /// Arabic number.
+    AN,
+    /// Non-spacing mark.
+    NSM,
+    /// Whitespace.
+    WS,
+    /// Other neutral.
+    ON,
+}
+
+pub fn bidi_class(ch: char) -> BidiClass {
+    match ch as u32 {
+        0x0030..=0x0039 | 0x00B2 | 0x00B3 | 0x00B9 | 0x06F0..=0x06F9 | 0xFF10..=0xFF19 => {
+            BidiClass::EN
+        }
+        0x0660..=0x0669 | 0x066B | 0x066C => BidiClass::AN,
+        0x0009 | 0x000A..=0x000D | 0x0020 | 0x2000..=0x200A | 0x2028 | 0x2029 | 0x3000 => {
+            BidiClass::WS
+        }
+        _ if is_combining_mark(ch) => BidiClass::NSM,
+        0x0590..=0x05FF | 0x07C0..=0x085F | 0xFB1D..=0xFB4F => BidiClass::R,
+        0x0600..=0x07BF | 0x0860..=0x08FF | 0xFB50..=0xFDFF | 0xFE70..=0xFEFF => BidiClass::AL,
+        _ if ch.is_alphabetic() => BidiClass::L,
+        _ => BidiClass::ON,
+    }
+}
+
+/// Find the direction of the first strongly-directional character in `text`.
+pub fn paragraph_direction(text: &str) -> Option<TextDirection> {
+    text.chars().find_map(|ch| match bidi_class(ch) {
+        BidiClass::L => Some(TextDirection::LeftToRight),
+        BidiClass::R | BidiClass::AL => Some(TextDirection::RightToLeft),
+        _ => None,
+    })
+}
+
+
+
+/// A paragraph of text with resolved embedding levels.
+#[derive(Clone, Debug)]
+pub struct BidiParagraph<'text> {
+    text: &'text str,
+    direction: TextDirection,
+    /// The resolved level of each character, indexed alongside `offsets`.
+    levels: Vec<u8>,
+    /// The byte offset of each character.
+    offsets: Vec<usize>,
+}
+
+impl<'text> BidiParagraph<'text> {
+    /// Resolve the levels of `text`, using `default_direction` if it has no
+    /// strong characters.
+    pub fn new(text: &'text str, default_direction: TextDirection) -> Self {
+        let direction = paragraph_direction(text).unwrap_or(default_direction);
+        let base_level = direction.level();
+        let base_class = match direction {
+            TextDirection::LeftToRight => BidiClass::L,
+            TextDirection::RightToLeft => BidiClass::R,
+        };
+
+        let offsets = text
+            .char_indices()
+            .map(|(offset, _)| offset)
+            .collect::<Vec<_>>();
+        let mut classes = text.chars().map(bidi_class).collect::<Vec<_>>();
+
+        // W1: Non-spacing marks take the class of the previous character.
+        let mut previous = base_class;
+        for class in &mut classes {
+            if *class == BidiClass::NSM {
+                *class = previous;
+            }
+            previous = *class;
+        }
+
+        // W2 + W3 + W7: European numbers following Arabic letters are Arabic numbers,
+        //              and those following left-to-right characters are
+        //              left-to-right. Arabic letters then become plain
+        //              right-to-left.
+        let mut last_strong = base_class;
+        for class in &mut classes {
+            match *class {
+                BidiClass::L | BidiClass::R | BidiClass::AL => last_strong = *class,
+                BidiClass::EN if last_strong == BidiClass::AL => *class = BidiClass::AN,
+                BidiClass::EN if last_strong == BidiClass::L => *class = BidiClass::L,
+                _ => {}
+            }
+            if *class == BidiClass::AL {
+                *class = BidiClass::R;
+            }
+        }
+
+        // N1 + N2: Neutrals between characters of the same direction take that
+        //          direction, otherwise they take the paragraph direction.
+        let strong_direction = |class: BidiClass| match class {
+            BidiClass::L => Some(BidiClass::L),
+            BidiClass::R | BidiClass::EN | BidiClass::AN => Some(BidiClass::R),
+            _ => None,
+        };
+        let mut index = 0;
+        while index < classes.len() {
+            if strong_direction(classes[index]).is_some() {
+                index += 1;
+                continue;
+            }
+            let start = index;
+            while index < classes.len() && strong_direction(classes[index]).is_none() {
+                index += 1;
+            }
+            let before = start
+                .checked_sub(1)
+                .and_then(|i| strong_direction(classes[i]))
+                .unwrap_or(base_class);
+            let after = classes
+                .get(index)
+                .and_then(|class| strong_direction(*class))
+                .unwrap_or(base_class);
+            let resolved = if before == after { before } else { base_class };
+            for class in &mut classes[start..index] {
+                *class = resolved;
+            }
+        }
+
+        // I1 + I2: Resolve implicit levels.
+        let mut levels = classes
+            .iter()
+            .map(|class| match (base_level % 2, class) {
+                (0, BidiClass::R) => base_level + 1,
+                (0, BidiClass::EN | BidiClass::AN) => base_level + 2,
+                (1, BidiClass::L | BidiClass::EN | BidiClass::AN) => base_level + 1,
+                _ => base_level,
+            })
+            .collect::<Vec<_>>();
+
+        // L1: Trailing whitespace is reset to the paragraph level.
+        for (level, ch) in levels.iter_mut().rev().zip(text.chars().rev()) {
+            if bidi_class(ch) != BidiClass::WS {
+                break;
+            }
+            *level = base_level;
+        }
+
+        Self {
+            text,
+            direction,
+            levels,
+            offsets,
+        }
+    }
+
+    #[inline]
+    pub fn text(&self) -> &'text str {
+        self.text
+    }
+
+    #[inline]
+    pub fn direction(&self) -> TextDirection {
+        self.direction
+    }
+
+    /// Get the resolved level of the character at the given byte offset.
+    pub fn level_at(&self, offset: usize) -> u8 {
+        match self.offsets.binary_search(&offset) {
+            Ok(index) => self.levels[index],
+            Err(index) => index
+                .checked_sub(1)
+                .map_or(self.direction.level(), |index| self.levels[index]),
+        }
+    }
+
+    /// Get the direction of the character at the given byte offset.
+    pub fn direction_at(&self, offset: usize) -> TextDirection {
+        TextDirection::from_level(self.level_at(offset))
+    }
+
+    /// Split the paragraph into runs of a single direction, in visual
+    /// (left-to-right display) order. Each run is a byte range into the
+    /// paragraph's text.
+    pub fn visual_runs(&self) -> Vec<(Range<usize>, TextDirection)> {
+        let mut runs: Vec<(Range<usize>, u8)> = Vec::new();
+        for (index, level) in self.levels.iter().copied().enumerate() {
+            let start = self.offsets[index];
+            let end = self
+                .offsets
+                .get(index + 1)
+                .copied()
+                .unwrap_or(self.text.len());
+            match runs.last_mut() {
+                Some((range, run_level)) if *run_level == level => range.end = end,
+                _ => runs.push((start..end, level)),
+            }
+        }
+
+        // L2: From the highest level down to the lowest odd level, reverse any
+        //     contiguous sequence of runs at that level or higher.
+        let highest = runs.iter().map(|(_, level)| *level).max().unwrap_or(0);
+        let lowest_odd = runs
+            .iter()
+            .map(|(_, level)| *level)
+            .filter(|level| level % 2 == 1)
+            .min()
+            .unwrap_or(highest + 1);
+        for level in (lowest_odd..=highest).rev() {
+            let mut index = 0;
+            while index < runs.len() {
+                if runs[index].1 < level {
+                    index += 1;
+                    continue;
+                }
+                let start = index;
+                while index < runs.len() && runs[index].1 >= level {
+                    index += 1;
+                }
+                runs[start..index].reverse();
+            }
+        }
+
+        runs.into_iter()
+            .map(|(range, level)| (range, TextDirection::from_level(level)))
+            .collect()
+    }
+
+    /// Split the paragraph into clusters (see [`next_cluster_boundary`]), in
+    /// visual order, so that it can be drawn from left to right. Clusters in
+    /// right-to-left runs are reversed, but the characters within each cluster
+    /// stay in logical order so that combining marks follow their base.
+    pub fn visual_clusters(&self) -> Vec<Range<usize>> {
+        let mut clusters = Vec::with_capacity(self.offsets.len());
+        for (run, direction) in self.visual_runs() {
+            let run_start = clusters.len();
+            let mut offset = run.start;
+            while offset < run.end {
+                let end = next_cluster_boundary(self.text, offset).min(run.end);
+                clusters.push(offset..end);
+                offset = end;
+            }
+            if direction == TextDirection::RightToLeft {
+                clusters[run_start..].reverse();
+            }
+        }
+
+        clusters
+    }
+}
+
+/// Whether the text has any strong right-to-left characters, without which it
+/// never needs to be reordered.
+pub fn has_right_to_left(text: &str) -> bool {
+    text.chars()
+        .any(|ch| matches!(bidi_class(ch), BidiClass::R | BidiClass::AL))
+}
+
+
+
+/// Whether the character is a combining mark that attaches to the previous
+/// character.
+pub fn is_combining_mark(ch: char) -> bool {
+    matches!(
+        ch as u32,
+        0x0300..=0x036F // Combining diacritical marks.
+            | 0x0483..=0x0489
+            | 0x0591..=0x05BD // Hebrew points.
+            | 0x05BF | 0x05C1 | 0x05C2 | 0x05C4 | 0x05C5 | 0x05C7
+            | 0x0610..=0x061A // Arabic marks.
+            | 0x064B..=0x065F
+            | 0x0670
+            | 0x06D6..=0x06DC
+            | 0x06DF..=0x06E4
+            | 0x06E7 | 0x06E8
+            | 0x06EA..=0x06ED
+            | 0x0900..=0x0903 // Devanagari signs.
+            | 0x093A..=0x093C
+            | 0x093E..=0x094F
+            | 0x0951..=0x0957
+            | 0x0962 | 0x0963
+            | 0x0981..=0x0983 // Bengali signs.
+            | 0x09BC
+            | 0x09BE..=0x09CD
+            | 0x0A01..=0x0A03 // Gurmukhi signs.
+            | 0x0A3C..=0x0A51
+            | 0x0B82 // Tamil signs.
+            | 0x0BBE..=0x0BCD
+            | 0x200C | 0x200D // Zero-width (non-)joiner.
+            | 0x20D0..=0x20FF
+            | 0xFE00..=0xFE0F // Variation selectors.
+            | 0xFE20..=0xFE2F
+    )
+}
+
+/// Whether the character is a virama, which joins the following consonant into
+/// a conjunct.
+fn is_virama(ch: char) -> bool {
+    matches!(ch as u32, 0x094D | 0x09CD | 0x0A4D | 0x0BCD)
+}
+
+/// Find the byte offset of the next cluster boundary after `offset`.
+///
+/// A cluster is a base character followed by any combining marks, with
+/// conjuncts (consonants joined by a virama) treated as a single cluster.
+pub fn next_cluster_boundary(text: &str, offset: usize) -> usize {
+    let mut chars = text[offset..].char_indices().peekable();
+    let Some((_, first)) = chars.next() else {
+        return text.len();
+    };
+
+    let mut previous = first;
+    while let Some((index, ch)) = chars.peek().copied() {
+        if is_combining_mark(ch) || is_virama(previous) {
+            previous = ch;
+            chars.next();
+        } else {
+            return offset + index;
+        }
+    }
+
+    text.len()
+}
+
+/// How a character joins to its neighbours in cursive scripts, see
+/// [`shaped_char_indices`].
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum Joining {
+    /// Joins to the characters on both sides.
+    Dual,
+    /// Only joins to the previous (logical) character.
+    Right,
+    /// Makes its neighbours join to it without changing its own shape, such
+    /// as the tatweel or a zero-width joiner.
+    Causing,
+    /// Skipped over when joining, such as combining marks.
+    Transparent,
+    /// Doesn't join to either side.
+    None,
+}
+
+/// The joining type of a character, and its isolated presentation form if it
+/// has any others. Forms follow the isolated one in the order final, initial,
+/// medial.
+fn arabic_joining(ch: char) -> (Joining, Option<u32>) {
+    let isolated = match ch as u32 {
+        0x0621 => return (Joining::None, None),
+        0x0622..=0x0625 => 0xFE81 + (ch as u32 - 0x0622) * 2,
+        0x0626 => 0xFE89,
+        0x0627 => 0xFE8D,
+        0x0628 => 0xFE8F,
+        0x0629 => 0xFE93,
+        0x062A..=0x062E => 0xFE95 + (ch as u32 - 0x062A) * 4,
+        0x062F..=0x0632 => 0xFEA9 + (ch as u32 - 0x062F) * 2,
+        0x0633..=0x063A => 0xFEB1 + (ch as u32 - 0x0633) * 4,
+        0x0640 | 0x200D => return (Joining::Causing, None),
+        0x0641..=0x0647 => 0xFED1 + (ch as u32 - 0x0641) * 4,
+        0x0648 => 0xFEED,
+        0x0649 => 0xFEEF,
+        0x064A => 0xFEF1,
+        0x200C => return (Joining::None, None),
+        _ if is_combining_mark(ch) => return (Joining::Transparent, None),
+        _ => return (Joining::None, None),
+    };
+    let joining = match ch as u32 {
+        0x0622..=0x0625 | 0x0627 | 0x0629 | 0x062F..=0x0632 | 0x0648 => Joining::Right,
+        _ => Joining::Dual,
+    };
+
+    (joining, Some(isolated))
+}
+
+/// The presentation form of a letter with the given isolated form, joined on
+/// the given sides.
+fn presentation_form(isolated: u32, joins_previous: bool, joins_next: bool) -> char {
+    let form = match (joins_previous, joins_next) {
+        (false, false) => isolated,
+        (true, false) => isolated + 1,
+        // NOTE: Alef maksura only has isolated and final forms in the Arabic block,
+        //       so its other forms come from the block for other languages.
+        (false, true) if isolated == 0xFEEF => 0xFBE8,
+        (true, true) if isolated == 0xFEEF => 0xFBE9,
+        (false, true) => isolated + 2,
+        (true, true) => isolated + 3,
+    };
+
+    char::from_u32(form).expect("presentation forms are valid characters")
+}
+
+/// The characters of `text` with their byte offsets (like
+/// [`str::char_indices`]), with each Arabic letter replaced by the form it
+/// takes where it joins to its neighbours: isolated, initial, medial, or final.
+///
+/// Each character is replaced by one form, so that offsets into the text stay
+/// valid, which means that ligatures (such as lam-alef) aren't formed.
+pub fn shaped_char_indices(text: &str) -> Vec<(usize, char)> {
+    let mut chars = text.char_indices().collect::<Vec<_>>();
+    if !chars
+        .iter()
+        .any(|(_, ch)| ('\u{0621}'..='\u{064A}').contains(ch))
+    {
+        return chars;
+    }
+
+    let joinings = chars
+        .iter()
+        .map(|(_, ch)| arabic_joining(*ch))
+        .collect::<Vec<_>>();
+    // Transparent characters are skipped when looking for the neighbours.
+    let is_joining = |(joining, _): &&(Joining, Option<u32>)| *joining != Joining::Transparent;
+    for (index, (_, ch)) in chars.iter_mut().enumerate() {
+        let (joining, Some(isolated)) = joinings[index] else {
+            continue;
+        };
+        let previous = joinings[..index]
+            .iter()
+            .rev()
+            .find(is_joining)
+            .map_or(Joining::None, |(joining, _)| *joining);
+        let next = joinings[index + 1..]
+            .iter()
+            .find(is_joining)
+            .map_or(Joining::None, |(joining, _)| *joining);
+
+        let joins_previous = matches!(previous, Joining::Dual | Joining::Causing);
+        let joins_next = joining == Joining::Dual
+            && matches!(next, Joining::Dual | Joining::Right | Joining::Causing);
+        *ch = presentation_form(isolated, joins_previous, joins_next);
+    }
+
+    chars
+}
+
+/// Find the byte offset of the previous cluster boundary before `offset`.
+pub fn prev_cluster_boundary(text: &str, offset: usize) -> usize {
+    let mut boundary = 0;
+    while boundary < text.len() {
+        let next = next_cluster_boundary(text, boundary);
+        if next >= offset {
+            break;
+        }
+        boundary = next;
+    }
+
+    boundary
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn paragraph_directions() {
+        assert_eq!(
+            paragraph_direction("hello"),
+            Some(TextDirection::LeftToRight)
+        );
+        assert_eq!(
+            paragraph_direction("123 שלום"),
+            Some(TextDirection::RightToLeft)
+        );
+        assert_eq!(paragraph_direction("123 !?"), None);
+    }
+
+    #[test]
+    fn visual_runs() {
+        let text = "abc שלום def";
+        let paragraph = BidiParagraph::new(text, TextDirection::LeftToRight);
+        let runs = paragraph
+            .visual_runs()
+            .into_iter()
+            .map(|(range, direction)| (&text[range], direction))
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            runs,
+            [
+                ("abc ", TextDirection::LeftToRight),
+                ("שלום", TextDirection::RightToLeft),
+                (" def", TextDirection::LeftToRight),
+            ]
+        );
+
+        let text = "שלום abc";
+        let paragraph = BidiParagraph::new(text, TextDirection::LeftToRight);
+        let runs = paragraph
+            .visual_runs()
+            .into_iter()
+            .map(|(range, _)| &text[range])
+            .collect::<Vec<_>>();
+
+        assert_eq!(paragraph.direction(), TextDirection::RightToLeft);
+        assert_eq!(runs, ["abc", "שלום "]);
+    }
+
+    #[test]
+    fn visual_clusters() {
+        // "e" with a combining acute accent, then two Hebrew letters, the second of
+        // which has a point.
+        let text = "e\u{301} \u{5E9}\u{5DC}\u{5B9}";
+        let paragraph = BidiParagraph::new(text, TextDirection::LeftToRight);
+        let clusters = paragraph
+            .visual_clusters()
+            .into_iter()
+            .map(|range| &text[range])
+            .collect::<Vec<_>>();
+
+        assert_eq!(clusters, ["e\u{301}", " ", "\u{5DC}\u{5B9}", "\u{5E9}"]);
+        assert!(has_right_to_left(text));
+        assert!(!has_right_to_left("e\u{301} 123"));
+    }
+
+    #[test]
+    fn clusters() {
+        // "e" followed by a combining acute accent.
+        let text = "e\u{301}x";
+        assert_eq!(next_cluster_boundary(text, 0), 3);
+        assert_eq!(prev_cluster_boundary(text, 3), 0);
+
+        // Devanagari "क्ष" (ka + virama + ssa) is a single conjunct.
+        let text = "क्षa";
+        assert_eq!(next_cluster_boundary(text, 0), 9);
+        assert_eq!(prev_cluster_boundary(text, text.len()), 9);
+        assert_eq!(prev_cluster_boundary(text, 9), 0);
+    }
+
+    fn shaped(text: &str) -> String {
+        shaped_char_indices(text)
+            .into_iter()
+            .map(|(_, ch)| ch)
+            .collect()
+    }
+
+    #[test]
+    fn arabic_joining_forms() {
+        // Beh, yeh, and teh all join on both sides.
+        assert_eq!(shaped("\u{628}\u{64A}\u{62A}"), "\u{FE91}\u{FEF4}\u{FE96}");
+        // Alef doesn't join to the next letter, which starts over.
+        assert_eq!(shaped("\u{628}\u{627}\u{628}"), "\u{FE91}\u{FE8E}\u{FE8F}");
+        // Marks are skipped over, and keep their offsets.
+        let text = "\u{628}\u{64E}\u{628} a";
+        assert_eq!(shaped(text), "\u{FE91}\u{64E}\u{FE90} a");
+        assert_eq!(
+            shaped_char_indices(text)
+                .into_iter()
+                .map(|(offset, _)| offset)
+                .collect::<Vec<_>>(),
+            text.char_indices()
+                .map(|(offset, _)| offset)
+                .collect::<Vec<_>>(),
+        );
+        // A zero-width non-joiner breaks the join, and a tatweel extends it.
+        assert_eq!(shaped("\u{628}\u{200C}\u{628}"), "\u{FE8F}\u{200C}\u{FE8F}");
+        assert_eq!(shaped("\u{628}\u{640}"), "\u{FE91}\u{640}");
+        // Alef maksura's initial form is outside of the Arabic block.
+        assert_eq!(shaped("\u{649}\u{628}"), "\u{FBE8}\u{FE90}");
+        assert_eq!(shaped("\u{5E9}\u{5DC}"), "\u{5E9}\u{5DC}");
+    }
+}
@@ -0,0 +1,379 @@
+//! # Canvas
+//!
+//! Vector paths, which elements draw with [`RenderPass::fill_path`] and
+//! [`RenderPass::stroke_path`], and the [`Canvas`] element for drawing
+//! arbitrary shapes (such as charts) without writing an element of your own.
+//!
+//! Hosts receive paths as [`RenderCommand::PathSegment`]s, which can be turned
+//! into straight lines for drawing with [`flatten`].
+//!
+//! [`RenderCommand::PathSegment`]: crate::RenderCommand::PathSegment
+
+use crate::{Aabb2D, Axis, Element, LengthRequest, MeasureContext, RenderPass, Rgba, Xy};
+
+
+
+/// A single step of a [`VectorPath`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[repr(C)]
+pub enum PathSegment {
+    /// Start a new subpath at the given point.
+    MoveTo(Xy<f32>),
+    LineTo(Xy<f32>),
+    /// A quadratic Bézier curve with one control point, then the end point.
+    QuadTo(Xy<f32>, Xy<f32>),
+    /// A cubic Bézier curve with two control points, then the end point.
+    CubicTo(Xy<f32>, Xy<f32>, Xy<f32>),
+    /// Draw a line back to the start of the current subpath.
+    Close,
+}
+
+impl PathSegment {
+    /// Every point in the segment, including control points.
+    pub fn points(&self) -> impl Iterator<Item = Xy<f32>> + use<> {
+        let (points, len) = match *self {
+            Self::MoveTo(to) | Self::LineTo(to) => ([to; 3], 1),
+            Self::QuadTo(control, to) => ([control, to, to], 2),
+            Self::CubicTo(control_a, control_b, to) => ([control_a, control_b, to], 3),
+            Self::Close => ([Xy::ZERO; 3], 0),
+        };
+
+        points.into_iter().take(len)
+    }
+
+    /// Apply `f` to every point in the segment.
+    pub fn map_points(self, mut f: impl FnMut(Xy<f32>) -> Xy<f32>) -> Self {
+        match self {
+            Self::MoveTo(to) => Self::MoveTo(f(to)),
+            Self::LineTo(to) => Self::LineTo(f(to)),
+            Self::QuadTo(control, to) => Self::QuadTo(f(control), f(to)),
+            Self::CubicTo(control_a, control_b, to) => {
+                Self::CubicTo(f(control_a), f(control_b), f(to))
+            }
+            Self::Close => Self::Close,
+        }
+    }
+}
+
+/// A shape made of lines and curves.
+///
+/// ```
+/// # use abi::{VectorPath, Xy};
+/// let triangle = VectorPath::new()
+///     .move_to(Xy::new(0.0, 10.0))
+///     .line_to(Xy::new(5.0, 0.0))
+///     .line_to(Xy::new(10.0, 10.0))
+///     .close();
+/// assert_eq!(triangle.segments().len(), 4);
+/// ```
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct VectorPath {
+    segments: Vec<PathSegment>,
+}
+
+impl VectorPath {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn move_to(mut self, to: Xy<f32>) -> Self {
+        self.segments.push(PathSegment::MoveTo(to));
+        self
+    }
+
+    pub fn line_to(mut self, to: Xy<f32>) -> Self {
+        self.segments.push(PathSegment::LineTo(to));
+        self
+    }
+
+    pub fn quad_to(mut self, control: Xy<f32>, to: Xy<f32>) -> Self {
+        self.segments.push(PathSegment::QuadTo(control, to));
+        self
+    }
+
+    pub fn cubic_to(mut self, control_a: Xy<f32>, control_b: Xy<f32>, to: Xy<f32>) -> Self {
+        self.segments
+            .push(PathSegment::CubicTo(control_a, control_b, to));
+        self
+    }
+
+    pub fn close(mut self) -> Self {
+        self.segments.push(PathSegment::Close);
+        self
+    }
+
+    /// A closed rectangle covering the given area.
+    pub fn rect(area: Aabb2D) -> Self {
+        Self::new()
+            .move_to(area.min)
+            .line_to(Xy::new(area.max.x, area.min.y))
+            .line_to(area.max)
+            .line_to(Xy::new(area.min.x, area.max.y))
+            .close()
+    }
+
+    /// Connect the given points with straight lines.
+    pub fn polyline(points: impl IntoIterator<Item = Xy<f32>>) -> Self {
+        let mut points = points.into_iter();
+        let Some(start) = points.next() else {
+            return Self::new();
+        };
+
+        points.fold(Self::new().move_to(start), Self::line_to)
+    }
+
+    #[inline]
+    pub fn segments(&self) -> &[PathSegment] {
+        &self.segments
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.segments.is_empty()
+    }
+
+    /// The smallest area containing every point in the path, including control
+    /// points, or `None` if it's empty.
+    pub fn bounds(&self) -> Option<Aabb2D> {
+        self.segments
+            .iter()
+            .flat_map(PathSegment::points)
+            .map(|point| Aabb2D::from_min_max(point, point))
+            .reduce(|area, point| area.union(point))
+    }
+}
+
+/// A subpath of a flattened path, see [`flatten`].
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Polyline {
+    pub points: Vec<Xy<f32>>,
+    /// Whether the last point connects back to the first.
+    pub closed: bool,
+}
+
+/// Approximate the curves in a path with straight lines, such that no point on
+/// a curve is further than `tolerance` from the lines replacing it.
+///
+/// Each subpath becomes its own [`Polyline`]. Filled paths should treat every
+/// polyline as closed.
+pub fn flatten(segments: &[PathSegment], tolerance: f32) -> Vec<Polyline> {
+    let tolerance = tolerance.max(0.001);
+    let mut polylines = Vec::new();
+    let mut current = Polyline::default();
+    let mut last = Xy::ZERO;
+
+    for segment in segments {
+        match *segment {
+            PathSegment::MoveTo(to) => {
+                if current.points.len() > 1 {
+                    polylines.push(std::mem::take(&mut current));
+                }
+                current.points.clear();
+                current.points.push(to);
+                last = to;
+                continue;
+            }
+            PathSegment::Close => {
+                if current.points.len() > 1 {
+                    let start = current.points[0];
+                    current.closed = true;
+                    polylines.push(std::mem::take(&mut current));
+                    // NOTE: Segments after a close (without a move) start from the same point
+                    //       as the closed subpath.
+                    current.points.push(start);
+                    last = start;
+                }
+                continue;
+            }
+            _ => {}
+        }
+
+        if current.points.is_empty() {
+            current.points.push(last);
+        }
+        match *segment {
+            PathSegment::LineTo(to) => current.points.push(to),
+            PathSegment::QuadTo(control, to) => {
+                let deviation = (last - control - control + to).length();
+                let steps = subdivisions(deviation * 0.25, tolerance);
+                current.points.extend((1..=steps).map(|step| {
+                    let t = step as f32 / steps as f32;
+                    let a = lerp(last, control, t);
+                    let b = lerp(control, to, t);
+                    lerp(a, b, t)
+                }));
+            }
+            PathSegment::CubicTo(control_a, control_b, to) => {
+                let deviation = (last - control_a - control_a + control_b)
+                    .length()
+                    .max((control_a - control_b - control_b + to).length());
+                let steps = subdivisions(deviation * 0.75, tolerance);
+                current.points.extend((1..=steps).map(|step| {
+                    let t = step as f32 / steps as f32;
+                    let a = lerp(last, control_a, t);
+                    let b = lerp(control_a, control_b, t);
+                    let c = lerp(control_b, to, t);
+                    let ab = lerp(a, b, t);
+                    let bc = lerp(b, c, t);
+                    lerp(ab, bc, t)
+                }));
+            }
+            PathSegment::MoveTo(_) | PathSegment::Close => unreachable!(),
+        }
+        last = *current.points.last().unwrap();
+    }
+    if current.points.len() > 1 {
+        polylines.push(current);
+    }
+
+    polylines
+}
+
+fn subdivisions(deviation: f32, tolerance: f32) -> usize {
+    ((deviation / tolerance).sqrt().ceil() as usize).clamp(1, 256)
+}
+
+fn lerp(a: Xy<f32>, b: Xy<f32>, t: f32) -> Xy<f32> {
+    Xy::new(a.x + (b.x - a.x) * t, a.y + (b.y - a.y) * t)
+}
+
+
+
+/// How a [`Canvas`] draws one of its paths.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum CanvasPaint {
+    Fill(Rgba),
+    Stroke { width: f32, color: Rgba },
+}
+
+/// An element that draws a retained list of paths, in the order they were
+/// added.
+///
+/// Paths are in the canvas' own coordinates, where `(0, 0)` is its top-left
+/// corner, and are scaled along with the rest of the view.
+pub struct Canvas {
+    size: Xy<f32>,
+    paths: Vec<(VectorPath, CanvasPaint)>,
+}
+
+impl Canvas {
+    pub fn new(size: Xy<f32>) -> Self {
+        Self {
+            size,
+            paths: Vec::new(),
+        }
+    }
+
+    pub fn with_fill(mut self, path: VectorPath, color: Rgba) -> Self {
+        self.fill(path, color);
+        self
+    }
+
+    pub fn with_stroke(mut self, path: VectorPath, width: f32, color: Rgba) -> Self {
+        self.stroke(path, width, color);
+        self
+    }
+
+    #[inline]
+    pub fn size(&self) -> Xy<f32> {
+        self.size
+    }
+
+    pub fn set_size(&mut self, size: Xy<f32>) {
+        self.size = size;
+    }
+
+    pub fn fill(&mut self, path: VectorPath, color: Rgba) {
+        self.paths.push((path, CanvasPaint::Fill(color)));
+    }
+
+    pub fn stroke(&mut self, path: VectorPath, width: f32, color: Rgba) {
+        self.paths
+            .push((path, CanvasPaint::Stroke { width, color }));
+    }
+
+    /// Remove every path, such as before redrawing a chart with new data.
+    pub fn clear(&mut self) {
+        self.paths.clear();
+    }
+
+    #[inline]
+    pub fn paths(&self) -> &[(VectorPath, CanvasPaint)] {
+        &self.paths
+    }
+}
+
+impl Element for Canvas {
+    fn render(&mut self, pass: &mut RenderPass<'_>) {
+        let origin = pass.bounds().position();
+        let zoom = Xy::new(pass.zoom(), pass.zoom());
+
+        let mut segments = Vec::new();
+        for (path, paint) in &self.paths {
+            segments.clear();
+            segments.extend(
+                path.segments()
+                    .iter()
+                    .map(|segment| segment.map_points(|point| origin + point * zoom)),
+            );
+            match *paint {
+                CanvasPaint::Fill(color) => pass.fill_path(&segments, color),
+                CanvasPaint::Stroke { width, color } => pass.stroke_path(&segments, width, color),
+            }
+        }
+    }
+
+    fn measure(
+        &mut self,
+        _context: &mut MeasureContext<'_>,
+        axis: Axis,
+        _length_request: LengthRequest,
+        _cross_length: Option<f32>,
+    ) -> f32 {
+        self.size.value_for_axis(axis)
+    }
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn flatten_subpaths() {
+        let path = VectorPath::rect(Aabb2D::new(0.0, 0.0, 10.0, 10.0))
+            .move_to(Xy::new(0.0, 20.0))
+            .quad_to(Xy::new(5.0, 30.0), Xy::new(10.0, 20.0));
+        let polylines = flatten(path.segments(), 0.1);
+
+        assert_eq!(polylines.len(), 2);
+        assert!(polylines[0].closed);
+        assert_eq!(polylines[0].points.len(), 4);
+
+        let curve = &polylines[1];
+        assert!(!curve.closed);
+        assert!(curve.points.len() > 3);
+        assert_eq!(curve.points.first(), Some(&Xy::new(0.0, 20.0)));
+        assert_eq!(curve.points.last(), Some(&Xy::new(10.0, 20.0)));
+        // The middle of the curve is halfway to its control point.
+        assert!(
+            curve
+                .points
+                .iter()
+                .any(|point| (point.y - 25.0).abs() < 0.1)
+        );
+    }
+
+    #[test]
+    fn path_bounds_include_control_points() {
+        let path = VectorPath::new().move_to(Xy::new(0.0, 0.0)).cubic_to(
+            Xy::new(-5.0, 8.0),
+            Xy::new(5.0, 12.0),
+            Xy::new(2.0, 4.0),
+        );
+
+        assert_eq!(path.bounds(), Some(Aabb2D::new(-5.0, 0.0, 5.0, 12.0)));
+        assert_eq!(VectorPath::new().bounds(), None);
+    }
+}
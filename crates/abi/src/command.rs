@@ -0,0 +1,309 @@
+//! # Shell Commands
+//!
+//! Commands that can be run from the shell's command line. Applications with
+//! the [`Capability::RegisterCommands`](crate::Capability::RegisterCommands)
+//! capability can add their own with [`register`].
+
+use std::fmt;
+
+
+
+/// The type of value an [`ArgSpec`] accepts.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum ArgKind {
+    /// Any single word.
+    Text,
+    /// A signed integer, such as `-12`.
+    Integer,
+    /// A file system path.
+    Path,
+    /// Every remaining word. Only valid as the last argument.
+    Rest,
+}
+
+/// A positional argument accepted by a [`CommandSpec`].
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct ArgSpec {
+    pub name: &'static str,
+    pub kind: ArgKind,
+    pub required: bool,
+    pub help: &'static str,
+}
+
+impl ArgSpec {
+    pub const fn required(name: &'static str, kind: ArgKind, help: &'static str) -> Self {
+        Self {
+            name,
+            kind,
+            required: true,
+            help,
+        }
+    }
+
+    pub const fn optional(name: &'static str, kind: ArgKind, help: &'static str) -> Self {
+        Self {
+            name,
+            kind,
+            required: false,
+            help,
+        }
+    }
+}
+
+/// A parsed argument, see [`Args`].
+#[derive(Clone, Debug, PartialEq)]
+pub enum ArgValue {
+    Text(String),
+    Integer(i64),
+    List(Vec<String>),
+}
+
+/// The arguments a command was run with, parsed according to its
+/// [`CommandSpec`].
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Args {
+    values: Vec<(&'static str, ArgValue)>,
+}
+
+impl Args {
+    pub fn get(&self, name: &str) -> Option<&ArgValue> {
+        self.values
+            .iter()
+            .find_map(|(arg_name, value)| (*arg_name == name).then_some(value))
+    }
+
+    /// Get a [`ArgKind::Text`] or [`ArgKind::Path`] argument.
+    pub fn text(&self, name: &str) -> Option<&str> {
+        match self.get(name)? {
+            ArgValue::Text(text) => Some(text),
+            _ => None,
+        }
+    }
+
+    /// Get a [`ArgKind::Integer`] argument.
+    pub fn integer(&self, name: &str) -> Option<i64> {
+        match self.get(name)? {
+            ArgValue::Integer(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    /// Get a [`ArgKind::Rest`] argument, which is empty if no words were given.
+    pub fn rest(&self, name: &str) -> &[String] {
+        match self.get(name) {
+            Some(ArgValue::List(words)) => words,
+            _ => &[],
+        }
+    }
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum ArgError {
+    /// A required argument wasn't given.
+    Missing(&'static str),
+    /// An argument couldn't be parsed as its [`ArgKind`].
+    Invalid { name: &'static str, value: String },
+    /// More arguments were given than the command accepts.
+    TooMany,
+}
+
+impl fmt::Display for ArgError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Missing(name) => write!(f, "missing argument <{name}>"),
+            Self::Invalid { name, value } => write!(f, "invalid value for <{name}>: '{value}'"),
+            Self::TooMany => write!(f, "too many arguments"),
+        }
+    }
+}
+
+/// Suggest completions for the argument at the given index, given what has been
+/// typed of it so far.
+pub type CompleteFn = fn(arg_index: usize, partial: &str) -> Vec<String>;
+
+/// The name, arguments, and documentation of a command.
+#[derive(Clone, Copy, Debug)]
+pub struct CommandSpec {
+    pub name: &'static str,
+    /// A single line describing what the command does.
+    pub summary: &'static str,
+    pub args: &'static [ArgSpec],
+    pub complete: Option<CompleteFn>,
+}
+
+impl CommandSpec {
+    /// Parse the words following the command's name.
+    pub fn parse(&self, words: &[&str]) -> Result<Args, ArgError> {
+        let mut values = Vec::with_capacity(self.args.len());
+        let mut words = words.iter();
+
+        for arg in self.args {
+            if arg.kind == ArgKind::Rest {
+                let rest = words
+                    .by_ref()
+                    .map(|word| word.to_string())
+                    .collect::<Vec<_>>();
+                if arg.required && rest.is_empty() {
+                    return Err(ArgError::Missing(arg.name));
+                }
+                values.push((arg.name, ArgValue::List(rest)));
+                continue;
+            }
+
+            let Some(word) = words.next() else {
+                if arg.required {
+                    return Err(ArgError::Missing(arg.name));
+                }
+                continue;
+            };
+            let value = match arg.kind {
+                ArgKind::Text | ArgKind::Path => ArgValue::Text(word.to_string()),
+                ArgKind::Integer => {
+                    ArgValue::Integer(word.parse().map_err(|_| ArgError::Invalid {
+                        name: arg.name,
+                        value: word.to_string(),
+                    })?)
+                }
+                ArgKind::Rest => unreachable!(),
+            };
+            values.push((arg.name, value));
+        }
+
+        if words.next().is_some() {
+            return Err(ArgError::TooMany);
+        }
+
+        Ok(Args { values })
+    }
+
+    /// A one-line usage string, such as `cd <path>` or `env [name]`.
+    pub fn usage(&self) -> String {
+        let mut usage = self.name.to_string();
+        for arg in self.args {
+            let name = if arg.kind == ArgKind::Rest {
+                format!("{}...", arg.name)
+            } else {
+                arg.name.to_string()
+            };
+            if arg.required {
+                usage.push_str(&format!(" <{name}>"));
+            } else {
+                usage.push_str(&format!(" [{name}]"));
+            }
+        }
+
+        usage
+    }
+
+    /// The full help text, with the usage, summary, and each argument.
+    pub fn help(&self) -> String {
+        let mut help = format!("{}\n    {}", self.usage(), self.summary);
+        for arg in self.args.iter().filter(|arg| !arg.help.is_empty()) {
+            help.push_str(&format!("\n    {:<12} {}", arg.name, arg.help));
+        }
+
+        help
+    }
+}
+
+/// A command provided by an application, see [`register`].
+#[derive(Debug)]
+pub struct Command {
+    pub spec: CommandSpec,
+    /// Run the command, returning the text to print.
+    pub run: fn(&Args) -> Result<String, String>,
+}
+
+/// Split a command line into words.
+pub fn split_words(line: &str) -> Vec<&str> {
+    line.split_whitespace().collect()
+}
+
+/// Check that a command name is non-empty and only contains ASCII letters,
+/// digits, `-` and `_`.
+pub fn is_valid_name(name: &str) -> bool {
+    !name.is_empty()
+        && name
+            .chars()
+            .all(|ch| ch.is_ascii_alphanumeric() || matches!(ch, '-' | '_'))
+}
+
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+#[repr(u8)]
+pub enum RegisterCommandError {
+    /// The calling application doesn't have the
+    /// [`Capability::RegisterCommands`](crate::Capability::RegisterCommands)
+    /// capability.
+    NotPermitted,
+    /// A command with the same name already exists.
+    AlreadyExists,
+    /// The command's name isn't valid, see [`is_valid_name`].
+    InvalidName,
+}
+
+impl RegisterCommandError {
+    pub const fn description(&self) -> &'static str {
+        match self {
+            Self::NotPermitted => "missing the register-commands capability",
+            Self::AlreadyExists => "a command with that name already exists",
+            Self::InvalidName => "invalid command name",
+        }
+    }
+}
+
+/// Add a command to the shell's command line.
+///
+/// Requires
+/// [`Capability::RegisterCommands`](crate::Capability::RegisterCommands).
+pub fn register(command: &'static Command) -> Result<(), RegisterCommandError> {
+    if !is_valid_name(command.spec.name) {
+        return Err(RegisterCommandError::InvalidName);
+    }
+
+    unsafe { __command_register(command) }
+}
+
+unsafe extern "Rust" {
+    fn __command_register(command: &'static Command) -> Result<(), RegisterCommandError>;
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SPEC: CommandSpec = CommandSpec {
+        name: "resize",
+        summary: "Resize something",
+        args: &[
+            ArgSpec::required("width", ArgKind::Integer, "The new width"),
+            ArgSpec::optional("label", ArgKind::Text, ""),
+            ArgSpec::optional("tags", ArgKind::Rest, ""),
+        ],
+        complete: None,
+    };
+
+    #[test]
+    fn parsing() {
+        let args = SPEC.parse(&["12", "box", "a", "b"]).unwrap();
+        assert_eq!(args.integer("width"), Some(12));
+        assert_eq!(args.text("label"), Some("box"));
+        assert_eq!(args.rest("tags"), ["a", "b"]);
+
+        let args = SPEC.parse(&["-3"]).unwrap();
+        assert_eq!(args.integer("width"), Some(-3));
+        assert_eq!(args.text("label"), None);
+        assert!(args.rest("tags").is_empty());
+
+        assert_eq!(SPEC.parse(&[]), Err(ArgError::Missing("width")));
+        assert_eq!(
+            SPEC.parse(&["wide"]),
+            Err(ArgError::Invalid {
+                name: "width",
+                value: "wide".to_string(),
+            }),
+        );
+        assert_eq!(SPEC.usage(), "resize <width> [label] [tags...]");
+    }
+}
@@ -0,0 +1,282 @@
+//! # Context Menus
+//!
+//! Menus opened by right-clicking an element, see [`Element::context_menu`].
+//!
+//! The view opens the menu in a popup above everything else, handles keyboard
+//! navigation and dismissal, then routes the chosen item's action back to the
+//! element that provided the menu through [`Element::on_context_menu_action`].
+
+use crate::{
+    Aabb2D, Axis, Element, EventPass, FontFamily, FontStyle, Key, KeyboardEvent, LengthRequest,
+    LineHeight, MeasureContext, PointerButton, PointerEvent, PopupId, RenderPass, TextAlignment,
+    TextWrapMode, Xy,
+};
+
+
+
+/// The items shown when an element is right-clicked.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ContextMenu {
+    pub items: Vec<ContextMenuItem>,
+}
+
+impl ContextMenu {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with(mut self, item: ContextMenuItem) -> Self {
+        self.items.push(item);
+        self
+    }
+
+    /// Add an enabled item with the given action and label.
+    pub fn with_item(self, action: u64, label: impl ToString) -> Self {
+        self.with(ContextMenuItem::new(action, label))
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ContextMenuItem {
+    /// Passed to [`Element::on_context_menu_action`] when this item is chosen.
+    pub action: u64,
+    pub label: String,
+    /// Disabled items are shown, but can't be highlighted or chosen.
+    pub enabled: bool,
+}
+
+impl ContextMenuItem {
+    pub fn new(action: u64, label: impl ToString) -> Self {
+        Self {
+            action,
+            label: label.to_string(),
+            enabled: true,
+        }
+    }
+
+    pub fn with_enabled(mut self, enabled: bool) -> Self {
+        self.enabled = enabled;
+        self
+    }
+}
+
+
+
+/// The popup root that shows a [`ContextMenu`] for the element that provided
+/// it.
+pub(crate) struct ContextMenuPopup {
+    owner_id: u64,
+    menu: ContextMenu,
+    highlighted: Option<usize>,
+    font_size: f32,
+    item_height: f32,
+    /// The theme's `"spacing.small"` as of the last measurement.
+    padding: f32,
+    dismissed: bool,
+}
+
+impl ContextMenuPopup {
+    pub(crate) fn new(owner_id: u64, menu: ContextMenu) -> Self {
+        Self {
+            owner_id,
+            menu,
+            highlighted: None,
+            font_size: 16.0,
+            item_height: 0.0,
+            padding: 0.0,
+            dismissed: false,
+        }
+    }
+
+    /// The index of the item at the given position, in logical units relative
+    /// to the menu.
+    fn item_at(&self, local_position: Xy<f32>) -> Option<usize> {
+        if local_position.x < 0.0 || local_position.y < self.padding {
+            return None;
+        }
+        let index = ((local_position.y - self.padding) / self.item_height) as usize;
+
+        (index < self.menu.items.len()).then_some(index)
+    }
+
+    /// Move the highlight by `step` items, skipping disabled ones and wrapping
+    /// around at either end.
+    fn move_highlight(&mut self, pass: &mut EventPass<'_>, step: isize) {
+        let len = self.menu.items.len() as isize;
+        let mut index = match self.highlighted {
+            Some(index) => index as isize,
+            None if step > 0 => -1,
+            None => len,
+        };
+        for _ in 0..len {
+            index = (index + step).rem_euclid(len);
+            if self.menu.items[index as usize].enabled {
+                self.highlighted = Some(index as usize);
+                pass.request_render();
+                return;
+            }
+        }
+    }
+
+    fn choose(&mut self, pass: &mut EventPass<'_>, index: usize) {
+        let Some(item) = self.menu.items.get(index) else {
+            return;
+        };
+        if !item.enabled {
+            return;
+        }
+        pass.send_context_menu_action(self.owner_id, item.action);
+        self.dismiss(pass);
+    }
+
+    fn dismiss(&mut self, pass: &mut EventPass<'_>) {
+        if self.dismissed {
+            return;
+        }
+        self.dismissed = true;
+        pass.release_pointer();
+        pass.close_popup(PopupId(pass.id()));
+    }
+}
+
+impl Element for ContextMenuPopup {
+    fn accepts_keyboard_events(&self) -> bool {
+        true
+    }
+
+    fn accepts_focus_events(&self) -> bool {
+        true
+    }
+
+    fn render(&mut self, pass: &mut RenderPass<'_>) {
+        let bounds = pass.bounds();
+        let zoom = pass.zoom();
+        pass.fill_quad(
+            bounds,
+            pass.color("surface.raised"),
+            1.0,
+            pass.color("border"),
+        );
+
+        for (index, item) in self.menu.items.iter().enumerate() {
+            let item_bounds = Aabb2D::from_size_position(
+                Xy::new(bounds.size().x, self.item_height * zoom),
+                bounds.position()
+                    + Xy::new(0.0, (self.padding + self.item_height * index as f32) * zoom),
+            );
+            if self.highlighted == Some(index) {
+                pass.fill_quad(
+                    item_bounds,
+                    pass.color("surface.sunken"),
+                    0.0,
+                    pass.color("border"),
+                );
+            }
+            pass.fill_text(
+                &item.label,
+                Aabb2D::from_size_position(
+                    item_bounds.size() - Xy::new(self.padding * 2.0 * zoom, 0.0),
+                    item_bounds.position() + Xy::new(self.padding * zoom, 0.0),
+                ),
+                pass.color(if item.enabled { "text" } else { "text.muted" }),
+                self.font_size,
+            );
+        }
+    }
+
+    fn measure(
+        &mut self,
+        context: &mut MeasureContext<'_>,
+        axis: Axis,
+        _length_request: LengthRequest,
+        _cross_length: Option<f32>,
+    ) -> f32 {
+        self.padding = context.metric("spacing.small");
+
+        let id = context.id();
+        let fonts = context.fonts_mut();
+        let mut max_size = Xy::new(0.0_f32, 0.0_f32);
+        for item in &self.menu.items {
+            let size = fonts.measure_text(
+                id,
+                &item.label,
+                &[],
+                None,
+                self.font_size,
+                LineHeight::Relative(1.0),
+                FontFamily::Proportional,
+                FontStyle::Normal,
+                TextAlignment::Start,
+                TextWrapMode::NoWrap,
+            );
+            max_size.x = max_size.x.max(size.x);
+            max_size.y = max_size.y.max(size.y);
+        }
+
+        self.item_height = max_size.y + self.padding;
+
+        match axis {
+            Axis::Horizontal => max_size.x + self.padding * 2.0,
+            Axis::Vertical => self.item_height * self.menu.items.len() as f32 + self.padding * 2.0,
+        }
+    }
+
+    fn on_keyboard_event(&mut self, pass: &mut EventPass<'_>, event: &KeyboardEvent) {
+        let KeyboardEvent::Down { key, .. } = event else {
+            return;
+        };
+
+        match key {
+            Key::ArrowDown => self.move_highlight(pass, 1),
+            Key::ArrowUp => self.move_highlight(pass, -1),
+            Key::Enter | Key::Space => {
+                if let Some(index) = self.highlighted {
+                    self.choose(pass, index);
+                }
+            }
+            Key::Escape | Key::Tab => self.dismiss(pass),
+            _ => {}
+        }
+        // NOTE: The menu is modal while it's focused, so it swallows every key.
+        pass.set_handled();
+    }
+
+    fn on_pointer_event(&mut self, pass: &mut EventPass<'_>, event: &PointerEvent) {
+        match event {
+            PointerEvent::Move { position, .. } => {
+                let index = self
+                    .item_at(pass.local_position(*position))
+                    .filter(|index| self.menu.items[*index].enabled);
+                if index != self.highlighted {
+                    self.highlighted = index;
+                    pass.request_render();
+                }
+            }
+            // Because the pointer capture is held while the menu is open, clicks outside of
+            // it also end up here and simply dismiss it.
+            PointerEvent::Down {
+                button: PointerButton::Primary,
+                position,
+                ..
+            } if pass.bounds().contains(*position) => {
+                if let Some(index) = self.item_at(pass.local_position(*position)) {
+                    self.choose(pass, index);
+                }
+            }
+            PointerEvent::Down { .. } => self.dismiss(pass),
+            _ => {}
+        }
+        pass.set_handled();
+    }
+
+    fn on_focus(&mut self, pass: &mut EventPass<'_>, focused: bool) {
+        if !focused {
+            self.dismiss(pass);
+        }
+    }
+}
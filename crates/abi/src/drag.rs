@@ -0,0 +1,143 @@
+//! # Drag and Drop
+//!
+//! Elements start a drag with [`EventPass::start_drag`], usually from
+//! [`Gesture::DragStart`](crate::Gesture::DragStart). While the pointer moves,
+//! the view offers the [`Drag`] to the element under it (and that element's
+//! ancestors) through [`Element::on_drag_over`], and drops it on the one that
+//! accepted it when the pointer is released. Pressing escape cancels the drag.
+
+use std::any::Any;
+
+use crate::{Axis, Element, LengthRequest, MeasureContext, RenderPass, Xy};
+
+
+
+/// A payload being dragged from one element to another.
+pub struct Drag {
+    source_id: u64,
+    payload: Box<dyn Any>,
+    position: Xy<f32>,
+}
+
+impl Drag {
+    pub(crate) fn new(source_id: u64, payload: Box<dyn Any>, position: Xy<f32>) -> Self {
+        Self {
+            source_id,
+            payload,
+            position,
+        }
+    }
+
+    /// The ID of the element that started the drag.
+    #[inline]
+    pub fn source_id(&self) -> u64 {
+        self.source_id
+    }
+
+    /// The position of the pointer, in the same space as pointer events.
+    #[inline]
+    pub fn position(&self) -> Xy<f32> {
+        self.position
+    }
+
+    #[inline]
+    pub(crate) fn set_position(&mut self, position: Xy<f32>) {
+        self.position = position;
+    }
+
+    /// The payload, if it's a `T`.
+    pub fn payload<T: Any>(&self) -> Option<&T> {
+        self.payload.downcast_ref()
+    }
+
+    /// Take the payload, returning the drag back if it isn't a `T`.
+    pub fn into_payload<T: Any>(self) -> Result<Box<T>, Self> {
+        let Self {
+            source_id,
+            payload,
+            position,
+        } = self;
+
+        payload.downcast().map_err(|payload| Self {
+            source_id,
+            payload,
+            position,
+        })
+    }
+}
+
+
+
+/// The popup root that follows the pointer during a drag, outlined in the
+/// accent color while it's over an element that accepts the drag.
+pub(crate) struct DragGhost {
+    size: Xy<f32>,
+    accepted: bool,
+}
+
+impl DragGhost {
+    /// How far the ghost is from the pointer, so that it doesn't cover what's
+    /// under it.
+    pub(crate) const OFFSET: Xy<f32> = Xy::new(8.0, 8.0);
+
+    pub(crate) fn new(size: Xy<f32>) -> Self {
+        Self {
+            size,
+            accepted: false,
+        }
+    }
+
+    /// Returns `true` if this changed whether the drag is accepted.
+    pub(crate) fn set_accepted(&mut self, accepted: bool) -> bool {
+        let changed = self.accepted != accepted;
+        self.accepted = accepted;
+
+        changed
+    }
+}
+
+impl Element for DragGhost {
+    fn accepts_pointer_events(&self) -> bool {
+        false
+    }
+
+    fn render(&mut self, pass: &mut RenderPass<'_>) {
+        let mut background = pass.color("surface.raised");
+        background.a /= 2;
+        pass.fill_quad(
+            pass.bounds(),
+            background,
+            if self.accepted { 2.0 } else { 1.0 },
+            pass.color(if self.accepted { "accent" } else { "border" }),
+        );
+    }
+
+    fn measure(
+        &mut self,
+        _context: &mut MeasureContext<'_>,
+        axis: Axis,
+        _length_request: LengthRequest,
+        _cross_length: Option<f32>,
+    ) -> f32 {
+        self.size.value_for_axis(axis)
+    }
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn drag_payload_downcasts() {
+        let drag = Drag::new(1, Box::new(42_u32), Xy::ZERO);
+
+        assert_eq!(drag.payload::<u32>(), Some(&42));
+        assert!(drag.payload::<String>().is_none());
+
+        let drag = drag.into_payload::<String>().unwrap_err();
+        assert_eq!(drag.source_id(), 1);
+        assert_eq!(*drag.into_payload::<u32>().ok().unwrap(), 42);
+    }
+}
@@ -11,6 +11,7 @@ pub struct Flex {
     axis: Axis,
     main_alignment: AxisAlignment,
     cross_alignment: CrossAlignment,
+    gap: f32,
     elements: Vec<FlexElement>,
 }
 
@@ -19,6 +20,7 @@ enum FlexElement {
         element: ChildElement,
         alignment: Option<CrossAlignment>,
         flex: f32,
+        shrink: f32,
         basis: Option<FlexBasis>,
 
         resolved_basis: f32,
@@ -38,6 +40,7 @@ impl Flex {
             axis,
             main_alignment: AxisAlignment::Start,
             cross_alignment: CrossAlignment::Center,
+            gap: 3.0,
             elements: Vec::new(),
         }
     }
@@ -58,6 +61,7 @@ impl Flex {
             element: ElementBuilder::new(child).into_child(),
             alignment: params.alignment,
             flex: params.flex,
+            shrink: params.shrink,
             basis: params.basis,
             resolved_basis: 0.0,
         });
@@ -83,6 +87,12 @@ impl Flex {
         self.cross_alignment = alignment;
         self
     }
+
+    /// Set the space between elements (and spacers) along the main axis.
+    pub fn with_gap(mut self, gap: f32) -> Self {
+        self.gap = gap;
+        self
+    }
 }
 
 impl Element for Flex {
@@ -126,7 +136,7 @@ impl Element for Flex {
     }
 
     fn layout(&mut self, pass: &mut LayoutPass<'_>) {
-        let gap_length = 3.0; // self.gap;
+        let gap_length = self.gap;
         let gap_count = self.elements.len().saturating_sub(1);
 
         let size = pass.size;
@@ -135,9 +145,12 @@ impl Element for Flex {
         let cross_space = size.value_for_axis(cross_axis);
 
         let mut main_space: f32 = size.value_for_axis(main_axis) - gap_count as f32 * gap_length;
-        let mut max_ascent: f32 = 0.0;
+        let mut max_first_ascent: f32 = 0.0;
+        let mut max_last_ascent: f32 = 0.0;
         let mut flex_sum: f32 = 0.0;
         let mut lowest_baseline: f32 = f32::INFINITY;
+        let mut highest_first_baseline: f32 = f32::NEG_INFINITY;
+        let mut scaled_shrink_sum: f32 = 0.0;
 
         let resolve_child_size =
             |pass: &mut LayoutPass<'_>,
@@ -162,26 +175,25 @@ impl Element for Flex {
             |pass: &mut LayoutPass<'_>, child: &mut ChildElement, child_size: Xy<f32>| {
                 pass.do_layout(child, child_size);
 
-                let baseline = pass
-                    .expect_child(child.id())
-                    .element
-                    .state
-                    .layout_baseline_offset;
-                let ascent = child_size.y - baseline;
-                max_ascent = max_ascent.max(ascent);
-            };
-        let mut place_child =
-            |pass: &mut LayoutPass<'_>, child: &mut ChildElement, child_origin: Xy<f32>| {
-                pass.place_child(child, child_origin);
-
-                let child_node = pass.expect_child(child.id());
-                let child_size = child_node.element.state.layout_bounds.size();
-                let child_baseline = child_node.element.state.layout_baseline_offset;
-                let child_bottom = child_origin.y + child_size.y;
-                let bottom_gap = size.y - child_bottom;
-                let baseline = child_baseline + bottom_gap;
-                lowest_baseline = lowest_baseline.min(baseline);
+                let child_state = &pass.expect_child(child.id()).element.state;
+                max_first_ascent =
+                    max_first_ascent.max(child_size.y - child_state.first_baseline_offset());
+                max_last_ascent =
+                    max_last_ascent.max(child_size.y - child_state.layout_baseline_offset);
             };
+        let mut place_child = |pass: &mut LayoutPass<'_>,
+                               child: &mut ChildElement,
+                               child_origin: Xy<f32>| {
+            pass.place_child(child, child_origin);
+
+            let child_state = &pass.expect_child(child.id()).element.state;
+            let child_size = child_state.layout_bounds.size();
+            let child_bottom = child_origin.y + child_size.y;
+            let bottom_gap = size.y - child_bottom;
+            lowest_baseline = lowest_baseline.min(child_state.layout_baseline_offset + bottom_gap);
+            highest_first_baseline =
+                highest_first_baseline.max(child_state.first_baseline_offset() + bottom_gap);
+        };
 
         // Add up flex factors, resolve bases, subtract bases from main space, and lay
         // out inflexible elements.
@@ -191,6 +203,7 @@ impl Element for Flex {
                     element,
                     alignment,
                     flex,
+                    shrink,
                     basis,
                     resolved_basis,
                 } => {
@@ -210,14 +223,17 @@ impl Element for Flex {
                             *resolved_basis = 0.0;
                         }
                     }
-                    if *flex == 0.0 {
+                    if *flex != 0.0 {
+                        flex_sum += *flex;
+                    } else if *shrink > 0.0 {
+                        // Shrinkable children are laid out once the overflow is known.
+                        scaled_shrink_sum += *shrink * *resolved_basis;
+                    } else {
                         let child_main_length = *resolved_basis;
                         let child_size =
                             resolve_child_size(pass, element, child_main_length, alignment);
 
                         do_child_layout(pass, element, child_size);
-                    } else {
-                        flex_sum += *flex;
                     }
                 }
                 FlexElement::Spacer {
@@ -238,6 +254,44 @@ impl Element for Flex {
             }
         }
 
+        // Take any overflow from shrinkable children, in proportion to their shrink
+        // factors and bases, without going below their minimum content lengths.
+        let overflow = (-main_space).max(0.0);
+        for child in &mut self.elements {
+            let FlexElement::Child {
+                element,
+                alignment,
+                flex,
+                shrink,
+                resolved_basis,
+                ..
+            } = child
+            else {
+                continue;
+            };
+            if *flex != 0.0 || *shrink <= 0.0 {
+                continue;
+            }
+
+            let shrink_length = if scaled_shrink_sum > 0.0 {
+                overflow * *shrink * *resolved_basis / scaled_shrink_sum
+            } else {
+                0.0
+            };
+            let min_length = pass.resolve_length(
+                element.id(),
+                main_axis,
+                Length::MinContent,
+                Some(cross_space),
+            );
+            let child_main_length = (*resolved_basis - shrink_length).max(min_length);
+            let child_size = resolve_child_size(pass, element, child_main_length, alignment);
+
+            do_child_layout(pass, element, child_size);
+
+            main_space += *resolved_basis - child_main_length;
+        }
+
         // Calculate the flex fraction, i.e. the amount of space per one flex factor.
         let flex_fraction = if flex_sum > 0.0 {
             main_space.max(0.0) / flex_sum
@@ -298,14 +352,15 @@ impl Element for Flex {
                         main_offset += space_between;
                     }
 
-                    let child_node = pass.expect_child(element.id());
-                    let child_size = child_node.element.state.layout_bounds.size();
+                    let child_state = &pass.expect_child(element.id()).element.state;
+                    let child_size = child_state.layout_bounds.size();
                     let alignment = alignment.unwrap_or(self.cross_alignment);
                     let child_origin_cross = match alignment {
-                        CrossAlignment::Baseline if main_axis == Axis::Horizontal => {
-                            let baseline = child_node.element.state.layout_baseline_offset;
-                            let ascent = child_size.y - baseline;
-                            max_ascent - ascent
+                        CrossAlignment::FirstBaseline if main_axis == Axis::Horizontal => {
+                            max_first_ascent - (child_size.y - child_state.first_baseline_offset())
+                        }
+                        CrossAlignment::LastBaseline if main_axis == Axis::Horizontal => {
+                            max_last_ascent - (child_size.y - child_state.layout_baseline_offset)
                         }
                         _ => {
                             let cross_unused = cross_space - child_size.value_for_axis(cross_axis);
@@ -330,17 +385,18 @@ impl Element for Flex {
             }
         }
 
-        // If we have at least one child then we can use the lowest child baseline.
-        let baseline = self
+        // If we have at least one child then we can use the lowest child baseline, and
+        // the highest first baseline.
+        pass.clear_baseline_offset();
+        if self
             .elements
             .iter()
             .any(|element| matches!(element, FlexElement::Child { .. }))
-            .then_some(lowest_baseline);
-
-        if let Some(baseline) = baseline {
-            pass.set_baseline_offset(baseline);
-        } else {
-            pass.clear_baseline_offset();
+        {
+            pass.set_baseline_offset(lowest_baseline);
+            if highest_first_baseline != lowest_baseline {
+                pass.set_first_baseline_offset(highest_first_baseline);
+            }
         }
     }
 
@@ -354,7 +410,7 @@ impl Element for Flex {
         let perpendicular_axis = measure_axis.cross();
         let main_axis = self.axis;
         let cross_axis = main_axis.cross();
-        let gap_length = 3.0; // self.gap;
+        let gap_length = self.gap;
         let gap_count = self.elements.len().saturating_sub(1);
 
         let (main_space, cross_space) = if perpendicular_axis == main_axis {
@@ -550,18 +606,52 @@ impl Element for Flex {
 #[derive(Clone, Copy, Debug, Default, PartialEq)]
 pub struct FlexParams {
     flex: f32,
+    shrink: f32,
     basis: Option<FlexBasis>,
     alignment: Option<CrossAlignment>,
 }
 
-impl From<f32> for FlexParams {
-    fn from(value: f32) -> Self {
+impl FlexParams {
+    /// Grow into free space on the main axis in proportion to `flex`, or stay
+    /// at the basis length if `flex` is zero.
+    pub fn new(flex: f32) -> Self {
         Self {
-            flex: value,
+            flex,
+            shrink: 0.0,
             basis: None,
             alignment: None,
         }
     }
+
+    /// Give up space when the elements overflow the main axis, in proportion to
+    /// `shrink` times the basis length. Only applies to inflexible elements.
+    pub fn with_shrink(mut self, shrink: f32) -> Self {
+        self.shrink = shrink;
+        self
+    }
+
+    pub fn with_basis(mut self, basis: FlexBasis) -> Self {
+        self.basis = Some(basis);
+        self
+    }
+
+    /// Override the container's [`CrossAlignment`] for this element.
+    pub fn with_alignment(mut self, alignment: CrossAlignment) -> Self {
+        self.alignment = Some(alignment);
+        self
+    }
+}
+
+impl From<f32> for FlexParams {
+    fn from(value: f32) -> Self {
+        Self::new(value)
+    }
+}
+
+impl From<CrossAlignment> for FlexParams {
+    fn from(value: CrossAlignment) -> Self {
+        Self::new(0.0).with_alignment(value)
+    }
 }
 
 fn effective_basis(basis: Option<FlexBasis>, flex: f32) -> FlexBasis {
@@ -636,7 +726,11 @@ pub enum CrossAlignment {
     Start,
     Center,
     End,
-    Baseline,
+    /// Line up the first baselines of the children, such as the first lines of
+    /// text with different font sizes. Only applies to rows.
+    FirstBaseline,
+    /// Line up the last baselines of the children. Only applies to rows.
+    LastBaseline,
     Stretch,
 }
 
@@ -644,7 +738,7 @@ impl CrossAlignment {
     pub fn offset(self, space: f32) -> f32 {
         match self {
             Self::Start => 0.0,
-            Self::Center | Self::Baseline => space / 2.0,
+            Self::Center | Self::FirstBaseline | Self::LastBaseline => space / 2.0,
             Self::End => space,
             Self::Stretch => 0.0,
         }
@@ -1,29 +1,78 @@
 //! # Application Binary Interface (ABI)
 
+pub mod animation;
+pub mod apps;
+pub mod bidi;
+pub mod canvas;
+pub mod command;
+pub mod context_menu;
 pub mod cursor_icon;
+pub mod drag;
 pub mod elf;
 pub mod flex;
 pub mod layout;
 pub mod math;
 pub mod mem;
 pub mod path;
+pub mod progress;
+pub mod raster;
+pub mod render_stream;
+pub mod shortcut;
+pub mod split;
 pub mod stable_string;
 pub mod stable_vec;
+pub mod stack;
+pub mod system;
+pub mod table;
+pub mod tabs;
+#[cfg(any(test, feature = "testing"))]
+pub mod testing;
 pub mod text;
+pub mod theme;
+pub mod touch;
 pub mod tree;
+pub mod tree_view;
 pub mod type_map;
+pub mod undo;
 pub mod view;
+pub mod views;
+pub mod window;
 
 pub use {
+    animation::{
+        Animated, AnimatedF32, AnimatedRgba, AnimatedXy, Easing, Interpolate, Timeline, TrackId,
+    },
+    apps::{
+        AppHost, AppId, AppRegion, AppRegions, DriverHealth, DriverWatchdog, crashed_placeholder,
+    },
+    canvas::{Canvas, CanvasPaint, PathSegment, VectorPath},
+    context_menu::{ContextMenu, ContextMenuItem},
     cursor_icon::CursorIcon,
-    flex::{AxisAlignment, CrossAlignment, Flex, FlexParams},
+    drag::Drag,
+    flex::{AxisAlignment, CrossAlignment, Flex, FlexBasis, FlexParams},
     math::{Aabb2D, Axis, Transform2D, Xy},
     path::Path,
+    progress::{ProgressBar, Spinner},
+    raster::{DecodeImageError, Image, ImageComparison},
+    render_stream::{RENDER_STREAM_VERSION, RenderStreamError},
+    split::SplitPane,
     stable_string::StableString,
     stable_vec::StableVec,
-    text::{FontStyle, LineHeight, TextAlignment, TextWrapMode},
+    stack::{Stack, StackAlignment, StackParams},
+    table::{SortDirection, Table, TableColumn, TableSource},
+    tabs::Tabs,
+    text::{
+        FontFamily, FontName, FontStyle, FontWeight, LineHeight, StyledText, TextAlignment,
+        TextDecoration, TextDirection, TextParams, TextSpan, TextStyle, TextWrapMode,
+    },
+    theme::{ColorMode, Theme, TokenColor},
+    touch::TouchTracker,
+    tree_view::{TreeItem, TreeProvider, TreeView},
     type_map::{TypeMap, TypeMapEntry},
+    undo::{EditHistory, TextEdit},
     view::*,
+    views::{AppViews, UnknownViewError, ViewId},
+    window::{WindowId, WindowManager},
 };
 
 use std::fmt::Debug;
@@ -32,12 +81,57 @@ pub const VERSION: &'static str = env!("CARGO_PKG_VERSION");
 
 
 
+// NOTE: Hosts read `abi_version` before trusting anything else in a manifest,
+//       so it must stay first, and new fields must be added at the end.
 #[derive(Debug)]
+#[repr(C)]
 pub struct Manifest {
+    /// The [`VERSION`] that the application was built against.
+    pub abi_version: &'static str,
     pub name: &'static str,
+    /// Builds the root of the application's main view.
     pub init: fn() -> ElementBuilder,
     pub dependencies: &'static [&'static str],
-    pub abi_version: &'static str,
+    pub capabilities: &'static [Capability],
+    /// The application's other views (such as a settings window), which are
+    /// opened with [`system::open_view`].
+    pub views: &'static [ViewDeclaration],
+}
+
+impl Manifest {
+    /// The function that builds the root of the view with the given name, where
+    /// [`MAIN_VIEW`] is the manifest's [`init`](Self::init).
+    pub fn view_init(&self, name: &str) -> Option<fn() -> ElementBuilder> {
+        if name == MAIN_VIEW {
+            return Some(self.init);
+        }
+
+        self.views
+            .iter()
+            .find(|view| view.name == name)
+            .map(|view| view.init)
+    }
+}
+
+/// The name of the view built by [`Manifest::init`].
+pub const MAIN_VIEW: &str = "main";
+
+/// A named view that an application can open in addition to its main view.
+#[derive(Debug)]
+pub struct ViewDeclaration {
+    pub name: &'static str,
+    pub init: fn() -> ElementBuilder,
+}
+
+/// A privileged operation that an application must declare in its [`Manifest`]
+/// before the host will perform it.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+#[repr(u32)]
+pub enum Capability {
+    /// See [`system::open_url`].
+    OpenUrl = 1,
+    /// See [`command::register`].
+    RegisterCommands = 2,
 }
 
 #[macro_export]
@@ -46,13 +140,44 @@ macro_rules! manifest {
         name: $name_def:expr,
         init: $init_def:expr,
         dependencies: $dependencies_def:expr,
+    ) => {
+        $crate::manifest! {
+            name: $name_def,
+            init: $init_def,
+            dependencies: $dependencies_def,
+            capabilities: &[],
+            views: &[],
+        }
+    };
+    (
+        name: $name_def:expr,
+        init: $init_def:expr,
+        dependencies: $dependencies_def:expr,
+        capabilities: $capabilities_def:expr,
+    ) => {
+        $crate::manifest! {
+            name: $name_def,
+            init: $init_def,
+            dependencies: $dependencies_def,
+            capabilities: $capabilities_def,
+            views: &[],
+        }
+    };
+    (
+        name: $name_def:expr,
+        init: $init_def:expr,
+        dependencies: $dependencies_def:expr,
+        capabilities: $capabilities_def:expr,
+        views: $views_def:expr,
     ) => {
         #[unsafe(no_mangle)]
         pub static __MANIFEST: $crate::Manifest = $crate::Manifest {
+            abi_version: $crate::VERSION,
             name: $name_def,
             init: $init_def,
             dependencies: $dependencies_def,
-            abi_version: $crate::VERSION,
+            capabilities: $capabilities_def,
+            views: $views_def,
         };
     };
 }
@@ -70,7 +195,7 @@ macro_rules! include {
             unsafe extern "Rust" {
                 $(
                     #[link_name = concat!("__", stringify!($name), "_", stringify!($fn_name))]
-                    pub fn $fn_name($($fn_arg: $fn_arg_ty)*) $(-> $fn_ret_ty)? ;
+                    pub fn $fn_name($($fn_arg: $fn_arg_ty),*) $(-> $fn_ret_ty)? ;
                 )*
             }
         }
@@ -90,7 +215,7 @@ macro_rules! declare {
     ) => {
         $(
             #[unsafe(export_name = concat!("__", stringify!($name), "_", stringify!($fn_name)))]
-            pub unsafe extern "Rust" fn $fn_name($($fn_arg: $fn_arg_ty)*) $(-> $fn_ret_ty)? {
+            pub unsafe extern "Rust" fn $fn_name($($fn_arg: $fn_arg_ty),*) $(-> $fn_ret_ty)? {
                 $($fn_body)*
             }
         )*
@@ -99,40 +224,251 @@ macro_rules! declare {
 
 
 
+/// The state that a driver and its host share.
+///
+/// Input events aren't part of it, they're sent from the host to the driver
+/// through a channel of their own (such as `linux_uapi::channel`) with the
+/// capacity agreed on in the [`DriverHandshake`], and passed to
+/// [`AppViews::handle_input`] alongside it. Renders are sent back through
+/// another channel, a [`RenderFrame`] at a time (see [`RenderSender`]).
 #[repr(C)]
 pub struct DriverInput {
+    /// The [`ViewId`] of the view that the input is for, see
+    /// [`AppViews::handle_input`].
     pub id: u64,
+    /// The [`AppId`] of the application that the input is for, when the driver
+    /// hosts more than one, see [`AppHost::handle_input`].
+    pub app: u64,
+    /// Advanced by the driver once per frame (see [`DriverInput::beat`]), so
+    /// that the host can tell when it has died, see [`DriverWatchdog`].
+    pub heartbeat: u64,
+    /// Advanced by the host at each of its vsyncs, see
+    /// [`DriverInput::can_present`].
+    pub vsync_sequence: u64,
+    /// The [`vsync_sequence`](Self::vsync_sequence) that the driver last
+    /// presented a frame for.
+    pub presented_sequence: u64,
     pub known_bounds: Aabb2D,
-    pub events: [Option<DriverInputEvent>; DRIVER_INPUT_EVENT_CAPACITY],
-    pub render: Render,
+    /// Messages from the driver to the host, the opposite direction of its
+    /// [input events](DriverInputEvent).
+    pub controls: [Option<DriverControl>; DRIVER_CONTROL_CAPACITY],
+    /// The cursor icon that was last sent with
+    /// [`DriverControl::SetCursorIcon`], see [`AppViews::write_controls`].
+    pub cursor_icon: CursorIcon,
+}
+
+/// The number of control messages that can be queued, which is at least the
+/// number of kinds of [`DriverControl`]s.
+pub const DRIVER_CONTROL_CAPACITY: usize = 8;
+
+/// The event capacity used when a driver doesn't request one.
+pub const DRIVER_INPUT_EVENT_CAPACITY: u32 = 16;
+/// The largest event capacity a driver can negotiate.
+pub const DRIVER_INPUT_MAX_EVENT_CAPACITY: u32 = 256;
+
+/// The parameters a driver requests before its host starts sending it input.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+#[repr(C)]
+pub struct DriverHandshake {
+    /// The number of events the driver wants to be able to queue between
+    /// frames.
+    pub requested_event_capacity: u32,
+}
+
+impl Default for DriverHandshake {
+    fn default() -> Self {
+        Self {
+            requested_event_capacity: DRIVER_INPUT_EVENT_CAPACITY,
+        }
+    }
 }
 
-pub const DRIVER_INPUT_EVENT_CAPACITY: usize = 16;
+impl DriverHandshake {
+    /// The capacity of the input channel, which is the requested event
+    /// capacity rounded up to a power of two, and clamped between 1 and
+    /// [`DRIVER_INPUT_MAX_EVENT_CAPACITY`].
+    pub const fn negotiated_event_capacity(&self) -> u32 {
+        if self.requested_event_capacity >= DRIVER_INPUT_MAX_EVENT_CAPACITY {
+            DRIVER_INPUT_MAX_EVENT_CAPACITY
+        } else {
+            self.requested_event_capacity.next_power_of_two()
+        }
+    }
+}
 
 impl DriverInput {
     pub fn new(initial_bounds: Aabb2D) -> Self {
         Self {
             id: 0,
+            app: 0,
+            heartbeat: 0,
+            // The first frame can be presented without waiting for a vsync.
+            vsync_sequence: 1,
+            presented_sequence: 0,
             known_bounds: initial_bounds,
-            events: [None; DRIVER_INPUT_EVENT_CAPACITY],
-            render: Render::default(),
+            controls: [None; DRIVER_CONTROL_CAPACITY],
+            cursor_icon: CursorIcon::Default,
         }
     }
 
-    pub fn push_event(&mut self, event: DriverInputEvent) -> Option<DriverInputEvent> {
-        if let Some(null_index) = self.events.iter().position(|event| event.is_none()) {
-            self.events[null_index] = Some(event);
-            None
+    /// Advance the [`heartbeat`](Self::heartbeat), which the driver does once
+    /// per frame.
+    #[inline]
+    pub fn beat(&mut self) {
+        self.heartbeat = self.heartbeat.wrapping_add(1);
+    }
+
+    /// Let the driver present another frame, which the host does at each of its
+    /// vsyncs.
+    #[inline]
+    pub fn signal_vsync(&mut self) {
+        self.vsync_sequence = self.vsync_sequence.wrapping_add(1);
+    }
+
+    /// Whether the host has had a vsync since the driver last presented a
+    /// frame, so that the driver renders at most one frame per vsync.
+    #[inline]
+    pub fn can_present(&self) -> bool {
+        self.presented_sequence != self.vsync_sequence
+    }
+
+    /// Mark a frame as presented, which the driver does after rendering one,
+    /// after which it must wait for the next vsync to present another.
+    #[inline]
+    pub fn present(&mut self) {
+        self.presented_sequence = self.vsync_sequence;
+    }
+
+    /// Queue a control message for the host.
+    ///
+    /// Only the latest message of each kind is kept, since hosts only care
+    /// about the latest cursor icon or title.
+    pub fn push_control(&mut self, control: DriverControl) {
+        let kind = core::mem::discriminant(&control);
+        // The queue never fills up, since there are fewer kinds of messages than
+        // slots.
+        if let Some(slot) = self
+            .controls
+            .iter_mut()
+            .find(|queued| queued.is_none_or(|queued| core::mem::discriminant(&queued) == kind))
+        {
+            *slot = Some(control);
+        }
+    }
+
+    pub fn drain_controls(&mut self) -> impl Iterator<Item = DriverControl> {
+        self.controls.iter_mut().flat_map(|control| control.take())
+    }
+}
+
+/// The driver's side of sending renders to the host one [`RenderFrame`] at a
+/// time, through a channel of their own.
+///
+/// Each page is written once there's room for it in the channel (see
+/// [`RenderReceiver`]), so renders of any size are delivered whole.
+#[derive(Default)]
+pub struct RenderSender {
+    render: Render,
+    /// The next page of `render` to write, or `None` once every page has been
+    /// written.
+    next_page: Option<usize>,
+    overflow_count: u64,
+}
+
+impl RenderSender {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Start sending a render, whose pages are then written by
+    /// [`write_page`](Self::write_page).
+    ///
+    /// The render is swapped with the sender's previous one, so that its
+    /// commands can be reused for the next render. If the previous render
+    /// hasn't been completely written yet, the rest of it is dropped (with its
+    /// damage added to the new render's) and counted in the
+    /// [`overflow_count`](Self::overflow_count).
+    pub fn start(&mut self, render: &mut Render) {
+        core::mem::swap(&mut self.render, render);
+        if self.next_page.is_some() {
+            self.overflow_count += 1;
+            if let Some(damage) = render.damage {
+                self.render.add_damage(damage);
+            }
+        }
+        self.next_page = Some(0);
+    }
+
+    /// Write the next page to `frame`, which the driver does once there's room
+    /// to send it. Returns `false` if every page has already been written.
+    pub fn write_page(&mut self, frame: &mut RenderFrame) -> bool {
+        let Some(page) = self.next_page else {
+            return false;
+        };
+
+        self.render.write_frame(page, frame);
+        self.next_page = (page + 1 < self.render.page_count()).then_some(page + 1);
+        true
+    }
+
+    /// Whether some pages of the current render haven't been written yet.
+    #[inline]
+    pub fn is_sending(&self) -> bool {
+        self.next_page.is_some()
+    }
+
+    /// The number of renders that were dropped before every page was written,
+    /// because there was a newer one to send.
+    #[inline]
+    pub fn overflow_count(&self) -> u64 {
+        self.overflow_count
+    }
+}
+
+/// The host's side of [`RenderSender`], which collects the pages it wrote back
+/// into a [`Render`].
+#[derive(Default)]
+pub struct RenderReceiver {
+    render: Render,
+    /// The page expected next, or `None` if the start of the current render
+    /// was missed.
+    next_page: Option<u32>,
+}
+
+impl RenderReceiver {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Take a page that the driver has sent. Returns `true` once the last
+    /// page of a render has been taken, after which the render is available
+    /// from [`RenderReceiver::render`].
+    pub fn receive(&mut self, frame: &RenderFrame) -> bool {
+        // A first page starts a new render, even if the last one wasn't finished.
+        if frame.page == 0 {
+            self.render.clear();
+            self.next_page = Some(0);
+        }
+        if self.next_page != Some(frame.page) {
+            self.next_page = None;
+            return false;
+        }
+
+        self.render.commands.extend(frame.commands.iter().cloned());
+        self.render.damage = frame.damage;
+        if frame.is_last_page() {
+            self.next_page = None;
+            true
         } else {
-            let missed_event = self.events[0].take();
-            self.events.rotate_left(1);
-            self.events[DRIVER_INPUT_EVENT_CAPACITY - 1] = Some(event);
-            missed_event
+            self.next_page = Some(frame.page + 1);
+            false
         }
     }
 
-    pub fn drain_events(&mut self) -> impl Iterator<Item = DriverInputEvent> {
-        self.events.iter_mut().flat_map(|event| event.take())
+    /// The last render that was completely received.
+    #[inline]
+    pub fn render(&self) -> &Render {
+        &self.render
     }
 }
 
@@ -140,6 +476,236 @@ impl DriverInput {
 #[repr(C, u32)]
 pub enum DriverInputEvent {
     Pointer(PointerEvent),
+    Keyboard(KeyboardEvent),
     Other(u32),
     WindowResize(Aabb2D),
+    /// The host is closing the view, such as because the user closed its
+    /// window.
+    Close,
+}
+
+impl DriverInputEvent {
+    /// Whether the event can be replaced by the next one like it when the
+    /// driver is falling behind, which is only true of pointer moves.
+    pub const fn coalesces(&self) -> bool {
+        matches!(self, Self::Pointer(PointerEvent::Move { .. }))
+    }
+}
+
+/// A message from a driver to its host, see [`DriverInput::push_control`].
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[repr(C, u32)]
+pub enum DriverControl {
+    /// The pointer is over the view, and should look like this.
+    SetCursorIcon(CursorIcon),
+    /// The title of the view's window changed.
+    SetTitle(DriverTitle),
+    /// The application wants its view to be closed. The host still decides
+    /// whether to close it, and sends [`DriverInputEvent::Close`] if it does.
+    RequestClose,
+    /// The view should be rendered again even if there's no new input, such as
+    /// because it's animating.
+    RequestRedraw,
+}
+
+/// The longest [`DriverTitle`], in bytes.
+pub const DRIVER_TITLE_CAPACITY: usize = 64;
+
+/// A fixed-size window title, for passing through shared memory.
+#[derive(Clone, Copy, Eq, PartialEq)]
+#[repr(C)]
+pub struct DriverTitle {
+    bytes: [u8; DRIVER_TITLE_CAPACITY],
+    len: u8,
+}
+
+impl DriverTitle {
+    /// Create a title, truncated to the last character that fits in
+    /// [`DRIVER_TITLE_CAPACITY`] bytes.
+    pub fn new(title: &str) -> Self {
+        let len = title.floor_char_boundary(DRIVER_TITLE_CAPACITY);
+        let mut bytes = [0; DRIVER_TITLE_CAPACITY];
+        bytes[..len].copy_from_slice(&title.as_bytes()[..len]);
+
+        Self {
+            bytes,
+            len: len as u8,
+        }
+    }
+
+    pub fn as_str(&self) -> &str {
+        let len = (self.len as usize).min(DRIVER_TITLE_CAPACITY);
+        // Titles written by another process may not be valid.
+        core::str::from_utf8(&self.bytes[..len]).unwrap_or_default()
+    }
+}
+
+impl Debug for DriverTitle {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        Debug::fmt(self.as_str(), f)
+    }
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn driver_controls() {
+        let mut input = DriverInput::new(Aabb2D::new(0.0, 0.0, 100.0, 100.0));
+
+        input.push_control(DriverControl::SetCursorIcon(CursorIcon::IBeam));
+        input.push_control(DriverControl::RequestRedraw);
+        // Only the latest of each kind is kept.
+        input.push_control(DriverControl::SetCursorIcon(CursorIcon::Default));
+        input.push_control(DriverControl::RequestRedraw);
+        assert_eq!(
+            input.drain_controls().collect::<Vec<_>>(),
+            [
+                DriverControl::SetCursorIcon(CursorIcon::Default),
+                DriverControl::RequestRedraw,
+            ]
+        );
+        assert_eq!(input.drain_controls().count(), 0);
+
+        let title = DriverTitle::new(&"\u{e9}".repeat(DRIVER_TITLE_CAPACITY));
+        assert_eq!(title.as_str().len(), DRIVER_TITLE_CAPACITY);
+        assert_eq!(DriverTitle::new("Files").as_str(), "Files");
+    }
+
+    #[test]
+    fn driver_event_capacity() {
+        let negotiate = |requested_event_capacity| {
+            DriverHandshake {
+                requested_event_capacity,
+            }
+            .negotiated_event_capacity()
+        };
+        assert_eq!(
+            DriverHandshake::default().negotiated_event_capacity(),
+            DRIVER_INPUT_EVENT_CAPACITY,
+        );
+        assert_eq!(negotiate(0), 1);
+        assert_eq!(negotiate(100), 128);
+        assert_eq!(negotiate(1000), DRIVER_INPUT_MAX_EVENT_CAPACITY);
+        assert_eq!(negotiate(u32::MAX), DRIVER_INPUT_MAX_EVENT_CAPACITY);
+
+        assert!(
+            DriverInputEvent::Pointer(PointerEvent::Move {
+                position: Xy::new(1.0, 0.0),
+                modifiers: Modifiers::default(),
+            })
+            .coalesces()
+        );
+        assert!(!DriverInputEvent::Other(0).coalesces());
+    }
+
+    #[test]
+    fn driver_render_paging() {
+        let mut sender = RenderSender::new();
+        let mut receiver = RenderReceiver::new();
+        let mut frame = RenderFrame::default();
+
+        let chars = |count: usize, c: char| {
+            let mut render = Render::default();
+            render
+                .commands
+                .extend(std::iter::repeat_n(RenderCommand::DrawChar(c), count));
+            render.add_damage(Aabb2D::new(0.0, 0.0, 10.0, 10.0));
+            render
+        };
+
+        let mut render = chars(RENDER_FRAME_CAPACITY * 2 + 1, 'a');
+        sender.start(&mut render);
+        let mut pages = 0;
+        while sender.write_page(&mut frame) {
+            pages += 1;
+            assert_eq!(receiver.receive(&frame), !sender.is_sending());
+        }
+        assert_eq!(pages, 3);
+        assert_eq!(
+            receiver.render().commands.len(),
+            RENDER_FRAME_CAPACITY * 2 + 1
+        );
+        assert_eq!(sender.overflow_count(), 0);
+
+        // Starting a new render before the last one was written drops the rest
+        // of it, but keeps its damage.
+        let mut render = chars(RENDER_FRAME_CAPACITY + 1, 'b');
+        sender.start(&mut render);
+        assert!(sender.write_page(&mut frame));
+        assert!(!receiver.receive(&frame));
+        let mut render = chars(1, 'c');
+        render.damage = Some(Aabb2D::new(50.0, 50.0, 60.0, 60.0));
+        sender.start(&mut render);
+        assert_eq!(sender.overflow_count(), 1);
+        assert!(sender.write_page(&mut frame));
+        assert!(!sender.is_sending());
+        assert!(receiver.receive(&frame));
+        assert!(matches!(
+            receiver.render().commands[..],
+            [RenderCommand::DrawChar('c')]
+        ));
+        assert_eq!(
+            receiver.render().damage,
+            Some(Aabb2D::new(0.0, 0.0, 60.0, 60.0)),
+        );
+    }
+
+    #[test]
+    fn driver_present_handshake() {
+        static MANIFEST: Manifest = Manifest {
+            abi_version: VERSION,
+            name: "test",
+            init: || ElementBuilder::new(Column::new()),
+            dependencies: &[],
+            capabilities: &[],
+            views: &[],
+        };
+        let mut views = AppViews::new(&MANIFEST, || Box::new(testing::MockFonts));
+        let id = views.open(MAIN_VIEW, Xy::new(100.0, 100.0)).unwrap();
+        let mut input = DriverInput::new(Aabb2D::new(0.0, 0.0, 100.0, 100.0));
+        input.id = id.0;
+
+        // The first frame is rendered straight away.
+        assert!(views.should_render(&input));
+        let view = views.get_mut(id).unwrap();
+        view.render(&mut Render::default());
+        input.present();
+        assert!(!input.can_present());
+
+        // Nothing has changed, so there's nothing to render even after a vsync.
+        input.signal_vsync();
+        assert!(input.can_present());
+        assert!(!views.should_render(&input));
+
+        views
+            .get_mut(id)
+            .unwrap()
+            .resize_window(Xy::new(50.0, 50.0));
+        assert!(views.should_render(&input));
+        input.present();
+        assert!(!views.should_render(&input));
+    }
+
+    #[test]
+    fn manifest_views() {
+        static MANIFEST: Manifest = Manifest {
+            abi_version: VERSION,
+            name: "test",
+            init: || ElementBuilder::new(Column::new()),
+            dependencies: &[],
+            capabilities: &[],
+            views: &[ViewDeclaration {
+                name: "settings",
+                init: || ElementBuilder::new(Column::new()),
+            }],
+        };
+
+        assert!(MANIFEST.view_init(MAIN_VIEW).is_some());
+        assert!(MANIFEST.view_init("settings").is_some());
+        assert!(MANIFEST.view_init("about").is_none());
+    }
 }
@@ -79,6 +79,14 @@ impl Aabb2D {
         Xy::new(self.min.x, self.min.y)
     }
 
+    #[inline]
+    pub const fn center(&self) -> Xy<f32> {
+        Xy::new(
+            (self.min.x + self.max.x) / 2.0,
+            (self.min.y + self.max.y) / 2.0,
+        )
+    }
+
     #[inline]
     pub const fn abs(&self) -> Self {
         let Self { min, max } = *self;
@@ -277,6 +285,7 @@ impl<V: Mul<V, Output = V>> Mul for Xy<V> {
 
 
 #[derive(Clone, Copy, Debug, PartialEq)]
+#[repr(C)]
 pub struct Transform2D([f32; 6]);
 
 impl Transform2D {
@@ -302,6 +311,40 @@ impl Transform2D {
         Transform2D([amount, 0.0, 0.0, amount, 0.0, 0.0])
     }
 
+    /// An affine transform representing scaling by a different amount on each
+    /// axis.
+    #[inline(always)]
+    pub const fn scale_xy(amount: Xy<f32>) -> Transform2D {
+        Transform2D([amount.x, 0.0, 0.0, amount.y, 0.0, 0.0])
+    }
+
+    /// An affine transform representing a clockwise rotation (in y-down space)
+    /// around the origin.
+    #[inline]
+    pub fn rotation(radians: f32) -> Transform2D {
+        let (sin, cos) = radians.sin_cos();
+        Transform2D([cos, sin, -sin, cos, 0.0, 0.0])
+    }
+
+    /// An affine transform representing a translation.
+    #[inline(always)]
+    pub const fn translate(translation: Xy<f32>) -> Transform2D {
+        Transform2D::IDENTITY.with_translation(translation)
+    }
+
+    /// The coefficients passed to [`Transform2D::new`].
+    #[inline(always)]
+    pub const fn coefficients(self) -> [f32; 6] {
+        self.0
+    }
+
+    /// Whether the transform only scales and translates, so that axis-aligned
+    /// areas stay axis-aligned.
+    #[inline]
+    pub const fn is_axis_aligned(self) -> bool {
+        self.0[1] == 0.0 && self.0[2] == 0.0
+    }
+
     #[inline(always)]
     pub const fn translation(self) -> Xy<f32> {
         Xy {
@@ -317,6 +360,13 @@ impl Transform2D {
         self
     }
 
+    /// The uniform scale that the transform applies to areas, ignoring
+    /// rotation and skew.
+    #[inline]
+    pub fn scale_factor(self) -> f32 {
+        self.determinant().abs().sqrt()
+    }
+
     pub const fn determinant(self) -> f32 {
         self.0[0] * self.0[3] - self.0[1] * self.0[2]
     }
@@ -340,7 +390,13 @@ impl Transform2D {
         let p10 = self * Xy::new(area.max.x, area.min.y);
         let p11 = self * Xy::new(area.max.x, area.max.y);
 
-        Aabb2D::from_min_max(p00, p01).union(Aabb2D::from_min_max(p10, p11))
+        // NOTE: Any corner can end up at the minimum once the area is rotated or
+        //       flipped, so each one is added on its own.
+        [p01, p10, p11]
+            .into_iter()
+            .fold(Aabb2D::from_min_max(p00, p00), |area, point| {
+                area.union(Aabb2D::from_min_max(point, point))
+            })
     }
 }
 
@@ -0,0 +1,226 @@
+//! # Progress Indicators
+//!
+//! [`ProgressBar`] shows how much of a task is complete, while [`Spinner`]
+//! shows that something is happening without knowing how long it will take.
+
+use std::f32::consts::TAU;
+
+use crate::{
+    Aabb2D, AnimatePass, Axis, Element, LengthRequest, MeasureContext, RenderPass, Rgba,
+    UpdatePass, Xy,
+};
+
+
+
+/// A horizontal bar that fills up as a task progresses.
+///
+/// The track uses the `"surface.sunken"` and `"border"` theme tokens, and the
+/// filled part uses `"accent"`.
+pub struct ProgressBar {
+    progress: f32,
+    thickness: f32,
+}
+
+impl Default for ProgressBar {
+    fn default() -> Self {
+        Self::new(0.0)
+    }
+}
+
+impl ProgressBar {
+    pub fn new(progress: f32) -> Self {
+        Self {
+            progress: progress.clamp(0.0, 1.0),
+            thickness: 6.0,
+        }
+    }
+
+    /// Set the height of the bar. Defaults to `6.0`.
+    pub fn with_thickness(mut self, thickness: f32) -> Self {
+        self.thickness = thickness;
+        self
+    }
+
+    /// How much of the task is complete, from `0.0` to `1.0`.
+    #[inline]
+    pub fn progress(&self) -> f32 {
+        self.progress
+    }
+
+    pub fn set_progress(&mut self, progress: f32) {
+        self.progress = progress.clamp(0.0, 1.0);
+    }
+}
+
+impl Element for ProgressBar {
+    fn render(&mut self, pass: &mut RenderPass<'_>) {
+        let bounds = pass.bounds();
+        pass.fill_quad(
+            bounds,
+            pass.color("surface.sunken"),
+            1.0,
+            pass.color("border"),
+        );
+
+        if self.progress > 0.0 {
+            let size = bounds.size();
+            pass.fill_quad(
+                Aabb2D::from_size_position(
+                    Xy::new(size.x * self.progress, size.y),
+                    bounds.position(),
+                ),
+                pass.color("accent"),
+                0.0,
+                Rgba::NONE,
+            );
+        }
+    }
+
+    fn measure(
+        &mut self,
+        _context: &mut MeasureContext<'_>,
+        axis: Axis,
+        length_request: LengthRequest,
+        _cross_length: Option<f32>,
+    ) -> f32 {
+        match axis {
+            Axis::Horizontal => match length_request {
+                LengthRequest::MinContent | LengthRequest::MaxContent => 100.0,
+                LengthRequest::FitContent(space) => space,
+            },
+            Axis::Vertical => self.thickness,
+        }
+    }
+}
+
+/// An indeterminate progress indicator, made of a ring of dots that fade in
+/// turn.
+///
+/// The dots use the `"accent"` theme token.
+pub struct Spinner {
+    size: f32,
+    period_ms: f32,
+    /// How far through the current rotation the spinner is, from `0.0` to
+    /// `1.0`.
+    phase: f32,
+}
+
+impl Default for Spinner {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Spinner {
+    const DOT_COUNT: usize = 8;
+
+    pub fn new() -> Self {
+        Self {
+            size: 24.0,
+            period_ms: 1000.0,
+            phase: 0.0,
+        }
+    }
+
+    /// Set the width and height of the spinner. Defaults to `24.0`.
+    pub fn with_size(mut self, size: f32) -> Self {
+        self.size = size;
+        self
+    }
+
+    /// Set how long a full rotation takes, in milliseconds. Defaults to
+    /// `1000.0`.
+    pub fn with_period(mut self, period_ms: f32) -> Self {
+        self.period_ms = period_ms.max(1.0);
+        self
+    }
+
+    fn advance(&mut self, ms: f32) {
+        self.phase = (self.phase + ms / self.period_ms).fract();
+    }
+
+    /// The opacity of the dot at `index`, where the dot the spinner is
+    /// currently on is fully opaque and those behind it fade out.
+    fn dot_alpha(&self, index: usize) -> f32 {
+        let count = Self::DOT_COUNT as f32;
+        let behind = (self.phase * count - index as f32).rem_euclid(count);
+
+        1.0 - behind / count
+    }
+}
+
+impl Element for Spinner {
+    fn on_build(&mut self, pass: &mut UpdatePass<'_>) {
+        pass.request_animate();
+    }
+
+    fn animate(&mut self, pass: &mut AnimatePass<'_>, dt: f64) {
+        self.advance((dt * 1000.0) as f32);
+        pass.request_render();
+        pass.request_animate();
+    }
+
+    fn render(&mut self, pass: &mut RenderPass<'_>) {
+        let bounds = pass.bounds();
+        let size = bounds.size();
+        let center = bounds.position() + Xy::new(size.x * 0.5, size.y * 0.5);
+        let diameter = size.x.min(size.y);
+        let dot_size = diameter * 0.2;
+        let radius = (diameter - dot_size) * 0.5;
+        let color = pass.color("accent");
+
+        for index in 0..Self::DOT_COUNT {
+            let angle = TAU * index as f32 / Self::DOT_COUNT as f32;
+            let dot_center = center + Xy::new(angle.sin() * radius, -angle.cos() * radius);
+            let alpha = color.a as f32 * self.dot_alpha(index);
+            pass.fill_quad(
+                Aabb2D::from_size_position(
+                    Xy::new(dot_size, dot_size),
+                    dot_center - Xy::new(dot_size * 0.5, dot_size * 0.5),
+                ),
+                color.with_alpha(alpha.round() as u8),
+                0.0,
+                Rgba::NONE,
+            );
+        }
+    }
+
+    fn measure(
+        &mut self,
+        _context: &mut MeasureContext<'_>,
+        _axis: Axis,
+        _length_request: LengthRequest,
+        _cross_length: Option<f32>,
+    ) -> f32 {
+        self.size
+    }
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn progress_is_clamped() {
+        let mut bar = ProgressBar::new(1.5);
+        assert_eq!(bar.progress(), 1.0);
+        bar.set_progress(-0.5);
+        assert_eq!(bar.progress(), 0.0);
+    }
+
+    #[test]
+    fn spinner_dots_follow_phase() {
+        let mut spinner = Spinner::new().with_period(800.0);
+        assert_eq!(spinner.dot_alpha(0), 1.0);
+
+        spinner.advance(300.0);
+        assert_eq!(spinner.dot_alpha(3), 1.0);
+        assert!(spinner.dot_alpha(2) > spinner.dot_alpha(1));
+
+        // Wraps around after a full rotation.
+        spinner.advance(500.0);
+        assert_eq!(spinner.dot_alpha(0), 1.0);
+    }
+}
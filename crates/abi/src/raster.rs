@@ -0,0 +1,859 @@
+//! # Software Rasterizer
+//!
+//! Draws a [`Render`] into an [`Image`] on the CPU, so that views can be
+//! compared pixel-by-pixel against golden images on machines without a GPU.
+//!
+//! The output is meant to be deterministic rather than pretty. Shapes aren't
+//! antialiased (each pixel is covered or not, depending on its center), and
+//! text is drawn as a block for each character, laid out like
+//! [`testing::MockFonts`](crate::testing::MockFonts) measures it.
+//!
+//! Images can be saved and loaded in the [PAM] format, which most image viewers
+//! can open, and saved (uncompressed) in the [PNG] format, which all of them
+//! can.
+//!
+//! [PAM]: https://netpbm.sourceforge.net/doc/pam.html
+//! [PNG]: https://www.w3.org/TR/png-3/
+
+use crate::{
+    Aabb2D, FontWeight, PathSegment, Render, RenderCommand, Rgba, TextDecoration, Transform2D, Xy,
+};
+
+
+
+/// The advance of every character, relative to the font size.
+const GLYPH_ADVANCE: f32 = 0.6;
+/// The number of lines that each curve is split into.
+const CURVE_STEPS: usize = 12;
+
+/// An RGBA image, with straight (not premultiplied) alpha.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Image {
+    width: u32,
+    height: u32,
+    pixels: Vec<Rgba>,
+}
+
+impl Image {
+    /// Create an image filled with the given color.
+    pub fn new(width: u32, height: u32, color: Rgba) -> Self {
+        Self {
+            width,
+            height,
+            pixels: vec![color; width as usize * height as usize],
+        }
+    }
+
+    #[inline]
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    #[inline]
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    /// Every pixel, row by row from the top left.
+    #[inline]
+    pub fn pixels(&self) -> &[Rgba] {
+        &self.pixels
+    }
+
+    #[inline]
+    pub fn pixels_mut(&mut self) -> &mut [Rgba] {
+        &mut self.pixels
+    }
+
+    pub fn pixel(&self, x: u32, y: u32) -> Option<Rgba> {
+        if x >= self.width || y >= self.height {
+            return None;
+        }
+
+        Some(self.pixels[y as usize * self.width as usize + x as usize])
+    }
+
+    pub fn fill(&mut self, color: Rgba) {
+        self.pixels.fill(color);
+    }
+
+    /// Draw the render's commands over this image, see the
+    /// [module documentation](self).
+    pub fn draw_render(&mut self, render: &Render) {
+        Rasterizer::new(self).draw(&render.commands);
+    }
+
+    /// Compare this image to another, such as a golden image, where channels
+    /// that differ by at most `tolerance` are considered equal.
+    pub fn compare(&self, other: &Image, tolerance: u8) -> ImageComparison {
+        if (self.width, self.height) != (other.width, other.height) {
+            return ImageComparison {
+                size_matches: false,
+                differing_pixels: self.pixels.len().max(other.pixels.len()),
+                max_difference: u8::MAX,
+            };
+        }
+
+        let mut differing_pixels = 0;
+        let mut max_difference = 0;
+        for (a, b) in self.pixels.iter().zip(&other.pixels) {
+            let difference = [
+                a.r.abs_diff(b.r),
+                a.g.abs_diff(b.g),
+                a.b.abs_diff(b.b),
+                a.a.abs_diff(b.a),
+            ]
+            .into_iter()
+            .max()
+            .unwrap_or_default();
+            if difference > tolerance {
+                differing_pixels += 1;
+            }
+            max_difference = max_difference.max(difference);
+        }
+
+        ImageComparison {
+            size_matches: true,
+            differing_pixels,
+            max_difference,
+        }
+    }
+
+    /// Encode the image in the PAM format.
+    pub fn encode_pam(&self) -> Vec<u8> {
+        let header = format!(
+            "P7\nWIDTH {}\nHEIGHT {}\nDEPTH 4\nMAXVAL 255\nTUPLTYPE RGB_ALPHA\nENDHDR\n",
+            self.width, self.height,
+        );
+        let mut bytes = Vec::with_capacity(header.len() + self.pixels.len() * 4);
+        bytes.extend_from_slice(header.as_bytes());
+        for pixel in &self.pixels {
+            bytes.extend_from_slice(&[pixel.r, pixel.g, pixel.b, pixel.a]);
+        }
+
+        bytes
+    }
+
+    /// Encode the image in the PNG format, with its image data stored rather
+    /// than compressed.
+    pub fn encode_png(&self) -> Vec<u8> {
+        // Each row starts with its filter type, which is always "none".
+        let mut data = Vec::with_capacity(self.height as usize * (1 + self.width as usize * 4));
+        if self.width != 0 {
+            for row in self.pixels.chunks_exact(self.width as usize) {
+                data.push(0);
+                for pixel in row {
+                    data.extend_from_slice(&[pixel.r, pixel.g, pixel.b, pixel.a]);
+                }
+            }
+        }
+
+        let mut header = Vec::with_capacity(13);
+        header.extend_from_slice(&self.width.to_be_bytes());
+        header.extend_from_slice(&self.height.to_be_bytes());
+        // 8 bits per channel, RGBA, and no interlacing.
+        header.extend_from_slice(&[8, 6, 0, 0, 0]);
+
+        let mut bytes = PNG_SIGNATURE.to_vec();
+        write_png_chunk(&mut bytes, b"IHDR", &header);
+        write_png_chunk(&mut bytes, b"IDAT", &zlib_stored(&data));
+        write_png_chunk(&mut bytes, b"IEND", &[]);
+
+        bytes
+    }
+
+    /// Decode an image written by [`Image::encode_pam`].
+    pub fn decode_pam(bytes: &[u8]) -> Result<Self, DecodeImageError> {
+        const END_OF_HEADER: &[u8] = b"ENDHDR\n";
+
+        let header_len = bytes
+            .windows(END_OF_HEADER.len())
+            .position(|window| window == END_OF_HEADER)
+            .ok_or(DecodeImageError::InvalidHeader)?;
+        let header =
+            str::from_utf8(&bytes[..header_len]).map_err(|_| DecodeImageError::InvalidHeader)?;
+        let data = &bytes[header_len + END_OF_HEADER.len()..];
+
+        let mut lines = header.lines();
+        if lines.next() != Some("P7") {
+            return Err(DecodeImageError::InvalidHeader);
+        }
+        let (mut width, mut height) = (None, None);
+        for line in lines {
+            let (name, value) = line
+                .split_once(' ')
+                .ok_or(DecodeImageError::InvalidHeader)?;
+            match name {
+                "WIDTH" => width = value.parse::<u32>().ok(),
+                "HEIGHT" => height = value.parse::<u32>().ok(),
+                "DEPTH" if value != "4" => return Err(DecodeImageError::Unsupported),
+                "MAXVAL" if value != "255" => return Err(DecodeImageError::Unsupported),
+                "TUPLTYPE" if value != "RGB_ALPHA" => return Err(DecodeImageError::Unsupported),
+                _ => {}
+            }
+        }
+        let (Some(width), Some(height)) = (width, height) else {
+            return Err(DecodeImageError::InvalidHeader);
+        };
+        if data.len() != width as usize * height as usize * 4 {
+            return Err(DecodeImageError::UnexpectedLength);
+        }
+
+        Ok(Self {
+            width,
+            height,
+            pixels: data
+                .chunks_exact(4)
+                .map(|pixel| Rgba::new(pixel[0], pixel[1], pixel[2], pixel[3]))
+                .collect(),
+        })
+    }
+}
+
+/// The result of [`Image::compare`].
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct ImageComparison {
+    /// Whether the images are the same size. If they aren't, every pixel is
+    /// considered different.
+    pub size_matches: bool,
+    /// The number of pixels with a channel that differs by more than the
+    /// tolerance.
+    pub differing_pixels: usize,
+    /// The largest difference between any channel of any pixel.
+    pub max_difference: u8,
+}
+
+impl ImageComparison {
+    #[inline]
+    pub fn matches(&self) -> bool {
+        self.size_matches && self.differing_pixels == 0
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum DecodeImageError {
+    /// The data doesn't start with a PAM header.
+    InvalidHeader,
+    /// The image isn't 8-bit RGBA.
+    Unsupported,
+    /// There isn't exactly one pixel for each position in the image.
+    UnexpectedLength,
+}
+
+impl core::fmt::Display for DecodeImageError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.write_str(match self {
+            Self::InvalidHeader => "invalid PAM header",
+            Self::Unsupported => "unsupported PAM image, expected 8-bit RGBA",
+            Self::UnexpectedLength => "PAM image data doesn't match its size",
+        })
+    }
+}
+
+impl core::error::Error for DecodeImageError {}
+
+const PNG_SIGNATURE: [u8; 8] = [0x89, b'P', b'N', b'G', b'\r', b'\n', 0x1A, b'\n'];
+
+/// The largest block of stored (uncompressed) data in a deflate stream.
+const MAX_STORED_BLOCK_LEN: usize = u16::MAX as usize;
+
+/// Write a PNG chunk, which is its length, type, data, and a CRC of its type
+/// and data.
+fn write_png_chunk(bytes: &mut Vec<u8>, kind: &[u8; 4], data: &[u8]) {
+    bytes.extend_from_slice(&(data.len() as u32).to_be_bytes());
+    let start = bytes.len();
+    bytes.extend_from_slice(kind);
+    bytes.extend_from_slice(data);
+    let crc = crc32(&bytes[start..]);
+    bytes.extend_from_slice(&crc.to_be_bytes());
+}
+
+/// Wrap data in a zlib stream of stored deflate blocks.
+// https://www.rfc-editor.org/rfc/rfc1950
+// https://www.rfc-editor.org/rfc/rfc1951#section-3.2.4
+fn zlib_stored(data: &[u8]) -> Vec<u8> {
+    let block_count = data.len().div_ceil(MAX_STORED_BLOCK_LEN).max(1);
+    let mut stream = Vec::with_capacity(data.len() + block_count * 5 + 6);
+    // Deflate with a 32 KiB window, and no preset dictionary.
+    stream.extend_from_slice(&[0x78, 0x01]);
+    for index in 0..block_count {
+        let start = index * MAX_STORED_BLOCK_LEN;
+        let block = &data[start..data.len().min(start + MAX_STORED_BLOCK_LEN)];
+        let len = block.len() as u16;
+        stream.push((index + 1 == block_count) as u8);
+        stream.extend_from_slice(&len.to_le_bytes());
+        stream.extend_from_slice(&(!len).to_le_bytes());
+        stream.extend_from_slice(block);
+    }
+    stream.extend_from_slice(&adler32(data).to_be_bytes());
+
+    stream
+}
+
+fn adler32(data: &[u8]) -> u32 {
+    const MODULUS: u32 = 65521;
+    // The sums can't overflow within this many bytes before being reduced.
+    const CHUNK_LEN: usize = 5552;
+
+    let (mut a, mut b) = (1u32, 0u32);
+    for chunk in data.chunks(CHUNK_LEN) {
+        for byte in chunk {
+            a += *byte as u32;
+            b += a;
+        }
+        a %= MODULUS;
+        b %= MODULUS;
+    }
+
+    (b << 16) | a
+}
+
+/// The CRC-32 of each byte, for [`crc32`].
+const CRC_TABLE: [u32; 256] = {
+    let mut table = [0; 256];
+    let mut index = 0;
+    while index < 256 {
+        let mut crc = index as u32;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = if crc & 1 != 0 {
+                0xEDB8_8320 ^ (crc >> 1)
+            } else {
+                crc >> 1
+            };
+            bit += 1;
+        }
+        table[index] = crc;
+        index += 1;
+    }
+    table
+};
+
+fn crc32(data: &[u8]) -> u32 {
+    !data.iter().fold(!0, |crc, byte| {
+        CRC_TABLE[((crc ^ *byte as u32) & 0xFF) as usize] ^ (crc >> 8)
+    })
+}
+
+
+
+/// The state that render commands set, starting with the same values as a
+/// [`RenderPass`](crate::RenderPass).
+struct Rasterizer<'image> {
+    image: &'image mut Image,
+    bounds: Aabb2D,
+    font_size: f32,
+    foreground_color: Rgba,
+    background_color: Rgba,
+    border_color: Rgba,
+    border_width: f32,
+    opacity: f32,
+    transform: Transform2D,
+    font_weight: FontWeight,
+    text_decoration: TextDecoration,
+    /// Where the next character is drawn, which is reset to the top left of the
+    /// bounds whenever anything other than text is drawn.
+    pen: Option<Xy<f32>>,
+    path: Vec<PathSegment>,
+}
+
+impl<'image> Rasterizer<'image> {
+    fn new(image: &'image mut Image) -> Self {
+        Self {
+            image,
+            bounds: Aabb2D::ZERO,
+            font_size: 16.0,
+            foreground_color: Rgba::WHITE,
+            background_color: Rgba::BLACK,
+            border_color: Rgba::NONE,
+            border_width: 0.0,
+            opacity: 1.0,
+            transform: Transform2D::IDENTITY,
+            font_weight: FontWeight::NORMAL,
+            text_decoration: TextDecoration::NONE,
+            pen: None,
+            path: Vec::new(),
+        }
+    }
+
+    fn draw(&mut self, commands: &[RenderCommand]) {
+        for command in commands {
+            match command {
+                RenderCommand::DrawChar(ch) => {
+                    self.draw_char(*ch);
+                    continue;
+                }
+                RenderCommand::DrawQuad => self.draw_quad(),
+                RenderCommand::SetBounds(bounds) => self.bounds = *bounds,
+                RenderCommand::SetForegroundColor(color) => {
+                    self.foreground_color = *color;
+                    continue;
+                }
+                RenderCommand::SetBackgroundColor(color) => self.background_color = *color,
+                RenderCommand::SetBorderColor(color) => self.border_color = *color,
+                RenderCommand::SetBorderWidth(width) => self.border_width = *width,
+                RenderCommand::SetFontSize(size) => self.font_size = *size,
+                RenderCommand::SetOpacity(opacity) => self.opacity = opacity.clamp(0.0, 1.0),
+                RenderCommand::SetTransform(transform) => self.transform = *transform,
+                RenderCommand::SetFontWeight(weight) => {
+                    self.font_weight = *weight;
+                    continue;
+                }
+                RenderCommand::SetTextDecoration(decoration) => {
+                    self.text_decoration = *decoration;
+                    continue;
+                }
+                RenderCommand::SetFontFamily(_) | RenderCommand::SetFontStyle(_) => continue,
+                RenderCommand::BeginPath => self.path.clear(),
+                RenderCommand::PathSegment(segment) => self.path.push(*segment),
+                RenderCommand::FillPath => self.fill_path(),
+                RenderCommand::StrokePath => self.stroke_path(),
+            }
+            // NOTE: Style changes happen in the middle of styled text, so only the
+            //       other commands end it.
+            self.pen = None;
+        }
+    }
+
+    fn draw_quad(&mut self) {
+        let bounds = self.bounds;
+        self.fill_rect(bounds, self.background_color);
+
+        let width = self
+            .border_width
+            .min(bounds.size().x / 2.0)
+            .min(bounds.size().y / 2.0);
+        if width <= 0.0 {
+            return;
+        }
+        let Aabb2D { min, max } = bounds;
+        let color = self.border_color;
+        self.fill_rect(Aabb2D::new(min.x, min.y, max.x, min.y + width), color);
+        self.fill_rect(Aabb2D::new(min.x, max.y - width, max.x, max.y), color);
+        self.fill_rect(
+            Aabb2D::new(min.x, min.y + width, min.x + width, max.y - width),
+            color,
+        );
+        self.fill_rect(
+            Aabb2D::new(max.x - width, min.y + width, max.x, max.y - width),
+            color,
+        );
+    }
+
+    fn draw_char(&mut self, ch: char) {
+        let size = self.font_size;
+        let advance = size * GLYPH_ADVANCE;
+        let bounds = self.bounds;
+        let mut pen = self.pen.unwrap_or(bounds.position());
+
+        if ch == '\n' {
+            self.pen = Some(Xy::new(bounds.min.x, pen.y + size));
+            return;
+        }
+        if pen.x + advance > bounds.max.x && pen.x > bounds.min.x {
+            pen = Xy::new(bounds.min.x, pen.y + size);
+        }
+        self.pen = Some(Xy::new(pen.x + advance, pen.y));
+
+        let color = self.foreground_color;
+        if !ch.is_whitespace() {
+            let inset = if self.font_weight.0 >= FontWeight::BOLD.0 {
+                0.05
+            } else {
+                0.1
+            };
+            self.fill_rect(
+                Aabb2D::new(
+                    pen.x + size * inset,
+                    pen.y + size * 0.25,
+                    pen.x + advance - size * inset,
+                    pen.y + size * 0.8,
+                ),
+                color,
+            );
+        }
+
+        let thickness = (size / 16.0).max(1.0);
+        if self.text_decoration.underline {
+            let y = pen.y + size * 0.9;
+            self.fill_rect(Aabb2D::new(pen.x, y, pen.x + advance, y + thickness), color);
+        }
+        if self.text_decoration.strikethrough {
+            let y = pen.y + size * 0.5;
+            self.fill_rect(Aabb2D::new(pen.x, y, pen.x + advance, y + thickness), color);
+        }
+    }
+
+    fn fill_path(&mut self) {
+        let polygons = flatten_path(&self.path)
+            .into_iter()
+            .map(|(points, _)| self.transform_points(points))
+            .collect::<Vec<_>>();
+        self.fill_polygons(&polygons, self.background_color);
+    }
+
+    /// Strokes are drawn as a rectangle along each line, so wide strokes have
+    /// gaps at sharp corners.
+    fn stroke_path(&mut self) {
+        let half_width = self.border_width / 2.0;
+        if half_width <= 0.0 {
+            return;
+        }
+
+        let mut polygons = Vec::new();
+        for (mut points, closed) in flatten_path(&self.path) {
+            if closed && let Some(first) = points.first() {
+                points.push(*first);
+            }
+            for line in points.windows(2) {
+                let (from, to) = (line[0], line[1]);
+                let direction = to - from;
+                let length = direction.length_squared().sqrt();
+                if length <= f32::EPSILON {
+                    continue;
+                }
+                let normal = Xy::new(
+                    -direction.y / length * half_width,
+                    direction.x / length * half_width,
+                );
+                polygons.push(vec![from + normal, to + normal, to - normal, from - normal]);
+            }
+        }
+        for polygon in polygons {
+            let polygon = self.transform_points(polygon);
+            self.fill_polygons(&[polygon], self.border_color);
+        }
+    }
+
+    fn transform_points(&self, mut points: Vec<Xy<f32>>) -> Vec<Xy<f32>> {
+        if self.transform != Transform2D::IDENTITY {
+            for point in &mut points {
+                *point = self.transform * *point;
+            }
+        }
+        points
+    }
+
+    fn fill_rect(&mut self, rect: Aabb2D, color: Rgba) {
+        if !self.transform.is_axis_aligned() {
+            let Aabb2D { min, max } = rect;
+            let corners = vec![min, Xy::new(max.x, min.y), max, Xy::new(min.x, max.y)];
+            let corners = self.transform_points(corners);
+            self.fill_polygons(&[corners], color);
+            return;
+        }
+
+        let rect = self.transform.transform_area(rect);
+        let Some((x_range, y_range)) = self.pixel_ranges(rect.min, rect.max) else {
+            return;
+        };
+        let color = self.with_opacity(color);
+        for y in y_range {
+            for x in x_range.clone() {
+                self.blend(x, y, color);
+            }
+        }
+    }
+
+    /// Fill the area enclosed by the polygons with the non-zero winding rule.
+    fn fill_polygons(&mut self, polygons: &[Vec<Xy<f32>>], color: Rgba) {
+        let points = polygons.iter().flatten();
+        let min = points
+            .clone()
+            .fold(Xy::new(f32::INFINITY, f32::INFINITY), |min, point| {
+                Xy::new(min.x.min(point.x), min.y.min(point.y))
+            });
+        let max = points.fold(
+            Xy::new(f32::NEG_INFINITY, f32::NEG_INFINITY),
+            |max, point| Xy::new(max.x.max(point.x), max.y.max(point.y)),
+        );
+        let Some((_, y_range)) = self.pixel_ranges(min, max) else {
+            return;
+        };
+        let color = self.with_opacity(color);
+
+        let mut crossings = Vec::new();
+        for y in y_range {
+            let center_y = y as f32 + 0.5;
+            crossings.clear();
+            for polygon in polygons {
+                for (index, from) in polygon.iter().enumerate() {
+                    let to = polygon[(index + 1) % polygon.len()];
+                    let winding = match (from.y <= center_y, to.y <= center_y) {
+                        (true, false) => 1,
+                        (false, true) => -1,
+                        _ => continue,
+                    };
+                    let t = (center_y - from.y) / (to.y - from.y);
+                    crossings.push((from.x + (to.x - from.x) * t, winding));
+                }
+            }
+            crossings.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+            let mut winding = 0;
+            for pair in crossings.windows(2) {
+                winding += pair[0].1;
+                if winding == 0 {
+                    continue;
+                }
+                let Some(x_range) = pixel_range(pair[0].0, pair[1].0, self.image.width) else {
+                    continue;
+                };
+                for x in x_range {
+                    self.blend(x, y, color);
+                }
+            }
+        }
+    }
+
+    /// The pixels whose centers are within the area, clipped to the image.
+    fn pixel_ranges(
+        &self,
+        min: Xy<f32>,
+        max: Xy<f32>,
+    ) -> Option<(core::ops::Range<u32>, core::ops::Range<u32>)> {
+        Some((
+            pixel_range(min.x, max.x, self.image.width)?,
+            pixel_range(min.y, max.y, self.image.height)?,
+        ))
+    }
+
+    fn with_opacity(&self, color: Rgba) -> Rgba {
+        Rgba {
+            a: (color.a as f32 * self.opacity).round() as u8,
+            ..color
+        }
+    }
+
+    /// Draw the color over the pixel.
+    fn blend(&mut self, x: u32, y: u32, color: Rgba) {
+        let index = y as usize * self.image.width as usize + x as usize;
+        let pixel = &mut self.image.pixels[index];
+        if color.a == 0 {
+            return;
+        }
+        if color.a == u8::MAX {
+            *pixel = color;
+            return;
+        }
+
+        let source_alpha = color.a as f32 / 255.0;
+        let dest_alpha = pixel.a as f32 / 255.0 * (1.0 - source_alpha);
+        let alpha = source_alpha + dest_alpha;
+        let channel = |source: u8, dest: u8| {
+            ((source as f32 * source_alpha + dest as f32 * dest_alpha) / alpha).round() as u8
+        };
+        *pixel = Rgba {
+            r: channel(color.r, pixel.r),
+            g: channel(color.g, pixel.g),
+            b: channel(color.b, pixel.b),
+            a: (alpha * 255.0).round() as u8,
+        };
+    }
+}
+
+/// The pixels whose centers are between `min` and `max`, clipped to `len`.
+fn pixel_range(min: f32, max: f32, len: u32) -> Option<core::ops::Range<u32>> {
+    let start = (min - 0.5).ceil().clamp(0.0, len as f32) as u32;
+    let end = (max - 0.5).ceil().clamp(0.0, len as f32) as u32;
+
+    (start < end).then_some(start..end)
+}
+
+/// Split the path into polylines, along with whether each was explicitly
+/// closed.
+///
+/// Curves are split into a fixed number of lines, so hosts that draw paths
+/// themselves can use this to match the rasterizer.
+pub fn flatten_path(segments: &[PathSegment]) -> Vec<(Vec<Xy<f32>>, bool)> {
+    let mut polylines = Vec::new();
+    let mut points: Vec<Xy<f32>> = Vec::new();
+    let mut finish = |points: &mut Vec<Xy<f32>>, closed: bool| {
+        if points.len() > 1 {
+            polylines.push((std::mem::take(points), closed));
+        } else {
+            points.clear();
+        }
+    };
+
+    for segment in segments {
+        let last = points.last().copied().unwrap_or(Xy::ZERO);
+        match *segment {
+            PathSegment::MoveTo(to) => {
+                finish(&mut points, false);
+                points.push(to);
+            }
+            PathSegment::LineTo(to) => {
+                if points.is_empty() {
+                    points.push(last);
+                }
+                points.push(to);
+            }
+            PathSegment::QuadTo(control, to) => {
+                if points.is_empty() {
+                    points.push(last);
+                }
+                for step in 1..=CURVE_STEPS {
+                    let t = step as f32 / CURVE_STEPS as f32;
+                    let u = 1.0 - t;
+                    points.push(Xy::new(
+                        u * u * last.x + 2.0 * u * t * control.x + t * t * to.x,
+                        u * u * last.y + 2.0 * u * t * control.y + t * t * to.y,
+                    ));
+                }
+            }
+            PathSegment::CubicTo(control_a, control_b, to) => {
+                if points.is_empty() {
+                    points.push(last);
+                }
+                for step in 1..=CURVE_STEPS {
+                    let t = step as f32 / CURVE_STEPS as f32;
+                    let u = 1.0 - t;
+                    let (a, b, c, d) = (u * u * u, 3.0 * u * u * t, 3.0 * u * t * t, t * t * t);
+                    points.push(Xy::new(
+                        a * last.x + b * control_a.x + c * control_b.x + d * to.x,
+                        a * last.y + b * control_a.y + c * control_b.y + d * to.y,
+                    ));
+                }
+            }
+            PathSegment::Close => finish(&mut points, true),
+        }
+    }
+    finish(&mut points, false);
+
+    polylines
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn quads_with_borders() {
+        let mut image = Image::new(4, 4, Rgba::BLACK);
+        image.draw_render(&Render {
+            commands: vec![
+                RenderCommand::SetBounds(Aabb2D::new(0.0, 0.0, 4.0, 4.0)),
+                RenderCommand::SetBackgroundColor(Rgba::WHITE),
+                RenderCommand::SetBorderColor(Rgba::rgb(255, 0, 0)),
+                RenderCommand::SetBorderWidth(1.0),
+                RenderCommand::DrawQuad,
+                RenderCommand::SetOpacity(0.5),
+                RenderCommand::SetBounds(Aabb2D::new(2.0, 2.0, 3.0, 3.0)),
+                RenderCommand::SetBackgroundColor(Rgba::BLACK),
+                RenderCommand::SetBorderWidth(0.0),
+                RenderCommand::DrawQuad,
+            ],
+            damage: None,
+        });
+
+        assert_eq!(image.pixel(0, 0), Some(Rgba::rgb(255, 0, 0)));
+        assert_eq!(image.pixel(1, 1), Some(Rgba::WHITE));
+        assert_eq!(image.pixel(2, 2), Some(Rgba::rgb(127, 127, 127)));
+        assert_eq!(image.pixel(3, 3), Some(Rgba::rgb(255, 0, 0)));
+    }
+
+    #[test]
+    fn paths_fill_with_nonzero_winding() {
+        let mut image = Image::new(10, 10, Rgba::NONE);
+        image.draw_render(&Render {
+            commands: vec![
+                RenderCommand::SetBackgroundColor(Rgba::WHITE),
+                RenderCommand::BeginPath,
+                RenderCommand::PathSegment(PathSegment::MoveTo(Xy::new(0.0, 0.0))),
+                RenderCommand::PathSegment(PathSegment::LineTo(Xy::new(10.0, 0.0))),
+                RenderCommand::PathSegment(PathSegment::LineTo(Xy::new(0.0, 10.0))),
+                RenderCommand::PathSegment(PathSegment::Close),
+                RenderCommand::FillPath,
+            ],
+            damage: None,
+        });
+
+        assert_eq!(image.pixel(1, 1), Some(Rgba::WHITE));
+        assert_eq!(image.pixel(8, 8), Some(Rgba::NONE));
+        assert_eq!(
+            image.pixels().iter().filter(|pixel| pixel.a > 0).count(),
+            45
+        );
+    }
+
+    #[test]
+    fn transformed_quads() {
+        let mut image = Image::new(10, 10, Rgba::BLACK);
+        image.draw_render(&Render {
+            commands: vec![
+                // A quarter turn around the center of the image.
+                RenderCommand::SetTransform(Transform2D::new([0.0, 1.0, -1.0, 0.0, 10.0, 0.0])),
+                RenderCommand::SetBounds(Aabb2D::new(0.0, 0.0, 10.0, 4.0)),
+                RenderCommand::SetBackgroundColor(Rgba::WHITE),
+                RenderCommand::DrawQuad,
+                RenderCommand::SetTransform(Transform2D::IDENTITY),
+                RenderCommand::SetBounds(Aabb2D::new(0.0, 9.0, 1.0, 10.0)),
+                RenderCommand::SetBackgroundColor(Rgba::rgb(255, 0, 0)),
+                RenderCommand::DrawQuad,
+            ],
+            damage: None,
+        });
+
+        // The top rows of the quad are turned into the rightmost columns.
+        assert_eq!(image.pixel(6, 0), Some(Rgba::WHITE));
+        assert_eq!(image.pixel(9, 9), Some(Rgba::WHITE));
+        assert_eq!(image.pixel(5, 0), Some(Rgba::BLACK));
+        assert_eq!(image.pixel(0, 0), Some(Rgba::BLACK));
+        assert_eq!(image.pixel(0, 9), Some(Rgba::rgb(255, 0, 0)));
+    }
+
+    #[test]
+    fn pam_round_trip() {
+        let mut image = Image::new(3, 2, Rgba::rgb(1, 2, 3));
+        image.draw_render(&Render {
+            commands: vec![
+                RenderCommand::SetBounds(Aabb2D::new(0.0, 0.0, 10.0, 10.0)),
+                RenderCommand::SetFontSize(10.0),
+                RenderCommand::DrawChar('x'),
+            ],
+            damage: None,
+        });
+
+        let decoded = Image::decode_pam(&image.encode_pam()).unwrap();
+        assert_eq!(decoded, image);
+        assert!(decoded.compare(&image, 0).matches());
+        assert!(!Image::new(3, 2, Rgba::BLACK).compare(&image, 0).matches());
+    }
+
+    #[test]
+    fn png_checksums() {
+        assert_eq!(adler32(b"Wikipedia"), 0x11E6_0398);
+        assert_eq!(crc32(b"IEND"), 0xAE42_6082);
+    }
+
+    #[test]
+    fn png_encoding() {
+        let image = Image::new(2, 1, Rgba::rgb(1, 2, 3));
+        let png = image.encode_png();
+
+        assert_eq!(png[..8], PNG_SIGNATURE);
+        // The header's length, type, and size.
+        assert_eq!(png[8..24], *b"\0\0\0\x0DIHDR\0\0\0\x02\0\0\0\x01");
+        assert!(png.ends_with(b"IEND\xAE\x42\x60\x82"));
+
+        let rows = [0, 1, 2, 3, 255, 1, 2, 3, 255];
+        let idat = png.windows(4).position(|kind| kind == b"IDAT").unwrap() + 4;
+        // The zlib header, then a single final block of the rows.
+        assert_eq!(png[idat..idat + 7], [0x78, 0x01, 1, 9, 0, 0xF6, 0xFF]);
+        assert_eq!(png[idat + 7..idat + 16], rows);
+        assert_eq!(png[idat + 16..idat + 20], adler32(&rows).to_be_bytes());
+    }
+
+    #[test]
+    fn zlib_splits_large_data_into_blocks() {
+        let data = vec![7; MAX_STORED_BLOCK_LEN + 1];
+        let stream = zlib_stored(&data);
+
+        assert_eq!(stream.len(), 2 + 5 + MAX_STORED_BLOCK_LEN + 5 + 1 + 4);
+        assert_eq!(stream[2], 0);
+        assert_eq!(stream[2 + 5 + MAX_STORED_BLOCK_LEN], 1);
+    }
+}
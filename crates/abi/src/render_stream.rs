@@ -0,0 +1,438 @@
+//! # Render Streams
+//!
+//! A compact binary encoding of a [`Render`], for passing render commands
+//! between processes without sharing any pointers.
+//!
+//! A stream starts with [`RENDER_STREAM_VERSION`] and the render's damage,
+//! followed by one command after another until the end of the stream. Each
+//! command is a tag byte followed by its fields in little-endian order.
+//! Consecutive [`RenderCommand::DrawChar`]s are encoded as a single
+//! length-prefixed UTF-8 run, so text costs about a byte per character.
+
+use crate::{
+    Aabb2D, FontFamily, FontName, FontStyle, FontWeight, PathSegment, Render, RenderCommand, Rgba,
+    TextDecoration, Transform2D, Xy,
+};
+
+
+
+/// The version of the encoding written by [`Render::encode`], which is the
+/// first byte of every stream.
+pub const RENDER_STREAM_VERSION: u8 = 4;
+
+const TAG_TEXT: u8 = 0;
+const TAG_DRAW_QUAD: u8 = 1;
+const TAG_SET_BOUNDS: u8 = 2;
+const TAG_SET_FOREGROUND_COLOR: u8 = 3;
+const TAG_SET_BACKGROUND_COLOR: u8 = 4;
+const TAG_SET_BORDER_COLOR: u8 = 5;
+const TAG_SET_BORDER_WIDTH: u8 = 6;
+const TAG_SET_FONT_SIZE: u8 = 7;
+const TAG_SET_OPACITY: u8 = 8;
+const TAG_SET_FONT_STYLE: u8 = 9;
+const TAG_SET_FONT_WEIGHT: u8 = 10;
+const TAG_SET_TEXT_DECORATION: u8 = 11;
+const TAG_SET_FONT_FAMILY: u8 = 12;
+const TAG_BEGIN_PATH: u8 = 13;
+const TAG_PATH_SEGMENT: u8 = 14;
+const TAG_FILL_PATH: u8 = 15;
+const TAG_STROKE_PATH: u8 = 16;
+const TAG_SET_TRANSFORM: u8 = 17;
+
+const SEGMENT_MOVE_TO: u8 = 0;
+const SEGMENT_LINE_TO: u8 = 1;
+const SEGMENT_QUAD_TO: u8 = 2;
+const SEGMENT_CUBIC_TO: u8 = 3;
+const SEGMENT_CLOSE: u8 = 4;
+
+const DECORATION_UNDERLINE: u8 = 1 << 0;
+const DECORATION_STRIKETHROUGH: u8 = 1 << 1;
+
+impl Render {
+    /// Append this render to the given buffer as a render stream.
+    pub fn encode(&self, out: &mut Vec<u8>) {
+        out.push(RENDER_STREAM_VERSION);
+        match self.damage {
+            Some(area) => {
+                out.push(1);
+                write_area(out, area);
+            }
+            None => out.push(0),
+        }
+
+        let mut text = String::new();
+        for command in &self.commands {
+            if let RenderCommand::DrawChar(ch) = command {
+                text.push(*ch);
+                continue;
+            }
+            flush_text(out, &mut text);
+
+            match command {
+                RenderCommand::DrawChar(_) => unreachable!(),
+                RenderCommand::DrawQuad => out.push(TAG_DRAW_QUAD),
+                RenderCommand::SetBounds(bounds) => {
+                    out.push(TAG_SET_BOUNDS);
+                    write_area(out, *bounds);
+                }
+                RenderCommand::SetForegroundColor(color) => {
+                    out.push(TAG_SET_FOREGROUND_COLOR);
+                    write_color(out, *color);
+                }
+                RenderCommand::SetBackgroundColor(color) => {
+                    out.push(TAG_SET_BACKGROUND_COLOR);
+                    write_color(out, *color);
+                }
+                RenderCommand::SetBorderColor(color) => {
+                    out.push(TAG_SET_BORDER_COLOR);
+                    write_color(out, *color);
+                }
+                RenderCommand::SetBorderWidth(width) => {
+                    out.push(TAG_SET_BORDER_WIDTH);
+                    out.extend_from_slice(&width.to_le_bytes());
+                }
+                RenderCommand::SetFontSize(size) => {
+                    out.push(TAG_SET_FONT_SIZE);
+                    out.extend_from_slice(&size.to_le_bytes());
+                }
+                RenderCommand::SetOpacity(opacity) => {
+                    out.push(TAG_SET_OPACITY);
+                    out.extend_from_slice(&opacity.to_le_bytes());
+                }
+                RenderCommand::SetFontFamily(family) => {
+                    out.push(TAG_SET_FONT_FAMILY);
+                    match family {
+                        FontFamily::Proportional => out.push(0),
+                        FontFamily::Monospace => out.push(1),
+                        FontFamily::Named(name) => {
+                            out.extend_from_slice(&[2, name.as_str().len() as u8]);
+                            out.extend_from_slice(name.as_str().as_bytes());
+                        }
+                    }
+                }
+                RenderCommand::SetFontStyle(style) => {
+                    out.push(TAG_SET_FONT_STYLE);
+                    out.push(*style as u8);
+                }
+                RenderCommand::SetFontWeight(weight) => {
+                    out.push(TAG_SET_FONT_WEIGHT);
+                    out.extend_from_slice(&weight.0.to_le_bytes());
+                }
+                RenderCommand::SetTextDecoration(decoration) => {
+                    let mut bits = 0;
+                    if decoration.underline {
+                        bits |= DECORATION_UNDERLINE;
+                    }
+                    if decoration.strikethrough {
+                        bits |= DECORATION_STRIKETHROUGH;
+                    }
+                    out.extend_from_slice(&[TAG_SET_TEXT_DECORATION, bits]);
+                }
+                RenderCommand::BeginPath => out.push(TAG_BEGIN_PATH),
+                RenderCommand::PathSegment(segment) => {
+                    let kind = match segment {
+                        PathSegment::MoveTo(_) => SEGMENT_MOVE_TO,
+                        PathSegment::LineTo(_) => SEGMENT_LINE_TO,
+                        PathSegment::QuadTo(..) => SEGMENT_QUAD_TO,
+                        PathSegment::CubicTo(..) => SEGMENT_CUBIC_TO,
+                        PathSegment::Close => SEGMENT_CLOSE,
+                    };
+                    out.extend_from_slice(&[TAG_PATH_SEGMENT, kind]);
+                    for point in segment.points() {
+                        write_point(out, point);
+                    }
+                }
+                RenderCommand::FillPath => out.push(TAG_FILL_PATH),
+                RenderCommand::StrokePath => out.push(TAG_STROKE_PATH),
+                RenderCommand::SetTransform(transform) => {
+                    out.push(TAG_SET_TRANSFORM);
+                    for coefficient in transform.coefficients() {
+                        out.extend_from_slice(&coefficient.to_le_bytes());
+                    }
+                }
+            }
+        }
+        flush_text(out, &mut text);
+    }
+
+    /// Read a render from a render stream written by [`Render::encode`].
+    pub fn decode(bytes: &[u8]) -> Result<Self, RenderStreamError> {
+        let mut reader = Reader { bytes };
+        let version = reader.u8()?;
+        if version != RENDER_STREAM_VERSION {
+            return Err(RenderStreamError::UnsupportedVersion(version));
+        }
+
+        let damage = match reader.u8()? {
+            0 => None,
+            _ => Some(reader.area()?),
+        };
+        let mut render = Render {
+            commands: Vec::new(),
+            damage,
+        };
+
+        while !reader.bytes.is_empty() {
+            let command = match reader.u8()? {
+                TAG_TEXT => {
+                    let len = reader.u32()? as usize;
+                    let text = str::from_utf8(reader.take(len)?)
+                        .map_err(|_| RenderStreamError::InvalidText)?;
+                    render
+                        .commands
+                        .extend(text.chars().map(RenderCommand::DrawChar));
+                    continue;
+                }
+                TAG_DRAW_QUAD => RenderCommand::DrawQuad,
+                TAG_SET_BOUNDS => RenderCommand::SetBounds(reader.area()?),
+                TAG_SET_FOREGROUND_COLOR => RenderCommand::SetForegroundColor(reader.color()?),
+                TAG_SET_BACKGROUND_COLOR => RenderCommand::SetBackgroundColor(reader.color()?),
+                TAG_SET_BORDER_COLOR => RenderCommand::SetBorderColor(reader.color()?),
+                TAG_SET_BORDER_WIDTH => RenderCommand::SetBorderWidth(reader.f32()?),
+                TAG_SET_FONT_SIZE => RenderCommand::SetFontSize(reader.f32()?),
+                TAG_SET_OPACITY => RenderCommand::SetOpacity(reader.f32()?),
+                TAG_SET_FONT_FAMILY => RenderCommand::SetFontFamily(match reader.u8()? {
+                    0 => FontFamily::Proportional,
+                    1 => FontFamily::Monospace,
+                    2 => {
+                        let len = reader.u8()? as usize;
+                        let name = str::from_utf8(reader.take(len)?)
+                            .map_err(|_| RenderStreamError::InvalidText)?;
+                        FontFamily::Named(
+                            FontName::new(name)
+                                .ok_or(RenderStreamError::InvalidValue(len as u8))?,
+                        )
+                    }
+                    value => return Err(RenderStreamError::InvalidValue(value)),
+                }),
+                TAG_SET_FONT_STYLE => RenderCommand::SetFontStyle(match reader.u8()? {
+                    0 => FontStyle::Normal,
+                    1 => FontStyle::Italic,
+                    2 => FontStyle::Oblique,
+                    value => return Err(RenderStreamError::InvalidValue(value)),
+                }),
+                TAG_SET_FONT_WEIGHT => RenderCommand::SetFontWeight(FontWeight(reader.u16()?)),
+                TAG_SET_TEXT_DECORATION => {
+                    let bits = reader.u8()?;
+                    RenderCommand::SetTextDecoration(TextDecoration {
+                        underline: bits & DECORATION_UNDERLINE != 0,
+                        strikethrough: bits & DECORATION_STRIKETHROUGH != 0,
+                    })
+                }
+                TAG_BEGIN_PATH => RenderCommand::BeginPath,
+                TAG_PATH_SEGMENT => RenderCommand::PathSegment(match reader.u8()? {
+                    SEGMENT_MOVE_TO => PathSegment::MoveTo(reader.point()?),
+                    SEGMENT_LINE_TO => PathSegment::LineTo(reader.point()?),
+                    SEGMENT_QUAD_TO => PathSegment::QuadTo(reader.point()?, reader.point()?),
+                    SEGMENT_CUBIC_TO => {
+                        PathSegment::CubicTo(reader.point()?, reader.point()?, reader.point()?)
+                    }
+                    SEGMENT_CLOSE => PathSegment::Close,
+                    value => return Err(RenderStreamError::InvalidValue(value)),
+                }),
+                TAG_FILL_PATH => RenderCommand::FillPath,
+                TAG_STROKE_PATH => RenderCommand::StrokePath,
+                TAG_SET_TRANSFORM => {
+                    let mut coefficients = [0.0; 6];
+                    for coefficient in &mut coefficients {
+                        *coefficient = reader.f32()?;
+                    }
+                    RenderCommand::SetTransform(Transform2D::new(coefficients))
+                }
+                tag => return Err(RenderStreamError::UnknownCommand(tag)),
+            };
+            render.commands.push(command);
+        }
+
+        Ok(render)
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum RenderStreamError {
+    /// The stream was written with a different version of the encoding.
+    UnsupportedVersion(u8),
+    /// The stream ended in the middle of a command.
+    UnexpectedEnd,
+    /// The stream contains a command tag that isn't known to this version.
+    UnknownCommand(u8),
+    /// A text run isn't valid UTF-8.
+    InvalidText,
+    /// A command has a field that's out of range, such as an unknown font
+    /// style.
+    InvalidValue(u8),
+}
+
+impl RenderStreamError {
+    pub const fn description(&self) -> &'static str {
+        match self {
+            Self::UnsupportedVersion(_) => "unsupported render stream version",
+            Self::UnexpectedEnd => "unexpected end of render stream",
+            Self::UnknownCommand(_) => "unknown render command",
+            Self::InvalidText => "invalid text in render stream",
+            Self::InvalidValue(_) => "invalid value in render command",
+        }
+    }
+}
+
+impl core::fmt::Display for RenderStreamError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::UnsupportedVersion(value)
+            | Self::UnknownCommand(value)
+            | Self::InvalidValue(value) => {
+                write!(f, "{} ({value})", self.description())
+            }
+            _ => f.write_str(self.description()),
+        }
+    }
+}
+
+impl core::error::Error for RenderStreamError {}
+
+
+
+fn flush_text(out: &mut Vec<u8>, text: &mut String) {
+    if text.is_empty() {
+        return;
+    }
+    out.push(TAG_TEXT);
+    out.extend_from_slice(&(text.len() as u32).to_le_bytes());
+    out.extend_from_slice(text.as_bytes());
+    text.clear();
+}
+
+fn write_area(out: &mut Vec<u8>, area: Aabb2D) {
+    for value in [area.min.x, area.min.y, area.max.x, area.max.y] {
+        out.extend_from_slice(&value.to_le_bytes());
+    }
+}
+
+fn write_point(out: &mut Vec<u8>, point: Xy<f32>) {
+    out.extend_from_slice(&point.x.to_le_bytes());
+    out.extend_from_slice(&point.y.to_le_bytes());
+}
+
+fn write_color(out: &mut Vec<u8>, color: Rgba) {
+    out.extend_from_slice(&[color.r, color.g, color.b, color.a]);
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8], RenderStreamError> {
+        if self.bytes.len() < len {
+            return Err(RenderStreamError::UnexpectedEnd);
+        }
+        let (taken, rest) = self.bytes.split_at(len);
+        self.bytes = rest;
+
+        Ok(taken)
+    }
+
+    fn array<const N: usize>(&mut self) -> Result<[u8; N], RenderStreamError> {
+        Ok(self.take(N)?.try_into().expect("infallible"))
+    }
+
+    fn u8(&mut self) -> Result<u8, RenderStreamError> {
+        Ok(self.array::<1>()?[0])
+    }
+
+    fn u16(&mut self) -> Result<u16, RenderStreamError> {
+        Ok(u16::from_le_bytes(self.array()?))
+    }
+
+    fn u32(&mut self) -> Result<u32, RenderStreamError> {
+        Ok(u32::from_le_bytes(self.array()?))
+    }
+
+    fn f32(&mut self) -> Result<f32, RenderStreamError> {
+        Ok(f32::from_le_bytes(self.array()?))
+    }
+
+    fn point(&mut self) -> Result<Xy<f32>, RenderStreamError> {
+        Ok(Xy::new(self.f32()?, self.f32()?))
+    }
+
+    fn area(&mut self) -> Result<Aabb2D, RenderStreamError> {
+        let min = self.point()?;
+        let max = self.point()?;
+
+        Ok(Aabb2D::from_min_max(min, max))
+    }
+
+    fn color(&mut self) -> Result<Rgba, RenderStreamError> {
+        let [r, g, b, a] = self.array()?;
+
+        Ok(Rgba { r, g, b, a })
+    }
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn render_stream_round_trips() {
+        let render = Render {
+            commands: vec![
+                RenderCommand::SetBounds(Aabb2D::from_min_max(
+                    Xy::new(1.0, 2.0),
+                    Xy::new(30.0, 40.0),
+                )),
+                RenderCommand::SetForegroundColor(Rgba::WHITE),
+                RenderCommand::DrawChar('h'),
+                RenderCommand::SetFontFamily(FontFamily::Monospace),
+                RenderCommand::DrawChar('x'),
+                RenderCommand::SetFontFamily(FontFamily::named("Noto Serif").unwrap()),
+                RenderCommand::SetFontWeight(FontWeight::BOLD),
+                RenderCommand::SetFontStyle(FontStyle::Italic),
+                RenderCommand::SetTextDecoration(TextDecoration::UNDERLINE),
+                RenderCommand::DrawChar('é'),
+                RenderCommand::SetOpacity(0.5),
+                RenderCommand::DrawQuad,
+                RenderCommand::BeginPath,
+                RenderCommand::PathSegment(PathSegment::MoveTo(Xy::new(0.0, 0.0))),
+                RenderCommand::PathSegment(PathSegment::QuadTo(
+                    Xy::new(5.0, 10.0),
+                    Xy::new(10.0, 0.0),
+                )),
+                RenderCommand::PathSegment(PathSegment::CubicTo(
+                    Xy::new(12.0, 2.0),
+                    Xy::new(14.0, 4.0),
+                    Xy::new(16.0, 0.0),
+                )),
+                RenderCommand::PathSegment(PathSegment::Close),
+                RenderCommand::FillPath,
+                RenderCommand::StrokePath,
+                RenderCommand::SetTransform(Transform2D::new([0.0, 1.0, -1.0, 0.0, 20.5, -3.0])),
+                RenderCommand::DrawChar('!'),
+            ],
+            damage: Some(Aabb2D::from_size(Xy::new(800.0, 600.0))),
+        };
+
+        let mut bytes = Vec::new();
+        render.encode(&mut bytes);
+        let decoded = Render::decode(&bytes).unwrap();
+
+        assert_eq!(decoded.commands, render.commands);
+        assert_eq!(decoded.damage, render.damage);
+
+        assert_eq!(
+            Render::decode(&bytes[..bytes.len() - 1]).err(),
+            Some(RenderStreamError::UnexpectedEnd),
+        );
+        assert_eq!(
+            Render::decode(&[RENDER_STREAM_VERSION, 0, 0xff]).err(),
+            Some(RenderStreamError::UnknownCommand(0xff)),
+        );
+        assert_eq!(
+            Render::decode(&[RENDER_STREAM_VERSION, 0, TAG_SET_FONT_STYLE, 7]).err(),
+            Some(RenderStreamError::InvalidValue(7)),
+        );
+    }
+}
@@ -0,0 +1,532 @@
+//! # Keyboard Shortcuts
+//!
+//! Multi-key shortcut sequences (e.g. `g g` or `Ctrl+K Ctrl+C`), with support
+//! for a leader key and a timeout between key presses.
+
+use std::time::{Duration, Instant};
+
+use crate::{Key, Modifiers};
+
+
+
+/// A key pressed with a set of modifier keys held, such as `Ctrl+K`.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct KeyChord {
+    pub key: Key,
+    pub modifiers: Modifiers,
+}
+
+impl KeyChord {
+    /// A key pressed without any modifiers.
+    pub const fn new(key: Key) -> Self {
+        Self {
+            key,
+            modifiers: Modifiers::NONE,
+        }
+    }
+
+    pub const fn with_modifiers(key: Key, modifiers: Modifiers) -> Self {
+        Self { key, modifiers }
+    }
+
+    /// The chord for a key pressed with the given modifiers held.
+    ///
+    /// Shift is ignored for characters, since it's already part of the
+    /// character (`G` rather than `Shift+g`).
+    pub fn from_key_press(key: Key, mut modifiers: Modifiers) -> Self {
+        if let Key::Char(_) = key {
+            modifiers.shift = false;
+        }
+
+        Self { key, modifiers }
+    }
+
+    /// Parse a single chord, such as `g`, `<up>` or `ctrl+k`, see
+    /// [`KeySequence::parse`].
+    fn parse(token: &str, leader: Option<Key>) -> Result<Self, &'static str> {
+        let (modifier_names, key) = match token.strip_suffix("++") {
+            Some(modifier_names) => (Some(modifier_names), "+"),
+            None => match token.rsplit_once('+') {
+                Some((modifier_names, key)) if !key.is_empty() => (Some(modifier_names), key),
+                _ => (None, token),
+            },
+        };
+
+        let mut modifiers = Modifiers::NONE;
+        for name in modifier_names
+            .into_iter()
+            .flat_map(|names| names.split('+'))
+        {
+            match name.to_ascii_lowercase().as_str() {
+                "ctrl" => modifiers.ctrl = true,
+                "shift" => modifiers.shift = true,
+                "alt" => modifiers.alt = true,
+                "meta" | "super" => modifiers.meta = true,
+                _ => return Err("unknown modifier name"),
+            }
+        }
+
+        let mut chars = key.chars();
+        if let (Some(ch), None) = (chars.next(), chars.next()) {
+            return Ok(Self::from_key_press(Key::Char(ch), modifiers));
+        }
+
+        let key = match key.to_ascii_lowercase().as_str() {
+            "<leader>" => leader.ok_or("no leader key set")?,
+            "<space>" => Key::Space,
+            "<tab>" => Key::Tab,
+            "<enter>" => Key::Enter,
+            "<backspace>" => Key::Backspace,
+            "<delete>" => Key::Delete,
+            "<escape>" => Key::Escape,
+            "<up>" => Key::ArrowUp,
+            "<down>" => Key::ArrowDown,
+            "<left>" => Key::ArrowLeft,
+            "<right>" => Key::ArrowRight,
+            "<pageup>" => Key::PageUp,
+            "<pagedown>" => Key::PageDown,
+            _ => return Err("unknown key name"),
+        };
+
+        Ok(Self::from_key_press(key, modifiers))
+    }
+
+    /// Get a short human-readable name for the chord, such as `Ctrl+K`.
+    pub fn name(&self) -> String {
+        let mut name = String::new();
+        for (held, modifier) in [
+            (self.modifiers.ctrl, "Ctrl+"),
+            (self.modifiers.alt, "Alt+"),
+            (self.modifiers.shift, "Shift+"),
+            (self.modifiers.meta, "Super+"),
+        ] {
+            if held {
+                name.push_str(modifier);
+            }
+        }
+        match self.key {
+            // Characters are upper-cased when combined with modifiers, like they're
+            // usually written (`Ctrl+K`).
+            Key::Char(ch) if self.modifiers != Modifiers::NONE => {
+                name.extend(ch.to_uppercase());
+            }
+            key => name.push_str(&key_name(key)),
+        }
+
+        name
+    }
+}
+
+impl From<Key> for KeyChord {
+    fn from(key: Key) -> Self {
+        Self::new(key)
+    }
+}
+
+/// A sequence of key chords that must be pressed in order.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct KeySequence(pub Vec<KeyChord>);
+
+impl KeySequence {
+    /// Parse a space-separated sequence of key chords, such as `"g g"`,
+    /// `"<leader> f"` or `"ctrl+k ctrl+c"`.
+    ///
+    /// Single characters map to [`Key::Char`], and the names `<space>`,
+    /// `<tab>`, `<enter>`, `<backspace>`, `<delete>`, `<escape>`, `<up>`,
+    /// `<down>`, `<left>`, `<right>`, `<pageup>` and `<pagedown>` map to their
+    /// respective keys. `<leader>` is replaced with `leader`.
+    ///
+    /// Keys can be prefixed with any of the modifiers `ctrl+`, `shift+`,
+    /// `alt+` and `meta+` (or `super+`).
+    pub fn parse(text: &str, leader: Option<Key>) -> Result<Self, &'static str> {
+        text.split_whitespace()
+            .map(|token| KeyChord::parse(token, leader))
+            .collect::<Result<Vec<_>, _>>()
+            .and_then(|chords| {
+                if chords.is_empty() {
+                    Err("empty key sequence")
+                } else {
+                    Ok(Self(chords))
+                }
+            })
+    }
+}
+
+/// What to do with the oldest of the keys that a [`ShortcutMap`] has
+/// intercepted, see [`ShortcutMap::handle_key`].
+///
+/// Keys that are part of an incomplete sequence are held back until the
+/// sequence is completed or abandoned, so each result covers the oldest `keys`
+/// keys that haven't been covered yet.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum ShortcutResult<A> {
+    /// The keys completed a sequence, so they're consumed and its action should
+    /// be triggered.
+    Matched { action: A, keys: usize },
+    /// The keys aren't part of any sequence, so they should be passed on as if
+    /// they were never intercepted.
+    Unmatched { keys: usize },
+}
+
+struct Binding<A> {
+    sequence: KeySequence,
+    action: A,
+    description: String,
+}
+
+/// A set of key sequences and the actions they trigger.
+pub struct ShortcutMap<A> {
+    bindings: Vec<Binding<A>>,
+    leader: Option<Key>,
+    timeout: Duration,
+    pending: Vec<KeyChord>,
+    last_key_time: Option<Instant>,
+}
+
+impl<A: Clone> ShortcutMap<A> {
+    pub fn new() -> Self {
+        Self {
+            bindings: Vec::new(),
+            leader: None,
+            timeout: Duration::from_millis(1000),
+            pending: Vec::new(),
+            last_key_time: None,
+        }
+    }
+
+    /// Set the key substituted for `<leader>` in sequences bound after this
+    /// call.
+    pub fn with_leader(mut self, leader: Key) -> Self {
+        self.leader = Some(leader);
+        self
+    }
+
+    /// Set how long to wait between keys before a pending sequence is
+    /// abandoned.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    #[inline]
+    pub fn timeout(&self) -> Duration {
+        self.timeout
+    }
+
+    /// Bind a sequence (see [`KeySequence::parse`]) to an action.
+    pub fn bind(
+        &mut self,
+        sequence: &str,
+        action: A,
+        description: impl ToString,
+    ) -> Result<(), &'static str> {
+        let sequence = KeySequence::parse(sequence, self.leader)?;
+        self.bind_sequence(sequence, action, description);
+
+        Ok(())
+    }
+
+    pub fn bind_sequence(&mut self, sequence: KeySequence, action: A, description: impl ToString) {
+        self.bindings.retain(|binding| binding.sequence != sequence);
+        self.bindings.push(Binding {
+            sequence,
+            action,
+            description: description.to_string(),
+        });
+    }
+
+    /// The keys pressed so far in an incomplete sequence.
+    #[inline]
+    pub fn pending(&self) -> &[KeyChord] {
+        &self.pending
+    }
+
+    pub fn is_pending(&self) -> bool {
+        !self.pending.is_empty()
+    }
+
+    /// Iterate over the possible continuations of the pending sequence, as the
+    /// remaining keys and the description of the bound action.
+    pub fn continuations(&self) -> impl Iterator<Item = (&[KeyChord], &str)> {
+        self.bindings
+            .iter()
+            .filter(|binding| {
+                self.is_pending()
+                    && binding.sequence.0.len() > self.pending.len()
+                    && binding.sequence.0.starts_with(&self.pending)
+            })
+            .map(|binding| {
+                (
+                    &binding.sequence.0[self.pending.len()..],
+                    binding.description.as_str(),
+                )
+            })
+    }
+
+    /// Abandon the pending sequence if the timeout has elapsed, see
+    /// [`ShortcutMap::abandon`].
+    pub fn expire(&mut self, now: Instant) -> Vec<ShortcutResult<A>> {
+        if self
+            .last_key_time
+            .is_some_and(|time| now.duration_since(time) >= self.timeout)
+        {
+            self.abandon()
+        } else {
+            Vec::new()
+        }
+    }
+
+    /// Stop waiting for the pending sequence to be completed.
+    ///
+    /// The longest run of pending keys that is a complete sequence on its own
+    /// (such as `g` when both `g` and `g g` are bound) is matched, and any keys
+    /// that aren't part of one are unmatched.
+    pub fn abandon(&mut self) -> Vec<ShortcutResult<A>> {
+        let mut keys = std::mem::take(&mut self.pending);
+        self.last_key_time = None;
+
+        let mut results = Vec::new();
+        while !keys.is_empty() {
+            let longest_match = (1..=keys.len())
+                .rev()
+                .find_map(|len| Some((len, self.exact_match(&keys[..len])?)));
+            let len = match longest_match {
+                Some((len, action)) => {
+                    results.push(ShortcutResult::Matched { action, keys: len });
+                    len
+                }
+                None => {
+                    push_result(&mut results, ShortcutResult::Unmatched { keys: 1 });
+                    1
+                }
+            };
+            keys.drain(..len);
+        }
+
+        results
+    }
+
+    pub fn reset(&mut self) {
+        self.pending.clear();
+        self.last_key_time = None;
+    }
+
+    /// Intercept a key press, returning what to do with the keys that are no
+    /// longer pending, oldest first. Nothing is returned while the key is part
+    /// of an incomplete sequence.
+    pub fn handle_key(
+        &mut self,
+        chord: impl Into<KeyChord>,
+        now: Instant,
+    ) -> Vec<ShortcutResult<A>> {
+        let chord = chord.into();
+        let mut results = self.expire(now);
+
+        self.pending.push(chord);
+        self.last_key_time = Some(now);
+
+        // NOTE: Longer sequences take priority, so an exact match that is also the
+        //       prefix of another sequence stays pending until the sequence is
+        //       broken or times out. Otherwise it would be impossible to type the
+        //       longer one.
+        if self.is_prefix_of_longer(&self.pending) {
+            return results;
+        }
+        if let Some(action) = self.exact_match(&self.pending) {
+            let keys = self.pending.len();
+            self.reset();
+            results.push(ShortcutResult::Matched { action, keys });
+            return results;
+        }
+
+        // The key broke the pending sequence, so the keys before it are abandoned
+        // and it gets a chance to start a new sequence.
+        if self.pending.len() > 1 {
+            self.pending.pop();
+            for result in self
+                .abandon()
+                .into_iter()
+                .chain(self.handle_key(chord, now))
+            {
+                push_result(&mut results, result);
+            }
+        } else {
+            self.reset();
+            push_result(&mut results, ShortcutResult::Unmatched { keys: 1 });
+        }
+
+        results
+    }
+
+    fn exact_match(&self, keys: &[KeyChord]) -> Option<A> {
+        self.bindings
+            .iter()
+            .find(|binding| binding.sequence.0 == keys)
+            .map(|binding| binding.action.clone())
+    }
+
+    fn is_prefix_of_longer(&self, keys: &[KeyChord]) -> bool {
+        self.bindings.iter().any(|binding| {
+            binding.sequence.0.len() > keys.len() && binding.sequence.0.starts_with(keys)
+        })
+    }
+}
+
+impl<A: Clone> Default for ShortcutMap<A> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Add a result, merging consecutive unmatched keys into a single result.
+fn push_result<A>(results: &mut Vec<ShortcutResult<A>>, result: ShortcutResult<A>) {
+    match (results.last_mut(), result) {
+        (
+            Some(ShortcutResult::Unmatched { keys: previous }),
+            ShortcutResult::Unmatched { keys },
+        ) => *previous += keys,
+        (_, result) => results.push(result),
+    }
+}
+
+/// Get a short human-readable name for a key.
+pub fn key_name(key: Key) -> String {
+    match key {
+        Key::Char(ch) => ch.to_string(),
+        Key::Space => "Space".into(),
+        Key::Tab => "Tab".into(),
+        Key::Enter => "Enter".into(),
+        Key::Backspace => "Backspace".into(),
+        Key::Delete => "Delete".into(),
+        Key::Escape => "Escape".into(),
+        Key::ArrowUp => "Up".into(),
+        Key::ArrowDown => "Down".into(),
+        Key::ArrowLeft => "Left".into(),
+        Key::ArrowRight => "Right".into(),
+        Key::PageUp => "PageUp".into(),
+        Key::PageDown => "PageDown".into(),
+    }
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn matched<A>(action: A, keys: usize) -> ShortcutResult<A> {
+        ShortcutResult::Matched { action, keys }
+    }
+
+    fn unmatched<A>(keys: usize) -> ShortcutResult<A> {
+        ShortcutResult::Unmatched { keys }
+    }
+
+    #[test]
+    fn sequences() {
+        let mut map = ShortcutMap::new().with_leader(Key::Space);
+        map.bind("g g", 1, "Go to top").unwrap();
+        map.bind("G", 2, "Go to bottom").unwrap();
+        map.bind("<leader> f", 3, "Find").unwrap();
+
+        let now = Instant::now();
+        assert_eq!(map.handle_key(Key::Char('g'), now), []);
+        assert_eq!(map.continuations().count(), 1);
+        assert_eq!(map.handle_key(Key::Char('g'), now), [matched(1, 2)]);
+        assert_eq!(map.handle_key(Key::Char('G'), now), [matched(2, 1)]);
+        assert_eq!(map.handle_key(Key::Space, now), []);
+        assert_eq!(map.handle_key(Key::Char('f'), now), [matched(3, 2)]);
+        assert_eq!(map.handle_key(Key::Char('x'), now), [unmatched(1)]);
+
+        // A broken sequence gives back its keys, and restarts with the new key.
+        assert_eq!(map.handle_key(Key::Char('g'), now), []);
+        assert_eq!(
+            map.handle_key(Key::Char('G'), now),
+            [unmatched(1), matched(2, 1)]
+        );
+        assert_eq!(map.handle_key(Key::Char('g'), now), []);
+        assert_eq!(map.handle_key(Key::Char('x'), now), [unmatched(2)]);
+    }
+
+    #[test]
+    fn chords() {
+        let mut map = ShortcutMap::new();
+        map.bind("ctrl+k ctrl+c", 1, "Comment").unwrap();
+        map.bind("ctrl+k u", 2, "Uppercase").unwrap();
+
+        let now = Instant::now();
+        let ctrl = |ch| KeyChord::with_modifiers(Key::Char(ch), Modifiers::CTRL);
+        assert_eq!(map.handle_key(ctrl('k'), now), []);
+        assert_eq!(map.handle_key(ctrl('c'), now), [matched(1, 2)]);
+        assert_eq!(map.handle_key(ctrl('k'), now), []);
+        assert_eq!(map.handle_key(Key::Char('u'), now), [matched(2, 2)]);
+
+        // Without the modifier, the keys are different.
+        assert_eq!(map.handle_key(Key::Char('k'), now), [unmatched(1)]);
+
+        assert_eq!(ctrl('k').name(), "Ctrl+K");
+        assert_eq!(
+            KeyChord::from_key_press(Key::Char('G'), Modifiers::SHIFT),
+            KeyChord::new(Key::Char('G')),
+        );
+    }
+
+    #[test]
+    fn timeout() {
+        let mut map = ShortcutMap::new().with_timeout(Duration::from_millis(100));
+        map.bind("g g", 1, "Go to top").unwrap();
+        map.bind("g", 2, "Go").unwrap();
+
+        let now = Instant::now();
+        assert_eq!(map.handle_key(Key::Char('g'), now), []);
+        assert_eq!(map.expire(now + Duration::from_millis(50)), []);
+
+        // An exact match that was waiting for a longer sequence is matched once it
+        // times out.
+        let later = now + Duration::from_millis(200);
+        assert_eq!(map.handle_key(Key::Char('g'), later), [matched(2, 1)]);
+        assert!(map.is_pending());
+        assert_eq!(
+            map.expire(later + Duration::from_millis(100)),
+            [matched(2, 1)]
+        );
+        assert!(!map.is_pending());
+
+        // Breaking the sequence does the same.
+        assert_eq!(map.handle_key(Key::Char('g'), now), []);
+        assert_eq!(
+            map.handle_key(Key::Char('x'), now),
+            [matched(2, 1), unmatched(1)]
+        );
+    }
+
+    #[test]
+    fn parsing() {
+        assert_eq!(
+            KeySequence::parse("<leader> <up> a", Some(Key::Tab)),
+            Ok(KeySequence(vec![
+                Key::Tab.into(),
+                Key::ArrowUp.into(),
+                Key::Char('a').into(),
+            ])),
+        );
+        assert_eq!(
+            KeySequence::parse("ctrl+shift+<enter> alt++", None),
+            Ok(KeySequence(vec![
+                KeyChord::with_modifiers(
+                    Key::Enter,
+                    Modifiers {
+                        ctrl: true,
+                        shift: true,
+                        ..Modifiers::NONE
+                    }
+                ),
+                KeyChord::with_modifiers(Key::Char('+'), Modifiers::ALT),
+            ])),
+        );
+        assert!(KeySequence::parse("<leader>", None).is_err());
+        assert!(KeySequence::parse("<nonsense>", None).is_err());
+        assert!(KeySequence::parse("hyper+a", None).is_err());
+        assert!(KeySequence::parse("", None).is_err());
+    }
+}
@@ -0,0 +1,355 @@
+//! # Split Panes
+
+use crate::{
+    Aabb2D, Axis, ChildElement, CursorIcon, Element, ElementBuilder, EventPass, LayoutPass,
+    LengthRequest, MeasureContext, PointerButton, PointerEvent, RenderPass, Rgba, UpdatePass, Xy,
+    system,
+};
+
+
+
+/// Two children side by side (or one above the other), separated by a divider
+/// that the user can drag to resize them.
+pub struct SplitPane {
+    axis: Axis,
+    first: ChildElement,
+    second: ChildElement,
+    /// The share of the available length given to the first child, from `0.0`
+    /// to `1.0`.
+    ratio: f32,
+    min_lengths: [f32; 2],
+    divider_thickness: f32,
+    /// The key that the ratio is saved under, see
+    /// [`SplitPane::with_persisted_ratio`].
+    setting_key: Option<String>,
+    /// The size of the pane and the length of its first child as of the last
+    /// layout.
+    size: Xy<f32>,
+    first_length: f32,
+    /// Where along the divider it was grabbed, while it's being dragged.
+    grab_offset: Option<f32>,
+    hovered: bool,
+    moved: bool,
+}
+
+impl SplitPane {
+    /// Place the children side by side, with a vertical divider between them.
+    pub fn horizontal(first: impl Element + 'static, second: impl Element + 'static) -> Self {
+        Self::with_axis(Axis::Horizontal, first, second)
+    }
+
+    /// Place the first child above the second, with a horizontal divider
+    /// between them.
+    pub fn vertical(first: impl Element + 'static, second: impl Element + 'static) -> Self {
+        Self::with_axis(Axis::Vertical, first, second)
+    }
+
+    pub fn with_axis(
+        axis: Axis,
+        first: impl Element + 'static,
+        second: impl Element + 'static,
+    ) -> Self {
+        Self {
+            axis,
+            first: ElementBuilder::new(first).into_child(),
+            second: ElementBuilder::new(second).into_child(),
+            ratio: 0.5,
+            min_lengths: [0.0; 2],
+            divider_thickness: 6.0,
+            setting_key: None,
+            size: Xy::ZERO,
+            first_length: 0.0,
+            grab_offset: None,
+            hovered: false,
+            moved: false,
+        }
+    }
+
+    /// Set the initial share of the available length given to the first child.
+    /// Defaults to `0.5`.
+    pub fn with_ratio(mut self, ratio: f32) -> Self {
+        self.set_ratio(ratio);
+        self
+    }
+
+    /// Set the minimum lengths of the first and second children along the
+    /// split axis, which the divider can't be dragged past. When there isn't
+    /// enough room for both, the first child's minimum wins.
+    pub fn with_min_lengths(mut self, first: f32, second: f32) -> Self {
+        self.min_lengths = [first.max(0.0), second.max(0.0)];
+        self
+    }
+
+    /// Set the thickness of the divider. Defaults to `6.0`.
+    pub fn with_divider_thickness(mut self, thickness: f32) -> Self {
+        self.divider_thickness = thickness.max(0.0);
+        self
+    }
+
+    /// Restore the ratio from the application setting with the given key when
+    /// built, and save it there whenever the user finishes dragging the
+    /// divider.
+    ///
+    /// This requires the view to be hosted, see [`system::setting`].
+    pub fn with_persisted_ratio(mut self, key: impl Into<String>) -> Self {
+        self.setting_key = Some(key.into());
+        self
+    }
+
+    #[inline]
+    pub fn axis(&self) -> Axis {
+        self.axis
+    }
+
+    /// The share of the available length given to the first child, from `0.0`
+    /// to `1.0`.
+    #[inline]
+    pub fn ratio(&self) -> f32 {
+        self.ratio
+    }
+
+    pub fn set_ratio(&mut self, ratio: f32) {
+        if !ratio.is_nan() {
+            self.ratio = ratio.clamp(0.0, 1.0);
+        }
+    }
+
+    /// Whether the user is currently dragging the divider.
+    #[inline]
+    pub fn is_dragging(&self) -> bool {
+        self.grab_offset.is_some()
+    }
+
+    /// Returns `true` if the user has moved the divider since the last call.
+    pub fn take_moved(&mut self) -> bool {
+        std::mem::take(&mut self.moved)
+    }
+
+    /// The length of the first child when `available` is shared between both,
+    /// respecting their minimum lengths.
+    fn first_length(&self, available: f32) -> f32 {
+        let [first_min, second_min] = self.min_lengths;
+
+        (self.ratio * available)
+            .min(available - second_min)
+            .max(first_min)
+            .min(available)
+            .max(0.0)
+    }
+
+    fn available_length(&self, length: f32) -> f32 {
+        (length - self.divider_thickness).max(0.0)
+    }
+
+    /// The divider's bounds relative to the pane, in logical units.
+    fn divider_bounds(&self, size: Xy<f32>) -> Aabb2D {
+        Aabb2D::from_size_position(
+            self.axis.pack_xy(
+                self.divider_thickness,
+                size.value_for_axis(self.axis.cross()),
+            ),
+            self.axis.pack_xy(self.first_length, 0.0),
+        )
+    }
+}
+
+impl Element for SplitPane {
+    fn children_ids(&self) -> Vec<u64> {
+        vec![self.first.id(), self.second.id()]
+    }
+
+    fn update_children(&mut self, pass: &mut UpdatePass<'_>) {
+        pass.update_child(&mut self.first);
+        pass.update_child(&mut self.second);
+    }
+
+    fn on_build(&mut self, _pass: &mut UpdatePass<'_>) {
+        if let Some(key) = &self.setting_key
+            && let Some(ratio) = system::setting(key).and_then(|ratio| ratio.parse().ok())
+        {
+            self.set_ratio(ratio);
+        }
+    }
+
+    fn render(&mut self, pass: &mut RenderPass<'_>) {
+        let bounds = pass.bounds();
+        let zoom = pass.zoom();
+        let divider = self.divider_bounds(self.size);
+        let divider = Aabb2D::from_size_position(
+            Xy::new(divider.size().x * zoom, divider.size().y * zoom),
+            bounds.position() + Xy::new(divider.min.x * zoom, divider.min.y * zoom),
+        );
+
+        let color = if self.grab_offset.is_some() || self.hovered {
+            pass.color("accent")
+        } else {
+            pass.color("border")
+        };
+        pass.fill_quad(divider, color, 0.0, Rgba::NONE);
+    }
+
+    fn layout(&mut self, pass: &mut LayoutPass<'_>) {
+        let size = pass.size;
+        self.size = size;
+        let cross_length = size.value_for_axis(self.axis.cross());
+        let available = self.available_length(size.value_for_axis(self.axis));
+        self.first_length = self.first_length(available);
+        let second_length = available - self.first_length;
+
+        pass.do_layout(
+            &mut self.first,
+            self.axis.pack_xy(self.first_length, cross_length),
+        );
+        pass.place_child(&mut self.first, Xy::ZERO);
+
+        pass.do_layout(
+            &mut self.second,
+            self.axis.pack_xy(second_length, cross_length),
+        );
+        pass.place_child(
+            &mut self.second,
+            self.axis
+                .pack_xy(self.first_length + self.divider_thickness, 0.0),
+        );
+
+        pass.clear_baseline_offset();
+    }
+
+    fn measure(
+        &mut self,
+        context: &mut MeasureContext<'_>,
+        axis: Axis,
+        length_request: LengthRequest,
+        cross_length: Option<f32>,
+    ) -> f32 {
+        if axis == self.axis
+            && let LengthRequest::FitContent(space) = length_request
+        {
+            return space;
+        }
+
+        let fallback_length = length_request.into();
+        let first_length =
+            context.resolve_length(self.first.id(), axis, fallback_length, cross_length);
+        let second_length =
+            context.resolve_length(self.second.id(), axis, fallback_length, cross_length);
+
+        if axis == self.axis {
+            let [first_min, second_min] = self.min_lengths;
+            first_length.max(first_min) + self.divider_thickness + second_length.max(second_min)
+        } else {
+            first_length.max(second_length)
+        }
+    }
+
+    fn cursor_icon(&self) -> CursorIcon {
+        match self.axis {
+            Axis::Horizontal => CursorIcon::SplitH,
+            Axis::Vertical => CursorIcon::SplitV,
+        }
+    }
+
+    fn on_hover(&mut self, pass: &mut EventPass<'_>, hovered: bool) {
+        self.hovered = hovered;
+        pass.request_render();
+    }
+
+    fn on_pointer_event(&mut self, pass: &mut EventPass<'_>, event: &PointerEvent) {
+        match event {
+            PointerEvent::Down {
+                button: PointerButton::Primary,
+                position,
+                ..
+            } => {
+                let position = pass.local_position(*position);
+                if !self.divider_bounds(self.size).contains(position) {
+                    return;
+                }
+
+                pass.capture_pointer();
+                self.grab_offset = Some(position.value_for_axis(self.axis) - self.first_length);
+                pass.request_render();
+                pass.set_handled();
+            }
+            PointerEvent::Move { position, .. } => {
+                let Some(grab_offset) = self.grab_offset else {
+                    return;
+                };
+                let position = pass.local_position(*position);
+                let available = self.available_length(self.size.value_for_axis(self.axis));
+                if available <= 0.0 {
+                    return;
+                }
+
+                let previous_ratio = self.ratio;
+                self.set_ratio((position.value_for_axis(self.axis) - grab_offset) / available);
+                // NOTE: Store the ratio that's actually shown, so that dragging past a
+                //       minimum length doesn't have to be undone before it moves again.
+                self.ratio = self.first_length(available) / available;
+                if self.ratio != previous_ratio {
+                    self.moved = true;
+                    pass.request_layout();
+                }
+            }
+            PointerEvent::Up {
+                button: PointerButton::Primary,
+                ..
+            } => {
+                if self.grab_offset.take().is_none() {
+                    return;
+                }
+                if let Some(key) = &self.setting_key {
+                    system::set_setting(key, &self.ratio.to_string());
+                }
+                pass.request_render();
+            }
+            _ => {}
+        }
+    }
+
+    fn save_state(&self) -> Option<Vec<u8>> {
+        Some(self.ratio.to_le_bytes().to_vec())
+    }
+
+    fn restore_state(&mut self, state: &[u8]) {
+        if let Ok(ratio) = state.try_into() {
+            self.set_ratio(f32::from_le_bytes(ratio));
+        }
+    }
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Empty;
+
+    impl Element for Empty {
+        fn measure(
+            &mut self,
+            _context: &mut MeasureContext<'_>,
+            _axis: Axis,
+            _length_request: LengthRequest,
+            _cross_length: Option<f32>,
+        ) -> f32 {
+            0.0
+        }
+    }
+
+    #[test]
+    fn first_length_respects_min_lengths() {
+        let pane = SplitPane::horizontal(Empty, Empty)
+            .with_ratio(0.1)
+            .with_min_lengths(50.0, 100.0);
+        assert_eq!(pane.first_length(400.0), 50.0);
+
+        let pane = pane.with_ratio(0.9);
+        assert_eq!(pane.first_length(400.0), 300.0);
+
+        // The first child's minimum wins when there isn't room for both.
+        assert_eq!(pane.first_length(120.0), 50.0);
+        assert_eq!(pane.first_length(30.0), 30.0);
+    }
+}
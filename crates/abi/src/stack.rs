@@ -0,0 +1,190 @@
+//! # Stack Layout
+
+use crate::{
+    Axis, ChildElement, CrossAlignment, Element, ElementBuilder, LayoutPass, Length, LengthRequest,
+    MeasureContext, UpdatePass, Xy,
+};
+
+
+
+/// Lays out every child at the same origin, with later children drawn on top of
+/// (and receiving pointer events before) earlier ones.
+///
+/// Useful for overlays, such as a badge on an icon or a spinner over content.
+pub struct Stack {
+    alignment: StackAlignment,
+    children: Vec<StackChild>,
+}
+
+struct StackChild {
+    element: ChildElement,
+    alignment: Option<StackAlignment>,
+    offset: Xy<f32>,
+}
+
+impl Default for Stack {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Stack {
+    pub fn new() -> Self {
+        Self {
+            alignment: StackAlignment::TOP_LEFT,
+            children: Vec::new(),
+        }
+    }
+
+    /// Add a child on top of the existing ones.
+    pub fn with(mut self, child: impl Element + 'static, params: impl Into<StackParams>) -> Self {
+        let params = params.into();
+        self.children.push(StackChild {
+            element: ElementBuilder::new(child).into_child(),
+            alignment: params.alignment,
+            offset: params.offset,
+        });
+        self
+    }
+
+    /// Set the alignment of children that don't specify their own. Defaults to
+    /// [`StackAlignment::TOP_LEFT`].
+    pub fn with_alignment(mut self, alignment: StackAlignment) -> Self {
+        self.alignment = alignment;
+        self
+    }
+}
+
+impl Element for Stack {
+    fn children_ids(&self) -> Vec<u64> {
+        self.children
+            .iter()
+            .map(|child| child.element.id())
+            .collect()
+    }
+
+    fn update_children(&mut self, pass: &mut UpdatePass<'_>) {
+        for child in self.children.iter_mut() {
+            pass.update_child(&mut child.element);
+        }
+    }
+
+    fn layout(&mut self, pass: &mut LayoutPass<'_>) {
+        let size = pass.size;
+
+        for child in &mut self.children {
+            let alignment = child.alignment.unwrap_or(self.alignment);
+            let auto_length = |alignment: CrossAlignment, space: f32| match alignment {
+                CrossAlignment::Stretch => Length::Exact(space),
+                _ => Length::FitContent(space),
+            };
+            let auto_size = Xy::new(
+                auto_length(alignment.x, size.x),
+                auto_length(alignment.y, size.y),
+            );
+
+            let child_size = pass.resolve_size(child.element.id(), auto_size);
+            pass.do_layout(&mut child.element, child_size);
+
+            let child_origin = Xy::new(
+                alignment.x.offset(size.x - child_size.x),
+                alignment.y.offset(size.y - child_size.y),
+            );
+            pass.place_child(&mut child.element, child_origin + child.offset);
+        }
+
+        // NOTE: The bottommost child is treated as the content, and anything above
+        //       it as an overlay, so its baseline is used.
+        match self.children.first() {
+            Some(child) => {
+                let child_node = pass.expect_child(child.element.id());
+                let child_bounds = child_node.element.state.layout_bounds;
+                let child_baseline = child_node.element.state.layout_baseline_offset;
+                let child_first_baseline = child_node.element.state.layout_first_baseline_offset;
+                let bottom_gap = size.y - child_bounds.max.y;
+                pass.clear_baseline_offset();
+                pass.set_baseline_offset(child_baseline + bottom_gap);
+                if let Some(first_baseline) = child_first_baseline {
+                    pass.set_first_baseline_offset(first_baseline + bottom_gap);
+                }
+            }
+            None => pass.clear_baseline_offset(),
+        }
+    }
+
+    fn measure(
+        &mut self,
+        context: &mut MeasureContext<'_>,
+        axis: Axis,
+        length_request: LengthRequest,
+        cross_length: Option<f32>,
+    ) -> f32 {
+        let fallback_length = length_request.into();
+
+        let mut length: f32 = 0.0;
+        for child in &mut self.children {
+            let child_length =
+                context.resolve_length(child.element.id(), axis, fallback_length, cross_length);
+            let offset = child.offset.value_for_axis(axis).max(0.0);
+            length = length.max(child_length + offset);
+        }
+
+        length
+    }
+}
+
+
+
+/// Where a child is placed within a [`Stack`] on each axis.
+///
+/// [`CrossAlignment::Stretch`] fills the stack's length on that axis, and the
+/// baseline alignments are treated as [`CrossAlignment::Center`].
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct StackAlignment {
+    pub x: CrossAlignment,
+    pub y: CrossAlignment,
+}
+
+impl StackAlignment {
+    pub const TOP_LEFT: Self = Self::new(CrossAlignment::Start, CrossAlignment::Start);
+    pub const TOP_RIGHT: Self = Self::new(CrossAlignment::End, CrossAlignment::Start);
+    pub const CENTER: Self = Self::new(CrossAlignment::Center, CrossAlignment::Center);
+    pub const BOTTOM_LEFT: Self = Self::new(CrossAlignment::Start, CrossAlignment::End);
+    pub const BOTTOM_RIGHT: Self = Self::new(CrossAlignment::End, CrossAlignment::End);
+    pub const FILL: Self = Self::new(CrossAlignment::Stretch, CrossAlignment::Stretch);
+
+    pub const fn new(x: CrossAlignment, y: CrossAlignment) -> Self {
+        Self { x, y }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct StackParams {
+    /// Overrides the [`Stack`]'s alignment for this child.
+    pub alignment: Option<StackAlignment>,
+    /// Moves the child after it has been aligned, such as to nudge a badge past
+    /// its icon's corner.
+    pub offset: Xy<f32>,
+}
+
+impl StackParams {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_alignment(mut self, alignment: StackAlignment) -> Self {
+        self.alignment = Some(alignment);
+        self
+    }
+
+    pub fn with_offset(mut self, offset: Xy<f32>) -> Self {
+        self.offset = offset;
+        self
+    }
+}
+
+impl From<StackAlignment> for StackParams {
+    fn from(alignment: StackAlignment) -> Self {
+        Self::new().with_alignment(alignment)
+    }
+}
@@ -0,0 +1,120 @@
+//! # System Services
+//!
+//! Functions provided by the host (the shell) to applications. Some of these
+//! require the calling application to declare a
+//! [`Capability`](crate::Capability) in its [`Manifest`](crate::Manifest).
+
+
+
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+#[repr(u8)]
+pub enum OpenUrlError {
+    /// The calling application doesn't have the
+    /// [`Capability::OpenUrl`](crate::Capability::OpenUrl) capability.
+    NotPermitted,
+    /// No application has been registered to handle URLs.
+    NoHandler,
+    /// The URL is empty or has no scheme.
+    InvalidUrl,
+}
+
+impl OpenUrlError {
+    pub const fn description(&self) -> &'static str {
+        match self {
+            Self::NotPermitted => "missing the open-url capability",
+            Self::NoHandler => "no URL handler registered",
+            Self::InvalidUrl => "invalid URL",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+#[repr(u8)]
+pub enum OpenViewError {
+    /// The calling application doesn't declare a view with the given name.
+    UnknownView,
+    /// The host can only show one view per application.
+    NotSupported,
+}
+
+impl OpenViewError {
+    pub const fn description(&self) -> &'static str {
+        match self {
+            Self::UnknownView => "no view with that name",
+            Self::NotSupported => "the host doesn't support multiple views",
+        }
+    }
+}
+
+/// Ask the host to open the given URL in the registered browser application.
+///
+/// Requires [`Capability::OpenUrl`](crate::Capability::OpenUrl).
+pub fn open_url(url: &str) -> Result<(), OpenUrlError> {
+    if !is_valid_url(url) {
+        return Err(OpenUrlError::InvalidUrl);
+    }
+
+    unsafe { __system_open_url(url) }
+}
+
+/// Check that the URL is non-empty and starts with a scheme (e.g. `https:`).
+pub fn is_valid_url(url: &str) -> bool {
+    let Some((scheme, rest)) = url.split_once(':') else {
+        return false;
+    };
+
+    !rest.is_empty()
+        && scheme.starts_with(|ch: char| ch.is_ascii_alphabetic())
+        && scheme
+            .chars()
+            .all(|ch| ch.is_ascii_alphanumeric() || matches!(ch, '+' | '-' | '.'))
+}
+
+/// Check that the setting key is non-empty and has no `=` or line breaks, which
+/// the host's settings file can't hold.
+pub fn is_valid_setting_key(key: &str) -> bool {
+    !key.is_empty() && !key.contains(['=', '\n', '\r'])
+}
+
+/// Get the value of one of the calling application's settings, which the host
+/// persists between runs. This is always `None` for invalid keys (see
+/// [`is_valid_setting_key`]).
+pub fn setting(key: &str) -> Option<String> {
+    if !is_valid_setting_key(key) {
+        return None;
+    }
+
+    unsafe { __system_get_setting(key) }
+}
+
+/// Set one of the calling application's settings, see [`setting`]. Invalid
+/// keys (see [`is_valid_setting_key`]) are ignored, and line breaks in the
+/// value are replaced with spaces.
+pub fn set_setting(key: &str, value: &str) {
+    if !is_valid_setting_key(key) {
+        return;
+    }
+
+    unsafe { __system_set_setting(key, value) }
+}
+
+/// Ask the host to open one of the calling application's views (see
+/// [`Manifest::views`](crate::Manifest::views)), or to bring it to the front if
+/// it's already open.
+pub fn open_view(name: &str) -> Result<(), OpenViewError> {
+    unsafe { __system_open_view(name) }
+}
+
+/// Ask the host to close one of the calling application's views, see
+/// [`open_view`].
+pub fn close_view(name: &str) {
+    unsafe { __system_close_view(name) }
+}
+
+unsafe extern "Rust" {
+    fn __system_open_url(url: &str) -> Result<(), OpenUrlError>;
+    fn __system_get_setting(key: &str) -> Option<String>;
+    fn __system_set_setting(key: &str, value: &str);
+    fn __system_open_view(name: &str) -> Result<(), OpenViewError>;
+    fn __system_close_view(name: &str);
+}
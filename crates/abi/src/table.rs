@@ -0,0 +1,608 @@
+//! # Tables
+//!
+//! See [`Table`] for more information.
+
+use std::borrow::Cow;
+
+use crate::{
+    Aabb2D, Axis, CursorIcon, Element, EventPass, FontFamily, FontStyle, Fonts, Key, KeyboardEvent,
+    LayoutPass, LengthRequest, LineHeight, MeasureContext, PointerButton, PointerEvent, RenderPass,
+    Rgba, TextAlignment, TextWrapMode, Xy,
+};
+
+
+
+/// How far from a column's edge its width can be grabbed.
+const RESIZE_HANDLE_WIDTH: f32 = 4.0;
+const SORT_ASCENDING_LABEL: &str = "▲";
+const SORT_DESCENDING_LABEL: &str = "▼";
+
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+pub enum SortDirection {
+    #[default]
+    Ascending,
+    Descending,
+}
+
+impl SortDirection {
+    pub const fn reversed(self) -> Self {
+        match self {
+            Self::Ascending => Self::Descending,
+            Self::Descending => Self::Ascending,
+        }
+    }
+}
+
+/// The rows shown by a [`Table`].
+///
+/// Cells are only requested for the rows that are visible, so sources can
+/// produce them on demand for large data sets.
+pub trait TableSource {
+    fn row_count(&self) -> usize;
+
+    fn cell(&self, row: usize, column: usize) -> Cow<'_, str>;
+
+    /// Called when the user clicks the header of a sortable column.
+    ///
+    /// Defaults to doing nothing.
+    #[allow(unused)]
+    fn sort(&mut self, column: usize, direction: SortDirection) {}
+}
+
+/// Rows of cells, sorted by comparing the text of the chosen column.
+impl TableSource for Vec<Vec<String>> {
+    fn row_count(&self) -> usize {
+        self.len()
+    }
+
+    fn cell(&self, row: usize, column: usize) -> Cow<'_, str> {
+        self.get(row)
+            .and_then(|row| row.get(column))
+            .map_or(Cow::Borrowed(""), |cell| Cow::Borrowed(cell.as_str()))
+    }
+
+    fn sort(&mut self, column: usize, direction: SortDirection) {
+        self.sort_by(|a, b| {
+            let ordering = a.get(column).cmp(&b.get(column));
+            match direction {
+                SortDirection::Ascending => ordering,
+                SortDirection::Descending => ordering.reverse(),
+            }
+        });
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct TableColumn {
+    pub title: String,
+    pub width: f32,
+    pub min_width: f32,
+    pub sortable: bool,
+}
+
+impl TableColumn {
+    pub fn new(title: impl Into<String>, width: f32) -> Self {
+        Self {
+            title: title.into(),
+            width,
+            min_width: 24.0,
+            sortable: false,
+        }
+    }
+
+    /// Set the width that the user can't resize the column below. Defaults to
+    /// `24.0`.
+    pub fn with_min_width(mut self, min_width: f32) -> Self {
+        self.min_width = min_width;
+        self.width = self.width.max(min_width);
+        self
+    }
+
+    /// Let the user sort the table by this column, see [`TableSource::sort`].
+    pub fn sortable(mut self) -> Self {
+        self.sortable = true;
+        self
+    }
+}
+
+/// A grid of rows under a row of column headers.
+///
+/// Columns can be resized by dragging the edges of their headers, and sorted by
+/// clicking them. The body scrolls on its own beneath the headers, and only the
+/// visible rows are rendered.
+///
+/// While focused, the up and down arrow keys (and page up and page down) move
+/// the selected row.
+pub struct Table<S: TableSource> {
+    source: S,
+    columns: Vec<TableColumn>,
+    sort: Option<(usize, SortDirection)>,
+    selected: Option<usize>,
+    selection_changed: bool,
+    focused: bool,
+    font_size: f32,
+    row_height: f32,
+    size: Xy<f32>,
+    scroll_offset: f32,
+    /// The column being resized, where the pointer grabbed it, and its width
+    /// at the time.
+    resizing: Option<(usize, f32, f32)>,
+    /// The column whose edge is hovered.
+    hovered_edge: Option<usize>,
+}
+
+impl<S: TableSource> Table<S> {
+    pub fn new(source: S, columns: impl IntoIterator<Item = TableColumn>) -> Self {
+        Self {
+            source,
+            columns: columns.into_iter().collect(),
+            sort: None,
+            selected: None,
+            selection_changed: false,
+            focused: false,
+            font_size: 14.0,
+            row_height: 0.0,
+            size: Xy::ZERO,
+            scroll_offset: 0.0,
+            resizing: None,
+            hovered_edge: None,
+        }
+    }
+
+    pub fn with_font_size(mut self, font_size: f32) -> Self {
+        self.font_size = font_size;
+        self
+    }
+
+    #[inline]
+    pub fn source(&self) -> &S {
+        &self.source
+    }
+
+    /// Access the table's rows, such as to add more. The selection is cleared
+    /// if it's no longer in range.
+    pub fn source_mut(&mut self) -> &mut S {
+        if self
+            .selected
+            .is_some_and(|row| row >= self.source.row_count())
+        {
+            self.selected = None;
+        }
+        &mut self.source
+    }
+
+    #[inline]
+    pub fn columns(&self) -> &[TableColumn] {
+        &self.columns
+    }
+
+    /// The column the table is sorted by, and in which direction.
+    #[inline]
+    pub fn sort(&self) -> Option<(usize, SortDirection)> {
+        self.sort
+    }
+
+    #[inline]
+    pub fn selected_row(&self) -> Option<usize> {
+        self.selected
+    }
+
+    pub fn set_selected_row(&mut self, row: Option<usize>) {
+        self.selected = row.filter(|row| *row < self.source.row_count());
+    }
+
+    /// Returns `true` if the user has changed the selection since the last
+    /// call.
+    pub fn take_selection_changed(&mut self) -> bool {
+        std::mem::take(&mut self.selection_changed)
+    }
+
+    fn header_height(&self) -> f32 {
+        self.row_height
+    }
+
+    fn body_height(&self) -> f32 {
+        (self.size.y - self.header_height()).max(0.0)
+    }
+
+    fn max_scroll_offset(&self) -> f32 {
+        (self.source.row_count() as f32 * self.row_height - self.body_height()).max(0.0)
+    }
+
+    fn scroll_to(&mut self, offset: f32) -> bool {
+        let offset = offset.clamp(0.0, self.max_scroll_offset());
+        if (offset - self.scroll_offset).abs() > 1e-6 {
+            self.scroll_offset = offset;
+            true
+        } else {
+            false
+        }
+    }
+
+    /// The rows that are at least partly visible.
+    fn visible_rows(&self) -> std::ops::Range<usize> {
+        if self.row_height <= 0.0 {
+            return 0..0;
+        }
+        let first = (self.scroll_offset / self.row_height).floor() as usize;
+        let last = ((self.scroll_offset + self.body_height()) / self.row_height).ceil() as usize;
+
+        first.min(self.source.row_count())..last.min(self.source.row_count())
+    }
+
+    /// The column whose right edge is under the given x coordinate.
+    fn edge_at(&self, x: f32) -> Option<usize> {
+        let mut edge = 0.0;
+        self.columns.iter().position(|column| {
+            edge += column.width;
+            (x - edge).abs() <= RESIZE_HANDLE_WIDTH
+        })
+    }
+
+    fn column_at(&self, x: f32) -> Option<usize> {
+        let mut start = 0.0;
+        self.columns.iter().position(|column| {
+            let end = start + column.width;
+            let found = (start..end).contains(&x);
+            start = end;
+            found
+        })
+    }
+
+    fn select(&mut self, row: usize) {
+        if self.selected != Some(row) {
+            self.selected = Some(row);
+            self.selection_changed = true;
+        }
+        // Keep the selected row visible.
+        let top = row as f32 * self.row_height;
+        let bottom = top + self.row_height;
+        if top < self.scroll_offset {
+            self.scroll_to(top);
+        } else if bottom > self.scroll_offset + self.body_height() {
+            self.scroll_to(bottom - self.body_height());
+        }
+    }
+
+    fn toggle_sort(&mut self, column: usize) {
+        let direction = match self.sort {
+            Some((sorted, direction)) if sorted == column => direction.reversed(),
+            _ => SortDirection::Ascending,
+        };
+        self.sort = Some((column, direction));
+        self.source.sort(column, direction);
+    }
+
+    fn measure_text(&self, id: u64, fonts: &mut dyn Fonts, text: &str) -> Xy<f32> {
+        fonts.measure_text(
+            id,
+            text,
+            &[],
+            None,
+            self.font_size,
+            LineHeight::Relative(1.0),
+            FontFamily::Proportional,
+            FontStyle::Normal,
+            TextAlignment::Start,
+            TextWrapMode::NoWrap,
+        )
+    }
+}
+
+impl<S: TableSource + 'static> Element for Table<S> {
+    fn accepts_keyboard_events(&self) -> bool {
+        true
+    }
+
+    fn accepts_focus_events(&self) -> bool {
+        true
+    }
+
+    fn render(&mut self, pass: &mut RenderPass<'_>) {
+        let bounds = pass.bounds();
+        let zoom = pass.zoom();
+        let to_physical = |area: Aabb2D| {
+            Aabb2D::from_size_position(
+                Xy::new(area.size().x * zoom, area.size().y * zoom),
+                bounds.position() + Xy::new(area.min.x * zoom, area.min.y * zoom),
+            )
+        };
+        let width = self.size.x;
+        let header_height = self.header_height();
+        let padding = pass.metric("spacing");
+
+        // NOTE: Rows that don't fit entirely below the headers are skipped, so that
+        //       nothing is drawn outside of the table's bounds.
+        for row in self.visible_rows() {
+            let top = header_height + row as f32 * self.row_height - self.scroll_offset;
+            if top < header_height - 0.5 || top + self.row_height > self.size.y + 0.5 {
+                continue;
+            }
+            if self.selected == Some(row) {
+                pass.fill_quad(
+                    to_physical(Aabb2D::new(0.0, top, width, top + self.row_height)),
+                    pass.color("surface.raised"),
+                    if self.focused { 1.0 } else { 0.0 },
+                    pass.color("accent"),
+                );
+            }
+
+            let mut left = 0.0;
+            for (index, column) in self.columns.iter().enumerate() {
+                pass.fill_text(
+                    self.source.cell(row, index),
+                    to_physical(Aabb2D::new(
+                        left + padding,
+                        top + padding,
+                        (left + column.width - padding).max(left + padding),
+                        top + self.row_height - padding,
+                    )),
+                    pass.color("text"),
+                    self.font_size,
+                );
+                left += column.width;
+            }
+        }
+
+        pass.fill_quad(
+            to_physical(Aabb2D::new(0.0, 0.0, width, header_height)),
+            pass.color("surface.sunken"),
+            0.0,
+            Rgba::NONE,
+        );
+        let mut left = 0.0;
+        for (index, column) in self.columns.iter().enumerate() {
+            let right = left + column.width;
+            let sort_label = match self.sort {
+                Some((sorted, SortDirection::Ascending)) if sorted == index => {
+                    Some(SORT_ASCENDING_LABEL)
+                }
+                Some((sorted, SortDirection::Descending)) if sorted == index => {
+                    Some(SORT_DESCENDING_LABEL)
+                }
+                _ => None,
+            };
+            let title_bounds = Aabb2D::new(
+                left + padding,
+                padding,
+                (right - padding).max(left + padding),
+                header_height - padding,
+            );
+            pass.fill_text(
+                &column.title,
+                to_physical(title_bounds),
+                pass.color("text.muted"),
+                self.font_size,
+            );
+            if let Some(sort_label) = sort_label {
+                let label_left = (right - padding - self.font_size).max(title_bounds.min.x);
+                pass.fill_text(
+                    sort_label,
+                    to_physical(Aabb2D::new(
+                        label_left,
+                        title_bounds.min.y,
+                        title_bounds.max.x,
+                        title_bounds.max.y,
+                    )),
+                    pass.color("text.muted"),
+                    self.font_size,
+                );
+            }
+
+            let edge_color = if self.hovered_edge == Some(index)
+                || self
+                    .resizing
+                    .is_some_and(|(resizing, ..)| resizing == index)
+            {
+                pass.color("accent")
+            } else {
+                pass.color("border")
+            };
+            pass.fill_quad(
+                to_physical(Aabb2D::new(right - 1.0, 0.0, right, header_height)),
+                edge_color,
+                0.0,
+                Rgba::NONE,
+            );
+            left = right;
+        }
+    }
+
+    fn measure(
+        &mut self,
+        context: &mut MeasureContext<'_>,
+        axis: Axis,
+        length_request: LengthRequest,
+        _cross_length: Option<f32>,
+    ) -> f32 {
+        let id = context.id();
+        let padding = context.metric("spacing");
+        self.row_height = self.measure_text(id, context.fonts_mut(), "M").y + padding * 2.0;
+
+        let (min_content, max_content) = match axis {
+            Axis::Horizontal => {
+                let width = self.columns.iter().map(|column| column.width).sum();
+                (width, width)
+            }
+            Axis::Vertical => (
+                self.header_height() + self.row_height,
+                self.header_height() + self.source.row_count() as f32 * self.row_height,
+            ),
+        };
+
+        match length_request {
+            LengthRequest::MinContent => min_content,
+            LengthRequest::MaxContent => max_content,
+            LengthRequest::FitContent(space) => max_content.min(space).max(min_content),
+        }
+    }
+
+    fn layout(&mut self, pass: &mut LayoutPass<'_>) {
+        self.size = pass.size;
+        self.scroll_to(self.scroll_offset);
+    }
+
+    fn cursor_icon(&self) -> CursorIcon {
+        if self.resizing.is_some() || self.hovered_edge.is_some() {
+            CursorIcon::SplitH
+        } else {
+            CursorIcon::Default
+        }
+    }
+
+    fn on_focus(&mut self, pass: &mut EventPass<'_>, focused: bool) {
+        self.focused = focused;
+        pass.request_render();
+    }
+
+    fn on_hover(&mut self, pass: &mut EventPass<'_>, hovered: bool) {
+        if !hovered && self.hovered_edge.take().is_some() {
+            pass.request_render();
+        }
+    }
+
+    fn on_pointer_event(&mut self, pass: &mut EventPass<'_>, event: &PointerEvent) {
+        match event {
+            PointerEvent::Down {
+                button: PointerButton::Primary,
+                position,
+                ..
+            } => {
+                let position = pass.local_position(*position);
+                if position.y < self.header_height() {
+                    if let Some(column) = self.edge_at(position.x) {
+                        self.resizing = Some((column, position.x, self.columns[column].width));
+                        pass.capture_pointer();
+                    } else if let Some(column) = self.column_at(position.x)
+                        && self.columns[column].sortable
+                    {
+                        self.toggle_sort(column);
+                    }
+                } else if self.row_height > 0.0 {
+                    let row = ((position.y - self.header_height() + self.scroll_offset)
+                        / self.row_height) as usize;
+                    if row < self.source.row_count() {
+                        self.select(row);
+                    }
+                    pass.request_focus();
+                }
+                pass.request_render();
+                pass.set_handled();
+            }
+            PointerEvent::Move { position, .. } => {
+                let position = pass.local_position(*position);
+                if let Some((column, grab_x, start_width)) = self.resizing {
+                    let column = &mut self.columns[column];
+                    let width = (start_width + position.x - grab_x).max(column.min_width);
+                    if width != column.width {
+                        column.width = width;
+                        pass.request_layout();
+                        pass.request_render();
+                    }
+                    return;
+                }
+
+                let hovered_edge = (position.y < self.header_height())
+                    .then(|| self.edge_at(position.x))
+                    .flatten();
+                if hovered_edge != self.hovered_edge {
+                    self.hovered_edge = hovered_edge;
+                    pass.request_render();
+                }
+            }
+            PointerEvent::Up {
+                button: PointerButton::Primary,
+                ..
+            } => {
+                if self.resizing.take().is_some() {
+                    pass.request_render();
+                }
+            }
+            PointerEvent::Scroll { delta, .. } => {
+                let delta = delta.to_pixels(Xy::new(self.row_height * 3.0, self.row_height * 3.0));
+                if self.scroll_to(self.scroll_offset - delta.y) {
+                    pass.request_render();
+                    pass.set_handled();
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn on_keyboard_event(&mut self, pass: &mut EventPass<'_>, event: &KeyboardEvent) {
+        let KeyboardEvent::Down { key, .. } = event else {
+            return;
+        };
+        let row_count = self.source.row_count();
+        if row_count == 0 || !self.focused {
+            return;
+        }
+        let page = ((self.body_height() / self.row_height.max(1.0)) as usize).max(1);
+        let row = match (key, self.selected) {
+            (Key::ArrowUp | Key::ArrowDown | Key::PageUp | Key::PageDown, None) => 0,
+            (Key::ArrowUp, Some(row)) => row.saturating_sub(1),
+            (Key::ArrowDown, Some(row)) => row + 1,
+            (Key::PageUp, Some(row)) => row.saturating_sub(page),
+            (Key::PageDown, Some(row)) => row + page,
+            _ => return,
+        };
+
+        self.select(row.min(row_count - 1));
+        pass.request_render();
+        pass.set_handled();
+    }
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn table() -> Table<Vec<Vec<String>>> {
+        let rows = (0..100)
+            .map(|row| vec![format!("{row:03}"), format!("{}", 99 - row)])
+            .collect::<Vec<_>>();
+        let mut table = Table::new(
+            rows,
+            [
+                TableColumn::new("A", 50.0).sortable(),
+                TableColumn::new("B", 80.0),
+            ],
+        );
+        table.row_height = 10.0;
+        table.size = Xy::new(130.0, 60.0);
+
+        table
+    }
+
+    #[test]
+    fn only_visible_rows_are_rendered() {
+        let mut table = table();
+        assert_eq!(table.visible_rows(), 0..5);
+
+        table.scroll_to(25.0);
+        assert_eq!(table.visible_rows(), 2..8);
+        table.scroll_to(10_000.0);
+        assert_eq!(table.scroll_offset, 950.0);
+        assert_eq!(table.visible_rows(), 95..100);
+
+        // Selecting a row scrolls it into view.
+        table.select(10);
+        assert_eq!(table.scroll_offset, 100.0);
+    }
+
+    #[test]
+    fn columns_are_hit_tested_and_sorted() {
+        let mut table = table();
+        assert_eq!(table.column_at(10.0), Some(0));
+        assert_eq!(table.column_at(60.0), Some(1));
+        assert_eq!(table.edge_at(52.0), Some(0));
+        assert_eq!(table.edge_at(60.0), None);
+
+        table.toggle_sort(0);
+        table.toggle_sort(0);
+        assert_eq!(table.sort(), Some((0, SortDirection::Descending)));
+        assert_eq!(table.source().cell(0, 0), "099");
+    }
+}
@@ -0,0 +1,465 @@
+//! # Tabs
+
+use crate::{
+    Aabb2D, Axis, ChildElement, Element, ElementBuilder, EventPass, FontFamily, FontStyle, Fonts,
+    Key, KeyboardEvent, LayoutPass, LengthRequest, LineHeight, MeasureContext, PointerButton,
+    PointerEvent, RenderPass, Rgba, TextAlignment, TextWrapMode, UpdatePass, Xy,
+};
+
+
+
+const CLOSE_LABEL: &str = "×";
+
+/// A strip of tab headers above a content area that shows the active tab.
+///
+/// Each tab's content is built the first time it's shown, and tabs that aren't
+/// shown are kept in the tree without being laid out or rendered.
+///
+/// Tabs can be switched by clicking their headers, with the arrow keys while
+/// the strip is focused, or with `Ctrl+Tab` and `Ctrl+Shift+Tab` from anywhere
+/// inside.
+pub struct Tabs {
+    tabs: Vec<Tab>,
+    active: usize,
+    font_size: f32,
+    closable: bool,
+    /// Tabs closed by the user since the last [`Tabs::take_closed`].
+    closed: Vec<String>,
+    /// The content of closed tabs, which is removed during the next update.
+    removed: Vec<ChildElement>,
+    focused: bool,
+    header_widths: Vec<f32>,
+    header_height: f32,
+    close_width: f32,
+    /// The theme's `"spacing.large"` as of the last measurement, which pads the
+    /// headers.
+    header_padding: f32,
+}
+
+struct Tab {
+    title: String,
+    build: Option<Box<dyn FnOnce() -> ElementBuilder>>,
+    content: Option<ChildElement>,
+}
+
+impl Default for Tabs {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Tabs {
+    pub fn new() -> Self {
+        Self {
+            tabs: Vec::new(),
+            active: 0,
+            font_size: 14.0,
+            closable: false,
+            closed: Vec::new(),
+            removed: Vec::new(),
+            focused: false,
+            header_widths: Vec::new(),
+            header_height: 0.0,
+            close_width: 0.0,
+            header_padding: 0.0,
+        }
+    }
+
+    /// Add a tab whose content is built by `build` when it's first shown.
+    pub fn with_tab<E: Element + 'static>(
+        mut self,
+        title: impl Into<String>,
+        build: impl FnOnce() -> E + 'static,
+    ) -> Self {
+        self.tabs.push(Tab {
+            title: title.into(),
+            build: Some(Box::new(move || ElementBuilder::new(build()))),
+            content: None,
+        });
+        self
+    }
+
+    pub fn with_font_size(mut self, font_size: f32) -> Self {
+        self.font_size = font_size;
+        self
+    }
+
+    /// Show a close button on each tab's header, see [`Tabs::take_closed`].
+    pub fn with_closable(mut self, closable: bool) -> Self {
+        self.closable = closable;
+        self
+    }
+
+    /// Set the initially active tab.
+    pub fn with_active(mut self, index: usize) -> Self {
+        self.active = index.min(self.tabs.len().saturating_sub(1));
+        self
+    }
+
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.tabs.len()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.tabs.is_empty()
+    }
+
+    /// The index of the active tab.
+    #[inline]
+    pub fn active(&self) -> usize {
+        self.active
+    }
+
+    pub fn title(&self, index: usize) -> Option<&str> {
+        self.tabs.get(index).map(|tab| tab.title.as_str())
+    }
+
+    /// The titles of the tabs closed by the user since the last call.
+    pub fn take_closed(&mut self) -> Vec<String> {
+        std::mem::take(&mut self.closed)
+    }
+
+    fn activate(&mut self, pass: &mut EventPass<'_>, index: usize) {
+        if index == self.active || index >= self.tabs.len() {
+            return;
+        }
+        self.active = index;
+        self.show_active(pass);
+    }
+
+    fn show_active(&mut self, pass: &mut EventPass<'_>) {
+        match self
+            .tabs
+            .get(self.active)
+            .and_then(|tab| tab.content.as_ref())
+        {
+            Some(content) if content.exists() => pass.request_child_layout(content.id()),
+            _ => pass.request_update(),
+        }
+        pass.request_layout();
+        pass.request_render();
+        pass.add_damage(pass.bounds());
+    }
+
+    fn step(&mut self, pass: &mut EventPass<'_>, forward: bool) {
+        let len = self.tabs.len();
+        if len < 2 {
+            return;
+        }
+        let index = if forward {
+            (self.active + 1) % len
+        } else {
+            (self.active + len - 1) % len
+        };
+        self.activate(pass, index);
+    }
+
+    fn close(&mut self, pass: &mut EventPass<'_>, index: usize) {
+        if index >= self.tabs.len() {
+            return;
+        }
+        let tab = self.tabs.remove(index);
+        self.closed.push(tab.title);
+        self.removed.extend(tab.content);
+        self.header_widths.clear();
+
+        if index < self.active || self.active >= self.tabs.len() {
+            self.active = self.active.saturating_sub(1);
+        }
+        self.show_active(pass);
+        pass.request_update();
+    }
+
+    fn active_content(&self) -> Option<&ChildElement> {
+        self.tabs
+            .get(self.active)
+            .and_then(|tab| tab.content.as_ref())
+            .filter(|content| content.exists())
+    }
+
+    fn measure_headers(&mut self, id: u64, fonts: &mut dyn Fonts, padding: f32) {
+        self.header_padding = padding;
+        let mut measure = |text: &str| {
+            fonts.measure_text(
+                id,
+                text,
+                &[],
+                None,
+                self.font_size,
+                LineHeight::Relative(1.0),
+                FontFamily::Proportional,
+                FontStyle::Normal,
+                TextAlignment::Start,
+                TextWrapMode::NoWrap,
+            )
+        };
+
+        let close_size = measure(CLOSE_LABEL);
+        self.close_width = if self.closable {
+            close_size.x + self.header_padding
+        } else {
+            0.0
+        };
+        let mut text_height = close_size.y;
+        self.header_widths.clear();
+        for tab in &self.tabs {
+            let size = measure(&tab.title);
+            text_height = text_height.max(size.y);
+            self.header_widths
+                .push(size.x + self.header_padding * 2.0 + self.close_width);
+        }
+        self.header_height = text_height + self.header_padding * 2.0;
+    }
+
+    /// The header's bounds relative to the element, in logical units.
+    fn header_bounds(&self, index: usize) -> Aabb2D {
+        let x = self.header_widths[..index].iter().sum();
+        Aabb2D::from_size_position(
+            Xy::new(self.header_widths[index], self.header_height),
+            Xy::new(x, 0.0),
+        )
+    }
+
+    fn header_at(&self, position: Xy<f32>) -> Option<usize> {
+        (0..self.header_widths.len()).find(|index| self.header_bounds(*index).contains(position))
+    }
+}
+
+impl Element for Tabs {
+    fn children_ids(&self) -> Vec<u64> {
+        self.active_content()
+            .map(|content| vec![content.id()])
+            .unwrap_or_default()
+    }
+
+    fn update_children(&mut self, pass: &mut UpdatePass<'_>) {
+        for content in self.removed.drain(..) {
+            pass.remove_child(content);
+        }
+
+        if let Some(tab) = self.tabs.get_mut(self.active)
+            && let Some(build) = tab.build.take()
+        {
+            tab.content = Some(build().into_child());
+        }
+        for tab in &mut self.tabs {
+            if let Some(content) = &mut tab.content {
+                pass.update_child(content);
+            }
+        }
+    }
+
+    fn accepts_keyboard_events(&self) -> bool {
+        true
+    }
+
+    fn accepts_focus_events(&self) -> bool {
+        true
+    }
+
+    fn render(&mut self, pass: &mut RenderPass<'_>) {
+        let bounds = pass.bounds();
+        let zoom = pass.zoom();
+        let to_physical = |area: Aabb2D| {
+            Aabb2D::from_size_position(
+                Xy::new(area.size().x * zoom, area.size().y * zoom),
+                bounds.position() + Xy::new(area.min.x * zoom, area.min.y * zoom),
+            )
+        };
+
+        pass.fill_quad(
+            to_physical(Aabb2D::from_size(Xy::new(
+                bounds.size().x / zoom,
+                self.header_height,
+            ))),
+            pass.color("surface.sunken"),
+            0.0,
+            Rgba::NONE,
+        );
+
+        for (index, tab) in self.tabs.iter().enumerate() {
+            if index >= self.header_widths.len() {
+                break;
+            }
+            let header = self.header_bounds(index);
+            let active = index == self.active;
+            if active {
+                pass.fill_quad(
+                    to_physical(header),
+                    pass.color("surface.raised"),
+                    1.0,
+                    if self.focused {
+                        pass.color("accent")
+                    } else {
+                        pass.color("border")
+                    },
+                );
+            }
+
+            let text_color = if active {
+                pass.color("text")
+            } else {
+                pass.color("text.muted")
+            };
+            let text_bounds = Aabb2D::new(
+                header.min.x + self.header_padding,
+                header.min.y + self.header_padding,
+                header.max.x - self.header_padding - self.close_width,
+                header.max.y - self.header_padding,
+            );
+            pass.fill_text(
+                &tab.title,
+                to_physical(text_bounds),
+                text_color,
+                self.font_size,
+            );
+            if self.closable {
+                let close_bounds = Aabb2D::new(
+                    text_bounds.max.x + self.header_padding,
+                    text_bounds.min.y,
+                    header.max.x - self.header_padding,
+                    text_bounds.max.y,
+                );
+                pass.fill_text(
+                    CLOSE_LABEL,
+                    to_physical(close_bounds),
+                    text_color,
+                    self.font_size,
+                );
+            }
+        }
+    }
+
+    fn layout(&mut self, pass: &mut LayoutPass<'_>) {
+        let id = pass.id();
+        let padding = pass.metric("spacing.large");
+        self.measure_headers(id, pass.fonts_mut(), padding);
+
+        let size = pass.size;
+        let content_size = Xy::new(size.x, (size.y - self.header_height).max(0.0));
+        if let Some(tab) = self.tabs.get_mut(self.active)
+            && let Some(content) = &mut tab.content
+            && content.exists()
+        {
+            pass.do_layout(content, content_size);
+            pass.place_child(content, Xy::new(0.0, self.header_height));
+        }
+
+        pass.clear_baseline_offset();
+    }
+
+    fn measure(
+        &mut self,
+        context: &mut MeasureContext<'_>,
+        axis: Axis,
+        length_request: LengthRequest,
+        cross_length: Option<f32>,
+    ) -> f32 {
+        let id = context.id();
+        let padding = context.metric("spacing.large");
+        self.measure_headers(id, context.fonts_mut(), padding);
+
+        let fallback_length = length_request.into();
+        let content_length = self.active_content().map_or(0.0, |content| {
+            let content_id = content.id();
+            let cross_length = match axis {
+                Axis::Horizontal => cross_length.map(|length| length - self.header_height),
+                Axis::Vertical => cross_length,
+            };
+            context.resolve_length(content_id, axis, fallback_length, cross_length)
+        });
+
+        match axis {
+            Axis::Horizontal => match length_request {
+                LengthRequest::FitContent(space) => space,
+                LengthRequest::MinContent | LengthRequest::MaxContent => {
+                    content_length.max(self.header_widths.iter().sum())
+                }
+            },
+            Axis::Vertical => self.header_height + content_length,
+        }
+    }
+
+    fn on_focus(&mut self, pass: &mut EventPass<'_>, focused: bool) {
+        self.focused = focused;
+        pass.request_render();
+    }
+
+    fn on_pointer_event(&mut self, pass: &mut EventPass<'_>, event: &PointerEvent) {
+        let PointerEvent::Down {
+            button: PointerButton::Primary,
+            position,
+            ..
+        } = event
+        else {
+            return;
+        };
+        let position = pass.local_position(*position);
+        let Some(index) = self.header_at(position) else {
+            return;
+        };
+
+        if self.closable && position.x >= self.header_bounds(index).max.x - self.close_width {
+            self.close(pass, index);
+        } else {
+            self.activate(pass, index);
+        }
+        pass.request_focus();
+        pass.set_handled();
+    }
+
+    fn on_keyboard_event(&mut self, pass: &mut EventPass<'_>, event: &KeyboardEvent) {
+        let KeyboardEvent::Down { key, modifiers, .. } = event else {
+            return;
+        };
+
+        match key {
+            Key::Tab if modifiers.ctrl => self.step(pass, !modifiers.shift),
+            // NOTE: Arrow keys are only handled while the strip itself is focused, as
+            //       content that doesn't use them shouldn't switch tabs.
+            Key::ArrowLeft if self.focused => self.step(pass, false),
+            Key::ArrowRight if self.focused => self.step(pass, true),
+            _ => return,
+        }
+        pass.set_handled();
+    }
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Empty;
+
+    impl Element for Empty {
+        fn measure(
+            &mut self,
+            _context: &mut MeasureContext<'_>,
+            _axis: Axis,
+            _length_request: LengthRequest,
+            _cross_length: Option<f32>,
+        ) -> f32 {
+            0.0
+        }
+    }
+
+    #[test]
+    fn headers_are_hit_tested_in_order() {
+        let mut tabs = Tabs::new()
+            .with_tab("One", || Empty)
+            .with_tab("Two", || Empty)
+            .with_active(5);
+        assert_eq!(tabs.active(), 1);
+
+        tabs.header_widths = vec![40.0, 60.0];
+        tabs.header_height = 20.0;
+        assert_eq!(tabs.header_at(Xy::new(10.0, 10.0)), Some(0));
+        assert_eq!(tabs.header_at(Xy::new(50.0, 10.0)), Some(1));
+        assert_eq!(tabs.header_at(Xy::new(110.0, 10.0)), None);
+        assert_eq!(tabs.header_at(Xy::new(10.0, 30.0)), None);
+    }
+}
@@ -0,0 +1,668 @@
+//! # Testing
+//!
+//! Helpers for testing elements without a host, enabled with the `testing`
+//! feature. See [`TestView`] for more information.
+//!
+//! The feature also provides a stand-in for the functions that hosts usually
+//! provide (see [`declare`](crate::declare)), so it must not be enabled for the
+//! host itself. Settings are kept in memory for each thread, and nothing can be
+//! opened.
+
+use std::time::{Duration, Instant};
+
+use crate::{
+    Element, ElementBuilder, FontFamily, FontStyle, Fonts, Key, KeyboardEvent, LineHeight,
+    Modifiers, PointerButton, PointerEvent, Render, RenderCommand, Rgba, ScrollDelta,
+    TextAlignment, TextParams, TextSpan, TextWrapMode, TouchPhase, View, Xy, raster::Image,
+};
+
+
+
+/// Approximate text metrics, so that layouts are deterministic without loading
+/// any fonts.
+///
+/// Every character is [`MockFonts::ADVANCE`] times the font size wide, in every
+/// font family and style.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct MockFonts;
+
+impl MockFonts {
+    /// The advance of every character, relative to the font size.
+    pub const ADVANCE: f32 = 0.6;
+
+    /// The most characters that fit on a row, for the given advance and
+    /// wrapping.
+    fn max_chars(char_advance: f32, max_advance: Option<f32>, wrap_mode: TextWrapMode) -> usize {
+        match (max_advance, wrap_mode) {
+            (Some(max_advance), TextWrapMode::Wrap) => {
+                ((max_advance / char_advance).floor() as usize).max(1)
+            }
+            _ => usize::MAX,
+        }
+    }
+
+    /// The byte offset that each row of text starts at, after wrapping, along
+    /// with the offsets of its characters (and the end of the row).
+    fn rows(params: &TextParams<'_>) -> Vec<Vec<usize>> {
+        let char_advance = params.font_size * Self::ADVANCE;
+        let max_chars = Self::max_chars(char_advance, params.max_advance, params.wrap_mode);
+
+        let mut rows = Vec::new();
+        let mut line_start = 0;
+        for line in params.text.split('\n') {
+            let mut offsets = line
+                .char_indices()
+                .map(|(index, _)| line_start + index)
+                .collect::<Vec<_>>();
+            offsets.push(line_start + line.len());
+            while offsets.len() > max_chars.saturating_add(1) {
+                let rest = offsets.split_off(max_chars);
+                let mut row = offsets;
+                row.push(rest[0]);
+                rows.push(row);
+                offsets = rest;
+            }
+            rows.push(offsets);
+            line_start += line.len() + 1;
+        }
+
+        rows
+    }
+}
+
+impl Fonts for MockFonts {
+    fn measure_text(
+        &mut self,
+        _id: u64,
+        text: &str,
+        // NOTE: Every style has the same advance in a monospace font.
+        _spans: &[TextSpan],
+        max_advance: Option<f32>,
+        font_size: f32,
+        line_height: LineHeight,
+        // NOTE: Every family is treated as monospace, so that layouts don't depend on
+        //       which fonts are installed.
+        _font_family: FontFamily,
+        _font_style: FontStyle,
+        _alignment: TextAlignment,
+        wrap_mode: TextWrapMode,
+    ) -> Xy<f32> {
+        let char_advance = font_size * Self::ADVANCE;
+        let line_height = line_height.resolve(font_size);
+        let max_chars = Self::max_chars(char_advance, max_advance, wrap_mode);
+
+        let mut width = 0usize;
+        let mut line_count = 0usize;
+        for line in text.split('\n') {
+            let len = line.chars().count();
+            width = width.max(len.min(max_chars));
+            line_count += len.div_ceil(max_chars).max(1);
+        }
+
+        Xy::new(width as f32 * char_advance, line_count as f32 * line_height)
+    }
+
+    fn cursor_position(&mut self, _id: u64, params: TextParams<'_>, offset: usize) -> Xy<f32> {
+        let char_advance = params.font_size * Self::ADVANCE;
+        let line_height = params.line_height.resolve(params.font_size);
+        let rows = Self::rows(&params);
+
+        // NOTE: An offset at the end of a wrapped row is placed at the start of the
+        //       next one, like the character after it.
+        let row_index = rows.iter().rposition(|row| row[0] <= offset).unwrap_or(0);
+        let column = rows[row_index]
+            .iter()
+            .take_while(|char_offset| **char_offset < offset)
+            .count();
+
+        Xy::new(column as f32 * char_advance, row_index as f32 * line_height)
+    }
+
+    fn hit_test_text(&mut self, _id: u64, params: TextParams<'_>, position: Xy<f32>) -> usize {
+        let char_advance = params.font_size * Self::ADVANCE;
+        let line_height = params.line_height.resolve(params.font_size);
+        let rows = Self::rows(&params);
+
+        let row_index = ((position.y / line_height).max(0.0) as usize).min(rows.len() - 1);
+        let row = &rows[row_index];
+        let column = ((position.x / char_advance).round().max(0.0) as usize).min(row.len() - 1);
+
+        row[column]
+    }
+}
+
+
+
+/// A [`View`] with [`MockFonts`] and a clock that only moves when it's told to,
+/// for driving elements with synthetic input and checking what they produce.
+///
+/// Snapshots of the tree and renders can be compared with
+/// [`TestView::assert_tree`] and [`TestView::assert_render`].
+pub struct TestView {
+    view: View,
+    time: Instant,
+    pointer_position: Xy<f32>,
+    modifiers: Modifiers,
+}
+
+impl TestView {
+    pub const DEFAULT_WINDOW_SIZE: Xy<f32> = Xy::new(800.0, 600.0);
+    /// The time between the frames run by [`TestView::advance`].
+    pub const FRAME_INTERVAL: Duration = Duration::from_nanos(1_000_000_000 / 60);
+
+    pub fn new(root: impl Element + 'static) -> Self {
+        Self::with_window_size(root, Self::DEFAULT_WINDOW_SIZE)
+    }
+
+    pub fn with_window_size(root: impl Element + 'static, window_size: Xy<f32>) -> Self {
+        Self::from_builder(ElementBuilder::new(root), window_size)
+    }
+
+    pub fn from_builder(root: ElementBuilder, window_size: Xy<f32>) -> Self {
+        Self {
+            view: View::new(root, Box::new(MockFonts), window_size),
+            time: Instant::now(),
+            pointer_position: Xy::ZERO,
+            modifiers: Modifiers::default(),
+        }
+    }
+
+    #[inline]
+    pub fn view(&self) -> &View {
+        &self.view
+    }
+
+    #[inline]
+    pub fn view_mut(&mut self) -> &mut View {
+        &mut self.view
+    }
+
+    /// The time of the current frame, which starts at the time the view was
+    /// created.
+    #[inline]
+    pub fn time(&self) -> Instant {
+        self.time
+    }
+
+    /// Set the modifier keys held for the following events.
+    pub fn set_modifiers(&mut self, modifiers: Modifiers) {
+        self.modifiers = modifiers;
+    }
+
+    pub fn move_pointer(&mut self, position: Xy<f32>) {
+        self.pointer_position = position;
+        self.view.handle_pointer_event(PointerEvent::Move {
+            position,
+            modifiers: self.modifiers,
+        });
+    }
+
+    /// Press the button at the pointer's position.
+    pub fn press(&mut self, button: PointerButton) {
+        self.view.handle_pointer_event(PointerEvent::Down {
+            button,
+            position: self.pointer_position,
+            modifiers: self.modifiers,
+        });
+    }
+
+    pub fn release(&mut self, button: PointerButton) {
+        self.view.handle_pointer_event(PointerEvent::Up {
+            button,
+            modifiers: self.modifiers,
+        });
+    }
+
+    /// Move the pointer to the given position, and click the primary button.
+    pub fn click(&mut self, position: Xy<f32>) {
+        self.move_pointer(position);
+        self.press(PointerButton::Primary);
+        self.release(PointerButton::Primary);
+    }
+
+    /// Press the primary button at `from`, move the pointer to `to`, and
+    /// release it there.
+    pub fn drag(&mut self, from: Xy<f32>, to: Xy<f32>) {
+        self.move_pointer(from);
+        self.press(PointerButton::Primary);
+        self.move_pointer(to);
+        self.release(PointerButton::Primary);
+    }
+
+    /// Scroll by the given number of pixels at the pointer's position.
+    pub fn scroll(&mut self, delta: Xy<f32>) {
+        self.view.handle_pointer_event(PointerEvent::Scroll {
+            delta: ScrollDelta::Pixels(delta),
+            modifiers: self.modifiers,
+        });
+    }
+
+    /// Touch the view with a finger, see [`PointerEvent::Touch`].
+    pub fn touch(&mut self, id: u64, phase: TouchPhase, position: Xy<f32>) {
+        self.view.handle_pointer_event(PointerEvent::Touch {
+            id,
+            phase,
+            position,
+            modifiers: self.modifiers,
+        });
+    }
+
+    /// Press and release the given key.
+    pub fn press_key(&mut self, key: Key) {
+        self.hold_key(key, 0);
+    }
+
+    /// Press the given key, hold it for the given number of key repeats, then
+    /// release it.
+    pub fn hold_key(&mut self, key: Key, repeats: usize) {
+        let modifiers = self.modifiers;
+        for index in 0..=repeats {
+            self.view.handle_keyboard_event(KeyboardEvent::Down {
+                key,
+                modifiers,
+                repeat: index != 0,
+            });
+        }
+        self.view
+            .handle_keyboard_event(KeyboardEvent::Up { key, modifiers });
+    }
+
+    /// Press and release the key for each character in the text.
+    pub fn type_text(&mut self, text: &str) {
+        for ch in text.chars() {
+            self.press_key(Key::Char(ch));
+        }
+    }
+
+    pub fn resize_window(&mut self, size: Xy<f32>) {
+        self.view.resize_window(size);
+    }
+
+    /// Run frames [`TestView::FRAME_INTERVAL`] apart until the given time has
+    /// passed, delivering updates and running animations and timers as a host
+    /// would.
+    pub fn advance(&mut self, duration: Duration) {
+        let end = self.time + duration;
+        while self.time < end {
+            self.time = (self.time + Self::FRAME_INTERVAL).min(end);
+            self.run_frame(None);
+        }
+    }
+
+    /// Run a frame at the current time, returning what it rendered.
+    pub fn render(&mut self) -> Render {
+        let mut render = Render::default();
+        self.run_frame(Some(&mut render));
+
+        render
+    }
+
+    /// Draw a new render with the [software rasterizer](crate::raster), onto
+    /// an image the size of the window with the given background color.
+    pub fn render_image(&mut self, background: Rgba) -> Image {
+        let size = self.view.window_size();
+        let mut image = Image::new(size.x.ceil() as u32, size.y.ceil() as u32, background);
+        image.draw_render(&self.render());
+
+        image
+    }
+
+    fn run_frame(&mut self, render: Option<&mut Render>) {
+        self.view.process_updates();
+        self.view.begin_frame(self.time);
+        if let Some(render) = render {
+            self.view.render(render);
+        }
+        self.view.end_frame();
+    }
+
+    /// The text drawn by a new render, in the order it was drawn.
+    pub fn text_in_render(&mut self) -> String {
+        self.render()
+            .commands
+            .iter()
+            .filter_map(|command| match command {
+                RenderCommand::DrawChar(ch) => Some(*ch),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// A snapshot of the element tree (see [`View::dump_tree`]), without the
+    /// element IDs, which depend on what else has been built in the process.
+    pub fn tree_snapshot(&self) -> String {
+        strip_element_ids(&self.view.dump_tree())
+    }
+
+    /// The center of the first element in the tree whose type name (as in
+    /// [`TestView::tree_snapshot`]) starts with `name`, in window coordinates.
+    pub fn element_center(&self, name: &str) -> Option<Xy<f32>> {
+        let snapshot = self.view.dump_tree();
+        let line = snapshot
+            .lines()
+            .find(|line| line.trim_start().starts_with(name))?;
+        let id = line
+            .split_whitespace()
+            .find_map(|word| word.strip_prefix('#')?.parse::<u64>().ok())?;
+
+        Some(self.view.element_bounds(id)?.center())
+    }
+
+    /// Panic if the tree doesn't match the snapshot, see
+    /// [`TestView::tree_snapshot`] and [`assert_snapshot`].
+    #[track_caller]
+    pub fn assert_tree(&self, expected: &str) {
+        assert_snapshot(&self.tree_snapshot(), expected);
+    }
+
+    /// Render a frame and panic if it doesn't match the snapshot, which is in
+    /// the format of [`Render`]'s [`Display`](std::fmt::Display)
+    /// implementation.
+    #[track_caller]
+    pub fn assert_render(&mut self, expected: &str) {
+        let render = self.render().to_string();
+        assert_snapshot(&render, expected);
+    }
+}
+
+/// Panic if the snapshot doesn't match the expected one, ignoring blank lines
+/// at the start and end, trailing whitespace, and any indentation shared by
+/// every line (so that expected snapshots can be indented in raw strings).
+#[track_caller]
+pub fn assert_snapshot(actual: &str, expected: &str) {
+    let actual = normalize_snapshot(actual);
+    let expected = normalize_snapshot(expected);
+    if actual == expected {
+        return;
+    }
+
+    let mut message = String::from("snapshots don't match\n");
+    let actual_lines = actual.lines().collect::<Vec<_>>();
+    let expected_lines = expected.lines().collect::<Vec<_>>();
+    for index in 0..actual_lines.len().max(expected_lines.len()) {
+        let actual_line = actual_lines.get(index).copied();
+        let expected_line = expected_lines.get(index).copied();
+        if actual_line == expected_line {
+            message.push_str(&format!("  {}\n", actual_line.unwrap_or_default()));
+            continue;
+        }
+        if let Some(line) = expected_line {
+            message.push_str(&format!("- {line}\n"));
+        }
+        if let Some(line) = actual_line {
+            message.push_str(&format!("+ {line}\n"));
+        }
+    }
+
+    panic!("{message}");
+}
+
+fn normalize_snapshot(snapshot: &str) -> String {
+    let lines = snapshot
+        .lines()
+        .map(str::trim_end)
+        .skip_while(|line| line.is_empty())
+        .collect::<Vec<_>>();
+    let end = lines
+        .iter()
+        .rposition(|line| !line.is_empty())
+        .map_or(0, |index| index + 1);
+    let lines = &lines[..end];
+    let indent = lines
+        .iter()
+        .filter(|line| !line.is_empty())
+        .map(|line| line.len() - line.trim_start().len())
+        .min()
+        .unwrap_or(0);
+
+    lines
+        .iter()
+        .map(|line| line.get(indent..).unwrap_or_default())
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Remove the ` #<id>` after each element's name in a [`View::dump_tree`].
+fn strip_element_ids(tree: &str) -> String {
+    let mut output = String::with_capacity(tree.len());
+    for line in tree.lines() {
+        let mut words = Vec::new();
+        let mut stripped = false;
+        for word in line.split(' ') {
+            if !stripped
+                && let Some(id) = word.strip_prefix('#')
+                && !id.is_empty()
+                && id.bytes().all(|byte| byte.is_ascii_digit())
+            {
+                stripped = true;
+                continue;
+            }
+            words.push(word);
+        }
+        output.push_str(&words.join(" "));
+        output.push('\n');
+    }
+
+    output
+}
+
+
+
+#[allow(non_snake_case)]
+mod host {
+    use {
+        crate::{
+            Axis, Label, LengthRequest, MeasureContext, RenderPass, TextStyle,
+            command::{Command, RegisterCommandError},
+            system::{OpenUrlError, OpenViewError},
+        },
+        std::{cell::RefCell, collections::HashMap},
+    };
+
+    thread_local! {
+        static SETTINGS: RefCell<HashMap<String, String>> = RefCell::new(HashMap::new());
+    }
+
+    crate::declare! {
+        mod system {
+            fn open_url(_url: &str) -> Result<(), OpenUrlError> {
+                Err(OpenUrlError::NoHandler)
+            }
+
+            fn get_setting(key: &str) -> Option<String> {
+                SETTINGS.with_borrow(|settings| settings.get(key).cloned())
+            }
+
+            fn set_setting(key: &str, value: &str) {
+                SETTINGS.with_borrow_mut(|settings| {
+                    settings.insert(key.to_string(), value.to_string())
+                });
+            }
+
+            fn open_view(_name: &str) -> Result<(), OpenViewError> {
+                Err(OpenViewError::NotSupported)
+            }
+
+            fn close_view(_name: &str) {}
+        }
+    }
+
+    crate::declare! {
+        mod command {
+            fn register(_command: &'static Command) -> Result<(), RegisterCommandError> {
+                Ok(())
+            }
+        }
+    }
+
+    // NOTE: Labels are drawn and measured like any other text, with the view's
+    //       fonts.
+    crate::declare! {
+        mod ui {
+            fn Label__render(label: &mut Label, pass: &mut RenderPass<'_>) {
+                pass.fill_styled_text(
+                    &label.text,
+                    &label.spans,
+                    TextStyle {
+                        family: Some(label.font_family),
+                        font_style: label.font_style,
+                        ..TextStyle::default()
+                    },
+                    pass.bounds(),
+                    label.color,
+                    label.font_size,
+                );
+            }
+
+            fn Label__measure(
+                label: &mut Label,
+                context: &mut MeasureContext<'_>,
+                axis: Axis,
+                length_request: LengthRequest,
+                cross_length: Option<f32>
+            ) -> f32 {
+                let max_advance = match (axis, length_request) {
+                    (Axis::Horizontal, LengthRequest::FitContent(space)) => Some(space),
+                    (Axis::Vertical, _) => cross_length,
+                    _ => None,
+                };
+                let id = context.id();
+                let size = context.fonts_mut().measure_text(
+                    id,
+                    &label.text,
+                    &label.spans,
+                    max_advance,
+                    label.font_size,
+                    label.line_height,
+                    label.font_family,
+                    label.font_style,
+                    label.alignment,
+                    label.wrap_mode,
+                );
+
+                size.value_for_axis(axis)
+            }
+        }
+    }
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::{Column, LineInput, Shortcuts, shortcut::ShortcutMap},
+    };
+
+    #[test]
+    fn mock_fonts_wrap() {
+        let size = MockFonts.measure_text(
+            0,
+            "hello world",
+            &[],
+            Some(30.0),
+            10.0,
+            LineHeight::Relative(1.0),
+            FontFamily::Proportional,
+            FontStyle::default(),
+            TextAlignment::Start,
+            TextWrapMode::Wrap,
+        );
+        assert_eq!(size, Xy::new(30.0, 30.0));
+    }
+
+    #[test]
+    fn snapshots_ignore_indentation() {
+        assert_snapshot(
+            "Column at (0, 0)\n  Label at (0, 0)\n",
+            "
+            Column at (0, 0)
+              Label at (0, 0)
+            ",
+        );
+        assert_eq!(
+            strip_element_ids("  Row #12 at (0, 0)"),
+            "  Row at (0, 0)\n"
+        );
+    }
+
+    #[test]
+    fn typing_into_input() {
+        let mut view = TestView::new(Column::new().with(LineInput::new("")));
+        let input = view.element_center("LineInput").unwrap();
+        view.click(input);
+        view.type_text("abc");
+        view.press_key(Key::Backspace);
+
+        assert_eq!(view.text_in_render(), "ab");
+        view.assert_tree(
+            "
+            Column at (0, 0) size 800x600 baseline 0 hovered focused
+              LineInput at (0, 0) size 800x16 baseline 0 hovered focused
+            ",
+        );
+    }
+
+    #[test]
+    fn holding_key_repeats() {
+        let mut view = TestView::new(Column::new().with(LineInput::new("")));
+        let input = view.element_center("LineInput").unwrap();
+        view.click(input);
+        view.type_text("abcde");
+        view.hold_key(Key::Backspace, 2);
+
+        assert_eq!(view.text_in_render(), "ab");
+    }
+
+    #[test]
+    fn shortcut_keys_reach_input_when_unmatched() {
+        let mut map = ShortcutMap::new();
+        map.bind("g g", (), "Clear").unwrap();
+        let input = Shortcuts::new(LineInput::new(""), map, |input, _pass, _action| {
+            input.text.clear();
+        });
+        let mut view = TestView::new(Column::new().with(input));
+        let input = view.element_center("Shortcuts").unwrap();
+        view.click(input);
+
+        view.type_text("abgg");
+        assert_eq!(view.text_in_render(), "");
+
+        // A broken sequence passes its keys on.
+        view.type_text("gx");
+        assert_eq!(view.text_in_render(), "gx");
+
+        // So does one that times out.
+        view.type_text("g");
+        // The input is followed by the hint for the pending sequence.
+        assert_eq!(view.text_in_render(), "gxg  Clear");
+        view.advance(Duration::from_secs(2));
+        assert_eq!(view.text_in_render(), "gxg");
+    }
+
+    #[test]
+    fn pinching_zooms_view() {
+        let mut view = TestView::new(Column::new());
+        view.touch(1, TouchPhase::Start, Xy::new(100.0, 100.0));
+        view.touch(2, TouchPhase::Start, Xy::new(200.0, 100.0));
+        view.touch(2, TouchPhase::Move, Xy::new(300.0, 100.0));
+        view.touch(2, TouchPhase::End, Xy::new(300.0, 100.0));
+        view.touch(1, TouchPhase::End, Xy::new(100.0, 100.0));
+
+        assert_eq!(view.view().zoom(), 2.0);
+    }
+
+    #[test]
+    fn rendering_images() {
+        let mut view = TestView::with_window_size(
+            Column::new().with_background_color(Rgba::rgb(0, 0, 255)),
+            Xy::new(4.0, 3.0),
+        );
+        let image = view.render_image(Rgba::BLACK);
+
+        assert_eq!((image.width(), image.height()), (4, 3));
+        assert_eq!(image.pixel(1, 1), Some(Rgba::rgb(0, 0, 255)));
+        assert_ne!(image.pixel(0, 0), image.pixel(1, 1));
+    }
+}
@@ -1,5 +1,9 @@
 //! # Text Types
 
+use std::{fmt, ops::Range};
+
+use crate::Rgba;
+
 
 
 #[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
@@ -36,6 +40,33 @@ pub enum TextAlignment {
     Justify = 5,
 }
 
+/// The direction in which text flows.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+#[repr(u8)]
+pub enum TextDirection {
+    #[default]
+    LeftToRight = 0,
+    RightToLeft = 1,
+}
+
+impl TextDirection {
+    /// The lowest bidirectional embedding level with this direction.
+    #[inline]
+    pub const fn level(self) -> u8 {
+        self as u8
+    }
+
+    /// Get the direction of text at the given bidirectional embedding level.
+    #[inline]
+    pub const fn from_level(level: u8) -> Self {
+        if level.is_multiple_of(2) {
+            Self::LeftToRight
+        } else {
+            Self::RightToLeft
+        }
+    }
+}
+
 #[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
 #[repr(u8)]
 pub enum FontStyle {
@@ -45,6 +76,302 @@ pub enum FontStyle {
     Oblique,
 }
 
+/// The family of fonts that text is drawn with.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+#[repr(C, u8)]
+pub enum FontFamily {
+    /// The system's default font, where characters have different widths.
+    #[default]
+    Proportional,
+    /// A font where every character has the same width, for terminals and code.
+    Monospace,
+    /// A font installed under the given name. Hosts that don't have it fall
+    /// back to [`FontFamily::Proportional`].
+    Named(FontName),
+}
+
+impl FontFamily {
+    /// The family with the given name, or `None` if the name is longer than
+    /// [`FontName::CAPACITY`] bytes.
+    pub fn named(name: &str) -> Option<Self> {
+        FontName::new(name).map(Self::Named)
+    }
+}
+
+/// The name of a font family, stored inline so that render commands can be
+/// passed between processes.
+#[derive(Clone, Copy, Eq, Hash, PartialEq)]
+#[repr(C)]
+pub struct FontName {
+    len: u8,
+    bytes: [u8; Self::CAPACITY],
+}
+
+impl FontName {
+    /// The maximum length of a name, in bytes.
+    pub const CAPACITY: usize = 31;
+
+    /// Returns `None` if the name is longer than [`Self::CAPACITY`] bytes.
+    pub fn new(name: &str) -> Option<Self> {
+        if name.len() > Self::CAPACITY {
+            return None;
+        }
+        let mut bytes = [0; Self::CAPACITY];
+        bytes[..name.len()].copy_from_slice(name.as_bytes());
+
+        Some(Self {
+            len: name.len() as u8,
+            bytes,
+        })
+    }
+
+    #[inline]
+    pub fn as_str(&self) -> &str {
+        // SAFETY: The bytes were copied from a `str` in `FontName::new`.
+        unsafe { str::from_utf8_unchecked(&self.bytes[..self.len as usize]) }
+    }
+}
+
+impl fmt::Debug for FontName {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(self.as_str(), f)
+    }
+}
+
+/// How thick the strokes of a font are, from 100 (thin) to 900 (black).
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct FontWeight(pub u16);
+
+impl Default for FontWeight {
+    fn default() -> Self {
+        Self::NORMAL
+    }
+}
+
+impl FontWeight {
+    pub const THIN: Self = Self(100);
+    pub const LIGHT: Self = Self(300);
+    pub const NORMAL: Self = Self(400);
+    pub const MEDIUM: Self = Self(500);
+    pub const BOLD: Self = Self(700);
+    pub const BLACK: Self = Self(900);
+}
+
+/// Lines drawn along with text.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+pub struct TextDecoration {
+    pub underline: bool,
+    pub strikethrough: bool,
+}
+
+impl TextDecoration {
+    pub const NONE: Self = Self {
+        underline: false,
+        strikethrough: false,
+    };
+    pub const UNDERLINE: Self = Self {
+        underline: true,
+        strikethrough: false,
+    };
+    pub const STRIKETHROUGH: Self = Self {
+        underline: false,
+        strikethrough: true,
+    };
+
+    #[inline]
+    pub const fn is_none(self) -> bool {
+        !self.underline && !self.strikethrough
+    }
+}
+
+/// How a run of text is drawn.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+pub struct TextStyle {
+    /// Overrides the font family of the text around it, such as for inline
+    /// code.
+    pub family: Option<FontFamily>,
+    pub font_style: FontStyle,
+    pub weight: FontWeight,
+    /// Overrides the color of the text around it.
+    pub color: Option<Rgba>,
+    pub decoration: TextDecoration,
+}
+
+impl TextStyle {
+    pub fn bold(mut self) -> Self {
+        self.weight = FontWeight::BOLD;
+        self
+    }
+
+    pub fn italic(mut self) -> Self {
+        self.font_style = FontStyle::Italic;
+        self
+    }
+
+    pub fn underline(mut self) -> Self {
+        self.decoration.underline = true;
+        self
+    }
+
+    pub fn strikethrough(mut self) -> Self {
+        self.decoration.strikethrough = true;
+        self
+    }
+
+    pub fn monospace(mut self) -> Self {
+        self.family = Some(FontFamily::Monospace);
+        self
+    }
+
+    pub fn with_family(mut self, family: FontFamily) -> Self {
+        self.family = Some(family);
+        self
+    }
+
+    pub fn with_weight(mut self, weight: FontWeight) -> Self {
+        self.weight = weight;
+        self
+    }
+
+    pub fn with_color(mut self, color: Rgba) -> Self {
+        self.color = Some(color);
+        self
+    }
+}
+
+/// A style applied to a range of bytes in a string.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct TextSpan {
+    pub range: Range<usize>,
+    pub style: TextStyle,
+}
+
+impl TextSpan {
+    pub fn new(range: Range<usize>, style: TextStyle) -> Self {
+        Self { range, style }
+    }
+}
+
+/// A string with styled spans, built up one piece at a time.
+///
+/// ```
+/// # use abi::{StyledText, TextStyle};
+/// let text = StyledText::new()
+///     .with_plain("Press ")
+///     .with("Save", TextStyle::default().bold())
+///     .with_plain(" to continue");
+///
+/// assert_eq!(text.text, "Press Save to continue");
+/// assert_eq!(text.spans[0].range, 6..10);
+/// ```
+#[derive(Clone, Debug, Default, Eq, Hash, PartialEq)]
+pub struct StyledText {
+    pub text: String,
+    pub spans: Vec<TextSpan>,
+}
+
+impl StyledText {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Append text in the given style.
+    pub fn with(mut self, text: &str, style: TextStyle) -> Self {
+        let start = self.text.len();
+        self.text.push_str(text);
+        if style != TextStyle::default() {
+            self.spans
+                .push(TextSpan::new(start..self.text.len(), style));
+        }
+        self
+    }
+
+    /// Append text in the style of the text around it.
+    pub fn with_plain(mut self, text: &str) -> Self {
+        self.text.push_str(text);
+        self
+    }
+}
+
+/// A string and everything it's laid out with, for the [`Fonts`](crate::Fonts)
+/// methods that work with positions within text.
+#[derive(Clone, Copy, Debug)]
+pub struct TextParams<'a> {
+    pub text: &'a str,
+    pub spans: &'a [TextSpan],
+    pub max_advance: Option<f32>,
+    pub font_size: f32,
+    pub line_height: LineHeight,
+    pub font_family: FontFamily,
+    pub font_style: FontStyle,
+    pub alignment: TextAlignment,
+    pub wrap_mode: TextWrapMode,
+}
+
+impl<'a> TextParams<'a> {
+    /// A single line of unstyled text at the given font size.
+    pub fn new(text: &'a str, font_size: f32) -> Self {
+        Self {
+            text,
+            spans: &[],
+            max_advance: None,
+            font_size,
+            line_height: LineHeight::FONT_PREFERRED,
+            font_family: FontFamily::Proportional,
+            font_style: FontStyle::Normal,
+            alignment: TextAlignment::Start,
+            wrap_mode: TextWrapMode::NoWrap,
+        }
+    }
+}
+
+/// Split `text` into runs that each have a single style, in order, where the
+/// unstyled runs use `base`.
+///
+/// Later spans take precedence over earlier ones where they overlap, and span
+/// ranges are clamped to the text and to character boundaries.
+pub fn styled_runs<'a>(
+    text: &'a str,
+    spans: &[TextSpan],
+    base: TextStyle,
+) -> Vec<(&'a str, TextStyle)> {
+    let clamp = |index: usize| {
+        let mut index = index.min(text.len());
+        while !text.is_char_boundary(index) {
+            index -= 1;
+        }
+        index
+    };
+
+    let mut boundaries = vec![0, text.len()];
+    for span in spans {
+        boundaries.push(clamp(span.range.start));
+        boundaries.push(clamp(span.range.end));
+    }
+    boundaries.sort_unstable();
+    boundaries.dedup();
+
+    let mut runs: Vec<(Range<usize>, TextStyle)> = Vec::new();
+    for bounds in boundaries.windows(2) {
+        let (start, end) = (bounds[0], bounds[1]);
+        let style = spans
+            .iter()
+            .rev()
+            .find(|span| clamp(span.range.start) <= start && end <= clamp(span.range.end))
+            .map_or(base, |span| span.style);
+
+        // Neighbouring runs with the same style are merged.
+        match runs.last_mut() {
+            Some((range, run_style)) if *run_style == style => range.end = end,
+            _ => runs.push((start..end, style)),
+        }
+    }
+
+    runs.into_iter()
+        .map(|(range, style)| (&text[range], style))
+        .collect()
+}
+
 #[derive(Clone, Copy, Debug, PartialEq)]
 pub enum LineHeight {
     Relative(f32),
@@ -59,4 +386,46 @@ impl Default for LineHeight {
 
 impl LineHeight {
     pub const FONT_PREFERRED: Self = Self::Relative(1.0);
+
+    /// The height of a line of text at the given font size.
+    pub fn resolve(self, font_size: f32) -> f32 {
+        match self {
+            Self::Relative(factor) => font_size * factor,
+            Self::Absolute(height) => height,
+        }
+    }
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn styled_runs_split_and_merge() {
+        let plain = TextStyle::default();
+        let bold = plain.bold();
+        let red = plain.with_color(Rgba::new(255, 0, 0, 255));
+        let spans = [
+            TextSpan::new(0..5, bold),
+            TextSpan::new(5..7, bold),
+            TextSpan::new(3..4, red),
+            // Starts in the middle of "é" and ends past the text, so it's clamped.
+            TextSpan::new(9..12, red),
+        ];
+
+        assert_eq!(
+            styled_runs("hello, wé", &spans, plain),
+            vec![
+                ("hel", bold),
+                ("l", red),
+                ("o, ", bold),
+                ("w", plain),
+                ("é", red),
+            ],
+        );
+        assert_eq!(styled_runs("plain", &[], plain), vec![("plain", plain)]);
+        assert_eq!(styled_runs("", &spans, plain), vec![]);
+    }
 }
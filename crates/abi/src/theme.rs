@@ -0,0 +1,255 @@
+//! # Themes
+//!
+//! Named design tokens (such as `"surface.raised"` or `"text.muted"`) that
+//! elements resolve to colors at render time, see
+//! [`RenderPass::color`](crate::RenderPass::color), and named metrics (such as
+//! `"spacing.small"`) that they resolve to lengths in logical units, see
+//! [`LayoutPass::metric`](crate::LayoutPass::metric).
+
+use std::collections::HashMap;
+
+use crate::Rgba;
+
+
+
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+pub enum ColorMode {
+    Light,
+    #[default]
+    Dark,
+}
+
+/// The value of a color token in each [`ColorMode`].
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct TokenColor {
+    pub light: Rgba,
+    pub dark: Rgba,
+}
+
+impl TokenColor {
+    pub const fn new(light: Rgba, dark: Rgba) -> Self {
+        Self { light, dark }
+    }
+
+    /// Use the same color in both modes.
+    pub const fn both(color: Rgba) -> Self {
+        Self::new(color, color)
+    }
+
+    #[inline]
+    pub const fn get(&self, mode: ColorMode) -> Rgba {
+        match mode {
+            ColorMode::Light => self.light,
+            ColorMode::Dark => self.dark,
+        }
+    }
+}
+
+/// The tokens defined by [`Theme::default`].
+pub const DEFAULT_TOKENS: &[(&str, TokenColor)] = &[
+    (
+        "surface",
+        TokenColor::new(Rgba::rgb(0xf5, 0xf5, 0xf7), Rgba::rgb(0x10, 0x10, 0x18)),
+    ),
+    (
+        "surface.raised",
+        TokenColor::new(
+            Rgba::rgb(0xff, 0xff, 0xff),
+            Rgba::new(0x1b, 0x1b, 0x27, 240),
+        ),
+    ),
+    (
+        "surface.sunken",
+        TokenColor::new(Rgba::rgb(0xe8, 0xe8, 0xec), Rgba::rgb(0x0b, 0x0b, 0x10)),
+    ),
+    (
+        "text",
+        TokenColor::new(Rgba::rgb(0x1b, 0x1b, 0x27), Rgba::rgb(0xee, 0xee, 0xee)),
+    ),
+    (
+        "text.muted",
+        TokenColor::new(Rgba::rgb(0x5c, 0x5c, 0x6b), Rgba::rgb(177, 177, 177)),
+    ),
+    (
+        "border",
+        TokenColor::new(Rgba::rgb(0xc8, 0xc8, 0xd0), Rgba::rgb(111, 111, 111)),
+    ),
+    (
+        "accent",
+        TokenColor::new(Rgba::rgb(0x2f, 0x6f, 0xeb), Rgba::rgb(0x5a, 0x8d, 0xff)),
+    ),
+    (
+        "error",
+        TokenColor::new(Rgba::rgb(0xc6, 0x28, 0x28), Rgba::rgb(0xff, 0x6b, 0x6b)),
+    ),
+];
+
+/// The metrics defined by [`Theme::default`], in logical units.
+pub const DEFAULT_METRICS: &[(&str, f32)] = &[
+    ("spacing.small", 4.0),
+    ("spacing", 6.0),
+    ("spacing.large", 8.0),
+    ("scrollbar.width", 12.0),
+    ("scrollbar.thumb.min_length", 40.0),
+];
+
+/// A set of named color tokens and metrics, the current [`ColorMode`], and any
+/// overrides an application has made to them.
+#[derive(Clone, Debug)]
+pub struct Theme {
+    mode: ColorMode,
+    tokens: HashMap<String, TokenColor>,
+    overrides: HashMap<String, TokenColor>,
+    metrics: HashMap<String, f32>,
+    metric_overrides: HashMap<String, f32>,
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        let mut theme = Self::empty(ColorMode::default());
+        for (name, color) in DEFAULT_TOKENS {
+            theme.define(*name, *color);
+        }
+        for (name, value) in DEFAULT_METRICS {
+            theme.define_metric(*name, *value);
+        }
+
+        theme
+    }
+}
+
+impl Theme {
+    /// The color used for tokens that aren't defined, chosen to stand out.
+    pub const MISSING: Rgba = Rgba::rgb(0xff, 0x00, 0xff);
+
+    /// Create a theme without any tokens or metrics.
+    pub fn empty(mode: ColorMode) -> Self {
+        Self {
+            mode,
+            tokens: HashMap::new(),
+            overrides: HashMap::new(),
+            metrics: HashMap::new(),
+            metric_overrides: HashMap::new(),
+        }
+    }
+
+    #[inline]
+    pub fn mode(&self) -> ColorMode {
+        self.mode
+    }
+
+    pub fn set_mode(&mut self, mode: ColorMode) {
+        self.mode = mode;
+    }
+
+    /// Define (or redefine) a token.
+    pub fn define(&mut self, name: impl Into<String>, color: TokenColor) {
+        self.tokens.insert(name.into(), color);
+    }
+
+    /// Override a token for the current application, taking precedence over its
+    /// definition until [`Theme::clear_overrides`] is called.
+    pub fn set_override(&mut self, name: impl Into<String>, color: TokenColor) {
+        self.overrides.insert(name.into(), color);
+    }
+
+    pub fn remove_override(&mut self, name: &str) -> Option<TokenColor> {
+        self.overrides.remove(name)
+    }
+
+    /// Remove every token and metric override.
+    pub fn clear_overrides(&mut self) {
+        self.overrides.clear();
+        self.metric_overrides.clear();
+    }
+
+    /// Get a token's value in every mode, or `None` if it isn't defined.
+    pub fn token(&self, name: &str) -> Option<TokenColor> {
+        self.overrides
+            .get(name)
+            .or_else(|| self.tokens.get(name))
+            .copied()
+    }
+
+    /// Resolve a token in the current mode, or `None` if it isn't defined.
+    pub fn resolve(&self, name: &str) -> Option<Rgba> {
+        self.token(name).map(|color| color.get(self.mode))
+    }
+
+    /// Like [`Theme::resolve`], but falling back to [`Theme::MISSING`].
+    pub fn color(&self, name: &str) -> Rgba {
+        self.resolve(name).unwrap_or(Self::MISSING)
+    }
+
+    /// Define (or redefine) a metric.
+    pub fn define_metric(&mut self, name: impl Into<String>, value: f32) {
+        self.metrics.insert(name.into(), value);
+    }
+
+    /// Override a metric for the current application, like
+    /// [`Theme::set_override`] does for tokens.
+    pub fn set_metric_override(&mut self, name: impl Into<String>, value: f32) {
+        self.metric_overrides.insert(name.into(), value);
+    }
+
+    pub fn remove_metric_override(&mut self, name: &str) -> Option<f32> {
+        self.metric_overrides.remove(name)
+    }
+
+    /// Get a metric's value, or `None` if it isn't defined.
+    pub fn resolve_metric(&self, name: &str) -> Option<f32> {
+        self.metric_overrides
+            .get(name)
+            .or_else(|| self.metrics.get(name))
+            .copied()
+    }
+
+    /// Like [`Theme::resolve_metric`], but falling back to `0.0`.
+    pub fn metric(&self, name: &str) -> f32 {
+        self.resolve_metric(name).unwrap_or(0.0)
+    }
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn modes_and_overrides() {
+        let mut theme = Theme::default();
+        assert_eq!(theme.color("text.muted"), Rgba::rgb(177, 177, 177));
+        theme.set_mode(ColorMode::Light);
+        assert_eq!(theme.color("text.muted"), Rgba::rgb(0x5c, 0x5c, 0x6b));
+
+        theme.set_override("text.muted", TokenColor::both(Rgba::BLACK));
+        assert_eq!(theme.color("text.muted"), Rgba::BLACK);
+        theme.define("text.muted", TokenColor::both(Rgba::WHITE));
+        assert_eq!(theme.color("text.muted"), Rgba::BLACK);
+        theme.clear_overrides();
+        assert_eq!(theme.color("text.muted"), Rgba::WHITE);
+
+        assert_eq!(theme.resolve("nonexistent"), None);
+        assert_eq!(theme.color("nonexistent"), Theme::MISSING);
+    }
+
+    #[test]
+    fn metric_overrides() {
+        let mut theme = Theme::default();
+        assert_eq!(theme.metric("spacing.small"), 4.0);
+
+        theme.set_metric_override("spacing.small", 2.0);
+        theme.define_metric("spacing.small", 3.0);
+        assert_eq!(theme.metric("spacing.small"), 2.0);
+        assert_eq!(theme.remove_metric_override("spacing.small"), Some(2.0));
+        assert_eq!(theme.metric("spacing.small"), 3.0);
+
+        theme.set_metric_override("spacing", 1.0);
+        theme.clear_overrides();
+        assert_eq!(theme.metric("spacing"), 6.0);
+
+        assert_eq!(theme.resolve_metric("nonexistent"), None);
+        assert_eq!(theme.metric("nonexistent"), 0.0);
+    }
+}
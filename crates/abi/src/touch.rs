@@ -0,0 +1,214 @@
+//! # Touch Input
+//!
+//! Touchscreens report each finger as a [`PointerEvent::Touch`], which views
+//! translate into the pointer events that elements already handle with a
+//! [`TouchTracker`]:
+//!
+//! - A single finger presses the primary button where it touches, drags while
+//!   it moves, and releases the button when it's lifted.
+//! - Two fingers scroll by the movement of the point between them, and zoom
+//!   (see [`PointerEvent::Zoom`]) as they're pinched together or spread apart.
+
+use crate::{PointerButton, PointerEvent, ScrollDelta, TouchPhase, Xy};
+
+
+
+/// Translates touches into pointer events, see the [module docs](self).
+///
+/// Only the first two fingers are used, and once a second finger touches, the
+/// primary button stays released until every finger has been lifted.
+#[derive(Clone, Debug, Default)]
+pub struct TouchTracker {
+    /// The fingers touching, by ID, in the order they started touching.
+    touches: Vec<(u64, Xy<f32>)>,
+    /// Whether the first finger is holding the primary button.
+    pressing: bool,
+}
+
+impl TouchTracker {
+    /// The pointer events that a touch translates into, which is nothing for
+    /// other events.
+    pub fn handle(&mut self, event: &PointerEvent) -> Vec<PointerEvent> {
+        let PointerEvent::Touch {
+            id,
+            phase,
+            position,
+            modifiers,
+        } = *event
+        else {
+            return Vec::new();
+        };
+
+        let mut events = Vec::new();
+        let index = self.touches.iter().position(|(touch, _)| *touch == id);
+        match (phase, index) {
+            (TouchPhase::Start, None) => {
+                self.touches.push((id, position));
+                if self.touches.len() == 1 {
+                    events.push(PointerEvent::Move {
+                        position,
+                        modifiers,
+                    });
+                    events.push(PointerEvent::Down {
+                        button: PointerButton::Primary,
+                        position,
+                        modifiers,
+                    });
+                    self.pressing = true;
+                } else if core::mem::take(&mut self.pressing) {
+                    events.push(PointerEvent::Up {
+                        button: PointerButton::Primary,
+                        modifiers,
+                    });
+                }
+            }
+            (TouchPhase::Start | TouchPhase::Move, Some(index)) => {
+                let before = self.pinch();
+                self.touches[index].1 = position;
+                if self.pressing {
+                    events.push(PointerEvent::Move {
+                        position,
+                        modifiers,
+                    });
+                } else if index < 2
+                    && let (Some((old_center, old_distance)), Some((center, distance))) =
+                        (before, self.pinch())
+                {
+                    events.push(PointerEvent::Move {
+                        position: center,
+                        modifiers,
+                    });
+                    // The content follows the fingers.
+                    let delta = center - old_center;
+                    if delta != Xy::ZERO {
+                        events.push(PointerEvent::Scroll {
+                            delta: ScrollDelta::Pixels(delta),
+                            modifiers,
+                        });
+                    }
+                    if old_distance > 0.0 && distance > 0.0 && distance != old_distance {
+                        events.push(PointerEvent::Zoom {
+                            factor: distance / old_distance,
+                            position: center,
+                            modifiers,
+                        });
+                    }
+                }
+            }
+            (TouchPhase::End | TouchPhase::Cancel, Some(index)) => {
+                self.touches.remove(index);
+                if core::mem::take(&mut self.pressing) {
+                    events.push(PointerEvent::Up {
+                        button: PointerButton::Primary,
+                        modifiers,
+                    });
+                }
+            }
+            (TouchPhase::Move | TouchPhase::End | TouchPhase::Cancel, None) => {}
+        }
+
+        events
+    }
+
+    /// The number of fingers touching.
+    #[inline]
+    pub fn touch_count(&self) -> usize {
+        self.touches.len()
+    }
+
+    /// The point between the first two fingers, and the distance between them.
+    fn pinch(&self) -> Option<(Xy<f32>, f32)> {
+        let [(_, a), (_, b), ..] = self.touches[..] else {
+            return None;
+        };
+
+        Some((
+            Xy::new((a.x + b.x) / 2.0, (a.y + b.y) / 2.0),
+            (b - a).length(),
+        ))
+    }
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    use {super::*, crate::Modifiers};
+
+    fn touch(id: u64, phase: TouchPhase, x: f32, y: f32) -> PointerEvent {
+        PointerEvent::Touch {
+            id,
+            phase,
+            position: Xy::new(x, y),
+            modifiers: Modifiers::default(),
+        }
+    }
+
+    #[test]
+    fn single_touch_presses_primary_button() {
+        let mut tracker = TouchTracker::default();
+
+        let events = tracker.handle(&touch(1, TouchPhase::Start, 10.0, 20.0));
+        assert!(matches!(
+            events[..],
+            [
+                PointerEvent::Move { .. },
+                PointerEvent::Down {
+                    button: PointerButton::Primary,
+                    ..
+                }
+            ]
+        ));
+        let events = tracker.handle(&touch(1, TouchPhase::Move, 15.0, 20.0));
+        assert!(matches!(
+            events[..],
+            [PointerEvent::Move { position, .. }] if position == Xy::new(15.0, 20.0)
+        ));
+        let events = tracker.handle(&touch(1, TouchPhase::End, 15.0, 20.0));
+        assert!(matches!(events[..], [PointerEvent::Up { .. }]));
+        assert_eq!(tracker.touch_count(), 0);
+    }
+
+    #[test]
+    fn two_touches_scroll_and_zoom() {
+        let mut tracker = TouchTracker::default();
+        tracker.handle(&touch(1, TouchPhase::Start, 0.0, 0.0));
+        // The second finger releases the button the first one pressed.
+        let events = tracker.handle(&touch(2, TouchPhase::Start, 10.0, 0.0));
+        assert!(matches!(events[..], [PointerEvent::Up { .. }]));
+
+        let events = tracker.handle(&touch(2, TouchPhase::Move, 20.0, 0.0));
+        let [
+            PointerEvent::Move { position, .. },
+            PointerEvent::Scroll {
+                delta: ScrollDelta::Pixels(delta),
+                ..
+            },
+            PointerEvent::Zoom { factor, .. },
+        ] = events[..]
+        else {
+            panic!("unexpected events: {events:?}");
+        };
+        assert_eq!(position, Xy::new(10.0, 0.0));
+        assert_eq!(delta, Xy::new(5.0, 0.0));
+        assert_eq!(factor, 2.0);
+
+        // Lifting a finger doesn't press the button again.
+        assert!(
+            tracker
+                .handle(&touch(2, TouchPhase::End, 20.0, 0.0))
+                .is_empty()
+        );
+        assert!(
+            tracker
+                .handle(&touch(1, TouchPhase::Move, 5.0, 0.0))
+                .is_empty()
+        );
+        assert!(
+            tracker
+                .handle(&touch(1, TouchPhase::Cancel, 5.0, 0.0))
+                .is_empty()
+        );
+        assert_eq!(tracker.touch_count(), 0);
+    }
+}
@@ -237,6 +237,11 @@ impl<'tree, T> LeavesRef<'tree, T> {
         }
     }
 
+    /// The IDs of the direct leaves.
+    pub fn ids(self) -> impl Iterator<Item = u64> + 'tree {
+        self.leaves.keys().copied()
+    }
+
     pub fn get(&self, id: impl Into<u64>) -> Option<NodeRef<'_, T>> {
         let id = id.into();
         self.leaves
@@ -0,0 +1,472 @@
+//! # Tree Views
+//!
+//! See [`TreeView`] for more information.
+
+use std::collections::{HashMap, HashSet};
+
+use crate::{
+    Aabb2D, Axis, Element, EventPass, FontFamily, FontStyle, Fonts, Key, KeyboardEvent,
+    LengthRequest, LineHeight, MeasureContext, PointerButton, PointerEvent, RenderPass, Rgba,
+    TextAlignment, TextWrapMode, Xy,
+};
+
+
+
+const INDENT: f32 = 16.0;
+const EXPANDED_LABEL: &str = "▾";
+const COLLAPSED_LABEL: &str = "▸";
+
+/// A node of a [`TreeView`], as given by its [`TreeProvider`].
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct TreeItem {
+    /// Identifies the node across calls to the provider, such as a hash of its
+    /// path.
+    pub id: u64,
+    pub label: String,
+    /// Whether the node can be expanded. Its children aren't requested until it
+    /// is.
+    pub has_children: bool,
+}
+
+impl TreeItem {
+    pub fn new(id: u64, label: impl Into<String>) -> Self {
+        Self {
+            id,
+            label: label.into(),
+            has_children: false,
+        }
+    }
+
+    /// Mark the node as having children.
+    pub fn with_children(mut self) -> Self {
+        self.has_children = true;
+        self
+    }
+}
+
+/// Provides the nodes of a [`TreeView`] as they're needed.
+///
+/// This is implemented for closures taking the parent's ID.
+pub trait TreeProvider {
+    /// The children of the node with the given ID, or the top-level nodes when
+    /// `parent` is `None`.
+    fn children(&mut self, parent: Option<u64>) -> Vec<TreeItem>;
+}
+
+impl<F: FnMut(Option<u64>) -> Vec<TreeItem>> TreeProvider for F {
+    fn children(&mut self, parent: Option<u64>) -> Vec<TreeItem> {
+        self(parent)
+    }
+}
+
+/// A hierarchy of expandable rows, such as the directories of a file system.
+///
+/// Nodes are requested from the [`TreeProvider`] when their parent is first
+/// expanded, so only the visible part of the hierarchy is ever loaded.
+///
+/// While focused, the up and down arrow keys move the selection, the right
+/// arrow expands the selected node (or moves to its first child), and the left
+/// arrow collapses it (or moves to its parent). `Enter` and `Space` toggle it.
+pub struct TreeView {
+    provider: Box<dyn TreeProvider>,
+    /// The children of each node that has been expanded, with the top-level
+    /// nodes under `None`.
+    loaded: HashMap<Option<u64>, Vec<TreeItem>>,
+    expanded: HashSet<u64>,
+    /// The nodes that are currently shown, in order.
+    rows: Vec<TreeRow>,
+    selected: Option<u64>,
+    selection_changed: bool,
+    focused: bool,
+    font_size: f32,
+    row_height: f32,
+}
+
+struct TreeRow {
+    item: TreeItem,
+    depth: usize,
+    parent: Option<u64>,
+}
+
+impl TreeView {
+    pub fn new(provider: impl TreeProvider + 'static) -> Self {
+        let mut tree_view = Self {
+            provider: Box::new(provider),
+            loaded: HashMap::new(),
+            expanded: HashSet::new(),
+            rows: Vec::new(),
+            selected: None,
+            selection_changed: false,
+            focused: false,
+            font_size: 14.0,
+            row_height: 0.0,
+        };
+        tree_view.rebuild_rows();
+
+        tree_view
+    }
+
+    pub fn with_font_size(mut self, font_size: f32) -> Self {
+        self.font_size = font_size;
+        self
+    }
+
+    /// The ID of the selected node.
+    #[inline]
+    pub fn selected(&self) -> Option<u64> {
+        self.selected
+    }
+
+    /// Returns `true` if the user has changed the selection since the last
+    /// call.
+    pub fn take_selection_changed(&mut self) -> bool {
+        std::mem::take(&mut self.selection_changed)
+    }
+
+    #[inline]
+    pub fn is_expanded(&self, id: u64) -> bool {
+        self.expanded.contains(&id)
+    }
+
+    /// Expand or collapse a node, loading its children if needed.
+    pub fn set_expanded(&mut self, id: u64, expanded: bool) {
+        let changed = if expanded {
+            self.expanded.insert(id)
+        } else {
+            self.expanded.remove(&id)
+        };
+        if changed {
+            self.rebuild_rows();
+        }
+    }
+
+    /// Forget the loaded children of a node (or the top-level nodes when `id`
+    /// is `None`), requesting them from the provider again if they're shown.
+    pub fn reload(&mut self, id: Option<u64>) {
+        self.loaded.remove(&id);
+        self.rebuild_rows();
+    }
+
+    /// The IDs of the nodes that are currently shown, in order.
+    pub fn visible_ids(&self) -> impl Iterator<Item = u64> + '_ {
+        self.rows.iter().map(|row| row.item.id)
+    }
+
+    fn rebuild_rows(&mut self) {
+        let mut rows = Vec::with_capacity(self.rows.len());
+        self.push_rows(None, 0, &mut rows);
+        self.rows = rows;
+
+        if let Some(selected) = self.selected
+            && !self.rows.iter().any(|row| row.item.id == selected)
+        {
+            self.selected = None;
+            self.selection_changed = true;
+        }
+    }
+
+    fn push_rows(&mut self, parent: Option<u64>, depth: usize, rows: &mut Vec<TreeRow>) {
+        let children = match self.loaded.get(&parent) {
+            Some(children) => children.clone(),
+            None => {
+                let children = self.provider.children(parent);
+                self.loaded.insert(parent, children.clone());
+                children
+            }
+        };
+
+        for item in children {
+            let expand = item.has_children && self.expanded.contains(&item.id);
+            let id = item.id;
+            rows.push(TreeRow {
+                item,
+                depth,
+                parent,
+            });
+            if expand {
+                self.push_rows(Some(id), depth + 1, rows);
+            }
+        }
+    }
+
+    fn selected_index(&self) -> Option<usize> {
+        let selected = self.selected?;
+        self.rows.iter().position(|row| row.item.id == selected)
+    }
+
+    fn select(&mut self, id: u64) {
+        if self.selected != Some(id) {
+            self.selected = Some(id);
+            self.selection_changed = true;
+        }
+    }
+
+    /// Handle a navigation key, returning `false` if it isn't one.
+    fn navigate(&mut self, key: Key) -> bool {
+        let Some(index) = self.selected_index() else {
+            if matches!(key, Key::ArrowUp | Key::ArrowDown)
+                && let Some(row) = self.rows.first()
+            {
+                self.select(row.item.id);
+                return true;
+            }
+            return false;
+        };
+        let row = &self.rows[index];
+        let (id, has_children, parent) = (row.item.id, row.item.has_children, row.parent);
+        let expanded = self.expanded.contains(&id);
+
+        match key {
+            Key::ArrowUp => {
+                if let Some(previous) = index.checked_sub(1) {
+                    self.select(self.rows[previous].item.id);
+                }
+            }
+            Key::ArrowDown => {
+                if let Some(next) = self.rows.get(index + 1) {
+                    self.select(next.item.id);
+                }
+            }
+            Key::ArrowRight if has_children => {
+                if !expanded {
+                    self.set_expanded(id, true);
+                } else if let Some(child) = self
+                    .rows
+                    .get(index + 1)
+                    .filter(|child| child.parent == Some(id))
+                {
+                    self.select(child.item.id);
+                }
+            }
+            Key::ArrowLeft => {
+                if expanded {
+                    self.set_expanded(id, false);
+                } else if let Some(parent) = parent {
+                    self.select(parent);
+                }
+            }
+            Key::Enter | Key::Space if has_children => {
+                self.set_expanded(id, !expanded);
+            }
+            _ => return false,
+        }
+
+        true
+    }
+
+    fn measure_text(&self, id: u64, fonts: &mut dyn Fonts, text: &str) -> Xy<f32> {
+        fonts.measure_text(
+            id,
+            text,
+            &[],
+            None,
+            self.font_size,
+            LineHeight::Relative(1.0),
+            FontFamily::Proportional,
+            FontStyle::Normal,
+            TextAlignment::Start,
+            TextWrapMode::NoWrap,
+        )
+    }
+}
+
+impl Element for TreeView {
+    fn accepts_keyboard_events(&self) -> bool {
+        true
+    }
+
+    fn accepts_focus_events(&self) -> bool {
+        true
+    }
+
+    fn render(&mut self, pass: &mut RenderPass<'_>) {
+        let bounds = pass.bounds();
+        let zoom = pass.zoom();
+        let to_physical = |area: Aabb2D| {
+            Aabb2D::from_size_position(
+                Xy::new(area.size().x * zoom, area.size().y * zoom),
+                bounds.position() + Xy::new(area.min.x * zoom, area.min.y * zoom),
+            )
+        };
+        let width = bounds.size().x / zoom;
+        let padding = pass.metric("spacing.small");
+
+        for (index, row) in self.rows.iter().enumerate() {
+            let top = index as f32 * self.row_height;
+            if self.selected == Some(row.item.id) {
+                pass.fill_quad(
+                    to_physical(Aabb2D::new(0.0, top, width, top + self.row_height)),
+                    pass.color("surface.raised"),
+                    if self.focused { 1.0 } else { 0.0 },
+                    pass.color("accent"),
+                );
+            }
+
+            for level in 0..row.depth {
+                let x = (level as f32 + 0.5) * INDENT;
+                pass.fill_quad(
+                    to_physical(Aabb2D::new(x, top, x + 1.0, top + self.row_height)),
+                    pass.color("border"),
+                    0.0,
+                    Rgba::NONE,
+                );
+            }
+
+            let indent = row.depth as f32 * INDENT;
+            let text_top = top + padding;
+            let text_bottom = top + self.row_height - padding;
+            if row.item.has_children {
+                pass.fill_text(
+                    if self.expanded.contains(&row.item.id) {
+                        EXPANDED_LABEL
+                    } else {
+                        COLLAPSED_LABEL
+                    },
+                    to_physical(Aabb2D::new(indent, text_top, indent + INDENT, text_bottom)),
+                    pass.color("text.muted"),
+                    self.font_size,
+                );
+            }
+            pass.fill_text(
+                &row.item.label,
+                to_physical(Aabb2D::new(
+                    indent + INDENT,
+                    text_top,
+                    width.max(indent + INDENT),
+                    text_bottom,
+                )),
+                pass.color("text"),
+                self.font_size,
+            );
+        }
+    }
+
+    fn measure(
+        &mut self,
+        context: &mut MeasureContext<'_>,
+        axis: Axis,
+        length_request: LengthRequest,
+        _cross_length: Option<f32>,
+    ) -> f32 {
+        let id = context.id();
+        let padding = context.metric("spacing.small");
+        let fonts = context.fonts_mut();
+        self.row_height = self.measure_text(id, fonts, COLLAPSED_LABEL).y + padding * 2.0;
+
+        match axis {
+            Axis::Horizontal => match length_request {
+                LengthRequest::FitContent(space) => space,
+                LengthRequest::MinContent | LengthRequest::MaxContent => self
+                    .rows
+                    .iter()
+                    .map(|row| {
+                        (row.depth + 1) as f32 * INDENT
+                            + self.measure_text(id, fonts, &row.item.label).x
+                    })
+                    .fold(0.0, f32::max),
+            },
+            Axis::Vertical => self.rows.len() as f32 * self.row_height,
+        }
+    }
+
+    fn on_focus(&mut self, pass: &mut EventPass<'_>, focused: bool) {
+        self.focused = focused;
+        pass.request_render();
+    }
+
+    fn on_pointer_event(&mut self, pass: &mut EventPass<'_>, event: &PointerEvent) {
+        let PointerEvent::Down {
+            button: PointerButton::Primary,
+            position,
+            ..
+        } = event
+        else {
+            return;
+        };
+        if self.row_height <= 0.0 {
+            return;
+        }
+        let position = pass.local_position(*position);
+        let Some(row) = self
+            .rows
+            .get((position.y / self.row_height).max(0.0) as usize)
+        else {
+            return;
+        };
+
+        let id = row.item.id;
+        let arrow_start = row.depth as f32 * INDENT;
+        let on_arrow = (arrow_start..arrow_start + INDENT).contains(&position.x);
+        if row.item.has_children && on_arrow {
+            self.set_expanded(id, !self.expanded.contains(&id));
+            pass.request_layout();
+        }
+        self.select(id);
+        pass.request_render();
+        pass.request_focus();
+        pass.set_handled();
+    }
+
+    fn on_keyboard_event(&mut self, pass: &mut EventPass<'_>, event: &KeyboardEvent) {
+        let KeyboardEvent::Down { key, .. } = event else {
+            return;
+        };
+        let row_count = self.rows.len();
+        if !self.navigate(*key) {
+            return;
+        }
+
+        if self.rows.len() != row_count {
+            pass.request_layout();
+        }
+        pass.request_render();
+        pass.request_scroll_into_view();
+        pass.set_handled();
+    }
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A tree where each node has two children, down to a depth of 3.
+    fn provider(parent: Option<u64>) -> Vec<TreeItem> {
+        let parent = parent.unwrap_or(0);
+        (1..=2)
+            .map(|index| {
+                let id = parent * 10 + index;
+                let item = TreeItem::new(id, id.to_string());
+                if id < 100 { item.with_children() } else { item }
+            })
+            .collect()
+    }
+
+    #[test]
+    fn keyboard_navigation_expands_lazily() {
+        let mut requests = Vec::new();
+        let mut tree_view = TreeView::new(move |parent| {
+            requests.push(parent);
+            assert_eq!(requests.iter().filter(|id| **id == parent).count(), 1);
+            provider(parent)
+        });
+        assert_eq!(tree_view.visible_ids().collect::<Vec<_>>(), [1, 2]);
+
+        assert!(tree_view.navigate(Key::ArrowDown));
+        assert_eq!(tree_view.selected(), Some(1));
+        assert!(tree_view.navigate(Key::ArrowRight));
+        assert_eq!(tree_view.visible_ids().collect::<Vec<_>>(), [1, 11, 12, 2]);
+        assert!(tree_view.navigate(Key::ArrowRight));
+        assert_eq!(tree_view.selected(), Some(11));
+
+        // Moving to the parent and collapsing it hides, but doesn't forget, the
+        // children.
+        assert!(tree_view.navigate(Key::ArrowLeft));
+        assert_eq!(tree_view.selected(), Some(1));
+        assert!(tree_view.navigate(Key::ArrowLeft));
+        assert_eq!(tree_view.visible_ids().collect::<Vec<_>>(), [1, 2]);
+        assert!(tree_view.navigate(Key::Enter));
+        assert_eq!(tree_view.visible_ids().collect::<Vec<_>>(), [1, 11, 12, 2]);
+    }
+}
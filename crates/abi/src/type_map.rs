@@ -67,6 +67,11 @@ impl<V> TypeMap<V> {
         self.map.get_mut(&TypeId::of::<K>())
     }
 
+    /// Remove the associated value `V` for `K`, returning it if there was one.
+    pub fn remove<K: ?Sized + 'static>(&mut self) -> Option<V> {
+        self.map.remove(&TypeId::of::<K>())
+    }
+
     /// Gets the given key’s corresponding [entry](TypeMapEntry) in this map for
     /// in-place manipulation.
     pub fn entry<K: ?Sized + 'static>(&mut self) -> TypeMapEntry<'_, V> {
@@ -154,6 +159,10 @@ mod tests {
         assert!(map.get::<B>().is_some_and(|i| *i == 2));
         assert!(map.get::<C>().is_some_and(|i| *i == 3));
 
-        assert!(map.drain().count() == 3);
+        assert_eq!(map.remove::<B>(), Some(2));
+        assert!(!map.has::<B>());
+        assert_eq!(map.remove::<B>(), None);
+
+        assert!(map.drain().count() == 2);
     }
 }
@@ -0,0 +1,232 @@
+//! # Undo and Redo
+//!
+//! Text editing elements record each change to their text in an
+//! [`EditHistory`], which can then step backwards and forwards through them.
+//! Consecutive characters typed in a row are merged into a single edit, so that
+//! undoing removes a whole word rather than one character at a time.
+
+use std::{collections::VecDeque, ops::Range};
+
+
+
+/// A single change to some text, replacing the `removed` text at `offset` with
+/// the `inserted` text.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct TextEdit {
+    /// The byte offset the edit starts at.
+    pub offset: usize,
+    pub removed: String,
+    pub inserted: String,
+    /// Where the cursor was before the edit, which is restored when undoing it.
+    pub cursor_before: usize,
+}
+
+impl TextEdit {
+    /// Replace the given range of `text` with `inserted`, returning the edit
+    /// that did so.
+    pub fn apply(
+        text: &mut String,
+        range: Range<usize>,
+        inserted: &str,
+        cursor_before: usize,
+    ) -> Self {
+        let removed = text[range.clone()].to_string();
+        text.replace_range(range.clone(), inserted);
+
+        Self {
+            offset: range.start,
+            removed,
+            inserted: inserted.to_string(),
+            cursor_before,
+        }
+    }
+
+    /// Where the cursor is after the edit.
+    #[inline]
+    pub fn cursor_after(&self) -> usize {
+        self.offset + self.inserted.len()
+    }
+
+    fn is_insertion(&self) -> bool {
+        self.removed.is_empty() && !self.inserted.is_empty()
+    }
+}
+
+/// The edits made to some text, for undoing and redoing them.
+#[derive(Clone, Debug)]
+pub struct EditHistory {
+    undo_stack: VecDeque<TextEdit>,
+    redo_stack: Vec<TextEdit>,
+    depth: usize,
+    /// Whether the next insertion can be merged into the last edit.
+    coalescing: bool,
+}
+
+impl Default for EditHistory {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl EditHistory {
+    /// The number of edits kept by [`EditHistory::new`].
+    pub const DEFAULT_DEPTH: usize = 100;
+
+    pub fn new() -> Self {
+        Self::with_depth(Self::DEFAULT_DEPTH)
+    }
+
+    /// Keep at most `depth` edits, forgetting the oldest ones first.
+    pub fn with_depth(depth: usize) -> Self {
+        Self {
+            undo_stack: VecDeque::new(),
+            redo_stack: Vec::new(),
+            depth,
+            coalescing: false,
+        }
+    }
+
+    #[inline]
+    pub fn depth(&self) -> usize {
+        self.depth
+    }
+
+    pub fn set_depth(&mut self, depth: usize) {
+        self.depth = depth;
+        while self.undo_stack.len() > depth {
+            self.undo_stack.pop_front();
+        }
+    }
+
+    #[inline]
+    pub fn can_undo(&self) -> bool {
+        !self.undo_stack.is_empty()
+    }
+
+    #[inline]
+    pub fn can_redo(&self) -> bool {
+        !self.redo_stack.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.undo_stack.clear();
+        self.redo_stack.clear();
+        self.coalescing = false;
+    }
+
+    /// Add an edit that has just been made, which clears anything that could be
+    /// redone.
+    pub fn record(&mut self, edit: TextEdit) {
+        self.redo_stack.clear();
+
+        if self.coalescing
+            && edit.is_insertion()
+            && let Some(last) = self.undo_stack.back_mut()
+            && last.is_insertion()
+            && last.cursor_after() == edit.offset
+            // NOTE: Typing after a space starts a new word, and so a new edit.
+            && !last.inserted.ends_with(char::is_whitespace)
+        {
+            last.inserted.push_str(&edit.inserted);
+            return;
+        }
+
+        self.coalescing = edit.is_insertion();
+        if self.depth == 0 {
+            return;
+        }
+        if self.undo_stack.len() >= self.depth {
+            self.undo_stack.pop_front();
+        }
+        self.undo_stack.push_back(edit);
+    }
+
+    /// Stop the next insertion from being merged into the last edit, such as
+    /// after the cursor is moved.
+    pub fn break_coalescing(&mut self) {
+        self.coalescing = false;
+    }
+
+    /// Revert the last edit to `text`, returning where the cursor should be
+    /// placed, or `None` if there is nothing to undo.
+    pub fn undo(&mut self, text: &mut String) -> Option<usize> {
+        let edit = self.undo_stack.pop_back()?;
+        text.replace_range(edit.offset..edit.cursor_after(), &edit.removed);
+        let cursor = edit.cursor_before;
+        self.redo_stack.push(edit);
+        self.coalescing = false;
+
+        Some(cursor)
+    }
+
+    /// Make the last undone edit to `text` again, returning where the cursor
+    /// should be placed, or `None` if there is nothing to redo.
+    pub fn redo(&mut self, text: &mut String) -> Option<usize> {
+        let edit = self.redo_stack.pop()?;
+        text.replace_range(
+            edit.offset..edit.offset + edit.removed.len(),
+            &edit.inserted,
+        );
+        let cursor = edit.cursor_after();
+        self.undo_stack.push_back(edit);
+        self.coalescing = false;
+
+        Some(cursor)
+    }
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn type_str(history: &mut EditHistory, text: &mut String, typed: &str) {
+        for ch in typed.chars() {
+            let offset = text.len();
+            let edit = TextEdit::apply(text, offset..offset, ch.encode_utf8(&mut [0; 4]), offset);
+            history.record(edit);
+        }
+    }
+
+    #[test]
+    fn undo_redo_coalesces_words() {
+        let mut history = EditHistory::new();
+        let mut text = String::new();
+
+        type_str(&mut history, &mut text, "hello world");
+        assert_eq!(text, "hello world");
+
+        assert_eq!(history.undo(&mut text), Some(6));
+        assert_eq!(text, "hello ");
+        assert_eq!(history.undo(&mut text), Some(0));
+        assert_eq!(text, "");
+        assert_eq!(history.undo(&mut text), None);
+
+        assert_eq!(history.redo(&mut text), Some(6));
+        assert_eq!(text, "hello ");
+
+        // Editing after undoing forgets what could be redone.
+        let edit = TextEdit::apply(&mut text, 0..5, "bye", 5);
+        history.record(edit);
+        assert_eq!(text, "bye ");
+        assert!(!history.can_redo());
+        assert_eq!(history.undo(&mut text), Some(5));
+        assert_eq!(text, "hello ");
+    }
+
+    #[test]
+    fn history_depth() {
+        let mut history = EditHistory::with_depth(2);
+        let mut text = String::new();
+        for word in ["a ", "b ", "c "] {
+            type_str(&mut history, &mut text, word);
+            history.break_coalescing();
+        }
+
+        assert!(history.undo(&mut text).is_some());
+        assert!(history.undo(&mut text).is_some());
+        assert!(history.undo(&mut text).is_none());
+        assert_eq!(text, "a ");
+    }
+}
@@ -1,20 +1,31 @@
 //! # View
 
 use std::{
-    any::Any,
-    collections::{HashMap, HashSet},
-    fmt::Debug,
+    any::{Any, TypeId},
+    collections::{HashMap, HashSet, VecDeque},
+    fmt::{self, Debug, Write as _},
+    hash::{DefaultHasher, Hash, Hasher},
     marker::PhantomData,
-    ops::{Deref, DerefMut},
+    ops::{Deref, DerefMut, Range},
     sync::{
         Arc,
         atomic::{AtomicU64, Ordering},
+        mpsc,
     },
-    time::Instant,
+    time::{Duration, Instant},
 };
 
 use crate::{
-    Aabb2D, Axis, CursorIcon, FontStyle, LineHeight, TextAlignment, TextWrapMode, Transform2D, Xy,
+    Aabb2D, Axis, ContextMenu, CrossAlignment, CursorIcon, EditHistory, FontFamily, FontStyle,
+    FontWeight, LineHeight, PathSegment, StyledText, TextAlignment, TextDecoration, TextDirection,
+    TextEdit, TextParams, TextSpan, TextStyle, TextWrapMode, Transform2D, TypeMap, Xy, bidi,
+    context_menu::ContextMenuPopup,
+    drag::{Drag, DragGhost},
+    shortcut::{KeyChord, ShortcutMap, ShortcutResult},
+    system::{self, OpenUrlError},
+    text,
+    theme::{ColorMode, Theme},
+    touch::TouchTracker,
     tree,
 };
 
@@ -39,7 +50,7 @@ impl Length {
     }
 }
 
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, PartialEq)]
 #[repr(C)]
 pub enum LengthRequest {
     MaxContent,
@@ -57,7 +68,7 @@ impl Into<Length> for LengthRequest {
     }
 }
 
-#[derive(Clone, Copy, Debug, PartialEq)]
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
 #[repr(C)]
 pub struct Rgba {
     pub r: u8,
@@ -114,21 +125,59 @@ pub struct View {
     root_element_id: u64,
     window_size: Xy<f32>,
     render_cache: HashMap<u64, (CachedRender, CachedRender)>,
+    render_cache_stats: RenderCacheStats,
+    theme: Theme,
+    zoom: f32,
+    /// Whether zoom changes are saved to the application's settings, see
+    /// [`View::load_zoom_setting`].
+    persist_zoom: bool,
+    modifiers: Modifiers,
     pointer_position: Option<Xy<f32>>,
     pointer_capture_target: Option<u64>,
+    pointer_capture_held: bool,
+    gesture_settings: GestureSettings,
+    gesture_state: GestureState,
+    touch_tracker: TouchTracker,
     hovered_path: Vec<u64>,
     cursor_icon: CursorIcon,
     focused_element: Option<u64>,
     next_focused_element: Option<u64>,
     focused_path: Vec<u64>,
     last_animation: Option<Instant>,
+    /// The time between frames that animations are throttled to, see
+    /// [`View::set_refresh_rate`].
+    refresh_interval: Option<Duration>,
+    /// The timestamp of the frame in progress, see [`View::begin_frame`].
+    frame_timestamp: Option<Instant>,
+    recording: Option<Recording>,
+    /// Whether element bounds are drawn above everything, see
+    /// [`View::set_debug_overlay`].
+    debug_overlay: bool,
+    popups: Vec<Popup>,
+    popup_requests: Vec<PopupRequest>,
+    /// Elements to scroll into view once the current event has been handled.
+    scroll_requests: Vec<u64>,
+    /// The actions chosen from context menus, along with the ID of the element
+    /// that provided each menu.
+    context_menu_actions: Vec<(u64, u64)>,
+    drag: Option<ActiveDrag>,
+    drag_request: Option<Drag>,
+    /// Damage from elements that were removed since the last render.
+    pending_damage: Option<Aabb2D>,
+    /// Events waiting to be taken by [`View::poll_events`].
+    events: VecDeque<ViewEvent>,
+    /// Kept so that [`UpdateSender`]s can be made at any time.
+    update_sender: mpsc::Sender<UpdateMessage>,
+    update_receiver: mpsc::Receiver<UpdateMessage>,
+    timers: Vec<Timer>,
 }
 
 impl View {
     pub fn new(root_builder: ElementBuilder, fonts: Box<dyn Fonts>, window_size: Xy<f32>) -> Self {
         let mut tree = tree::Tree::new();
 
-        let Some(ElementBuilder { id, element }) = root_builder.into_child().take_inner() else {
+        let Some(ElementBuilder { id, element, .. }) = root_builder.into_child().take_inner()
+        else {
             unreachable!();
         };
 
@@ -136,6 +185,7 @@ impl View {
         let info = ElementInfo { element, state };
 
         tree.roots_mut().insert(id, info);
+        let (update_sender, update_receiver) = mpsc::channel();
 
         let mut this = Self {
             fonts,
@@ -143,14 +193,38 @@ impl View {
             root_element_id: id,
             window_size,
             render_cache: HashMap::new(),
+            render_cache_stats: RenderCacheStats::default(),
+            theme: Theme::default(),
+            zoom: 1.0,
+            persist_zoom: false,
+            modifiers: Modifiers::default(),
             pointer_position: None,
             pointer_capture_target: None,
+            pointer_capture_held: false,
+            gesture_settings: GestureSettings::default(),
+            gesture_state: GestureState::default(),
+            touch_tracker: TouchTracker::default(),
             hovered_path: Vec::new(),
             cursor_icon: CursorIcon::Default,
             focused_element: None,
             next_focused_element: None,
             focused_path: Vec::new(),
             last_animation: None,
+            refresh_interval: None,
+            frame_timestamp: None,
+            recording: None,
+            debug_overlay: false,
+            popups: Vec::new(),
+            popup_requests: Vec::new(),
+            scroll_requests: Vec::new(),
+            context_menu_actions: Vec::new(),
+            drag: None,
+            drag_request: None,
+            pending_damage: None,
+            events: VecDeque::new(),
+            update_sender,
+            update_receiver,
+            timers: Vec::new(),
         };
 
         update_pass(&mut this);
@@ -161,1249 +235,1824 @@ impl View {
     }
 
     #[inline]
-    pub fn cursor_icon(&self) -> CursorIcon {
-        self.cursor_icon
+    pub fn debug_overlay(&self) -> bool {
+        self.debug_overlay
+    }
+
+    /// Draw every element's bounds, ID, size and baseline above the rest of the
+    /// view, highlighting the hovered and focused elements. This can also be
+    /// toggled with `Ctrl+Shift+I`.
+    pub fn set_debug_overlay(&mut self, enabled: bool) {
+        if self.debug_overlay != enabled {
+            self.debug_overlay = enabled;
+            self.pending_damage = Some(union_damage(
+                self.pending_damage,
+                Aabb2D::from_size(self.window_size),
+            ));
+        }
     }
 
-    pub fn animating(&self) -> bool {
-        self.tree
-            .roots()
-            .get(self.root_element_id)
-            .expect("infallible")
-            .element
-            .state
-            .needs_animate
+    /// A textual snapshot of the element tree, with one line per element
+    /// showing its type, ID, bounds, baseline and whether it's hovered or
+    /// focused. Each popup's tree follows the main one.
+    pub fn dump_tree(&self) -> String {
+        let mut output = String::new();
+        for id in self.layer_root_ids() {
+            let node = self
+                .tree
+                .find(id)
+                .expect("failed to find a layer's root node");
+            dump_element(node, 0, &mut output);
+        }
+
+        output
     }
 
-    pub fn resize_window(&mut self, size: Xy<f32>) {
-        if self.window_size == size {
+    /// Begin recording every event and pass this view executes, along with a
+    /// snapshot of each element's dirty flags after it runs. Any previous
+    /// recording is discarded.
+    pub fn start_recording(&mut self) {
+        self.recording = Some(Recording::default());
+    }
+
+    /// Stop recording, returning everything recorded since
+    /// [`View::start_recording`].
+    pub fn stop_recording(&mut self) -> Option<Recording> {
+        self.recording.take()
+    }
+
+    #[inline]
+    pub fn recording(&self) -> Option<&Recording> {
+        self.recording.as_ref()
+    }
+
+    fn record(&mut self, kind: RecordedKind) {
+        if self.recording.is_none() {
             return;
         }
-        self.window_size = size;
 
-        layout_pass(self);
+        let mut elements = Vec::new();
+        for id in self.layer_root_ids() {
+            snapshot_element(
+                self.tree
+                    .find(id)
+                    .expect("failed to find a layer's root node"),
+                &mut elements,
+            );
+        }
+
+        if let Some(recording) = &mut self.recording {
+            recording.push(kind, elements);
+        }
     }
 
-    pub fn render(&mut self, render: &mut Render) {
-        let now = Instant::now();
-        let last = self.last_animation.take();
-        let elapsed = last.map(|t| now.duration_since(t)).unwrap_or_default();
+    #[inline]
+    pub fn cursor_icon(&self) -> CursorIcon {
+        self.cursor_icon
+    }
 
-        animation_pass(self, elapsed.as_secs_f64());
+    /// Take the interaction events that have happened since the last call,
+    /// oldest first.
+    ///
+    /// Only the most recent [`MAX_QUEUED_VIEW_EVENTS`] are kept, so hosts that
+    /// don't poll every frame may miss some.
+    pub fn poll_events(&mut self) -> impl Iterator<Item = ViewEvent> + '_ {
+        self.events.drain(..)
+    }
 
-        self.last_animation = self.animating().then_some(now);
+    fn push_event(&mut self, event: ViewEvent) {
+        if self.events.len() >= MAX_QUEUED_VIEW_EVENTS {
+            self.events.pop_front();
+        }
+        self.events.push_back(event);
+    }
 
-        render_pass(self, render);
+    /// The [`Element::debug_name`] of the element with the given ID, such as to
+    /// describe the target of a [`ViewEvent`].
+    pub fn element_debug_name(&self, element_id: u64) -> Option<&'static str> {
+        self.tree
+            .find(element_id)
+            .map(|node| node.element.element.debug_name())
     }
 
-    pub fn handle_keyboard_event(&mut self, event: KeyboardEvent) {
-        keyboard_event_pass(self, &event);
-        layout_pass(self);
-        compose_pass(self);
+    /// The bounds of the element with the given ID in the window, as of the
+    /// last compose pass.
+    pub fn element_bounds(&self, element_id: u64) -> Option<Aabb2D> {
+        self.tree
+            .find(element_id)
+            .map(|node| node.element.state.bounds)
+    }
+
+    /// Make a sender for messages to the element with the given ID, such as for
+    /// the host to pass results from its own threads to the application.
+    /// Elements can get one for themselves with
+    /// [`EventPass::update_sender`] or [`UpdatePass::update_sender`].
+    pub fn update_sender(&self, element_id: u64) -> UpdateSender {
+        UpdateSender {
+            element_id,
+            sender: self.update_sender.clone(),
+        }
     }
 
-    pub fn handle_pointer_event(&mut self, event: PointerEvent) {
-        pointer_event_pass(self, &event);
-        update_pointer_pass(self);
+    /// Deliver the messages sent with [`UpdateSender`]s since the last call
+    /// (see [`Element::on_message`]), returning the number that were
+    /// delivered.
+    ///
+    /// Hosts should call this before each render. Messages to elements that
+    /// have since been removed are dropped.
+    pub fn process_updates(&mut self) -> usize {
+        let mut delivered = 0;
+        while let Ok((element_id, message)) = self.update_receiver.try_recv() {
+            if self.tree.find(element_id).is_none() {
+                continue;
+            }
+            let mut message = Some(message);
+            single_event_pass(self, Some(element_id), |element, pass| {
+                if let Some(message) = message.take() {
+                    element.on_message(pass, message);
+                }
+            });
+            delivered += 1;
+        }
+        if delivered == 0 {
+            return 0;
+        }
+
+        self.apply_popup_requests();
+        update_pass(self);
         update_focus_pass(self);
         layout_pass(self);
+        self.record(RecordedKind::LayoutPass);
         compose_pass(self);
-    }
-}
-
-pub trait Fonts {
-    fn measure_text(
-        &mut self,
-        id: u64,
-        text: &str,
-        max_advance: Option<f32>,
-        font_size: f32,
-        line_height: LineHeight,
-        font_style: FontStyle,
-        alignment: TextAlignment,
-        wrap_mode: TextWrapMode,
-    ) -> Xy<f32>;
-}
+        self.record(RecordedKind::ComposePass);
+        if self.apply_scroll_requests() {
+            compose_pass(self);
+            self.record(RecordedKind::ComposePass);
+        }
 
-pub trait Element: Any {
-    fn children_ids(&self) -> Vec<u64> {
-        Vec::new()
+        delivered
     }
 
-    #[allow(unused)]
-    fn update_children(&mut self, pass: &mut UpdatePass<'_>) {}
+    /// Get statistics about the render cache since the view was created, or
+    /// since the last call to [`View::reset_render_cache_stats`].
+    pub fn render_cache_stats(&self) -> RenderCacheStats {
+        RenderCacheStats {
+            entries: self.render_cache.len(),
+            ..self.render_cache_stats
+        }
+    }
 
-    /// Defaults to `true`.
-    fn accepts_pointer_events(&self) -> bool {
-        true
+    pub fn reset_render_cache_stats(&mut self) {
+        self.render_cache_stats = RenderCacheStats::default();
     }
 
-    /// Defaults to `false`.
-    fn accepts_keyboard_events(&self) -> bool {
-        false
+    #[inline]
+    pub fn theme(&self) -> &Theme {
+        &self.theme
     }
 
-    /// Defaults to `false`.
-    fn accepts_focus_events(&self) -> bool {
-        false
+    /// The size of the window, in physical pixels.
+    #[inline]
+    pub fn window_size(&self) -> Xy<f32> {
+        self.window_size
     }
 
-    #[allow(unused)]
-    fn render(&mut self, pass: &mut RenderPass<'_>) {}
+    /// The factor that everything in the view is scaled by, independent of the
+    /// display's scale. Defaults to `1.0`.
+    #[inline]
+    pub fn zoom(&self) -> f32 {
+        self.zoom
+    }
 
-    #[allow(unused)]
-    fn render_overlay(&mut self, pass: &mut RenderPass<'_>) {}
+    /// Set the zoom factor, clamped to the range of [`ZOOM_LEVELS`].
+    pub fn set_zoom(&mut self, zoom: f32) {
+        let zoom = zoom.clamp(ZOOM_LEVELS[0], ZOOM_LEVELS[ZOOM_LEVELS.len() - 1]);
+        if zoom == self.zoom || zoom.is_nan() {
+            return;
+        }
+        self.zoom = zoom;
 
-    #[allow(unused)]
-    fn animate(&mut self, pass: &mut AnimatePass<'_>, dt: f64) {}
+        if self.persist_zoom {
+            system::set_setting(ZOOM_SETTING, &zoom.to_string());
+        }
 
-    #[allow(unused)]
-    fn layout(&mut self, pass: &mut LayoutPass<'_>) {}
+        // NOTE: Elements that aren't laid out (such as exiting ones) still need to be
+        //       composed with the new scale.
+        for id in self.layer_root_ids().collect::<Vec<_>>() {
+            let node = self
+                .tree
+                .find_mut(id)
+                .expect("failed to find a layer's root node");
+            node.element.state.transformed = true;
+            // Text is shaped at the zoomed size, so everything needs to be laid out again.
+            for_each_element_state_mut(node, &mut request_full_layout);
+        }
+        layout_pass(self);
+        self.record(RecordedKind::LayoutPass);
+        compose_pass(self);
+        self.record(RecordedKind::ComposePass);
+    }
 
-    fn measure(
-        &mut self,
-        context: &mut MeasureContext<'_>,
-        axis: Axis,
-        length_request: LengthRequest,
-        cross_length: Option<f32>,
-    ) -> f32;
+    /// Move to the next of the [`ZOOM_LEVELS`] (bound to `Ctrl+=`).
+    pub fn zoom_in(&mut self) {
+        if let Some(zoom) = ZOOM_LEVELS.iter().find(|zoom| **zoom > self.zoom + 0.001) {
+            self.set_zoom(*zoom);
+        }
+    }
 
-    #[allow(unused)]
-    fn compose(&mut self, pass: &mut ComposePass<'_>) {}
+    /// Move to the previous of the [`ZOOM_LEVELS`] (bound to `Ctrl+-`).
+    pub fn zoom_out(&mut self) {
+        if let Some(zoom) = ZOOM_LEVELS
+            .iter()
+            .rev()
+            .find(|zoom| **zoom < self.zoom - 0.001)
+        {
+            self.set_zoom(*zoom);
+        }
+    }
 
-    fn cursor_icon(&self) -> CursorIcon {
-        CursorIcon::Default
+    /// Reset the zoom to `1.0` (bound to `Ctrl+0`).
+    pub fn reset_zoom(&mut self) {
+        self.set_zoom(1.0);
     }
 
-    /// Called when this element is added to the view tree.
-    #[allow(unused)]
-    fn on_build(&mut self, pass: &mut UpdatePass<'_>) {}
+    /// Restore the zoom saved in the application's settings, and save it there
+    /// whenever it changes from now on.
+    ///
+    /// This requires the view to be hosted, see [`system::setting`].
+    pub fn load_zoom_setting(&mut self) {
+        self.persist_zoom = false;
+        if let Some(zoom) = system::setting(ZOOM_SETTING).and_then(|zoom| zoom.parse().ok()) {
+            self.set_zoom(zoom);
+        }
+        self.persist_zoom = true;
+    }
 
-    /// Called when this element is interacted with by the user's keyboard.
-    #[allow(unused)]
-    fn on_keyboard_event(&mut self, pass: &mut EventPass<'_>, event: &KeyboardEvent) {}
+    fn logical_window_size(&self) -> Xy<f32> {
+        Xy::new(
+            self.window_size.x / self.zoom,
+            self.window_size.y / self.zoom,
+        )
+    }
 
-    /// Called when this element is interacted with by the user's pointer.
-    #[allow(unused)]
-    fn on_pointer_event(&mut self, pass: &mut EventPass<'_>, event: &PointerEvent) {}
+    /// Update the state of the modifier keys, such as when they change without
+    /// any other key being pressed. Each [`KeyboardEvent`] and [`PointerEvent`]
+    /// also updates it with the modifiers it carries.
+    pub fn set_modifiers(&mut self, modifiers: Modifiers) {
+        self.modifiers = modifiers;
+    }
 
-    #[allow(unused)]
-    fn on_hover(&mut self, pass: &mut EventPass<'_>, hovered: bool) {}
+    #[inline]
+    pub fn modifiers(&self) -> Modifiers {
+        self.modifiers
+    }
 
-    #[allow(unused)]
-    fn on_child_hover(&mut self, pass: &mut EventPass<'_>, hovered: bool) {}
+    #[inline]
+    pub fn gesture_settings(&self) -> GestureSettings {
+        self.gesture_settings
+    }
 
-    #[allow(unused)]
-    fn on_focus(&mut self, pass: &mut EventPass<'_>, focused: bool) {}
+    /// Change how pointer events are recognized as gestures, see
+    /// [`Element::on_gesture`].
+    pub fn set_gesture_settings(&mut self, settings: GestureSettings) {
+        self.gesture_settings = settings;
+    }
 
-    #[allow(unused)]
-    fn on_child_focus(&mut self, pass: &mut EventPass<'_>, focused: bool) {}
-}
+    /// Handle the view's own keyboard shortcuts, returning `true` if the event
+    /// was used.
+    fn handle_view_shortcut(&mut self, event: &KeyboardEvent) -> bool {
+        let KeyboardEvent::Down {
+            key: Key::Char(ch), ..
+        } = event
+        else {
+            return false;
+        };
+        if !self.modifiers.ctrl || self.modifiers.alt || self.modifiers.meta {
+            return false;
+        }
 
-pub struct ElementInfo {
-    pub element: Box<dyn Element>,
-    pub state: ElementState,
-}
+        match ch {
+            // Holding the shortcut would make the overlay flicker.
+            'i' | 'I' if self.modifiers.shift => {
+                if !event.is_repeat() {
+                    self.set_debug_overlay(!self.debug_overlay);
+                }
+            }
+            '=' | '+' => self.zoom_in(),
+            '-' => self.zoom_out(),
+            '0' => self.reset_zoom(),
+            _ => return false,
+        }
 
-impl Deref for ElementInfo {
-    type Target = dyn Element;
+        true
+    }
 
-    #[inline]
-    fn deref(&self) -> &Self::Target {
-        &*self.element
+    /// Switch between light and dark mode, re-rendering everything.
+    pub fn set_color_mode(&mut self, mode: ColorMode) {
+        if self.theme.mode() != mode {
+            self.update_theme(|theme| theme.set_mode(mode));
+        }
     }
-}
 
-impl DerefMut for ElementInfo {
-    #[inline]
-    fn deref_mut(&mut self) -> &mut Self::Target {
-        &mut *self.element
+    /// Modify the theme (such as to override tokens for this application),
+    /// laying out and re-rendering everything.
+    pub fn update_theme(&mut self, f: impl FnOnce(&mut Theme)) {
+        f(&mut self.theme);
+
+        // NOTE: Content hashes don't cover the theme, so cached commands can't be
+        // reused.
+        for (render, overlay_render) in self.render_cache.values_mut() {
+            render.key = None;
+            overlay_render.key = None;
+        }
+        for id in self.layer_root_ids().collect::<Vec<_>>() {
+            let node = self
+                .tree
+                .find_mut(id)
+                .expect("failed to find a layer's root node");
+            // NOTE: Measurements can depend on the theme's metrics, so cached ones
+            //       can't be reused either.
+            for_each_element_state_mut(node, &mut |state| {
+                state.measure_cache.clear();
+                request_full_layout(state);
+                state.needs_render = true;
+                state.wants_render = true;
+                state.wants_overlay_render = true;
+            });
+        }
+        layout_pass(self);
+        self.record(RecordedKind::LayoutPass);
+        compose_pass(self);
+        self.record(RecordedKind::ComposePass);
     }
-}
 
-pub struct ElementState {
-    pub id: u64,
+    pub fn animating(&self) -> bool {
+        self.layer_root_ids().any(|id| {
+            self.tree
+                .roots()
+                .get(id)
+                .expect("infallible")
+                .element
+                .state
+                .needs_animate
+        })
+    }
 
-    pub bounds: Aabb2D,
-    pub baseline_offset: f32,
-    pub layout_bounds: Aabb2D,
-    pub layout_baseline_offset: f32,
+    /// Open a popup, see [`EventPass::open_popup`].
+    pub fn open_popup(&mut self, builder: ElementBuilder, options: PopupOptions) -> PopupId {
+        let id = PopupId(builder.id);
+        self.popup_requests
+            .push(PopupRequest::Open { builder, options });
+        self.apply_popup_requests();
+        layout_pass(self);
+        compose_pass(self);
 
-    pub scroll_translation: Xy<f32>,
-    pub local_transform: Transform2D,
-    pub global_transform: Transform2D,
+        id
+    }
 
-    pub newly_added: bool,
-    pub children_changed: bool,
+    /// Close a popup, returning `false` if it wasn't open.
+    pub fn close_popup(&mut self, id: PopupId) -> bool {
+        if !self.popups.iter().any(|popup| popup.id == id.0) {
+            return false;
+        }
+        self.popup_requests.push(PopupRequest::Close(id));
+        self.apply_popup_requests();
+        update_pointer_pass(self);
+        update_focus_pass(self);
 
-    pub needs_render: bool,
-    pub wants_render: bool,
-    pub wants_overlay_render: bool,
+        true
+    }
 
-    pub needs_animate: bool,
-    pub wants_animate: bool,
+    /// Scroll every scroll container above the given element so that it's
+    /// visible, returning `false` if there is no element with the given ID.
+    ///
+    /// See [`EventPass::request_scroll_into_view`].
+    pub fn scroll_to(&mut self, element_id: u64) -> bool {
+        if self.tree.find(element_id).is_none() {
+            return false;
+        }
+        self.scroll_requests.push(element_id);
+        self.apply_scroll_requests();
+        compose_pass(self);
 
-    pub needs_layout: bool,
-    pub wants_layout: bool,
+        true
+    }
 
-    pub needs_compose: bool,
-    pub wants_compose: bool,
-    pub transformed: bool,
+    /// Pass the bounds of each element that requested to be scrolled into view
+    /// up through its ancestors, returning `true` if there were any
+    /// requests.
+    ///
+    /// This relies on the bounds from the last compose pass, so it should be
+    /// followed by another.
+    fn apply_scroll_requests(&mut self) -> bool {
+        let requests = std::mem::take(&mut self.scroll_requests);
+        for id in &requests {
+            let Some(node) = self.tree.find(*id) else {
+                continue;
+            };
+            let mut area = node.element.state.bounds;
+            let ancestor_ids = self.tree.branches().get_id_path(*id, None);
+            for ancestor_id in ancestor_ids.into_iter().skip(1) {
+                single_event_pass(self, Some(ancestor_id), |element, pass| {
+                    element.on_scroll_into_view(pass, &mut area);
+                });
+            }
+        }
 
-    pub hovered: bool,
-    pub focused: bool,
-}
+        !requests.is_empty()
+    }
 
-impl ElementState {
-    /// See [`UpdatePass::update_child`].
-    fn new(id: u64) -> Self {
-        Self {
-            id,
-            bounds: Aabb2D::ZERO,
-            baseline_offset: 0.0,
-            layout_bounds: Aabb2D::ZERO,
-            layout_baseline_offset: 0.0,
-            scroll_translation: Xy::ZERO,
-            local_transform: Transform2D::IDENTITY,
-            global_transform: Transform2D::IDENTITY,
-            newly_added: true,
-            children_changed: true,
-            needs_render: true,
-            wants_render: true,
-            wants_overlay_render: true,
-            needs_animate: true,
-            wants_animate: true,
-            needs_layout: true,
-            wants_layout: true,
-            needs_compose: true,
-            wants_compose: true,
-            transformed: true,
-            hovered: false,
-            focused: false,
+    /// Whether a drag started with [`EventPass::start_drag`] is in progress.
+    #[inline]
+    pub fn is_dragging(&self) -> bool {
+        self.drag.is_some()
+    }
+
+    /// Iterate over the currently open popups, from bottom to top.
+    pub fn popups(&self) -> impl DoubleEndedIterator<Item = PopupId> {
+        self.popups.iter().map(|popup| PopupId(popup.id))
+    }
+
+    /// The root element of each layer of the view, from bottom to top.
+    fn layer_root_ids(&self) -> impl DoubleEndedIterator<Item = u64> + use<'_> {
+        std::iter::once(self.root_element_id).chain(self.popups.iter().map(|popup| popup.id))
+    }
+
+    fn apply_popup_requests(&mut self) {
+        for request in std::mem::take(&mut self.popup_requests) {
+            match request {
+                PopupRequest::Open { builder, options } => {
+                    let ElementBuilder { id, element, .. } = builder;
+                    let state = ElementState::new(id);
+                    self.tree
+                        .roots_mut()
+                        .insert(id, ElementInfo { element, state });
+                    self.popups.push(Popup {
+                        id,
+                        position: options.position,
+                        modal: options.modal,
+                    });
+
+                    let mut removed_ids = Vec::new();
+                    update_element_tree(
+                        self.tree.find_mut(id).expect("infallible"),
+                        &mut removed_ids,
+                        &self.update_sender,
+                        &mut self.timers,
+                    );
+                    self.forget_elements(removed_ids);
+                }
+                PopupRequest::Close(PopupId(id)) => {
+                    let Some(index) = self.popups.iter().position(|popup| popup.id == id) else {
+                        continue;
+                    };
+                    self.popups.remove(index);
+
+                    let mut removed_ids = Vec::new();
+                    collect_element_ids(
+                        self.tree
+                            .find(id)
+                            .expect("popup root should be in the tree"),
+                        &mut removed_ids,
+                    );
+                    self.tree.roots_mut().remove(id);
+                    self.forget_elements(removed_ids);
+                }
+            }
         }
     }
 
-    fn merge_with_child(&mut self, child_state: &Self) {
-        self.children_changed |= child_state.children_changed;
-        self.needs_render |= child_state.needs_render;
-        self.needs_animate |= child_state.needs_animate;
-        self.needs_layout |= child_state.needs_layout;
-        self.needs_compose |= child_state.needs_compose;
+    /// Clean up after elements that have been removed from the tree.
+    fn forget_elements(&mut self, removed_ids: Vec<u64>) {
+        if removed_ids.is_empty() {
+            return;
+        }
+
+        for removed_id in &removed_ids {
+            if let Some((render, overlay_render)) = self.render_cache.remove(removed_id) {
+                for area in [render.area, overlay_render.area].into_iter().flatten() {
+                    self.pending_damage = Some(union_damage(self.pending_damage, area));
+                }
+            }
+            if self.focused_element == Some(*removed_id) {
+                self.focused_element = None;
+            }
+            if self.next_focused_element == Some(*removed_id) {
+                self.next_focused_element = None;
+            }
+            if self.pointer_capture_target == Some(*removed_id) {
+                self.pointer_capture_target = None;
+                self.pointer_capture_held = false;
+            }
+        }
+
+        // NOTE: The remaining ancestors are notified that they lost their hovered or
+        //       focused child during the next pointer and focus passes.
+        let removed_ids = removed_ids.into_iter().collect::<HashSet<_>>();
+        self.timers
+            .retain(|timer| !removed_ids.contains(&timer.element_id));
+        self.hovered_path.retain(|id| !removed_ids.contains(id));
+        self.focused_path.retain(|id| !removed_ids.contains(id));
     }
-}
 
-pub struct ElementBuilder {
-    id: u64,
-    element: Box<dyn Element>,
-}
+    pub fn resize_window(&mut self, size: Xy<f32>) {
+        if self.window_size == size {
+            return;
+        }
+        self.window_size = size;
+        self.record(RecordedKind::Resize(size));
 
-impl ElementBuilder {
-    pub fn new<E: Element + 'static>(element: E) -> Self {
-        static NEXT_ELEMENT_ID: AtomicU64 = AtomicU64::new(1);
-        let id = NEXT_ELEMENT_ID.fetch_add(1, Ordering::Relaxed);
+        layout_pass(self);
+        self.record(RecordedKind::LayoutPass);
+    }
+
+    /// The host's refresh rate in frames per second, if it has set one.
+    pub fn refresh_rate(&self) -> Option<f32> {
+        self.refresh_interval
+            .map(|interval| interval.as_secs_f32().recip())
+    }
+
+    /// Set the refresh rate that animations are throttled to, usually the
+    /// display's. Frames that begin sooner than this after the last animated
+    /// frame skip the animation pass. `None` animates on every frame.
+    pub fn set_refresh_rate(&mut self, frames_per_second: Option<f32>) {
+        self.refresh_interval = frames_per_second
+            .filter(|fps| fps.is_finite() && *fps > 0.0)
+            .map(|fps| Duration::from_secs_f32(fps.recip()));
+    }
+
+    /// When the host should draw the next frame, or `None` if nothing has
+    /// changed and no animations or timers are running.
+    ///
+    /// While animating with a [refresh rate](View::set_refresh_rate), this is
+    /// one refresh interval after the last animated frame. While only waiting
+    /// for timers, it's when the next one is due. Otherwise, it's as soon as
+    /// possible.
+    pub fn next_frame_deadline(&self) -> Option<Instant> {
+        let next_timer = self.timers.iter().map(|timer| timer.deadline).min();
+        let animating = self.animating();
+        if animating
+            && let (Some(last), Some(interval)) = (self.last_animation, self.refresh_interval)
+        {
+            let deadline = last + interval;
+            return Some(next_timer.map_or(deadline, |timer| timer.min(deadline)));
+        }
+        if animating || self.needs_render() {
+            return Some(Instant::now());
+        }
 
-        Self {
-            id,
-            element: Box::new(element),
+        next_timer
+    }
+
+    /// Start a frame that will be presented at the given time, such as the
+    /// display's next vsync, running the animation pass.
+    ///
+    /// [`View::render`] uses this timestamp until [`View::end_frame`] is
+    /// called.
+    pub fn begin_frame(&mut self, timestamp: Instant) {
+        self.frame_timestamp = Some(timestamp);
+        self.fire_timers(timestamp);
+
+        let elapsed = self
+            .last_animation
+            .map(|last| timestamp.saturating_duration_since(last))
+            .unwrap_or_default();
+        // NOTE: Frame timestamps jitter a little, so frames that are almost a full
+        //       interval apart still animate.
+        if let Some(interval) = self.refresh_interval
+            && self.last_animation.is_some()
+            && elapsed + interval / 4 < interval
+        {
+            return;
         }
-    }
 
-    pub fn into_child(self) -> ChildElement {
-        ChildElement {
-            id: self.id,
-            inner: ChildElementInner::New(self),
+        animation_pass(self, elapsed.as_secs_f64());
+        self.record(RecordedKind::AnimatePass);
+        // Remove any elements that finished their exit transitions.
+        update_pass(self);
+
+        self.last_animation = self.animating().then_some(timestamp);
+    }
+
+    /// Call [`Element::on_timer`] for each timer that's due at the given time.
+    fn fire_timers(&mut self, now: Instant) {
+        let mut due = self
+            .timers
+            .iter()
+            .filter(|timer| timer.deadline <= now)
+            .map(|timer| (timer.deadline, timer.token, timer.element_id))
+            .collect::<Vec<_>>();
+        if due.is_empty() {
+            return;
         }
-    }
-}
+        due.sort_by_key(|(deadline, ..)| *deadline);
 
-pub struct ChildElement {
-    id: u64,
-    inner: ChildElementInner,
-}
+        self.timers.retain_mut(|timer| {
+            if timer.deadline > now {
+                return true;
+            }
+            let Some(interval) = timer.interval else {
+                return false;
+            };
+            // NOTE: Intervals that fell behind (such as while the host wasn't
+            //       drawing) fire once, rather than once for each missed interval.
+            timer.deadline += interval;
+            if timer.deadline <= now {
+                timer.deadline = now + interval;
+            }
+            true
+        });
+        for (_, token, element_id) in due {
+            if self.tree.find(element_id).is_none() {
+                continue;
+            }
+            single_event_pass(self, Some(element_id), |element, pass| {
+                element.on_timer(pass, token)
+            });
+        }
 
-impl ChildElement {
-    #[inline]
-    pub fn id(&self) -> u64 {
-        self.id
+        self.apply_popup_requests();
+        update_pass(self);
+        update_focus_pass(self);
+        layout_pass(self);
+        self.record(RecordedKind::LayoutPass);
+        compose_pass(self);
+        self.record(RecordedKind::ComposePass);
+        if self.apply_scroll_requests() {
+            compose_pass(self);
+            self.record(RecordedKind::ComposePass);
+        }
     }
 
-    pub fn exists(&self) -> bool {
-        matches!(self.inner, ChildElementInner::Existing)
+    /// Finish the frame started with [`View::begin_frame`].
+    pub fn end_frame(&mut self) {
+        self.frame_timestamp = None;
     }
 
-    fn take_inner(&mut self) -> Option<ElementBuilder> {
-        match std::mem::replace(&mut self.inner, ChildElementInner::Existing) {
-            ChildElementInner::New(builder) => Some(builder),
-            ChildElementInner::Existing => None,
+    /// Render the view. If this isn't between [`View::begin_frame`] and
+    /// [`View::end_frame`], it runs a frame timestamped with the current time.
+    pub fn render(&mut self, render: &mut Render) {
+        let in_frame = self.frame_timestamp.is_some();
+        if !in_frame {
+            self.begin_frame(Instant::now());
         }
-    }
-}
-
-enum ChildElementInner {
-    Existing,
-    New(ElementBuilder),
-}
 
-pub struct TypedChildElement<E: Element> {
-    pub inner: ChildElement,
-    _type: PhantomData<E>,
-}
+        render_pass(self, render);
+        self.record(RecordedKind::RenderPass);
 
-impl<E: Element + 'static> TypedChildElement<E> {
-    pub fn new(element: E) -> Self {
-        Self {
-            inner: ElementBuilder::new(element).into_child(),
-            _type: PhantomData,
+        if !in_frame {
+            self.end_frame();
         }
     }
 
-    #[inline]
-    pub fn id(&self) -> u64 {
-        self.inner.id
+    /// Whether anything has changed since the last render.
+    fn needs_render(&self) -> bool {
+        self.pending_damage.is_some()
+            || self.layer_root_ids().any(|id| {
+                self.tree
+                    .roots()
+                    .get(id)
+                    .expect("infallible")
+                    .element
+                    .state
+                    .needs_render
+            })
     }
-}
 
+    /// Lay out and render an element and its descendants offscreen, as if it
+    /// were the root of a window of the given size. Returns `None` if there is
+    /// no element with the given ID.
+    ///
+    /// The live tree is left as it was, so this can be used to print or export
+    /// documents, or to generate thumbnails and previews.
+    pub fn render_subtree(&mut self, element_id: u64, size: Xy<f32>) -> Option<Render> {
+        let mut node = self.tree.find_mut(element_id)?;
 
+        let mut live_states = HashMap::new();
+        for_each_element_state_mut(node.reborrow_mut(), &mut |state| {
+            live_states.insert(state.id, state.clone());
+        });
+        let live_size = node.element.state.layout_bounds.size();
 
-pub trait ExtensionElement {
-    fn element(&self) -> &dyn Element;
-    fn element_mut(&mut self) -> &mut dyn Element;
+        for_each_element_state_mut(node.reborrow_mut(), &mut request_full_layout);
+        layout_element(
+            &mut *self.fonts,
+            1.0,
+            &self.theme,
+            node.reborrow_mut(),
+            size,
+        );
+        node.element.state.scroll_translation = Xy::ZERO;
+        move_element(&mut node.element.state, Xy::ZERO);
+        compose_element(node.reborrow_mut(), Transform2D::IDENTITY, 1.0, true);
+        for_each_element_state_mut(node.reborrow_mut(), &mut |state| {
+            state.wants_render = true;
+            state.wants_overlay_render = true;
+        });
 
-    #[inline(always)]
-    fn children_ids(&self) -> Vec<u64> {
-        self.element().children_ids()
-    }
+        let mut render = Render::default();
+        let mut render_cache = HashMap::new();
+        let mut vars = RenderPassVariables::default();
+        let mut overlays = Vec::new();
+        render_element(
+            node.reborrow_mut(),
+            &mut render_cache,
+            &mut RenderCacheStats::default(),
+            &self.theme,
+            &mut render,
+            &mut vars,
+            &mut overlays,
+        );
+        for (id, opacity, transform) in overlays {
+            if let Some((_, overlay_render)) = render_cache.get(&id) {
+                render.extend_cached(overlay_render, opacity, transform, &mut vars);
+            }
+        }
+        render.damage = Some(Aabb2D::from_size(size));
+
+        // NOTE: Elements keep their own layout information (such as the sizes of their
+        //       children), so they need to be laid out again at their live size before
+        //       the states are restored.
+        for_each_element_state_mut(node.reborrow_mut(), &mut request_full_layout);
+        layout_element(
+            &mut *self.fonts,
+            self.zoom,
+            &self.theme,
+            node.reborrow_mut(),
+            live_size,
+        );
+        for_each_element_state_mut(node, &mut |state| {
+            if let Some(mut live_state) = live_states.remove(&state.id) {
+                live_state.data = std::mem::take(&mut state.data);
+                *state = live_state;
+            }
+        });
 
-    #[inline(always)]
-    fn update_children(&mut self, pass: &mut UpdatePass<'_>) {
-        self.element_mut().update_children(pass)
-    }
+        Some(render)
+    }
+
+    /// Save the state of every element that provides some (see
+    /// [`Element::save_state`]) along with which element is focused, so that it
+    /// can be restored into a new view of the same application, such as after
+    /// the application is rebuilt and reloaded.
+    ///
+    /// Elements are identified by their place in the tree, using their keys
+    /// (see [`ElementBuilder::with_key`]) where they have them and their
+    /// index among their siblings otherwise. The saved state doesn't refer
+    /// to anything in the application, so it can outlive it.
+    pub fn serialize_state(&self) -> ViewState {
+        let mut state = ViewState::default();
+        let root = self
+            .tree
+            .find(self.root_element_id)
+            .expect("failed to find the view's root node");
+        save_element_state(root, &mut Vec::new(), self.focused_element, &mut state);
+
+        state
+    }
+
+    /// Restore state from [`View::serialize_state`], returning the number of
+    /// elements whose state was restored.
+    ///
+    /// Saved state is skipped for elements that are no longer in the same
+    /// place, or whose type has changed.
+    pub fn restore_state(&mut self, state: &ViewState) -> usize {
+        let mut restored = 0;
+        let mut focused = None;
+        let root = self
+            .tree
+            .find_mut(self.root_element_id)
+            .expect("failed to find the view's root node");
+        restore_element_state(root, &mut Vec::new(), state, &mut focused, &mut restored);
+        if restored == 0 && focused.is_none() {
+            return 0;
+        }
 
-    #[inline(always)]
-    fn accepts_pointer_events(&self) -> bool {
-        self.element().accepts_pointer_events()
-    }
+        if focused.is_some() {
+            self.next_focused_element = focused;
+            update_focus_pass(self);
+            self.record(RecordedKind::FocusPass);
+        }
+        // NOTE: Elements don't say what their restored state affects, so everything
+        //       is laid out again.
+        for id in self.layer_root_ids().collect::<Vec<_>>() {
+            let node = self
+                .tree
+                .find_mut(id)
+                .expect("failed to find a layer's root node");
+            for_each_element_state_mut(node, &mut request_full_layout);
+        }
+        layout_pass(self);
+        self.record(RecordedKind::LayoutPass);
+        compose_pass(self);
+        self.record(RecordedKind::ComposePass);
 
-    #[inline(always)]
-    fn accepts_keyboard_events(&self) -> bool {
-        self.element().accepts_keyboard_events()
+        restored
     }
 
-    #[inline(always)]
-    fn accepts_focus_events(&self) -> bool {
-        self.element().accepts_focus_events()
-    }
+    pub fn handle_keyboard_event(&mut self, event: KeyboardEvent) {
+        self.modifiers = event.modifiers();
+        if self.handle_view_shortcut(&event) {
+            return;
+        }
 
-    #[inline(always)]
-    fn render(&mut self, pass: &mut RenderPass<'_>) {
-        self.element_mut().render(pass)
+        keyboard_event_pass(self, &event);
+        self.record(RecordedKind::KeyboardEvent(event));
+        self.apply_popup_requests();
+        update_pass(self);
+        update_focus_pass(self);
+        layout_pass(self);
+        self.record(RecordedKind::LayoutPass);
+        compose_pass(self);
+        self.record(RecordedKind::ComposePass);
+        if self.apply_scroll_requests() {
+            compose_pass(self);
+            self.record(RecordedKind::ComposePass);
+        }
     }
 
-    #[inline(always)]
-    fn render_overlay(&mut self, pass: &mut RenderPass<'_>) {
-        self.element_mut().render_overlay(pass)
+    /// Handle a pointer event, followed by the events it's translated into if
+    /// it's a [`PointerEvent::Touch`] (see [`TouchTracker`]).
+    pub fn handle_pointer_event(&mut self, event: PointerEvent) {
+        let translated = self.touch_tracker.handle(&event);
+        self.handle_single_pointer_event(event);
+        for event in translated {
+            self.handle_single_pointer_event(event);
+        }
     }
 
-    #[inline(always)]
-    fn animate(&mut self, pass: &mut AnimatePass<'_>, dt: f64) {
-        self.element_mut().animate(pass, dt)
+    fn handle_single_pointer_event(&mut self, event: PointerEvent) {
+        self.modifiers = event.modifiers();
+        pointer_event_pass(self, &event);
+        self.record(RecordedKind::PointerEvent(event));
+        self.apply_popup_requests();
+        update_pass(self);
+        update_pointer_pass(self);
+        self.record(RecordedKind::PointerPass);
+        update_focus_pass(self);
+        self.record(RecordedKind::FocusPass);
+        layout_pass(self);
+        self.record(RecordedKind::LayoutPass);
+        compose_pass(self);
+        self.record(RecordedKind::ComposePass);
+        if self.apply_scroll_requests() {
+            compose_pass(self);
+            self.record(RecordedKind::ComposePass);
+        }
     }
+}
 
-    #[inline(always)]
-    fn layout(&mut self, pass: &mut LayoutPass<'_>) {
-        self.element_mut().layout(pass)
-    }
+pub trait Fonts {
+    /// Measure `text`, where `font_style` applies to everything outside of the
+    /// given spans (see [`text::styled_runs`](crate::text::styled_runs)).
+    fn measure_text(
+        &mut self,
+        id: u64,
+        text: &str,
+        spans: &[TextSpan],
+        max_advance: Option<f32>,
+        font_size: f32,
+        line_height: LineHeight,
+        font_family: FontFamily,
+        font_style: FontStyle,
+        alignment: TextAlignment,
+        wrap_mode: TextWrapMode,
+    ) -> Xy<f32>;
 
-    #[inline(always)]
-    fn measure(
+    /// The distance from the bottom of a line of text to its baseline.
+    fn line_baseline_offset(
         &mut self,
-        context: &mut MeasureContext<'_>,
-        axis: Axis,
-        length_request: LengthRequest,
-        cross_length: Option<f32>,
+        font_size: f32,
+        line_height: LineHeight,
+        _font_style: FontStyle,
     ) -> f32 {
-        self.element_mut()
-            .measure(context, axis, length_request, cross_length)
-    }
-
-    #[inline(always)]
-    fn compose(&mut self, pass: &mut ComposePass<'_>) {
-        self.element_mut().compose(pass)
-    }
-
-    #[inline(always)]
-    fn cursor_icon(&self) -> CursorIcon {
-        self.element().cursor_icon()
-    }
+        // NOTE: Most fonts descend about a fifth of their size below the baseline, and
+        //       any extra line height is split evenly above and below the text.
+        let half_leading = (line_height.resolve(font_size) - font_size) / 2.0;
+
+        half_leading + font_size * 0.2
+    }
+
+    /// The position of the cursor in front of the byte at `offset`, relative to
+    /// the top left of the text.
+    ///
+    /// The default implementation measures each line on its own, ignoring
+    /// wrapping and alignment, so backends that wrap text should override it
+    /// along with [`Fonts::hit_test_text`].
+    fn cursor_position(&mut self, id: u64, params: TextParams<'_>, offset: usize) -> Xy<f32> {
+        let text = params.text;
+        let offset = floor_char_boundary(text, offset);
+        let line_start = text[..offset].rfind('\n').map_or(0, |index| index + 1);
+        let line_index = text[..line_start].matches('\n').count();
+
+        let width = self
+            .measure_text(
+                id,
+                &text[line_start..offset],
+                &slice_spans(params.spans, line_start..offset),
+                None,
+                params.font_size,
+                params.line_height,
+                params.font_family,
+                params.font_style,
+                TextAlignment::Start,
+                TextWrapMode::NoWrap,
+            )
+            .x;
 
-    #[inline(always)]
-    fn on_build(&mut self, pass: &mut UpdatePass<'_>) {
-        self.element_mut().on_build(pass);
+        Xy::new(
+            width,
+            line_index as f32 * params.line_height.resolve(params.font_size),
+        )
     }
 
-    #[inline(always)]
-    fn on_keyboard_event(&mut self, pass: &mut EventPass<'_>, event: &KeyboardEvent) {
-        self.element_mut().on_keyboard_event(pass, event)
-    }
+    /// The byte offset of the cursor position closest to `position`, which is
+    /// relative to the top left of the text. Always on a cluster boundary.
+    fn hit_test_text(&mut self, id: u64, params: TextParams<'_>, position: Xy<f32>) -> usize {
+        let text = params.text;
+        let line_height = params.line_height.resolve(params.font_size);
+        let line_index = (position.y / line_height).max(0.0) as usize;
 
-    #[inline(always)]
-    fn on_pointer_event(&mut self, pass: &mut EventPass<'_>, event: &PointerEvent) {
-        self.element_mut().on_pointer_event(pass, event)
-    }
+        let mut line_start = 0;
+        for _ in 0..line_index {
+            match text[line_start..].find('\n') {
+                Some(index) => line_start += index + 1,
+                None => break,
+            }
+        }
+        let line_end = text[line_start..]
+            .find('\n')
+            .map_or(text.len(), |index| line_start + index);
+
+        let mut closest = (line_start, f32::INFINITY);
+        let mut offset = line_start;
+        loop {
+            let distance = (self.cursor_position(id, params, offset).x - position.x).abs();
+            if distance < closest.1 {
+                closest = (offset, distance);
+            }
+            if offset >= line_end {
+                break;
+            }
+            offset = bidi::next_cluster_boundary(text, offset).min(line_end);
+        }
 
-    #[inline(always)]
-    fn on_hover(&mut self, pass: &mut EventPass<'_>, hovered: bool) {
-        self.element_mut().on_hover(pass, hovered)
+        closest.0
     }
+}
 
-    #[inline(always)]
-    fn on_child_hover(&mut self, pass: &mut EventPass<'_>, hovered: bool) {
-        self.element_mut().on_child_hover(pass, hovered)
+fn floor_char_boundary(text: &str, offset: usize) -> usize {
+    let mut offset = offset.min(text.len());
+    while !text.is_char_boundary(offset) {
+        offset -= 1;
     }
 
-    #[inline(always)]
-    fn on_focus(&mut self, pass: &mut EventPass<'_>, focused: bool) {
-        self.element_mut().on_focus(pass, focused)
-    }
+    offset
+}
 
-    #[inline(always)]
-    fn on_child_focus(&mut self, pass: &mut EventPass<'_>, focused: bool) {
-        self.element_mut().on_child_focus(pass, focused)
-    }
+/// The spans that overlap `range`, relative to its start.
+fn slice_spans(spans: &[TextSpan], range: Range<usize>) -> Vec<TextSpan> {
+    spans
+        .iter()
+        .filter(|span| span.range.start < range.end && span.range.end > range.start)
+        .map(|span| {
+            let start = span.range.start.max(range.start) - range.start;
+            let end = span.range.end.min(range.end) - range.start;
+            TextSpan::new(start..end, span.style)
+        })
+        .collect()
 }
 
-impl<T: ExtensionElement + 'static> Element for T {
-    #[inline(always)]
+pub trait Element: Any {
     fn children_ids(&self) -> Vec<u64> {
-        self.children_ids()
+        Vec::new()
     }
 
-    #[inline(always)]
-    fn update_children(&mut self, pass: &mut UpdatePass<'_>) {
-        self.update_children(pass)
-    }
+    #[allow(unused)]
+    fn update_children(&mut self, pass: &mut UpdatePass<'_>) {}
 
-    #[inline(always)]
+    /// Defaults to `true`.
     fn accepts_pointer_events(&self) -> bool {
-        self.accepts_pointer_events()
+        true
     }
 
-    #[inline(always)]
+    /// Defaults to `false`.
     fn accepts_keyboard_events(&self) -> bool {
-        self.accepts_keyboard_events()
+        false
     }
 
-    #[inline(always)]
+    /// Defaults to `false`.
     fn accepts_focus_events(&self) -> bool {
-        self.accepts_focus_events()
+        false
     }
 
-    #[inline(always)]
-    fn render(&mut self, pass: &mut RenderPass<'_>) {
-        self.render(pass)
-    }
+    #[allow(unused)]
+    fn render(&mut self, pass: &mut RenderPass<'_>) {}
 
-    #[inline(always)]
-    fn render_overlay(&mut self, pass: &mut RenderPass<'_>) {
-        self.render_overlay(pass)
+    /// A hash of everything other than the element's bounds that affects the
+    /// output of [`Element::render`].
+    ///
+    /// When the hash and bounds are unchanged, requested renders reuse the
+    /// cached commands instead. Defaults to `None`, which always re-renders.
+    fn content_hash(&self) -> Option<u64> {
+        None
     }
 
-    #[inline(always)]
-    fn animate(&mut self, pass: &mut AnimatePass<'_>, dt: f64) {
-        self.animate(pass, dt)
-    }
+    #[allow(unused)]
+    fn render_overlay(&mut self, pass: &mut RenderPass<'_>) {}
 
-    #[inline(always)]
-    fn layout(&mut self, pass: &mut LayoutPass<'_>) {
-        self.layout(pass)
-    }
+    #[allow(unused)]
+    fn animate(&mut self, pass: &mut AnimatePass<'_>, dt: f64) {}
+
+    #[allow(unused)]
+    fn layout(&mut self, pass: &mut LayoutPass<'_>) {}
 
-    #[inline(always)]
     fn measure(
         &mut self,
         context: &mut MeasureContext<'_>,
         axis: Axis,
         length_request: LengthRequest,
         cross_length: Option<f32>,
-    ) -> f32 {
-        self.measure(context, axis, length_request, cross_length)
-    }
+    ) -> f32;
 
-    #[inline(always)]
-    fn compose(&mut self, pass: &mut ComposePass<'_>) {
-        self.compose(pass)
-    }
+    #[allow(unused)]
+    fn compose(&mut self, pass: &mut ComposePass<'_>) {}
 
-    #[inline(always)]
     fn cursor_icon(&self) -> CursorIcon {
-        self.cursor_icon()
+        CursorIcon::Default
     }
 
-    #[inline(always)]
-    fn on_build(&mut self, pass: &mut UpdatePass<'_>) {
-        self.on_build(pass);
+    /// Called when this element is added to the view tree.
+    #[allow(unused)]
+    fn on_build(&mut self, pass: &mut UpdatePass<'_>) {}
+
+    /// Called after [`Element::on_build`] to start an enter transition, which
+    /// is usually driven by [`Element::animate`].
+    #[allow(unused)]
+    fn on_enter(&mut self, pass: &mut UpdatePass<'_>) {}
+
+    /// Called when this element's parent removes it with
+    /// [`UpdatePass::remove_child`].
+    ///
+    /// Return `true` to start an exit transition, in which case the element
+    /// stays in the tree until it calls [`AnimatePass::finish_exit`]. Defaults
+    /// to `false`, which removes it immediately.
+    #[allow(unused)]
+    fn on_exit(&mut self, pass: &mut UpdatePass<'_>) -> bool {
+        false
     }
 
-    #[inline(always)]
-    fn on_keyboard_event(&mut self, pass: &mut EventPass<'_>, event: &KeyboardEvent) {
-        self.on_keyboard_event(pass, event)
+    /// Called when this element is interacted with by the user's keyboard.
+    #[allow(unused)]
+    fn on_keyboard_event(&mut self, pass: &mut EventPass<'_>, event: &KeyboardEvent) {}
+
+    /// Called when this element is interacted with by the user's pointer.
+    #[allow(unused)]
+    fn on_pointer_event(&mut self, pass: &mut EventPass<'_>, event: &PointerEvent) {}
+
+    /// Called when a gesture is recognized from the pointer events sent to this
+    /// element (or one of its children), after the pointer event that completed
+    /// it.
+    #[allow(unused)]
+    fn on_gesture(&mut self, pass: &mut EventPass<'_>, gesture: &Gesture) {}
+
+    /// Called when a keyboard event occurs while this element (or one of its
+    /// children) is hovered, regardless of which element has focus.
+    #[allow(unused)]
+    fn on_hovered_keyboard_event(&mut self, pass: &mut EventPass<'_>, event: &KeyboardEvent) {}
+
+    #[allow(unused)]
+    fn on_hover(&mut self, pass: &mut EventPass<'_>, hovered: bool) {}
+
+    #[allow(unused)]
+    fn on_child_hover(&mut self, pass: &mut EventPass<'_>, hovered: bool) {}
+
+    #[allow(unused)]
+    fn on_focus(&mut self, pass: &mut EventPass<'_>, focused: bool) {}
+
+    #[allow(unused)]
+    fn on_child_focus(&mut self, pass: &mut EventPass<'_>, focused: bool) {}
+
+    /// Called on each ancestor of an element that should be scrolled into view
+    /// (see [`EventPass::request_scroll_into_view`]), from the nearest outward.
+    ///
+    /// `area` is the part of the element to reveal, in the same space as
+    /// [`ElementState::bounds`]. Scroll containers should scroll to reveal it,
+    /// then update it to where it ended up so that containers further out
+    /// reveal the right area.
+    #[allow(unused)]
+    fn on_scroll_into_view(&mut self, pass: &mut EventPass<'_>, area: &mut Aabb2D) {}
+
+    /// The menu to show when this element (or one of its children without a
+    /// menu of its own) is right-clicked at the given position.
+    ///
+    /// Defaults to `None`, which lets the element's ancestors provide one.
+    #[allow(unused)]
+    fn context_menu(&mut self, position: Xy<f32>) -> Option<ContextMenu> {
+        None
     }
 
-    #[inline(always)]
-    fn on_pointer_event(&mut self, pass: &mut EventPass<'_>, event: &PointerEvent) {
-        self.on_pointer_event(pass, event)
+    /// The name shown for this element by [`View::dump_tree`] and the debug
+    /// overlay. Defaults to the element's type name.
+    fn debug_name(&self) -> &'static str {
+        std::any::type_name::<Self>()
     }
 
-    #[inline(always)]
-    fn on_hover(&mut self, pass: &mut EventPass<'_>, hovered: bool) {
-        self.on_hover(pass, hovered)
+    /// Called with the [`ContextMenuItem::action`] of the item chosen from this
+    /// element's [`Element::context_menu`].
+    #[allow(unused)]
+    fn on_context_menu_action(&mut self, pass: &mut EventPass<'_>, action: u64) {}
+
+    /// Called with each message sent to this element with an [`UpdateSender`],
+    /// when the host calls [`View::process_updates`].
+    #[allow(unused)]
+    fn on_message(&mut self, pass: &mut EventPass<'_>, message: Box<dyn Any>) {}
+
+    /// Called when a timer started with [`EventPass::request_timer`] (or
+    /// [`EventPass::request_interval`]) is due, from [`View::begin_frame`].
+    #[allow(unused)]
+    fn on_timer(&mut self, pass: &mut EventPass<'_>, token: TimerToken) {}
+
+    /// Called when a [`Drag`] moves over this element (or one of its children
+    /// that didn't accept it). Return `true` to accept it, making this element
+    /// the drop target.
+    ///
+    /// Defaults to `false`.
+    #[allow(unused)]
+    fn on_drag_over(&mut self, pass: &mut EventPass<'_>, drag: &Drag) -> bool {
+        false
     }
 
-    #[inline(always)]
-    fn on_child_hover(&mut self, pass: &mut EventPass<'_>, hovered: bool) {
-        self.on_child_hover(pass, hovered)
+    /// Called when a drag this element accepted moves elsewhere or is
+    /// cancelled.
+    #[allow(unused)]
+    fn on_drag_leave(&mut self, pass: &mut EventPass<'_>) {}
+
+    /// Called when a drag this element accepted is dropped on it.
+    #[allow(unused)]
+    fn on_drop(&mut self, pass: &mut EventPass<'_>, drag: Drag) {}
+
+    /// Save anything the user would expect to survive the application being
+    /// reloaded (such as text they've typed), see [`View::serialize_state`].
+    ///
+    /// Defaults to `None`.
+    fn save_state(&self) -> Option<Vec<u8>> {
+        None
     }
 
-    #[inline(always)]
-    fn on_focus(&mut self, pass: &mut EventPass<'_>, focused: bool) {
-        self.on_focus(pass, focused)
+    /// Restore state from [`Element::save_state`] into a newly built element in
+    /// the same place in the tree. The element is laid out again afterwards.
+    #[allow(unused)]
+    fn restore_state(&mut self, state: &[u8]) {}
+}
+
+pub struct ElementInfo {
+    pub element: Box<dyn Element>,
+    pub state: ElementState,
+}
+
+impl Deref for ElementInfo {
+    type Target = dyn Element;
+
+    #[inline]
+    fn deref(&self) -> &Self::Target {
+        &*self.element
     }
+}
 
-    #[inline(always)]
-    fn on_child_focus(&mut self, pass: &mut EventPass<'_>, focused: bool) {
-        self.on_child_focus(pass, focused)
+impl DerefMut for ElementInfo {
+    #[inline]
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut *self.element
     }
 }
 
+#[derive(Clone)]
+pub struct ElementState {
+    pub id: u64,
 
+    pub bounds: Aabb2D,
+    pub baseline_offset: f32,
+    pub layout_bounds: Aabb2D,
+    /// The distance from the bottom of the element to its last baseline, or
+    /// zero if it doesn't have one.
+    pub layout_baseline_offset: f32,
+    /// The distance from the bottom of the element to its first baseline, if
+    /// it's different from the last one.
+    pub layout_first_baseline_offset: Option<f32>,
 
-macro_rules! impl_deref_for_simple_extensions {
-    ($($ty:ty),* $(,)?) => {
-        $(
-            impl<E: Element> Deref for $ty {
-                type Target = E;
+    pub scroll_translation: Xy<f32>,
+    pub local_transform: Transform2D,
+    pub global_transform: Transform2D,
+    /// Siblings with a higher `z_index` are painted (and hit-tested) above
+    /// those with a lower one. Siblings with the same `z_index` are painted
+    /// in tree order.
+    pub z_index: i32,
+    /// The opacity of this element and its descendants relative to its parent,
+    /// see [`ComposePass::set_child_opacity`].
+    pub opacity: f32,
+    /// The opacity of this element after multiplying in its ancestors'.
+    pub global_opacity: f32,
+    /// The area that needs to be repainted the next time this element is
+    /// rendered, see [`Render::damage`].
+    pub damage: Option<Aabb2D>,
 
-                #[inline]
-                fn deref(&self) -> &Self::Target {
-                    &self.element
-                }
-            }
+    pub newly_added: bool,
+    pub children_changed: bool,
+    /// Whether this element has been removed by its parent and is playing its
+    /// exit transition, see [`Element::on_exit`].
+    pub exiting: bool,
+    /// Whether this element's exit transition has finished, see
+    /// [`AnimatePass::finish_exit`].
+    pub exited: bool,
+    /// Children that have been removed, but are still playing their exit
+    /// transitions. These are rendered and animated, but aren't laid out or
+    /// hit-tested.
+    pub exiting_children: Vec<u64>,
+    /// The key this element was built with, see [`ElementBuilder::with_key`].
+    pub key: Option<u64>,
+    /// Keyed children that have been removed during the current update, and
+    /// can still be reused by a new child with the same key.
+    detached_children: Vec<u64>,
+    /// Keyed children that have been reused during the current update.
+    reused_children: Vec<u64>,
 
-            impl<E: Element> DerefMut for $ty {
-                #[inline]
-                fn deref_mut(&mut self) -> &mut Self::Target {
-                    &mut self.element
-                }
-            }
-        )*
-    };
-}
+    pub needs_render: bool,
+    pub wants_render: bool,
+    pub wants_overlay_render: bool,
 
-impl_deref_for_simple_extensions![OnClick<E>, OnHover<E>];
+    pub needs_animate: bool,
+    pub wants_animate: bool,
 
+    /// Whether this element or one of its descendants needs to be laid out.
+    pub needs_layout: bool,
+    /// Whether [`Element::layout`] needs to be called for this element, even if
+    /// its size hasn't changed.
+    pub wants_layout: bool,
+    /// The results of recent calls to [`Element::measure`], which are reused
+    /// until this element or one of its descendants requests a layout.
+    measure_cache: Vec<MeasureCacheEntry>,
 
+    pub needs_compose: bool,
+    pub wants_compose: bool,
+    pub transformed: bool,
 
-pub struct OnHover<E: Element> {
-    pub element: E,
-    pub callback: fn(&mut E, &mut EventPass<'_>, bool),
+    pub hovered: bool,
+    pub focused: bool,
+
+    /// Values attached to this element by its ancestors, extensions, or the
+    /// application, see [`EventPass::insert_data`].
+    pub data: ElementData,
 }
 
-impl<E: Element> OnHover<E> {
-    #[inline(always)]
-    pub const fn new(element: E, callback: fn(&mut E, &mut EventPass<'_>, bool)) -> Self {
-        Self { element, callback }
+impl ElementState {
+    /// See [`UpdatePass::update_child`].
+    fn new(id: u64) -> Self {
+        Self {
+            id,
+            bounds: Aabb2D::ZERO,
+            baseline_offset: 0.0,
+            layout_bounds: Aabb2D::ZERO,
+            layout_baseline_offset: 0.0,
+            layout_first_baseline_offset: None,
+            scroll_translation: Xy::ZERO,
+            local_transform: Transform2D::IDENTITY,
+            global_transform: Transform2D::IDENTITY,
+            z_index: 0,
+            opacity: 1.0,
+            global_opacity: 1.0,
+            damage: None,
+            newly_added: true,
+            children_changed: true,
+            exiting: false,
+            exited: false,
+            exiting_children: Vec::new(),
+            key: None,
+            detached_children: Vec::new(),
+            reused_children: Vec::new(),
+            needs_render: true,
+            wants_render: true,
+            wants_overlay_render: true,
+            needs_animate: true,
+            wants_animate: true,
+            needs_layout: true,
+            wants_layout: true,
+            measure_cache: Vec::new(),
+            needs_compose: true,
+            wants_compose: true,
+            transformed: true,
+            hovered: false,
+            focused: false,
+            data: ElementData::default(),
+        }
     }
-}
 
-impl<E: Element> ExtensionElement for OnHover<E> {
-    #[inline(always)]
-    fn element(&self) -> &dyn Element {
-        &self.element
+    /// The distance from the bottom of the element to its first baseline.
+    pub fn first_baseline_offset(&self) -> f32 {
+        self.layout_first_baseline_offset
+            .unwrap_or(self.layout_baseline_offset)
     }
 
-    #[inline(always)]
-    fn element_mut(&mut self) -> &mut dyn Element {
-        &mut self.element
+    /// The space that the element is painted in, as the transform from it to
+    /// the view and the element's bounds in it.
+    ///
+    /// Elements are painted in the view's space within their
+    /// [`bounds`](Self::bounds), unless they're rotated or skewed. Those are
+    /// painted upright in a space of their own, scaled like the view so that
+    /// they look the same as they would unrotated.
+    fn paint_space(&self) -> (Transform2D, Aabb2D) {
+        if self.global_transform.is_axis_aligned() {
+            return (Transform2D::IDENTITY, self.bounds);
+        }
+
+        let scale = self.global_transform.scale_factor();
+        let size = self.layout_bounds.size();
+        (
+            self.global_transform * Transform2D::scale(scale.recip()),
+            Aabb2D::from_size(Xy::new(size.x * scale, size.y * scale)),
+        )
     }
 
-    #[inline(always)]
-    fn on_hover(&mut self, pass: &mut EventPass<'_>, hovered: bool) {
-        (self.callback)(&mut self.element, pass, hovered)
+    fn merge_with_child(&mut self, child_state: &Self) {
+        self.children_changed |= child_state.children_changed;
+        self.needs_render |= child_state.needs_render;
+        self.needs_animate |= child_state.needs_animate;
+        self.needs_layout |= child_state.needs_layout;
+        self.needs_compose |= child_state.needs_compose;
     }
 }
 
-pub struct OnClick<E: Element> {
-    pub element: E,
-    pub callback: fn(&mut E, &mut EventPass<'_>, bool),
+/// Typed values attached to an element, at most one of each type.
+#[derive(Default)]
+pub struct ElementData {
+    map: TypeMap<Box<dyn Any>>,
 }
 
-impl<E: Element> OnClick<E> {
-    #[inline(always)]
-    pub const fn new(element: E, callback: fn(&mut E, &mut EventPass<'_>, bool)) -> Self {
-        Self { element, callback }
+// NOTE: States are only cloned to be restored later, and the data is moved
+//       back when they are (see `View::render_subtree`), so it isn't copied.
+impl Clone for ElementData {
+    fn clone(&self) -> Self {
+        Self::default()
     }
 }
 
-impl<E: Element> ExtensionElement for OnClick<E> {
-    #[inline(always)]
-    fn element(&self) -> &dyn Element {
-        &self.element
+impl ElementData {
+    pub fn has<T: Any>(&self) -> bool {
+        self.map.has::<T>()
     }
 
-    #[inline(always)]
-    fn element_mut(&mut self) -> &mut dyn Element {
-        &mut self.element
+    pub fn get<T: Any>(&self) -> Option<&T> {
+        self.map.get::<T>()?.downcast_ref()
     }
 
-    #[inline(always)]
-    fn on_pointer_event(&mut self, pass: &mut EventPass<'_>, event: &PointerEvent) {
-        match event {
-            PointerEvent::Down {
-                button: PointerButton::Primary,
-                position: _,
-            } => {
-                pass.capture_pointer();
-                (self.callback)(&mut self.element, pass, true)
-            }
-            PointerEvent::Up {
-                button: PointerButton::Primary,
-            } => (self.callback)(&mut self.element, pass, false),
-            other => self.element.on_pointer_event(pass, other),
-        }
+    pub fn get_mut<T: Any>(&mut self) -> Option<&mut T> {
+        self.map.get_mut::<T>()?.downcast_mut()
     }
-}
 
+    /// Attach a value, returning the value of the same type it replaced.
+    pub fn insert<T: Any>(&mut self, value: T) -> Option<T> {
+        let previous = self.remove::<T>();
+        self.map.insert::<T>(Box::new(value));
 
+        previous
+    }
 
-#[macro_export]
-macro_rules! column {
-    (@_ { $col:expr } gap: $gap:expr; $($rest:tt)*) => {
-        $crate::column!(@_ { $col .with_gap($gap) } $($rest)*)
-    };
-    (@_ { $col:expr } $($rest:expr),* $(,)?) => {
-        $col $(.with($rest))*
-    };
-    ($($items:tt)*) => {
-        $crate::column!(@_ { Column::new() } $($items)*)
-    };
+    pub fn remove<T: Any>(&mut self) -> Option<T> {
+        self.map
+            .remove::<T>()
+            .and_then(|value| value.downcast().ok())
+            .map(|value| *value)
+    }
 }
 
-#[macro_export]
-macro_rules! row {
-    (@_ { $row:expr } gap: $gap:expr; $($rest:tt)*) => {
-        $crate::row!(@_ { $row .with_gap($gap) } $($rest)*)
-    };
-    (@_ { $row:expr } $($rest:expr),* $(,)?) => {
-        $row $(.with($rest))*
-    };
-    ($($items:tt)*) => {
-        $crate::row!(@_ { Row::new() } $($items)*)
-    };
+pub struct ElementBuilder {
+    id: u64,
+    key: Option<u64>,
+    element: Box<dyn Element>,
 }
 
-pub struct Column {
-    children: Vec<ChildElement>,
-    background_color: Rgba,
-    border_color: Rgba,
-    gap: f32,
-}
+impl ElementBuilder {
+    pub fn new<E: Element + 'static>(element: E) -> Self {
+        static NEXT_ELEMENT_ID: AtomicU64 = AtomicU64::new(1);
+        let id = NEXT_ELEMENT_ID.fetch_add(1, Ordering::Relaxed);
 
-impl Column {
-    pub fn new() -> Self {
         Self {
-            children: Vec::new(),
-            background_color: Rgba {
-                r: 33,
-                g: 33,
-                b: 33,
-                a: 255,
-            },
-            border_color: Rgba {
-                r: 111,
-                g: 111,
-                b: 111,
-                a: 255,
-            },
-            gap: 0.0,
+            id,
+            key: None,
+            element: Box::new(element),
         }
     }
 
-    pub fn with_gap(mut self, gap: f32) -> Self {
-        self.gap = gap;
+    /// Give this element a key that identifies it among its siblings.
+    ///
+    /// When a parent rebuilds its children, a new child with the same key (and
+    /// type) as an existing one takes over that child's ID, element, and
+    /// state (focus, scroll position, etc.) instead of replacing it, see
+    /// [`UpdatePass::update_child`].
+    pub fn with_key(mut self, key: impl Hash) -> Self {
+        self.key = Some(hash_content(&key));
         self
     }
 
-    pub fn with(mut self, child: impl Element + 'static) -> Self {
-        self.children.push(ElementBuilder::new(child).into_child());
-        self
+    pub fn into_child(self) -> ChildElement {
+        ChildElement {
+            id: self.id,
+            inner: ChildElementInner::New(self),
+        }
     }
 }
 
-impl Element for Column {
-    fn children_ids(&self) -> Vec<u64> {
-        self.children.iter().map(|child| child.id()).collect()
+pub struct ChildElement {
+    id: u64,
+    inner: ChildElementInner,
+}
+
+impl ChildElement {
+    #[inline]
+    pub fn id(&self) -> u64 {
+        self.id
     }
 
-    fn update_children(&mut self, pass: &mut UpdatePass<'_>) {
-        for child in self.children.iter_mut() {
-            pass.update_child(child);
-        }
+    pub fn exists(&self) -> bool {
+        matches!(self.inner, ChildElementInner::Existing)
     }
 
-    fn render(&mut self, pass: &mut RenderPass<'_>) {
-        pass.fill_quad(pass.bounds(), self.background_color, 1.0, self.border_color);
+    fn take_inner(&mut self) -> Option<ElementBuilder> {
+        match std::mem::replace(&mut self.inner, ChildElementInner::Existing) {
+            ChildElementInner::New(builder) => Some(builder),
+            ChildElementInner::Existing => None,
+        }
     }
+}
 
-    fn layout(&mut self, pass: &mut LayoutPass<'_>) {
-        let width = Length::FitContent(pass.size.x);
-        let height = Length::FitContent(pass.size.y);
-        let auto_size = Xy::new(width, height);
+enum ChildElementInner {
+    Existing,
+    New(ElementBuilder),
+}
 
-        let mut y_offset = 0.0;
-        for child in &mut self.children {
-            let child_size = pass.resolve_size(child.id(), auto_size);
-            pass.do_layout(child, child_size);
-            pass.place_child(child, Xy::new(0.0, y_offset));
+/// A list of children identified by keys, for elements whose children are
+/// built from changing data (such as the entries of a directory).
+///
+/// Call [`KeyedChildren::update`] from [`Element::update_children`], and
+/// request an update after each [`KeyedChildren::reconcile`] that returns
+/// `true`.
+pub struct KeyedChildren<K> {
+    children: Vec<(K, ChildElement)>,
+    removed: Vec<ChildElement>,
+}
 
-            y_offset += child_size.y + self.gap;
+impl<K> Default for KeyedChildren<K> {
+    fn default() -> Self {
+        Self {
+            children: Vec::new(),
+            removed: Vec::new(),
         }
     }
+}
 
-    fn measure(
-        &mut self,
-        context: &mut MeasureContext<'_>,
-        axis: Axis,
-        length_request: LengthRequest,
-        cross_length: Option<f32>,
-    ) -> f32 {
-        let length_request = match length_request {
-            LengthRequest::MinContent | LengthRequest::MaxContent => length_request,
-            LengthRequest::FitContent(_space) => LengthRequest::MinContent,
-        };
-
-        let fallback_length = length_request.into();
+impl<K: Eq + Hash> KeyedChildren<K> {
+    pub fn new() -> Self {
+        Self::default()
+    }
 
-        let mut length: f32 = 0.0;
-        for child in &mut self.children {
-            let child_length =
-                context.resolve_length(child.id(), axis, fallback_length, cross_length);
-            match axis {
-                Axis::Horizontal => length = length.max(child_length),
-                Axis::Vertical => length += child_length,
+    /// Make the children match the given keys, in order.
+    ///
+    /// Children whose keys are still present are kept (and moved as needed),
+    /// new keys are built with `build`, and children whose keys are gone are
+    /// removed. Only the first of any duplicate keys is used, as each key
+    /// identifies one child. Returns `true` if anything changed, including the
+    /// order of the children.
+    pub fn reconcile<E: Element + 'static>(
+        &mut self,
+        keys: impl IntoIterator<Item = K>,
+        mut build: impl FnMut(&K) -> E,
+    ) -> bool {
+        let previous_ids = self.ids().collect::<Vec<_>>();
+        let mut existing = std::mem::take(&mut self.children)
+            .into_iter()
+            .collect::<HashMap<_, _>>();
+
+        let keys = keys.into_iter().collect::<Vec<_>>();
+        let mut seen = HashSet::with_capacity(keys.len());
+        let first_occurrences = keys.iter().map(|key| seen.insert(key)).collect::<Vec<_>>();
+        for (key, first) in keys.into_iter().zip(first_occurrences) {
+            if !first {
+                continue;
             }
+            let child = existing
+                .remove(&key)
+                .unwrap_or_else(|| ElementBuilder::new(build(&key)).into_child());
+            self.children.push((key, child));
         }
+        self.removed.extend(existing.into_values());
 
-        if axis == Axis::Vertical {
-            let gap_count = (self.children.len() - 1) as f32;
-            length += gap_count * self.gap;
-        }
-
-        length
+        !self.ids().eq(previous_ids)
     }
 
-    fn on_child_hover(&mut self, pass: &mut EventPass<'_>, hovered: bool) {
-        if hovered {
-            self.border_color = Rgba {
-                r: 133,
-                g: 133,
-                b: 133,
-                a: 255,
-            };
-        } else {
-            self.border_color = Rgba {
-                r: 111,
-                g: 111,
-                b: 111,
-                a: 255,
-            };
-        }
-        pass.request_render();
+    pub fn get(&self, key: &K) -> Option<&ChildElement> {
+        self.children
+            .iter()
+            .find_map(|(child_key, child)| (child_key == key).then_some(child))
     }
-}
 
-pub struct Row {
-    children: Vec<ChildElement>,
-    background_color: Rgba,
-    border_color: Rgba,
-    gap: f32,
+    pub fn get_mut(&mut self, key: &K) -> Option<&mut ChildElement> {
+        self.children
+            .iter_mut()
+            .find_map(|(child_key, child)| (*child_key == *key).then_some(child))
+    }
 }
 
-impl Row {
-    pub fn new() -> Self {
-        Self {
-            children: Vec::new(),
-            background_color: Rgba {
-                r: 33,
-                g: 33,
-                b: 33,
-                a: 255,
-            },
-            border_color: Rgba {
-                r: 111,
-                g: 111,
-                b: 111,
-                a: 255,
-            },
-            gap: 0.0,
-        }
+impl<K> KeyedChildren<K> {
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.children.len()
     }
 
-    pub fn with_gap(mut self, gap: f32) -> Self {
-        self.gap = gap;
-        self
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.children.is_empty()
     }
 
-    pub fn with(mut self, child: impl Element + 'static) -> Self {
-        self.children.push(ElementBuilder::new(child).into_child());
-        self
+    /// Remove every child.
+    pub fn clear(&mut self) {
+        self.removed
+            .extend(self.children.drain(..).map(|(_, child)| child));
     }
-}
 
-impl Element for Row {
-    fn children_ids(&self) -> Vec<u64> {
-        self.children.iter().map(|child| child.id()).collect()
+    /// The IDs of the children, in order, for [`Element::children_ids`].
+    pub fn ids(&self) -> impl Iterator<Item = u64> + '_ {
+        self.children.iter().map(|(_, child)| child.id())
     }
 
-    fn update_children(&mut self, pass: &mut UpdatePass<'_>) {
-        for child in self.children.iter_mut() {
-            pass.update_child(child);
-        }
+    pub fn iter(&self) -> impl Iterator<Item = (&K, &ChildElement)> {
+        self.children.iter().map(|(key, child)| (key, child))
     }
 
-    fn render(&mut self, pass: &mut RenderPass<'_>) {
-        pass.fill_quad(pass.bounds(), self.background_color, 1.0, self.border_color);
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = (&K, &mut ChildElement)> {
+        self.children.iter_mut().map(|(key, child)| (&*key, child))
     }
 
-    fn layout(&mut self, pass: &mut LayoutPass<'_>) {
-        let width = Length::FitContent(pass.size.x);
-        let height = Length::FitContent(pass.size.y);
-        let auto_size = Xy::new(width, height);
+    /// Add new children to the tree and remove old ones.
+    pub fn update(&mut self, pass: &mut UpdatePass<'_>) {
+        for child in self.removed.drain(..) {
+            pass.remove_child(child);
+        }
+        for (_, child) in &mut self.children {
+            pass.update_child(child);
+        }
+    }
+}
 
-        let mut x_offset = 0.0;
-        for child in &mut self.children {
-            let child_size = pass.resolve_size(child.id(), auto_size);
-            pass.do_layout(child, child_size);
-            pass.place_child(child, Xy::new(x_offset, 0.0));
+pub struct TypedChildElement<E: Element> {
+    pub inner: ChildElement,
+    _type: PhantomData<E>,
+}
 
-            x_offset += child_size.x + self.gap;
+impl<E: Element + 'static> TypedChildElement<E> {
+    pub fn new(element: E) -> Self {
+        Self {
+            inner: ElementBuilder::new(element).into_child(),
+            _type: PhantomData,
         }
     }
 
-    fn measure(
-        &mut self,
-        context: &mut MeasureContext<'_>,
-        axis: Axis,
-        length_request: LengthRequest,
-        cross_length: Option<f32>,
-    ) -> f32 {
-        let length_request = match length_request {
-            LengthRequest::MinContent | LengthRequest::MaxContent => length_request,
-            LengthRequest::FitContent(_space) => LengthRequest::MinContent,
-        };
+    #[inline]
+    pub fn id(&self) -> u64 {
+        self.inner.id
+    }
+}
 
-        let fallback_length = length_request.into();
 
-        let mut length: f32 = 0.0;
-        for child in &mut self.children {
-            let child_length =
-                context.resolve_length(child.id(), axis, fallback_length, cross_length);
-            match axis {
-                Axis::Horizontal => length += child_length,
-                Axis::Vertical => length = length.max(child_length),
-            }
-        }
 
-        if axis == Axis::Horizontal {
-            let gap_count = (self.children.len() - 1) as f32;
-            length += gap_count * self.gap;
-        }
+pub trait ExtensionElement {
+    fn element(&self) -> &dyn Element;
+    fn element_mut(&mut self) -> &mut dyn Element;
 
-        length
+    #[inline(always)]
+    fn children_ids(&self) -> Vec<u64> {
+        self.element().children_ids()
     }
 
-    fn on_child_hover(&mut self, pass: &mut EventPass<'_>, hovered: bool) {
-        if hovered {
-            self.border_color = Rgba {
-                r: 133,
-                g: 133,
-                b: 133,
-                a: 255,
-            };
-        } else {
-            self.border_color = Rgba {
-                r: 111,
-                g: 111,
-                b: 111,
-                a: 255,
-            };
-        }
-        pass.request_render();
+    #[inline(always)]
+    fn update_children(&mut self, pass: &mut UpdatePass<'_>) {
+        self.element_mut().update_children(pass)
     }
-}
 
-pub struct ScrollBar {
-    progress: f32,
-    area_height: f32,
-    content_height: f32,
-    grab_anchor: Option<f32>,
-    moved: bool,
-}
+    #[inline(always)]
+    fn accepts_pointer_events(&self) -> bool {
+        self.element().accepts_pointer_events()
+    }
 
-impl ScrollBar {
-    pub fn new() -> Self {
-        Self {
-            progress: 0.0,
-            area_height: 0.0,
-            content_height: 0.0,
-            grab_anchor: None,
-            moved: false,
-        }
+    #[inline(always)]
+    fn accepts_keyboard_events(&self) -> bool {
+        self.element().accepts_keyboard_events()
     }
-}
 
-impl Element for ScrollBar {
+    #[inline(always)]
+    fn accepts_focus_events(&self) -> bool {
+        self.element().accepts_focus_events()
+    }
+
+    #[inline(always)]
     fn render(&mut self, pass: &mut RenderPass<'_>) {
-        let height_ratio = if self.content_height != 0.0 {
-            self.area_height / self.content_height
-        } else {
-            1.0
-        };
-        let height_ratio = height_ratio.clamp(0.0, 1.0);
-        let min_height = 40.0; // TODO: Theme.
-        let layout_size = pass.bounds().size();
-        let bar_height = (height_ratio * layout_size.y).max(min_height);
-        let empty_space = layout_size.y - bar_height;
+        self.element_mut().render(pass)
+    }
 
-        pass.fill_quad(
-            Aabb2D::from_size_position(
-                Xy::new(layout_size.x, bar_height),
-                pass.bounds().position() + Xy::new(0.0, self.progress * empty_space),
-            ),
-            if self.grab_anchor.is_some() {
-                Rgba {
-                    r: 0x73,
-                    g: 0x73,
-                    b: 0x89,
-                    a: 255,
-                }
-            } else {
-                Rgba {
-                    r: 0x53,
-                    g: 0x53,
-                    b: 0x6d,
-                    a: 255,
-                }
-            },
-            0.0,
-            Rgba::NONE,
-        );
+    #[inline(always)]
+    fn render_overlay(&mut self, pass: &mut RenderPass<'_>) {
+        self.element_mut().render_overlay(pass)
+    }
+
+    #[inline(always)]
+    fn animate(&mut self, pass: &mut AnimatePass<'_>, dt: f64) {
+        self.element_mut().animate(pass, dt)
+    }
+
+    #[inline(always)]
+    fn layout(&mut self, pass: &mut LayoutPass<'_>) {
+        self.element_mut().layout(pass)
     }
 
+    #[inline(always)]
     fn measure(
         &mut self,
-        _context: &mut MeasureContext<'_>,
+        context: &mut MeasureContext<'_>,
         axis: Axis,
         length_request: LengthRequest,
-        _cross_length: Option<f32>,
+        cross_length: Option<f32>,
     ) -> f32 {
-        if axis == Axis::Vertical {
-            match length_request {
-                LengthRequest::MinContent | LengthRequest::MaxContent => self.area_height,
-                LengthRequest::FitContent(space) => space,
-            }
-        } else {
-            let scrollbar_width = 12.0; // TODO: Theming
+        self.element_mut()
+            .measure(context, axis, length_request, cross_length)
+    }
 
-            scrollbar_width
-        }
+    #[inline(always)]
+    fn compose(&mut self, pass: &mut ComposePass<'_>) {
+        self.element_mut().compose(pass)
+    }
+
+    #[inline(always)]
+    fn content_hash(&self) -> Option<u64> {
+        self.element().content_hash()
     }
 
+    #[inline(always)]
     fn cursor_icon(&self) -> CursorIcon {
-        if self.grab_anchor.is_some() {
-            CursorIcon::Grabbing
-        } else {
-            CursorIcon::Grab
-        }
+        self.element().cursor_icon()
     }
 
-    fn on_pointer_event(&mut self, pass: &mut EventPass<'_>, event: &PointerEvent) {
-        match event {
-            PointerEvent::Down {
-                position: mouse_pos,
-                ..
-            } => {
-                pass.capture_pointer();
+    #[inline(always)]
+    fn on_build(&mut self, pass: &mut UpdatePass<'_>) {
+        self.element_mut().on_build(pass);
+    }
 
-                let size = pass.bounds().size();
-                let height_ratio = if self.content_height != 0.0 {
-                    self.area_height / self.content_height
-                } else {
-                    1.0
-                };
-                let height_ratio = height_ratio.clamp(0.0, 1.0);
-                let min_height = 40.0; // TODO: Theme.
-                let bar_height = (height_ratio * size.y).max(min_height);
-                let empty_space = size.y - bar_height;
-
-                let bar_bounds = Aabb2D::from_size_position(
-                    Xy::new(size.x, bar_height),
-                    pass.bounds().position() + Xy::new(0.0, self.progress * empty_space),
-                );
+    #[inline(always)]
+    fn on_enter(&mut self, pass: &mut UpdatePass<'_>) {
+        self.element_mut().on_enter(pass);
+    }
 
-                // let mouse_pos = pass.local_position(*mouse_pos);
-                let mut changed = false;
-                if bar_bounds.contains(*mouse_pos) {
-                    let y_min = bar_bounds.min.y;
-                    let y_max = bar_bounds.max.y;
-                    self.grab_anchor = Some((mouse_pos.y - y_min) / (y_max - y_min));
-                } else {
-                    let height_ratio = if self.content_height != 0.0 {
-                        self.area_height / self.content_height
-                    } else {
-                        1.0
-                    };
-                    let height_ratio = height_ratio.clamp(0.0, 1.0);
-                    let min_height = 40.0; // TODO: Theme.
-                    let bar_height = (height_ratio * size.y).max(min_height);
-                    let empty_space = size.y - bar_height;
+    #[inline(always)]
+    fn on_exit(&mut self, pass: &mut UpdatePass<'_>) -> bool {
+        self.element_mut().on_exit(pass)
+    }
 
-                    let progress = (mouse_pos.y - bar_height * 0.5) / empty_space;
-                    let progress = progress.clamp(0.0, 1.0);
+    #[inline(always)]
+    fn on_keyboard_event(&mut self, pass: &mut EventPass<'_>, event: &KeyboardEvent) {
+        self.element_mut().on_keyboard_event(pass, event)
+    }
 
-                    changed |= (progress - self.progress).abs() > 1e-12;
+    #[inline(always)]
+    fn on_pointer_event(&mut self, pass: &mut EventPass<'_>, event: &PointerEvent) {
+        self.element_mut().on_pointer_event(pass, event)
+    }
 
-                    self.progress = progress;
-                    self.grab_anchor = Some(0.5);
-                };
-                if changed {
-                    pass.request_render();
-                }
-            }
-            PointerEvent::Move {
-                position: mouse_pos,
-            } => {
-                if let Some(grab_anchor) = self.grab_anchor {
-                    let size = pass.bounds().size();
-                    let height_ratio = if self.content_height != 0.0 {
-                        self.area_height / self.content_height
-                    } else {
-                        1.0
-                    };
-                    let height_ratio = height_ratio.clamp(0.0, 1.0);
-                    let min_height = 40.0; // TODO: Theme.
-                    let bar_height = (height_ratio * size.y).max(min_height);
-                    let empty_space = size.y - bar_height;
-
-                    let progress = (mouse_pos.y - bar_height * grab_anchor) / empty_space;
-                    let progress = progress.clamp(0.0, 1.0);
-                    if (progress - self.progress).abs() > 1e-12 {
-                        self.progress = progress;
-                        self.moved = true;
-                        pass.request_render();
-                    }
-                }
-            }
-            PointerEvent::Up { .. } => {
-                self.grab_anchor = None;
-            }
-            _ => {}
-        }
+    #[inline(always)]
+    fn on_gesture(&mut self, pass: &mut EventPass<'_>, gesture: &Gesture) {
+        self.element_mut().on_gesture(pass, gesture)
     }
-}
 
-pub struct VerticalScroll {
-    column: TypedChildElement<Column>,
-    scroll_bar: TypedChildElement<ScrollBar>,
-    viewport_offset: Xy<f32>,
-    content_size: Xy<f32>,
-}
+    #[inline(always)]
+    fn on_hovered_keyboard_event(&mut self, pass: &mut EventPass<'_>, event: &KeyboardEvent) {
+        self.element_mut().on_hovered_keyboard_event(pass, event)
+    }
 
-impl VerticalScroll {
-    pub fn new(column: Column) -> Self {
-        Self {
-            column: TypedChildElement::new(column),
-            scroll_bar: TypedChildElement::new(ScrollBar::new()),
-            viewport_offset: Xy::ZERO,
-            content_size: Xy::ZERO,
-        }
+    #[inline(always)]
+    fn on_hover(&mut self, pass: &mut EventPass<'_>, hovered: bool) {
+        self.element_mut().on_hover(pass, hovered)
     }
-}
 
-impl Element for VerticalScroll {
-    fn children_ids(&self) -> Vec<u64> {
-        vec![self.column.id(), self.scroll_bar.id()]
+    #[inline(always)]
+    fn on_child_hover(&mut self, pass: &mut EventPass<'_>, hovered: bool) {
+        self.element_mut().on_child_hover(pass, hovered)
     }
 
-    fn update_children(&mut self, pass: &mut UpdatePass<'_>) {
-        pass.update_child(&mut self.column.inner);
-        pass.update_child(&mut self.scroll_bar.inner);
+    #[inline(always)]
+    fn on_focus(&mut self, pass: &mut EventPass<'_>, focused: bool) {
+        self.element_mut().on_focus(pass, focused)
     }
 
-    fn layout(&mut self, pass: &mut LayoutPass<'_>) {
-        let auto_size = Xy::new(Length::FitContent(pass.size.x), Length::MaxContent);
-        self.content_size = pass.resolve_size(self.column.id(), auto_size);
+    #[inline(always)]
+    fn on_child_focus(&mut self, pass: &mut EventPass<'_>, focused: bool) {
+        self.element_mut().on_child_focus(pass, focused)
+    }
 
-        pass.do_layout(&mut self.column.inner, self.content_size);
-        pass.place_child(&mut self.column.inner, Xy::ZERO);
+    #[inline(always)]
+    fn on_scroll_into_view(&mut self, pass: &mut EventPass<'_>, area: &mut Aabb2D) {
+        self.element_mut().on_scroll_into_view(pass, area)
+    }
 
-        let viewport_max_pos = (self.content_size - pass.size).max(Xy::ZERO);
-        let pos = Xy::new(
-            self.viewport_offset.x.clamp(0.0, viewport_max_pos.x),
-            self.viewport_offset.y.clamp(0.0, viewport_max_pos.y),
-        );
-        if (pos - self.viewport_offset).length_squared() > 1e-12 {
-            self.viewport_offset = pos;
-        }
+    #[inline(always)]
+    fn context_menu(&mut self, position: Xy<f32>) -> Option<ContextMenu> {
+        self.element_mut().context_menu(position)
+    }
 
-        {
-            let area_size = pass.size;
-            let scroll_bar = pass.typed_child_mut(&mut self.scroll_bar);
-            scroll_bar.area_height = area_size.y;
-            scroll_bar.content_height = self.content_size.y;
-            pass.request_child_render(self.scroll_bar.id());
-        }
+    #[inline(always)]
+    fn on_context_menu_action(&mut self, pass: &mut EventPass<'_>, action: u64) {
+        self.element_mut().on_context_menu_action(pass, action)
+    }
 
-        let scroll_bar_size = pass.resolve_size(
-            self.scroll_bar.id(),
-            Xy::new(
-                Length::FitContent(pass.size.x),
-                Length::FitContent(pass.size.y),
-            ),
-        );
-        pass.do_layout(&mut self.scroll_bar.inner, scroll_bar_size);
-        pass.place_child(
-            &mut self.scroll_bar.inner,
-            Xy::new(pass.size.x - scroll_bar_size.x, 0.0),
-        );
+    #[inline(always)]
+    fn on_message(&mut self, pass: &mut EventPass<'_>, message: Box<dyn Any>) {
+        self.element_mut().on_message(pass, message)
     }
 
-    fn measure(
-        &mut self,
-        context: &mut MeasureContext<'_>,
-        axis: Axis,
-        length_request: LengthRequest,
-        cross_length: Option<f32>,
-    ) -> f32 {
-        match length_request {
-            LengthRequest::MaxContent => {
-                context.resolve_length(self.column.id(), axis, Length::MaxContent, cross_length)
-            }
-            LengthRequest::MinContent => 0.0,
-            LengthRequest::FitContent(space) => space,
-        }
+    #[inline(always)]
+    fn on_timer(&mut self, pass: &mut EventPass<'_>, token: TimerToken) {
+        self.element_mut().on_timer(pass, token)
     }
 
-    fn compose(&mut self, pass: &mut ComposePass<'_>) {
-        pass.set_child_scroll(
-            &mut self.column.inner,
-            Xy::new(-self.viewport_offset.x, -self.viewport_offset.y),
-        );
+    #[inline(always)]
+    fn on_drag_over(&mut self, pass: &mut EventPass<'_>, drag: &Drag) -> bool {
+        self.element_mut().on_drag_over(pass, drag)
     }
 
-    fn on_pointer_event(&mut self, pass: &mut EventPass<'_>, event: &PointerEvent) {
-        let scroll_range = (self.content_size - pass.state.bounds.size()).max(Xy::ZERO);
+    #[inline(always)]
+    fn on_drag_leave(&mut self, pass: &mut EventPass<'_>) {
+        self.element_mut().on_drag_leave(pass)
+    }
 
-        let mut changed = false;
-        match event {
-            PointerEvent::Scroll { delta } => {
-                let pixel_delta = delta.to_pixels(Xy::new(120.0, 120.0));
-                let delta = Xy::new(0.0, pixel_delta.y);
-                let pos = self.viewport_offset - delta;
-                let pos = Xy::new(
-                    pos.x.clamp(0.0, scroll_range.x),
-                    pos.y.clamp(0.0, scroll_range.y),
-                );
-
-                if (pos - self.viewport_offset).length_squared() > 1e-12 {
-                    changed = true;
-                    self.viewport_offset = pos;
-                    pass.set_handled();
-                }
-            }
-            _ => {}
-        }
-        {
-            let scroll_bar = pass.typed_child_mut(&mut self.scroll_bar);
-            if scroll_bar.moved {
-                scroll_bar.moved = false;
-                let y = scroll_bar.progress * scroll_range.y;
-                let pos = Xy::new(self.viewport_offset.x, y.clamp(0.0, scroll_range.y));
-                if (pos - self.viewport_offset).length_squared() > 1e-12 {
-                    changed = true;
-                    self.viewport_offset = pos;
-                }
-            }
-        }
+    #[inline(always)]
+    fn on_drop(&mut self, pass: &mut EventPass<'_>, drag: Drag) {
+        self.element_mut().on_drop(pass, drag)
+    }
 
-        if changed {
-            pass.set_handled();
-            pass.request_compose();
-            let progress = if scroll_range.y > 1e-12 {
-                (self.viewport_offset.y / scroll_range.y).clamp(0.0, 1.0)
-            } else {
-                0.0
-            };
+    #[inline(always)]
+    fn save_state(&self) -> Option<Vec<u8>> {
+        self.element().save_state()
+    }
 
-            {
-                let scroll_bar = pass.typed_child_mut(&mut self.scroll_bar);
-                scroll_bar.progress = progress;
-                pass.request_child_render(self.scroll_bar.id());
-            }
-        }
+    #[inline(always)]
+    fn restore_state(&mut self, state: &[u8]) {
+        self.element_mut().restore_state(state)
     }
 }
 
-pub struct Label {
-    pub text: Arc<str>,
-    pub font_size: f32,
-    pub color: Rgba,
-    // pub visual_font_size: AnimatedF32,
-    pub line_height: LineHeight,
-    pub font_style: FontStyle,
-    pub alignment: TextAlignment,
-    pub wrap_mode: TextWrapMode,
-}
+impl<T: ExtensionElement + 'static> Element for T {
+    #[inline(always)]
+    fn children_ids(&self) -> Vec<u64> {
+        self.children_ids()
+    }
 
-impl Label {
-    pub fn new(text: impl Into<Arc<str>>) -> Self {
-        Self {
-            text: text.into(),
-            color: Rgba::WHITE,
-            font_size: 16.0,
-            line_height: LineHeight::FONT_PREFERRED,
-            font_style: FontStyle::Normal,
-            alignment: TextAlignment::Start,
-            wrap_mode: TextWrapMode::Wrap,
-            // visual_font_size: AnimatedF32::new(16.0),
-        }
+    #[inline(always)]
+    fn update_children(&mut self, pass: &mut UpdatePass<'_>) {
+        self.update_children(pass)
     }
 
-    pub fn with_color(mut self, color: Rgba) -> Self {
-        self.color = color;
-        self
+    #[inline(always)]
+    fn accepts_pointer_events(&self) -> bool {
+        self.accepts_pointer_events()
     }
 
-    pub fn with_font_size(mut self, font_size: f32) -> Self {
-        self.font_size = font_size;
-        // self.visual_font_size = AnimatedF32::new(font_size);
-        self
+    #[inline(always)]
+    fn accepts_keyboard_events(&self) -> bool {
+        self.accepts_keyboard_events()
     }
-}
 
-impl Element for Label {
+    #[inline(always)]
+    fn accepts_focus_events(&self) -> bool {
+        self.accepts_focus_events()
+    }
+
+    #[inline(always)]
     fn render(&mut self, pass: &mut RenderPass<'_>) {
-        unsafe { __ui_Label__render(self, pass) }
+        self.render(pass)
     }
 
-    // fn animate(&mut self, pass: &mut AnimatePass<'_>, dt: f64) {
-    //     let ms = (dt * 1000.0) as f32;
-    //     let done = self.visual_font_size.advance(ms);
-    //     if !done {
-    //         pass.request_animate();
-    //     }
-    //     pass.request_render();
-    // }
+    #[inline(always)]
+    fn render_overlay(&mut self, pass: &mut RenderPass<'_>) {
+        self.render_overlay(pass)
+    }
+
+    #[inline(always)]
+    fn animate(&mut self, pass: &mut AnimatePass<'_>, dt: f64) {
+        self.animate(pass, dt)
+    }
+
+    #[inline(always)]
+    fn layout(&mut self, pass: &mut LayoutPass<'_>) {
+        self.layout(pass)
+    }
 
+    #[inline(always)]
     fn measure(
         &mut self,
         context: &mut MeasureContext<'_>,
@@ -1411,131 +2060,334 @@ impl Element for Label {
         length_request: LengthRequest,
         cross_length: Option<f32>,
     ) -> f32 {
-        unsafe { __ui_Label__measure(self, context, axis, length_request, cross_length) }
+        self.measure(context, axis, length_request, cross_length)
+    }
+
+    #[inline(always)]
+    fn compose(&mut self, pass: &mut ComposePass<'_>) {
+        self.compose(pass)
+    }
+
+    #[inline(always)]
+    fn content_hash(&self) -> Option<u64> {
+        self.content_hash()
     }
 
+    #[inline(always)]
     fn cursor_icon(&self) -> CursorIcon {
-        CursorIcon::IBeam
+        self.cursor_icon()
+    }
+
+    #[inline(always)]
+    fn on_build(&mut self, pass: &mut UpdatePass<'_>) {
+        self.on_build(pass);
+    }
+
+    #[inline(always)]
+    fn on_enter(&mut self, pass: &mut UpdatePass<'_>) {
+        self.on_enter(pass);
+    }
+
+    #[inline(always)]
+    fn on_exit(&mut self, pass: &mut UpdatePass<'_>) -> bool {
+        self.on_exit(pass)
+    }
+
+    #[inline(always)]
+    fn on_keyboard_event(&mut self, pass: &mut EventPass<'_>, event: &KeyboardEvent) {
+        self.on_keyboard_event(pass, event)
     }
 
+    #[inline(always)]
     fn on_pointer_event(&mut self, pass: &mut EventPass<'_>, event: &PointerEvent) {
-        if matches!(
-            event,
-            PointerEvent::Down {
-                button: PointerButton::Primary,
-                ..
-            },
-        ) {
-            pass.request_focus();
-        }
+        self.on_pointer_event(pass, event)
     }
 
-    // fn on_hover(&mut self, pass: &mut EventPass<'_>, hovered: bool) {
-    //     if hovered {
-    //         self.visual_font_size.move_to(self.font_size * 2.0, 1000.0);
-    //     } else {
-    //         self.visual_font_size.move_to(self.font_size, 1000.0);
-    //     }
-    //     pass.request_animate();
-    //     pass.request_render();
-    //     pass.set_handled();
-    // }
+    #[inline(always)]
+    fn on_gesture(&mut self, pass: &mut EventPass<'_>, gesture: &Gesture) {
+        self.on_gesture(pass, gesture)
+    }
+
+    #[inline(always)]
+    fn on_hovered_keyboard_event(&mut self, pass: &mut EventPass<'_>, event: &KeyboardEvent) {
+        self.on_hovered_keyboard_event(pass, event)
+    }
+
+    #[inline(always)]
+    fn on_hover(&mut self, pass: &mut EventPass<'_>, hovered: bool) {
+        self.on_hover(pass, hovered)
+    }
+
+    #[inline(always)]
+    fn on_child_hover(&mut self, pass: &mut EventPass<'_>, hovered: bool) {
+        self.on_child_hover(pass, hovered)
+    }
 
+    #[inline(always)]
     fn on_focus(&mut self, pass: &mut EventPass<'_>, focused: bool) {
-        if focused {
-            self.font_size *= 2.0;
-        } else {
-            self.font_size /= 2.0;
-        }
-        pass.request_layout();
-        pass.request_render();
-        pass.set_handled();
+        self.on_focus(pass, focused)
     }
-}
 
-unsafe extern "Rust" {
-    fn __ui_Label__render(label: &mut Label, pass: &mut RenderPass<'_>);
+    #[inline(always)]
+    fn on_child_focus(&mut self, pass: &mut EventPass<'_>, focused: bool) {
+        self.on_child_focus(pass, focused)
+    }
 
-    fn __ui_Label__measure(
-        label: &mut Label,
-        context: &mut MeasureContext<'_>,
-        axis: Axis,
-        length_request: LengthRequest,
-        cross_length: Option<f32>,
-    ) -> f32;
-}
+    #[inline(always)]
+    fn on_scroll_into_view(&mut self, pass: &mut EventPass<'_>, area: &mut Aabb2D) {
+        self.on_scroll_into_view(pass, area)
+    }
 
-pub struct LineInput {
-    pub text: String,
-    pub font_size: f32,
+    #[inline(always)]
+    fn context_menu(&mut self, position: Xy<f32>) -> Option<ContextMenu> {
+        self.context_menu(position)
+    }
 
-    cursor_offset: usize,
-    width_before_cursor: f32,
-    width_after_cursor: f32,
-    show_cursor: bool,
-}
+    #[inline(always)]
+    fn on_context_menu_action(&mut self, pass: &mut EventPass<'_>, action: u64) {
+        self.on_context_menu_action(pass, action)
+    }
 
-impl LineInput {
-    pub fn new(text: impl ToString) -> Self {
-        let text = text.to_string();
-        let cursor_offset = text.len();
-        Self {
-            text,
-            font_size: 16.0,
-            cursor_offset,
-            width_before_cursor: 0.0,
-            width_after_cursor: 0.0,
-            show_cursor: false,
-        }
+    #[inline(always)]
+    fn on_message(&mut self, pass: &mut EventPass<'_>, message: Box<dyn Any>) {
+        self.on_message(pass, message)
     }
 
-    pub fn with_font_size(mut self, font_size: f32) -> Self {
-        self.font_size = font_size;
-        self
+    #[inline(always)]
+    fn on_timer(&mut self, pass: &mut EventPass<'_>, token: TimerToken) {
+        self.on_timer(pass, token)
+    }
+
+    #[inline(always)]
+    fn on_drag_over(&mut self, pass: &mut EventPass<'_>, drag: &Drag) -> bool {
+        self.on_drag_over(pass, drag)
+    }
+
+    #[inline(always)]
+    fn on_drag_leave(&mut self, pass: &mut EventPass<'_>) {
+        self.on_drag_leave(pass)
+    }
+
+    #[inline(always)]
+    fn on_drop(&mut self, pass: &mut EventPass<'_>, drag: Drag) {
+        self.on_drop(pass, drag)
+    }
+
+    #[inline(always)]
+    fn save_state(&self) -> Option<Vec<u8>> {
+        self.save_state()
+    }
+
+    #[inline(always)]
+    fn restore_state(&mut self, state: &[u8]) {
+        self.restore_state(state)
     }
 }
 
-impl Element for LineInput {
-    fn render(&mut self, pass: &mut RenderPass<'_>) {
-        pass.fill_quad(
-            pass.bounds(),
-            Rgba::NONE,
-            1.0,
-            Rgba {
-                r: 111,
-                g: 111,
-                b: 111,
-                a: 255,
-            },
+
+
+macro_rules! impl_deref_for_simple_extensions {
+    ($($ty:ty),* $(,)?) => {
+        $(
+            impl<E: Element> Deref for $ty {
+                type Target = E;
+
+                #[inline]
+                fn deref(&self) -> &Self::Target {
+                    &self.element
+                }
+            }
+
+            impl<E: Element> DerefMut for $ty {
+                #[inline]
+                fn deref_mut(&mut self) -> &mut Self::Target {
+                    &mut self.element
+                }
+            }
+        )*
+    };
+}
+
+impl_deref_for_simple_extensions![OnClick<E>, OnHover<E>, Tooltip<E>];
+
+
+
+pub struct OnHover<E: Element> {
+    pub element: E,
+    pub callback: fn(&mut E, &mut EventPass<'_>, bool),
+}
+
+impl<E: Element> OnHover<E> {
+    #[inline(always)]
+    pub const fn new(element: E, callback: fn(&mut E, &mut EventPass<'_>, bool)) -> Self {
+        Self { element, callback }
+    }
+}
+
+impl<E: Element> ExtensionElement for OnHover<E> {
+    #[inline(always)]
+    fn element(&self) -> &dyn Element {
+        &self.element
+    }
+
+    #[inline(always)]
+    fn element_mut(&mut self) -> &mut dyn Element {
+        &mut self.element
+    }
+
+    #[inline(always)]
+    fn on_hover(&mut self, pass: &mut EventPass<'_>, hovered: bool) {
+        (self.callback)(&mut self.element, pass, hovered)
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum TooltipPlacement {
+    Above,
+    #[default]
+    Below,
+    Left,
+    Right,
+}
+
+pub struct Tooltip<E: Element> {
+    pub element: E,
+    pub text: String,
+    pub font_size: f32,
+    /// How long the pointer must hover over the element before the tooltip is
+    /// shown.
+    pub delay_ms: f32,
+    pub placement: TooltipPlacement,
+
+    hover_time_ms: Option<f32>,
+    visible: bool,
+    text_size: Xy<f32>,
+}
+
+impl<E: Element> Tooltip<E> {
+    pub fn new(element: E, text: impl ToString) -> Self {
+        Self {
+            element,
+            text: text.to_string(),
+            font_size: 14.0,
+            delay_ms: 500.0,
+            placement: TooltipPlacement::Below,
+            hover_time_ms: None,
+            visible: false,
+            text_size: Xy::ZERO,
+        }
+    }
+
+    pub fn with_delay(mut self, delay_ms: f32) -> Self {
+        self.delay_ms = delay_ms;
+        self
+    }
+
+    pub fn with_placement(mut self, placement: TooltipPlacement) -> Self {
+        self.placement = placement;
+        self
+    }
+
+    pub fn with_font_size(mut self, font_size: f32) -> Self {
+        self.font_size = font_size;
+        self
+    }
+
+    #[inline]
+    pub fn is_visible(&self) -> bool {
+        self.visible
+    }
+
+    fn dismiss(&mut self, pass: &mut EventPass<'_>) {
+        self.hover_time_ms = None;
+        if self.visible {
+            self.visible = false;
+            pass.request_overlay_render();
+        }
+    }
+
+    /// The bounds of the bubble next to `anchor`, with the given padding around
+    /// the text and gap between them.
+    fn bubble_bounds(&self, anchor: Aabb2D, padding: f32, gap: f32) -> Aabb2D {
+        let size = self.text_size + Xy::new(padding * 2.0, padding * 2.0);
+        let anchor_size = anchor.size();
+
+        let position = match self.placement {
+            TooltipPlacement::Above => Xy::new(
+                anchor.min.x + (anchor_size.x - size.x) * 0.5,
+                anchor.min.y - gap - size.y,
+            ),
+            TooltipPlacement::Below => Xy::new(
+                anchor.min.x + (anchor_size.x - size.x) * 0.5,
+                anchor.max.y + gap,
+            ),
+            TooltipPlacement::Left => Xy::new(
+                anchor.min.x - gap - size.x,
+                anchor.min.y + (anchor_size.y - size.y) * 0.5,
+            ),
+            TooltipPlacement::Right => Xy::new(
+                anchor.max.x + gap,
+                anchor.min.y + (anchor_size.y - size.y) * 0.5,
+            ),
+        };
+
+        Aabb2D::from_size_position(size, position)
+    }
+}
+
+impl<E: Element> ExtensionElement for Tooltip<E> {
+    #[inline(always)]
+    fn element(&self) -> &dyn Element {
+        &self.element
+    }
+
+    #[inline(always)]
+    fn element_mut(&mut self) -> &mut dyn Element {
+        &mut self.element
+    }
+
+    fn render_overlay(&mut self, pass: &mut RenderPass<'_>) {
+        self.element.render_overlay(pass);
+
+        if !self.visible {
+            return;
+        }
+
+        let padding = pass.metric("spacing.small");
+        let bubble_bounds = self.bubble_bounds(pass.bounds(), padding, padding);
+        pass.fill_quad(
+            bubble_bounds,
+            pass.color("surface.raised"),
+            1.0,
+            pass.color("border"),
         );
         pass.fill_text(
             &self.text,
-            pass.bounds()
-                .with_width(self.width_before_cursor + self.width_after_cursor),
-            Rgba {
-                r: 177,
-                g: 177,
-                b: 177,
-                a: 255,
-            },
+            Aabb2D::from_size_position(
+                self.text_size,
+                bubble_bounds.position() + Xy::new(padding, padding),
+            ),
+            pass.color("text.muted"),
             self.font_size,
         );
+    }
 
-        if self.show_cursor {
-            let cursor_size = Xy::new(2.0, pass.bounds().size().y);
-            let cursor_pos = pass.bounds().position() + Xy::new(self.width_before_cursor, 0.0);
+    fn animate(&mut self, pass: &mut AnimatePass<'_>, dt: f64) {
+        self.element.animate(pass, dt);
 
-            pass.fill_quad(
-                Aabb2D::from_size_position(cursor_size, cursor_pos),
-                Rgba {
-                    r: 177,
-                    g: 177,
-                    b: 177,
-                    a: 200,
-                },
-                0.0,
-                Rgba::NONE,
-            );
+        let Some(hover_time_ms) = &mut self.hover_time_ms else {
+            return;
+        };
+        if self.visible {
+            return;
+        }
+
+        *hover_time_ms += (dt * 1000.0) as f32;
+        if *hover_time_ms >= self.delay_ms {
+            self.visible = true;
+            pass.request_overlay_render();
+        } else {
+            pass.request_animate();
         }
     }
 
@@ -1544,1392 +2396,6432 @@ impl Element for LineInput {
         context: &mut MeasureContext<'_>,
         axis: Axis,
         length_request: LengthRequest,
-        _cross_length: Option<f32>,
+        cross_length: Option<f32>,
     ) -> f32 {
         let id = context.id();
-        let fonts = context.fonts_mut();
-        let max_advance = match axis {
-            Axis::Horizontal => match length_request {
-                LengthRequest::MinContent | LengthRequest::MaxContent => None,
-                LengthRequest::FitContent(space) => Some(space),
-            },
-            Axis::Vertical => None,
-        };
-        let before_cursor_size = fonts.measure_text(
-            id,
-            &self.text[..self.cursor_offset],
-            max_advance,
-            self.font_size,
-            LineHeight::Relative(1.0),
-            FontStyle::Normal,
-            TextAlignment::Start,
-            TextWrapMode::NoWrap,
-        );
-        let after_cursor_size = fonts.measure_text(
+        self.text_size = context.fonts_mut().measure_text(
             id,
-            &self.text[self.cursor_offset..],
-            max_advance,
+            &self.text,
+            &[],
+            None,
             self.font_size,
             LineHeight::Relative(1.0),
+            FontFamily::Proportional,
             FontStyle::Normal,
             TextAlignment::Start,
             TextWrapMode::NoWrap,
         );
 
-        self.width_before_cursor = before_cursor_size.x;
-        self.width_after_cursor = after_cursor_size.x;
-
-        match axis {
-            Axis::Horizontal => match length_request {
-                LengthRequest::MinContent | LengthRequest::MaxContent => {
-                    before_cursor_size.x + after_cursor_size.x
-                }
-                LengthRequest::FitContent(space) => space,
-            },
-            Axis::Vertical => before_cursor_size.y,
-        }
+        self.element
+            .measure(context, axis, length_request, cross_length)
     }
 
-    fn cursor_icon(&self) -> CursorIcon {
-        CursorIcon::IBeam
-    }
+    fn on_hovered_keyboard_event(&mut self, pass: &mut EventPass<'_>, event: &KeyboardEvent) {
+        self.element.on_hovered_keyboard_event(pass, event);
 
-    fn on_keyboard_event(&mut self, pass: &mut EventPass<'_>, event: &KeyboardEvent) {
-        match event {
-            KeyboardEvent::Down { key } => {
-                if self.cursor_offset > self.text.len() {
-                    self.cursor_offset = self.text.len();
-                }
-                match key {
-                    Key::Char(ch) => {
-                        self.text.insert(self.cursor_offset, *ch);
-                        self.cursor_offset = self.cursor_offset.saturating_add(1);
-                    }
-                    Key::Backspace => {
-                        if self.cursor_offset == 0 {
-                            return;
-                        }
-                        _ = self.text.remove(self.cursor_offset.saturating_sub(1));
-                        self.cursor_offset = self.cursor_offset.saturating_sub(1);
-                    }
-                    Key::Delete => {
-                        if self.cursor_offset >= self.text.len() {
-                            return;
-                        }
-                        _ = self.text.remove(self.cursor_offset);
-                    }
-                    Key::ArrowLeft => {
-                        self.cursor_offset = self.cursor_offset.saturating_sub(1);
-                    }
-                    Key::ArrowRight => {
-                        if self.cursor_offset >= self.text.len() {
-                            return;
-                        }
-                        self.cursor_offset = self.cursor_offset.saturating_add(1);
-                    }
-                    _ => {
-                        return;
-                    }
-                }
-                pass.request_layout();
-                pass.request_render();
-                pass.set_handled();
-            }
-            KeyboardEvent::Up { key: _ } => {}
+        if matches!(event, KeyboardEvent::Down { .. }) {
+            self.dismiss(pass);
         }
     }
 
-    fn on_pointer_event(&mut self, pass: &mut EventPass<'_>, event: &PointerEvent) {
-        if matches!(
-            event,
-            PointerEvent::Down {
-                button: PointerButton::Primary,
-                ..
-            },
-        ) {
-            pass.request_focus();
-        }
-    }
+    // NOTE: This is called for the hovered element itself as well as its
+    //       ancestors, so it covers wrapped elements that have children of
+    //       their own.
+    fn on_child_hover(&mut self, pass: &mut EventPass<'_>, hovered: bool) {
+        self.element.on_child_hover(pass, hovered);
 
-    fn on_focus(&mut self, pass: &mut EventPass<'_>, focused: bool) {
-        self.show_cursor = focused;
-        pass.request_render();
-        pass.set_handled();
+        if hovered {
+            self.hover_time_ms = Some(0.0);
+            pass.request_animate();
+        } else {
+            self.dismiss(pass);
+        }
     }
 }
 
-
-
-#[derive(Clone, Debug)]
-pub struct AnimatedF32 {
-    current: f32,
-    target: f32,
-    rate: f32,
+/// Triggers actions from keyboard shortcut sequences that reach the wrapped
+/// element, showing the possible continuations of a pending sequence in the
+/// overlay layer.
+///
+/// Keys that start a sequence are held back from the wrapped element until the
+/// sequence is completed, and passed on to it if the sequence is broken or
+/// times out instead.
+pub struct Shortcuts<E: Element, A: Clone + 'static> {
+    pub element: E,
+    pub map: ShortcutMap<A>,
+    pub callback: fn(&mut E, &mut EventPass<'_>, &A),
+    pub show_hints: bool,
+    pub font_size: f32,
+    /// The key presses in the pending sequence, along with the releases of
+    /// those keys.
+    held_events: Vec<KeyboardEvent>,
+    /// Abandons the pending sequence once it times out.
+    timeout_timer: Option<TimerToken>,
 }
 
-impl AnimatedF32 {
-    pub const fn new(value: f32) -> Self {
+impl<E: Element, A: Clone + 'static> Shortcuts<E, A> {
+    pub fn new(
+        element: E,
+        map: ShortcutMap<A>,
+        callback: fn(&mut E, &mut EventPass<'_>, &A),
+    ) -> Self {
         Self {
-            current: value,
-            target: value,
-            rate: 0.0,
+            element,
+            map,
+            callback,
+            show_hints: true,
+            font_size: 14.0,
+            held_events: Vec::new(),
+            timeout_timer: None,
         }
     }
 
-    #[inline]
-    pub const fn get(&self) -> f32 {
-        self.current
+    pub fn without_hints(mut self) -> Self {
+        self.show_hints = false;
+        self
     }
 
-    pub fn move_to(&mut self, target: f32, time_ms: f32) {
-        self.target = target;
-        match time_ms.partial_cmp(&0.0) {
-            Some(std::cmp::Ordering::Equal | std::cmp::Ordering::Less) => self.current = target,
-            Some(std::cmp::Ordering::Greater) => {
-                self.rate = (self.target - self.current) / time_ms;
+    /// Take the oldest `keys` held key presses, along with the releases that
+    /// followed them.
+    fn take_held_keys(&mut self, keys: usize) -> Vec<KeyboardEvent> {
+        let mut presses = 0;
+        let end = self
+            .held_events
+            .iter()
+            .position(|event| {
+                if let KeyboardEvent::Down { .. } = event {
+                    presses += 1;
+                }
+                presses > keys
+            })
+            .unwrap_or(self.held_events.len());
+
+        self.held_events.drain(..end).collect()
+    }
+
+    fn apply_results(&mut self, pass: &mut EventPass<'_>, results: Vec<ShortcutResult<A>>) {
+        for result in results {
+            match result {
+                ShortcutResult::Matched { action, keys } => {
+                    self.take_held_keys(keys);
+                    (self.callback)(&mut self.element, pass, &action);
+                }
+                ShortcutResult::Unmatched { keys } => {
+                    for event in self.take_held_keys(keys) {
+                        self.element.on_keyboard_event(pass, &event);
+                    }
+                }
             }
-            None => panic!(),
         }
     }
+}
+
+impl<E: Element, A: Clone + 'static> Deref for Shortcuts<E, A> {
+    type Target = E;
+
+    #[inline]
+    fn deref(&self) -> &Self::Target {
+        &self.element
+    }
+}
+
+impl<E: Element, A: Clone + 'static> DerefMut for Shortcuts<E, A> {
+    #[inline]
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.element
+    }
+}
+
+impl<E: Element, A: Clone + 'static> ExtensionElement for Shortcuts<E, A> {
+    #[inline(always)]
+    fn element(&self) -> &dyn Element {
+        &self.element
+    }
+
+    #[inline(always)]
+    fn element_mut(&mut self) -> &mut dyn Element {
+        &mut self.element
+    }
+
+    fn render_overlay(&mut self, pass: &mut RenderPass<'_>) {
+        self.element.render_overlay(pass);
+
+        if !self.show_hints || !self.map.is_pending() {
+            return;
+        }
+
+        let hints = self
+            .map
+            .continuations()
+            .map(|(keys, description)| {
+                let keys = keys
+                    .iter()
+                    .map(KeyChord::name)
+                    .collect::<Vec<_>>()
+                    .join(" ");
+                format!("{keys}  {description}")
+            })
+            .collect::<Vec<_>>();
+
+        let padding = pass.metric("spacing.small");
+        let line_height = (self.font_size * 1.2).round();
+        let bounds = pass.bounds();
+        let hints_height = line_height * hints.len() as f32 + padding * 2.0;
+        let hints_bounds = Aabb2D::new(
+            bounds.min.x,
+            bounds.max.y - hints_height,
+            bounds.max.x,
+            bounds.max.y,
+        );
+
+        pass.fill_quad(hints_bounds, pass.color("surface.raised"), 0.0, Rgba::NONE);
+        for (index, hint) in hints.iter().enumerate() {
+            pass.fill_text(
+                hint,
+                Aabb2D::from_size_position(
+                    Xy::new(bounds.size().x - padding * 2.0, line_height),
+                    hints_bounds.position()
+                        + Xy::new(padding, padding + line_height * index as f32),
+                ),
+                pass.color("text.muted"),
+                self.font_size,
+            );
+        }
+    }
+
+    fn on_timer(&mut self, pass: &mut EventPass<'_>, token: TimerToken) {
+        if self.timeout_timer != Some(token) {
+            self.element.on_timer(pass, token);
+            return;
+        }
+
+        self.timeout_timer = None;
+        let results = self.map.abandon();
+        self.apply_results(pass, results);
+        pass.request_overlay_render();
+    }
+
+    fn on_keyboard_event(&mut self, pass: &mut EventPass<'_>, event: &KeyboardEvent) {
+        let (key, modifiers) = match event {
+            KeyboardEvent::Down { key, modifiers, .. } => (*key, *modifiers),
+            KeyboardEvent::Up { key, .. } => {
+                let held = self.held_events.iter().any(|held| {
+                    matches!(held, KeyboardEvent::Down { key: held_key, .. } if held_key == key)
+                });
+                if held {
+                    self.held_events.push(*event);
+                    pass.set_handled();
+                } else {
+                    self.element.on_keyboard_event(pass, event);
+                }
+                return;
+            }
+        };
+
+        let was_pending = self.map.is_pending();
+        self.held_events.push(*event);
+        let results = self
+            .map
+            .handle_key(KeyChord::from_key_press(key, modifiers), Instant::now());
+        // The key is still held if it's part of the pending sequence, and was
+        // consumed if it completed one. Otherwise, it was just passed on.
+        let passed_on = !self.map.is_pending()
+            && matches!(results.last(), Some(ShortcutResult::Unmatched { .. }));
+        self.apply_results(pass, results);
+        if !passed_on {
+            pass.set_handled();
+        }
+
+        if let Some(timer) = self.timeout_timer.take() {
+            pass.cancel_timer(timer);
+        }
+        if self.map.is_pending() {
+            self.timeout_timer = Some(pass.request_timer(self.map.timeout()));
+        }
+        if was_pending || self.map.is_pending() {
+            pass.request_overlay_render();
+        }
+    }
+}
+
+pub struct OnClick<E: Element> {
+    pub element: E,
+    pub callback: fn(&mut E, &mut EventPass<'_>, bool),
+}
+
+impl<E: Element> OnClick<E> {
+    #[inline(always)]
+    pub const fn new(element: E, callback: fn(&mut E, &mut EventPass<'_>, bool)) -> Self {
+        Self { element, callback }
+    }
+}
+
+impl<E: Element> ExtensionElement for OnClick<E> {
+    #[inline(always)]
+    fn element(&self) -> &dyn Element {
+        &self.element
+    }
+
+    #[inline(always)]
+    fn element_mut(&mut self) -> &mut dyn Element {
+        &mut self.element
+    }
+
+    #[inline(always)]
+    fn on_pointer_event(&mut self, pass: &mut EventPass<'_>, event: &PointerEvent) {
+        match event {
+            PointerEvent::Down {
+                button: PointerButton::Primary,
+                ..
+            } => {
+                pass.capture_pointer();
+                (self.callback)(&mut self.element, pass, true)
+            }
+            PointerEvent::Up {
+                button: PointerButton::Primary,
+                ..
+            } => (self.callback)(&mut self.element, pass, false),
+            other => self.element.on_pointer_event(pass, other),
+        }
+    }
+}
+
+
+
+#[macro_export]
+macro_rules! column {
+    (@_ { $col:expr } gap: $gap:expr; $($rest:tt)*) => {
+        $crate::column!(@_ { $col .with_gap($gap) } $($rest)*)
+    };
+    (@_ { $col:expr } $($rest:expr),* $(,)?) => {
+        $col $(.with($rest))*
+    };
+    ($($items:tt)*) => {
+        $crate::column!(@_ { Column::new() } $($items)*)
+    };
+}
+
+#[macro_export]
+macro_rules! row {
+    (@_ { $row:expr } gap: $gap:expr; $($rest:tt)*) => {
+        $crate::row!(@_ { $row .with_gap($gap) } $($rest)*)
+    };
+    (@_ { $row:expr } $($rest:expr),* $(,)?) => {
+        $row $(.with($rest))*
+    };
+    ($($items:tt)*) => {
+        $crate::row!(@_ { Row::new() } $($items)*)
+    };
+}
+
+/// Build an element tree declaratively.
+///
+/// Each element is written as its type, followed by the arguments to its `new`
+/// function, its attributes, and its children, where each part is optional:
+///
+/// - Attributes are `name: value` pairs for common builder methods (`gap`,
+///   `padding`, `background_color`, `color`, `font_size`, and `cross_align`
+///   call `with_gap` and so on), or any other builder method called as
+///   `.method(args)`.
+/// - `on_click`, `on_hover`, and `tooltip` attributes wrap the element in an
+///   [`OnClick`], [`OnHover`], or [`Tooltip`], after everything else.
+/// - Children are added with the element's `with` method, so they're only
+///   supported by elements like [`Column`] and [`Row`]. An element built some
+///   other way can be given as a child in parentheses.
+///
+/// ```
+/// # use abi::{Column, CrossAlignment, LineInput, Row, view};
+/// let search = LineInput::new("");
+/// let column = view! {
+///     Column { gap: 8.0, padding: 4.0 } [
+///         LineInput("Title") { font_size: 20.0 },
+///         Row { gap: 4.0, cross_align: CrossAlignment::Center } [
+///             LineInput("Bigger") {
+///                 .with_history_depth(10),
+///                 on_click: |input, pass, pressed| {
+///                     if pressed {
+///                         input.font_size += 1.0;
+///                         pass.request_layout();
+///                     }
+///                 },
+///             },
+///             (search),
+///         ],
+///     ]
+/// };
+/// assert_eq!(column.len(), 2);
+/// ```
+#[macro_export]
+macro_rules! view {
+    (@with $element:expr;) => {
+        $element
+    };
+    (@with $element:expr; gap: $value:expr $(, $($rest:tt)*)?) => {
+        $crate::view!(@with $element.with_gap($value); $($($rest)*)?)
+    };
+    (@with $element:expr; padding: $value:expr $(, $($rest:tt)*)?) => {
+        $crate::view!(@with $element.with_padding($value); $($($rest)*)?)
+    };
+    (@with $element:expr; background_color: $value:expr $(, $($rest:tt)*)?) => {
+        $crate::view!(@with $element.with_background_color($value); $($($rest)*)?)
+    };
+    (@with $element:expr; color: $value:expr $(, $($rest:tt)*)?) => {
+        $crate::view!(@with $element.with_color($value); $($($rest)*)?)
+    };
+    (@with $element:expr; font_size: $value:expr $(, $($rest:tt)*)?) => {
+        $crate::view!(@with $element.with_font_size($value); $($($rest)*)?)
+    };
+    (@with $element:expr; cross_align: $value:expr $(, $($rest:tt)*)?) => {
+        $crate::view!(@with $element.with_cross_align($value); $($($rest)*)?)
+    };
+    (@with $element:expr; .$method:ident($($args:tt)*) $(, $($rest:tt)*)?) => {
+        $crate::view!(@with $element.$method($($args)*); $($($rest)*)?)
+    };
+    (@with $element:expr; on_click: $value:expr $(, $($rest:tt)*)?) => {
+        $crate::view!(@with $element; $($($rest)*)?)
+    };
+    (@with $element:expr; on_hover: $value:expr $(, $($rest:tt)*)?) => {
+        $crate::view!(@with $element; $($($rest)*)?)
+    };
+    (@with $element:expr; tooltip: $value:expr $(, $($rest:tt)*)?) => {
+        $crate::view!(@with $element; $($($rest)*)?)
+    };
+    (@with $element:expr; $name:ident: $($rest:tt)*) => {
+        compile_error!(concat!("unknown `view!` attribute `", stringify!($name), "`"))
+    };
+
+    (@wrap $element:expr;) => {
+        $element
+    };
+    (@wrap $element:expr; on_click: $callback:expr $(, $($rest:tt)*)?) => {
+        $crate::view!(@wrap $crate::OnClick::new($element, $callback); $($($rest)*)?)
+    };
+    (@wrap $element:expr; on_hover: $callback:expr $(, $($rest:tt)*)?) => {
+        $crate::view!(@wrap $crate::OnHover::new($element, $callback); $($($rest)*)?)
+    };
+    (@wrap $element:expr; tooltip: $text:expr $(, $($rest:tt)*)?) => {
+        $crate::view!(@wrap $crate::Tooltip::new($element, $text); $($($rest)*)?)
+    };
+    (@wrap $element:expr; $name:ident: $value:expr $(, $($rest:tt)*)?) => {
+        $crate::view!(@wrap $element; $($($rest)*)?)
+    };
+    (@wrap $element:expr; .$method:ident($($args:tt)*) $(, $($rest:tt)*)?) => {
+        $crate::view!(@wrap $element; $($($rest)*)?)
+    };
+
+    (@children $element:expr;) => {
+        $element
+    };
+    (@children $element:expr; ($child:expr) $(, $($rest:tt)*)?) => {
+        $crate::view!(@children $element.with($child); $($($rest)*)?)
+    };
+    (@children
+        $element:expr;
+        $($ty:ident)::+ $(($($args:tt)*))? $({ $($attrs:tt)* })? $([ $($children:tt)* ])?
+        $(, $($rest:tt)*)?
+    ) => {
+        $crate::view!(
+            @children
+            $element.with($crate::view!(
+                $($ty)::+ $(($($args)*))? $({ $($attrs)* })? $([ $($children)* ])?
+            ));
+            $($($rest)*)?
+        )
+    };
+
+    ($($ty:ident)::+ $(($($args:tt)*))? $({ $($attrs:tt)* })? $([ $($children:tt)* ])?) => {
+        $crate::view!(
+            @wrap
+            $crate::view!(
+                @children
+                $crate::view!(@with $($ty)::+::new($($($args)*)?); $($($attrs)*)?);
+                $($($children)*)?
+            );
+            $($($attrs)*)?
+        )
+    };
+}
+
+pub struct Column {
+    children: Vec<ChildElement>,
+    removed_children: Vec<ChildElement>,
+    background_color: Rgba,
+    border_color: Rgba,
+    gap: f32,
+    /// The space between the column's edges and its children.
+    padding: f32,
+}
+
+impl Column {
+    pub fn new() -> Self {
+        Self {
+            children: Vec::new(),
+            removed_children: Vec::new(),
+            background_color: Rgba {
+                r: 33,
+                g: 33,
+                b: 33,
+                a: 255,
+            },
+            border_color: Rgba {
+                r: 111,
+                g: 111,
+                b: 111,
+                a: 255,
+            },
+            gap: 0.0,
+            padding: 0.0,
+        }
+    }
+
+    pub fn with_gap(mut self, gap: f32) -> Self {
+        self.gap = gap;
+        self
+    }
+
+    pub fn with_padding(mut self, padding: f32) -> Self {
+        self.padding = padding;
+        self
+    }
+
+    pub fn with_background_color(mut self, color: Rgba) -> Self {
+        self.background_color = color;
+        self
+    }
+
+    pub fn with(mut self, child: impl Element + 'static) -> Self {
+        self.children.push(ElementBuilder::new(child).into_child());
+        self
+    }
+
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.children.len()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.children.is_empty()
+    }
+
+    /// Insert a child at the given index, returning its ID.
+    ///
+    /// The column's update must then be requested (see
+    /// [`EventPass::request_child_update`]), which adds the child to the tree
+    /// and calls its [`Element::on_enter`].
+    pub fn insert(&mut self, index: usize, child: impl Element + 'static) -> u64 {
+        let child = ElementBuilder::new(child).into_child();
+        let id = child.id();
+        self.children.insert(index, child);
+
+        id
+    }
+
+    /// See [`Column::insert`].
+    pub fn push(&mut self, child: impl Element + 'static) -> u64 {
+        self.insert(self.children.len(), child)
+    }
+
+    /// Remove the child at the given index, returning its ID.
+    ///
+    /// The column's update must then be requested (see
+    /// [`EventPass::request_child_update`]), which calls the child's
+    /// [`Element::on_exit`].
+    pub fn remove(&mut self, index: usize) -> u64 {
+        let child = self.children.remove(index);
+        let id = child.id();
+        self.removed_children.push(child);
+
+        id
+    }
+}
+
+impl Element for Column {
+    fn children_ids(&self) -> Vec<u64> {
+        self.children.iter().map(|child| child.id()).collect()
+    }
+
+    fn update_children(&mut self, pass: &mut UpdatePass<'_>) {
+        for child in self.removed_children.drain(..) {
+            pass.remove_child(child);
+        }
+        for child in self.children.iter_mut() {
+            pass.update_child(child);
+        }
+    }
+
+    fn render(&mut self, pass: &mut RenderPass<'_>) {
+        pass.fill_quad(pass.bounds(), self.background_color, 1.0, self.border_color);
+    }
+
+    fn content_hash(&self) -> Option<u64> {
+        Some(hash_content(&(self.background_color, self.border_color)))
+    }
+
+    fn layout(&mut self, pass: &mut LayoutPass<'_>) {
+        let inner_size =
+            (pass.size - Xy::new(self.padding * 2.0, self.padding * 2.0)).max(Xy::ZERO);
+        let width = Length::FitContent(inner_size.x);
+        let height = Length::FitContent(inner_size.y);
+        let auto_size = Xy::new(width, height);
+
+        let mut y_offset = self.padding;
+        for child in &mut self.children {
+            let child_size = pass.resolve_size(child.id(), auto_size);
+            pass.do_layout(child, child_size);
+            pass.place_child(child, Xy::new(self.padding, y_offset));
+
+            y_offset += child_size.y + self.gap;
+        }
+    }
+
+    fn measure(
+        &mut self,
+        context: &mut MeasureContext<'_>,
+        axis: Axis,
+        length_request: LengthRequest,
+        cross_length: Option<f32>,
+    ) -> f32 {
+        let length_request = match length_request {
+            LengthRequest::MinContent | LengthRequest::MaxContent => length_request,
+            LengthRequest::FitContent(_space) => LengthRequest::MinContent,
+        };
+
+        let fallback_length = length_request.into();
+        let cross_length = cross_length.map(|length| (length - self.padding * 2.0).max(0.0));
+
+        let mut length: f32 = self.padding * 2.0;
+        for child in &mut self.children {
+            let child_length =
+                context.resolve_length(child.id(), axis, fallback_length, cross_length);
+            match axis {
+                Axis::Horizontal => length = length.max(child_length + self.padding * 2.0),
+                Axis::Vertical => length += child_length,
+            }
+        }
+
+        if axis == Axis::Vertical {
+            let gap_count = (self.children.len() - 1) as f32;
+            length += gap_count * self.gap;
+        }
+
+        length
+    }
+
+    fn on_child_hover(&mut self, pass: &mut EventPass<'_>, hovered: bool) {
+        if hovered {
+            self.border_color = Rgba {
+                r: 133,
+                g: 133,
+                b: 133,
+                a: 255,
+            };
+        } else {
+            self.border_color = Rgba {
+                r: 111,
+                g: 111,
+                b: 111,
+                a: 255,
+            };
+        }
+        pass.request_render();
+    }
+}
+
+pub struct Row {
+    children: Vec<ChildElement>,
+    background_color: Rgba,
+    border_color: Rgba,
+    gap: f32,
+    /// The space between the row's edges and its children.
+    padding: f32,
+    cross_alignment: CrossAlignment,
+}
+
+impl Row {
+    pub fn new() -> Self {
+        Self {
+            children: Vec::new(),
+            background_color: Rgba {
+                r: 33,
+                g: 33,
+                b: 33,
+                a: 255,
+            },
+            border_color: Rgba {
+                r: 111,
+                g: 111,
+                b: 111,
+                a: 255,
+            },
+            gap: 0.0,
+            padding: 0.0,
+            cross_alignment: CrossAlignment::Start,
+        }
+    }
+
+    pub fn with_gap(mut self, gap: f32) -> Self {
+        self.gap = gap;
+        self
+    }
+
+    pub fn with_padding(mut self, padding: f32) -> Self {
+        self.padding = padding;
+        self
+    }
+
+    pub fn with_background_color(mut self, color: Rgba) -> Self {
+        self.background_color = color;
+        self
+    }
+
+    /// Set how children are placed vertically, such as
+    /// [`CrossAlignment::FirstBaseline`] to line up text of different sizes.
+    pub fn with_cross_align(mut self, alignment: CrossAlignment) -> Self {
+        self.cross_alignment = alignment;
+        self
+    }
+
+    pub fn with(mut self, child: impl Element + 'static) -> Self {
+        self.children.push(ElementBuilder::new(child).into_child());
+        self
+    }
+}
+
+impl Element for Row {
+    fn children_ids(&self) -> Vec<u64> {
+        self.children.iter().map(|child| child.id()).collect()
+    }
+
+    fn update_children(&mut self, pass: &mut UpdatePass<'_>) {
+        for child in self.children.iter_mut() {
+            pass.update_child(child);
+        }
+    }
+
+    fn render(&mut self, pass: &mut RenderPass<'_>) {
+        pass.fill_quad(pass.bounds(), self.background_color, 1.0, self.border_color);
+    }
+
+    fn content_hash(&self) -> Option<u64> {
+        Some(hash_content(&(self.background_color, self.border_color)))
+    }
+
+    fn layout(&mut self, pass: &mut LayoutPass<'_>) {
+        let inner_size =
+            (pass.size - Xy::new(self.padding * 2.0, self.padding * 2.0)).max(Xy::ZERO);
+        let width = Length::FitContent(inner_size.x);
+        let height = Length::FitContent(inner_size.y);
+        let auto_size = Xy::new(width, height);
+
+        let auto_size = match self.cross_alignment {
+            CrossAlignment::Stretch => Xy::new(width, Length::Exact(inner_size.y)),
+            _ => auto_size,
+        };
+
+        // Children are laid out before any are placed, because baseline alignment
+        // depends on all of their ascents.
+        let mut max_first_ascent: f32 = 0.0;
+        let mut max_last_ascent: f32 = 0.0;
+        for child in &mut self.children {
+            let child_size = pass.resolve_size(child.id(), auto_size);
+            pass.do_layout(child, child_size);
+
+            let child_state = &pass.expect_child(child.id()).element.state;
+            max_first_ascent =
+                max_first_ascent.max(child_size.y - child_state.first_baseline_offset());
+            max_last_ascent =
+                max_last_ascent.max(child_size.y - child_state.layout_baseline_offset);
+        }
+
+        let mut x_offset = self.padding;
+        let mut lowest_baseline: f32 = f32::INFINITY;
+        let mut highest_first_baseline: f32 = f32::NEG_INFINITY;
+        for child in &mut self.children {
+            let child_state = &pass.expect_child(child.id()).element.state;
+            let child_size = child_state.layout_bounds.size();
+            let first_baseline = child_state.first_baseline_offset();
+            let last_baseline = child_state.layout_baseline_offset;
+            let y_offset = self.padding
+                + match self.cross_alignment {
+                    CrossAlignment::FirstBaseline => {
+                        max_first_ascent - (child_size.y - first_baseline)
+                    }
+                    CrossAlignment::LastBaseline => {
+                        max_last_ascent - (child_size.y - last_baseline)
+                    }
+                    alignment => alignment.offset(inner_size.y - child_size.y),
+                };
+            pass.place_child(child, Xy::new(x_offset, y_offset));
+
+            let bottom_gap = pass.size.y - (y_offset + child_size.y);
+            lowest_baseline = lowest_baseline.min(last_baseline + bottom_gap);
+            highest_first_baseline = highest_first_baseline.max(first_baseline + bottom_gap);
+
+            x_offset += child_size.x + self.gap;
+        }
+
+        pass.clear_baseline_offset();
+        if !self.children.is_empty() {
+            pass.set_baseline_offset(lowest_baseline);
+            if highest_first_baseline != lowest_baseline {
+                pass.set_first_baseline_offset(highest_first_baseline);
+            }
+        }
+    }
+
+    fn measure(
+        &mut self,
+        context: &mut MeasureContext<'_>,
+        axis: Axis,
+        length_request: LengthRequest,
+        cross_length: Option<f32>,
+    ) -> f32 {
+        let length_request = match length_request {
+            LengthRequest::MinContent | LengthRequest::MaxContent => length_request,
+            LengthRequest::FitContent(_space) => LengthRequest::MinContent,
+        };
+
+        let fallback_length = length_request.into();
+        let cross_length = cross_length.map(|length| (length - self.padding * 2.0).max(0.0));
+
+        let mut length: f32 = self.padding * 2.0;
+        for child in &mut self.children {
+            let child_length =
+                context.resolve_length(child.id(), axis, fallback_length, cross_length);
+            match axis {
+                Axis::Horizontal => length += child_length,
+                Axis::Vertical => length = length.max(child_length + self.padding * 2.0),
+            }
+        }
+
+        if axis == Axis::Horizontal {
+            let gap_count = (self.children.len() - 1) as f32;
+            length += gap_count * self.gap;
+        }
+
+        length
+    }
+
+    fn on_child_hover(&mut self, pass: &mut EventPass<'_>, hovered: bool) {
+        if hovered {
+            self.border_color = Rgba {
+                r: 133,
+                g: 133,
+                b: 133,
+                a: 255,
+            };
+        } else {
+            self.border_color = Rgba {
+                r: 111,
+                g: 111,
+                b: 111,
+                a: 255,
+            };
+        }
+        pass.request_render();
+    }
+}
+
+/// Renders part of a [`ScrollBar`] within the given bounds.
+pub type ScrollBarRenderFn = fn(&ScrollBar, &mut RenderPass<'_>, Aabb2D);
+
+pub struct ScrollBar {
+    axis: Axis,
+    progress: f32,
+    area_length: f32,
+    content_length: f32,
+    grab_anchor: Option<f32>,
+    moved: bool,
+    /// The theme's `"scrollbar.thumb.min_length"` as of the last layout.
+    min_thumb_length: f32,
+    render_track: Option<ScrollBarRenderFn>,
+    render_thumb: Option<ScrollBarRenderFn>,
+}
+
+impl ScrollBar {
+    pub fn new() -> Self {
+        Self::with_axis(Axis::Vertical)
+    }
+
+    pub fn horizontal() -> Self {
+        Self::with_axis(Axis::Horizontal)
+    }
+
+    pub fn with_axis(axis: Axis) -> Self {
+        Self {
+            axis,
+            progress: 0.0,
+            area_length: 0.0,
+            content_length: 0.0,
+            grab_anchor: None,
+            moved: false,
+            min_thumb_length: 0.0,
+            render_track: None,
+            render_thumb: None,
+        }
+    }
+
+    /// Replace the default (empty) track rendering.
+    pub fn with_track_renderer(mut self, render_track: ScrollBarRenderFn) -> Self {
+        self.render_track = Some(render_track);
+        self
+    }
+
+    /// Replace the default thumb rendering.
+    pub fn with_thumb_renderer(mut self, render_thumb: ScrollBarRenderFn) -> Self {
+        self.render_thumb = Some(render_thumb);
+        self
+    }
+
+    #[inline]
+    pub fn axis(&self) -> Axis {
+        self.axis
+    }
+
+    /// The position of the thumb along the track, from `0.0` to `1.0`.
+    #[inline]
+    pub fn progress(&self) -> f32 {
+        self.progress
+    }
+
+    pub fn set_progress(&mut self, progress: f32) {
+        self.progress = progress.clamp(0.0, 1.0);
+    }
+
+    /// Set the length of the visible area and the total length of the content
+    /// it shows, which determines the size of the thumb.
+    pub fn set_range(&mut self, area_length: f32, content_length: f32) {
+        self.area_length = area_length;
+        self.content_length = content_length;
+    }
+
+    /// Whether the user is currently dragging the thumb.
+    #[inline]
+    pub fn is_grabbed(&self) -> bool {
+        self.grab_anchor.is_some()
+    }
+
+    /// Returns `true` if the user has moved the thumb since the last call.
+    pub fn take_moved(&mut self) -> bool {
+        std::mem::take(&mut self.moved)
+    }
+
+    fn thumb_length(&self, track_length: f32) -> f32 {
+        let length_ratio = if self.content_length != 0.0 {
+            self.area_length / self.content_length
+        } else {
+            1.0
+        };
+        (length_ratio.clamp(0.0, 1.0) * track_length)
+            .max(self.min_thumb_length)
+            .min(track_length)
+    }
+
+    fn thumb_bounds(&self, track_bounds: Aabb2D) -> Aabb2D {
+        let track_size = track_bounds.size();
+        let track_length = track_size.value_for_axis(self.axis);
+        let thumb_length = self.thumb_length(track_length);
+        let empty_space = track_length - thumb_length;
+
+        Aabb2D::from_size_position(
+            self.axis
+                .pack_xy(thumb_length, track_size.value_for_axis(self.axis.cross())),
+            track_bounds.position() + self.axis.pack_xy(self.progress * empty_space, 0.0),
+        )
+    }
+
+    /// Move the thumb so that the point `anchor` along its length is at
+    /// `position`.
+    fn move_thumb(&mut self, track_bounds: Aabb2D, position: Xy<f32>, anchor: f32) -> bool {
+        let track_length = track_bounds.size().value_for_axis(self.axis);
+        let thumb_length = self.thumb_length(track_length);
+        let empty_space = track_length - thumb_length;
+        if empty_space <= 0.0 {
+            return false;
+        }
+
+        let local_position =
+            position.value_for_axis(self.axis) - track_bounds.min.value_for_axis(self.axis);
+        let progress = ((local_position - thumb_length * anchor) / empty_space).clamp(0.0, 1.0);
+        if (progress - self.progress).abs() > 1e-12 {
+            self.progress = progress;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn default_render_thumb(&self, pass: &mut RenderPass<'_>, thumb_bounds: Aabb2D) {
+        pass.fill_quad(
+            thumb_bounds,
+            if self.grab_anchor.is_some() {
+                Rgba {
+                    r: 0x73,
+                    g: 0x73,
+                    b: 0x89,
+                    a: 255,
+                }
+            } else {
+                Rgba {
+                    r: 0x53,
+                    g: 0x53,
+                    b: 0x6d,
+                    a: 255,
+                }
+            },
+            0.0,
+            Rgba::NONE,
+        );
+    }
+}
+
+impl Element for ScrollBar {
+    fn render(&mut self, pass: &mut RenderPass<'_>) {
+        let track_bounds = pass.bounds();
+        let thumb_bounds = self.thumb_bounds(track_bounds);
+
+        if let Some(render_track) = self.render_track {
+            render_track(self, pass, track_bounds);
+        }
+        if let Some(render_thumb) = self.render_thumb {
+            render_thumb(self, pass, thumb_bounds);
+        } else {
+            self.default_render_thumb(pass, thumb_bounds);
+        }
+    }
+
+    fn layout(&mut self, pass: &mut LayoutPass<'_>) {
+        self.min_thumb_length = pass.metric("scrollbar.thumb.min_length");
+    }
+
+    fn measure(
+        &mut self,
+        context: &mut MeasureContext<'_>,
+        axis: Axis,
+        length_request: LengthRequest,
+        _cross_length: Option<f32>,
+    ) -> f32 {
+        if axis == self.axis {
+            match length_request {
+                LengthRequest::MinContent | LengthRequest::MaxContent => self.area_length,
+                LengthRequest::FitContent(space) => space,
+            }
+        } else {
+            context.metric("scrollbar.width")
+        }
+    }
+
+    fn cursor_icon(&self) -> CursorIcon {
+        if self.grab_anchor.is_some() {
+            CursorIcon::Grabbing
+        } else {
+            CursorIcon::Grab
+        }
+    }
+
+    fn on_pointer_event(&mut self, pass: &mut EventPass<'_>, event: &PointerEvent) {
+        match event {
+            PointerEvent::Down {
+                position: mouse_pos,
+                ..
+            } => {
+                pass.capture_pointer();
+
+                let track_bounds = pass.bounds();
+                let thumb_bounds = self.thumb_bounds(track_bounds);
+
+                if thumb_bounds.contains(*mouse_pos) {
+                    let min = thumb_bounds.min.value_for_axis(self.axis);
+                    let max = thumb_bounds.max.value_for_axis(self.axis);
+                    self.grab_anchor =
+                        Some((mouse_pos.value_for_axis(self.axis) - min) / (max - min));
+                } else {
+                    // Jump so that the thumb is centered on the pointer.
+                    if self.move_thumb(track_bounds, *mouse_pos, 0.5) {
+                        self.moved = true;
+                    }
+                    self.grab_anchor = Some(0.5);
+                }
+                pass.request_render();
+            }
+            PointerEvent::Move {
+                position: mouse_pos,
+                ..
+            } => {
+                if let Some(grab_anchor) = self.grab_anchor
+                    && self.move_thumb(pass.bounds(), *mouse_pos, grab_anchor)
+                {
+                    self.moved = true;
+                    pass.request_render();
+                }
+            }
+            PointerEvent::Up { .. } => {
+                self.grab_anchor = None;
+                pass.request_render();
+            }
+            _ => {}
+        }
+    }
+}
+
+pub struct VerticalScroll {
+    column: TypedChildElement<Column>,
+    scroll_bar: TypedChildElement<ScrollBar>,
+    viewport_offset: Xy<f32>,
+    content_size: Xy<f32>,
+}
+
+impl VerticalScroll {
+    pub fn new(column: Column) -> Self {
+        Self {
+            column: TypedChildElement::new(column),
+            scroll_bar: TypedChildElement::new(ScrollBar::new()),
+            viewport_offset: Xy::ZERO,
+            content_size: Xy::ZERO,
+        }
+    }
+
+    fn scrolled(&mut self, pass: &mut EventPass<'_>, scroll_range: Xy<f32>) {
+        pass.request_compose();
+        let progress = if scroll_range.y > 1e-12 {
+            (self.viewport_offset.y / scroll_range.y).clamp(0.0, 1.0)
+        } else {
+            0.0
+        };
+
+        let scroll_bar = pass.typed_child_mut(&mut self.scroll_bar);
+        scroll_bar.set_progress(progress);
+        pass.request_child_render(self.scroll_bar.id());
+    }
+}
+
+impl Element for VerticalScroll {
+    fn children_ids(&self) -> Vec<u64> {
+        vec![self.column.id(), self.scroll_bar.id()]
+    }
+
+    fn update_children(&mut self, pass: &mut UpdatePass<'_>) {
+        pass.update_child(&mut self.column.inner);
+        pass.update_child(&mut self.scroll_bar.inner);
+    }
+
+    fn layout(&mut self, pass: &mut LayoutPass<'_>) {
+        let auto_size = Xy::new(Length::FitContent(pass.size.x), Length::MaxContent);
+        self.content_size = pass.resolve_size(self.column.id(), auto_size);
+
+        pass.do_layout(&mut self.column.inner, self.content_size);
+        pass.place_child(&mut self.column.inner, Xy::ZERO);
+
+        let viewport_max_pos = (self.content_size - pass.size).max(Xy::ZERO);
+        let pos = Xy::new(
+            self.viewport_offset.x.clamp(0.0, viewport_max_pos.x),
+            self.viewport_offset.y.clamp(0.0, viewport_max_pos.y),
+        );
+        if (pos - self.viewport_offset).length_squared() > 1e-12 {
+            self.viewport_offset = pos;
+        }
+
+        {
+            let area_size = pass.size;
+            let scroll_range = (self.content_size.y - area_size.y).max(0.0);
+            let scroll_bar = pass.typed_child_mut(&mut self.scroll_bar);
+            scroll_bar.set_range(area_size.y, self.content_size.y);
+            scroll_bar.set_progress(if scroll_range > 1e-12 {
+                (self.viewport_offset.y / scroll_range).clamp(0.0, 1.0)
+            } else {
+                0.0
+            });
+            pass.request_child_render(self.scroll_bar.id());
+        }
+
+        let scroll_bar_size = pass.resolve_size(
+            self.scroll_bar.id(),
+            Xy::new(
+                Length::FitContent(pass.size.x),
+                Length::FitContent(pass.size.y),
+            ),
+        );
+        pass.do_layout(&mut self.scroll_bar.inner, scroll_bar_size);
+        pass.place_child(
+            &mut self.scroll_bar.inner,
+            Xy::new(pass.size.x - scroll_bar_size.x, 0.0),
+        );
+    }
+
+    fn measure(
+        &mut self,
+        context: &mut MeasureContext<'_>,
+        axis: Axis,
+        length_request: LengthRequest,
+        cross_length: Option<f32>,
+    ) -> f32 {
+        match length_request {
+            LengthRequest::MaxContent => {
+                context.resolve_length(self.column.id(), axis, Length::MaxContent, cross_length)
+            }
+            LengthRequest::MinContent => 0.0,
+            LengthRequest::FitContent(space) => space,
+        }
+    }
+
+    fn compose(&mut self, pass: &mut ComposePass<'_>) {
+        pass.set_child_scroll(
+            &mut self.column.inner,
+            Xy::new(-self.viewport_offset.x, -self.viewport_offset.y),
+        );
+    }
+
+    fn on_pointer_event(&mut self, pass: &mut EventPass<'_>, event: &PointerEvent) {
+        let scroll_range = (self.content_size - pass.state.bounds.size()).max(Xy::ZERO);
+
+        let mut changed = false;
+        match event {
+            PointerEvent::Scroll { delta, .. } => {
+                let pixel_delta = delta.to_pixels(Xy::new(120.0, 120.0));
+                let delta = Xy::new(0.0, pixel_delta.y);
+                let pos = self.viewport_offset - delta;
+                let pos = Xy::new(
+                    pos.x.clamp(0.0, scroll_range.x),
+                    pos.y.clamp(0.0, scroll_range.y),
+                );
+
+                if (pos - self.viewport_offset).length_squared() > 1e-12 {
+                    changed = true;
+                    self.viewport_offset = pos;
+                    pass.set_handled();
+                }
+            }
+            _ => {}
+        }
+        {
+            let scroll_bar = pass.typed_child_mut(&mut self.scroll_bar);
+            if scroll_bar.take_moved() {
+                let y = scroll_bar.progress() * scroll_range.y;
+                let pos = Xy::new(self.viewport_offset.x, y.clamp(0.0, scroll_range.y));
+                if (pos - self.viewport_offset).length_squared() > 1e-12 {
+                    changed = true;
+                    self.viewport_offset = pos;
+                }
+            }
+        }
+
+        if changed {
+            pass.set_handled();
+            self.scrolled(pass, scroll_range);
+        }
+    }
+
+    fn on_scroll_into_view(&mut self, pass: &mut EventPass<'_>, area: &mut Aabb2D) {
+        let viewport = pass.state.bounds;
+        let viewport_size = pass.state.layout_bounds.size();
+        let scroll_range = (self.content_size - viewport_size).max(Xy::ZERO);
+        // NOTE: The bounds are zoomed, but the viewport offset isn't.
+        let scale = if viewport_size.y > 1e-12 {
+            viewport.size().y / viewport_size.y
+        } else {
+            1.0
+        };
+
+        // Areas taller than the viewport are aligned to its top.
+        let delta = if area.min.y < viewport.min.y {
+            area.min.y - viewport.min.y
+        } else if area.max.y > viewport.max.y {
+            (area.max.y - viewport.max.y).min(area.min.y - viewport.min.y)
+        } else {
+            0.0
+        };
+        let y = (self.viewport_offset.y + delta / scale).clamp(0.0, scroll_range.y);
+        let scrolled = y - self.viewport_offset.y;
+        if scrolled.abs() > 1e-6 {
+            self.viewport_offset.y = y;
+            self.scrolled(pass, scroll_range);
+        }
+
+        *area = area
+            .translate(Xy::new(0.0, -scrolled * scale))
+            .intersect(viewport);
+    }
+
+    fn save_state(&self) -> Option<Vec<u8>> {
+        let mut state = Vec::with_capacity(8);
+        state.extend_from_slice(&self.viewport_offset.x.to_le_bytes());
+        state.extend_from_slice(&self.viewport_offset.y.to_le_bytes());
+        Some(state)
+    }
+
+    fn restore_state(&mut self, state: &[u8]) {
+        // NOTE: The offset is clamped to the content when laid out again.
+        if let Ok([x0, x1, x2, x3, y0, y1, y2, y3]) = <[u8; 8]>::try_from(state) {
+            let offset = Xy::new(
+                f32::from_le_bytes([x0, x1, x2, x3]),
+                f32::from_le_bytes([y0, y1, y2, y3]),
+            );
+            if !offset.x.is_nan() && !offset.y.is_nan() {
+                self.viewport_offset = offset;
+            }
+        }
+    }
+}
+
+pub struct Label {
+    pub text: Arc<str>,
+    pub font_size: f32,
+    pub color: Rgba,
+    // pub visual_font_size: AnimatedF32,
+    pub line_height: LineHeight,
+    pub font_family: FontFamily,
+    pub font_style: FontStyle,
+    /// Styles for parts of the text, such as bold or underlined words.
+    pub spans: Vec<TextSpan>,
+    pub alignment: TextAlignment,
+    pub wrap_mode: TextWrapMode,
+}
+
+impl Label {
+    pub fn new(text: impl Into<Arc<str>>) -> Self {
+        Self {
+            text: text.into(),
+            color: Rgba::WHITE,
+            font_size: 16.0,
+            line_height: LineHeight::FONT_PREFERRED,
+            font_family: FontFamily::Proportional,
+            font_style: FontStyle::Normal,
+            spans: Vec::new(),
+            alignment: TextAlignment::Start,
+            wrap_mode: TextWrapMode::Wrap,
+            // visual_font_size: AnimatedF32::new(16.0),
+        }
+    }
+
+    pub fn with_color(mut self, color: Rgba) -> Self {
+        self.color = color;
+        self
+    }
+
+    pub fn with_font_size(mut self, font_size: f32) -> Self {
+        self.font_size = font_size;
+        // self.visual_font_size = AnimatedF32::new(font_size);
+        self
+    }
+
+    pub fn with_font_family(mut self, family: FontFamily) -> Self {
+        self.font_family = family;
+        self
+    }
+
+    /// Create a label with the text and spans of the given [`StyledText`].
+    pub fn styled(text: StyledText) -> Self {
+        Self::new(text.text).with_spans(text.spans)
+    }
+
+    pub fn with_spans(mut self, spans: Vec<TextSpan>) -> Self {
+        self.spans = spans;
+        self
+    }
+}
+
+impl Element for Label {
+    fn render(&mut self, pass: &mut RenderPass<'_>) {
+        if self.spans.is_empty() && self.font_family == FontFamily::Proportional {
+            unsafe { __ui_Label__render(self, pass) }
+        } else {
+            pass.fill_styled_text(
+                &self.text,
+                &self.spans,
+                TextStyle {
+                    family: Some(self.font_family),
+                    font_style: self.font_style,
+                    ..TextStyle::default()
+                },
+                pass.bounds(),
+                self.color,
+                self.font_size,
+            );
+        }
+    }
+
+    fn content_hash(&self) -> Option<u64> {
+        Some(hash_content(&(
+            &self.text,
+            &self.spans,
+            self.color,
+            self.font_size.to_bits(),
+            self.font_family,
+            self.font_style,
+            self.alignment,
+            self.wrap_mode,
+        )))
+    }
+
+    // fn animate(&mut self, pass: &mut AnimatePass<'_>, dt: f64) {
+    //     let ms = (dt * 1000.0) as f32;
+    //     let done = self.visual_font_size.advance(ms);
+    //     if !done {
+    //         pass.request_animate();
+    //     }
+    //     pass.request_render();
+    // }
+
+    fn layout(&mut self, pass: &mut LayoutPass<'_>) {
+        let line_height = self.line_height.resolve(self.font_size);
+        let line_baseline = pass.fonts_mut().line_baseline_offset(
+            self.font_size,
+            self.line_height,
+            self.font_style,
+        );
+
+        pass.clear_baseline_offset();
+        pass.set_baseline_offset(line_baseline);
+        if pass.size.y > line_height {
+            pass.set_first_baseline_offset(pass.size.y - line_height + line_baseline);
+        }
+    }
+
+    fn measure(
+        &mut self,
+        context: &mut MeasureContext<'_>,
+        axis: Axis,
+        length_request: LengthRequest,
+        cross_length: Option<f32>,
+    ) -> f32 {
+        unsafe { __ui_Label__measure(self, context, axis, length_request, cross_length) }
+    }
+
+    fn cursor_icon(&self) -> CursorIcon {
+        CursorIcon::IBeam
+    }
+
+    fn on_pointer_event(&mut self, pass: &mut EventPass<'_>, event: &PointerEvent) {
+        if matches!(
+            event,
+            PointerEvent::Down {
+                button: PointerButton::Primary,
+                ..
+            },
+        ) {
+            pass.request_focus();
+        }
+    }
+
+    // fn on_hover(&mut self, pass: &mut EventPass<'_>, hovered: bool) {
+    //     if hovered {
+    //         self.visual_font_size.move_to(self.font_size * 2.0, 1000.0);
+    //     } else {
+    //         self.visual_font_size.move_to(self.font_size, 1000.0);
+    //     }
+    //     pass.request_animate();
+    //     pass.request_render();
+    //     pass.set_handled();
+    // }
+
+    fn on_focus(&mut self, pass: &mut EventPass<'_>, focused: bool) {
+        if focused {
+            self.font_size *= 2.0;
+        } else {
+            self.font_size /= 2.0;
+        }
+        pass.request_layout();
+        pass.request_render();
+        pass.set_handled();
+    }
+}
+
+unsafe extern "Rust" {
+    fn __ui_Label__render(label: &mut Label, pass: &mut RenderPass<'_>);
+
+    fn __ui_Label__measure(
+        label: &mut Label,
+        context: &mut MeasureContext<'_>,
+        axis: Axis,
+        length_request: LengthRequest,
+        cross_length: Option<f32>,
+    ) -> f32;
+}
+
+pub struct LineInput {
+    pub text: String,
+    pub font_size: f32,
+
+    cursor_offset: usize,
+    width_before_cursor: f32,
+    width_after_cursor: f32,
+    show_cursor: bool,
+    /// Toggles `show_cursor` while focused.
+    blink_timer: Option<TimerToken>,
+    /// Undone with `Ctrl+Z`, and redone with `Ctrl+Shift+Z`.
+    history: EditHistory,
+}
+
+impl LineInput {
+    /// How long the cursor is shown (and then hidden) for while it blinks.
+    pub const CURSOR_BLINK_INTERVAL: Duration = Duration::from_millis(530);
+
+    pub fn new(text: impl ToString) -> Self {
+        let text = text.to_string();
+        let cursor_offset = text.len();
+        Self {
+            text,
+            font_size: 16.0,
+            cursor_offset,
+            width_before_cursor: 0.0,
+            width_after_cursor: 0.0,
+            show_cursor: false,
+            blink_timer: None,
+            history: EditHistory::new(),
+        }
+    }
+
+    pub fn with_font_size(mut self, font_size: f32) -> Self {
+        self.font_size = font_size;
+        self
+    }
+
+    /// Set how many edits can be undone, which defaults to
+    /// [`EditHistory::DEFAULT_DEPTH`].
+    pub fn with_history_depth(mut self, depth: usize) -> Self {
+        self.history.set_depth(depth);
+        self
+    }
+
+    fn edit(&mut self, range: Range<usize>, inserted: &str) {
+        let edit = TextEdit::apply(&mut self.text, range, inserted, self.cursor_offset);
+        self.cursor_offset = edit.cursor_after();
+        self.history.record(edit);
+    }
+}
+
+impl Element for LineInput {
+    fn render(&mut self, pass: &mut RenderPass<'_>) {
+        pass.fill_quad(
+            pass.bounds(),
+            Rgba::NONE,
+            1.0,
+            Rgba {
+                r: 111,
+                g: 111,
+                b: 111,
+                a: 255,
+            },
+        );
+        pass.fill_text(
+            &self.text,
+            pass.bounds()
+                .with_width(self.width_before_cursor + self.width_after_cursor),
+            Rgba {
+                r: 177,
+                g: 177,
+                b: 177,
+                a: 255,
+            },
+            self.font_size,
+        );
+
+        if self.show_cursor {
+            let cursor_size = Xy::new(2.0, pass.bounds().size().y);
+            let cursor_pos = pass.bounds().position() + Xy::new(self.width_before_cursor, 0.0);
+
+            pass.fill_quad(
+                Aabb2D::from_size_position(cursor_size, cursor_pos),
+                Rgba {
+                    r: 177,
+                    g: 177,
+                    b: 177,
+                    a: 200,
+                },
+                0.0,
+                Rgba::NONE,
+            );
+        }
+    }
+
+    fn measure(
+        &mut self,
+        context: &mut MeasureContext<'_>,
+        axis: Axis,
+        length_request: LengthRequest,
+        _cross_length: Option<f32>,
+    ) -> f32 {
+        let id = context.id();
+        let fonts = context.fonts_mut();
+        let max_advance = match axis {
+            Axis::Horizontal => match length_request {
+                LengthRequest::MinContent | LengthRequest::MaxContent => None,
+                LengthRequest::FitContent(space) => Some(space),
+            },
+            Axis::Vertical => None,
+        };
+        let before_cursor_size = fonts.measure_text(
+            id,
+            &self.text[..self.cursor_offset],
+            &[],
+            max_advance,
+            self.font_size,
+            LineHeight::Relative(1.0),
+            FontFamily::Proportional,
+            FontStyle::Normal,
+            TextAlignment::Start,
+            TextWrapMode::NoWrap,
+        );
+        let after_cursor_size = fonts.measure_text(
+            id,
+            &self.text[self.cursor_offset..],
+            &[],
+            max_advance,
+            self.font_size,
+            LineHeight::Relative(1.0),
+            FontFamily::Proportional,
+            FontStyle::Normal,
+            TextAlignment::Start,
+            TextWrapMode::NoWrap,
+        );
+
+        self.width_before_cursor = before_cursor_size.x;
+        self.width_after_cursor = after_cursor_size.x;
+
+        match axis {
+            Axis::Horizontal => match length_request {
+                LengthRequest::MinContent | LengthRequest::MaxContent => {
+                    before_cursor_size.x + after_cursor_size.x
+                }
+                LengthRequest::FitContent(space) => space,
+            },
+            Axis::Vertical => before_cursor_size.y,
+        }
+    }
+
+    fn cursor_icon(&self) -> CursorIcon {
+        CursorIcon::IBeam
+    }
+
+    fn on_keyboard_event(&mut self, pass: &mut EventPass<'_>, event: &KeyboardEvent) {
+        match event {
+            KeyboardEvent::Down { key, .. } => {
+                if self.cursor_offset > self.text.len() {
+                    self.cursor_offset = self.text.len();
+                }
+                // Arrow keys move visually, so they're swapped for right-to-left text.
+                let rtl = bidi::paragraph_direction(&self.text) == Some(TextDirection::RightToLeft);
+                let modifiers = pass.modifiers();
+                match key {
+                    Key::Char('z' | 'Z') if modifiers.ctrl && !modifiers.alt && !modifiers.meta => {
+                        let cursor = if modifiers.shift {
+                            self.history.redo(&mut self.text)
+                        } else {
+                            self.history.undo(&mut self.text)
+                        };
+                        let Some(cursor) = cursor else {
+                            return;
+                        };
+                        self.cursor_offset = cursor;
+                    }
+                    Key::Char(ch) => {
+                        let offset = self.cursor_offset;
+                        self.edit(offset..offset, ch.encode_utf8(&mut [0; 4]));
+                    }
+                    Key::Backspace => {
+                        // Backspace removes a single character rather than the whole cluster,
+                        // so that combining marks can be removed individually.
+                        let Some((offset, _)) =
+                            self.text[..self.cursor_offset].char_indices().next_back()
+                        else {
+                            return;
+                        };
+                        self.edit(offset..self.cursor_offset, "");
+                    }
+                    Key::Delete => {
+                        if self.cursor_offset >= self.text.len() {
+                            return;
+                        }
+                        let end = bidi::next_cluster_boundary(&self.text, self.cursor_offset);
+                        self.edit(self.cursor_offset..end, "");
+                    }
+                    Key::ArrowLeft | Key::ArrowRight => {
+                        self.history.break_coalescing();
+                        if (*key == Key::ArrowLeft) != rtl {
+                            self.cursor_offset =
+                                bidi::prev_cluster_boundary(&self.text, self.cursor_offset);
+                        } else {
+                            if self.cursor_offset >= self.text.len() {
+                                return;
+                            }
+                            self.cursor_offset =
+                                bidi::next_cluster_boundary(&self.text, self.cursor_offset);
+                        }
+                    }
+                    _ => {
+                        return;
+                    }
+                }
+                // The cursor stays visible while typing, and starts blinking again after.
+                if let Some(timer) = self.blink_timer.take() {
+                    pass.cancel_timer(timer);
+                    self.show_cursor = true;
+                    self.blink_timer = Some(pass.request_interval(Self::CURSOR_BLINK_INTERVAL));
+                }
+                pass.request_layout();
+                pass.request_render();
+                pass.set_handled();
+            }
+            KeyboardEvent::Up { .. } => {}
+        }
+    }
+
+    fn on_pointer_event(&mut self, pass: &mut EventPass<'_>, event: &PointerEvent) {
+        if let PointerEvent::Down {
+            button: PointerButton::Primary,
+            position,
+            ..
+        } = event
+        {
+            let id = pass.id();
+            let local_position = pass.local_position(*position);
+            let offset = pass.fonts_mut().hit_test_text(
+                id,
+                TextParams::new(&self.text, self.font_size),
+                local_position,
+            );
+            if offset != self.cursor_offset {
+                self.cursor_offset = offset;
+                self.history.break_coalescing();
+                pass.request_layout();
+                pass.request_render();
+            }
+            pass.request_focus();
+        }
+    }
+
+    fn on_focus(&mut self, pass: &mut EventPass<'_>, focused: bool) {
+        self.show_cursor = focused;
+        if let Some(timer) = self.blink_timer.take() {
+            pass.cancel_timer(timer);
+        }
+        if focused {
+            self.blink_timer = Some(pass.request_interval(Self::CURSOR_BLINK_INTERVAL));
+        }
+        pass.request_render();
+        pass.set_handled();
+    }
+
+    fn on_timer(&mut self, pass: &mut EventPass<'_>, token: TimerToken) {
+        if self.blink_timer == Some(token) {
+            self.show_cursor = !self.show_cursor;
+            pass.request_render();
+        }
+    }
+
+    fn save_state(&self) -> Option<Vec<u8>> {
+        let mut state = Vec::with_capacity(8 + self.text.len());
+        state.extend_from_slice(&(self.cursor_offset as u64).to_le_bytes());
+        state.extend_from_slice(self.text.as_bytes());
+        Some(state)
+    }
+
+    fn restore_state(&mut self, state: &[u8]) {
+        let Some((cursor_offset, text)) = state.split_first_chunk::<8>() else {
+            return;
+        };
+        let Ok(text) = std::str::from_utf8(text) else {
+            return;
+        };
+        self.text = text.to_string();
+        let cursor_offset =
+            usize::try_from(u64::from_le_bytes(*cursor_offset)).unwrap_or(usize::MAX);
+        self.cursor_offset = floor_char_boundary(&self.text, cursor_offset);
+        // NOTE: The restored text didn't come from any edits that could be undone.
+        self.history.clear();
+    }
+}
+
+pub struct Dropdown {
+    pub options: Vec<String>,
+    pub selected: usize,
+    pub font_size: f32,
+
+    open: bool,
+    highlighted: usize,
+    option_height: f32,
+}
+
+impl Dropdown {
+    pub fn new(options: impl IntoIterator<Item = impl ToString>) -> Self {
+        Self {
+            options: options
+                .into_iter()
+                .map(|option| option.to_string())
+                .collect(),
+            selected: 0,
+            font_size: 16.0,
+            open: false,
+            highlighted: 0,
+            option_height: 0.0,
+        }
+    }
+
+    pub fn with_selected(mut self, selected: usize) -> Self {
+        self.selected = selected;
+        self
+    }
+
+    pub fn with_font_size(mut self, font_size: f32) -> Self {
+        self.font_size = font_size;
+        self
+    }
+
+    #[inline]
+    pub fn is_open(&self) -> bool {
+        self.open
+    }
+
+    pub fn selected_option(&self) -> Option<&str> {
+        self.options.get(self.selected).map(String::as_str)
+    }
+
+    fn option_bounds(&self, bounds: Aabb2D, index: usize) -> Aabb2D {
+        Aabb2D::from_size_position(
+            Xy::new(bounds.size().x, self.option_height),
+            Xy::new(
+                bounds.min.x,
+                bounds.max.y + self.option_height * index as f32,
+            ),
+        )
+    }
+
+    fn option_at(&self, bounds: Aabb2D, position: Xy<f32>) -> Option<usize> {
+        (0..self.options.len()).find(|index| self.option_bounds(bounds, *index).contains(position))
+    }
+
+    fn set_open(&mut self, pass: &mut EventPass<'_>, open: bool) {
+        if self.open == open {
+            return;
+        }
+        self.open = open;
+        if open {
+            self.highlighted = self.selected;
+            pass.hold_pointer_capture();
+        } else {
+            pass.release_pointer();
+        }
+        pass.request_render();
+        pass.request_overlay_render();
+    }
+
+    fn select(&mut self, pass: &mut EventPass<'_>, index: usize) {
+        if index < self.options.len() && index != self.selected {
+            self.selected = index;
+            pass.request_layout();
+            pass.request_render();
+        }
+    }
+}
+
+impl Element for Dropdown {
+    fn accepts_keyboard_events(&self) -> bool {
+        true
+    }
+
+    fn accepts_focus_events(&self) -> bool {
+        true
+    }
+
+    fn render(&mut self, pass: &mut RenderPass<'_>) {
+        pass.fill_quad(
+            pass.bounds(),
+            Rgba {
+                r: 0x2b,
+                g: 0x2b,
+                b: 0x3b,
+                a: 255,
+            },
+            1.0,
+            Rgba {
+                r: 111,
+                g: 111,
+                b: 111,
+                a: 255,
+            },
+        );
+        if let Some(option) = self.options.get(self.selected) {
+            pass.fill_text(
+                option,
+                pass.bounds(),
+                Rgba {
+                    r: 177,
+                    g: 177,
+                    b: 177,
+                    a: 255,
+                },
+                self.font_size,
+            );
+        }
+    }
+
+    fn render_overlay(&mut self, pass: &mut RenderPass<'_>) {
+        if !self.open {
+            return;
+        }
+
+        let bounds = pass.bounds();
+        for (index, option) in self.options.iter().enumerate() {
+            let option_bounds = self.option_bounds(bounds, index);
+            pass.fill_quad(
+                option_bounds,
+                if index == self.highlighted {
+                    Rgba {
+                        r: 0x53,
+                        g: 0x53,
+                        b: 0x6d,
+                        a: 255,
+                    }
+                } else {
+                    Rgba {
+                        r: 0x2b,
+                        g: 0x2b,
+                        b: 0x3b,
+                        a: 255,
+                    }
+                },
+                0.0,
+                Rgba::NONE,
+            );
+            pass.fill_text(
+                option,
+                option_bounds,
+                Rgba {
+                    r: 177,
+                    g: 177,
+                    b: 177,
+                    a: 255,
+                },
+                self.font_size,
+            );
+        }
+    }
+
+    fn measure(
+        &mut self,
+        context: &mut MeasureContext<'_>,
+        axis: Axis,
+        length_request: LengthRequest,
+        _cross_length: Option<f32>,
+    ) -> f32 {
+        let id = context.id();
+        let fonts = context.fonts_mut();
+        let mut max_size = Xy::new(0.0_f32, 0.0_f32);
+        for option in &self.options {
+            let size = fonts.measure_text(
+                id,
+                option,
+                &[],
+                None,
+                self.font_size,
+                LineHeight::Relative(1.0),
+                FontFamily::Proportional,
+                FontStyle::Normal,
+                TextAlignment::Start,
+                TextWrapMode::NoWrap,
+            );
+            max_size.x = max_size.x.max(size.x);
+            max_size.y = max_size.y.max(size.y);
+        }
+
+        self.option_height = max_size.y;
+
+        match axis {
+            Axis::Horizontal => match length_request {
+                LengthRequest::MinContent | LengthRequest::MaxContent => max_size.x,
+                LengthRequest::FitContent(space) => space,
+            },
+            Axis::Vertical => max_size.y,
+        }
+    }
+
+    fn on_keyboard_event(&mut self, pass: &mut EventPass<'_>, event: &KeyboardEvent) {
+        let KeyboardEvent::Down { key, .. } = event else {
+            return;
+        };
+
+        match key {
+            Key::ArrowDown if self.open => {
+                if self.highlighted + 1 < self.options.len() {
+                    self.highlighted += 1;
+                    pass.request_overlay_render();
+                }
+            }
+            Key::ArrowUp if self.open => {
+                if self.highlighted > 0 {
+                    self.highlighted -= 1;
+                    pass.request_overlay_render();
+                }
+            }
+            Key::ArrowDown => {
+                self.select(pass, self.selected.saturating_add(1));
+            }
+            Key::ArrowUp => {
+                if self.selected > 0 {
+                    self.select(pass, self.selected - 1);
+                }
+            }
+            Key::Enter | Key::Space => {
+                if self.open {
+                    self.select(pass, self.highlighted);
+                    self.set_open(pass, false);
+                } else {
+                    self.set_open(pass, true);
+                }
+            }
+            Key::Tab if self.open => {
+                self.set_open(pass, false);
+                return;
+            }
+            _ => {
+                return;
+            }
+        }
+        pass.set_handled();
+    }
+
+    fn on_pointer_event(&mut self, pass: &mut EventPass<'_>, event: &PointerEvent) {
+        match event {
+            PointerEvent::Down {
+                button: PointerButton::Primary,
+                position,
+                ..
+            } => {
+                pass.request_focus();
+                if self.open {
+                    // Because the pointer is captured while open, clicks outside of the list
+                    // also end up here and simply dismiss it.
+                    if let Some(index) = self.option_at(pass.bounds(), *position) {
+                        self.select(pass, index);
+                    }
+                    self.set_open(pass, false);
+                } else {
+                    self.set_open(pass, true);
+                }
+                pass.set_handled();
+            }
+            PointerEvent::Down { .. } if self.open => {
+                self.set_open(pass, false);
+                pass.set_handled();
+            }
+            PointerEvent::Move { position, .. } if self.open => {
+                if let Some(index) = self.option_at(pass.bounds(), *position)
+                    && index != self.highlighted
+                {
+                    self.highlighted = index;
+                    pass.request_overlay_render();
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn on_focus(&mut self, pass: &mut EventPass<'_>, focused: bool) {
+        if !focused {
+            self.set_open(pass, false);
+        }
+    }
+}
+
+pub struct Link {
+    pub text: String,
+    pub url: String,
+    pub font_size: f32,
+
+    hovered: bool,
+    pressed: bool,
+    text_size: Xy<f32>,
+    last_error: Option<OpenUrlError>,
+}
+
+impl Link {
+    pub fn new(text: impl ToString, url: impl ToString) -> Self {
+        Self {
+            text: text.to_string(),
+            url: url.to_string(),
+            font_size: 16.0,
+            hovered: false,
+            pressed: false,
+            text_size: Xy::ZERO,
+            last_error: None,
+        }
+    }
+
+    pub fn with_font_size(mut self, font_size: f32) -> Self {
+        self.font_size = font_size;
+        self
+    }
+
+    /// The error produced by the last attempt to open this link, if it failed.
+    #[inline]
+    pub fn last_error(&self) -> Option<OpenUrlError> {
+        self.last_error
+    }
+
+    fn activate(&mut self, pass: &mut EventPass<'_>) {
+        self.last_error = system::open_url(&self.url).err();
+        pass.set_handled();
+    }
+}
+
+impl Element for Link {
+    fn accepts_keyboard_events(&self) -> bool {
+        true
+    }
+
+    fn accepts_focus_events(&self) -> bool {
+        true
+    }
+
+    fn render(&mut self, pass: &mut RenderPass<'_>) {
+        let color = Rgba {
+            r: 0x6d,
+            g: 0x9e,
+            b: 0xeb,
+            a: 255,
+        };
+
+        pass.fill_text(&self.text, pass.bounds(), color, self.font_size);
+
+        if self.hovered {
+            let bounds = pass.bounds();
+            pass.fill_quad(
+                Aabb2D::from_size_position(
+                    Xy::new(self.text_size.x, 1.0),
+                    Xy::new(bounds.min.x, bounds.min.y + self.text_size.y),
+                ),
+                color,
+                0.0,
+                Rgba::NONE,
+            );
+        }
+    }
+
+    fn measure(
+        &mut self,
+        context: &mut MeasureContext<'_>,
+        axis: Axis,
+        length_request: LengthRequest,
+        _cross_length: Option<f32>,
+    ) -> f32 {
+        let id = context.id();
+        self.text_size = context.fonts_mut().measure_text(
+            id,
+            &self.text,
+            &[],
+            None,
+            self.font_size,
+            LineHeight::Relative(1.0),
+            FontFamily::Proportional,
+            FontStyle::Normal,
+            TextAlignment::Start,
+            TextWrapMode::NoWrap,
+        );
+
+        match axis {
+            Axis::Horizontal => match length_request {
+                LengthRequest::MinContent | LengthRequest::MaxContent => self.text_size.x,
+                LengthRequest::FitContent(space) => space.min(self.text_size.x),
+            },
+            // Leave room for the underline.
+            Axis::Vertical => self.text_size.y + 1.0,
+        }
+    }
+
+    fn cursor_icon(&self) -> CursorIcon {
+        CursorIcon::PointingHand
+    }
+
+    fn on_keyboard_event(&mut self, pass: &mut EventPass<'_>, event: &KeyboardEvent) {
+        if matches!(
+            event,
+            KeyboardEvent::Down {
+                key: Key::Enter,
+                ..
+            }
+        ) {
+            self.activate(pass);
+        }
+    }
+
+    fn on_pointer_event(&mut self, pass: &mut EventPass<'_>, event: &PointerEvent) {
+        match event {
+            PointerEvent::Down {
+                button: PointerButton::Primary,
+                ..
+            } => {
+                self.pressed = true;
+                pass.capture_pointer();
+                pass.request_focus();
+            }
+            PointerEvent::Up {
+                button: PointerButton::Primary,
+                ..
+            } => {
+                // Only activate when the pointer is released over the link.
+                if std::mem::take(&mut self.pressed) && self.hovered {
+                    self.activate(pass);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn on_hover(&mut self, pass: &mut EventPass<'_>, hovered: bool) {
+        self.hovered = hovered;
+        pass.request_render();
+    }
+}
+
+
+
+pub struct UpdatePass<'view> {
+    state: &'view mut ElementState,
+    children: tree::LeavesMut<'view, ElementInfo>,
+    removed_ids: &'view mut Vec<u64>,
+    updates: &'view mpsc::Sender<UpdateMessage>,
+    timers: &'view mut Vec<Timer>,
+}
+
+impl UpdatePass<'_> {
+    /// Make a sender for messages to this element, such as to hand to a
+    /// background thread, see [`UpdateSender`].
+    pub fn update_sender(&self) -> UpdateSender {
+        UpdateSender {
+            element_id: self.state.id,
+            sender: self.updates.clone(),
+        }
+    }
+
+    /// See [`Element::update_children`].
+    ///
+    /// If the child was built with a key (see [`ElementBuilder::with_key`])
+    /// that matches an existing child of the same type, the existing child is
+    /// reused, and `child` takes over its ID.
+    pub fn update_child(&mut self, child: &mut ChildElement) {
+        let Some(ElementBuilder { id, key, element }) = child.take_inner() else {
+            return;
+        };
+
+        if let Some(key) = key
+            && let Some(reused_id) = self.find_reusable_child(key, (*element).type_id())
+        {
+            self.state.detached_children.retain(|id| *id != reused_id);
+            self.state.reused_children.push(reused_id);
+            child.id = reused_id;
+            return;
+        }
+
+        let mut state = ElementState::new(id);
+        state.key = key;
+        let info = ElementInfo { element, state };
+
+        self.children.insert(id, info);
+    }
+
+    fn find_reusable_child(&self, key: u64, type_id: TypeId) -> Option<u64> {
+        let children = self.children.reborrow();
+        let is_match = |id: &u64| {
+            !self.state.reused_children.contains(id)
+                && children.get(*id).is_some_and(|node| {
+                    node.element.state.key == Some(key)
+                        && !node.element.state.exiting
+                        && (*node.element.element).type_id() == type_id
+                })
+        };
+
+        // NOTE: Children that were removed during this update are preferred, as
+        //       any others are still listed by the element.
+        self.state
+            .detached_children
+            .iter()
+            .copied()
+            .find(is_match)
+            .or_else(|| self.children.reborrow().ids().find(is_match))
+    }
+
+    /// Remove a child that this element no longer lists in
+    /// [`Element::children_ids`], letting it play its exit transition first
+    /// (see [`Element::on_exit`]).
+    pub fn remove_child(&mut self, child: ChildElement) {
+        self.remove_child_by_id(child.id());
+    }
+
+    /// Remove every child of this element, see [`UpdatePass::remove_child`].
+    ///
+    /// The element should also drop its [`ChildElement`]s.
+    pub fn clear_children(&mut self) {
+        let child_ids = self
+            .children
+            .reborrow()
+            .ids()
+            .filter(|id| !self.state.exiting_children.contains(id))
+            .collect::<Vec<_>>();
+        for child_id in child_ids {
+            self.remove_child_by_id(child_id);
+        }
+    }
+
+    fn remove_child_by_id(&mut self, child_id: u64) {
+        if self.state.reused_children.contains(&child_id)
+            || self.state.detached_children.contains(&child_id)
+        {
+            return;
+        }
+        let Some(node) = self.children.get(child_id) else {
+            return;
+        };
+        if node.element.state.exiting {
+            return;
+        }
+        // Keyed children are only removed after the update, in case they're
+        // rebuilt.
+        if node.element.state.key.is_some() {
+            self.state.detached_children.push(child_id);
+            return;
+        }
+
+        self.remove_child_now(child_id);
+    }
+
+    /// Remove the keyed children that weren't reused during this update.
+    fn finish_reuse(&mut self) {
+        self.state.reused_children.clear();
+        for child_id in std::mem::take(&mut self.state.detached_children) {
+            self.remove_child_now(child_id);
+        }
+    }
+
+    fn remove_child_now(&mut self, child_id: u64) {
+        let Some(mut node) = self.children.get_mut(child_id) else {
+            return;
+        };
+
+        let element = &mut *node.element.element;
+        let state = &mut node.element.state;
+        let mut pass = UpdatePass {
+            state,
+            children: node.leaves.reborrow_mut(),
+            removed_ids: self.removed_ids,
+            updates: self.updates,
+            timers: self.timers,
+        };
+        let exiting = element.on_exit(&mut pass);
+        pass.finish_reuse();
+
+        if exiting {
+            node.element.state.exiting = true;
+            self.state.exiting_children.push(child_id);
+            self.state.merge_with_child(&node.element.state);
+        } else {
+            collect_element_ids(node.reborrow(), self.removed_ids);
+            self.children.remove(child_id);
+        }
+    }
+}
+
+pub fn update_pass(view: &mut View) {
+    let mut removed_ids = Vec::new();
+    for id in view.layer_root_ids().collect::<Vec<_>>() {
+        let node = view
+            .tree
+            .find_mut(id)
+            .expect("failed to find a layer's root node");
+
+        update_element_tree(
+            node,
+            &mut removed_ids,
+            &view.update_sender,
+            &mut view.timers,
+        );
+    }
+
+    view.forget_elements(removed_ids);
+}
+
+fn update_element_tree(
+    node: tree::NodeMut<'_, ElementInfo>,
+    removed_ids: &mut Vec<u64>,
+    updates: &mpsc::Sender<UpdateMessage>,
+    timers: &mut Vec<Timer>,
+) {
+    let mut children = node.leaves;
+    let element = &mut *node.element.element;
+    let state = &mut node.element.state;
+
+    if !state.children_changed {
+        return;
+    }
+
+    state.children_changed = false;
+    // NOTE: Adding or removing children usually changes this element's size.
+    state.needs_layout = true;
+    state.wants_layout = true;
+
+    // Children whose exit transitions have finished can now be removed.
+    for child_id in std::mem::take(&mut state.exiting_children) {
+        match children.get(child_id) {
+            Some(child) if child.element.state.exited => {
+                collect_element_ids(child, removed_ids);
+                children.remove(child_id);
+            }
+            Some(_) => state.exiting_children.push(child_id),
+            None => {}
+        }
+    }
+
+    let mut pass = UpdatePass {
+        state,
+        children: children.reborrow_mut(),
+        removed_ids,
+        updates,
+        timers,
+    };
+    element.update_children(&mut pass);
+    if pass.state.newly_added {
+        pass.state.newly_added = false;
+        element.on_build(&mut pass);
+        element.on_enter(&mut pass);
+    }
+    pass.finish_reuse();
+
+    let exiting = state.exiting_children.clone();
+    let parent_state = &mut *state;
+    for_each_child_element(element, &exiting, children, |mut node| {
+        update_element_tree(node.reborrow_mut(), removed_ids, updates, timers);
+        parent_state.merge_with_child(&node.element.state);
+    });
+}
+
+fn update_pointer_pass(view: &mut View) {
+    let next_hovered_element = view
+        .pointer_position
+        .and_then(|pos| find_layered_pointer_target(view, pos));
+    let next_hovered_path = next_hovered_element.map_or(Vec::new(), |node_id| {
+        view.tree.branches().get_id_path(node_id, None)
+    });
+    let prev_hovered_path = std::mem::take(&mut view.hovered_path);
+    let prev_hovered_element = prev_hovered_path.first().copied();
+
+    if prev_hovered_path != next_hovered_path {
+        let mut hovered_set = HashSet::new();
+        for node_id in &next_hovered_path {
+            hovered_set.insert(*node_id);
+        }
+
+        for node_id in prev_hovered_path.iter().copied() {
+            if view
+                .tree
+                .find_mut(node_id)
+                .map(|node| node.element.state.hovered != hovered_set.contains(&node_id))
+                .unwrap_or(false)
+            {
+                let hovered = hovered_set.contains(&node_id);
+                event_pass(view, Some(node_id), |element, pass| {
+                    if pass.state.hovered != hovered {
+                        element.on_child_hover(pass, hovered);
+                    }
+                    pass.state.hovered = hovered;
+                });
+            }
+        }
+        for node_id in next_hovered_path.iter().copied() {
+            if view
+                .tree
+                .find_mut(node_id)
+                .map(|node| node.element.state.hovered != hovered_set.contains(&node_id))
+                .unwrap_or(false)
+            {
+                let hovered = hovered_set.contains(&node_id);
+                event_pass(view, Some(node_id), |element, pass| {
+                    if pass.state.hovered != hovered {
+                        element.on_child_hover(pass, hovered);
+                    }
+                    pass.state.hovered = hovered;
+                });
+            }
+        }
+    }
+
+    if prev_hovered_element != next_hovered_element {
+        single_event_pass(view, prev_hovered_element, |element, pass| {
+            pass.state.hovered = false;
+            element.on_hover(pass, false);
+        });
+        single_event_pass(view, next_hovered_element, |element, pass| {
+            pass.state.hovered = true;
+            element.on_hover(pass, true);
+        });
+        if let Some(element) = prev_hovered_element {
+            view.push_event(ViewEvent::PointerLeave { element });
+        }
+        if let Some(element) = next_hovered_element {
+            view.push_event(ViewEvent::PointerEnter { element });
+        }
+    }
+
+    let next_cursor_icon =
+        if let Some(node_id) = view.pointer_capture_target.or(next_hovered_element) {
+            let node = view
+                .tree
+                .find_mut(node_id)
+                .expect("failed to find the view's root node");
+
+            node.element.element.cursor_icon()
+        } else {
+            CursorIcon::Default
+        };
+
+    view.cursor_icon = next_cursor_icon;
+    view.hovered_path = next_hovered_path;
+}
+
+fn update_focus_pass(view: &mut View) {
+    let next_focused_element = view.next_focused_element;
+    let next_focused_path = next_focused_element.map_or(Vec::new(), |node_id| {
+        view.tree.branches().get_id_path(node_id, None)
+    });
+    let prev_focused_path = std::mem::take(&mut view.focused_path);
+    let prev_focused_element = prev_focused_path.first().copied();
+
+    if prev_focused_path != next_focused_path {
+        let mut focused_set = HashSet::new();
+        for node_id in &next_focused_path {
+            focused_set.insert(*node_id);
+        }
+
+        for node_id in prev_focused_path.iter().copied() {
+            if view
+                .tree
+                .find_mut(node_id)
+                .map(|node| node.element.state.focused != focused_set.contains(&node_id))
+                .unwrap_or(false)
+            {
+                let focused = focused_set.contains(&node_id);
+                event_pass(view, Some(node_id), |element, pass| {
+                    if pass.state.focused != focused {
+                        element.on_child_focus(pass, focused);
+                    }
+                    pass.state.focused = focused;
+                });
+            }
+        }
+        for node_id in next_focused_path.iter().copied() {
+            if view
+                .tree
+                .find_mut(node_id)
+                .map(|node| node.element.state.focused != focused_set.contains(&node_id))
+                .unwrap_or(false)
+            {
+                let focused = focused_set.contains(&node_id);
+                event_pass(view, Some(node_id), |element, pass| {
+                    if pass.state.focused != focused {
+                        element.on_child_focus(pass, focused);
+                    }
+                    pass.state.focused = focused;
+                });
+            }
+        }
+    }
+
+    if prev_focused_element != next_focused_element {
+        single_event_pass(view, prev_focused_element, |element, pass| {
+            pass.state.focused = false;
+            element.on_focus(pass, false);
+        });
+        single_event_pass(view, next_focused_element, |element, pass| {
+            pass.state.focused = true;
+            element.on_focus(pass, true);
+        });
+        view.push_event(ViewEvent::Focus {
+            previous: prev_focused_element,
+            element: next_focused_element,
+        });
+        view.scroll_requests.extend(next_focused_element);
+    }
+
+    view.focused_element = next_focused_element;
+    view.focused_path = next_focused_path;
+}
+
+
+
+pub struct EventPass<'view> {
+    state: &'view mut ElementState,
+    children: tree::LeavesMut<'view, ElementInfo>,
+    handled: bool,
+    next_focus: &'view mut Option<u64>,
+    pointer_capture_target: &'view mut Option<u64>,
+    pointer_capture_held: &'view mut bool,
+    popup_requests: &'view mut Vec<PopupRequest>,
+    scroll_requests: &'view mut Vec<u64>,
+    context_menu_actions: &'view mut Vec<(u64, u64)>,
+    drag_request: &'view mut Option<Drag>,
+    updates: &'view mpsc::Sender<UpdateMessage>,
+    timers: &'view mut Vec<Timer>,
+    fonts: &'view mut dyn Fonts,
+    modifiers: Modifiers,
+}
+
+impl EventPass<'_> {
+    pub fn set_handled(&mut self) {
+        self.handled = true;
+    }
+
+    /// Make a sender for messages to this element, such as to hand to a
+    /// background thread, see [`UpdateSender`].
+    pub fn update_sender(&self) -> UpdateSender {
+        UpdateSender {
+            element_id: self.state.id,
+            sender: self.updates.clone(),
+        }
+    }
+
+    /// The view's fonts, such as to find where in some text the pointer is with
+    /// [`Fonts::hit_test_text`].
+    #[inline]
+    pub fn fonts_mut(&mut self) -> &mut dyn Fonts {
+        self.fonts
+    }
+
+    /// The modifier keys held when the event occurred.
+    #[inline]
+    pub fn modifiers(&self) -> Modifiers {
+        self.modifiers
+    }
+
+    pub fn request_focus(&mut self) {
+        *self.next_focus = Some(self.state.id);
+    }
+
+    pub fn capture_pointer(&mut self) {
+        *self.pointer_capture_target = Some(self.state.id);
+        *self.pointer_capture_held = false;
+    }
+
+    /// Capture the pointer until [`EventPass::release_pointer`] is called,
+    /// rather than until the next [`PointerEvent::Up`].
+    ///
+    /// This is useful for popups that need to see clicks outside of their
+    /// bounds.
+    pub fn hold_pointer_capture(&mut self) {
+        *self.pointer_capture_target = Some(self.state.id);
+        *self.pointer_capture_held = true;
+    }
+
+    /// Open the given element in a new layer above the rest of the view. Popups
+    /// receive pointer events before anything below them, and modal popups
+    /// prevent any events from reaching the layers below them.
+    ///
+    /// The popup is opened once this event has finished propagating.
+    pub fn open_popup(&mut self, builder: ElementBuilder, options: PopupOptions) -> PopupId {
+        let id = PopupId(builder.id);
+        self.popup_requests
+            .push(PopupRequest::Open { builder, options });
+
+        id
+    }
+
+    /// Close the given popup once this event has finished propagating.
+    pub fn close_popup(&mut self, id: PopupId) {
+        self.popup_requests.push(PopupRequest::Close(id));
+    }
+
+    /// Scroll this element's scroll container ancestors so that it's visible,
+    /// see [`Element::on_scroll_into_view`].
+    ///
+    /// The scroll happens once this event has finished propagating. Elements
+    /// are scrolled into view automatically when they gain focus.
+    pub fn request_scroll_into_view(&mut self) {
+        self.scroll_requests.push(self.state.id);
+    }
+
+    pub fn release_pointer(&mut self) {
+        if *self.pointer_capture_target == Some(self.state.id) {
+            *self.pointer_capture_target = None;
+            *self.pointer_capture_held = false;
+        }
+    }
+
+    /// Start dragging the given payload from this element, replacing any drag
+    /// that's already in progress. See [`Element::on_drag_over`].
+    ///
+    /// The drag starts once this event has finished propagating, and ends when
+    /// the pointer is released.
+    pub fn start_drag(&mut self, payload: Box<dyn Any>) {
+        *self.drag_request = Some(Drag::new(self.state.id, payload, Xy::ZERO));
+    }
+
+    /// Send an action chosen from a context menu to the element that provided
+    /// the menu, once this event has finished propagating.
+    pub(crate) fn send_context_menu_action(&mut self, owner_id: u64, action: u64) {
+        self.context_menu_actions.push((owner_id, action));
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+#[repr(u32)] // TODO: Finish adding the extra pointer buttons (up to button 32).
+pub enum PointerButton {
+    // TODO: Nullable type?
+    Primary = 1,
+    Secondary = 1 << 1,
+    Auxiliary = 1 << 2,
+    Back = 1 << 3,
+    Forward = 1 << 4,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[repr(C)]
+pub enum PointerEvent {
+    Down {
+        button: PointerButton,
+        position: Xy<f32>,
+        modifiers: Modifiers,
+    },
+    Up {
+        button: PointerButton,
+        modifiers: Modifiers,
+    },
+    Move {
+        position: Xy<f32>,
+        modifiers: Modifiers,
+    },
+    Scroll {
+        delta: ScrollDelta,
+        modifiers: Modifiers,
+    },
+    /// A finger touched, moved on, or left a touchscreen. Each touch has its
+    /// own `id` from when it starts until it ends.
+    ///
+    /// Views also translate touches into the other pointer events (see
+    /// [`TouchTracker`](crate::touch::TouchTracker)), so elements only need to
+    /// handle these to track individual fingers.
+    Touch {
+        id: u64,
+        phase: TouchPhase,
+        position: Xy<f32>,
+        modifiers: Modifiers,
+    },
+    /// A request to scale the content around `position` by `factor`, such as
+    /// from pinching two fingers together (less than 1) or apart (more than 1).
+    ///
+    /// The view zooms (see [`View::set_zoom`]) if no element handles it.
+    Zoom {
+        factor: f32,
+        position: Xy<f32>,
+        modifiers: Modifiers,
+    },
+}
+
+impl PointerEvent {
+    /// The modifier keys that were held when the event occurred.
+    pub fn modifiers(&self) -> Modifiers {
+        match self {
+            Self::Down { modifiers, .. }
+            | Self::Up { modifiers, .. }
+            | Self::Move { modifiers, .. }
+            | Self::Scroll { modifiers, .. }
+            | Self::Touch { modifiers, .. }
+            | Self::Zoom { modifiers, .. } => *modifiers,
+        }
+    }
+}
+
+/// See [`PointerEvent::Touch`].
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+#[repr(u8)]
+pub enum TouchPhase {
+    Start,
+    Move,
+    End,
+    /// The touch stopped without the finger being lifted, such as because the
+    /// touchscreen was unplugged.
+    Cancel,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ScrollDelta {
+    Pixels(Xy<f32>),
+    Lines(Xy<f32>),
+}
+
+impl ScrollDelta {
+    pub fn to_pixels(self, line_size: Xy<f32>) -> Xy<f32> {
+        match self {
+            Self::Pixels(delta) => delta,
+            ScrollDelta::Lines(delta) => delta * line_size,
+        }
+    }
+}
+
+/// A higher-level interaction recognized from a sequence of
+/// [`PointerEvent`]s, see [`Element::on_gesture`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Gesture {
+    /// A button was pressed. `count` is 2 for a double-click, 3 for a
+    /// triple-click, and so on.
+    Click {
+        button: PointerButton,
+        position: Xy<f32>,
+        count: u32,
+    },
+    /// The pointer moved further than the [`GestureSettings::drag_threshold`]
+    /// while a button was held. Sent to the element the button was pressed on.
+    DragStart {
+        button: PointerButton,
+        /// Where the button was pressed.
+        origin: Xy<f32>,
+    },
+    DragMove {
+        button: PointerButton,
+        position: Xy<f32>,
+        /// The movement since the last [`Gesture::DragStart`] or
+        /// [`Gesture::DragMove`].
+        delta: Xy<f32>,
+    },
+    DragEnd {
+        button: PointerButton,
+        position: Xy<f32>,
+    },
+}
+
+/// A high-level interaction with a [`View`], which hosts can observe with
+/// [`View::poll_events`] (such as for logging, or to decide which window is
+/// active).
+///
+/// Events are reported whether or not an element handled the input that caused
+/// them.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ViewEvent {
+    /// A button was pressed on an element, see [`Gesture::Click`].
+    Click {
+        element: u64,
+        button: PointerButton,
+        position: Xy<f32>,
+        count: u32,
+    },
+    /// The focus moved from `previous` to `element`.
+    Focus {
+        previous: Option<u64>,
+        element: Option<u64>,
+    },
+    /// The pointer moved onto an element (and off of the one it was over).
+    PointerEnter {
+        element: u64,
+    },
+    PointerLeave {
+        element: u64,
+    },
+}
+
+/// The most events a [`View`] keeps for [`View::poll_events`].
+pub const MAX_QUEUED_VIEW_EVENTS: usize = 256;
+
+/// A message for an element, along with its ID.
+type UpdateMessage = (u64, Box<dyn Any + Send>);
+
+/// Sends messages to an element from any thread, which it receives in
+/// [`Element::on_message`] once the host calls [`View::process_updates`].
+///
+/// This lets elements update in response to work done in the background, such
+/// as reading a file.
+#[derive(Clone)]
+pub struct UpdateSender {
+    element_id: u64,
+    sender: mpsc::Sender<UpdateMessage>,
+}
+
+impl Debug for UpdateSender {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("UpdateSender")
+            .field("element_id", &self.element_id)
+            .finish_non_exhaustive()
+    }
+}
+
+impl UpdateSender {
+    /// The ID of the element that messages are sent to.
+    #[inline]
+    pub fn element_id(&self) -> u64 {
+        self.element_id
+    }
+
+    /// Queue a message for the element, failing if the view was dropped.
+    pub fn send(&self, message: impl Any + Send) -> Result<(), ViewDroppedError> {
+        self.sender
+            .send((self.element_id, Box::new(message)))
+            .map_err(|_| ViewDroppedError)
+    }
+}
+
+/// See [`UpdateSender::send`].
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct ViewDroppedError;
+
+impl fmt::Display for ViewDroppedError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("the view was dropped")
+    }
+}
+
+impl std::error::Error for ViewDroppedError {}
+
+/// See [`View::set_gesture_settings`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct GestureSettings {
+    /// The longest time between presses that still counts as a multi-click.
+    pub multi_click_timeout: Duration,
+    /// The furthest the pointer can move between presses that still counts as a
+    /// multi-click.
+    pub multi_click_distance: f32,
+    /// How far the pointer must move while a button is held to start a drag.
+    pub drag_threshold: f32,
+}
+
+impl Default for GestureSettings {
+    fn default() -> Self {
+        Self {
+            multi_click_timeout: Duration::from_millis(500),
+            multi_click_distance: 4.0,
+            drag_threshold: 4.0,
+        }
+    }
+}
+
+#[derive(Default)]
+struct GestureState {
+    press: Option<GesturePress>,
+    last_click: Option<LastClick>,
+}
+
+struct GesturePress {
+    button: PointerButton,
+    origin: Xy<f32>,
+    /// The element that was pressed, which receives any drag gestures.
+    target: u64,
+    /// The last position the pointer was dragged to, or `None` if it hasn't
+    /// started dragging.
+    drag_position: Option<Xy<f32>>,
+}
+
+struct LastClick {
+    button: PointerButton,
+    position: Xy<f32>,
+    time: Instant,
+    count: u32,
+}
+
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+#[repr(C)]
+pub enum Key {
+    Char(char),
+
+    Space,
+    Tab,
+    Enter,
+    Backspace,
+    Delete,
+    Escape,
+
+    ArrowUp,
+    ArrowDown,
+    ArrowLeft,
+    ArrowRight,
+
+    PageUp,
+    PageDown,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[repr(C)]
+pub enum KeyboardEvent {
+    Down {
+        key: Key,
+        modifiers: Modifiers,
+        /// Whether the key is being held, and this event is one of the host's
+        /// key repeats rather than the key being pressed.
+        repeat: bool,
+    },
+    Up {
+        key: Key,
+        modifiers: Modifiers,
+    },
+}
+
+impl KeyboardEvent {
+    pub fn key(&self) -> Key {
+        match self {
+            Self::Down { key, .. } | Self::Up { key, .. } => *key,
+        }
+    }
+
+    /// The modifier keys that were held when the event occurred.
+    pub fn modifiers(&self) -> Modifiers {
+        match self {
+            Self::Down { modifiers, .. } | Self::Up { modifiers, .. } => *modifiers,
+        }
+    }
+
+    /// Whether this is a key repeat, see [`KeyboardEvent::Down`].
+    pub fn is_repeat(&self) -> bool {
+        matches!(self, Self::Down { repeat: true, .. })
+    }
+}
+
+/// The modifier keys that are held down, which every [`KeyboardEvent`] and
+/// [`PointerEvent`] carries.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+#[repr(C)]
+pub struct Modifiers {
+    pub ctrl: bool,
+    pub shift: bool,
+    pub alt: bool,
+    /// The "super" key, such as the Windows or Command key.
+    pub meta: bool,
+}
+
+impl Modifiers {
+    pub const NONE: Self = Self {
+        ctrl: false,
+        shift: false,
+        alt: false,
+        meta: false,
+    };
+    pub const CTRL: Self = Self {
+        ctrl: true,
+        ..Self::NONE
+    };
+    pub const SHIFT: Self = Self {
+        shift: true,
+        ..Self::NONE
+    };
+    pub const ALT: Self = Self {
+        alt: true,
+        ..Self::NONE
+    };
+    pub const META: Self = Self {
+        meta: true,
+        ..Self::NONE
+    };
+
+    /// Whether no modifier keys are held.
+    pub const fn is_empty(&self) -> bool {
+        !(self.ctrl || self.shift || self.alt || self.meta)
+    }
+
+    /// Combine two sets of modifiers, such as
+    /// `Modifiers::CTRL.union(Modifiers::SHIFT)`.
+    pub const fn union(self, other: Self) -> Self {
+        Self {
+            ctrl: self.ctrl || other.ctrl,
+            shift: self.shift || other.shift,
+            alt: self.alt || other.alt,
+            meta: self.meta || other.meta,
+        }
+    }
+}
+
+/// The zoom factors stepped through by [`View::zoom_in`] and
+/// [`View::zoom_out`].
+pub const ZOOM_LEVELS: &[f32] = &[
+    0.5, 0.67, 0.75, 0.8, 0.9, 1.0, 1.1, 1.25, 1.5, 1.75, 2.0, 2.5, 3.0,
+];
+
+/// The setting that [`View::load_zoom_setting`] persists the zoom in.
+const ZOOM_SETTING: &str = "view.zoom";
+
+/// Send an event to the target and each of its ancestors until one of them
+/// handles it, returning whether one did.
+fn event_pass(
+    view: &mut View,
+    target: Option<u64>,
+    mut callback: impl FnMut(&mut dyn Element, &mut EventPass<'_>),
+) -> bool {
+    let mut target_id = target;
+    let mut handled = false;
+    while let Some(node_id) = target_id {
+        let parent_id = {
+            let node = view
+                .tree
+                .find_mut(node_id)
+                .expect("invalid element ID for event target");
+
+            if !handled {
+                let mut pass = EventPass {
+                    state: &mut node.element.state,
+                    children: node.leaves,
+                    handled: false,
+                    next_focus: &mut view.next_focused_element,
+                    pointer_capture_target: &mut view.pointer_capture_target,
+                    pointer_capture_held: &mut view.pointer_capture_held,
+                    popup_requests: &mut view.popup_requests,
+                    scroll_requests: &mut view.scroll_requests,
+                    context_menu_actions: &mut view.context_menu_actions,
+                    drag_request: &mut view.drag_request,
+                    updates: &view.update_sender,
+                    timers: &mut view.timers,
+                    fonts: &mut *view.fonts,
+                    modifiers: view.modifiers,
+                };
+                callback(&mut *node.element.element, &mut pass);
+
+                handled = pass.handled;
+            }
+
+            node.branch_id
+        };
+
+        if let Some(parent_id) = parent_id {
+            let mut parent_node = view.tree.find_mut(parent_id).unwrap();
+            let node = parent_node.leaves.get_mut(node_id).unwrap();
+
+            parent_node
+                .element
+                .state
+                .merge_with_child(&node.element.state);
+        }
+
+        target_id = parent_id;
+    }
+
+    handled
+}
+
+fn single_event_pass(
+    view: &mut View,
+    target: Option<u64>,
+    mut callback: impl FnMut(&mut dyn Element, &mut EventPass<'_>),
+) {
+    let Some(target) = target else {
+        return;
+    };
+
+    let node = view
+        .tree
+        .find_mut(target)
+        .expect("invalid element ID passed to single_event_pass");
+
+    let mut pass = EventPass {
+        state: &mut node.element.state,
+        children: node.leaves,
+        handled: false,
+        next_focus: &mut view.next_focused_element,
+        pointer_capture_target: &mut view.pointer_capture_target,
+        pointer_capture_held: &mut view.pointer_capture_held,
+        popup_requests: &mut view.popup_requests,
+        scroll_requests: &mut view.scroll_requests,
+        context_menu_actions: &mut view.context_menu_actions,
+        drag_request: &mut view.drag_request,
+        updates: &view.update_sender,
+        timers: &mut view.timers,
+        fonts: &mut *view.fonts,
+        modifiers: view.modifiers,
+    };
+    callback(&mut *node.element.element, &mut pass);
+
+    let mut current_id = Some(target);
+    while let Some(node_id) = current_id {
+        let parent_id = view
+            .tree
+            .find_mut(node_id)
+            .expect("invalid element ID for pointer target")
+            .branch_id;
+        if let Some(parent_id) = parent_id {
+            let mut parent_node = view.tree.find_mut(parent_id).unwrap();
+            let node = parent_node.leaves.get_mut(node_id).unwrap();
+
+            parent_node
+                .element
+                .state
+                .merge_with_child(&node.element.state);
+        }
+
+        current_id = parent_id;
+    }
+}
+
+fn keyboard_event_pass(view: &mut View, event: &KeyboardEvent) {
+    if view.drag.is_some()
+        && let KeyboardEvent::Down {
+            key: Key::Escape, ..
+        } = event
+    {
+        cancel_drag(view);
+        return;
+    }
+
+    let focused_element = view
+        .focused_element
+        .filter(|id| !is_below_modal_popup(view, *id));
+
+    event_pass(view, focused_element, |element, pass| {
+        element.on_keyboard_event(pass, event)
+    });
+
+    for node_id in view.hovered_path.clone() {
+        if view.tree.find(node_id).is_some() && !is_below_modal_popup(view, node_id) {
+            single_event_pass(view, Some(node_id), |element, pass| {
+                element.on_hovered_keyboard_event(pass, event)
+            });
+        }
+    }
+
+    send_context_menu_actions(view);
+}
+
+fn pointer_event_pass(view: &mut View, event: &PointerEvent) {
+    // let mut pointer_entered = false;
+    if let PointerEvent::Move { position, .. } = &event {
+        if view.pointer_position == Some(*position) {
+            return;
+        }
+        // pointer_entered = view.pointer_position.is_none();
+        view.pointer_position = Some(*position);
+    }
+    let target_position = match *event {
+        PointerEvent::Touch { position, .. } | PointerEvent::Zoom { position, .. } => {
+            Some(position)
+        }
+        _ => view.pointer_position,
+    };
+    let pointer_target = get_pointer_target(&view, target_position);
+
+    if matches!(event, PointerEvent::Down { .. })
+        && let Some(target_id) = pointer_target
+    {
+        // Clear the focus when the user clicks outside the focused element.
+        if let Some(focused_element) = view.focused_element {
+            // Focused element isn't an ancestor of the pointer target.
+            if !view
+                .tree
+                .branches()
+                .get_id_path(target_id, None)
+                .contains(&focused_element)
+            {
+                view.next_focused_element = None;
+            }
+        }
+    }
+
+    let handled = event_pass(view, pointer_target, |element, pass| {
+        element.on_pointer_event(pass, event)
+    });
+    if let PointerEvent::Zoom { factor, .. } = *event
+        && !handled
+    {
+        view.set_zoom(view.zoom * factor);
+    }
+
+    if let PointerEvent::Down {
+        button: PointerButton::Secondary,
+        position,
+        ..
+    } = *event
+    {
+        open_context_menu(view, pointer_target, position);
+    }
+
+    if let Some((target, gesture)) = recognize_gesture(view, event, pointer_target)
+        && view.tree.find(target).is_some()
+    {
+        if let Gesture::Click {
+            button,
+            position,
+            count,
+        } = gesture
+        {
+            view.push_event(ViewEvent::Click {
+                element: target,
+                button,
+                position,
+                count,
+            });
+        }
+        event_pass(view, Some(target), |element, pass| {
+            element.on_gesture(pass, &gesture)
+        });
+    }
+
+    if matches!(event, PointerEvent::Up { .. }) && !view.pointer_capture_held {
+        view.pointer_capture_target = None;
+    }
+
+    begin_drag(view);
+    match *event {
+        PointerEvent::Move { position, .. } => update_drag(view, position),
+        PointerEvent::Up { .. } => finish_drag(view),
+        _ => {}
+    }
+
+    send_context_menu_actions(view);
+}
+
+struct ActiveDrag {
+    drag: Drag,
+    ghost_id: u64,
+    /// The element that accepted the drag, if any.
+    target_id: Option<u64>,
+}
+
+/// Start the drag requested with [`EventPass::start_drag`], if any.
+fn begin_drag(view: &mut View) {
+    let Some(mut drag) = view.drag_request.take() else {
+        return;
+    };
+    cancel_drag(view);
+
+    let position = view.pointer_position.unwrap_or(Xy::ZERO);
+    drag.set_position(position);
+    let size = view
+        .tree
+        .find(drag.source_id())
+        .map_or(Xy::ZERO, |node| node.element.state.layout_bounds.size());
+
+    let builder = ElementBuilder::new(DragGhost::new(size));
+    let ghost_id = builder.id;
+    view.popup_requests.push(PopupRequest::Open {
+        builder,
+        options: PopupOptions {
+            position: drag_ghost_position(view, position),
+            modal: false,
+        },
+    });
+    view.drag = Some(ActiveDrag {
+        drag,
+        ghost_id,
+        target_id: None,
+    });
+}
+
+/// Move the drag ghost to the pointer, and offer the drag to the element under
+/// it and that element's ancestors until one accepts it.
+fn update_drag(view: &mut View, position: Xy<f32>) {
+    let Some(mut active) = view.drag.take() else {
+        return;
+    };
+    active.drag.set_position(position);
+
+    let ghost_position = drag_ghost_position(view, position);
+    if let Some(popup) = view
+        .popups
+        .iter_mut()
+        .find(|popup| popup.id == active.ghost_id)
+    {
+        popup.position = ghost_position;
+    }
+
+    let mut next_target_id = None;
+    if let Some(hit_id) = find_layered_pointer_target(view, position) {
+        for node_id in view.tree.branches().get_id_path(hit_id, None) {
+            let mut accepted = false;
+            single_event_pass(view, Some(node_id), |element, pass| {
+                accepted = element.on_drag_over(pass, &active.drag);
+            });
+            if accepted {
+                next_target_id = Some(node_id);
+                break;
+            }
+        }
+    }
+
+    if active.target_id != next_target_id {
+        if let Some(target_id) = active.target_id
+            && view.tree.find(target_id).is_some()
+        {
+            single_event_pass(view, Some(target_id), |element, pass| {
+                element.on_drag_leave(pass)
+            });
+        }
+        active.target_id = next_target_id;
+    }
+
+    if let Some(node) = view.tree.find_mut(active.ghost_id) {
+        let accepted = next_target_id.is_some();
+        if let Some(ghost) =
+            (&mut *node.element.element as &mut dyn Any).downcast_mut::<DragGhost>()
+            && ghost.set_accepted(accepted)
+        {
+            node.element.state.wants_render = true;
+            node.element.state.needs_render = true;
+        }
+    }
+
+    view.drag = Some(active);
+}
+
+/// Drop the drag on the element that accepted it, if any.
+fn finish_drag(view: &mut View) {
+    let Some(active) = view.drag.take() else {
+        return;
+    };
+    view.popup_requests
+        .push(PopupRequest::Close(PopupId(active.ghost_id)));
+
+    if let Some(target_id) = active.target_id
+        && view.tree.find(target_id).is_some()
+    {
+        let mut drag = Some(active.drag);
+        single_event_pass(view, Some(target_id), |element, pass| {
+            if let Some(drag) = drag.take() {
+                element.on_drop(pass, drag);
+            }
+        });
+    }
+}
+
+fn cancel_drag(view: &mut View) {
+    let Some(active) = view.drag.take() else {
+        return;
+    };
+    view.popup_requests
+        .push(PopupRequest::Close(PopupId(active.ghost_id)));
+
+    if let Some(target_id) = active.target_id
+        && view.tree.find(target_id).is_some()
+    {
+        single_event_pass(view, Some(target_id), |element, pass| {
+            element.on_drag_leave(pass)
+        });
+    }
+}
+
+/// Popups are positioned in logical units, unlike the pointer.
+fn drag_ghost_position(view: &View, pointer_position: Xy<f32>) -> Xy<f32> {
+    Xy::new(
+        pointer_position.x / view.zoom,
+        pointer_position.y / view.zoom,
+    ) + DragGhost::OFFSET
+}
+
+/// Open the context menu provided by the given element or its nearest ancestor
+/// that has one, if any.
+fn open_context_menu(view: &mut View, target: Option<u64>, position: Xy<f32>) {
+    let Some(target) = target else {
+        return;
+    };
+    // NOTE: Right-clicking an open menu (or outside of it, since it holds the
+    //       pointer capture) only dismisses it.
+    if view.tree.find(target).is_none() || view.pointer_capture_held {
+        return;
+    }
+
+    let mut provided_menu = None;
+    for node_id in view.tree.branches().get_id_path(target, None) {
+        let node = view.tree.find_mut(node_id).expect("infallible");
+        if let Some(menu) = node.element.element.context_menu(position)
+            && !menu.is_empty()
+        {
+            provided_menu = Some((node_id, menu));
+            break;
+        }
+    }
+    let Some((owner_id, menu)) = provided_menu else {
+        return;
+    };
+
+    let builder = ElementBuilder::new(ContextMenuPopup::new(owner_id, menu));
+    let menu_id = builder.id;
+    view.popup_requests.push(PopupRequest::Open {
+        builder,
+        options: PopupOptions {
+            position: Xy::new(position.x / view.zoom, position.y / view.zoom),
+            modal: false,
+        },
+    });
+    view.pointer_capture_target = Some(menu_id);
+    view.pointer_capture_held = true;
+    view.next_focused_element = Some(menu_id);
+}
+
+fn send_context_menu_actions(view: &mut View) {
+    for (owner_id, action) in std::mem::take(&mut view.context_menu_actions) {
+        if view.tree.find(owner_id).is_some() {
+            single_event_pass(view, Some(owner_id), |element, pass| {
+                element.on_context_menu_action(pass, action)
+            });
+        }
+    }
+}
+
+/// Update the gesture state with the given event, returning the gesture it
+/// completed (if any) and the element to send it to.
+fn recognize_gesture(
+    view: &mut View,
+    event: &PointerEvent,
+    pointer_target: Option<u64>,
+) -> Option<(u64, Gesture)> {
+    let settings = view.gesture_settings;
+    let state = &mut view.gesture_state;
+
+    match *event {
+        PointerEvent::Down {
+            button, position, ..
+        } => {
+            let target = pointer_target?;
+            let now = Instant::now();
+            let count = match &state.last_click {
+                Some(last_click)
+                    if last_click.button == button
+                        && now.duration_since(last_click.time) <= settings.multi_click_timeout
+                        && (position - last_click.position).length()
+                            <= settings.multi_click_distance =>
+                {
+                    last_click.count + 1
+                }
+                _ => 1,
+            };
+            state.last_click = Some(LastClick {
+                button,
+                position,
+                time: now,
+                count,
+            });
+            state.press = Some(GesturePress {
+                button,
+                origin: position,
+                target,
+                drag_position: None,
+            });
+
+            Some((
+                target,
+                Gesture::Click {
+                    button,
+                    position,
+                    count,
+                },
+            ))
+        }
+        PointerEvent::Move { position, .. } => {
+            let press = state.press.as_mut()?;
+            match press.drag_position {
+                Some(last_position) => {
+                    press.drag_position = Some(position);
+                    Some((
+                        press.target,
+                        Gesture::DragMove {
+                            button: press.button,
+                            position,
+                            delta: position - last_position,
+                        },
+                    ))
+                }
+                None if (position - press.origin).length() > settings.drag_threshold => {
+                    press.drag_position = Some(press.origin);
+                    // NOTE: A drag can't also be part of a multi-click.
+                    state.last_click = None;
+                    Some((
+                        press.target,
+                        Gesture::DragStart {
+                            button: press.button,
+                            origin: press.origin,
+                        },
+                    ))
+                }
+                None => None,
+            }
+        }
+        PointerEvent::Up { button, .. } => {
+            if state.press.as_ref()?.button != button {
+                return None;
+            }
+            let press = state.press.take()?;
+            press.drag_position?;
+
+            Some((
+                press.target,
+                Gesture::DragEnd {
+                    button,
+                    position: view.pointer_position.unwrap_or(press.origin),
+                },
+            ))
+        }
+        PointerEvent::Scroll { .. } | PointerEvent::Touch { .. } | PointerEvent::Zoom { .. } => {
+            None
+        }
+    }
+}
+
+fn get_pointer_target(view: &View, pointer_pos: Option<Xy<f32>>) -> Option<u64> {
+    if let Some(capture_target) = view.pointer_capture_target
+        && view.tree.find(capture_target).is_some()
+    {
+        return Some(capture_target);
+    }
+
+    pointer_pos.and_then(|pos| find_layered_pointer_target(view, pos))
+}
+
+/// Find the pointer target in the topmost layer that contains one, stopping at
+/// the first modal popup.
+fn find_layered_pointer_target(view: &View, position: Xy<f32>) -> Option<u64> {
+    for popup in view.popups.iter().rev() {
+        let node = view
+            .tree
+            .find(popup.id)
+            .expect("failed to find a popup's root node");
+        if let Some(target) = find_pointer_target(node, position) {
+            return Some(target.id());
+        }
+        if popup.modal {
+            return None;
+        }
+    }
+
+    find_pointer_target(
+        view.tree
+            .find(view.root_element_id)
+            .expect("failed to find the view's root node"),
+        position,
+    )
+    .map(|node| node.id())
+}
+
+/// Whether the element is in a layer below the topmost modal popup.
+fn is_below_modal_popup(view: &View, id: u64) -> bool {
+    let Some(modal_index) = view.popups.iter().rposition(|popup| popup.modal) else {
+        return false;
+    };
+    let Some(layer_root) = view.tree.branches().get_id_path(id, None).last().copied() else {
+        return false;
+    };
+
+    !view.popups[modal_index..]
+        .iter()
+        .any(|popup| popup.id == layer_root)
+}
+
+fn find_pointer_target<'view>(
+    node: tree::NodeRef<'view, ElementInfo>,
+    position: Xy<f32>,
+) -> Option<tree::NodeRef<'view, ElementInfo>> {
+    // NOTE: The bounds are axis-aligned, so rotated elements are hit-tested in
+    //       their own space instead, which is the space that they're painted in
+    //       (see `ElementState::paint_space`) without the view's scale.
+    let state = &node.element.state;
+    let local_position = state.global_transform.inverse() * position;
+    if !state.bounds.contains(position)
+        || !Aabb2D::from_size(state.layout_bounds.size()).contains(local_position)
+    {
+        return None;
+    }
+
+    for child_id in paint_order(&*node.element.element, &[], node.leaves.reborrow())
+        .iter()
+        .rev()
+    {
+        if let Some(child) = find_pointer_target(
+            node.leaves
+                .reborrow_up()
+                .get_into(*child_id)
+                .expect("passed invalid child ID to find_pointer_target"),
+            position,
+        ) {
+            return Some(child);
+        }
+    }
+
+    if node.element.element.accepts_pointer_events() {
+        // && ctx.size().to_rect().contains(local_pos) {
+        Some(node)
+    } else {
+        None
+    }
+}
+
+
+
+#[derive(Clone, Debug)]
+#[repr(C)]
+pub struct SizedVec<T: Sized, const SIZE: usize> {
+    inner: [Option<T>; SIZE],
+}
+
+impl<T: Sized + Clone + Debug, const SIZE: usize> Default for SizedVec<T, SIZE> {
+    fn default() -> Self {
+        Self::empty()
+    }
+}
+
+impl<T: Sized + Clone + Debug, const SIZE: usize> SizedVec<T, SIZE> {
+    pub fn empty() -> Self {
+        const {
+            assert!(SIZE > 0);
+        }
+
+        Self {
+            inner: (0..SIZE)
+                .map(|_| None)
+                .collect::<Vec<_>>()
+                .try_into()
+                .expect("length should equal SIZE"),
+        }
+    }
+
+    /// Push an element onto the end of the vector, returning it if the vector
+    /// is full.
+    #[must_use = "the element is returned if the vector is full"]
+    pub fn push(&mut self, element: T) -> Option<T> {
+        if let Some(null_index) = self.inner.iter().position(|item| item.is_none()) {
+            self.inner[null_index] = Some(element);
+            None
+        } else {
+            Some(element)
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.inner.iter_mut().for_each(|item| {
+            item.take();
+        });
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &T> {
+        self.inner.iter().flat_map(|item| item.as_ref())
+    }
+
+    pub fn len(&self) -> usize {
+        self.inner.iter().filter(|item| item.is_some()).count()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.inner.iter().all(|item| item.is_none())
+    }
+
+    #[inline]
+    pub const fn capacity(&self) -> usize {
+        SIZE
+    }
+
+    pub fn is_full(&self) -> bool {
+        self.inner.iter().all(|item| item.is_some())
+    }
+}
+
+
+
+#[derive(Default)]
+pub struct Render {
+    pub commands: Vec<RenderCommand>,
+    /// The area of the screen that changed since the last render, or `None` if
+    /// nothing did.
+    ///
+    /// Hosts can limit repainting to this area, though the full command list is
+    /// always provided.
+    pub damage: Option<Aabb2D>,
+}
+
+/// The number of commands that fit in a single [`RenderFrame`].
+pub const RENDER_FRAME_CAPACITY: usize = 512;
+
+/// A fixed-size page of a [`Render`], for passing render commands through
+/// shared memory.
+///
+/// Renders with more than [`RENDER_FRAME_CAPACITY`] commands are split across
+/// several frames, see [`Render::write_frame`].
+#[derive(Default)]
+#[repr(C)]
+pub struct RenderFrame {
+    pub commands: SizedVec<RenderCommand, RENDER_FRAME_CAPACITY>,
+    /// The index of this frame in the render it was taken from.
+    pub page: u32,
+    /// The number of frames needed to hold the entire render. Consumers should
+    /// wait until every page has been received before drawing.
+    pub page_count: u32,
+    /// See [`Render::damage`].
+    pub damage: Option<Aabb2D>,
+}
+
+impl RenderFrame {
+    #[inline]
+    pub fn is_last_page(&self) -> bool {
+        self.page + 1 >= self.page_count
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct RenderQuad {
+    pub bounds: Aabb2D,
+    pub color: Rgba,
+    pub border_width: f32,
+    pub border_color: Rgba,
+}
+
+#[derive(Clone, Debug)]
+pub struct RenderText {
+    pub content: Arc<str>,
+    pub bounds: Aabb2D,
+    pub color: Rgba,
+    pub font_size: f32,
+}
+
+impl Render {
+    pub fn clear(&mut self) {
+        self.commands.clear();
+        self.damage = None;
+    }
+
+    pub fn add_damage(&mut self, area: Aabb2D) {
+        self.damage = Some(union_damage(self.damage, area));
+    }
+
+    /// The number of [`RenderFrame`]s needed to hold every command. This is
+    /// always at least 1, so that empty renders are still delivered.
+    pub fn page_count(&self) -> usize {
+        self.commands.len().div_ceil(RENDER_FRAME_CAPACITY).max(1)
+    }
+
+    /// Copy a page of commands into the given frame, returning `false` if
+    /// `page` is out of range.
+    pub fn write_frame(&self, page: usize, frame: &mut RenderFrame) -> bool {
+        let page_count = self.page_count();
+        if page >= page_count {
+            return false;
+        }
+
+        frame.commands.clear();
+        for command in self
+            .commands
+            .iter()
+            .skip(page * RENDER_FRAME_CAPACITY)
+            .take(RENDER_FRAME_CAPACITY)
+        {
+            let overflow = frame.commands.push(command.clone());
+            debug_assert!(overflow.is_none());
+        }
+        frame.page = page as u32;
+        frame.page_count = page_count as u32;
+        frame.damage = self.damage;
+
+        true
+    }
+
+    /// Split the render into frames, see [`Render::write_frame`].
+    pub fn frames(&self) -> impl Iterator<Item = RenderFrame> + use<'_> {
+        (0..self.page_count()).map(|page| {
+            let mut frame = RenderFrame::default();
+            self.write_frame(page, &mut frame);
+            frame
+        })
+    }
+
+    /// Add cached commands that should be drawn with the given opacity and
+    /// transform (see [`RenderCommand::SetTransform`]), setting them first if
+    /// they differ from those of the previous commands.
+    fn extend_cached(
+        &mut self,
+        other: &CachedRender,
+        opacity: f32,
+        transform: Transform2D,
+        vars: &mut RenderPassVariables,
+    ) {
+        if other.commands.is_empty() {
+            return;
+        }
+        if opacity != vars.opacity {
+            self.commands.push(RenderCommand::SetOpacity(opacity));
+            vars.opacity = opacity;
+        }
+        if transform != vars.transform {
+            self.commands.push(RenderCommand::SetTransform(transform));
+            vars.transform = transform;
+        }
+        self.commands.extend_from_slice(&other.commands);
+    }
+}
+
+#[derive(Default)]
+struct CachedRender {
+    commands: Vec<RenderCommand>,
+    /// The area covered by these commands, used for damage tracking.
+    area: Option<Aabb2D>,
+    /// The [`Element::content_hash`] and bounds these commands were rendered
+    /// with.
+    key: Option<(u64, Aabb2D)>,
+}
+
+impl CachedRender {
+    fn clear(&mut self) {
+        self.commands.clear();
+        self.area = None;
+        self.key = None;
+    }
+
+    /// Compute the area covered by the commands, which includes the element's
+    /// bounds in case a redundant [`RenderCommand::SetBounds`] was skipped, and
+    /// every point of any paths. The commands are drawn with the given
+    /// transform, see [`ElementState::paint_space`].
+    fn update_area(&mut self, element_bounds: Aabb2D, transform: Transform2D) {
+        if self.commands.is_empty() {
+            self.area = None;
+            return;
+        }
+
+        self.area = Some(
+            self.commands
+                .iter()
+                .filter_map(|command| match command {
+                    RenderCommand::SetBounds(bounds) => Some(*bounds),
+                    RenderCommand::PathSegment(segment) => segment
+                        .points()
+                        .map(|point| Aabb2D::from_min_max(point, point))
+                        .reduce(|area, point| area.union(point)),
+                    _ => None,
+                })
+                .fold(element_bounds, |area, bounds| area.union(bounds)),
+        )
+        .map(|area| transform.transform_area(area));
+    }
+}
+
+fn union_damage(damage: Option<Aabb2D>, area: Aabb2D) -> Aabb2D {
+    damage.map_or(area, |damage| damage.union(area))
+}
+
+/// Hash a value for [`Element::content_hash`].
+pub fn hash_content(value: &impl Hash) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    value.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// Statistics about the render cache, see [`View::render_cache_stats`].
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct RenderCacheStats {
+    /// The number of elements with cached commands.
+    pub entries: usize,
+    /// The number of renders skipped because the element's content hash and
+    /// bounds were unchanged.
+    pub hits: u64,
+    /// The number of renders that generated new commands.
+    pub misses: u64,
+    /// The number of misses whose commands were identical to the cached ones,
+    /// and so didn't add any damage.
+    pub unchanged: u64,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+#[repr(C)]
+pub enum RenderCommand {
+    DrawChar(char),
+    DrawQuad,
+    SetBounds(Aabb2D),
+    SetForegroundColor(Rgba),
+    SetBackgroundColor(Rgba),
+    SetBorderColor(Rgba),
+    SetBorderWidth(f32),
+    SetFontSize(f32),
+    /// Multiply the alpha of everything drawn after this by the given opacity,
+    /// until the next `SetOpacity`. Renders start at an opacity of 1.
+    SetOpacity(f32),
+    /// Renders start with [`FontFamily::Proportional`].
+    SetFontFamily(FontFamily),
+    /// Renders start with [`FontStyle::Normal`].
+    SetFontStyle(FontStyle),
+    /// Renders start with [`FontWeight::NORMAL`].
+    SetFontWeight(FontWeight),
+    /// Renders start with [`TextDecoration::NONE`].
+    SetTextDecoration(TextDecoration),
+    /// Discard the current path, so that the following segments start a new
+    /// one.
+    BeginPath,
+    /// Add a segment to the current path, in the same space as
+    /// [`RenderCommand::SetBounds`].
+    PathSegment(PathSegment),
+    /// Fill the area enclosed by the current path with the background color.
+    FillPath,
+    /// Draw the outline of the current path with the border color and width.
+    StrokePath,
+    /// Transform everything drawn after this (bounds and paths included) from
+    /// the space it's given in to the render's, until the next `SetTransform`.
+    /// Renders start with the identity transform, and only rotated or skewed
+    /// elements are drawn with another, see
+    /// [`ComposePass::set_child_transform`].
+    SetTransform(Transform2D),
+}
+
+/// One command per line, in a format that's stable across versions, so that
+/// renders can be saved and compared (see [`Render`]'s implementation).
+impl fmt::Display for RenderCommand {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::DrawChar(ch) => write!(f, "char {}", ch.escape_default()),
+            Self::DrawQuad => write!(f, "quad"),
+            Self::SetBounds(bounds) => write!(
+                f,
+                "bounds {} {} {} {}",
+                bounds.min.x, bounds.min.y, bounds.max.x, bounds.max.y,
+            ),
+            Self::SetForegroundColor(color) => write!(f, "fg {}", HexColor(*color)),
+            Self::SetBackgroundColor(color) => write!(f, "bg {}", HexColor(*color)),
+            Self::SetBorderColor(color) => write!(f, "border {}", HexColor(*color)),
+            Self::SetBorderWidth(width) => write!(f, "border-width {width}"),
+            Self::SetFontSize(size) => write!(f, "font-size {size}"),
+            Self::SetOpacity(opacity) => write!(f, "opacity {opacity}"),
+            Self::SetFontFamily(family) => match family {
+                FontFamily::Proportional => write!(f, "font-family proportional"),
+                FontFamily::Monospace => write!(f, "font-family monospace"),
+                FontFamily::Named(name) => write!(f, "font-family named {}", name.as_str()),
+            },
+            Self::SetFontStyle(style) => write!(
+                f,
+                "font-style {}",
+                match style {
+                    FontStyle::Normal => "normal",
+                    FontStyle::Italic => "italic",
+                    FontStyle::Oblique => "oblique",
+                },
+            ),
+            Self::SetFontWeight(weight) => write!(f, "font-weight {}", weight.0),
+            Self::SetTextDecoration(decoration) => {
+                f.write_str("decoration")?;
+                if decoration.is_none() {
+                    f.write_str(" none")?;
+                }
+                if decoration.underline {
+                    f.write_str(" underline")?;
+                }
+                if decoration.strikethrough {
+                    f.write_str(" strikethrough")?;
+                }
+                Ok(())
+            }
+            Self::BeginPath => write!(f, "begin-path"),
+            Self::PathSegment(segment) => match segment {
+                PathSegment::MoveTo(to) => write!(f, "move-to {} {}", to.x, to.y),
+                PathSegment::LineTo(to) => write!(f, "line-to {} {}", to.x, to.y),
+                PathSegment::QuadTo(control, to) => {
+                    write!(f, "quad-to {} {} {} {}", control.x, control.y, to.x, to.y)
+                }
+                PathSegment::CubicTo(control_a, control_b, to) => write!(
+                    f,
+                    "cubic-to {} {} {} {} {} {}",
+                    control_a.x, control_a.y, control_b.x, control_b.y, to.x, to.y,
+                ),
+                PathSegment::Close => write!(f, "close-path"),
+            },
+            Self::FillPath => write!(f, "fill-path"),
+            Self::StrokePath => write!(f, "stroke-path"),
+            Self::SetTransform(transform) => {
+                let [a, b, c, d, e, g] = transform.coefficients();
+                write!(f, "transform {a} {b} {c} {d} {e} {g}")
+            }
+        }
+    }
+}
+
+/// Serializes the render as a `damage` line followed by each command on its own
+/// line.
+impl fmt::Display for Render {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.damage {
+            Some(area) => writeln!(
+                f,
+                "damage {} {} {} {}",
+                area.min.x, area.min.y, area.max.x, area.max.y,
+            )?,
+            None => writeln!(f, "damage none")?,
+        }
+        for command in &self.commands {
+            writeln!(f, "{command}")?;
+        }
+
+        Ok(())
+    }
+}
+
+struct HexColor(Rgba);
+
+impl fmt::Display for HexColor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let Rgba { r, g, b, a } = self.0;
+        write!(f, "#{r:02x}{g:02x}{b:02x}{a:02x}")
+    }
+}
+
+struct RenderPassVariables {
+    bounds: Aabb2D,
+    font_size: f32,
+    foreground_color: Rgba,
+    background_color: Rgba,
+    border_color: Rgba,
+    border_width: f32,
+    font_family: FontFamily,
+    font_style: FontStyle,
+    font_weight: FontWeight,
+    text_decoration: TextDecoration,
+    /// The opacity of the commands added to the final render so far, which
+    /// (unlike the other variables) is tracked as cached commands are added.
+    opacity: f32,
+    /// The transform of the commands added to the final render so far, which
+    /// is tracked like the opacity.
+    transform: Transform2D,
+}
+
+impl Default for RenderPassVariables {
+    fn default() -> Self {
+        Self {
+            bounds: Aabb2D::ZERO,
+            font_size: 16.0,
+            foreground_color: Rgba::WHITE,
+            background_color: Rgba::BLACK,
+            border_color: Rgba::NONE,
+            border_width: 0.0,
+            font_family: FontFamily::Proportional,
+            font_style: FontStyle::Normal,
+            font_weight: FontWeight::NORMAL,
+            text_decoration: TextDecoration::NONE,
+            opacity: 1.0,
+            transform: Transform2D::IDENTITY,
+        }
+    }
+}
+
+pub struct RenderPass<'view> {
+    state: &'view mut ElementState,
+    render: &'view mut CachedRender,
+    vars: &'view mut RenderPassVariables,
+    theme: &'view Theme,
+}
+
+impl<'view> RenderPass<'view> {
+    fn new(
+        state: &'view mut ElementState,
+        render: &'view mut CachedRender,
+        vars: &'view mut RenderPassVariables,
+        theme: &'view Theme,
+    ) -> Self {
+        Self {
+            state,
+            render,
+            vars,
+            theme,
+        }
+    }
+
+    /// The scale from logical units to pixels, see [`View::zoom`]. Bounds
+    /// passed to the render pass are in pixels, while font sizes and border
+    /// widths are scaled automatically.
+    #[inline]
+    pub fn zoom(&self) -> f32 {
+        self.state.global_transform.scale_factor()
+    }
+
+    #[inline]
+    pub fn theme(&self) -> &Theme {
+        self.theme
+    }
+
+    /// Resolve a color token (such as `"text.muted"`) through the view's
+    /// [`Theme`], see [`Theme::color`].
+    #[inline]
+    pub fn color(&self, token: &str) -> Rgba {
+        self.theme.color(token)
+    }
+
+    /// Resolve a metric (such as `"spacing.small"`) through the view's
+    /// [`Theme`], see [`Theme::metric`].
+    #[inline]
+    pub fn metric(&self, name: &str) -> f32 {
+        self.theme.metric(name)
+    }
+
+    pub fn fill_quad(
+        &mut self,
+        bounds: Aabb2D,
+        color: Rgba,
+        border_width: f32,
+        border_color: Rgba,
+    ) {
+        let border_width = border_width * self.zoom();
+        if bounds != self.vars.bounds {
+            self.render.commands.push(RenderCommand::SetBounds(bounds));
+            self.vars.bounds = bounds;
+        }
+        if color != self.vars.background_color {
+            self.render
+                .commands
+                .push(RenderCommand::SetBackgroundColor(color));
+            self.vars.background_color = color;
+        }
+        if border_width != self.vars.border_width {
+            self.render
+                .commands
+                .push(RenderCommand::SetBorderWidth(border_width));
+            self.vars.border_width = border_width;
+        }
+        if border_color != self.vars.border_color {
+            self.render
+                .commands
+                .push(RenderCommand::SetBorderColor(border_color));
+            self.vars.border_color = border_color;
+        }
+
+        self.render.commands.push(RenderCommand::DrawQuad);
+    }
+
+    pub fn fill_text(
+        &mut self,
+        content: impl AsRef<str>,
+        bounds: Aabb2D,
+        color: Rgba,
+        font_size: f32,
+    ) {
+        self.fill_styled_text(content, &[], TextStyle::default(), bounds, color, font_size);
+    }
+
+    /// Fill text where each of the given spans has its own style, and
+    /// everything else is drawn in the `base` style. Spans without a color
+    /// use the given color.
+    pub fn fill_styled_text(
+        &mut self,
+        content: impl AsRef<str>,
+        spans: &[TextSpan],
+        base: TextStyle,
+        bounds: Aabb2D,
+        color: Rgba,
+        font_size: f32,
+    ) {
+        let font_size = font_size * self.zoom();
+        if bounds != self.vars.bounds {
+            self.render.commands.push(RenderCommand::SetBounds(bounds));
+            self.vars.bounds = bounds;
+        }
+        if font_size != self.vars.font_size {
+            self.render
+                .commands
+                .push(RenderCommand::SetFontSize(font_size));
+            self.vars.font_size = font_size;
+        }
+
+        let content = content.as_ref();
+        let family = base.family.unwrap_or_default();
+        let runs = text::styled_runs(content, spans, base);
+        if !bidi::has_right_to_left(content) {
+            for (run, style) in runs {
+                self.set_text_style(style, color, family);
+                for ch in run.chars() {
+                    self.render.commands.push(RenderCommand::DrawChar(ch));
+                }
+            }
+            return;
+        }
+
+        // Right-to-left text is drawn in visual order, one line at a time, so styles
+        // are looked up by offset rather than by run.
+        let run_starts = runs
+            .iter()
+            .scan(0, |offset, (run, _)| {
+                let start = *offset;
+                *offset += run.len();
+                Some(start)
+            })
+            .collect::<Vec<_>>();
+        let style_at =
+            |offset: usize| runs[run_starts.partition_point(|start| *start <= offset) - 1].1;
+
+        let mut line_start = 0;
+        for line in content.split_inclusive('\n') {
+            let line_text = line.strip_suffix('\n').unwrap_or(line);
+            let paragraph = bidi::BidiParagraph::new(line_text, TextDirection::LeftToRight);
+            let shaped = bidi::shaped_char_indices(line_text);
+            for cluster in paragraph.visual_clusters() {
+                let first = shaped.partition_point(|(offset, _)| *offset < cluster.start);
+                for (offset, ch) in shaped[first..]
+                    .iter()
+                    .take_while(|(offset, _)| *offset < cluster.end)
+                {
+                    self.set_text_style(style_at(line_start + offset), color, family);
+                    self.render.commands.push(RenderCommand::DrawChar(*ch));
+                }
+            }
+            if line_text.len() < line.len() {
+                self.set_text_style(style_at(line_start + line_text.len()), color, family);
+                self.render.commands.push(RenderCommand::DrawChar('\n'));
+            }
+            line_start += line.len();
+        }
+    }
+
+    /// Fill the area enclosed by a path, whose points are in the same space as
+    /// [`RenderPass::bounds`]. Subpaths are closed automatically.
+    pub fn fill_path(&mut self, segments: &[PathSegment], color: Rgba) {
+        if color != self.vars.background_color {
+            self.render
+                .commands
+                .push(RenderCommand::SetBackgroundColor(color));
+            self.vars.background_color = color;
+        }
+        self.push_path(segments);
+        self.render.commands.push(RenderCommand::FillPath);
+    }
+
+    /// Draw the outline of a path, see [`RenderPass::fill_path`].
+    pub fn stroke_path(&mut self, segments: &[PathSegment], width: f32, color: Rgba) {
+        let width = width * self.zoom();
+        if width != self.vars.border_width {
+            self.render
+                .commands
+                .push(RenderCommand::SetBorderWidth(width));
+            self.vars.border_width = width;
+        }
+        if color != self.vars.border_color {
+            self.render
+                .commands
+                .push(RenderCommand::SetBorderColor(color));
+            self.vars.border_color = color;
+        }
+        self.push_path(segments);
+        self.render.commands.push(RenderCommand::StrokePath);
+    }
+
+    fn push_path(&mut self, segments: &[PathSegment]) {
+        self.render.commands.push(RenderCommand::BeginPath);
+        self.render
+            .commands
+            .extend(segments.iter().copied().map(RenderCommand::PathSegment));
+    }
+
+    fn set_text_style(&mut self, style: TextStyle, color: Rgba, family: FontFamily) {
+        let color = style.color.unwrap_or(color);
+        if color != self.vars.foreground_color {
+            self.render
+                .commands
+                .push(RenderCommand::SetForegroundColor(color));
+            self.vars.foreground_color = color;
+        }
+        let family = style.family.unwrap_or(family);
+        if family != self.vars.font_family {
+            self.render
+                .commands
+                .push(RenderCommand::SetFontFamily(family));
+            self.vars.font_family = family;
+        }
+        if style.font_style != self.vars.font_style {
+            self.render
+                .commands
+                .push(RenderCommand::SetFontStyle(style.font_style));
+            self.vars.font_style = style.font_style;
+        }
+        if style.weight != self.vars.font_weight {
+            self.render
+                .commands
+                .push(RenderCommand::SetFontWeight(style.weight));
+            self.vars.font_weight = style.weight;
+        }
+        if style.decoration != self.vars.text_decoration {
+            self.render
+                .commands
+                .push(RenderCommand::SetTextDecoration(style.decoration));
+            self.vars.text_decoration = style.decoration;
+        }
+    }
+}
+
+pub fn render_pass(view: &mut View, render: &mut Render) {
+    render.clear();
+    if let Some(damage) = view.pending_damage.take() {
+        render.add_damage(damage);
+    }
+    let mut vars = RenderPassVariables::default();
+
+    for layer_root_id in view.layer_root_ids().collect::<Vec<_>>() {
+        let root_node = view
+            .tree
+            .find_mut(layer_root_id)
+            .expect("failed to find a layer's root node");
+        let mut overlays = Vec::new();
+
+        render_element(
+            root_node,
+            &mut view.render_cache,
+            &mut view.render_cache_stats,
+            &view.theme,
+            render,
+            &mut vars,
+            &mut overlays,
+        );
+
+        // Overlays are drawn after the entire layer so that they appear above any
+        // siblings.
+        for (id, opacity, transform) in overlays {
+            if let Some((_, overlay_render)) = view.render_cache.get(&id) {
+                render.extend_cached(overlay_render, opacity, transform, &mut vars);
+            }
+        }
+    }
+
+    if view.debug_overlay {
+        render_debug_overlay(view, render, &mut vars);
+    }
+}
+
+fn render_element(
+    node: tree::NodeMut<'_, ElementInfo>,
+    render_cache: &mut HashMap<u64, (CachedRender, CachedRender)>,
+    stats: &mut RenderCacheStats,
+    theme: &Theme,
+    final_render: &mut Render,
+    vars: &mut RenderPassVariables,
+    overlays: &mut Vec<(u64, f32, Transform2D)>,
+) {
+    let children = node.leaves;
+    let element = &mut *node.element.element;
+    let state = &mut node.element.state;
+    let (paint_transform, paint_bounds) = state.paint_space();
 
-    pub fn advance(&mut self, ms: f32) -> bool {
-        let original_cmp = self.current.partial_cmp(&self.target).unwrap();
-        self.current += self.rate * ms;
-        let final_cmp = self.current.partial_cmp(&self.target).unwrap();
+    if state.wants_render || state.wants_overlay_render {
+        let (render, overlay_render) = render_cache.entry(state.id).or_default();
+        // NOTE: Elements render within `bounds`, so it's swapped for the bounds in
+        //       their paint space while they do, and the areas are mapped back to
+        //       the view by `update_area`.
+        let view_bounds = std::mem::replace(&mut state.bounds, paint_bounds);
+
+        // Both the previously rendered area and the new one need to be repainted.
+        let key = element.content_hash().map(|hash| (hash, paint_bounds));
+        if state.wants_render && key.is_some() && key == render.key {
+            stats.hits += 1;
+            // NOTE: Rotated elements are rendered in their own space, so they can
+            //       be rotated further without being rendered again, but the area
+            //       that they cover in the view still changes.
+            let old_area = render.area;
+            render.update_area(paint_bounds, paint_transform);
+            if render.area != old_area {
+                for area in [old_area, render.area].into_iter().flatten() {
+                    state.damage = Some(union_damage(state.damage, area));
+                }
+            }
+        } else if state.wants_render {
+            stats.misses += 1;
+            let old_commands = std::mem::take(&mut render.commands);
+            let old_area = render.area;
+            render.clear();
+            let mut pass = RenderPass::new(state, render, vars, theme);
+            element.render(&mut pass);
+            render.update_area(paint_bounds, paint_transform);
+            render.key = key;
 
-        if final_cmp.is_eq() || original_cmp != final_cmp {
-            self.current = self.target;
-            self.rate = 0.0;
-            true
-        } else {
-            false
+            if render.commands == old_commands && render.area == old_area {
+                stats.unchanged += 1;
+            } else {
+                for area in [old_area, render.area].into_iter().flatten() {
+                    state.damage = Some(union_damage(state.damage, area));
+                }
+            }
+        }
+        if state.wants_overlay_render {
+            if let Some(area) = overlay_render.area {
+                state.damage = Some(union_damage(state.damage, area));
+            }
+            overlay_render.clear();
+            let mut pass = RenderPass::new(state, overlay_render, vars, theme);
+            element.render_overlay(&mut pass);
+            overlay_render.update_area(paint_bounds, paint_transform);
+            if let Some(area) = overlay_render.area {
+                state.damage = Some(union_damage(state.damage, area));
+            }
         }
+        state.bounds = view_bounds;
+    }
+
+    if let Some(damage) = state.damage.take() {
+        final_render.add_damage(damage);
+    }
+
+    state.needs_render = false;
+    state.wants_render = false;
+    state.wants_overlay_render = false;
+
+    {
+        let Some((render, _)) = &mut render_cache.get(&state.id) else {
+            return;
+        };
+
+        final_render.extend_cached(render, state.global_opacity, paint_transform, vars);
+    }
+
+    let exiting = state.exiting_children.clone();
+    let parent_state = &mut *state;
+    for_each_child_element_in_paint_order(element, &exiting, children, |mut node| {
+        render_element(
+            node.reborrow_mut(),
+            render_cache,
+            stats,
+            theme,
+            final_render,
+            vars,
+            overlays,
+        );
+        parent_state.merge_with_child(&node.element.state);
+    });
+
+    if render_cache
+        .get(&state.id)
+        .is_some_and(|(_, overlay_render)| !overlay_render.commands.is_empty())
+    {
+        overlays.push((state.id, state.global_opacity, paint_transform));
     }
 }
 
 
 
-pub struct UpdatePass<'view> {
+pub struct AnimatePass<'view> {
     state: &'view mut ElementState,
     children: tree::LeavesMut<'view, ElementInfo>,
 }
 
-impl UpdatePass<'_> {
-    /// See [`Element::update_children`].
-    pub fn update_child(&mut self, child: &mut ChildElement) {
-        let Some(ElementBuilder { id, element }) = child.take_inner() else {
-            return;
-        };
-
-        let state = ElementState::new(id);
-        let info = ElementInfo { element, state };
+impl AnimatePass<'_> {
+    /// Whether this element is playing its exit transition, see
+    /// [`Element::on_exit`].
+    #[inline]
+    pub fn is_exiting(&self) -> bool {
+        self.state.exiting
+    }
 
-        self.children.insert(id, info);
+    /// End this element's exit transition, removing it from the tree during
+    /// the next update.
+    pub fn finish_exit(&mut self) {
+        if self.state.exiting {
+            self.state.exited = true;
+            self.state.children_changed = true;
+        }
     }
 }
 
-pub fn update_pass(view: &mut View) {
-    let node = view
-        .tree
-        .find_mut(view.root_element_id)
-        .expect("failed to find the view's root node");
-
-    update_element_tree(node);
+fn animation_pass(view: &mut View, time_delta: f64) {
+    for id in view.layer_root_ids().collect::<Vec<_>>() {
+        let node = view
+            .tree
+            .find_mut(id)
+            .expect("failed to find a layer's root node");
+        animate_element(node, time_delta);
+    }
 }
 
-fn update_element_tree(node: tree::NodeMut<'_, ElementInfo>) {
+fn animate_element(node: tree::NodeMut<'_, ElementInfo>, time_delta: f64) {
     let mut children = node.leaves;
     let element = &mut *node.element.element;
     let state = &mut node.element.state;
 
-    if !state.children_changed {
+    if !state.needs_animate {
         return;
     }
+    state.needs_animate = false;
 
-    state.children_changed = false;
-
-    element.update_children(&mut UpdatePass {
-        state,
-        children: children.reborrow_mut(),
-    });
-
-    if state.newly_added {
-        state.newly_added = false;
-        element.on_build(&mut UpdatePass {
-            state,
-            children: children.reborrow_mut(),
-        });
+    if state.wants_animate {
+        state.wants_animate = false;
+        element.animate(
+            &mut AnimatePass {
+                state,
+                children: children.reborrow_mut(),
+            },
+            time_delta,
+        );
     }
 
+    state.needs_render = true;
+
+    let exiting = state.exiting_children.clone();
     let parent_state = &mut *state;
-    for_each_child_element(element, children, |mut node| {
-        update_element_tree(node.reborrow_mut());
+    for_each_child_element(element, &exiting, children, |mut node| {
+        animate_element(node.reborrow_mut(), time_delta);
         parent_state.merge_with_child(&node.element.state);
     });
 }
 
-fn update_pointer_pass(view: &mut View) {
-    let next_hovered_element = view
-        .pointer_position
-        .and_then(|pos| {
-            find_pointer_target(
-                view.tree
-                    .find(view.root_element_id)
-                    .expect("failed to find the view's root node"),
-                pos,
-            )
-        })
-        .map(|node| node.id());
-    let next_hovered_path = next_hovered_element.map_or(Vec::new(), |node_id| {
-        view.tree.branches().get_id_path(node_id, None)
-    });
-    let prev_hovered_path = std::mem::take(&mut view.hovered_path);
-    let prev_hovered_element = prev_hovered_path.first().copied();
 
-    if prev_hovered_path != next_hovered_path {
-        let mut hovered_set = HashSet::new();
-        for node_id in &next_hovered_path {
-            hovered_set.insert(*node_id);
-        }
 
-        for node_id in prev_hovered_path.iter().copied() {
-            if view
-                .tree
-                .find_mut(node_id)
-                .map(|node| node.element.state.hovered != hovered_set.contains(&node_id))
-                .unwrap_or(false)
-            {
-                let hovered = hovered_set.contains(&node_id);
-                event_pass(view, Some(node_id), |element, pass| {
-                    if pass.state.hovered != hovered {
-                        element.on_child_hover(pass, hovered);
-                    }
-                    pass.state.hovered = hovered;
-                });
-            }
-        }
-        for node_id in next_hovered_path.iter().copied() {
-            if view
-                .tree
-                .find_mut(node_id)
-                .map(|node| node.element.state.hovered != hovered_set.contains(&node_id))
-                .unwrap_or(false)
-            {
-                let hovered = hovered_set.contains(&node_id);
-                event_pass(view, Some(node_id), |element, pass| {
-                    if pass.state.hovered != hovered {
-                        element.on_child_hover(pass, hovered);
-                    }
-                    pass.state.hovered = hovered;
-                });
-            }
+pub struct ComposePass<'view> {
+    state: &'view mut ElementState,
+    children: tree::LeavesMut<'view, ElementInfo>,
+}
+
+impl ComposePass<'_> {
+    pub fn set_child_scroll(&mut self, child: &mut ChildElement, translation: Xy<f32>) {
+        let translation = translation.round();
+
+        let child_state = &mut self
+            .children
+            .get_mut(child.id())
+            .expect("invalid child passed to ComposePass::set_child_scroll_translation")
+            .element
+            .state;
+        if translation != child_state.scroll_translation {
+            child_state.scroll_translation = translation;
+            child_state.transformed = true;
         }
     }
 
-    if prev_hovered_element != next_hovered_element {
-        single_event_pass(view, prev_hovered_element, |element, pass| {
-            pass.state.hovered = false;
-            element.on_hover(pass, false);
-        });
-        single_event_pass(view, next_hovered_element, |element, pass| {
-            pass.state.hovered = true;
-            element.on_hover(pass, true);
-        });
+    /// Set a transform (such as a rotation or scale) to apply to a child and
+    /// its descendants, around the child's top-left corner. Pointer events
+    /// are hit-tested through it, and children that end up rotated or skewed
+    /// are painted through it with [`RenderCommand::SetTransform`].
+    ///
+    /// Use [`Transform2D::translate`] to transform around another point, such
+    /// as the child's center.
+    pub fn set_child_transform(&mut self, child: &mut ChildElement, transform: Transform2D) {
+        let child_state = &mut self
+            .children
+            .get_mut(child.id())
+            .expect("invalid child passed to ComposePass::set_child_transform")
+            .element
+            .state;
+        if transform != child_state.local_transform {
+            child_state.local_transform = transform;
+            child_state.transformed = true;
+        }
     }
 
-    let next_cursor_icon =
-        if let Some(node_id) = view.pointer_capture_target.or(next_hovered_element) {
-            let node = view
-                .tree
-                .find_mut(node_id)
-                .expect("failed to find the view's root node");
+    /// Set the opacity of a child and its descendants, from 0 (invisible) to 1
+    /// (the default). This is multiplied with the opacity of this element.
+    pub fn set_child_opacity(&mut self, child: &mut ChildElement, opacity: f32) {
+        let opacity = opacity.clamp(0.0, 1.0);
 
-            node.element.element.cursor_icon()
-        } else {
-            CursorIcon::Default
-        };
+        let child_state = &mut self
+            .children
+            .get_mut(child.id())
+            .expect("invalid child passed to ComposePass::set_child_opacity")
+            .element
+            .state;
+        if opacity != child_state.opacity {
+            child_state.opacity = opacity;
+            child_state.transformed = true;
+        }
+    }
+}
 
-    view.cursor_icon = next_cursor_icon;
-    view.hovered_path = next_hovered_path;
+pub fn compose_pass(view: &mut View) {
+    for id in view.layer_root_ids().collect::<Vec<_>>() {
+        let node = view
+            .tree
+            .find_mut(id)
+            .expect("failed to find a layer's root node");
+        compose_element(node, Transform2D::scale(view.zoom), 1.0, false);
+    }
 }
 
-fn update_focus_pass(view: &mut View) {
-    let next_focused_element = view.next_focused_element;
-    let next_focused_path = next_focused_element.map_or(Vec::new(), |node_id| {
-        view.tree.branches().get_id_path(node_id, None)
-    });
-    let prev_focused_path = std::mem::take(&mut view.focused_path);
-    let prev_focused_element = prev_focused_path.first().copied();
+fn compose_element(
+    node: tree::NodeMut<'_, ElementInfo>,
+    parent_global_transform: Transform2D,
+    parent_global_opacity: f32,
+    parent_transformed: bool,
+) {
+    let mut children = node.leaves;
+    let element = &mut *node.element.element;
+    let state = &mut node.element.state;
 
-    if prev_focused_path != next_focused_path {
-        let mut focused_set = HashSet::new();
-        for node_id in &next_focused_path {
-            focused_set.insert(*node_id);
-        }
+    let transformed = parent_transformed || state.transformed;
 
-        for node_id in prev_focused_path.iter().copied() {
-            if view
-                .tree
-                .find_mut(node_id)
-                .map(|node| node.element.state.focused != focused_set.contains(&node_id))
-                .unwrap_or(false)
-            {
-                let focused = focused_set.contains(&node_id);
-                event_pass(view, Some(node_id), |element, pass| {
-                    if pass.state.focused != focused {
-                        element.on_child_focus(pass, focused);
-                    }
-                    pass.state.focused = focused;
-                });
-            }
-        }
-        for node_id in next_focused_path.iter().copied() {
-            if view
-                .tree
-                .find_mut(node_id)
-                .map(|node| node.element.state.focused != focused_set.contains(&node_id))
-                .unwrap_or(false)
-            {
-                let focused = focused_set.contains(&node_id);
-                event_pass(view, Some(node_id), |element, pass| {
-                    if pass.state.focused != focused {
-                        element.on_child_focus(pass, focused);
-                    }
-                    pass.state.focused = focused;
-                });
-            }
-        }
+    if !transformed && !state.needs_compose {
+        return;
+    }
+
+    let local_translation = state.scroll_translation + state.layout_bounds.position();
+    let global_transform =
+        parent_global_transform * state.local_transform.with_translation(local_translation);
+    if global_transform != state.global_transform
+        && !(global_transform.is_axis_aligned() && state.global_transform.is_axis_aligned())
+    {
+        // Rotated elements are painted through their transform, see
+        // `ElementState::paint_space`.
+        state.wants_render = true;
     }
+    state.global_transform = global_transform;
+    state.bounds = state
+        .global_transform
+        .transform_area(Aabb2D::from_size(state.layout_bounds.size()));
 
-    if prev_focused_element != next_focused_element {
-        single_event_pass(view, prev_focused_element, |element, pass| {
-            pass.state.focused = false;
-            element.on_focus(pass, false);
-        });
-        single_event_pass(view, next_focused_element, |element, pass| {
-            pass.state.focused = true;
-            element.on_focus(pass, true);
+    let global_opacity = parent_global_opacity * state.opacity;
+    if global_opacity != state.global_opacity {
+        state.global_opacity = global_opacity;
+        state.damage = Some(union_damage(state.damage, state.bounds));
+    }
+
+    if state.wants_compose {
+        element.compose(&mut ComposePass {
+            state,
+            children: children.reborrow_mut(),
         });
     }
 
-    view.focused_element = next_focused_element;
-    view.focused_path = next_focused_path;
+    state.needs_render = true;
+    state.needs_compose = false;
+    state.wants_compose = false;
+    state.transformed = false;
+
+    let exiting = state.exiting_children.clone();
+    let parent_state = &mut *state;
+    for_each_child_element(element, &exiting, children, |mut node| {
+        compose_element(
+            node.reborrow_mut(),
+            parent_state.global_transform,
+            parent_state.global_opacity,
+            transformed,
+        );
+        parent_state.merge_with_child(&node.element.state);
+    });
 }
 
 
 
-pub struct EventPass<'view> {
+pub struct LayoutPass<'view> {
+    fonts: &'view mut dyn Fonts,
+    zoom: f32,
+    theme: &'view Theme,
     state: &'view mut ElementState,
     children: tree::LeavesMut<'view, ElementInfo>,
-    handled: bool,
-    next_focus: &'view mut Option<u64>,
-    pointer_capture_target: &'view mut Option<u64>,
+    pub size: Xy<f32>,
 }
 
-impl EventPass<'_> {
-    pub fn set_handled(&mut self) {
-        self.handled = true;
+impl LayoutPass<'_> {
+    #[inline]
+    pub fn fonts(&self) -> &dyn Fonts {
+        self.fonts
     }
 
-    pub fn request_focus(&mut self) {
-        *self.next_focus = Some(self.state.id);
+    #[inline]
+    pub fn fonts_mut(&mut self) -> &mut dyn Fonts {
+        self.fonts
     }
 
-    pub fn capture_pointer(&mut self) {
-        *self.pointer_capture_target = Some(self.state.id);
+    pub fn do_layout(&mut self, child: &mut ChildElement, size: Xy<f32>) {
+        let mut node = self
+            .children
+            .get_mut(child.id)
+            .expect("invalid child passed to LayoutPass::do_layout");
+        layout_element(self.fonts, self.zoom, self.theme, node.reborrow_mut(), size);
+        self.state.merge_with_child(&node.element.state);
     }
-}
-
-#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
-#[repr(u32)] // TODO: Finish adding the extra pointer buttons (up to button 32).
-pub enum PointerButton {
-    // TODO: Nullable type?
-    Primary = 1,
-    Secondary = 1 << 1,
-    Auxiliary = 1 << 2,
-    Back = 1 << 3,
-    Forward = 1 << 4,
-}
 
-#[derive(Clone, Copy, Debug, PartialEq)]
-#[repr(C)]
-pub enum PointerEvent {
-    Down {
-        button: PointerButton,
-        position: Xy<f32>,
-    },
-    Up {
-        button: PointerButton,
-    },
-    Move {
-        position: Xy<f32>,
-    },
-    Scroll {
-        delta: ScrollDelta,
-    },
-}
+    pub fn place_child(&mut self, child: &mut ChildElement, position: Xy<f32>) {
+        move_element(
+            &mut self
+                .children
+                .get_mut(child.id)
+                .expect("invalid child passed to LayoutPass::place_child")
+                .element
+                .state,
+            position,
+        );
+    }
 
-#[derive(Clone, Copy, Debug, PartialEq)]
-pub enum ScrollDelta {
-    Pixels(Xy<f32>),
-    Lines(Xy<f32>),
-}
+    pub fn resolve_size(&mut self, child_id: u64, fallback_size: Xy<Length>) -> Xy<f32> {
+        let node = self
+            .children
+            .get_mut(child_id)
+            .expect("provided invalid child ID to LayoutPass::resolve_size");
 
-impl ScrollDelta {
-    pub fn to_pixels(self, line_size: Xy<f32>) -> Xy<f32> {
-        match self {
-            Self::Pixels(delta) => delta,
-            ScrollDelta::Lines(delta) => delta * line_size,
-        }
+        resolve_element_size(self.fonts, self.zoom, self.theme, node, fallback_size) // , self.size)
     }
 }
 
-#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
-#[repr(C)]
-pub enum Key {
-    Char(char),
-
-    Space,
-    Tab,
-    Enter,
-    Backspace,
-    Delete,
+/// Lay out the elements that need it.
+///
+/// Only the dirty parts of the tree are laid out again: an element is skipped
+/// while it's clean and its size hasn't changed, and an element whose own
+/// layout is still valid just lays out its dirty children again at their
+/// current sizes. When a change to a child could change its measurements, its
+/// parent is laid out again as well, which can in turn resize the child's
+/// siblings.
+pub fn layout_pass(view: &mut View) {
+    for id in view.layer_root_ids().collect::<Vec<_>>() {
+        let node = view
+            .tree
+            .find_mut(id)
+            .expect("failed to find a layer's root node");
+        invalidate_layout(&mut *view.fonts, view.zoom, &view.theme, node);
+    }
 
-    ArrowUp,
-    ArrowDown,
-    ArrowLeft,
-    ArrowRight,
+    // Everything is laid out in logical units, which are scaled up by the zoom
+    // during the compose pass.
+    let window_size = view.logical_window_size();
+    let node = view
+        .tree
+        .find_mut(view.root_element_id)
+        .expect("failed to find the view's root node");
+    layout_element(&mut *view.fonts, view.zoom, &view.theme, node, window_size);
 
-    PageUp,
-    PageDown,
-}
+    // Popups are sized to fit their content, but never larger than the window.
+    for popup in &view.popups {
+        let mut node = view
+            .tree
+            .find_mut(popup.id)
+            .expect("failed to find a popup's root node");
+        let size = resolve_element_size(
+            &mut *view.fonts,
+            view.zoom,
+            &view.theme,
+            node.reborrow_mut(),
+            Xy::new(
+                Length::FitContent(window_size.x),
+                Length::FitContent(window_size.y),
+            ),
+        );
+        let size = Xy::new(size.x.min(window_size.x), size.y.min(window_size.y));
+        let position = Xy::new(
+            popup.position.x.clamp(0.0, window_size.x - size.x),
+            popup.position.y.clamp(0.0, window_size.y - size.y),
+        );
 
-#[derive(Clone, Copy, Debug, PartialEq)]
-#[repr(C)]
-pub enum KeyboardEvent {
-    Down { key: Key },
-    Up { key: Key },
+        layout_element(
+            &mut *view.fonts,
+            view.zoom,
+            &view.theme,
+            node.reborrow_mut(),
+            size,
+        );
+        move_element(&mut node.element.state, position);
+    }
 }
 
-fn event_pass(
-    view: &mut View,
-    target: Option<u64>,
-    mut callback: impl FnMut(&mut dyn Element, &mut EventPass<'_>),
+fn layout_element(
+    fonts: &mut dyn Fonts,
+    zoom: f32,
+    theme: &Theme,
+    node: tree::NodeMut<'_, ElementInfo>,
+    size: Xy<f32>,
 ) {
-    let mut target_id = target;
-    let mut handled = false;
-    while let Some(node_id) = target_id {
-        let parent_id = {
-            let node = view
-                .tree
-                .find_mut(node_id)
-                .expect("invalid element ID for event target");
+    let element = &mut *node.element.element;
+    let state = &mut node.element.state;
+    let children = node.leaves;
 
-            if !handled {
-                let mut pass = EventPass {
-                    state: &mut node.element.state,
-                    children: node.leaves,
-                    handled: false,
-                    next_focus: &mut view.next_focused_element,
-                    pointer_capture_target: &mut view.pointer_capture_target,
-                };
-                callback(&mut *node.element.element, &mut pass);
+    let resized = size != state.layout_bounds.size();
+    if !resized && !state.needs_layout {
+        return;
+    }
 
-                handled = pass.handled;
-            }
+    if resized || state.wants_layout {
+        state.layout_bounds.set_size(size);
 
-            node.branch_id
+        let mut pass = LayoutPass {
+            fonts,
+            zoom,
+            theme,
+            state,
+            children,
+            size,
         };
+        element.layout(&mut pass);
 
-        if let Some(parent_id) = parent_id {
-            let mut parent_node = view.tree.find_mut(parent_id).unwrap();
-            let node = parent_node.leaves.get_mut(node_id).unwrap();
+        state.needs_render = true;
+        state.wants_render = true;
+        state.needs_compose = true;
+        state.wants_compose = true;
+    } else {
+        // NOTE: This element's layout is still valid, so its children keep their
+        //       sizes and positions. Only the dirty ones (including those that
+        //       are exiting) need to be laid out again.
+        let exiting = state.exiting_children.clone();
+        let parent_state = &mut *state;
+        for_each_child_element(element, &exiting, children, |mut node| {
+            if node.element.state.needs_layout {
+                let child_size = node.element.state.layout_bounds.size();
+                layout_element(fonts, zoom, theme, node.reborrow_mut(), child_size);
+                parent_state.merge_with_child(&node.element.state);
+            }
+        });
+    }
 
-            parent_node
-                .element
-                .state
-                .merge_with_child(&node.element.state);
+    state.needs_layout = false;
+    state.wants_layout = false;
+}
+
+/// Propagate layout requests up the tree, clearing the measure cache of every
+/// element that requested a layout. Returns `true` if the element's
+/// measurements may have changed, in which case its parent needs to be laid out
+/// again too.
+fn invalidate_layout(
+    fonts: &mut dyn Fonts,
+    zoom: f32,
+    theme: &Theme,
+    node: tree::NodeMut<'_, ElementInfo>,
+) -> bool {
+    let element = &mut *node.element.element;
+    let state = &mut node.element.state;
+    let mut children = node.leaves;
+
+    let exiting = state.exiting_children.clone();
+    let parent_state = &mut *state;
+    for_each_child_element(element, &exiting, children.reborrow_mut(), |mut node| {
+        if invalidate_layout(fonts, zoom, theme, node.reborrow_mut()) {
+            parent_state.wants_layout = true;
         }
+        parent_state.merge_with_child(&node.element.state);
+    });
 
-        target_id = parent_id;
+    if !state.wants_layout {
+        return false;
     }
-}
+    state.needs_layout = true;
 
-fn single_event_pass(
-    view: &mut View,
-    target: Option<u64>,
-    mut callback: impl FnMut(&mut dyn Element, &mut EventPass<'_>),
-) {
-    let Some(target) = target else {
-        return;
+    // Measure the element again with the requests it has seen, to find out whether
+    // its parent's layout is still valid.
+    let previous = std::mem::take(&mut state.measure_cache);
+    if previous.len() >= MEASURE_CACHE_CAPACITY {
+        // Some requests may have been evicted, so there's no way to tell.
+        return true;
+    }
+    let mut context = MeasureContext {
+        fonts,
+        zoom,
+        theme,
+        state,
+        children,
     };
+    previous
+        .into_iter()
+        .filter(|entry| entry.zoom == zoom)
+        .any(|entry| {
+            let length = resolve_axis_measurement(
+                &mut context,
+                element,
+                entry.axis,
+                entry.length_request.into(),
+                entry.cross_length,
+            );
 
-    let node = view
-        .tree
-        .find_mut(target)
-        .expect("invalid element ID passed to single_event_pass");
+            length != entry.length
+        })
+}
 
-    let mut pass = EventPass {
-        state: &mut node.element.state,
-        children: node.leaves,
-        handled: false,
-        next_focus: &mut view.next_focused_element,
-        pointer_capture_target: &mut view.pointer_capture_target,
-    };
-    callback(&mut *node.element.element, &mut pass);
+fn request_full_layout(state: &mut ElementState) {
+    state.needs_layout = true;
+    state.wants_layout = true;
+}
 
-    let mut current_id = Some(target);
-    while let Some(node_id) = current_id {
-        let parent_id = view
-            .tree
-            .find_mut(node_id)
-            .expect("invalid element ID for pointer target")
-            .branch_id;
-        if let Some(parent_id) = parent_id {
-            let mut parent_node = view.tree.find_mut(parent_id).unwrap();
-            let node = parent_node.leaves.get_mut(node_id).unwrap();
+fn move_element(state: &mut ElementState, position: Xy<f32>) {
+    let end_point = position + state.layout_bounds.size();
 
-            parent_node
-                .element
-                .state
-                .merge_with_child(&node.element.state);
-        }
+    let position = position.round();
+    let end_point = end_point.round();
+    let baseline_offset = (end_point.y - state.layout_baseline_offset).round();
 
-        current_id = parent_id;
+    if position != state.layout_bounds.min {
+        state.transformed = true;
     }
+
+    state.layout_bounds.min = position;
+    state.layout_bounds.max = end_point;
+    state.baseline_offset = baseline_offset;
 }
 
-fn keyboard_event_pass(view: &mut View, event: &KeyboardEvent) {
-    event_pass(view, view.focused_element, |element, pass| {
-        element.on_keyboard_event(pass, event)
-    });
+pub struct MeasureContext<'pass> {
+    fonts: &'pass mut dyn Fonts,
+    zoom: f32,
+    theme: &'pass Theme,
+    state: &'pass mut ElementState,
+    children: tree::LeavesMut<'pass, ElementInfo>,
 }
 
-fn pointer_event_pass(view: &mut View, event: &PointerEvent) {
-    // let mut pointer_entered = false;
-    if let PointerEvent::Move { position } = &event {
-        if view.pointer_position == Some(*position) {
-            return;
-        }
-        // pointer_entered = view.pointer_position.is_none();
-        view.pointer_position = Some(*position);
+impl MeasureContext<'_> {
+    #[inline]
+    pub fn fonts(&self) -> &dyn Fonts {
+        self.fonts
     }
-    let pointer_target = get_pointer_target(&view, view.pointer_position);
 
-    if matches!(event, PointerEvent::Down { .. })
-        && let Some(target_id) = pointer_target
-    {
-        // Clear the focus when the user clicks outside the focused element.
-        if let Some(focused_element) = view.focused_element {
-            // Focused element isn't an ancestor of the pointer target.
-            if !view
-                .tree
-                .branches()
-                .get_id_path(target_id, None)
-                .contains(&focused_element)
-            {
-                view.next_focused_element = None;
-            }
-        }
+    #[inline]
+    pub fn fonts_mut(&mut self) -> &mut dyn Fonts {
+        self.fonts
     }
+}
 
-    event_pass(view, pointer_target, |element, pass| {
-        element.on_pointer_event(pass, event)
-    });
+// TODO: Don't just default to the fallback here. Get something from the child
+//       state?
+fn resolve_element_size(
+    fonts: &mut dyn Fonts,
+    zoom: f32,
+    theme: &Theme,
+    node: tree::NodeMut<'_, ElementInfo>,
+    fallback_size: Xy<Length>,
+) -> Xy<f32> {
+    let element = &mut *node.element.element;
+    let state = &mut node.element.state;
+    let children = node.leaves;
 
-    if matches!(event, PointerEvent::Up { .. }) {
-        view.pointer_capture_target = None;
-    }
-}
+    // TODO: Consider supporting different inline/block axes?
 
-fn get_pointer_target(view: &View, pointer_pos: Option<Xy<f32>>) -> Option<u64> {
-    if let Some(capture_target) = view.pointer_capture_target
-        && view.tree.find(capture_target).is_some()
+    let inline_axis = Axis::Horizontal;
+    let block_axis = Axis::Vertical;
+
+    let inline_length = fallback_size.x;
+    let block_length = fallback_size.y;
+
+    let inline_measurement = inline_length.exact();
+    let block_measurement = block_length.exact();
+
+    // Early return.
+    if let Some(x) = inline_measurement
+        && let Some(y) = block_measurement
     {
-        return Some(capture_target);
+        return Xy::new(x, y);
     }
 
-    if let Some(pointer_pos) = pointer_pos {
-        return find_pointer_target(
-            view.tree
-                .find(view.root_element_id)
-                .expect("failed to find the view's root node"),
-            pointer_pos,
+    let mut context = MeasureContext {
+        fonts,
+        zoom,
+        theme,
+        state,
+        children,
+    };
+
+    let inline_measurement = inline_measurement.unwrap_or_else(|| {
+        resolve_axis_measurement(
+            &mut context,
+            element,
+            inline_axis,
+            inline_length,
+            block_measurement,
         )
-        .map(|node| node.id());
-    }
+    });
+
+    let block_measurement = block_measurement.unwrap_or_else(|| {
+        resolve_axis_measurement(
+            &mut context,
+            element,
+            block_axis,
+            block_length,
+            Some(inline_measurement),
+        )
+    });
 
-    None
+    Xy::new(inline_measurement, block_measurement)
 }
 
-fn find_pointer_target<'view>(
-    node: tree::NodeRef<'view, ElementInfo>,
-    position: Xy<f32>,
-) -> Option<tree::NodeRef<'view, ElementInfo>> {
-    if !node.element.state.bounds.contains(position) {
-        return None;
-    }
+fn resolve_axis_measurement(
+    context: &mut MeasureContext<'_>,
+    element: &mut dyn Element,
+    axis: Axis,
+    length: Length,
+    cross_length: Option<f32>,
+) -> f32 {
+    let length_request = match length {
+        Length::MaxContent => LengthRequest::MaxContent,
+        Length::MinContent => LengthRequest::MinContent,
+        Length::FitContent(max_size) => LengthRequest::FitContent(max_size),
+        Length::Exact(amount) => return amount,
+    };
 
-    for child_id in node.element.element.children_ids().iter().rev() {
-        if let Some(child) = find_pointer_target(
-            node.leaves
-                .reborrow_up()
-                .get_into(*child_id)
-                .expect("passed invalid child ID to find_pointer_target"),
-            position,
-        ) {
-            return Some(child);
-        }
+    let zoom = context.zoom;
+    if let Some(entry) = context.state.measure_cache.iter().find(|entry| {
+        entry.axis == axis
+            && entry.length_request == length_request
+            && entry.cross_length == cross_length
+            && entry.zoom == zoom
+    }) {
+        return entry.length;
     }
 
-    if node.element.element.accepts_pointer_events() {
-        // && ctx.size().to_rect().contains(local_pos) {
-        Some(node)
-    } else {
-        None
+    let length = element.measure(context, axis, length_request, cross_length);
+
+    let cache = &mut context.state.measure_cache;
+    if cache.len() >= MEASURE_CACHE_CAPACITY {
+        cache.remove(0);
     }
-}
+    cache.push(MeasureCacheEntry {
+        axis,
+        length_request,
+        cross_length,
+        zoom,
+        length,
+    });
 
+    length
+}
 
+/// The number of measurements cached per element, which covers the requests an
+/// element typically receives during a single layout.
+const MEASURE_CACHE_CAPACITY: usize = 8;
 
-#[derive(Clone, Debug)]
-#[repr(C)]
-pub struct SizedVec<T: Sized, const SIZE: usize> {
-    inner: [Option<T>; SIZE],
+#[derive(Clone, Copy, Debug)]
+struct MeasureCacheEntry {
+    axis: Axis,
+    length_request: LengthRequest,
+    cross_length: Option<f32>,
+    zoom: f32,
+    length: f32,
 }
 
-impl<T: Sized + Clone + Debug, const SIZE: usize> Default for SizedVec<T, SIZE> {
-    fn default() -> Self {
-        Self::empty()
-    }
+
+
+macro_rules! multi_impl {
+    ($ty:ty, { $($item:item)+ }) => {
+        impl $ty { $($item)+ }
+    };
+    ($ty:ty, $($others:ty),+, { $($item:item)+ }) => {
+        multi_impl!($ty, { $($item)+ });
+        multi_impl!($($others),+, { $($item)+ });
+    };
 }
 
-impl<T: Sized + Clone + Debug, const SIZE: usize> SizedVec<T, SIZE> {
-    pub fn empty() -> Self {
-        const {
-            assert!(SIZE > 0);
+multi_impl! {
+    LayoutPass<'_>,
+    MeasureContext<'_>,
+    {
+        /// The view's zoom factor, see [`View::zoom`]. Layout happens in logical units,
+        /// so this is only needed for things that should stay a fixed number of pixels.
+        #[inline]
+        pub fn zoom(&self) -> f32 {
+            self.zoom
         }
 
-        Self {
-            inner: (0..SIZE)
-                .map(|_| None)
-                .collect::<Vec<_>>()
-                .try_into()
-                .expect("length should equal SIZE"),
+        #[inline]
+        pub fn theme(&self) -> &Theme {
+            self.theme
         }
-    }
 
-    pub fn push(&mut self, element: T) -> Option<T> {
-        if let Some(null_index) = self.inner.iter().position(|item| item.is_none()) {
-            self.inner[null_index] = Some(element);
-            None
-        } else {
-            Some(element)
+        /// Resolve a metric (such as `"spacing.small"`) through the view's [`Theme`],
+        /// see [`Theme::metric`].
+        #[inline]
+        pub fn metric(&self, name: &str) -> f32 {
+            self.theme.metric(name)
         }
-    }
 
-    pub fn clear(&mut self) {
-        self.inner.iter_mut().for_each(|item| {
-            item.take();
-        });
+        // TODO: Don't just default to the fallback here. Get something from the child
+        //       state?
+        pub fn resolve_length(
+            &mut self,
+            child_id: u64,
+            axis: Axis,
+            fallback_length: Length,
+            cross_length: Option<f32>,
+        ) -> f32 {
+            let child = self
+                .children
+                .get_mut(child_id)
+                .expect("invalid child ID provided to MeasureContext::resolve_length");
+            let element = &mut *child.element.element;
+            let state = &mut child.element.state;
+            let children = child.leaves;
+
+            let mut context = MeasureContext {
+                fonts: self.fonts,
+                zoom: self.zoom,
+                theme: self.theme,
+                state,
+                children,
+            };
+
+            fallback_length.exact().unwrap_or_else(|| {
+                resolve_axis_measurement(&mut context, element, axis, fallback_length, cross_length)
+            })
+        }
     }
+}
 
-    pub fn iter(&self) -> impl Iterator<Item = &T> {
-        self.inner.iter().flat_map(|item| item.as_ref())
+multi_impl! {
+    EventPass<'_>,
+    UpdatePass<'_>,
+    {
+        /// Call [`Element::on_timer`] for this element once the given delay has
+        /// passed, unless it's cancelled with [`EventPass::cancel_timer`].
+        pub fn request_timer(&mut self, delay: Duration) -> TimerToken {
+            let token = TimerToken::next();
+            self.timers.push(Timer {
+                token,
+                element_id: self.state.id,
+                deadline: Instant::now() + delay,
+                interval: None,
+            });
+
+            token
+        }
+
+        /// Call [`Element::on_timer`] for this element every `interval` until
+        /// it's cancelled with [`EventPass::cancel_timer`].
+        pub fn request_interval(&mut self, interval: Duration) -> TimerToken {
+            let token = TimerToken::next();
+            self.timers.push(Timer {
+                token,
+                element_id: self.state.id,
+                deadline: Instant::now() + interval,
+                interval: Some(interval),
+            });
+
+            token
+        }
+
+        /// Stop the given timer, returning `false` if it had already finished.
+        pub fn cancel_timer(&mut self, token: TimerToken) -> bool {
+            let count = self.timers.len();
+            self.timers.retain(|timer| timer.token != token);
+
+            self.timers.len() != count
+        }
     }
 }
 
+// Types with a `state: &mut ElementState` field.
+multi_impl! {
+    AnimatePass<'_>,
+    ComposePass<'_>,
+    EventPass<'_>,
+    LayoutPass<'_>,
+    MeasureContext<'_>,
+    RenderPass<'_>,
+    UpdatePass<'_>,
+    {
+        #[inline]
+        pub fn id(&self) -> u64 {
+            self.state.id
+        }
+
+        #[inline]
+        pub fn bounds(&self) -> Aabb2D {
+            self.state.bounds
+        }
+
+        pub fn local_position(&self, point: Xy<f32>) -> Xy<f32> {
+            self.state.global_transform.inverse() * point
+        }
+
+        pub fn baseline_offset(&self) -> f32 {
+            self.state.layout_bounds.max.y - self.state.baseline_offset
+        }
 
+        pub fn set_baseline_offset(&mut self, offset: f32) {
+            self.state.layout_baseline_offset = offset;
+        }
 
-#[derive(Default)]
-#[repr(C)]
-pub struct Render {
-    pub commands: SizedVec<RenderCommand, 512>,
-}
+        /// Set the distance from the bottom of this element to its first baseline, for
+        /// elements with more than one (such as wrapped text).
+        pub fn set_first_baseline_offset(&mut self, offset: f32) {
+            self.state.layout_first_baseline_offset = Some(offset);
+        }
 
-#[derive(Clone, Debug)]
-pub struct RenderQuad {
-    pub bounds: Aabb2D,
-    pub color: Rgba,
-    pub border_width: f32,
-    pub border_color: Rgba,
-}
+        pub fn clear_baseline_offset(&mut self) {
+            self.state.layout_baseline_offset = 0.0;
+            self.state.layout_first_baseline_offset = None;
+        }
 
-#[derive(Clone, Debug)]
-pub struct RenderText {
-    pub content: Arc<str>,
-    pub bounds: Aabb2D,
-    pub color: Rgba,
-    pub font_size: f32,
-}
+        pub fn request_render(&mut self) {
+            self.state.wants_render = true;
+        }
 
-impl Render {
-    pub fn clear(&mut self) {
-        self.commands.clear();
-    }
+        /// Mark an area as needing to be repainted, in addition to anything this element
+        /// renders.
+        pub fn add_damage(&mut self, area: Aabb2D) {
+            self.state.damage = Some(union_damage(self.state.damage, area));
+            self.state.needs_render = true;
+        }
 
-    fn extend(&mut self, other: &CachedRender) {
-        for command in other.commands.iter().cloned() {
-            self.commands.push(command);
+        /// See [`ElementState::z_index`].
+        pub fn set_z_index(&mut self, z_index: i32) {
+            if self.state.z_index != z_index {
+                self.state.z_index = z_index;
+                self.add_damage(self.state.bounds);
+            }
         }
-    }
-}
 
-#[derive(Default)]
-struct CachedRender {
-    commands: Vec<RenderCommand>,
-}
+        pub fn request_overlay_render(&mut self) {
+            self.state.wants_overlay_render = true;
+        }
 
-impl CachedRender {
-    fn clear(&mut self) {
-        self.commands.clear();
-    }
-}
+        pub fn request_layout(&mut self) {
+            self.state.wants_layout = true;
+            self.state.needs_layout = true;
+        }
 
-#[derive(Clone, Debug)]
-#[repr(C)]
-pub enum RenderCommand {
-    DrawChar(char),
-    DrawQuad,
-    SetBounds(Aabb2D),
-    SetForegroundColor(Rgba),
-    SetBackgroundColor(Rgba),
-    SetBorderColor(Rgba),
-    SetBorderWidth(f32),
-    SetFontSize(f32),
-}
+        /// Request that [`Element::update_children`] be called, such as after adding or
+        /// removing children.
+        pub fn request_update(&mut self) {
+            self.state.children_changed = true;
+        }
 
-struct RenderPassVariables {
-    bounds: Aabb2D,
-    font_size: f32,
-    foreground_color: Rgba,
-    background_color: Rgba,
-    border_color: Rgba,
-    border_width: f32,
-}
+        pub fn request_compose(&mut self) {
+            self.state.wants_compose = true;
+            self.state.needs_compose = true;
+        }
 
-impl Default for RenderPassVariables {
-    fn default() -> Self {
-        Self {
-            bounds: Aabb2D::ZERO,
-            font_size: 16.0,
-            foreground_color: Rgba::WHITE,
-            background_color: Rgba::BLACK,
-            border_color: Rgba::NONE,
-            border_width: 0.0,
+        pub fn request_animate(&mut self) {
+            self.state.wants_animate = true;
+            self.state.needs_animate = true;
         }
-    }
-}
 
-pub struct RenderPass<'view> {
-    state: &'view mut ElementState,
-    render: &'view mut CachedRender,
-    vars: &'view mut RenderPassVariables,
-}
+        /// Attach a value to this element, replacing (and returning) any value of the
+        /// same type. Values stay attached until they're removed, or the element is.
+        ///
+        /// This lets extensions and applications keep their own state on an element
+        /// without wrapping it. Other elements can read it through the element's state,
+        /// such as with [`EventPass::child`].
+        pub fn insert_data<T: Any>(&mut self, value: T) -> Option<T> {
+            self.state.data.insert(value)
+        }
 
-impl<'view> RenderPass<'view> {
-    fn new(
-        state: &'view mut ElementState,
-        render: &'view mut CachedRender,
-        vars: &'view mut RenderPassVariables,
-    ) -> Self {
-        Self {
-            state,
-            render,
-            vars,
+        pub fn get_data<T: Any>(&self) -> Option<&T> {
+            self.state.data.get()
         }
-    }
 
-    pub fn fill_quad(
-        &mut self,
-        bounds: Aabb2D,
-        color: Rgba,
-        border_width: f32,
-        border_color: Rgba,
-    ) {
-        if bounds != self.vars.bounds {
-            self.render.commands.push(RenderCommand::SetBounds(bounds));
-            self.vars.bounds = bounds;
+        pub fn get_data_mut<T: Any>(&mut self) -> Option<&mut T> {
+            self.state.data.get_mut()
         }
-        if color != self.vars.background_color {
-            self.render
-                .commands
-                .push(RenderCommand::SetBackgroundColor(color));
-            self.vars.background_color = color;
+
+        pub fn remove_data<T: Any>(&mut self) -> Option<T> {
+            self.state.data.remove()
         }
-        if border_width != self.vars.border_width {
-            self.render
-                .commands
-                .push(RenderCommand::SetBorderWidth(border_width));
-            self.vars.border_width = border_width;
+    }
+}
+
+// Types with a `children: tree::LeavesMut<'_, ElementInfo>` field.
+multi_impl! {
+    AnimatePass<'_>,
+    ComposePass<'_>,
+    EventPass<'_>,
+    LayoutPass<'_>,
+    MeasureContext<'_>,
+    UpdatePass<'_>,
+    {
+        pub fn child(&self, id: u64) -> Option<tree::NodeRef<'_, ElementInfo>> {
+            self.children.get(id)
         }
-        if border_color != self.vars.border_color {
-            self.render
-                .commands
-                .push(RenderCommand::SetBorderColor(border_color));
-            self.vars.border_color = border_color;
+
+        pub fn expect_child(&self, id: u64) -> tree::NodeRef<'_, ElementInfo> {
+            self.children.get(id).expect("invalid ID passed to `expect_child`")
         }
 
-        self.render.commands.push(RenderCommand::DrawQuad);
-    }
+        pub fn typed_child_mut<T: Element>(
+            &mut self,
+            child: &mut TypedChildElement<T>,
+        ) -> &mut T {
+            let node_mut = self
+                .children
+                .get_mut(child.id())
+                .expect("get_mut: child element not found");
+            // NOTE: The child's content may be changed, which can change its size.
+            node_mut.element.state.wants_layout = true;
+            node_mut.element.state.needs_layout = true;
+            self.state.needs_layout = true;
 
-    pub fn fill_text(
-        &mut self,
-        content: impl AsRef<str>,
-        bounds: Aabb2D,
-        color: Rgba,
-        font_size: f32,
-    ) {
-        if bounds != self.vars.bounds {
-            self.render.commands.push(RenderCommand::SetBounds(bounds));
-            self.vars.bounds = bounds;
+            (&mut *node_mut.element.element as &mut dyn Any).downcast_mut().unwrap()
         }
-        if color != self.vars.foreground_color {
-            self.render
-                .commands
-                .push(RenderCommand::SetForegroundColor(color));
-            self.vars.foreground_color = color;
+
+        /// See [`EventPass::request_update`].
+        pub fn request_child_update(&mut self, id: u64) {
+            self.children
+                .get_mut(id)
+                .expect("invalid child ID passed to request_child_update")
+                .element
+                .state
+                .children_changed = true;
+            self.state.children_changed = true;
         }
-        if font_size != self.vars.font_size {
-            self.render
-                .commands
-                .push(RenderCommand::SetFontSize(font_size));
-            self.vars.font_size = font_size;
+
+        pub fn request_child_render(&mut self, id: u64) {
+            self.children
+                .get_mut(id)
+                .expect("invalid child ID passed to request_child_render")
+                .element
+                .state
+                .wants_render = true;
         }
 
-        for ch in content.as_ref().chars() {
-            self.render.commands.push(RenderCommand::DrawChar(ch));
+        /// Lay out a child and all of its descendants again, such as when an element
+        /// starts listing a child in [`Element::children_ids`] that it had hidden
+        /// while it wasn't being laid out.
+        pub fn request_child_layout(&mut self, id: u64) {
+            let node = self
+                .children
+                .get_mut(id)
+                .expect("invalid child ID passed to request_child_layout");
+            for_each_element_state_mut(node, &mut request_full_layout);
+            self.state.needs_layout = true;
         }
     }
 }
 
-pub fn render_pass(view: &mut View, render: &mut Render) {
-    render.clear();
-    let root_node = view
-        .tree
-        .find_mut(view.root_element_id)
-        .expect("failed to find the view's root node");
-    let mut vars = RenderPassVariables::default();
 
-    render_element(root_node, &mut view.render_cache, render, &mut vars);
-}
 
-fn render_element(
-    node: tree::NodeMut<'_, ElementInfo>,
-    render_cache: &mut HashMap<u64, (CachedRender, CachedRender)>,
-    final_render: &mut Render,
-    vars: &mut RenderPassVariables,
+/// Call the callback with each of the element's children, followed by any
+/// children in `exiting` (see [`ElementState::exiting_children`]).
+fn for_each_child_element(
+    element: &mut dyn Element,
+    exiting: &[u64],
+    mut children: tree::LeavesMut<'_, ElementInfo>,
+    mut callback: impl FnMut(tree::NodeMut<'_, ElementInfo>),
 ) {
-    let children = node.leaves;
-    let element = &mut *node.element.element;
-    let state = &mut node.element.state;
-
-    if state.wants_render || state.wants_overlay_render {
-        let (render, overlay_render) = render_cache.entry(state.id).or_default();
+    for child_id in element
+        .children_ids()
+        .into_iter()
+        .chain(exiting.iter().copied())
+    {
+        callback(
+            children
+                .get_mut(child_id)
+                .expect("Element::children_ids produced an invalid child ID"),
+        );
+    }
+}
 
-        if state.wants_render {
-            render.clear();
-            let mut pass = RenderPass::new(state, render, vars);
-            element.render(&mut pass);
-        }
-        if state.wants_overlay_render {
-            overlay_render.clear();
-            let mut pass = RenderPass::new(state, overlay_render, vars);
-            element.render_overlay(&mut pass);
-        }
+/// Like [`for_each_child_element`], but ordered by [`ElementState::z_index`].
+fn for_each_child_element_in_paint_order(
+    element: &mut dyn Element,
+    exiting: &[u64],
+    mut children: tree::LeavesMut<'_, ElementInfo>,
+    mut callback: impl FnMut(tree::NodeMut<'_, ElementInfo>),
+) {
+    for child_id in paint_order(element, exiting, children.reborrow()) {
+        callback(
+            children
+                .get_mut(child_id)
+                .expect("Element::children_ids produced an invalid child ID"),
+        );
     }
+}
 
-    state.needs_render = false;
-    state.wants_render = false;
-    state.wants_overlay_render = false;
+/// Get the element's children IDs (and those in `exiting`), sorted from bottom
+/// to top.
+fn paint_order(
+    element: &dyn Element,
+    exiting: &[u64],
+    children: tree::LeavesRef<'_, ElementInfo>,
+) -> Vec<u64> {
+    let mut child_ids = element.children_ids();
+    child_ids.extend_from_slice(exiting);
+    // NOTE: This is a stable sort, so tree order is preserved for equal z-indices.
+    child_ids.sort_by_key(|child_id| {
+        children
+            .get(*child_id)
+            .map_or(0, |child| child.element.state.z_index)
+    });
 
-    {
-        let Some((render, _)) = &mut render_cache.get(&state.id) else {
-            return;
-        };
+    child_ids
+}
 
-        final_render.extend(render);
+/// Call the callback with the state of the node's element and every element
+/// below it.
+fn for_each_element_state_mut(
+    node: tree::NodeMut<'_, ElementInfo>,
+    callback: &mut dyn FnMut(&mut ElementState),
+) {
+    callback(&mut node.element.state);
+
+    let leaf_ids = node.leaf_ids().into_iter().collect::<Vec<_>>();
+    let mut leaves = node.leaves;
+    for leaf_id in leaf_ids {
+        for_each_element_state_mut(
+            leaves.get_mut(leaf_id).expect("leaf IDs should be valid"),
+            callback,
+        );
     }
+}
 
-    let parent_state = &mut *state;
-    for_each_child_element(element, children, |mut node| {
-        render_element(node.reborrow_mut(), render_cache, final_render, vars);
-        parent_state.merge_with_child(&node.element.state);
-    });
 
-    {
-        let Some((_, overlay_render)) = &mut render_cache.get(&state.id) else {
-            return;
-        };
 
-        final_render.extend(overlay_render);
-    }
+/// A handle to a popup opened with [`EventPass::open_popup`] or
+/// [`View::open_popup`].
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct PopupId(pub(crate) u64);
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct PopupOptions {
+    /// The top-left corner of the popup, which is moved as needed to keep it
+    /// within the window.
+    pub position: Xy<f32>,
+    /// Whether the popup blocks events from reaching everything below it.
+    pub modal: bool,
 }
 
+/// Identifies a timer started with [`EventPass::request_timer`] or
+/// [`EventPass::request_interval`].
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct TimerToken(u64);
 
-
-pub struct AnimatePass<'view> {
-    state: &'view mut ElementState,
-    children: tree::LeavesMut<'view, ElementInfo>,
+impl TimerToken {
+    fn next() -> Self {
+        static NEXT_TIMER_TOKEN: AtomicU64 = AtomicU64::new(1);
+        Self(NEXT_TIMER_TOKEN.fetch_add(1, Ordering::Relaxed))
+    }
 }
 
-fn animation_pass(view: &mut View, time_delta: f64) {
-    let node = view
-        .tree
-        .find_mut(view.root_element_id)
-        .expect("failed to find the view's root node");
-    animate_element(node, time_delta);
+struct Timer {
+    token: TimerToken,
+    element_id: u64,
+    deadline: Instant,
+    /// The time between repeats, for timers started with
+    /// [`EventPass::request_interval`].
+    interval: Option<Duration>,
 }
 
-fn animate_element(node: tree::NodeMut<'_, ElementInfo>, time_delta: f64) {
-    let mut children = node.leaves;
-    let element = &mut *node.element.element;
-    let state = &mut node.element.state;
+struct Popup {
+    id: u64,
+    position: Xy<f32>,
+    modal: bool,
+}
 
-    if !state.needs_animate {
-        return;
-    }
-    state.needs_animate = false;
+enum PopupRequest {
+    Open {
+        builder: ElementBuilder,
+        options: PopupOptions,
+    },
+    Close(PopupId),
+}
 
-    if state.wants_animate {
-        state.wants_animate = false;
-        element.animate(
-            &mut AnimatePass {
-                state,
-                children: children.reborrow_mut(),
-            },
-            time_delta,
+fn collect_element_ids(node: tree::NodeRef<'_, ElementInfo>, ids: &mut Vec<u64>) {
+    ids.push(node.id());
+    for leaf_id in node.leaf_ids() {
+        collect_element_ids(
+            node.leaves
+                .reborrow_up()
+                .get_into(leaf_id)
+                .expect("leaf IDs should be valid"),
+            ids,
         );
     }
+}
 
-    state.needs_render = true;
 
-    let parent_state = &mut *state;
-    for_each_child_element(element, children, |mut node| {
-        animate_element(node.reborrow_mut(), time_delta);
-        parent_state.merge_with_child(&node.element.state);
-    });
-}
 
+const DEBUG_BOUNDS_COLOR: Rgba = Rgba::new(0xff, 0x40, 0x40, 0xa0);
+const DEBUG_BASELINE_COLOR: Rgba = Rgba::new(0x40, 0xa0, 0xff, 0xc0);
+const DEBUG_HOVERED_COLOR: Rgba = Rgba::new(0xff, 0xd0, 0x40, 0x40);
+const DEBUG_FOCUSED_COLOR: Rgba = Rgba::new(0x40, 0xff, 0x80, 0x40);
+const DEBUG_LABEL_FONT_SIZE: f32 = 10.0;
 
+/// Draw the debug overlay above everything, see [`View::set_debug_overlay`].
+fn render_debug_overlay(view: &View, render: &mut Render, vars: &mut RenderPassVariables) {
+    if vars.opacity != 1.0 {
+        render.commands.push(RenderCommand::SetOpacity(1.0));
+        vars.opacity = 1.0;
+    }
+    if vars.transform != Transform2D::IDENTITY {
+        render
+            .commands
+            .push(RenderCommand::SetTransform(Transform2D::IDENTITY));
+        vars.transform = Transform2D::IDENTITY;
+    }
 
-pub struct ComposePass<'view> {
-    state: &'view mut ElementState,
-    children: tree::LeavesMut<'view, ElementInfo>,
+    let hovered_id = view.hovered_path.first().copied();
+    for id in view.layer_root_ids() {
+        let node = view
+            .tree
+            .find(id)
+            .expect("failed to find a layer's root node");
+        render_debug_element(node, hovered_id, view.focused_element, render);
+    }
+
+    // NOTE: Anything could have moved under the overlay, so it's redrawn entirely.
+    render.add_damage(Aabb2D::from_size(view.window_size));
 }
 
-impl ComposePass<'_> {
-    pub fn set_child_scroll(&mut self, child: &mut ChildElement, translation: Xy<f32>) {
-        let translation = translation.round();
+fn render_debug_element(
+    node: tree::NodeRef<'_, ElementInfo>,
+    hovered_id: Option<u64>,
+    focused_id: Option<u64>,
+    render: &mut Render,
+) {
+    let state = &node.element.state;
+    let fill = if focused_id == Some(state.id) {
+        DEBUG_FOCUSED_COLOR
+    } else if hovered_id == Some(state.id) {
+        DEBUG_HOVERED_COLOR
+    } else {
+        Rgba::NONE
+    };
+    render.commands.extend([
+        RenderCommand::SetBounds(state.bounds),
+        RenderCommand::SetBackgroundColor(fill),
+        RenderCommand::SetBorderWidth(1.0),
+        RenderCommand::SetBorderColor(DEBUG_BOUNDS_COLOR),
+        RenderCommand::DrawQuad,
+    ]);
+
+    if state.layout_baseline_offset != 0.0 {
+        let size = state.layout_bounds.size();
+        let baseline_y = size.y - state.layout_baseline_offset;
+        let start = state.global_transform * Xy::new(0.0, baseline_y);
+        let end = state.global_transform * Xy::new(size.x, baseline_y);
+        render.commands.extend([
+            RenderCommand::SetBounds(Aabb2D::from_min_max(start, end + Xy::new(0.0, 1.0))),
+            RenderCommand::SetBackgroundColor(DEBUG_BASELINE_COLOR),
+            RenderCommand::SetBorderWidth(0.0),
+            RenderCommand::DrawQuad,
+        ]);
+    }
+
+    let size = state.layout_bounds.size();
+    let label = format!("#{} {}x{}", state.id, size.x, size.y);
+    render.commands.extend([
+        RenderCommand::SetBounds(Aabb2D::from_size_position(
+            Xy::new(state.bounds.size().x, DEBUG_LABEL_FONT_SIZE),
+            state.bounds.position(),
+        )),
+        RenderCommand::SetForegroundColor(DEBUG_BOUNDS_COLOR),
+        RenderCommand::SetFontSize(DEBUG_LABEL_FONT_SIZE),
+    ]);
+    render
+        .commands
+        .extend(label.chars().map(RenderCommand::DrawChar));
+
+    for child_id in node.element.element.children_ids() {
+        render_debug_element(
+            node.leaves
+                .reborrow_up()
+                .get_into(child_id)
+                .expect("Element::children_ids produced an invalid child ID"),
+            hovered_id,
+            focused_id,
+            render,
+        );
+    }
+}
 
-        let child_state = &mut self
-            .children
-            .get_mut(child.id())
-            .expect("invalid child passed to ComposePass::set_child_scroll_translation")
-            .element
-            .state;
-        if translation != child_state.scroll_translation {
-            child_state.scroll_translation = translation;
-            child_state.transformed = true;
-        }
+fn dump_element(node: tree::NodeRef<'_, ElementInfo>, depth: usize, output: &mut String) {
+    let state = &node.element.state;
+    let position = state.layout_bounds.position();
+    let size = state.layout_bounds.size();
+    let _ = write!(
+        output,
+        "{:indent$}{} #{} at ({}, {}) size {}x{} baseline {}",
+        "",
+        short_type_name(node.element.element.debug_name()),
+        state.id,
+        position.x,
+        position.y,
+        size.x,
+        size.y,
+        state.layout_baseline_offset,
+        indent = depth * 2,
+    );
+    if state.hovered {
+        output.push_str(" hovered");
+    }
+    if state.focused {
+        output.push_str(" focused");
+    }
+    output.push('\n');
+
+    for child_id in node.element.element.children_ids() {
+        dump_element(
+            node.leaves
+                .reborrow_up()
+                .get_into(child_id)
+                .expect("Element::children_ids produced an invalid child ID"),
+            depth + 1,
+            output,
+        );
     }
 }
 
-pub fn compose_pass(view: &mut View) {
-    let node = view
-        .tree
-        .find_mut(view.root_element_id)
-        .expect("failed to find the view's root node");
-    compose_element(node, Transform2D::IDENTITY, false);
+/// Strip the module paths from a type name, such as
+/// `abi::view::Tooltip<abi::view::Label>` to `Tooltip<Label>`.
+fn short_type_name(name: &str) -> String {
+    let mut short = String::with_capacity(name.len());
+    let mut path = String::new();
+    for ch in name.chars() {
+        if ch.is_alphanumeric() || ch == '_' || ch == ':' {
+            path.push(ch);
+        } else {
+            short.push_str(path.rsplit("::").next().unwrap_or_default());
+            path.clear();
+            short.push(ch);
+        }
+    }
+    short.push_str(path.rsplit("::").next().unwrap_or_default());
+
+    short
 }
 
-fn compose_element(
-    node: tree::NodeMut<'_, ElementInfo>,
-    parent_global_transform: Transform2D,
-    parent_transformed: bool,
-) {
-    let mut children = node.leaves;
-    let element = &mut *node.element.element;
-    let state = &mut node.element.state;
 
-    let transformed = parent_transformed || state.transformed;
 
-    if !transformed && !state.needs_compose {
-        return;
-    }
+/// The maximum number of frames kept by a [`Recording`] before the oldest are
+/// dropped.
+pub const MAX_RECORDED_FRAMES: usize = 4096;
 
-    let local_translation = state.scroll_translation + state.layout_bounds.position();
-    state.global_transform =
-        parent_global_transform * state.local_transform.with_translation(local_translation);
-    state.bounds = state
-        .global_transform
-        .transform_area(Aabb2D::from_size(state.layout_bounds.size()));
+/// The state of a [`View`]'s elements, see [`View::serialize_state`].
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ViewState {
+    /// The state of each element, keyed by its path from the root.
+    elements: HashMap<Vec<u64>, SavedElementState>,
+    focused: Option<Vec<u64>>,
+}
 
-    if state.wants_compose {
-        element.compose(&mut ComposePass {
-            state,
-            children: children.reborrow_mut(),
-        });
+#[derive(Clone, Debug, PartialEq)]
+struct SavedElementState {
+    /// The element's [`Element::debug_name`], so that state isn't restored into
+    /// an element of a different type.
+    type_name: String,
+    data: Vec<u8>,
+}
+
+impl ViewState {
+    /// The number of elements with saved state.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.elements.len()
     }
 
-    state.needs_render = true;
-    state.needs_compose = false;
-    state.wants_compose = false;
-    state.transformed = false;
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.elements.is_empty() && self.focused.is_none()
+    }
+}
 
-    let parent_state = &mut *state;
-    for_each_child_element(element, children, |mut node| {
-        compose_element(
-            node.reborrow_mut(),
-            parent_state.global_transform,
-            transformed,
+/// The segment of an element's path in a [`ViewState`].
+fn state_path_segment(state: &ElementState, index: usize) -> u64 {
+    state.key.unwrap_or(index as u64)
+}
+
+fn save_element_state(
+    node: tree::NodeRef<'_, ElementInfo>,
+    path: &mut Vec<u64>,
+    focused: Option<u64>,
+    state: &mut ViewState,
+) {
+    let element = &*node.element.element;
+    if let Some(data) = element.save_state() {
+        state.elements.insert(
+            path.clone(),
+            SavedElementState {
+                type_name: element.debug_name().to_string(),
+                data,
+            },
         );
-        parent_state.merge_with_child(&node.element.state);
-    });
+    }
+    if focused == Some(node.element.state.id) {
+        state.focused = Some(path.clone());
+    }
+
+    for (index, child_id) in element.children_ids().into_iter().enumerate() {
+        let child = node
+            .leaves
+            .reborrow_up()
+            .get_into(child_id)
+            .expect("Element::children_ids produced an invalid child ID");
+        path.push(state_path_segment(&child.element.state, index));
+        save_element_state(child, path, focused, state);
+        path.pop();
+    }
 }
 
+fn restore_element_state(
+    node: tree::NodeMut<'_, ElementInfo>,
+    path: &mut Vec<u64>,
+    state: &ViewState,
+    focused: &mut Option<u64>,
+    restored: &mut usize,
+) {
+    let element = &mut *node.element.element;
+    if let Some(saved) = state.elements.get(path)
+        && saved.type_name == element.debug_name()
+    {
+        element.restore_state(&saved.data);
+        *restored += 1;
+    }
+    if state.focused.as_ref() == Some(path) {
+        *focused = Some(node.element.state.id);
+    }
 
+    let mut leaves = node.leaves;
+    for (index, child_id) in element.children_ids().into_iter().enumerate() {
+        let child = leaves
+            .get_mut(child_id)
+            .expect("Element::children_ids produced an invalid child ID");
+        path.push(state_path_segment(&child.element.state, index));
+        restore_element_state(child, path, state, focused, restored);
+        path.pop();
+    }
+}
 
-pub struct LayoutPass<'view> {
-    fonts: &'view mut dyn Fonts,
-    state: &'view mut ElementState,
-    children: tree::LeavesMut<'view, ElementInfo>,
-    pub size: Xy<f32>,
+/// A log of events and passes executed by a [`View`], see
+/// [`View::start_recording`].
+#[derive(Clone, Debug, Default)]
+pub struct Recording {
+    frames: VecDeque<RecordedFrame>,
+    next_sequence: u64,
 }
 
-impl LayoutPass<'_> {
-    #[inline]
-    pub fn fonts(&self) -> &dyn Fonts {
-        self.fonts
+impl Recording {
+    fn push(&mut self, kind: RecordedKind, elements: Vec<ElementSnapshot>) {
+        if self.frames.len() >= MAX_RECORDED_FRAMES {
+            self.frames.pop_front();
+        }
+        self.frames.push_back(RecordedFrame {
+            sequence: self.next_sequence,
+            kind,
+            elements,
+        });
+        self.next_sequence += 1;
     }
 
     #[inline]
-    pub fn fonts_mut(&mut self) -> &mut dyn Fonts {
-        self.fonts
+    pub fn len(&self) -> usize {
+        self.frames.len()
     }
 
-    pub fn do_layout(&mut self, child: &mut ChildElement, size: Xy<f32>) {
-        let mut node = self
-            .children
-            .get_mut(child.id)
-            .expect("invalid child passed to LayoutPass::do_layout");
-        layout_element(self.fonts, node.reborrow_mut(), size);
-        self.state.merge_with_child(&node.element.state);
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.frames.is_empty()
     }
 
-    pub fn place_child(&mut self, child: &mut ChildElement, position: Xy<f32>) {
-        move_element(
-            &mut self
-                .children
-                .get_mut(child.id)
-                .expect("invalid child passed to LayoutPass::place_child")
-                .element
-                .state,
-            position,
-        );
+    pub fn frames(&self) -> impl DoubleEndedIterator<Item = &RecordedFrame> {
+        self.frames.iter()
     }
 
-    pub fn resolve_size(&mut self, child_id: u64, fallback_size: Xy<Length>) -> Xy<f32> {
-        let node = self
-            .children
-            .get_mut(child_id)
-            .expect("provided invalid child ID to LayoutPass::resolve_size");
+    /// Get the frame at the given index, where `0` is the oldest frame still
+    /// recorded.
+    #[inline]
+    pub fn get(&self, index: usize) -> Option<&RecordedFrame> {
+        self.frames.get(index)
+    }
+
+    /// Find the frame with the given sequence number, if it hasn't been
+    /// dropped.
+    pub fn find(&self, sequence: u64) -> Option<&RecordedFrame> {
+        let first = self.frames.front()?.sequence;
+        self.frames.get(sequence.checked_sub(first)? as usize)
+    }
+
+    /// Get the element snapshots that changed between the frame at `index` and
+    /// the one before it, as `(before, after)` pairs. Elements added during
+    /// the frame have no `before`.
+    pub fn changes(
+        &self,
+        index: usize,
+    ) -> impl Iterator<Item = (Option<&ElementSnapshot>, &ElementSnapshot)> {
+        let previous = index
+            .checked_sub(1)
+            .and_then(|index| self.frames.get(index));
+        let current = self.frames.get(index);
+
+        current
+            .into_iter()
+            .flat_map(|frame| frame.elements.iter())
+            .filter_map(move |after| {
+                let before = previous.and_then(|frame| frame.element(after.id));
+                (before != Some(after)).then_some((before, after))
+            })
+    }
 
-        resolve_element_size(self.fonts, node, fallback_size) // , self.size)
+    /// Start stepping through the frames from the newest one.
+    pub fn cursor(&self) -> RecordingCursor<'_> {
+        RecordingCursor {
+            recording: self,
+            index: self.frames.len().saturating_sub(1),
+        }
     }
 }
 
-pub fn layout_pass(view: &mut View) {
-    let node = view
-        .tree
-        .find_mut(view.root_element_id)
-        .expect("failed to find the view's root node");
-    layout_element(&mut *view.fonts, node, view.window_size);
+/// A position in a [`Recording`] that steps backwards and forwards through its
+/// frames, such as to find the event that caused a bad layout or a missing
+/// render.
+#[derive(Clone, Copy, Debug)]
+pub struct RecordingCursor<'a> {
+    recording: &'a Recording,
+    index: usize,
 }
 
-fn layout_element(fonts: &mut dyn Fonts, node: tree::NodeMut<'_, ElementInfo>, size: Xy<f32>) {
-    let element = &mut *node.element.element;
-    let state = &mut node.element.state;
-    let children = node.leaves;
+impl<'a> RecordingCursor<'a> {
+    /// The index of the current frame, see [`Recording::get`].
+    #[inline]
+    pub fn index(&self) -> usize {
+        self.index
+    }
 
-    state.layout_bounds.set_size(size);
+    /// The current frame, or `None` if the recording is empty.
+    #[inline]
+    pub fn frame(&self) -> Option<&'a RecordedFrame> {
+        self.recording.get(self.index)
+    }
 
-    let mut pass = LayoutPass {
-        fonts,
-        state,
-        children,
-        size,
-    };
-    element.layout(&mut pass);
+    /// Move to the frame at the given index, returning `false` (without
+    /// moving) if there isn't one.
+    pub fn seek(&mut self, index: usize) -> bool {
+        if index >= self.recording.len() {
+            return false;
+        }
+        self.index = index;
 
-    state.needs_render = true;
-    state.wants_render = true;
-    state.needs_compose = true;
-    state.wants_compose = true;
-}
+        true
+    }
 
-fn move_element(state: &mut ElementState, position: Xy<f32>) {
-    let end_point = position + state.layout_bounds.size();
+    /// Move to the next frame, returning `false` if this is the newest one.
+    pub fn step_forward(&mut self) -> bool {
+        self.seek(self.index + 1)
+    }
 
-    let position = position.round();
-    let end_point = end_point.round();
-    let baseline_offset = (end_point.y - state.layout_baseline_offset).round();
+    /// Move to the previous frame, returning `false` if this is the oldest one.
+    pub fn step_back(&mut self) -> bool {
+        self.index
+            .checked_sub(1)
+            .is_some_and(|index| self.seek(index))
+    }
 
-    if position != state.layout_bounds.min {
-        state.transformed = true;
+    /// The elements that changed during the current frame, see
+    /// [`Recording::changes`].
+    pub fn changes(
+        &self,
+    ) -> impl Iterator<Item = (Option<&'a ElementSnapshot>, &'a ElementSnapshot)> + use<'a> {
+        self.recording.changes(self.index)
     }
 
-    state.layout_bounds.min = position;
-    state.layout_bounds.max = end_point;
-    state.baseline_offset = baseline_offset;
+    /// Step back to the most recent frame (up to and including the current one)
+    /// after which `predicate` holds for the given element, but didn't before,
+    /// and return it. The cursor doesn't move if there isn't one.
+    pub fn step_back_to(
+        &mut self,
+        element_id: u64,
+        predicate: impl Fn(&ElementSnapshot) -> bool,
+    ) -> Option<&'a RecordedFrame> {
+        let holds = |index: usize| {
+            self.recording
+                .get(index)
+                .and_then(|frame| frame.element(element_id))
+                .is_some_and(&predicate)
+        };
+        let index = (0..=self.index)
+            .rev()
+            .find(|index| holds(*index) && !index.checked_sub(1).is_some_and(holds))?;
+        self.index = index;
+
+        self.frame()
+    }
 }
 
-pub struct MeasureContext<'pass> {
-    fonts: &'pass mut dyn Fonts,
-    state: &'pass mut ElementState,
-    children: tree::LeavesMut<'pass, ElementInfo>,
+#[derive(Clone, Debug)]
+pub struct RecordedFrame {
+    pub sequence: u64,
+    pub kind: RecordedKind,
+    /// The state of every element in the tree after this frame, in tree order.
+    pub elements: Vec<ElementSnapshot>,
 }
 
-impl MeasureContext<'_> {
-    #[inline]
-    pub fn fonts(&self) -> &dyn Fonts {
-        self.fonts
+impl RecordedFrame {
+    pub fn element(&self, id: u64) -> Option<&ElementSnapshot> {
+        self.elements.iter().find(|element| element.id == id)
     }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum RecordedKind {
+    KeyboardEvent(KeyboardEvent),
+    PointerEvent(PointerEvent),
+    Resize(Xy<f32>),
+    PointerPass,
+    FocusPass,
+    AnimatePass,
+    RenderPass,
+    LayoutPass,
+    ComposePass,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ElementSnapshot {
+    pub id: u64,
+    pub parent_id: Option<u64>,
+    pub bounds: Aabb2D,
+
+    pub needs_render: bool,
+    pub wants_render: bool,
+    pub wants_overlay_render: bool,
+    pub needs_animate: bool,
+    pub wants_animate: bool,
+    pub needs_layout: bool,
+    pub wants_layout: bool,
+    pub needs_compose: bool,
+    pub wants_compose: bool,
+
+    pub hovered: bool,
+    pub focused: bool,
+}
 
-    #[inline]
-    pub fn fonts_mut(&mut self) -> &mut dyn Fonts {
-        self.fonts
+impl ElementSnapshot {
+    fn new(state: &ElementState, parent_id: Option<u64>) -> Self {
+        Self {
+            id: state.id,
+            parent_id,
+            bounds: state.bounds,
+            needs_render: state.needs_render,
+            wants_render: state.wants_render,
+            wants_overlay_render: state.wants_overlay_render,
+            needs_animate: state.needs_animate,
+            wants_animate: state.wants_animate,
+            needs_layout: state.needs_layout,
+            wants_layout: state.wants_layout,
+            needs_compose: state.needs_compose,
+            wants_compose: state.wants_compose,
+            hovered: state.hovered,
+            focused: state.focused,
+        }
     }
 }
 
-// TODO: Don't just default to the fallback here. Get something from the child
-//       state?
-fn resolve_element_size(
-    fonts: &mut dyn Fonts,
-    node: tree::NodeMut<'_, ElementInfo>,
-    fallback_size: Xy<Length>,
-) -> Xy<f32> {
-    let element = &mut *node.element.element;
-    let state = &mut node.element.state;
-    let children = node.leaves;
+/// The element's ID and bounds, followed by the name of each flag that's set.
+impl fmt::Display for ElementSnapshot {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let Aabb2D { min, max } = self.bounds;
+        write!(f, "#{} {} {} {} {}", self.id, min.x, min.y, max.x, max.y)?;
+        for (set, name) in [
+            (self.needs_render, "needs-render"),
+            (self.wants_render, "wants-render"),
+            (self.wants_overlay_render, "wants-overlay-render"),
+            (self.needs_animate, "needs-animate"),
+            (self.wants_animate, "wants-animate"),
+            (self.needs_layout, "needs-layout"),
+            (self.wants_layout, "wants-layout"),
+            (self.needs_compose, "needs-compose"),
+            (self.wants_compose, "wants-compose"),
+            (self.hovered, "hovered"),
+            (self.focused, "focused"),
+        ] {
+            if set {
+                write!(f, " {name}")?;
+            }
+        }
 
-    // TODO: Consider supporting different inline/block axes?
+        Ok(())
+    }
+}
 
-    let inline_axis = Axis::Horizontal;
-    let block_axis = Axis::Vertical;
+fn snapshot_element(node: tree::NodeRef<'_, ElementInfo>, elements: &mut Vec<ElementSnapshot>) {
+    elements.push(ElementSnapshot::new(&node.element.state, node.branch_id));
 
-    let inline_length = fallback_size.x;
-    let block_length = fallback_size.y;
+    for child_id in node.element.element.children_ids() {
+        snapshot_element(
+            node.leaves
+                .reborrow_up()
+                .get_into(child_id)
+                .expect("Element::children_ids produced an invalid child ID"),
+            elements,
+        );
+    }
+}
 
-    let inline_measurement = inline_length.exact();
-    let block_measurement = block_length.exact();
 
-    // Early return.
-    if let Some(x) = inline_measurement
-        && let Some(y) = block_measurement
-    {
-        return Xy::new(x, y);
-    }
 
-    let mut context = MeasureContext {
-        fonts,
-        state,
-        children,
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        std::{cell::Cell, rc::Rc},
     };
 
-    let inline_measurement = inline_measurement.unwrap_or_else(|| {
-        resolve_axis_measurement(
-            &mut context,
-            element,
-            inline_axis,
-            inline_length,
-            block_measurement,
-        )
-    });
+    struct Leaf;
 
-    let block_measurement = block_measurement.unwrap_or_else(|| {
-        resolve_axis_measurement(
-            &mut context,
-            element,
-            block_axis,
-            block_length,
-            Some(inline_measurement),
-        )
-    });
+    impl Element for Leaf {
+        fn measure(
+            &mut self,
+            _context: &mut MeasureContext<'_>,
+            _axis: Axis,
+            _length_request: LengthRequest,
+            _cross_length: Option<f32>,
+        ) -> f32 {
+            0.0
+        }
+    }
 
-    Xy::new(inline_measurement, block_measurement)
-}
+    #[test]
+    fn keyed_children_reconcile() {
+        let build = |_: &u32| Leaf;
+        let mut children = KeyedChildren::new();
+        assert!(children.reconcile([1, 2, 3], build));
+        let ids = children.ids().collect::<Vec<_>>();
+        assert!(!children.reconcile([1, 2, 3], build));
+
+        // Reordering keeps every child, but is still a change.
+        assert!(children.reconcile([3, 1, 2], build));
+        assert_eq!(children.ids().collect::<Vec<_>>(), [ids[2], ids[0], ids[1]]);
+        assert!(children.removed.is_empty());
+
+        // Inserting and removing.
+        assert!(children.reconcile([3, 4, 2], build));
+        let new_ids = children.ids().collect::<Vec<_>>();
+        assert_eq!(new_ids[0], ids[2]);
+        assert!(!ids.contains(&new_ids[1]));
+        assert_eq!(new_ids[2], ids[1]);
+        assert_eq!(
+            children
+                .removed
+                .iter()
+                .map(ChildElement::id)
+                .collect::<Vec<_>>(),
+            [ids[0]],
+        );
 
-fn resolve_axis_measurement(
-    context: &mut MeasureContext<'_>,
-    element: &mut dyn Element,
-    axis: Axis,
-    length: Length,
-    cross_length: Option<f32>,
-) -> f32 {
-    let length_request = match length {
-        Length::MaxContent => LengthRequest::MaxContent,
-        Length::MinContent => LengthRequest::MinContent,
-        Length::FitContent(max_size) => LengthRequest::FitContent(max_size),
-        Length::Exact(amount) => return amount,
-    };
-    element.measure(context, axis, length_request, cross_length)
-}
+        // Duplicates are ignored rather than building a second child.
+        assert!(children.reconcile([2, 2, 3, 2], build));
+        assert_eq!(children.ids().collect::<Vec<_>>(), [ids[1], ids[2]]);
+        assert_eq!(children.removed.len(), 2);
+        assert!(!children.reconcile([2, 3, 3], build));
+    }
 
+    struct NoFonts;
 
+    impl Fonts for NoFonts {
+        fn measure_text(
+            &mut self,
+            _id: u64,
+            _text: &str,
+            _spans: &[TextSpan],
+            _max_advance: Option<f32>,
+            _font_size: f32,
+            _line_height: LineHeight,
+            _font_family: FontFamily,
+            _font_style: FontStyle,
+            _alignment: TextAlignment,
+            _wrap_mode: TextWrapMode,
+        ) -> Xy<f32> {
+            Xy::new(0.0, 0.0)
+        }
+    }
 
-macro_rules! multi_impl {
-    ($ty:ty, { $($item:item)+ }) => {
-        impl $ty { $($item)+ }
-    };
-    ($ty:ty, $($others:ty),+, { $($item:item)+ }) => {
-        multi_impl!($ty, { $($item)+ });
-        multi_impl!($($others),+, { $($item)+ });
-    };
-}
+    /// A leaf that remembers which update of its [`KeyedList`] built it.
+    struct Built(u32);
 
-multi_impl! {
-    LayoutPass<'_>,
-    MeasureContext<'_>,
-    {
-        // TODO: Don't just default to the fallback here. Get something from the child
-        //       state?
-        pub fn resolve_length(
+    impl Element for Built {
+        fn measure(
             &mut self,
-            child_id: u64,
-            axis: Axis,
-            fallback_length: Length,
-            cross_length: Option<f32>,
+            _context: &mut MeasureContext<'_>,
+            _axis: Axis,
+            _length_request: LengthRequest,
+            _cross_length: Option<f32>,
         ) -> f32 {
-            let child = self
-                .children
-                .get_mut(child_id)
-                .expect("invalid child ID provided to MeasureContext::resolve_length");
-            let element = &mut *child.element.element;
-            let state = &mut child.element.state;
-            let children = child.leaves;
-
-            let mut context = MeasureContext {
-                fonts: self.fonts,
-                state,
-                children,
-            };
-
-            fallback_length.exact().unwrap_or_else(|| {
-                resolve_axis_measurement(&mut context, element, axis, fallback_length, cross_length)
-            })
+            0.0
         }
     }
-}
 
-// Types with a `state: &mut ElementState` field.
-multi_impl! {
-    AnimatePass<'_>,
-    ComposePass<'_>,
-    EventPass<'_>,
-    LayoutPass<'_>,
-    MeasureContext<'_>,
-    RenderPass<'_>,
-    UpdatePass<'_>,
-    {
-        #[inline]
-        pub fn id(&self) -> u64 {
-            self.state.id
-        }
+    /// Rebuilds a keyed child for each of its keys on every update.
+    struct KeyedList {
+        keys: Vec<u32>,
+        generation: u32,
+        children: Vec<ChildElement>,
+    }
 
-        #[inline]
-        pub fn bounds(&self) -> Aabb2D {
-            self.state.bounds
+    impl Element for KeyedList {
+        fn children_ids(&self) -> Vec<u64> {
+            self.children.iter().map(ChildElement::id).collect()
         }
 
-        pub fn local_position(&self, point: Xy<f32>) -> Xy<f32> {
-            self.state.global_transform.inverse() * point
+        fn update_children(&mut self, pass: &mut UpdatePass<'_>) {
+            for child in std::mem::take(&mut self.children) {
+                pass.remove_child(child);
+            }
+            self.generation += 1;
+            for key in &self.keys {
+                let builder = ElementBuilder::new(Built(self.generation)).with_key(key);
+                let mut child = builder.into_child();
+                pass.update_child(&mut child);
+                self.children.push(child);
+            }
         }
 
-        pub fn baseline_offset(&self) -> f32 {
-            self.state.layout_bounds.max.y - self.state.baseline_offset
+        fn measure(
+            &mut self,
+            _context: &mut MeasureContext<'_>,
+            _axis: Axis,
+            _length_request: LengthRequest,
+            _cross_length: Option<f32>,
+        ) -> f32 {
+            0.0
         }
+    }
 
-        pub fn set_baseline_offset(&mut self, offset: f32) {
-            self.state.layout_baseline_offset = offset;
+    #[test]
+    fn reordered_keyed_children_keep_their_state() {
+        let list = KeyedList {
+            keys: vec![1, 2, 3],
+            generation: 0,
+            children: Vec::new(),
+        };
+        let mut view = View::new(
+            ElementBuilder::new(list),
+            Box::new(NoFonts),
+            Xy::new(100.0, 100.0),
+        );
+        let root_id = view.root_element_id;
+        let list_ids = |view: &View| view.tree.find(root_id).unwrap().element.children_ids();
+        let ids = list_ids(&view);
+        for (index, id) in ids.iter().enumerate() {
+            let node = view.tree.find_mut(*id).unwrap();
+            node.element.state.scroll_translation = Xy::new(index as f32, 0.0);
         }
 
-        pub fn clear_baseline_offset(&mut self) {
-            self.state.layout_baseline_offset = 0.0;
+        let root = view.tree.find_mut(root_id).unwrap();
+        (&mut *root.element.element as &mut dyn Any)
+            .downcast_mut::<KeyedList>()
+            .unwrap()
+            .keys = vec![3, 1, 2];
+        root.element.state.children_changed = true;
+        update_pass(&mut view);
+
+        // Each key still has its first child, in the new order.
+        assert_eq!(list_ids(&view), [ids[2], ids[0], ids[1]]);
+        for (index, id) in ids.iter().enumerate() {
+            let node = view.tree.find(*id).unwrap();
+            assert_eq!(node.element.state.scroll_translation.x, index as f32);
+            let built = (&*node.element.element as &dyn Any)
+                .downcast_ref::<Built>()
+                .unwrap();
+            assert_eq!(built.0, 1);
         }
+    }
 
-        pub fn request_render(&mut self) {
-            self.state.wants_render = true;
+    /// A leaf that counts its layouts, and requests another when it's pressed.
+    struct LayoutProbe {
+        layouts: Rc<Cell<usize>>,
+        height: Rc<Cell<f32>>,
+    }
+
+    impl LayoutProbe {
+        fn new(height: f32) -> (Self, Rc<Cell<usize>>, Rc<Cell<f32>>) {
+            let layouts = Rc::new(Cell::new(0));
+            let height = Rc::new(Cell::new(height));
+            let probe = Self {
+                layouts: layouts.clone(),
+                height: height.clone(),
+            };
+
+            (probe, layouts, height)
         }
+    }
 
-        pub fn request_layout(&mut self) {
-            self.state.needs_layout = true;
+    impl Element for LayoutProbe {
+        fn layout(&mut self, _pass: &mut LayoutPass<'_>) {
+            self.layouts.set(self.layouts.get() + 1);
         }
 
-        pub fn request_compose(&mut self) {
-            self.state.wants_compose = true;
-            self.state.needs_compose = true;
+        fn measure(
+            &mut self,
+            _context: &mut MeasureContext<'_>,
+            axis: Axis,
+            _length_request: LengthRequest,
+            _cross_length: Option<f32>,
+        ) -> f32 {
+            match axis {
+                Axis::Horizontal => 50.0,
+                Axis::Vertical => self.height.get(),
+            }
         }
 
-        pub fn request_animate(&mut self) {
-            self.state.wants_animate = true;
-            self.state.needs_animate = true;
+        fn on_pointer_event(&mut self, pass: &mut EventPass<'_>, event: &PointerEvent) {
+            if let PointerEvent::Down { .. } = event {
+                pass.request_layout();
+            }
         }
     }
-}
 
-// Types with a `children: tree::LeavesMut<'_, ElementInfo>` field.
-multi_impl! {
-    AnimatePass<'_>,
-    ComposePass<'_>,
-    EventPass<'_>,
-    LayoutPass<'_>,
-    MeasureContext<'_>,
-    UpdatePass<'_>,
-    {
-        pub fn child(&self, id: u64) -> Option<tree::NodeRef<'_, ElementInfo>> {
-            self.children.get(id)
+    #[test]
+    fn clean_tree_skips_layout() {
+        let (first, first_layouts, _) = LayoutProbe::new(10.0);
+        let (second, second_layouts, _) = LayoutProbe::new(10.0);
+        let mut view = crate::testing::TestView::new(Column::new().with(first).with(second));
+        view.render();
+        assert_eq!((first_layouts.get(), second_layouts.get()), (1, 1));
+
+        view.render();
+        view.advance(Duration::from_millis(100));
+        assert_eq!((first_layouts.get(), second_layouts.get()), (1, 1));
+    }
+
+    #[test]
+    fn dirty_leaf_is_laid_out_alone() {
+        let (first, first_layouts, _) = LayoutProbe::new(10.0);
+        let (second, second_layouts, _) = LayoutProbe::new(10.0);
+        let mut view = crate::testing::TestView::new(Column::new().with(first).with(second));
+        view.render();
+
+        // Its size didn't change, so nothing else needs to be laid out.
+        view.click(Xy::new(5.0, 15.0));
+        view.render();
+        assert_eq!((first_layouts.get(), second_layouts.get()), (1, 2));
+    }
+
+    #[test]
+    fn measurement_change_lays_out_parent() {
+        let (first, first_layouts, first_height) = LayoutProbe::new(10.0);
+        let (second, second_layouts, _) = LayoutProbe::new(10.0);
+        let mut view = crate::testing::TestView::new(Column::new().with(first).with(second));
+        view.render();
+        view.assert_tree(
+            "
+            Column at (0, 0) size 800x600 baseline 0
+              LayoutProbe at (0, 0) size 50x10 baseline 0
+              LayoutProbe at (0, 10) size 50x10 baseline 0
+            ",
+        );
+
+        first_height.set(30.0);
+        view.click(Xy::new(5.0, 5.0));
+        view.render();
+        assert_eq!(first_layouts.get(), 2);
+        // The column moved the second probe down, without resizing it.
+        assert_eq!(second_layouts.get(), 1);
+        view.assert_tree(
+            "
+            Column at (0, 0) size 800x600 baseline 0 hovered
+              LayoutProbe at (0, 0) size 50x30 baseline 0 hovered
+              LayoutProbe at (0, 30) size 50x10 baseline 0
+            ",
+        );
+    }
+
+    #[test]
+    fn zoom_change_lays_out_everything() {
+        let (first, first_layouts, _) = LayoutProbe::new(10.0);
+        let (second, second_layouts, _) = LayoutProbe::new(10.0);
+        let mut view = crate::testing::TestView::new(Column::new().with(first).with(second));
+        view.render();
+
+        view.view_mut().set_zoom(2.0);
+        view.render();
+        assert_eq!((first_layouts.get(), second_layouts.get()), (2, 2));
+    }
+
+    #[test]
+    fn recordings_step_back_to_the_event_that_requested_a_layout() {
+        let builder = ElementBuilder::new(LayoutProbe::new(10.0).0);
+        let id = builder.id;
+        let mut view = crate::testing::TestView::from_builder(builder, Xy::new(100.0, 100.0));
+        view.render();
+
+        view.view_mut().start_recording();
+        view.click(Xy::new(50.0, 50.0));
+        view.render();
+        let recording = view.view_mut().stop_recording().unwrap();
+
+        let mut cursor = recording.cursor();
+        assert_eq!(cursor.frame().unwrap().kind, RecordedKind::RenderPass);
+        assert!(!cursor.step_forward());
+        let frame = cursor
+            .step_back_to(id, |element| element.wants_layout)
+            .unwrap();
+        assert!(matches!(
+            frame.kind,
+            RecordedKind::PointerEvent(PointerEvent::Down { .. }),
+        ));
+        assert!(
+            cursor
+                .changes()
+                .any(|(before, after)| after.id == id && !before.unwrap().wants_layout)
+        );
+
+        // The layout pass that follows clears the request.
+        assert!(cursor.step_forward());
+        while cursor.frame().unwrap().kind != RecordedKind::LayoutPass {
+            assert!(cursor.step_forward());
         }
+        assert!(!cursor.frame().unwrap().element(id).unwrap().wants_layout);
 
-        pub fn expect_child(&self, id: u64) -> tree::NodeRef<'_, ElementInfo> {
-            self.children.get(id).expect("invalid ID passed to `expect_child`")
+        assert!(cursor.seek(0));
+        assert!(!cursor.step_back());
+        assert!(!cursor.seek(recording.len()));
+    }
+
+    #[test]
+    fn metric_overrides_lay_out_everything() {
+        let mut view = crate::testing::TestView::new(
+            Row::new()
+                .with(ScrollBar::new())
+                .with(LayoutProbe::new(10.0).0),
+        );
+        view.render();
+        assert_eq!(view.element_center("ScrollBar").unwrap().x, 6.0);
+
+        view.view_mut()
+            .update_theme(|theme| theme.set_metric_override("scrollbar.width", 20.0));
+        view.render();
+        assert_eq!(view.element_center("ScrollBar").unwrap().x, 10.0);
+    }
+
+    #[test]
+    fn right_to_left_text_is_drawn_in_visual_order() {
+        let mut view = crate::testing::TestView::new(Label::new("ab \u{5E9}\u{5DC}\u{5B9}\ncd"));
+
+        assert_eq!(view.text_in_render(), "ab \u{5DC}\u{5B9}\u{5E9}\ncd",);
+    }
+
+    #[test]
+    fn arabic_text_is_drawn_in_its_joining_forms() {
+        // Beh, yeh, and teh, which are drawn from right to left.
+        let mut view = crate::testing::TestView::new(Label::new("\u{628}\u{64A}\u{62A}"));
+
+        assert_eq!(view.text_in_render(), "\u{FE96}\u{FEF4}\u{FE91}");
+    }
+
+    /// A leaf that's red on its left half and white on its right half.
+    struct Swatch;
+
+    impl Element for Swatch {
+        fn render(&mut self, pass: &mut RenderPass<'_>) {
+            let bounds = pass.bounds();
+            pass.fill_quad(bounds, Rgba::WHITE, 0.0, Rgba::NONE);
+            let half = Aabb2D::new(
+                bounds.min.x,
+                bounds.min.y,
+                (bounds.min.x + bounds.max.x) / 2.0,
+                bounds.max.y,
+            );
+            pass.fill_quad(half, Rgba::rgb(255, 0, 0), 0.0, Rgba::NONE);
         }
 
-        pub fn typed_child_mut<T: Element>(
+        fn measure(
             &mut self,
-            child: &mut TypedChildElement<T>,
-        ) -> &mut T {
-            let node_mut = self
-                .children
-                .get_mut(child.id())
-                .expect("get_mut: child element not found");
+            _context: &mut MeasureContext<'_>,
+            _axis: Axis,
+            _length_request: LengthRequest,
+            _cross_length: Option<f32>,
+        ) -> f32 {
+            0.0
+        }
+    }
 
-            (&mut *node_mut.element.element as &mut dyn Any).downcast_mut().unwrap()
+    /// Places a 40x20 child with its center at (50, 50), turned a quarter of
+    /// the way around it.
+    struct Rotated(ChildElement);
+
+    impl Element for Rotated {
+        fn children_ids(&self) -> Vec<u64> {
+            vec![self.0.id()]
         }
 
-        pub fn request_child_render(&mut self, id: u64) {
-            self.children
-                .get_mut(id)
-                .expect("invalid child ID passed to request_child_render")
-                .element
-                .state
-                .wants_render = true;
+        fn update_children(&mut self, pass: &mut UpdatePass<'_>) {
+            pass.update_child(&mut self.0);
         }
-    }
-}
 
+        fn layout(&mut self, pass: &mut LayoutPass<'_>) {
+            pass.do_layout(&mut self.0, Xy::new(40.0, 20.0));
+            pass.place_child(&mut self.0, Xy::new(30.0, 40.0));
+        }
 
+        fn compose(&mut self, pass: &mut ComposePass<'_>) {
+            let center = Xy::new(20.0, 10.0);
+            let transform = Transform2D::translate(center)
+                * Transform2D::rotation(std::f32::consts::FRAC_PI_2)
+                * Transform2D::translate(Xy::ZERO - center);
+            pass.set_child_transform(&mut self.0, transform);
+        }
 
-fn for_each_child_element(
-    element: &mut dyn Element,
-    mut children: tree::LeavesMut<'_, ElementInfo>,
-    mut callback: impl FnMut(tree::NodeMut<'_, ElementInfo>),
-) {
-    for child_id in element.children_ids() {
-        callback(
-            children
-                .get_mut(child_id)
-                .expect("Element::children_ids produced an invalid child ID"),
+        fn measure(
+            &mut self,
+            _context: &mut MeasureContext<'_>,
+            _axis: Axis,
+            _length_request: LengthRequest,
+            _cross_length: Option<f32>,
+        ) -> f32 {
+            0.0
+        }
+    }
+
+    #[test]
+    fn rotated_elements_are_painted_where_they_are_hit() {
+        let swatch = ElementBuilder::new(Swatch);
+        let swatch_id = swatch.id;
+        let mut view = crate::testing::TestView::with_window_size(
+            Rotated(swatch.into_child()),
+            Xy::new(100.0, 100.0),
         );
+        let image = view.render_image(Rgba::BLACK);
+
+        // The swatch covers (40, 30) to (60, 70), with its left half on top.
+        assert_eq!(image.pixel(50, 35), Some(Rgba::rgb(255, 0, 0)));
+        assert_eq!(image.pixel(50, 65), Some(Rgba::WHITE));
+        view.move_pointer(Xy::new(50.0, 35.0));
+        assert_eq!(view.view().hovered_path.first(), Some(&swatch_id));
+        view.move_pointer(Xy::new(50.0, 65.0));
+        assert_eq!(view.view().hovered_path.first(), Some(&swatch_id));
+
+        // It would cover (30, 40) to (70, 60) if it wasn't rotated.
+        assert_eq!(image.pixel(35, 50), Some(Rgba::BLACK));
+        view.move_pointer(Xy::new(35.0, 50.0));
+        assert_ne!(view.view().hovered_path.first(), Some(&swatch_id));
     }
 }
@@ -0,0 +1,239 @@
+//! # Application Views
+//!
+//! See [`AppViews`] for more information.
+
+use std::{collections::BTreeMap, time::Instant};
+
+use crate::{DriverControl, DriverInput, DriverInputEvent, Fonts, Manifest, View, ViewState, Xy};
+
+
+
+/// Identifies one of the views in an [`AppViews`].
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+#[repr(transparent)]
+pub struct ViewId(pub u64);
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct UnknownViewError {
+    pub name: String,
+}
+
+impl std::fmt::Display for UnknownViewError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "the application has no view named `{}`", self.name)
+    }
+}
+
+impl std::error::Error for UnknownViewError {}
+
+/// The open views of a single application, for hosts that let applications
+/// have more than one (see [`Manifest::views`]).
+///
+/// Each view is its own [`View`], with its own element tree, focus, and
+/// render, and at most one view with each name is open at a time.
+pub struct AppViews {
+    manifest: &'static Manifest,
+    make_fonts: Box<dyn FnMut() -> Box<dyn Fonts>>,
+    views: BTreeMap<ViewId, (String, View)>,
+    next_id: u64,
+}
+
+impl AppViews {
+    /// Manage the views of the given application, where `make_fonts` provides
+    /// the fonts for each view that's opened.
+    pub fn new(
+        manifest: &'static Manifest,
+        make_fonts: impl FnMut() -> Box<dyn Fonts> + 'static,
+    ) -> Self {
+        Self {
+            manifest,
+            make_fonts: Box::new(make_fonts),
+            views: BTreeMap::new(),
+            next_id: 1,
+        }
+    }
+
+    #[inline]
+    pub fn manifest(&self) -> &'static Manifest {
+        self.manifest
+    }
+
+    /// Open the view with the given name (see [`MAIN_VIEW`](crate::MAIN_VIEW)),
+    /// or return the ID of the one that's already open.
+    pub fn open(&mut self, name: &str, window_size: Xy<f32>) -> Result<ViewId, UnknownViewError> {
+        if let Some(id) = self.find(name) {
+            return Ok(id);
+        }
+        let init = self
+            .manifest
+            .view_init(name)
+            .ok_or_else(|| UnknownViewError {
+                name: name.to_string(),
+            })?;
+
+        let id = ViewId(self.next_id);
+        self.next_id += 1;
+        let view = View::new(init(), (self.make_fonts)(), window_size);
+        self.views.insert(id, (name.to_string(), view));
+
+        Ok(id)
+    }
+
+    /// Close the view, returning it so that the host can drop it before
+    /// unloading the application.
+    pub fn close(&mut self, id: ViewId) -> Option<View> {
+        self.views.remove(&id).map(|(_, view)| view)
+    }
+
+    /// The ID of the open view with the given name.
+    pub fn find(&self, name: &str) -> Option<ViewId> {
+        self.views
+            .iter()
+            .find(|(_, (view_name, _))| *view_name == name)
+            .map(|(id, _)| *id)
+    }
+
+    pub fn name(&self, id: ViewId) -> Option<&str> {
+        self.views.get(&id).map(|(name, _)| name.as_str())
+    }
+
+    pub fn get(&self, id: ViewId) -> Option<&View> {
+        self.views.get(&id).map(|(_, view)| view)
+    }
+
+    pub fn get_mut(&mut self, id: ViewId) -> Option<&mut View> {
+        self.views.get_mut(&id).map(|(_, view)| view)
+    }
+
+    /// The IDs of every open view, in the order they were opened.
+    pub fn ids(&self) -> impl Iterator<Item = ViewId> + '_ {
+        self.views.keys().copied()
+    }
+
+    /// Every open view, in the order they were opened.
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = (ViewId, &mut View)> {
+        self.views.iter_mut().map(|(id, (_, view))| (*id, view))
+    }
+
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.views.len()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.views.is_empty()
+    }
+
+    /// Save the state of every open view (see [`View::serialize_state`]) by
+    /// name, so that they can be reopened with [`AppViews::restore`], such as
+    /// after the driver hosting them has crashed and been restarted.
+    pub fn save(&self) -> Vec<(String, ViewState)> {
+        self.views
+            .values()
+            .map(|(name, view)| (name.clone(), view.serialize_state()))
+            .collect()
+    }
+
+    /// Open each of the saved views and restore its state, returning the number
+    /// of elements whose state was restored.
+    ///
+    /// Views that the application no longer has are skipped.
+    pub fn restore(&mut self, saved: &[(String, ViewState)], window_size: Xy<f32>) -> usize {
+        let mut restored = 0;
+        for (name, state) in saved {
+            let Ok(id) = self.open(name, window_size) else {
+                continue;
+            };
+            if let Some((_, view)) = self.views.get_mut(&id) {
+                restored += view.restore_state(state);
+            }
+        }
+
+        restored
+    }
+
+    /// Send the events that the driver received to the view with the input's
+    /// [`id`](DriverInput::id), returning `false` (and discarding the events)
+    /// if there's no such view.
+    ///
+    /// A [`DriverInputEvent::Close`] closes the view, and any events after it
+    /// are discarded.
+    pub fn handle_input(
+        &mut self,
+        input: &mut DriverInput,
+        events: impl IntoIterator<Item = DriverInputEvent>,
+    ) -> bool {
+        let id = ViewId(input.id);
+        let Some((_, view)) = self.views.get_mut(&id) else {
+            events.into_iter().for_each(drop);
+            return false;
+        };
+
+        let mut events = events.into_iter();
+        let mut closed = false;
+        for event in &mut events {
+            match event {
+                DriverInputEvent::Pointer(event) => view.handle_pointer_event(event),
+                DriverInputEvent::Keyboard(event) => view.handle_keyboard_event(event),
+                DriverInputEvent::WindowResize(bounds) => {
+                    input.known_bounds = bounds;
+                    view.resize_window(bounds.size());
+                }
+                DriverInputEvent::Close => {
+                    closed = true;
+                    break;
+                }
+                DriverInputEvent::Other(_) => {}
+            }
+        }
+        if closed {
+            events.for_each(drop);
+            self.views.remove(&id);
+        }
+
+        true
+    }
+
+    /// Whether the view with the input's [`id`](DriverInput::id) should be
+    /// rendered now.
+    ///
+    /// This is only the case when the host has had a vsync since the last frame
+    /// was presented (see [`DriverInput::can_present`]), and the view has
+    /// changed (such as because of the events it was just sent), is animating,
+    /// or has a timer that's due (see [`View::next_frame_deadline`]). The first
+    /// frame is always rendered.
+    pub fn should_render(&self, input: &DriverInput) -> bool {
+        let Some((_, view)) = self.views.get(&ViewId(input.id)) else {
+            return false;
+        };
+
+        input.can_present()
+            && (input.presented_sequence == 0
+                || view
+                    .next_frame_deadline()
+                    .is_some_and(|deadline| deadline <= Instant::now()))
+    }
+
+    /// Queue the control messages for the view with the input's
+    /// [`id`](DriverInput::id), after it has been rendered.
+    ///
+    /// This sends the view's cursor icon when it changes, requests a redraw
+    /// while it's animating, and requests that it's closed once it's no longer
+    /// open (such as because the application closed it).
+    pub fn write_controls(&mut self, input: &mut DriverInput) {
+        let Some((_, view)) = self.views.get(&ViewId(input.id)) else {
+            input.push_control(DriverControl::RequestClose);
+            return;
+        };
+
+        let cursor_icon = view.cursor_icon();
+        if cursor_icon != input.cursor_icon {
+            input.cursor_icon = cursor_icon;
+            input.push_control(DriverControl::SetCursorIcon(cursor_icon));
+        }
+        if view.animating() {
+            input.push_control(DriverControl::RequestRedraw);
+        }
+    }
+}
@@ -0,0 +1,867 @@
+//! # Windows
+//!
+//! See [`WindowManager`] for more information.
+
+use crate::{
+    Aabb2D, Axis, ChildElement, CursorIcon, Element, ElementBuilder, EventPass, LayoutPass, Length,
+    LengthRequest, MeasureContext, PointerButton, PointerEvent, RenderPass, Rgba,
+    TypedChildElement, UpdatePass, Xy,
+};
+
+
+
+const BORDER_WIDTH: f32 = 1.0;
+/// How far inside a window's edge it can be grabbed to resize it.
+const RESIZE_MARGIN: f32 = 5.0;
+/// How much of a window's title bar is kept inside the desktop when it's moved.
+const MIN_VISIBLE_WIDTH: f32 = 48.0;
+const MIN_WINDOW_WIDTH: f32 = 120.0;
+const TASKBAR_BUTTON_WIDTH: f32 = 160.0;
+const MINIMIZE_LABEL: &str = "–";
+const CLOSE_LABEL: &str = "×";
+
+/// Identifies a window within its [`WindowManager`].
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct WindowId(u64);
+
+/// A desktop of overlapping windows, each hosting its own element (such as the
+/// root of an application), above a taskbar listing every window.
+///
+/// Windows can be moved by dragging their title bars, resized by dragging their
+/// edges, and minimized or closed with the buttons in their title bars.
+/// Clicking a window raises it above the others and makes it the active window,
+/// as does focusing anything inside it. Minimized windows are kept in the tree
+/// without being laid out or rendered, and are restored from the taskbar.
+pub struct WindowManager {
+    /// Every window, in the order they were opened.
+    windows: Vec<Window>,
+    /// The frames of closed windows, which are removed during the next update.
+    removed: Vec<ChildElement>,
+    /// Windows closed by the user since the last
+    /// [`WindowManager::take_closed`].
+    closed: Vec<WindowId>,
+    active: Option<WindowId>,
+    next_stacking: u64,
+    font_size: f32,
+    /// The theme's `"spacing"` as of the last layout, which pads the titles.
+    title_padding: f32,
+    size: Xy<f32>,
+    drag: Option<WindowDrag>,
+}
+
+struct Window {
+    title: String,
+    frame: TypedChildElement<WindowFrame>,
+    /// The frame's bounds relative to the manager, in logical units.
+    bounds: Aabb2D,
+    minimized: bool,
+    /// Windows with a higher stacking order are drawn above those with a lower
+    /// one.
+    stacking: u64,
+}
+
+struct WindowDrag {
+    id: WindowId,
+    /// The edges being dragged, or `None` if the window is being moved.
+    edges: Option<Edges>,
+    origin: Xy<f32>,
+    start_bounds: Aabb2D,
+}
+
+impl Default for WindowManager {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl WindowManager {
+    pub fn new() -> Self {
+        Self {
+            windows: Vec::new(),
+            removed: Vec::new(),
+            closed: Vec::new(),
+            active: None,
+            next_stacking: 0,
+            font_size: 14.0,
+            title_padding: 0.0,
+            size: Xy::ZERO,
+            drag: None,
+        }
+    }
+
+    /// Open a window, see [`WindowManager::open`].
+    pub fn with_window(
+        mut self,
+        title: impl Into<String>,
+        content: impl Element + 'static,
+        bounds: Aabb2D,
+    ) -> Self {
+        self.open(title, content, bounds);
+        self
+    }
+
+    pub fn with_font_size(mut self, font_size: f32) -> Self {
+        self.font_size = font_size;
+        self
+    }
+
+    /// Open a window above the others with the given bounds (including its
+    /// title bar), relative to the manager, and make it the active window.
+    ///
+    /// The manager's update must then be requested (see
+    /// [`EventPass::request_child_update`]), which adds the window to the tree.
+    pub fn open(
+        &mut self,
+        title: impl Into<String>,
+        content: impl Element + 'static,
+        bounds: Aabb2D,
+    ) -> WindowId {
+        let title = title.into();
+        let frame = TypedChildElement::new(WindowFrame {
+            title: title.clone(),
+            active: true,
+            font_size: self.font_size,
+            title_padding: self.title_padding,
+            content: ElementBuilder::new(content).into_child(),
+            size: Xy::ZERO,
+            hovered_zone: FrameZone::Content,
+        });
+        let id = WindowId(frame.id());
+        self.windows.push(Window {
+            title,
+            frame,
+            bounds,
+            minimized: false,
+            stacking: self.next_stacking,
+        });
+        self.next_stacking += 1;
+        self.active = Some(id);
+
+        id
+    }
+
+    /// Close a window, returning `false` if it doesn't exist.
+    ///
+    /// The manager's update must then be requested (see
+    /// [`EventPass::request_child_update`]), which removes the window from the
+    /// tree.
+    pub fn close(&mut self, id: WindowId) -> bool {
+        let Some(index) = self.windows.iter().position(|window| window.id() == id) else {
+            return false;
+        };
+        let window = self.windows.remove(index);
+        self.removed.push(window.frame.inner);
+        if self.active == Some(id) {
+            self.active = self.topmost_visible();
+        }
+        if self.drag.as_ref().is_some_and(|drag| drag.id == id) {
+            self.drag = None;
+        }
+
+        true
+    }
+
+    /// Every window, from the bottom of the stack to the top (including those
+    /// that are minimized).
+    pub fn windows(&self) -> impl Iterator<Item = WindowId> {
+        self.stacked().map(Window::id)
+    }
+
+    /// The window that was last clicked or focused.
+    #[inline]
+    pub fn active(&self) -> Option<WindowId> {
+        self.active
+    }
+
+    pub fn title(&self, id: WindowId) -> Option<&str> {
+        self.window(id).map(|window| window.title.as_str())
+    }
+
+    /// The window's bounds (including its title bar), relative to the manager.
+    pub fn window_bounds(&self, id: WindowId) -> Option<Aabb2D> {
+        self.window(id).map(|window| window.bounds)
+    }
+
+    pub fn is_minimized(&self, id: WindowId) -> bool {
+        self.window(id).is_some_and(|window| window.minimized)
+    }
+
+    /// The windows closed by the user since the last call.
+    pub fn take_closed(&mut self) -> Vec<WindowId> {
+        std::mem::take(&mut self.closed)
+    }
+
+    fn window(&self, id: WindowId) -> Option<&Window> {
+        self.windows.iter().find(|window| window.id() == id)
+    }
+
+    fn window_mut(&mut self, id: WindowId) -> Option<&mut Window> {
+        self.windows.iter_mut().find(|window| window.id() == id)
+    }
+
+    fn stacked(&self) -> impl Iterator<Item = &Window> {
+        let mut windows = self.windows.iter().collect::<Vec<_>>();
+        windows.sort_by_key(|window| window.stacking);
+        windows.into_iter()
+    }
+
+    fn topmost_visible(&self) -> Option<WindowId> {
+        self.stacked()
+            .filter(|window| !window.minimized)
+            .last()
+            .map(Window::id)
+    }
+
+    fn title_height(&self) -> f32 {
+        self.font_size + self.title_padding * 2.0
+    }
+
+    /// The area that windows are placed in, above the taskbar.
+    fn desktop_size(&self) -> Xy<f32> {
+        Xy::new(self.size.x, (self.size.y - self.title_height()).max(0.0))
+    }
+
+    fn min_window_size(&self) -> Xy<f32> {
+        Xy::new(MIN_WINDOW_WIDTH, self.title_height() * 2.0)
+    }
+
+    fn taskbar_button_at(&self, position: Xy<f32>) -> Option<WindowId> {
+        let desktop_height = self.desktop_size().y;
+        if position.y < desktop_height || position.x < 0.0 {
+            return None;
+        }
+
+        self.windows
+            .get((position.x / TASKBAR_BUTTON_WIDTH) as usize)
+            .map(Window::id)
+    }
+
+    /// The topmost window under the given position, and where in the window it
+    /// is.
+    fn window_at(&self, position: Xy<f32>) -> Option<(WindowId, FrameZone)> {
+        let title_height = self.title_height();
+        self.stacked()
+            .filter(|window| !window.minimized && window.bounds.contains(position))
+            .last()
+            .map(|window| {
+                let zone = FrameZone::at(
+                    window.bounds.size(),
+                    title_height,
+                    position - window.bounds.min,
+                );
+                (window.id(), zone)
+            })
+    }
+
+    /// Raise the window above the others and make it the active window.
+    fn activate(&mut self, pass: &mut EventPass<'_>, id: WindowId) {
+        let next_stacking = self.next_stacking;
+        let Some(window) = self.window_mut(id) else {
+            return;
+        };
+        let raised = window.stacking + 1 != next_stacking;
+        if raised {
+            window.stacking = next_stacking;
+        }
+        if window.minimized {
+            window.minimized = false;
+            pass.request_child_layout(window.frame.id());
+            pass.request_layout();
+        }
+        if raised {
+            self.next_stacking += 1;
+        }
+
+        let previous = self.active.replace(id);
+        if previous != Some(id) {
+            self.set_frame_active(pass, previous, false);
+            self.set_frame_active(pass, Some(id), true);
+        }
+        if raised || previous != Some(id) {
+            pass.request_render();
+            pass.add_damage(pass.bounds());
+        }
+    }
+
+    fn minimize(&mut self, pass: &mut EventPass<'_>, id: WindowId) {
+        let Some(window) = self.window_mut(id) else {
+            return;
+        };
+        window.minimized = true;
+        if self.active == Some(id) {
+            self.set_frame_active(pass, Some(id), false);
+            self.active = self.topmost_visible();
+            self.set_frame_active(pass, self.active, true);
+        }
+        pass.request_layout();
+        pass.request_render();
+        pass.add_damage(pass.bounds());
+    }
+
+    fn close_from_event(&mut self, pass: &mut EventPass<'_>, id: WindowId) {
+        if self.close(id) {
+            self.closed.push(id);
+            self.set_frame_active(pass, self.active, true);
+            pass.request_update();
+            pass.request_layout();
+            pass.request_render();
+            pass.add_damage(pass.bounds());
+        }
+    }
+
+    fn set_frame_active(&mut self, pass: &mut EventPass<'_>, id: Option<WindowId>, active: bool) {
+        let Some(window) = id.and_then(|id| self.window_mut(id)) else {
+            return;
+        };
+        if !window.frame.inner.exists() {
+            return;
+        }
+        pass.typed_child_mut(&mut window.frame).active = active;
+        pass.request_child_render(window.frame.id());
+    }
+
+    /// Keep enough of the window's title bar inside the desktop that it can
+    /// still be grabbed.
+    fn clamp_position(&self, bounds: Aabb2D) -> Aabb2D {
+        let desktop = self.desktop_size();
+        let size = bounds.size();
+        let x = bounds
+            .min
+            .x
+            .min(desktop.x - MIN_VISIBLE_WIDTH)
+            .max(MIN_VISIBLE_WIDTH - size.x);
+        let y = bounds.min.y.min(desktop.y - self.title_height()).max(0.0);
+
+        Aabb2D::from_size_position(size, Xy::new(x, y))
+    }
+
+    fn dragged_bounds(&self, drag: &WindowDrag, position: Xy<f32>) -> Aabb2D {
+        let delta = position - drag.origin;
+        let start = drag.start_bounds;
+        let Some(edges) = drag.edges else {
+            return self
+                .clamp_position(Aabb2D::from_size_position(start.size(), start.min + delta));
+        };
+
+        let min_size = self.min_window_size();
+        let desktop = self.desktop_size();
+        let mut bounds = start;
+        if edges.left {
+            bounds.min.x = (start.min.x + delta.x).min(start.max.x - min_size.x);
+        }
+        if edges.right {
+            bounds.max.x = (start.max.x + delta.x).max(start.min.x + min_size.x);
+        }
+        if edges.top {
+            bounds.min.y = (start.min.y + delta.y)
+                .min(start.max.y - min_size.y)
+                .max(0.0);
+        }
+        if edges.bottom {
+            bounds.max.y = (start.max.y + delta.y)
+                .max(start.min.y + min_size.y)
+                .min(desktop.y.max(start.min.y + min_size.y));
+        }
+
+        bounds
+    }
+}
+
+impl Window {
+    fn id(&self) -> WindowId {
+        WindowId(self.frame.id())
+    }
+}
+
+impl Element for WindowManager {
+    fn children_ids(&self) -> Vec<u64> {
+        self.stacked()
+            .filter(|window| !window.minimized)
+            .map(|window| window.frame.id())
+            .collect()
+    }
+
+    fn update_children(&mut self, pass: &mut UpdatePass<'_>) {
+        for frame in self.removed.drain(..) {
+            pass.remove_child(frame);
+        }
+        for window in &mut self.windows {
+            pass.update_child(&mut window.frame.inner);
+            // NOTE: Windows can be opened and closed outside of an event, so the frames
+            //       are told which of them is active here too.
+            let active = self.active == Some(window.id());
+            let frame = pass.typed_child_mut(&mut window.frame);
+            if frame.active != active {
+                frame.active = active;
+                pass.request_child_render(window.frame.id());
+            }
+        }
+    }
+
+    fn render(&mut self, pass: &mut RenderPass<'_>) {
+        let bounds = pass.bounds();
+        let zoom = pass.zoom();
+        let to_physical = |area: Aabb2D| {
+            Aabb2D::from_size_position(
+                Xy::new(area.size().x * zoom, area.size().y * zoom),
+                bounds.position() + Xy::new(area.min.x * zoom, area.min.y * zoom),
+            )
+        };
+        let desktop_height = self.desktop_size().y;
+
+        pass.fill_quad(
+            to_physical(Aabb2D::new(0.0, desktop_height, self.size.x, self.size.y)),
+            pass.color("surface.sunken"),
+            0.0,
+            Rgba::NONE,
+        );
+        pass.fill_quad(
+            to_physical(Aabb2D::new(
+                0.0,
+                desktop_height,
+                self.size.x,
+                desktop_height + BORDER_WIDTH,
+            )),
+            pass.color("border"),
+            0.0,
+            Rgba::NONE,
+        );
+
+        for (index, window) in self.windows.iter().enumerate() {
+            let left = index as f32 * TASKBAR_BUTTON_WIDTH;
+            if left >= self.size.x {
+                break;
+            }
+            let button = Aabb2D::new(
+                left + BORDER_WIDTH,
+                desktop_height + BORDER_WIDTH * 2.0,
+                left + TASKBAR_BUTTON_WIDTH - BORDER_WIDTH,
+                self.size.y - BORDER_WIDTH,
+            );
+            let active = self.active == Some(window.id()) && !window.minimized;
+            if active {
+                pass.fill_quad(
+                    to_physical(button),
+                    pass.color("surface.raised"),
+                    1.0,
+                    pass.color("accent"),
+                );
+            }
+            pass.fill_text(
+                &window.title,
+                to_physical(Aabb2D::new(
+                    button.min.x + self.title_padding,
+                    button.min.y + self.title_padding - BORDER_WIDTH * 2.0,
+                    button.max.x - self.title_padding,
+                    button.max.y - self.title_padding + BORDER_WIDTH,
+                )),
+                if window.minimized {
+                    pass.color("text.muted")
+                } else {
+                    pass.color("text")
+                },
+                self.font_size,
+            );
+        }
+    }
+
+    fn layout(&mut self, pass: &mut LayoutPass<'_>) {
+        self.size = pass.size;
+        self.title_padding = pass.metric("spacing");
+
+        for index in 0..self.windows.len() {
+            let bounds = self.clamp_position(self.windows[index].bounds);
+            let window = &mut self.windows[index];
+            window.bounds = bounds;
+            if window.minimized {
+                continue;
+            }
+            pass.do_layout(&mut window.frame.inner, bounds.size());
+            pass.place_child(&mut window.frame.inner, bounds.min);
+        }
+
+        pass.clear_baseline_offset();
+    }
+
+    fn measure(
+        &mut self,
+        context: &mut MeasureContext<'_>,
+        axis: Axis,
+        length_request: LengthRequest,
+        _cross_length: Option<f32>,
+    ) -> f32 {
+        self.title_padding = context.metric("spacing");
+        if let LengthRequest::FitContent(space) = length_request {
+            return space;
+        }
+
+        let windows_length = self
+            .windows
+            .iter()
+            .map(|window| window.bounds.max.value_for_axis(axis))
+            .fold(0.0, f32::max);
+        match axis {
+            Axis::Horizontal => windows_length,
+            Axis::Vertical => windows_length + self.title_height(),
+        }
+    }
+
+    fn cursor_icon(&self) -> CursorIcon {
+        match self.drag.as_ref().map(|drag| drag.edges) {
+            Some(None) => CursorIcon::Grabbing,
+            Some(Some(edges)) => edges.cursor_icon(),
+            None => CursorIcon::Default,
+        }
+    }
+
+    // NOTE: Clicks that an application handles itself never reach the manager, so
+    //       windows are also activated when anything inside them gains focus.
+    fn on_child_focus(&mut self, pass: &mut EventPass<'_>, focused: bool) {
+        if !focused {
+            return;
+        }
+        let focused_window = self.windows.iter().find_map(|window| {
+            pass.child(window.frame.id())
+                .is_some_and(|frame| frame.element.state.focused)
+                .then(|| window.id())
+        });
+        if let Some(id) = focused_window {
+            self.activate(pass, id);
+        }
+    }
+
+    fn on_pointer_event(&mut self, pass: &mut EventPass<'_>, event: &PointerEvent) {
+        match event {
+            PointerEvent::Down {
+                button: PointerButton::Primary,
+                position,
+                ..
+            } => {
+                let position = pass.local_position(*position);
+                if let Some(id) = self.taskbar_button_at(position) {
+                    let on_top = self.topmost_visible() == Some(id);
+                    if self.active == Some(id) && on_top {
+                        self.minimize(pass, id);
+                    } else {
+                        self.activate(pass, id);
+                    }
+                    pass.set_handled();
+                    return;
+                }
+                let Some((id, zone)) = self.window_at(position) else {
+                    return;
+                };
+
+                self.activate(pass, id);
+                match zone {
+                    FrameZone::Close => self.close_from_event(pass, id),
+                    FrameZone::Minimize => self.minimize(pass, id),
+                    FrameZone::TitleBar | FrameZone::Edges(_) => {
+                        let Some(window) = self.window(id) else {
+                            return;
+                        };
+                        self.drag = Some(WindowDrag {
+                            id,
+                            edges: match zone {
+                                FrameZone::Edges(edges) => Some(edges),
+                                _ => None,
+                            },
+                            origin: position,
+                            start_bounds: window.bounds,
+                        });
+                        pass.capture_pointer();
+                    }
+                    FrameZone::Content => {}
+                }
+                pass.set_handled();
+            }
+            PointerEvent::Move { position, .. } => {
+                let Some(drag) = &self.drag else {
+                    return;
+                };
+                let position = pass.local_position(*position);
+                let bounds = self.dragged_bounds(drag, position);
+                let id = drag.id;
+                if let Some(window) = self.window_mut(id)
+                    && window.bounds != bounds
+                {
+                    window.bounds = bounds;
+                    pass.request_layout();
+                    pass.request_render();
+                    pass.add_damage(pass.bounds());
+                }
+            }
+            PointerEvent::Up {
+                button: PointerButton::Primary,
+                ..
+            } => {
+                if self.drag.take().is_some() {
+                    pass.release_pointer();
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+
+
+/// The edges of a window that are being dragged to resize it.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+struct Edges {
+    left: bool,
+    top: bool,
+    right: bool,
+    bottom: bool,
+}
+
+impl Edges {
+    fn cursor_icon(self) -> CursorIcon {
+        let horizontal = self.left || self.right;
+        let vertical = self.top || self.bottom;
+        match (horizontal, vertical) {
+            (true, true) => CursorIcon::AllScroll,
+            (true, false) => CursorIcon::SplitH,
+            (false, true) => CursorIcon::SplitV,
+            (false, false) => CursorIcon::Default,
+        }
+    }
+}
+
+/// A part of a window's frame.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum FrameZone {
+    Content,
+    TitleBar,
+    Minimize,
+    Close,
+    Edges(Edges),
+}
+
+impl FrameZone {
+    /// The part of a frame with the given size under the given position,
+    /// relative to the frame.
+    fn at(size: Xy<f32>, title_height: f32, position: Xy<f32>) -> Self {
+        let edges = Edges {
+            left: position.x < RESIZE_MARGIN,
+            top: position.y < RESIZE_MARGIN,
+            right: position.x >= size.x - RESIZE_MARGIN,
+            bottom: position.y >= size.y - RESIZE_MARGIN,
+        };
+        if edges != Edges::default() {
+            return Self::Edges(edges);
+        }
+        if position.y >= title_height {
+            return Self::Content;
+        }
+
+        let button = ((size.x - position.x) / title_height) as usize;
+        match button {
+            0 => Self::Close,
+            1 => Self::Minimize,
+            _ => Self::TitleBar,
+        }
+    }
+}
+
+/// The title bar and border around a window's content.
+struct WindowFrame {
+    title: String,
+    active: bool,
+    font_size: f32,
+    /// The theme's `"spacing"` as of the last layout.
+    title_padding: f32,
+    content: ChildElement,
+    size: Xy<f32>,
+    hovered_zone: FrameZone,
+}
+
+impl WindowFrame {
+    fn title_height(&self) -> f32 {
+        self.font_size + self.title_padding * 2.0
+    }
+}
+
+impl Element for WindowFrame {
+    fn children_ids(&self) -> Vec<u64> {
+        vec![self.content.id()]
+    }
+
+    fn update_children(&mut self, pass: &mut UpdatePass<'_>) {
+        pass.update_child(&mut self.content);
+    }
+
+    fn render(&mut self, pass: &mut RenderPass<'_>) {
+        let bounds = pass.bounds();
+        let zoom = pass.zoom();
+        let to_physical = |area: Aabb2D| {
+            Aabb2D::from_size_position(
+                Xy::new(area.size().x * zoom, area.size().y * zoom),
+                bounds.position() + Xy::new(area.min.x * zoom, area.min.y * zoom),
+            )
+        };
+        let width = bounds.size().x / zoom;
+        let title_height = self.title_height();
+
+        pass.fill_quad(
+            bounds,
+            pass.color("surface"),
+            BORDER_WIDTH * zoom,
+            if self.active {
+                pass.color("accent")
+            } else {
+                pass.color("border")
+            },
+        );
+        pass.fill_quad(
+            to_physical(Aabb2D::new(
+                BORDER_WIDTH,
+                BORDER_WIDTH,
+                width - BORDER_WIDTH,
+                title_height,
+            )),
+            if self.active {
+                pass.color("surface.raised")
+            } else {
+                pass.color("surface.sunken")
+            },
+            0.0,
+            Rgba::NONE,
+        );
+
+        let text_color = if self.active {
+            pass.color("text")
+        } else {
+            pass.color("text.muted")
+        };
+        pass.fill_text(
+            &self.title,
+            to_physical(Aabb2D::new(
+                self.title_padding,
+                self.title_padding,
+                (width - title_height * 2.0).max(self.title_padding),
+                title_height - self.title_padding,
+            )),
+            text_color,
+            self.font_size,
+        );
+        for (index, label) in [CLOSE_LABEL, MINIMIZE_LABEL].into_iter().enumerate() {
+            let right = width - title_height * index as f32;
+            let button = Aabb2D::new(right - title_height, 0.0, right, title_height);
+            let hovered = match self.hovered_zone {
+                FrameZone::Close => index == 0,
+                FrameZone::Minimize => index == 1,
+                _ => false,
+            };
+            if hovered {
+                pass.fill_quad(
+                    to_physical(button),
+                    if index == 0 {
+                        pass.color("error")
+                    } else {
+                        pass.color("surface.sunken")
+                    },
+                    0.0,
+                    Rgba::NONE,
+                );
+            }
+            pass.fill_text(
+                label,
+                to_physical(Aabb2D::new(
+                    button.min.x + self.title_padding,
+                    self.title_padding,
+                    button.max.x - self.title_padding,
+                    title_height - self.title_padding,
+                )),
+                text_color,
+                self.font_size,
+            );
+        }
+    }
+
+    fn layout(&mut self, pass: &mut LayoutPass<'_>) {
+        let size = pass.size;
+        self.size = size;
+        self.title_padding = pass.metric("spacing");
+        let title_height = self.title_height();
+        let content_size = Xy::new(
+            (size.x - BORDER_WIDTH * 2.0).max(0.0),
+            (size.y - title_height - BORDER_WIDTH).max(0.0),
+        );
+
+        pass.do_layout(&mut self.content, content_size);
+        pass.place_child(&mut self.content, Xy::new(BORDER_WIDTH, title_height));
+        pass.clear_baseline_offset();
+    }
+
+    fn measure(
+        &mut self,
+        context: &mut MeasureContext<'_>,
+        axis: Axis,
+        length_request: LengthRequest,
+        cross_length: Option<f32>,
+    ) -> f32 {
+        let fallback_length: Length = length_request.into();
+        let content_length =
+            context.resolve_length(self.content.id(), axis, fallback_length, cross_length);
+        match axis {
+            Axis::Horizontal => content_length + BORDER_WIDTH * 2.0,
+            Axis::Vertical => content_length + self.title_height() + BORDER_WIDTH,
+        }
+    }
+
+    fn cursor_icon(&self) -> CursorIcon {
+        match self.hovered_zone {
+            FrameZone::Edges(edges) => edges.cursor_icon(),
+            FrameZone::TitleBar => CursorIcon::Grab,
+            FrameZone::Close | FrameZone::Minimize => CursorIcon::PointingHand,
+            FrameZone::Content => CursorIcon::Default,
+        }
+    }
+
+    fn on_hover(&mut self, pass: &mut EventPass<'_>, hovered: bool) {
+        if !hovered && self.hovered_zone != FrameZone::Content {
+            self.hovered_zone = FrameZone::Content;
+            pass.request_render();
+        }
+    }
+
+    fn on_pointer_event(&mut self, pass: &mut EventPass<'_>, event: &PointerEvent) {
+        // NOTE: Only the hovered part of the frame is tracked here, for its cursor and
+        //       buttons. Everything else is left to bubble up to the manager.
+        if let PointerEvent::Move { position, .. } = event {
+            let position = pass.local_position(*position);
+            let zone = FrameZone::at(self.size, self.title_height(), position);
+            if zone != self.hovered_zone {
+                self.hovered_zone = zone;
+                pass.request_render();
+            }
+        }
+    }
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn frame_zones() {
+        let size = Xy::new(300.0, 200.0);
+        let zone = |x, y| FrameZone::at(size, 26.0, Xy::new(x, y));
+
+        assert_eq!(zone(150.0, 100.0), FrameZone::Content);
+        assert_eq!(zone(100.0, 10.0), FrameZone::TitleBar);
+        assert_eq!(zone(290.0, 10.0), FrameZone::Close);
+        assert_eq!(zone(260.0, 10.0), FrameZone::Minimize);
+        assert_eq!(
+            zone(298.0, 198.0),
+            FrameZone::Edges(Edges {
+                right: true,
+                bottom: true,
+                ..Edges::default()
+            })
+        );
+    }
+}
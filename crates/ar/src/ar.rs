@@ -0,0 +1,369 @@
+//! # Archives (`ar`)
+//!
+//! Static libraries (`.a`) and Rust libraries (`.rlib`) are both archives of
+//! object files. This supports the GNU format, with its `/` (and `/SYM64/`)
+//! symbol table and `//` long name table, and BSD-style long names.
+
+#![no_std]
+
+/// The bytes every archive starts with.
+pub const MAGIC: &[u8; 8] = b"!<arch>\n";
+
+const HEADER_SIZE: usize = 60;
+const HEADER_END: &[u8; 2] = b"`\n";
+
+
+
+/// A parsed archive.
+#[derive(Clone, Copy, Debug)]
+pub struct Archive<'a> {
+    input: &'a [u8],
+    /// The contents of the `/` or `/SYM64/` member.
+    symbol_table: Option<&'a [u8]>,
+    /// Whether the symbol table uses 64-bit offsets (`/SYM64/`).
+    symbol_table_64: bool,
+    /// The contents of the `//` member.
+    long_names: Option<&'a [u8]>,
+}
+
+impl<'a> Archive<'a> {
+    pub fn new(input: &'a [u8]) -> Result<Self, &'static str> {
+        if !input.starts_with(MAGIC) {
+            return Err("not an archive");
+        }
+
+        let mut archive = Self {
+            input,
+            symbol_table: None,
+            symbol_table_64: false,
+            long_names: None,
+        };
+
+        // The special members always come first.
+        let mut offset = MAGIC.len();
+        while offset < input.len() {
+            let header = Header::parse(input, offset)?;
+            match header.raw_name {
+                b"/" => archive.symbol_table = Some(header.data),
+                b"/SYM64/" => {
+                    archive.symbol_table = Some(header.data);
+                    archive.symbol_table_64 = true;
+                }
+                b"//" => archive.long_names = Some(header.data),
+                _ => break,
+            }
+            offset = header.next_offset;
+        }
+
+        Ok(archive)
+    }
+
+    /// Iterate over the regular members of this archive, skipping the symbol
+    /// and long name tables.
+    pub fn members(&self) -> impl Iterator<Item = Result<Member<'a>, &'static str>> + '_ {
+        let mut offset = MAGIC.len();
+        core::iter::from_fn(move || {
+            loop {
+                if offset >= self.input.len() {
+                    return None;
+                }
+                let member = self.member_at(offset);
+                match member {
+                    Ok(Some((member, next_offset))) => {
+                        offset = next_offset;
+                        return Some(Ok(member));
+                    }
+                    Ok(None) => {
+                        offset = Header::parse(self.input, offset).ok()?.next_offset;
+                    }
+                    Err(error) => {
+                        offset = self.input.len();
+                        return Some(Err(error));
+                    }
+                }
+            }
+        })
+    }
+
+    /// Get the regular member whose header starts at the given offset, like
+    /// the offsets in the symbol table.
+    pub fn member(&self, offset: usize) -> Result<Member<'a>, &'static str> {
+        self.member_at(offset)?
+            .map(|(member, _next_offset)| member)
+            .ok_or("not a regular archive member")
+    }
+
+    /// Iterate over the symbol table, which maps each global symbol to the
+    /// offset of the [member](Self::member) that defines it.
+    ///
+    /// This is empty if the archive doesn't have a symbol table.
+    pub fn symbols(&self) -> SymbolIter<'a> {
+        SymbolIter {
+            table: self.symbol_table.unwrap_or(&[]),
+            word_size: if self.symbol_table_64 { 8 } else { 4 },
+            index: 0,
+            name_offset: None,
+        }
+    }
+
+    /// Get the member at the given offset, and the offset of the next one, or
+    /// `None` if it's a symbol or long name table.
+    fn member_at(&self, offset: usize) -> Result<Option<(Member<'a>, usize)>, &'static str> {
+        let header = Header::parse(self.input, offset)?;
+        let (name, data) = match header.raw_name {
+            b"/" | b"/SYM64/" | b"//" => return Ok(None),
+            // GNU long names are an offset into the long name table, where they end with
+            // "/\n".
+            [b'/', index @ ..] => {
+                let index = parse_decimal(index).ok_or("invalid archive member name")?;
+                let name = self
+                    .long_names
+                    .and_then(|names| names.get(index..))
+                    .and_then(|names| {
+                        let end = names.windows(2).position(|window| window == b"/\n")?;
+                        Some(&names[..end])
+                    })
+                    .ok_or("archive member's long name is out of bounds")?;
+                (name, header.data)
+            }
+            // BSD long names come right before the member's data.
+            [b'#', b'1', b'/', len @ ..] => {
+                let len = parse_decimal(len).ok_or("invalid archive member name")?;
+                if len > header.data.len() {
+                    return Err("archive member's long name is out of bounds");
+                }
+                let (name, data) = header.data.split_at(len);
+                let name_len = name.iter().position(|byte| *byte == 0).unwrap_or(len);
+                (&name[..name_len], data)
+            }
+            name => (name.strip_suffix(b"/").unwrap_or(name), header.data),
+        };
+
+        let name = core::str::from_utf8(name).map_err(|_| "invalid archive member name")?;
+        Ok(Some((Member { name, offset, data }, header.next_offset)))
+    }
+}
+
+/// An iterator over the symbol table of an [`Archive`], see
+/// [`Archive::symbols`].
+pub struct SymbolIter<'a> {
+    table: &'a [u8],
+    word_size: usize,
+    index: usize,
+    /// The offset of the next symbol's name, once the symbol count is known.
+    name_offset: Option<usize>,
+}
+
+impl<'a> SymbolIter<'a> {
+    /// Read a big-endian word from the table.
+    fn word(&self, index: usize) -> Result<usize, &'static str> {
+        let offset = index * self.word_size;
+        let bytes = self
+            .table
+            .get(offset..offset + self.word_size)
+            .ok_or("archive symbol table is out of bounds")?;
+
+        Ok(bytes
+            .iter()
+            .fold(0, |word, byte| (word << 8) | *byte as usize))
+    }
+
+    fn next_symbol(&mut self) -> Result<Option<(&'a str, usize)>, &'static str> {
+        if self.table.is_empty() {
+            return Ok(None);
+        }
+        let count = self.word(0)?;
+        if self.index >= count {
+            return Ok(None);
+        }
+
+        // The member offsets come first, followed by the names in the same order.
+        let name_offset = self.name_offset.unwrap_or((count + 1) * self.word_size);
+        let member_offset = self.word(self.index + 1)?;
+        let name = self
+            .table
+            .get(name_offset..)
+            .and_then(|rest| Some(&rest[..rest.iter().position(|byte| *byte == 0)?]))
+            .and_then(|name| core::str::from_utf8(name).ok())
+            .ok_or("invalid name in archive symbol table")?;
+
+        self.index += 1;
+        self.name_offset = Some(name_offset + name.len() + 1);
+
+        Ok(Some((name, member_offset)))
+    }
+}
+
+impl<'a> Iterator for SymbolIter<'a> {
+    type Item = Result<(&'a str, usize), &'static str>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let symbol = self.next_symbol().transpose();
+        if matches!(symbol, Some(Err(_))) {
+            // Don't keep reading a malformed table.
+            self.table = &[];
+        }
+
+        symbol
+    }
+}
+
+/// A file within an [`Archive`].
+#[derive(Clone, Copy, Debug)]
+pub struct Member<'a> {
+    pub name: &'a str,
+    /// The offset of this member's header into the archive.
+    pub offset: usize,
+    pub data: &'a [u8],
+}
+
+struct Header<'a> {
+    /// The name field, without its padding.
+    raw_name: &'a [u8],
+    data: &'a [u8],
+    /// The offset of the following header, since data is padded to an even
+    /// length.
+    next_offset: usize,
+}
+
+impl<'a> Header<'a> {
+    fn parse(input: &'a [u8], offset: usize) -> Result<Self, &'static str> {
+        let header = input
+            .get(offset..offset + HEADER_SIZE)
+            .ok_or("archive member header is out of bounds")?;
+        if &header[58..60] != HEADER_END {
+            return Err("invalid archive member header");
+        }
+
+        let raw_name = trim_padding(&header[0..16]);
+        let size =
+            parse_decimal(trim_padding(&header[48..58])).ok_or("invalid archive member size")?;
+        let data_start = offset + HEADER_SIZE;
+        let data = input
+            .get(data_start..data_start + size)
+            .ok_or("archive member is out of bounds")?;
+
+        Ok(Self {
+            raw_name,
+            data,
+            next_offset: data_start + size.next_multiple_of(2),
+        })
+    }
+}
+
+fn trim_padding(field: &[u8]) -> &[u8] {
+    let len = field
+        .iter()
+        .rposition(|byte| *byte != b' ')
+        .map_or(0, |index| index + 1);
+    &field[..len]
+}
+
+fn parse_decimal(digits: &[u8]) -> Option<usize> {
+    if digits.is_empty() {
+        return None;
+    }
+
+    digits.iter().try_fold(0usize, |value, digit| {
+        digit
+            .is_ascii_digit()
+            .then(|| value.checked_mul(10)?.checked_add((digit - b'0') as usize))
+            .flatten()
+    })
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    extern crate std;
+
+    use std::{format, vec::Vec};
+
+    fn push_member(archive: &mut Vec<u8>, name: &str, data: &[u8]) -> usize {
+        let offset = archive.len();
+        archive.extend_from_slice(
+            format!(
+                "{name:<16}{:<12}{:<6}{:<6}{:<8}{:<10}`\n",
+                0,
+                0,
+                0,
+                644,
+                data.len()
+            )
+            .as_bytes(),
+        );
+        archive.extend_from_slice(data);
+        if data.len() % 2 == 1 {
+            archive.push(b'\n');
+        }
+        offset
+    }
+
+    /// An archive with a symbol table, a long name, and an odd-sized member.
+    fn build_archive() -> Vec<u8> {
+        let names = b"first\0second\0";
+        let symbol_table_size = 4 + 4 * 2 + names.len();
+
+        // Build everything after the symbol table first, so that the offsets of its
+        // members are known.
+        let mut rest = Vec::new();
+        push_member(&mut rest, "//", b"a_very_long_member_name.o/\n");
+        let short = push_member(&mut rest, "short.o/", b"abc");
+        let long = push_member(&mut rest, "/0", b"defg");
+        let base = MAGIC.len() + HEADER_SIZE + symbol_table_size.next_multiple_of(2);
+
+        let mut symbol_table = Vec::new();
+        symbol_table.extend_from_slice(&2u32.to_be_bytes());
+        symbol_table.extend_from_slice(&((base + short) as u32).to_be_bytes());
+        symbol_table.extend_from_slice(&((base + long) as u32).to_be_bytes());
+        symbol_table.extend_from_slice(names);
+
+        let mut archive = MAGIC.to_vec();
+        push_member(&mut archive, "/", &symbol_table);
+        archive.extend_from_slice(&rest);
+
+        archive
+    }
+
+    #[test]
+    fn reading_members() {
+        let bytes = build_archive();
+        let archive = Archive::new(&bytes).unwrap();
+        let members = archive.members().collect::<Result<Vec<_>, _>>().unwrap();
+
+        assert_eq!(members.len(), 2);
+        assert_eq!(members[0].name, "short.o");
+        assert_eq!(members[0].data, b"abc");
+        assert_eq!(members[1].name, "a_very_long_member_name.o");
+        assert_eq!(members[1].data, b"defg");
+    }
+
+    #[test]
+    fn reading_symbol_table() {
+        let bytes = build_archive();
+        let archive = Archive::new(&bytes).unwrap();
+        let symbols = archive.symbols().collect::<Result<Vec<_>, _>>().unwrap();
+
+        assert_eq!(symbols.len(), 2);
+        assert_eq!(symbols[0].0, "first");
+        assert_eq!(archive.member(symbols[0].1).unwrap().name, "short.o");
+        assert_eq!(symbols[1].0, "second");
+        assert_eq!(
+            archive.member(symbols[1].1).unwrap().name,
+            "a_very_long_member_name.o",
+        );
+    }
+
+    #[test]
+    fn rejecting_invalid_input() {
+        assert!(Archive::new(b"\x7fELF").is_err());
+
+        let mut bytes = build_archive();
+        bytes.truncate(bytes.len() - 2);
+        let archive = Archive::new(&bytes).unwrap();
+        assert!(archive.members().any(|member| member.is_err()));
+    }
+}
@@ -98,6 +98,95 @@ impl<'a> ElfFile<'a> {
             _ => Err("no symbol table found, file may have been stripped"),
         }
     }
+
+    /// Get the entries of the dynamic section (`PT_DYNAMIC`) of a shared object
+    /// or dynamically linked executable.
+    pub fn get_dynamic_entries(&self) -> Result<&'a [Dynamic], &'static str> {
+        let header = self
+            .program_iter()
+            .find(|header| header.get_type() == Ok(ProgramHeaderType::Dynamic))
+            .ok_or("no dynamic segment")?;
+
+        let entries: &[Dynamic] = pod::read_array(header.raw_data(self)?);
+        let len = entries
+            .iter()
+            .position(|entry| entry.tag == DT_NULL)
+            .unwrap_or(entries.len());
+
+        Ok(&entries[..len])
+    }
+
+    /// Get the value of the first dynamic entry with the given tag, if there
+    /// is one.
+    pub fn get_dynamic_value(&self, tag: i64) -> Result<Option<u64>, &'static str> {
+        Ok(self
+            .get_dynamic_entries()?
+            .iter()
+            .find(|entry| entry.tag == tag)
+            .map(|entry| entry.value))
+    }
+
+    /// Get a string from the dynamic string table (`DT_STRTAB`), such as the
+    /// name of a dynamic symbol or a `DT_NEEDED` entry.
+    pub fn get_dynamic_string(&self, index: u64) -> Result<&'a str, &'static str> {
+        let addr = self
+            .get_dynamic_value(DT_STRTAB)?
+            .ok_or("no dynamic string table")?;
+        let size = self
+            .get_dynamic_value(DT_STRSZ)?
+            .ok_or("no dynamic string table size")?;
+        let table = self.get_data_at_address(addr, size as usize)?;
+
+        Ok(pod::read_str(
+            table
+                .get(index as usize..)
+                .ok_or("dynamic string index is out of bounds")?,
+        ))
+    }
+
+    /// Get the relocation tables that the dynamic section points to, which are
+    /// `DT_RELA` and the PLT's `DT_JMPREL`.
+    pub fn get_dynamic_relocations(&self) -> Result<[&'a [Rela]; 2], &'static str> {
+        if self.get_dynamic_value(DT_REL)?.is_some()
+            || self
+                .get_dynamic_value(DT_PLTREL)?
+                .is_some_and(|kind| kind != DT_RELA as u64)
+        {
+            return Err("dynamic relocations without addends aren't supported");
+        }
+
+        let table = |addr_tag, size_tag| -> Result<&'a [Rela], &'static str> {
+            match (
+                self.get_dynamic_value(addr_tag)?,
+                self.get_dynamic_value(size_tag)?,
+            ) {
+                (Some(addr), Some(size)) => Ok(pod::read_array(
+                    self.get_data_at_address(addr, size as usize)?,
+                )),
+                _ => Ok(&[]),
+            }
+        };
+
+        Ok([table(DT_RELA, DT_RELASZ)?, table(DT_JMPREL, DT_PLTRELSZ)?])
+    }
+
+    /// Get the `len` bytes of the file that are loaded at the given virtual
+    /// address by a `PT_LOAD` segment.
+    pub fn get_data_at_address(&self, addr: u64, len: usize) -> Result<&'a [u8], &'static str> {
+        let header = self
+            .program_iter()
+            .filter(|header| header.get_type() == Ok(ProgramHeaderType::Load))
+            .find(|header| {
+                (header.virtual_addr..header.virtual_addr + header.file_size).contains(&addr)
+            })
+            .ok_or("address isn't loaded from the file")?;
+        let start = (addr - header.virtual_addr) as usize;
+
+        header
+            .raw_data(self)?
+            .get(start..start + len)
+            .ok_or("data extends beyond the end of its segment")
+    }
 }
 
 
@@ -370,9 +459,7 @@ impl SectionHeader {
                     SectionData::Rel(pod::read_array(data))
                 }
                 SectionHeaderType::Dynamic => {
-                    todo!()
-                    // let data = self.raw_data(file);
-                    // SectionData::Dynamic(read_array(data))
+                    SectionData::Dynamic(pod::read_array(self.raw_data(file)))
                 }
                 SectionHeaderType::Group => {
                     let data = self.raw_data(file);
@@ -514,7 +601,7 @@ pub enum SectionData<'a> {
     SymTabShIndex(&'a [u32]),
     Rela(&'a [Rela]),
     Rel(&'a [Rel]),
-    // Dynamic(&'a [Dynamic]),
+    Dynamic(&'a [Dynamic]),
 }
 
 #[derive(Debug)]
@@ -549,6 +636,51 @@ impl Rela {
     }
 }
 
+/// An entry of the dynamic section, see [`ElfFile::get_dynamic_entries`].
+#[derive(Debug)]
+#[repr(C)]
+pub struct Dynamic {
+    tag: i64,
+    value: u64,
+}
+
+impl Dynamic {
+    /// Get the tag of this entry, one of the `DT_*` constants.
+    pub fn get_tag(&self) -> i64 {
+        self.tag
+    }
+
+    /// Get the value of this entry, which is an address or a size depending on
+    /// its tag.
+    pub fn get_value(&self) -> u64 {
+        self.value
+    }
+}
+
+pub const DT_NULL: i64 = 0;
+pub const DT_NEEDED: i64 = 1;
+pub const DT_PLTRELSZ: i64 = 2;
+pub const DT_HASH: i64 = 4;
+pub const DT_STRTAB: i64 = 5;
+pub const DT_SYMTAB: i64 = 6;
+pub const DT_RELA: i64 = 7;
+pub const DT_RELASZ: i64 = 8;
+pub const DT_RELAENT: i64 = 9;
+pub const DT_STRSZ: i64 = 10;
+pub const DT_SYMENT: i64 = 11;
+pub const DT_INIT: i64 = 12;
+pub const DT_FINI: i64 = 13;
+pub const DT_SONAME: i64 = 14;
+pub const DT_REL: i64 = 17;
+pub const DT_PLTREL: i64 = 20;
+pub const DT_TEXTREL: i64 = 22;
+pub const DT_JMPREL: i64 = 23;
+pub const DT_INIT_ARRAY: i64 = 25;
+pub const DT_FINI_ARRAY: i64 = 26;
+pub const DT_INIT_ARRAYSZ: i64 = 27;
+pub const DT_FINI_ARRAYSZ: i64 = 28;
+pub const DT_GNU_HASH: i64 = 0x6ffffef5;
+
 #[derive(Clone, Copy, Debug, Eq, PartialEq)]
 pub enum SymbolType {
     NoType,
@@ -640,6 +772,26 @@ impl SymbolTableEntry {
         }
     }
 
+    /// Whether this symbol is visible to other objects, which is the case for
+    /// both global and weak symbols.
+    pub fn is_global(&self) -> bool {
+        matches!(
+            self.get_binding(),
+            Ok(SymbolBinding::Global | SymbolBinding::Weak),
+        )
+    }
+
+    /// Whether this symbol is weak. Weak definitions yield to global ones, and
+    /// weak references that are never defined resolve to zero.
+    pub fn is_weak(&self) -> bool {
+        self.get_binding() == Ok(SymbolBinding::Weak)
+    }
+
+    /// Whether this symbol is defined in another object.
+    pub const fn is_undefined(&self) -> bool {
+        self.shndx == SHN_UNDEF
+    }
+
     pub fn get_name<'a>(&'a self, file: &ElfFile<'a>) -> Result<&'a str, &'static str> {
         file.get_string(self.name)
     }
@@ -771,6 +923,14 @@ impl ProgramHeader {
         }
     }
 
+    /// Get the bytes of this segment that are stored in the file, which can be
+    /// fewer than it takes up in memory.
+    pub fn raw_data<'a>(&self, file: &ElfFile<'a>) -> Result<&'a [u8], &'static str> {
+        file.input
+            .get(self.offset as usize..(self.offset + self.file_size) as usize)
+            .ok_or("segment extends beyond the end of the file")
+    }
+
     pub fn is_execute(&self) -> bool {
         self.flags & PROGRAM_FLAG_EXECUTE == PROGRAM_FLAG_EXECUTE
     }
@@ -817,4 +977,66 @@ unsafe impl pod::Pod for SectionHeader {}
 unsafe impl pod::Pod for ProgramHeader {}
 unsafe impl pod::Pod for Rel {}
 unsafe impl pod::Pod for Rela {}
+unsafe impl pod::Pod for Dynamic {}
 unsafe impl pod::Pod for SymbolTableEntry {}
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const STB_LOCAL: u8 = 0;
+    const STB_GLOBAL: u8 = 1;
+    const STB_WEAK: u8 = 2;
+    const STT_NOTYPE: u8 = 0;
+    const STT_OBJECT: u8 = 1;
+    const STT_FUNC: u8 = 2;
+
+    fn symbol(binding: u8, kind: u8, shndx: u16) -> SymbolTableEntry {
+        SymbolTableEntry {
+            name: 0,
+            info: (binding << 4) | kind,
+            other: 0,
+            shndx,
+            value: 0,
+            size: 0,
+        }
+    }
+
+    #[test]
+    fn weak_definitions() {
+        // `#[linkage = "weak"] fn ...` is a weak function defined in its own section.
+        let entry = symbol(STB_WEAK, STT_FUNC, 3);
+
+        assert_eq!(entry.get_binding(), Ok(SymbolBinding::Weak));
+        assert_eq!(entry.get_type(), Ok(SymbolType::Func));
+        assert!(entry.is_global());
+        assert!(entry.is_weak());
+        assert!(!entry.is_undefined());
+    }
+
+    #[test]
+    fn weak_references() {
+        // `#[linkage = "extern_weak"] static ...` is a weak reference without a type,
+        // to a symbol that might not be defined anywhere.
+        let entry = symbol(STB_WEAK, STT_NOTYPE, SHN_UNDEF);
+
+        assert!(entry.is_weak());
+        assert!(entry.is_undefined());
+
+        // Strong references are undefined too, but aren't weak.
+        let entry = symbol(STB_GLOBAL, STT_OBJECT, SHN_UNDEF);
+        assert!(entry.is_global());
+        assert!(!entry.is_weak());
+        assert!(entry.is_undefined());
+    }
+
+    #[test]
+    fn local_symbols() {
+        let entry = symbol(STB_LOCAL, STT_OBJECT, 5);
+
+        assert!(!entry.is_global());
+        assert!(!entry.is_weak());
+    }
+}
@@ -0,0 +1,211 @@
+//! # Lazy Binding Stubs
+//!
+//! The code of the stubs that lazily bound calls go through on x86_64, and
+//! how a process continues once a stub's function has been resolved.
+//!
+//! Each stub starts out as `push <id>; int <interrupt>`. The call to the stub
+//! already pushed a return address, so when the interrupt is raised, the stack
+//! pointer points at the stub's ID, with the caller's return address above it.
+//! Once the function is resolved, the process continues in the function with
+//! the ID popped, as if it had been called directly, and the stub is rewritten
+//! to `jmp [rip]` followed by the function's address for any later calls.
+
+#![no_std]
+
+
+
+/// The size of a stub's code in bytes.
+pub const STUB_SIZE: usize = 16;
+/// The largest ID that a stub can push, since `push imm32` sign-extends.
+pub const MAX_STUB_ID: usize = i32::MAX as usize;
+
+const PUSH_IMM32: u8 = 0x68;
+const INT_IMM8: u8 = 0xcd;
+const INT3: u8 = 0xcc;
+const JMP_RIP: [u8; 6] = [0xff, 0x25, 0x00, 0x00, 0x00, 0x00];
+
+/// What a stub's code does, see [`decode`].
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum StubCode {
+    /// Push the stub's ID and raise the interrupt.
+    Unresolved { id: u32, interrupt: u8 },
+    /// Jump to the resolved function.
+    Resolved { target: u64 },
+}
+
+/// The code of a stub that raises `interrupt` with `id` pushed, or `None` if
+/// the ID is larger than [`MAX_STUB_ID`].
+pub fn unresolved_code(id: usize, interrupt: u8) -> Option<[u8; STUB_SIZE]> {
+    if id > MAX_STUB_ID {
+        return None;
+    }
+
+    let mut code = [INT3; STUB_SIZE];
+    code[0] = PUSH_IMM32;
+    code[1..5].copy_from_slice(&(id as u32).to_le_bytes());
+    code[5] = INT_IMM8;
+    code[6] = interrupt;
+
+    Some(code)
+}
+
+/// The code of a stub whose function has been resolved to `target`.
+pub fn resolved_code(target: u64) -> [u8; STUB_SIZE] {
+    let mut code = [INT3; STUB_SIZE];
+    code[..6].copy_from_slice(&JMP_RIP);
+    code[6..14].copy_from_slice(&target.to_le_bytes());
+
+    code
+}
+
+/// Decode code written by [`unresolved_code`] or [`resolved_code`], returning
+/// `None` for anything else.
+pub fn decode(code: &[u8; STUB_SIZE]) -> Option<StubCode> {
+    if code[0] == PUSH_IMM32 && code[5] == INT_IMM8 && code[7..].iter().all(|b| *b == INT3) {
+        let id = u32::from_le_bytes(code[1..5].try_into().ok()?);
+        return (id as usize <= MAX_STUB_ID).then_some(StubCode::Unresolved {
+            id,
+            interrupt: code[6],
+        });
+    }
+    if code[..6] == JMP_RIP && code[14..].iter().all(|b| *b == INT3) {
+        return Some(StubCode::Resolved {
+            target: u64::from_le_bytes(code[6..14].try_into().ok()?),
+        });
+    }
+
+    None
+}
+
+/// The ID of the stub that raised the interrupt, given the word at the stack
+/// pointer when it was raised.
+///
+/// Returns `None` if the word can't have been pushed by a stub, which can
+/// happen because any process can raise the interrupt itself.
+pub fn pushed_id(word: u64) -> Option<usize> {
+    (word <= MAX_STUB_ID as u64).then_some(word as usize)
+}
+
+/// Where a process that was interrupted by a stub continues.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Resume {
+    pub instruction_pointer: u64,
+    pub stack_pointer: u64,
+}
+
+/// Where a process continues once the function of the stub that interrupted
+/// it (with the given stack pointer) is resolved to `target`.
+///
+/// The stub's ID is popped, so the function returns straight to the caller.
+pub fn resume(stack_pointer: u64, target: u64) -> Resume {
+    Resume {
+        instruction_pointer: target,
+        stack_pointer: stack_pointer + size_of::<u64>() as u64,
+    }
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const INTERRUPT: u8 = 0x42;
+
+    /// A process calling a stub, with a stack that grows down from `0x1000`.
+    struct Process {
+        stack: [u64; 8],
+        stack_pointer: u64,
+        instruction_pointer: u64,
+    }
+
+    impl Process {
+        fn new() -> Self {
+            Self {
+                stack: [0; 8],
+                stack_pointer: 0x1000,
+                instruction_pointer: 0,
+            }
+        }
+
+        fn push(&mut self, word: u64) {
+            self.stack_pointer -= 8;
+            self.stack[((0x1000 - self.stack_pointer) / 8) as usize - 1] = word;
+        }
+
+        fn top(&self) -> u64 {
+            self.stack[((0x1000 - self.stack_pointer) / 8) as usize - 1]
+        }
+
+        /// Call a stub at `stub_addr`, returning to `return_addr`, and run it
+        /// until it raises the interrupt or jumps elsewhere.
+        fn call(&mut self, code: &[u8; STUB_SIZE], stub_addr: u64, return_addr: u64) -> bool {
+            self.push(return_addr);
+            self.instruction_pointer = stub_addr;
+            match decode(code).unwrap() {
+                StubCode::Unresolved { id, interrupt } => {
+                    // `push imm32` sign-extends.
+                    self.push(id as i32 as i64 as u64);
+                    assert_eq!(interrupt, INTERRUPT);
+                    true
+                }
+                StubCode::Resolved { target } => {
+                    self.instruction_pointer = target;
+                    false
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn stub_resolution() {
+        let target = 0x4444_0000_1234;
+        let mut code = unresolved_code(7, INTERRUPT).unwrap();
+        assert_eq!(code[..7], [0x68, 7, 0, 0, 0, 0xcd, INTERRUPT]);
+
+        let mut process = Process::new();
+        assert!(process.call(&code, 0x2000, 0x3005));
+        let id = pushed_id(process.top()).unwrap();
+        assert_eq!(id, 7);
+
+        let resumed = resume(process.stack_pointer, target);
+        process.instruction_pointer = resumed.instruction_pointer;
+        process.stack_pointer = resumed.stack_pointer;
+        // The function returns to the caller.
+        assert_eq!(process.instruction_pointer, target);
+        assert_eq!(process.stack_pointer, 0x1000 - 8);
+        assert_eq!(process.top(), 0x3005);
+
+        // Later calls jump straight to the function.
+        code = resolved_code(target);
+        assert_eq!(decode(&code), Some(StubCode::Resolved { target }));
+        let mut process = Process::new();
+        assert!(!process.call(&code, 0x2000, 0x3005));
+        assert_eq!(process.instruction_pointer, target);
+        assert_eq!(process.top(), 0x3005);
+    }
+
+    #[test]
+    fn stub_ids_fit_in_a_push() {
+        assert!(unresolved_code(MAX_STUB_ID, INTERRUPT).is_some());
+        assert_eq!(unresolved_code(MAX_STUB_ID + 1, INTERRUPT), None);
+
+        let code = unresolved_code(MAX_STUB_ID, INTERRUPT).unwrap();
+        let mut process = Process::new();
+        process.call(&code, 0x2000, 0x3005);
+        assert_eq!(pushed_id(process.top()), Some(MAX_STUB_ID));
+    }
+
+    #[test]
+    fn foreign_interrupts_are_rejected() {
+        // Processes can raise the interrupt with anything on their stack.
+        assert_eq!(pushed_id(u64::MAX), None);
+        assert_eq!(pushed_id(0x3005_0000_0000), None);
+        assert_eq!(pushed_id(MAX_STUB_ID as u64 + 1), None);
+
+        let mut code = resolved_code(0x1234);
+        code[15] = 0x90;
+        assert_eq!(decode(&code), None);
+        assert_eq!(decode(&[INT3; STUB_SIZE]), None);
+    }
+}
@@ -360,6 +360,24 @@ impl Level4PageTable {
         Ok(FlushMapping(page))
     }
 
+    /// Unmap the page, returning the frame it was mapped to.
+    pub fn unmap(&mut self, page: Page) -> Result<(Frame, FlushMapping), TranslationError> {
+        let l4 = &self.inner;
+        let l3 = Self::get_table(&l4[page.l4_index()])?;
+        let l2 = Self::get_table(&l3[page.l3_index()])?;
+        let l1 = Self::get_table(&l2[page.l2_index()])?;
+
+        let l1_entry = &mut l1[page.l1_index()];
+        if l1_entry.is_unused() {
+            return Err(TranslationError::NotMapped);
+        }
+        let frame = Frame::from_base_addr(l1_entry.addr())
+            .ok_or(TranslationError::InvalidFrameAddress(l1_entry.addr()))?;
+        l1_entry.set_unused();
+
+        Ok((frame, FlushMapping(page)))
+    }
+
     fn next_table<'a, A>(
         entry: &'a mut PageTableEntry,
         flags: PageTableFlags,
@@ -0,0 +1,173 @@
+//! # Static Thread-Local Storage
+//!
+//! The layout of a static TLS block, which holds the thread-local storage of
+//! every loaded object at fixed offsets from the thread pointer.
+//!
+//! On x86_64, the block ends at the thread pointer (the `%fs` base), and the
+//! word at the thread pointer points to itself. Sections are placed downward
+//! from a fixed limit, so that their offsets from the thread pointer don't
+//! change as more sections are added. A section's position is its offset into
+//! the whole block, so the limit is where the thread pointer is.
+//!
+//! Only the space that's actually used is allocated for each thread's copy of
+//! the block (its area), which is laid out like this:
+//!
+//! ```text
+//! area start                          thread pointer
+//! |                                   |
+//! [ padding | section | ... | section ][ self pointer ]
+//! |<-------------- block size ------->|<--- 8 B ---->|
+//! ```
+
+#![no_std]
+
+
+
+/// Where each section goes in a static TLS block, see the [crate docs](crate).
+#[derive(Clone, Copy, Debug)]
+pub struct StaticTlsLayout {
+    limit: usize,
+    /// The number of bytes below `limit` that are in use.
+    used: usize,
+    /// The largest alignment of any section.
+    align: usize,
+}
+
+impl StaticTlsLayout {
+    /// An empty block that can hold up to `limit` bytes of sections.
+    ///
+    /// The limit should be aligned to at least the largest section alignment,
+    /// so that the positions of aligned sections are aligned too.
+    pub const fn new(limit: usize) -> Self {
+        Self {
+            limit,
+            used: 0,
+            align: 1,
+        }
+    }
+
+    #[inline]
+    pub const fn limit(&self) -> usize {
+        self.limit
+    }
+
+    /// Whether no sections have been reserved yet.
+    #[inline]
+    pub const fn is_empty(&self) -> bool {
+        self.used == 0
+    }
+
+    /// Reserve space for a section, returning its position in the block, or
+    /// `None` if the block is full.
+    pub fn reserve(&mut self, size: usize, align: usize) -> Option<usize> {
+        let align = align.max(1);
+        let used = self
+            .used
+            .checked_add(size)?
+            .checked_next_multiple_of(align)?;
+        if used > self.limit {
+            return None;
+        }
+        self.used = used;
+        self.align = self.align.max(align);
+
+        Some(self.limit - used)
+    }
+
+    /// The size of the part of the block that's in use, rounded up to the
+    /// largest section alignment so that the thread pointer stays aligned.
+    #[inline]
+    pub fn block_size(&self) -> usize {
+        self.used.next_multiple_of(self.align)
+    }
+
+    /// The size of a thread's area, including the word at the thread pointer.
+    #[inline]
+    pub fn area_size(&self) -> usize {
+        self.block_size() + size_of::<u64>()
+    }
+
+    /// The offset into a thread's area of the section at the given position,
+    /// or `None` if the position is outside of the used part of the block.
+    pub fn area_offset(&self, position: usize) -> Option<usize> {
+        position
+            .checked_sub(self.limit - self.block_size())
+            .filter(|offset| *offset < self.block_size())
+    }
+}
+
+/// The offset from the thread pointer of the given position (plus any addend)
+/// in a block with the given limit, as written by `TPOFF` relocations.
+#[inline]
+pub const fn thread_pointer_offset(position: u64, limit: usize) -> u64 {
+    position.wrapping_sub(limit as u64)
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sections_are_placed_downward() {
+        let mut layout = StaticTlsLayout::new(0x1000);
+        assert!(layout.is_empty());
+        assert_eq!(layout.block_size(), 0);
+
+        assert_eq!(layout.reserve(4, 4), Some(0xffc));
+        // Aligned down past the first section.
+        assert_eq!(layout.reserve(8, 8), Some(0xff0));
+        assert_eq!(layout.reserve(1, 0), Some(0xfef));
+        assert!(!layout.is_empty());
+
+        // The block is padded to the largest alignment.
+        assert_eq!(layout.block_size(), 24);
+        assert_eq!(layout.area_size(), 32);
+    }
+
+    #[test]
+    fn full_block_is_unchanged() {
+        let mut layout = StaticTlsLayout::new(0x100);
+        assert_eq!(layout.reserve(0xf0, 16), Some(0x10));
+        assert_eq!(layout.reserve(0x20, 1), None);
+        assert_eq!(layout.reserve(usize::MAX, 1), None);
+        assert_eq!(layout.block_size(), 0xf0);
+
+        assert_eq!(layout.reserve(0x10, 16), Some(0));
+        assert_eq!(layout.reserve(1, 1), None);
+    }
+
+    #[test]
+    fn area_offsets_match_thread_pointer_offsets() {
+        let limit = 0x10_0000;
+        let mut layout = StaticTlsLayout::new(limit);
+        let sections = [(3, 1), (16, 16), (4, 4), (40, 8)]
+            .map(|(size, align)| (layout.reserve(size, align).unwrap(), size, align));
+        let block_size = layout.block_size();
+        assert_eq!(block_size % 16, 0);
+
+        for (position, size, align) in sections {
+            let area_offset = layout.area_offset(position).unwrap();
+            assert!(area_offset + size <= block_size);
+            // The thread pointer is at the end of the block, so the section is
+            // as far below it in the area as it is below the limit.
+            let tp_offset = thread_pointer_offset(position as u64, limit) as i64;
+            assert_eq!(area_offset as i64 - block_size as i64, tp_offset);
+            assert_eq!(tp_offset % align as i64, 0);
+        }
+
+        assert_eq!(layout.area_offset(limit), None);
+        assert_eq!(layout.area_offset(limit - block_size - 1), None);
+    }
+
+    #[test]
+    fn addends_apply_before_the_offset() {
+        let limit = 0x10_0000;
+        let mut layout = StaticTlsLayout::new(limit);
+        let position = layout.reserve(16, 8).unwrap() as u64;
+        assert_eq!(thread_pointer_offset(position, limit) as i64, -16);
+        assert_eq!(thread_pointer_offset(position + 8, limit) as i64, -8);
+        assert_eq!(thread_pointer_offset(position + 8, limit) as u32 as i32, -8);
+    }
+}
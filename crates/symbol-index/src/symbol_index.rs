@@ -0,0 +1,273 @@
+//! # Symbol Index
+//!
+//! A persistent map from each global symbol in a directory of objects to the
+//! object (and section) that defines it, so that the loader doesn't have to
+//! read and demangle every object in its search path to find a dependency.
+//!
+//! The index is stored as text, one record per line, with tab-separated
+//! fields:
+//!
+//! ```text
+//! symbol-index 1
+//! object <name> <file> <size>
+//! symbol <section index> <demangled name>
+//! ```
+//!
+//! Each `symbol` record belongs to the `object` record before it. An object's
+//! `file` is relative to the indexed directory, and its `size` is used to tell
+//! whether the file has changed since it was indexed.
+
+#![no_std]
+
+extern crate alloc;
+
+use {
+    alloc::{
+        collections::btree_map::BTreeMap,
+        string::{String, ToString as _},
+        vec::Vec,
+    },
+    core::fmt,
+};
+
+/// The name of the index file within the directory it indexes.
+pub const FILE_NAME: &str = "symbols.idx";
+
+const HEADER: &str = "symbol-index 1";
+
+
+
+/// A map from demangled symbol names to the objects that define them.
+#[derive(Clone, Debug, Default)]
+pub struct SymbolIndex {
+    objects: Vec<IndexedObject>,
+    symbols: BTreeMap<String, IndexedSymbol>,
+}
+
+/// An object file, or archive member, that defines indexed symbols.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct IndexedObject {
+    /// The name the object is loaded with.
+    pub name: String,
+    /// The file the object is read from, which is an archive for members.
+    pub file: String,
+    /// The size of `file` in bytes when it was indexed.
+    pub size: usize,
+}
+
+#[derive(Clone, Copy, Debug)]
+struct IndexedSymbol {
+    object: usize,
+    section: usize,
+}
+
+impl SymbolIndex {
+    pub fn parse(input: &str) -> Result<Self, &'static str> {
+        let mut lines = input.lines();
+        if lines.next() != Some(HEADER) {
+            return Err("unsupported symbol index header");
+        }
+
+        let mut index = Self::default();
+        for line in lines.filter(|line| !line.is_empty()) {
+            let mut fields = line.split('\t');
+            match fields.next() {
+                Some("object") => {
+                    let (Some(name), Some(file), Some(size), None) =
+                        (fields.next(), fields.next(), fields.next(), fields.next())
+                    else {
+                        return Err("invalid object record in symbol index");
+                    };
+                    index.add_object(IndexedObject {
+                        name: name.to_string(),
+                        file: file.to_string(),
+                        size: size
+                            .parse()
+                            .map_err(|_| "invalid object size in symbol index")?,
+                    });
+                }
+                Some("symbol") => {
+                    let (Some(section), Some(name), None) =
+                        (fields.next(), fields.next(), fields.next())
+                    else {
+                        return Err("invalid symbol record in symbol index");
+                    };
+                    let object = index
+                        .objects
+                        .len()
+                        .checked_sub(1)
+                        .ok_or("symbol index record comes before any object")?;
+                    index.add_symbol(
+                        object,
+                        name,
+                        section
+                            .parse()
+                            .map_err(|_| "invalid section index in symbol index")?,
+                    );
+                }
+                _ => return Err("unknown record in symbol index"),
+            }
+        }
+
+        Ok(index)
+    }
+
+    /// Add an object to the index, returning the value to pass to
+    /// [`add_symbol`](Self::add_symbol) for the symbols it defines.
+    pub fn add_object(&mut self, object: IndexedObject) -> usize {
+        self.objects.push(object);
+        self.objects.len() - 1
+    }
+
+    /// Record that a symbol is defined by the section at `section` in an
+    /// object.
+    ///
+    /// If the symbol is already indexed, the object that was added first wins,
+    /// the same as a linker's search order.
+    pub fn add_symbol(&mut self, object: usize, name: &str, section: usize) {
+        assert!(object < self.objects.len(), "object is not in the index");
+        self.symbols
+            .entry(name.to_string())
+            .or_insert(IndexedSymbol { object, section });
+    }
+
+    /// Get the object that defines a symbol, and the index of the section
+    /// within it.
+    pub fn get(&self, symbol: &str) -> Option<(&IndexedObject, usize)> {
+        let symbol = self.symbols.get(symbol)?;
+        Some((&self.objects[symbol.object], symbol.section))
+    }
+
+    pub fn objects(&self) -> &[IndexedObject] {
+        &self.objects
+    }
+
+    pub fn symbol_count(&self) -> usize {
+        self.symbols.len()
+    }
+
+    /// Remove every object for which `keep` returns `false`, along with the
+    /// symbols it defines.
+    ///
+    /// Returns the number of objects that were removed.
+    pub fn retain_objects(&mut self, mut keep: impl FnMut(&IndexedObject) -> bool) -> usize {
+        let mut new_indices = Vec::with_capacity(self.objects.len());
+        let mut kept = 0;
+        for object in &self.objects {
+            if keep(object) {
+                new_indices.push(Some(kept));
+                kept += 1;
+            } else {
+                new_indices.push(None);
+            }
+        }
+
+        let removed = self.objects.len() - kept;
+        if removed == 0 {
+            return 0;
+        }
+
+        let mut index = 0;
+        self.objects.retain(|_object| {
+            index += 1;
+            new_indices[index - 1].is_some()
+        });
+        self.symbols.retain(|_name, symbol| {
+            let Some(object) = new_indices[symbol.object] else {
+                return false;
+            };
+            symbol.object = object;
+            true
+        });
+
+        removed
+    }
+}
+
+impl fmt::Display for SymbolIndex {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{HEADER}")?;
+
+        let mut symbols_by_object = BTreeMap::<usize, Vec<(&str, usize)>>::new();
+        for (name, symbol) in &self.symbols {
+            symbols_by_object
+                .entry(symbol.object)
+                .or_default()
+                .push((name, symbol.section));
+        }
+
+        for (index, object) in self.objects.iter().enumerate() {
+            writeln!(f, "object\t{}\t{}\t{}", object.name, object.file, object.size)?;
+            for (name, section) in symbols_by_object.remove(&index).unwrap_or_default() {
+                writeln!(f, "symbol\t{section}\t{name}")?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    extern crate std;
+
+    fn build_index() -> SymbolIndex {
+        let mut index = SymbolIndex::default();
+        let time = index.add_object(IndexedObject {
+            name: "time".to_string(),
+            file: "time.o".to_string(),
+            size: 1234,
+        });
+        index.add_symbol(time, "time::now", 3);
+        let core = index.add_object(IndexedObject {
+            name: "core.rlib(core-1.o)".to_string(),
+            file: "core.rlib".to_string(),
+            size: 5678,
+        });
+        index.add_symbol(core, "core::panicking::panic", 7);
+        index.add_symbol(core, "time::now", 9);
+
+        index
+    }
+
+    #[test]
+    fn round_trip() {
+        let text = build_index().to_string();
+        let index = SymbolIndex::parse(&text).unwrap();
+
+        assert_eq!(index.objects().len(), 2);
+        assert_eq!(index.symbol_count(), 2);
+        let (object, section) = index.get("core::panicking::panic").unwrap();
+        assert_eq!(object.name, "core.rlib(core-1.o)");
+        assert_eq!(object.size, 5678);
+        assert_eq!(section, 7);
+        // The first definition wins.
+        assert_eq!(index.get("time::now").unwrap().0.name, "time");
+    }
+
+    #[test]
+    fn removing_objects() {
+        let mut index = build_index();
+
+        assert_eq!(index.retain_objects(|object| object.name != "time"), 1);
+        assert_eq!(index.objects().len(), 1);
+        assert!(index.get("time::now").is_none());
+        assert_eq!(
+            index.get("core::panicking::panic").unwrap().0.file,
+            "core.rlib",
+        );
+    }
+
+    #[test]
+    fn rejecting_invalid_input() {
+        assert!(SymbolIndex::parse("").is_err());
+        assert!(SymbolIndex::parse("symbol-index 2\n").is_err());
+        assert!(SymbolIndex::parse("symbol-index 1\nsymbol\t1\tfoo\n").is_err());
+        assert!(SymbolIndex::parse("symbol-index 1\nobject\tfoo\tfoo.o\n").is_err());
+        assert!(SymbolIndex::parse("symbol-index 1\nobject\tfoo\tfoo.o\tbig\n").is_err());
+    }
+}
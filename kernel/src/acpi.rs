@@ -57,6 +57,11 @@ pub fn init(boot_info: &BootInfo) {
 
                     // None of the application processors should be running at this point.
                     assert!(processor.state != ProcessorState::Running);
+                    if processor.state == ProcessorState::WaitingForSipi {
+                        unsafe {
+                            APPLICATION_PROCESSOR_COUNT += 1;
+                        }
+                    }
                 }
             }
 
@@ -76,6 +81,14 @@ pub fn init(boot_info: &BootInfo) {
     };
 }
 
+static mut APPLICATION_PROCESSOR_COUNT: usize = 0;
+
+/// The number of application processors that can be started, see
+/// [`smp::init`](crate::smp::init).
+pub fn application_processor_count() -> usize {
+    unsafe { APPLICATION_PROCESSOR_COUNT }
+}
+
 const PM_TIMER_FREQ: u32 = 3579545;
 static mut PM_TIMER_PORT: Option<u16> = None;
 
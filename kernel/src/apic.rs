@@ -49,6 +49,29 @@ define_interrupt_handler_with_context!(timer_interrupt_handler {
     with_scheduler(|scheduler| scheduler.preempt_current());
 });
 
+/// Send an INIT IPI to every other processor.
+pub fn send_init_ipi_all() {
+    unsafe {
+        LOCAL_APIC
+            .lock()
+            .as_mut()
+            .expect("APIC initialized")
+            .send_init_ipi_all()
+    };
+}
+
+/// Send a startup IPI to every other processor, which starts the ones waiting
+/// for it in real mode at `vector << 12`.
+pub fn send_startup_ipi_all(vector: u8) {
+    unsafe {
+        LOCAL_APIC
+            .lock()
+            .as_mut()
+            .expect("APIC initialized")
+            .send_sipi_all(vector)
+    };
+}
+
 fn end_of_interrupt() {
     unsafe {
         LOCAL_APIC
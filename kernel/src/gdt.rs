@@ -88,6 +88,23 @@ pub fn init() {
     }
 }
 
+/// Load the GDT on an application processor, see [`smp`](crate::smp).
+///
+/// The TSS is only loaded on the bootstrap processor (it can't be loaded twice),
+/// so application processors have no interrupt stacks.
+pub fn init_application_processor() {
+    unsafe {
+        GDT.load();
+
+        SS::set_reg(SELECTORS.kernel_data);
+        CS::set_reg(SELECTORS.kernel_code);
+        DS::set_reg(SELECTORS.kernel_data);
+        ES::set_reg(SELECTORS.kernel_data);
+        FS::set_reg(SELECTORS.kernel_data);
+        GS::set_reg(SELECTORS.kernel_data);
+    }
+}
+
 pub fn selectors() -> &'static Selectors {
     unsafe { &SELECTORS }
 }
@@ -6,6 +6,7 @@ use {
         loader::global_loader,
         memory::{FRAMEBUFFER_MAPPING, kernel_address_space},
         scheduler::{self, AccessPolicy, with_scheduler},
+        unwind,
     },
     log::{error, info},
     memory_types::{PageTableFlags, VirtualAddress},
@@ -48,7 +49,18 @@ pub fn init() {
             .set_handler_fn(scheduler::exit_interrupt_handler)
             .set_privilege_level(x86_64::PrivilegeLevel::Ring3)
             .set_stack_index(gdt::USER_IST);
+        IDT[scheduler::LAZY_BIND_INTERRUPT_NUMBER]
+            .set_handler_fn(scheduler::lazy_bind_interrupt_handler)
+            .set_privilege_level(x86_64::PrivilegeLevel::Ring3)
+            .set_stack_index(gdt::USER_IST);
+
+        IDT.load();
+    }
+}
 
+/// Load the IDT that [`init`] set up, on an application processor.
+pub fn load() {
+    unsafe {
         IDT.load();
     }
 }
@@ -117,6 +129,13 @@ extern "x86-interrupt" fn page_fault_handler(
                     "Userspace process `{}` tried to access a nonexistent section at {addr:x}",
                     address_space.name(),
                 );
+                let ins_addr = VirtualAddress::new(stack_frame.instruction_pointer.as_u64() as _);
+                if let Some(frame) = unwind::find_frame(ins_addr) {
+                    error!(
+                        "\tin the function at {:#x} (FDE @ {:#x}, LSDA @ {:x?})",
+                        frame.start, frame.fde, frame.lsda,
+                    );
+                }
 
                 exit_process = true;
             }
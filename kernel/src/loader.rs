@@ -4,8 +4,10 @@
 
 use {
     crate::{
-        FileSystem,
+        DirectoryEntry, FileSystem,
         memory::{AddressSpace, KernelMapping},
+        scheduler::LAZY_BIND_INTERRUPT_NUMBER,
+        smp, unwind,
     },
     alloc::{
         boxed::Box,
@@ -16,21 +18,58 @@ use {
     },
     boot_info::BootInfo,
     core::{
+        fmt,
         ops::Range,
-        sync::atomic::{AtomicU64, AtomicUsize, Ordering},
+        sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering},
         time::Duration,
     },
     elf::{
-        ElfFile, ObjectFileType, SHF_ALLOC, SHF_EXECINSTR, SHF_TLS, SHF_WRITE, SectionData,
-        SectionHeaderType, SymbolBinding, SymbolType,
+        DT_FINI_ARRAY, DT_FINI_ARRAYSZ, DT_INIT_ARRAY, DT_INIT_ARRAYSZ, DT_NEEDED, ElfFile,
+        ObjectFileType, ProgramHeaderType, SHF_ALLOC, SHF_EXECINSTR, SHF_TLS, SHF_WRITE, SHN_ABS,
+        SectionData, SectionHeaderType, SymbolType,
     },
     hashbrown::HashMap,
-    log::{debug, error, info, trace},
-    memory_types::{Page, PageRange, PageTableFlags, VirtualAddress},
+    lazy_stub::STUB_SIZE,
+    log::{debug, error, info, trace, warn},
+    memory_types::{PAGE_SIZE, Page, PageRange, PageTableFlags, VirtualAddress},
     spin_mutex::Mutex,
+    static_tls::StaticTlsLayout,
+    symbol_index::SymbolIndex,
 };
 
 const AUTO_MAP_DEPENDENCIES: bool = false;
+/// Whether to reload `example_dep` with [`Loader::swap_object`] at startup,
+/// to check that its dependents are relocated correctly.
+const CHECK_HOT_SWAP: bool = false;
+/// Whether loaded objects keep mappings that are both writable and
+/// executable, see [`Loader::set_relaxed_permissions`].
+const RELAXED_PERMISSIONS: bool = false;
+/// Whether calls to functions in objects that aren't loaded yet are bound
+/// lazily, see [`Loader::set_lazy_binding`].
+const LAZY_BINDING: bool = false;
+/// Whether to [verify](Loader::verify_object) every object loaded by name
+/// before it's mapped, logging the result and failing early on errors.
+const VERIFY_OBJECTS: bool = false;
+/// Whether to time loading the drivers as a batch against loading them one at
+/// a time at startup, see [`Loader::benchmark_batch_loading`].
+const BENCHMARK_BATCH_LOADING: bool = false;
+/// Whether batches of objects have their sections loaded in parallel on every
+/// [online processor](smp::processor_count), see [`Loader::load_objects_in`].
+const PARALLEL_BATCH_LOADING: bool = false;
+
+/// The directory that objects requested by name are read from.
+pub const OBJECT_SEARCH_PATH: &str = "/";
+
+/// The [namespace](Loader::create_namespace) that objects are loaded into
+/// unless another one is given.
+pub const DEFAULT_NAMESPACE: &str = "default";
+
+/// The most thread-local storage that all loaded objects can have combined.
+///
+/// TLS sections are placed at fixed offsets below this limit, so that their
+/// `%fs`-relative offsets don't change as more objects are loaded. Only the
+/// space that's actually used is allocated for each [`TlsArea`].
+pub const STATIC_TLS_LIMIT: usize = 0x10_0000;
 
 const FUNDAMENTAL_SYMBOLS: &[&str] = &[
     "memcmp",
@@ -61,6 +100,8 @@ pub fn init(boot_info: &BootInfo, fs: impl FileSystem + 'static) {
         });
     }
 
+    global_loader().set_relaxed_permissions(RELAXED_PERMISSIONS);
+    global_loader().set_lazy_binding(LAZY_BINDING);
     init_fundamental_symbols();
 
     // FIXME: The only reason this exists is because `core` relies on a symbol
@@ -76,26 +117,29 @@ pub fn init(boot_info: &BootInfo, fs: impl FileSystem + 'static) {
         )
         .unwrap();
 
+    if CHECK_HOT_SWAP {
+        global_loader()
+            .swap_object(
+                "example_dep",
+                "example_dep",
+                &AddressSpace::new("swap_dep", None),
+                // The actual value of this address doesn't matter.
+                Page::containing_addr(VirtualAddress::new(0x3333_0000_0000)),
+            )
+            .unwrap();
+    }
+
+    if BENCHMARK_BATCH_LOADING {
+        global_loader()
+            .benchmark_batch_loading(&["time", "framebuffer", "input"])
+            .unwrap();
+    }
+
     global_loader()
-        .load_object(
-            "time",
-            &AddressSpace::new("load_time", None),
-            // The actual value of this address doesn't matter.
-            Page::containing_addr(VirtualAddress::new(0x3333_0000_0000)),
-        )
-        .unwrap();
-    global_loader()
-        .load_object(
-            "framebuffer",
-            &AddressSpace::new("load_framebuffer", None),
-            // The actual value of this address doesn't matter.
-            Page::containing_addr(VirtualAddress::new(0x3333_0000_0000)),
-        )
-        .unwrap();
-    global_loader()
-        .load_object(
-            "input",
-            &AddressSpace::new("load_input", None),
+        .load_objects_in(
+            DEFAULT_NAMESPACE,
+            &["time", "framebuffer", "input"],
+            &AddressSpace::new("load_drivers", None),
             // The actual value of this address doesn't matter.
             Page::containing_addr(VirtualAddress::new(0x3333_0000_0000)),
         )
@@ -147,6 +191,8 @@ pub fn init(boot_info: &BootInfo, fs: impl FileSystem + 'static) {
     );
 
     // global_loader().dump_info();
+    debug!("{}", global_loader().audit());
+    debug!("{}", global_loader().stats());
 }
 
 fn with_sym<T, F>(section_prefix: &str, section_suffix: &str, op: F)
@@ -206,7 +252,9 @@ pub struct GlobalObjectProvider {
 
 impl<'a> ObjectProvider for &'a GlobalObjectProvider {
     fn list_objects(&self, prefix: &str) -> Result<Vec<String>, &'static str> {
-        self.fs.lock().list(&format!("/{prefix}"))
+        self.fs
+            .lock()
+            .list(&format!("{OBJECT_SEARCH_PATH}{prefix}"))
     }
 
     fn read_object(&self, name: &str) -> Result<Vec<u8>, &'static str> {
@@ -214,7 +262,7 @@ impl<'a> ObjectProvider for &'a GlobalObjectProvider {
             let path = self
                 .list_objects(name)?
                 .into_iter()
-                .find(|object_name| object_name == &format!("/{name}.o"))
+                .find(|object_name| object_name == &object_path(name))
                 .ok_or("no object found")?;
 
             self.fs.lock().read(&path)
@@ -222,14 +270,80 @@ impl<'a> ObjectProvider for &'a GlobalObjectProvider {
             self.fs.lock().read(name)
         }
     }
+
+    fn object_entry(&self, path: &str) -> Result<DirectoryEntry, &'static str> {
+        self.fs.lock().entry(path)
+    }
 }
 
 /// A set of loaded [objects](LoadedObject) and [sections](LoadedSection).
 #[derive(Debug)]
 pub struct Loader {
-    objects: Mutex<HashMap<Arc<str>, Arc<Mutex<LoadedObject>>, rustc_hash::FxBuildHasher>>,
-    sections: Mutex<HashMap<Arc<str>, Weak<LoadedSection>, rustc_hash::FxBuildHasher>>,
+    namespaces: Mutex<Namespaces>,
     sections_by_addr: Mutex<BTreeMap<(VirtualAddress, usize), Weak<LoadedSection>>>,
+    tls: Mutex<StaticTls>,
+    /// Whether loaded objects keep writable and executable mappings after
+    /// they're relocated, see [`Loader::set_relaxed_permissions`].
+    relaxed_permissions: AtomicBool,
+    /// Whether calls to functions in objects that aren't loaded yet are bound
+    /// lazily, see [`Loader::set_lazy_binding`].
+    lazy_binding: AtomicBool,
+    /// Every [`LazyStub`] that has been written, indexed by its ID.
+    lazy_stubs: Mutex<Vec<LazyStub>>,
+    /// The archives that dependencies have been loaded from, by crate name.
+    archives: Mutex<HashMap<Arc<str>, Arc<ArchiveIndex>, rustc_hash::FxBuildHasher>>,
+    /// The [symbol index](symbol_index) of the [`OBJECT_SEARCH_PATH`], once
+    /// it's been read.
+    symbol_index: Mutex<Option<Arc<SymbolIndex>>>,
+    /// The number of bytes of mappings that were still referenced when their
+    /// object was unloaded, see [`Loader::stats`].
+    leaked_bytes: AtomicUsize,
+}
+
+/// The symbol table of an archive that dependencies are loaded from, see
+/// [`archive_file_names`].
+#[derive(Debug)]
+struct ArchiveIndex {
+    /// The name of the archive within the [`OBJECT_SEARCH_PATH`].
+    file_name: Arc<str>,
+    /// The whole archive, which members are loaded from as they're needed.
+    bytes: Vec<u8>,
+    /// The offset of the member that defines each demangled symbol.
+    symbols: HashMap<String, usize, rustc_hash::FxBuildHasher>,
+}
+
+type Namespaces = HashMap<Arc<str>, Namespace, rustc_hash::FxBuildHasher>;
+
+/// A set of loaded objects whose global sections are only visible to each
+/// other, and to namespaces that share this one.
+#[derive(Debug, Default)]
+struct Namespace {
+    objects: HashMap<Arc<str>, Arc<Mutex<LoadedObject>>, rustc_hash::FxBuildHasher>,
+    sections: HashMap<Arc<str>, Weak<LoadedSection>, rustc_hash::FxBuildHasher>,
+    /// The namespaces that are searched, in order, for sections that aren't in
+    /// this one.
+    shared: Vec<Arc<str>>,
+    /// The COMDAT section groups loaded into this namespace, by signature.
+    comdat_groups: HashMap<Arc<str>, ComdatGroup, rustc_hash::FxBuildHasher>,
+}
+
+/// The first loaded copy of a COMDAT section group, which every later copy of
+/// the group is [deduplicated](LoadedObject::deduplicated_sections) against.
+#[derive(Debug)]
+struct ComdatGroup {
+    owner: Weak<Mutex<LoadedObject>>,
+    /// The group's loaded sections, by their names in the ELF file.
+    sections: HashMap<Arc<str>, Weak<LoadedSection>, rustc_hash::FxBuildHasher>,
+}
+
+impl ComdatGroup {
+    fn is_loaded(&self) -> bool {
+        self.owner.strong_count() > 0
+            && self
+                .sections
+                .values()
+                .all(|section| section.strong_count() > 0)
+    }
 }
 
 /// An object that has been loaded into memory.
@@ -237,6 +351,10 @@ pub struct Loader {
 pub struct LoadedObject {
     /// The demangled name of this object.
     pub name: Arc<str>,
+    /// The [namespace](Loader::create_namespace) this object was loaded into.
+    pub namespace: Arc<str>,
+    /// The path this object was read from, see [`object_path`].
+    pub path: Arc<str>,
     /// The sections that have been loaded into memory for this object.
     pub sections: HashMap<usize, Arc<LoadedSection>>,
     /// A set of section indices representing the global sections of this
@@ -249,11 +367,54 @@ pub struct LoadedObject {
     /// sections of this object. They can be used as keys for
     /// [`self.sections`](Self::sections).
     pub tls_sections: BTreeSet<usize>,
-    /// Objects this object depends on.
+    /// Objects this object depends on, which can't be
+    /// [unloaded](Loader::unload_object_in) before this one is.
     pub dependencies: Vec<Weak<Mutex<LoadedObject>>>,
     pub executable_mapping: Option<Arc<Mutex<KernelMapping>>>,
     pub read_only_mapping: Option<Arc<Mutex<KernelMapping>>>,
     pub read_write_mapping: Option<Arc<Mutex<KernelMapping>>>,
+    /// The branch stubs written while relocating this object, for branches
+    /// whose targets were out of range.
+    pub veneer_mapping: Option<Arc<Mutex<KernelMapping>>>,
+    /// The stubs that lazily bound calls in this object branch to until
+    /// they're resolved, see [`Loader::set_lazy_binding`].
+    pub lazy_stub_mapping: Option<Arc<Mutex<KernelMapping>>>,
+    /// A set of section indices representing the executable sections of this
+    /// object that relocations were written into.
+    pub text_relocations: BTreeSet<usize>,
+    /// The demangled names of weak symbols this object references that
+    /// couldn't be found. They resolve to address zero.
+    pub unresolved_weak_symbols: Vec<Arc<str>>,
+    /// The sections of COMDAT groups that another object had already loaded,
+    /// by the index of this object's copy. Relocations against this object's
+    /// copy are resolved against the loaded one instead.
+    pub deduplicated_sections: HashMap<usize, Weak<LoadedSection>>,
+    /// A list of section indices representing the `.init_array` sections of
+    /// this object, in the order they're run once it's relocated. They can be
+    /// used as keys for [`self.sections`](Self::sections).
+    pub init_array_sections: Vec<usize>,
+    /// A list of section indices representing the `.fini_array` sections of
+    /// this object, which are run in reverse order when it's unloaded. They can
+    /// be used as keys for [`self.sections`](Self::sections).
+    pub fini_array_sections: Vec<usize>,
+    /// The mapping that a [shared object](Loader::load_object)'s segments are
+    /// loaded into, which keeps their layout. Relocatable objects use the
+    /// other mappings instead.
+    pub image_mapping: Option<Arc<Mutex<KernelMapping>>>,
+    /// The parts of [`self.image_mapping`](Self::image_mapping) that are
+    /// protected differently from each other.
+    pub image_segments: Vec<LoadedSegment>,
+}
+
+/// A run of pages of a shared object's
+/// [image mapping](LoadedObject::image_mapping) with the same flags.
+#[derive(Clone, Debug)]
+pub struct LoadedSegment {
+    /// The offset into the image mapping at which this segment starts.
+    pub offset: usize,
+    /// The size of this segment in bytes, a multiple of the page size.
+    pub size: usize,
+    pub flags: PageTableFlags,
 }
 
 /// An object section that has been loaded into memory.
@@ -265,9 +426,15 @@ pub struct LoadedSection {
     pub kind: SectionKind,
     /// Whether this section is global (public).
     pub global: bool,
+    /// Whether this section is only defined by weak symbols, in which case a
+    /// global section with the same name takes precedence over it.
+    pub weak: bool,
     /// The size of this section in bytes.
     pub size: usize,
     /// The memory address of this section.
+    ///
+    /// For TLS sections, this is instead the section's offset into the static
+    /// TLS block, which ends at [`STATIC_TLS_LIMIT`].
     pub addr: VirtualAddress,
     /// A reference to the mapping that contains this section's data.
     pub mapping: Arc<Mutex<KernelMapping>>,
@@ -276,10 +443,46 @@ pub struct LoadedSection {
     pub mapping_offset: usize,
     /// The object that contains this section.
     pub owner: Weak<Mutex<LoadedObject>>,
+    /// The sections of other objects that this section's relocations refer to.
+    pub dependencies: Mutex<Vec<SectionDependency>>,
+    /// The sections of other objects whose relocations refer to this section,
+    /// see [`Loader::dependents_of`].
+    pub dependents: Mutex<Vec<Weak<LoadedSection>>>,
+}
+
+/// A relocation in one [`LoadedSection`] that refers to a section of another
+/// object, which keeps that section alive.
+#[derive(Debug)]
+pub struct SectionDependency {
+    /// The section that the relocation refers to.
+    pub source: Arc<LoadedSection>,
+    /// The offset of the referenced symbol into the source section.
+    pub source_offset: usize,
+    pub relocation: Relocation,
+}
+
+/// The parts of a relocation entry needed to write it (again).
+#[derive(Clone, Copy, Debug)]
+pub struct Relocation {
+    /// The architecture-specific type of relocation.
+    pub kind: u32,
+    /// The offset into the section being relocated.
+    pub offset: usize,
+    pub addend: u64,
+}
+
+impl From<&elf::Rela> for Relocation {
+    fn from(entry: &elf::Rela) -> Self {
+        Self {
+            kind: entry.get_type(),
+            offset: entry.get_offset() as usize,
+            addend: entry.get_addend(),
+        }
+    }
 }
 
 /// The type of a [`LoadedSection`].
-#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
 pub enum SectionKind {
     /// Executable code.
     Text,
@@ -318,6 +521,55 @@ pub trait ObjectProvider {
     fn list_objects(&self, prefix: &str) -> Result<Vec<String>, &'static str>;
     /// Read the bytes of the object with the given name.
     fn read_object(&self, name: &str) -> Result<Vec<u8>, &'static str>;
+    /// Get the directory entry of the file at the given path.
+    fn object_entry(&self, path: &str) -> Result<DirectoryEntry, &'static str>;
+}
+
+/// An error produced by the [`Loader`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum LoaderError {
+    /// No loaded object defines a symbol that an object refers to, and no
+    /// object that would define it could be found.
+    MissingSymbol {
+        /// The demangled name of the symbol.
+        symbol: Arc<str>,
+        /// The object that refers to the symbol.
+        object: Arc<str>,
+    },
+    /// A relocation has a type that isn't supported on this architecture.
+    UnsupportedRelocation(u32),
+    /// Something doesn't fit where it has to go, like a relocated value that's
+    /// out of range of its field, or a TLS section when the static TLS block
+    /// is full.
+    SectionOverflow(&'static str),
+    /// Reading an object from the [`ObjectProvider`] failed.
+    Io(&'static str),
+    /// Anything else, like a malformed object, or a request that doesn't make
+    /// sense for the objects that are loaded.
+    Other(&'static str),
+}
+
+impl From<&'static str> for LoaderError {
+    fn from(error: &'static str) -> Self {
+        Self::Other(error)
+    }
+}
+
+impl fmt::Display for LoaderError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::MissingSymbol { symbol, object } => {
+                write!(
+                    f,
+                    "`{object}` refers to `{symbol}`, which couldn't be found"
+                )
+            }
+            Self::UnsupportedRelocation(kind) => write!(f, "unsupported relocation type {kind}"),
+            Self::SectionOverflow(error) => write!(f, "section overflow: {error}"),
+            Self::Io(error) => write!(f, "failed to read object: {error}"),
+            Self::Other(error) => f.write_str(error),
+        }
+    }
 }
 
 impl Loader {
@@ -325,26 +577,88 @@ impl Loader {
     /// [sections](LoadedSection).
     pub const fn new() -> Self {
         Self {
-            objects: Mutex::new(HashMap::with_hasher(rustc_hash::FxBuildHasher)),
-            sections: Mutex::new(HashMap::with_hasher(rustc_hash::FxBuildHasher)),
+            namespaces: Mutex::new(HashMap::with_hasher(rustc_hash::FxBuildHasher)),
             sections_by_addr: Mutex::new(BTreeMap::new()),
+            tls: Mutex::new(StaticTls::new()),
+            relaxed_permissions: AtomicBool::new(false),
+            lazy_binding: AtomicBool::new(false),
+            lazy_stubs: Mutex::new(Vec::new()),
+            archives: Mutex::new(HashMap::with_hasher(rustc_hash::FxBuildHasher)),
+            symbol_index: Mutex::new(None),
+            leaked_bytes: AtomicUsize::new(0),
+        }
+    }
+
+    /// Set whether objects loaded from now on should keep mappings that are
+    /// both writable and executable after they're relocated.
+    ///
+    /// By default, executable mappings lose write permission and data mappings
+    /// lose execute permission once an object is relocated. Relaxing this is
+    /// only useful for debugging (e.g. patching code in place).
+    pub fn set_relaxed_permissions(&self, relaxed: bool) {
+        self.relaxed_permissions.store(relaxed, Ordering::Relaxed);
+    }
+
+    /// Set whether objects loaded from now on should bind calls to functions in
+    /// objects that aren't loaded yet lazily.
+    ///
+    /// Instead of loading the function's object while relocating, the call is
+    /// pointed at a stub that raises [`LAZY_BIND_INTERRUPT_NUMBER`]. The first
+    /// call then [resolves](Self::resolve_lazy_stub) the function, loading its
+    /// object, and patches the call sites to branch to it directly. Only direct
+    /// calls on x86_64 are bound lazily.
+    pub fn set_lazy_binding(&self, lazy: bool) {
+        self.lazy_binding.store(lazy, Ordering::Relaxed);
+    }
+
+    /// Create a namespace that objects can be [loaded](Self::load_object_in)
+    /// into, similar to `dlmopen`.
+    ///
+    /// Symbols are resolved against the objects in the namespace first, then
+    /// against each of the `shared` namespaces in order (but not the ones they
+    /// share). Dependencies that can't be found are loaded into the namespace
+    /// itself. If the namespace already exists, only its shared namespaces are
+    /// replaced.
+    pub fn create_namespace(&self, name: &str, shared: &[&str]) {
+        self.namespaces
+            .lock()
+            .entry(name.into())
+            .or_default()
+            .shared = shared.iter().map(|name| (*name).into()).collect();
+    }
+
+    /// The flags a mapping should have once its object has been relocated.
+    fn final_mapping_flags(&self, executable: bool, writable: bool) -> PageTableFlags {
+        let flags = PageTableFlags::PRESENT | PageTableFlags::USER_ACCESSIBLE;
+        if self.relaxed_permissions.load(Ordering::Relaxed) {
+            flags | PageTableFlags::WRITABLE
+        } else if executable {
+            flags
+        } else if writable {
+            flags | PageTableFlags::WRITABLE | PageTableFlags::NO_EXECUTE
+        } else {
+            flags | PageTableFlags::NO_EXECUTE
         }
     }
 
     /// Dump debug information to the logger.
     pub fn dump_info(&self) {
-        let objects = self.objects.lock();
-        // let sections = self.sections.lock();
+        let namespaces = self.namespaces.lock();
 
         debug!(
             "--- OBJECTS ---\n{}",
-            objects
+            namespaces
                 .iter()
-                .map(|(name, object)| {
+                .flat_map(|(namespace, ns)| {
+                    ns.objects
+                        .iter()
+                        .map(move |(name, object)| (namespace, name, object))
+                })
+                .map(|(namespace, name, object)| {
                     let object = object.lock();
                     let section_count = object.sections.len();
                     format!(
-                        "    {name}:{}\n",
+                        "    {namespace}::{name}:{}\n",
                         if section_count > 20 {
                             format!("\n        {section_count} sections")
                         } else {
@@ -383,27 +697,324 @@ impl Loader {
         // );
     }
 
+    /// Check whether an object could be loaded into the given namespace,
+    /// without mapping anything.
+    ///
+    /// Dependencies are looked for the same way they are while loading, but
+    /// only the object's own references are checked, not those of the objects
+    /// that would be loaded for it.
+    pub fn verify_object(
+        &self,
+        namespace: &str,
+        object_bytes: &[u8],
+    ) -> Result<ObjectVerification, LoaderError> {
+        let elf_file = ElfFile::new(object_bytes)?;
+        if elf_file.header.get_type() != ObjectFileType::Relocatable {
+            return Err(LoaderError::Other("not a relocatable ELF file"));
+        }
+
+        let mut verification = ObjectVerification::default();
+        for entry in elf_file.get_symbol_table()? {
+            if !entry.is_global() || !entry.is_undefined() {
+                continue;
+            }
+            let name: Arc<str> = rustc_demangle::demangle(entry.get_name(&elf_file)?)
+                .to_string()
+                .into();
+            if verification
+                .undefined_symbols
+                .iter()
+                .any(|symbol| symbol.name == name)
+            {
+                continue;
+            }
+
+            let resolution = if self.find_section(namespace, &name).is_some() {
+                SymbolResolution::Loaded
+            } else if entry.is_weak() {
+                // Weak references never cause objects to be loaded.
+                SymbolResolution::Missing
+            } else {
+                self.find_dependency(namespace, &name)?
+                    .map_or(SymbolResolution::Missing, SymbolResolution::Load)
+            };
+            verification.undefined_symbols.push(UndefinedSymbol {
+                name,
+                weak: entry.is_weak(),
+                resolution,
+            });
+        }
+
+        // Write each relocation into a scratch buffer to find the unsupported ones.
+        for section in elf_file
+            .section_iter()
+            .filter(|section| section.get_type() == Ok(SectionHeaderType::Rela))
+        {
+            let Ok(SectionData::Rela(rela_array)) = section.get_data(&elf_file) else {
+                return Err(LoaderError::Other(
+                    "found `rela` section that wasn't able to be parsed",
+                ));
+            };
+            for rela_entry in rela_array {
+                let relocation = Relocation {
+                    offset: 0,
+                    addend: 0,
+                    ..Relocation::from(rela_entry)
+                };
+                if let Err(LoaderError::UnsupportedRelocation(kind)) = write_relocation(
+                    relocation,
+                    &mut [0; size_of::<u64>()],
+                    0,
+                    VirtualAddress::new(0),
+                    &mut Veneers::empty(),
+                ) {
+                    verification.unsupported_relocations.insert(kind);
+                }
+            }
+        }
+
+        Ok(verification)
+    }
+
+    /// Find the name of the object that [`Loader::get_or_load_section`] would
+    /// load for a symbol that isn't loaded, without loading it.
+    fn find_dependency(
+        &self,
+        namespace: &str,
+        symbol: &str,
+    ) -> Result<Option<String>, LoaderError> {
+        if let Some((object, _section_index)) = self.get_or_read_symbol_index().get(symbol)
+            && !self.is_loaded(namespace, &object.name)
+        {
+            return Ok(Some(object.name.clone()));
+        }
+
+        let provider = global_object_provider();
+        for crate_name in crate_names_in_symbol(symbol) {
+            if self.is_loaded(namespace, crate_name) {
+                continue;
+            }
+            if provider
+                .list_objects(crate_name)
+                .map_err(LoaderError::Io)?
+                .iter()
+                .any(|path| *path == object_path(crate_name))
+            {
+                return Ok(Some(crate_name.to_string()));
+            }
+            if let Some(archive) = self.get_or_index_archive(crate_name)?
+                && let Some(&member_offset) = archive.symbols.get(symbol)
+            {
+                let member = ar::Archive::new(&archive.bytes)?.member(member_offset)?;
+                return Ok(Some(format!("{}({})", archive.file_name, member.name)));
+            }
+        }
+
+        Ok(None)
+    }
+
+    /// Report anything about the currently loaded objects that weakens the
+    /// system's hardening.
+    pub fn audit(&self) -> LoaderAudit {
+        let mut audit = LoaderAudit::default();
+
+        for object in self
+            .namespaces
+            .lock()
+            .values()
+            .flat_map(|namespace| namespace.objects.values())
+        {
+            let object = object.lock();
+
+            for mapping in [
+                &object.executable_mapping,
+                &object.read_only_mapping,
+                &object.read_write_mapping,
+                &object.veneer_mapping,
+                &object.lazy_stub_mapping,
+            ]
+            .into_iter()
+            .flatten()
+            {
+                let mapping = mapping.lock();
+                audit.mappings.push(MappingAudit {
+                    object: object.name.clone(),
+                    name: mapping.name.clone(),
+                    addr: mapping.addr,
+                    size: mapping.size,
+                    flags: mapping.flags,
+                });
+            }
+            if let Some(mapping) = &object.image_mapping {
+                let mapping = mapping.lock();
+                for segment in &object.image_segments {
+                    audit.mappings.push(MappingAudit {
+                        object: object.name.clone(),
+                        name: format!("{}+{:#x}", mapping.name, segment.offset),
+                        addr: mapping.addr + segment.offset,
+                        size: segment.size,
+                        flags: segment.flags,
+                    });
+                }
+            }
+            for section in object
+                .text_relocations
+                .iter()
+                .filter_map(|index| object.sections.get(index))
+            {
+                audit.text_relocations.push(SectionAudit {
+                    object: object.name.clone(),
+                    section: section.name.clone(),
+                    addr: section.addr,
+                });
+            }
+            for symbol in &object.unresolved_weak_symbols {
+                audit.unresolved_weak_symbols.push(SymbolAudit {
+                    object: object.name.clone(),
+                    symbol: symbol.clone(),
+                });
+            }
+            if !is_in_search_path(&object.path) {
+                audit.objects_outside_search_path.push(ObjectAudit {
+                    object: object.name.clone(),
+                    path: object.path.clone(),
+                });
+            }
+        }
+
+        audit.mappings.sort_by_key(|mapping| mapping.addr);
+        audit.text_relocations.sort_by_key(|section| section.addr);
+        audit
+            .unresolved_weak_symbols
+            .sort_by(|a, b| (&a.object, &a.symbol).cmp(&(&b.object, &b.symbol)));
+        audit
+            .objects_outside_search_path
+            .sort_by(|a, b| a.object.cmp(&b.object));
+
+        audit
+    }
+
     /// Get the [object](LoadedObject) with the given name.
+    /// Report how much memory the loaded objects use, and how many of the
+    /// symbols they refer to were resolved.
+    pub fn stats(&self) -> LoaderStats {
+        let lazy_stubs = self.lazy_stubs.lock();
+        let mut stats = LoaderStats {
+            lazy_stub_count: lazy_stubs.len(),
+            leaked_bytes: self.leaked_bytes.load(Ordering::Relaxed),
+            ..LoaderStats::default()
+        };
+
+        for object in self.iter_objects().filter_map(|object| object.upgrade()) {
+            let owner = Arc::downgrade(&object);
+            let object = object.lock();
+            let mut usage = UsageStats {
+                section_count: object.sections.len(),
+                global_section_count: object.global_sections.len(),
+                unresolved_weak_symbols: object.unresolved_weak_symbols.len(),
+                ..UsageStats::default()
+            };
+
+            let mapping_size = |mapping: &Option<Arc<Mutex<KernelMapping>>>| {
+                mapping.as_ref().map_or(0, |mapping| mapping.lock().size)
+            };
+            usage.text_bytes = mapping_size(&object.executable_mapping)
+                + mapping_size(&object.veneer_mapping)
+                + mapping_size(&object.lazy_stub_mapping);
+            usage.rodata_bytes = mapping_size(&object.read_only_mapping);
+            usage.data_bytes = mapping_size(&object.read_write_mapping);
+            for segment in &object.image_segments {
+                if !segment.flags.get(PageTableFlags::NO_EXECUTE) {
+                    usage.text_bytes += segment.size;
+                } else if segment.flags.get(PageTableFlags::WRITABLE) {
+                    usage.data_bytes += segment.size;
+                } else {
+                    usage.rodata_bytes += segment.size;
+                }
+            }
+
+            // Each section of another object that relocations were written against is
+            // counted once.
+            let mut resolved = BTreeSet::new();
+            for section in object.sections.values() {
+                for dependency in section.dependencies.lock().iter() {
+                    resolved.insert(Arc::as_ptr(&dependency.source).addr());
+                }
+            }
+            usage.resolved_symbols = resolved.len();
+            for stub in lazy_stubs.iter().filter(|stub| stub.object.ptr_eq(&owner)) {
+                if stub.resolved.is_some() {
+                    usage.resolved_symbols += 1;
+                } else {
+                    usage.lazy_symbols += 1;
+                }
+            }
+
+            stats.total += usage;
+            stats.objects.push(ObjectStats {
+                namespace: object.namespace.clone(),
+                name: object.name.clone(),
+                usage,
+            });
+        }
+
+        stats
+    }
+
+    /// Get every loaded object, in every namespace, sorted by namespace and
+    /// name.
+    ///
+    /// The objects are collected up front, so they can be loaded and unloaded
+    /// while iterating.
+    pub fn iter_objects(&self) -> impl Iterator<Item = Weak<Mutex<LoadedObject>>> + use<> {
+        let mut objects = self
+            .namespaces
+            .lock()
+            .iter()
+            .flat_map(|(namespace, ns)| {
+                ns.objects
+                    .iter()
+                    .map(move |(name, object)| ((namespace.clone(), name.clone()), object))
+            })
+            .map(|(key, object)| (key, Arc::downgrade(object)))
+            .collect::<Vec<_>>();
+        objects.sort_by(|(a, _), (b, _)| a.cmp(b));
+
+        objects.into_iter().map(|(_key, object)| object)
+    }
+
     pub fn get_object(&self, name: &str) -> Option<Weak<Mutex<LoadedObject>>> {
-        self.objects.lock().get(name).map(Arc::downgrade)
+        self.get_object_in(DEFAULT_NAMESPACE, name)
+    }
+
+    /// Get the object with the given name in the given namespace.
+    pub fn get_object_in(&self, namespace: &str, name: &str) -> Option<Weak<Mutex<LoadedObject>>> {
+        self.namespaces
+            .lock()
+            .get(namespace)?
+            .objects
+            .get(name)
+            .map(Arc::downgrade)
     }
 
-    /// Get the first [section](LoadedSection) that starts with the given prefix
-    /// and ends with the given suffix.
+    /// Get the first [section](LoadedSection) in any namespace that starts with
+    /// the given prefix and ends with the given suffix.
     pub fn get_section(&self, prefix: &str, suffix: &str) -> Option<Weak<LoadedSection>> {
-        self.sections
+        self.namespaces
             .lock()
-            .iter()
+            .values()
+            .flat_map(|namespace| namespace.sections.iter())
             .find(|(name, _section)| name.starts_with(prefix) && name.ends_with(suffix))
             .map(|(_name, section)| section.clone())
     }
 
-    /// Get the first text [section](LoadedSection) that starts with the given
-    /// prefix and ends with the given suffix.
+    /// Get the first text [section](LoadedSection) in any namespace that starts
+    /// with the given prefix and ends with the given suffix.
     pub fn get_text_section(&self, prefix: &str, suffix: &str) -> Option<Weak<LoadedSection>> {
-        self.sections
+        self.namespaces
             .lock()
-            .iter()
+            .values()
+            .flat_map(|namespace| namespace.sections.iter())
             .find(|(name, section)| {
                 section
                     .upgrade()
@@ -425,22 +1036,282 @@ impl Loader {
             .map(|(_range, section)| section.clone())
     }
 
+    /// Search the given namespace, then the namespaces it shares, for a
+    /// [section](LoadedSection) or [object](LoadedObject).
+    fn find_in_namespace<T>(
+        &self,
+        namespace: &str,
+        find: impl Fn(&Namespace) -> Option<T>,
+    ) -> Option<T> {
+        let namespaces = self.namespaces.lock();
+        let namespace = namespaces.get(namespace)?;
+
+        find(namespace).or_else(|| {
+            namespace
+                .shared
+                .iter()
+                .filter_map(|shared| namespaces.get(shared))
+                .find_map(&find)
+        })
+    }
+
+    /// Find a global [section](LoadedSection) by name, as seen from the given
+    /// namespace.
+    fn find_section(&self, namespace: &str, name: &str) -> Option<Weak<LoadedSection>> {
+        self.find_in_namespace(namespace, |namespace| namespace.sections.get(name).cloned())
+    }
+
+    /// Whether an object with the given name is loaded in the given namespace,
+    /// or a namespace it shares.
+    fn is_loaded(&self, namespace: &str, object_name: &str) -> bool {
+        self.find_in_namespace(namespace, |namespace| {
+            namespace.objects.get(object_name).map(|_| ())
+        })
+        .is_some()
+    }
+
+    /// Read the object that should define the given symbol for a crate. That's
+    /// either the crate's own object file, or the member of the crate's
+    /// [archive](archive_file_names) whose symbol table defines the symbol.
+    ///
+    /// Returns `None` if the member is already loaded, or the archive doesn't
+    /// define the symbol.
+    fn read_dependency(
+        &self,
+        namespace: &str,
+        crate_name: &str,
+        symbol: &str,
+    ) -> Result<Option<(String, Vec<u8>)>, LoaderError> {
+        let provider = global_object_provider();
+        if provider
+            .list_objects(crate_name)
+            .map_err(LoaderError::Io)?
+            .iter()
+            .any(|path| *path == object_path(crate_name))
+        {
+            return Ok(Some((
+                crate_name.to_string(),
+                provider.read_object(crate_name).map_err(LoaderError::Io)?,
+            )));
+        }
+
+        let archive = self
+            .get_or_index_archive(crate_name)?
+            .ok_or("no object found")?;
+        let Some(&member_offset) = archive.symbols.get(symbol) else {
+            return Ok(None);
+        };
+        let member = ar::Archive::new(&archive.bytes)?.member(member_offset)?;
+        let object_name = format!("{}({})", archive.file_name, member.name);
+        if self.is_loaded(namespace, &object_name) {
+            return Ok(None);
+        }
+
+        Ok(Some((object_name, member.data.to_vec())))
+    }
+
+    /// Get the index of a crate's archive, reading and indexing it if it hasn't
+    /// been already.
+    ///
+    /// Returns `None` if the crate doesn't have an archive.
+    fn get_or_index_archive(
+        &self,
+        crate_name: &str,
+    ) -> Result<Option<Arc<ArchiveIndex>>, LoaderError> {
+        if let Some(archive) = self.archives.lock().get(crate_name) {
+            return Ok(Some(Arc::clone(archive)));
+        }
+
+        let provider = global_object_provider();
+        let Some(file_name) = archive_file_names(crate_name)
+            .into_iter()
+            .find(|file_name| {
+                provider.list_objects(file_name).is_ok_and(|paths| {
+                    paths
+                        .iter()
+                        .any(|path| path.strip_prefix(OBJECT_SEARCH_PATH) == Some(file_name))
+                })
+            })
+        else {
+            return Ok(None);
+        };
+        let bytes = provider
+            .read_object(&format!("{OBJECT_SEARCH_PATH}{file_name}"))
+            .map_err(LoaderError::Io)?;
+
+        let mut symbols = HashMap::default();
+        {
+            let archive = ar::Archive::new(&bytes)?;
+            for symbol in archive.symbols() {
+                let (name, member_offset) = symbol?;
+                symbols.insert(rustc_demangle::demangle(name).to_string(), member_offset);
+            }
+
+            // Without a symbol table, fall back to the symbol tables of the members.
+            if symbols.is_empty() {
+                for member in archive.members() {
+                    let member = member?;
+                    let Ok(elf_file) = ElfFile::new(member.data) else {
+                        continue;
+                    };
+                    for entry in elf_file.get_symbol_table()?.iter() {
+                        if entry.is_global() && !entry.is_undefined() {
+                            let name = entry.get_name(&elf_file)?;
+                            symbols
+                                .insert(rustc_demangle::demangle(name).to_string(), member.offset);
+                        }
+                    }
+                }
+            }
+        }
+        debug!("Indexed {} symbol(s) in `{file_name}`", symbols.len());
+
+        let archive = Arc::new(ArchiveIndex {
+            file_name: file_name.into(),
+            bytes,
+            symbols,
+        });
+        self.archives
+            .lock()
+            .insert(crate_name.into(), Arc::clone(&archive));
+
+        Ok(Some(archive))
+    }
+
+    /// Get the [symbol index](symbol_index) of the [`OBJECT_SEARCH_PATH`],
+    /// reading it if it hasn't been already.
+    ///
+    /// The index is written by the runner when it builds the search path,
+    /// since the file system is read-only. Objects that have been modified (or
+    /// resized) since the index was written are left out of it, and their
+    /// symbols are found the slow way instead.
+    fn get_or_read_symbol_index(&self) -> Arc<SymbolIndex> {
+        let mut symbol_index = self.symbol_index.lock();
+        if let Some(index) = symbol_index.as_ref() {
+            return Arc::clone(index);
+        }
+
+        let provider = global_object_provider();
+        let path = format!("{OBJECT_SEARCH_PATH}{}", symbol_index::FILE_NAME);
+        let index = provider
+            .object_entry(&path)
+            .and_then(|entry| Ok((entry, provider.read_object(&path)?)))
+            .and_then(|(entry, bytes)| {
+                let text = core::str::from_utf8(&bytes).map_err(|_| "symbol index isn't UTF-8")?;
+                Ok((entry, SymbolIndex::parse(text)?))
+            });
+        let index = match index {
+            Ok((index_entry, mut index)) => {
+                let stale = index.retain_objects(|object| {
+                    provider
+                        .object_entry(&format!("{OBJECT_SEARCH_PATH}{}", object.file))
+                        .is_ok_and(|entry| {
+                            entry.size == object.size && entry.modified <= index_entry.modified
+                        })
+                });
+                debug!(
+                    "Read {} symbol(s) from `{path}`, ignoring {stale} stale object(s)",
+                    index.symbol_count(),
+                );
+                index
+            }
+            Err(error) => {
+                warn!("Not using a symbol index for `{OBJECT_SEARCH_PATH}`: {error}");
+                SymbolIndex::default()
+            }
+        };
+
+        Arc::clone(symbol_index.insert(Arc::new(index)))
+    }
+
+    /// Read the object that the [symbol index](symbol_index) says defines the
+    /// given symbol, along with the index of the section that defines it.
+    ///
+    /// Returns `None` if the symbol isn't indexed, or its object is already
+    /// loaded.
+    fn read_indexed_dependency(
+        &self,
+        namespace: &str,
+        symbol: &str,
+    ) -> Result<Option<(String, Vec<u8>, usize)>, LoaderError> {
+        let index = self.get_or_read_symbol_index();
+        let Some((object, section_index)) = index.get(symbol) else {
+            return Ok(None);
+        };
+        if self.is_loaded(namespace, &object.name) {
+            return Ok(None);
+        }
+
+        let bytes = global_object_provider()
+            .read_object(&format!("{OBJECT_SEARCH_PATH}{}", object.file))
+            .map_err(LoaderError::Io)?;
+        let bytes = match object
+            .name
+            .strip_prefix(object.file.as_str())
+            .and_then(|member| member.strip_prefix('('))
+            .and_then(|member| member.strip_suffix(')'))
+        {
+            Some(member_name) => ar::Archive::new(&bytes)?
+                .members()
+                .find(|member| member.is_ok_and(|member| member.name == member_name))
+                .ok_or("indexed archive member not found")??
+                .data
+                .to_vec(),
+            None => bytes,
+        };
+
+        Ok(Some((object.name.clone(), bytes, section_index)))
+    }
+
     fn get_or_load_section(
         &self,
         name: &str,
         for_object: &LoadedObject,
         address_space: &AddressSpace,
         start_page: &mut Page,
-    ) -> Result<Weak<LoadedSection>, &'static str> {
-        if let Some(section) = self.sections.lock().get(name) {
-            return Ok(section.clone());
+    ) -> Result<Weak<LoadedSection>, LoaderError> {
+        let find_section = || self.find_section(&for_object.namespace, name);
+        if let Some(section) = find_section() {
+            return Ok(section);
+        }
+
+        if let Some((object_name, object_bytes, section_index)) =
+            self.read_indexed_dependency(&for_object.namespace, name)?
+        {
+            trace!(
+                "Loading indexed object `{object_name}` as a dependency of `{}` for symbol \
+                `{name}`",
+                for_object.name,
+            );
+
+            let object = self.load_object_impl(
+                &for_object.namespace,
+                &object_name,
+                &object_bytes,
+                address_space,
+                start_page,
+            )?;
+            let section = object
+                .lock()
+                .sections
+                .get(&section_index)
+                .filter(|section| section.global && *section.name == *name)
+                .map(Arc::downgrade);
+            if let Some(section) = section.or_else(find_section) {
+                return Ok(section);
+            }
         }
 
-        for object_name in crate_names_in_symbol(name) {
+        for crate_name in crate_names_in_symbol(name) {
             // Skip already loaded objects.
-            if self.get_object(&object_name).is_some() {
+            if self.is_loaded(&for_object.namespace, crate_name) {
                 continue;
             }
+            let Some((object_name, object_bytes)) =
+                self.read_dependency(&for_object.namespace, crate_name, name)?
+            else {
+                continue;
+            };
 
             trace!(
                 "Loading object `{object_name}` as a dependency of `{}` for symbol `{name}`",
@@ -448,27 +1319,36 @@ impl Loader {
             );
 
             self.load_object_impl(
+                &for_object.namespace,
                 &object_name,
-                &global_object_provider().read_object(&object_name)?,
+                &object_bytes,
                 address_space,
                 start_page,
             )?;
 
-            if let Some(section) = self.sections.lock().get(name) {
-                return Ok(section.clone());
+            if let Some(section) = find_section() {
+                return Ok(section);
             }
         }
 
         // error!("Failed to load `{name}` for `{}`", for_object.name);
 
-        Err("section not found")
+        Err(LoaderError::MissingSymbol {
+            symbol: name.into(),
+            object: for_object.name.clone(),
+        })
     }
 
     fn add_alias_to_section(&self, name: &str, section: Weak<LoadedSection>) {
-        self.sections.lock().insert(name.into(), section);
+        self.namespaces
+            .lock()
+            .entry(DEFAULT_NAMESPACE.into())
+            .or_default()
+            .sections
+            .insert(name.into(), section);
     }
 
-    /// Load an object into memory.
+    /// Load an object into memory, in the [`DEFAULT_NAMESPACE`].
     ///
     /// Internally, this method uses the [`GlobalObjectProvider`] to read
     /// object data.
@@ -483,54 +1363,1271 @@ impl Loader {
         &self,
         object_name: &str,
         address_space: &AddressSpace,
-        mut start_page: Page,
-    ) -> Result<Arc<Mutex<LoadedObject>>, &'static str> {
-        info!("Loading `{object_name}`...");
-        let object_bytes = global_object_provider().read_object(object_name)?;
-        self.load_object_impl(object_name, &object_bytes, address_space, &mut start_page)
+        start_page: Page,
+    ) -> Result<Arc<Mutex<LoadedObject>>, LoaderError> {
+        self.load_object_in(DEFAULT_NAMESPACE, object_name, address_space, start_page)
     }
 
-    fn load_object_impl(
+    /// Load an object into memory, in the given namespace. The namespace is
+    /// created without any shared namespaces if it doesn't exist yet.
+    ///
+    /// See [`Loader::load_object`] for the other arguments.
+    pub fn load_object_in(
         &self,
+        namespace: &str,
         object_name: &str,
-        object_bytes: &[u8],
         address_space: &AddressSpace,
-        start_page: &mut Page,
-    ) -> Result<Arc<Mutex<LoadedObject>>, &'static str> {
-        let mut mappings = BTreeSet::new();
-        let (object, elf_file) = self.load_object_sections(
+        mut start_page: Page,
+    ) -> Result<Arc<Mutex<LoadedObject>>, LoaderError> {
+        info!("Loading `{object_name}` into `{namespace}`...");
+        let object_bytes = global_object_provider()
+            .read_object(object_name)
+            .map_err(LoaderError::Io)?;
+        if VERIFY_OBJECTS && !is_shared_object(&object_bytes) {
+            let verification = self.verify_object(namespace, &object_bytes)?;
+            debug!("`{object_name}`:\n{verification}");
+            if let Some(error) = verification.error(object_name) {
+                return Err(error);
+            }
+        }
+        self.load_object_impl(
+            namespace,
+            object_name,
+            &object_bytes,
+            address_space,
+            &mut start_page,
+        )
+    }
+
+    /// Load several objects into memory at once, in the given namespace.
+    ///
+    /// Every object's sections are loaded before any of them are relocated,
+    /// in a final pass. This way, the objects can refer to each other without
+    /// being loaded as each other's dependencies, and loading each object's
+    /// sections doesn't depend on any of the others.
+    ///
+    /// If any object fails to load, every object in the batch that was already
+    /// loaded is unloaded again, so that nothing unrelocated is left in the
+    /// namespace.
+    ///
+    /// NOTE: With `PARALLEL_BATCH_LOADING`, the relocatable objects' sections
+    ///       are loaded in parallel on every online processor (see
+    ///       [`smp::processor_count`]), then mapped and added to the namespace
+    ///       in order. Shared objects and relocation still run on this
+    ///       processor alone, since they can load other objects. See
+    ///       [`Loader::benchmark_batch_loading`] for how long each phase takes.
+    ///
+    /// See [`Loader::load_object`] for the other arguments.
+    pub fn load_objects_in(
+        &self,
+        namespace: &str,
+        object_names: &[&str],
+        address_space: &AddressSpace,
+        start_page: Page,
+    ) -> Result<Vec<Arc<Mutex<LoadedObject>>>, LoaderError> {
+        info!("Loading {object_names:?} into `{namespace}`...");
+        let mut objects = Vec::with_capacity(object_names.len());
+        let result = self.load_batch(
+            namespace,
+            object_names,
+            address_space,
+            start_page,
+            &mut objects,
+            PARALLEL_BATCH_LOADING,
+        );
+        match result {
+            Ok(timings) => {
+                debug!(
+                    "Loaded {} object(s) into `{namespace}`: {timings}",
+                    objects.len(),
+                );
+                Ok(objects)
+            }
+            Err(error) => {
+                warn!(
+                    "Failed to load {object_names:?}, unloading the {} object(s) already loaded",
+                    objects.len(),
+                );
+                // Later objects can depend on earlier ones, but not the other way around.
+                for object in objects.into_iter().rev() {
+                    let name = object.lock().name.clone();
+                    self.remove_object(self.namespaces.lock(), &name, object, false);
+                }
+                Err(error)
+            }
+        }
+    }
+
+    /// Load the given objects into temporary namespaces as a batch (see
+    /// [`Loader::load_objects_in`]) with their sections loaded in parallel,
+    /// then as a batch on this processor alone, then one at a time, logging
+    /// how long each took, and unload them again.
+    ///
+    /// The objects must not be loaded by name as dependencies while
+    /// relocating, or they would be loaded into the default namespace.
+    pub fn benchmark_batch_loading(&self, object_names: &[&str]) -> Result<(), LoaderError> {
+        const BATCH_NAMESPACE: &str = "benchmark.batch";
+        const SEQUENTIAL_NAMESPACE: &str = "benchmark.sequential";
+
+        let address_space = AddressSpace::new("benchmark", None);
+        // The actual value of this address doesn't matter.
+        let start_page = Page::containing_addr(VirtualAddress::new(0x3333_0000_0000));
+        self.create_namespace(BATCH_NAMESPACE, &[DEFAULT_NAMESPACE]);
+        self.create_namespace(SEQUENTIAL_NAMESPACE, &[DEFAULT_NAMESPACE]);
+
+        let mut batch_timings = [BatchTimings::default(); 2];
+        for (timings, parallel) in batch_timings.iter_mut().zip([true, false]) {
+            let mut objects = Vec::with_capacity(object_names.len());
+            let batch_result = self.load_batch(
+                BATCH_NAMESPACE,
+                object_names,
+                &address_space,
+                start_page,
+                &mut objects,
+                parallel,
+            );
+            for object in objects.into_iter().rev() {
+                let name = object.lock().name.clone();
+                self.remove_object(self.namespaces.lock(), &name, object, true);
+            }
+            *timings = batch_result?;
+        }
+        let [parallel_timings, batch_timings] = batch_timings;
+
+        let start = time::now();
+        let mut sequential_result = Ok(());
+        for object_name in object_names {
+            if let Err(error) = self.load_object_in(
+                SEQUENTIAL_NAMESPACE,
+                object_name,
+                &address_space,
+                start_page,
+            ) {
+                sequential_result = Err(error);
+                break;
+            }
+        }
+        let sequential_time = start.elapsed();
+        for object_name in object_names.iter().rev() {
+            let _ = self.unload_object_in(SEQUENTIAL_NAMESPACE, object_name);
+        }
+        sequential_result?;
+
+        info!(
+            "Loading {object_names:?} as a batch took {parallel_timings} with parallel sections, \
+            {batch_timings} without, and one at a time took {sequential_time:?}",
+        );
+        info!(
+            "Loading sections on {} processor(s) was {:.2}x as fast as on one",
+            parallel_timings.processors,
+            batch_timings.sections_time.as_secs_f64()
+                / parallel_timings.sections_time.as_secs_f64(),
+        );
+
+        Ok(())
+    }
+
+    /// See [`Loader::load_objects_in`]. The loaded objects are added to
+    /// `objects` as they're loaded, so that they can be unloaded again if a
+    /// later one fails.
+    ///
+    /// If `parallel` is set, the relocatable objects' sections are loaded
+    /// [in parallel](smp::map_parallel) on every online processor.
+    fn load_batch(
+        &self,
+        namespace: &str,
+        object_names: &[&str],
+        address_space: &AddressSpace,
+        mut start_page: Page,
+        loaded_objects: &mut Vec<Arc<Mutex<LoadedObject>>>,
+        parallel: bool,
+    ) -> Result<BatchTimings, LoaderError> {
+        let start = time::now();
+
+        let provider = global_object_provider();
+        let object_bytes = object_names
+            .iter()
+            .map(|name| provider.read_object(name).map_err(LoaderError::Io))
+            .collect::<Result<Vec<_>, _>>()?;
+        let read_time = start.elapsed();
+
+        // Shared objects are already linked, so they're loaded in one go.
+        let mut shared_objects = Vec::with_capacity(object_names.len());
+        let mut relocatables = Vec::with_capacity(object_names.len());
+        for (object_name, object_bytes) in object_names.iter().zip(&object_bytes) {
+            if is_shared_object(object_bytes) {
+                let object = self.load_shared_object(
+                    namespace,
+                    object_name,
+                    object_bytes,
+                    address_space,
+                    &mut start_page,
+                )?;
+                loaded_objects.push(Arc::clone(&object));
+                shared_objects.push(Some(object));
+            } else {
+                relocatables.push((*object_name, object_bytes.as_slice()));
+                shared_objects.push(None);
+            }
+        }
+
+        let mut results =
+            smp::map_parallel(&relocatables, parallel, |(object_name, object_bytes)| {
+                self.load_object_sections(namespace, object_name, object_bytes)
+            })
+            .into_iter()
+            .zip(&relocatables);
+        // The objects are mapped and registered in order, so that the batch is laid
+        // out the same way however their sections were loaded. Every object that
+        // loaded is registered before returning an error, so that it's unloaded
+        // with the rest of the batch.
+        let mut objects = Vec::with_capacity(object_names.len());
+        let mut first_error = None;
+        for shared_object in shared_objects {
+            if let Some(object) = shared_object {
+                objects.push((object, None));
+                continue;
+            }
+
+            let (result, (object_name, _)) = results
+                .next()
+                .expect("every relocatable object should have a result");
+            let (object, elf_file) = match result {
+                Ok(loaded) => loaded,
+                Err(error) => {
+                    first_error.get_or_insert(error);
+                    continue;
+                }
+            };
+            let mut mappings = BTreeSet::new();
+            self.register_unrelocated_object(
+                namespace,
+                object_name,
+                &object,
+                address_space,
+                &mut start_page,
+                &mut mappings,
+            );
+            loaded_objects.push(Arc::clone(&object));
+            objects.push((object, Some((elf_file, mappings))));
+        }
+        if let Some(error) = first_error {
+            return Err(error);
+        }
+        let sections_time = start.elapsed() - read_time;
+
+        for (object, unrelocated) in &mut objects {
+            if let Some((elf_file, mappings)) = unrelocated {
+                self.relocate_object(elf_file, object, address_space, &mut start_page, mappings)?;
+            }
+        }
+        // Every object in the batch is relocated before any of their initializers run,
+        // since they can call into each other.
+        for (object, _unrelocated) in &objects {
+            run_array_functions(object, false);
+        }
+        let relocation_time = start.elapsed() - read_time - sections_time;
+
+        Ok(BatchTimings {
+            read_time,
+            sections_time,
+            relocation_time,
+            processors: if parallel { smp::processor_count() } else { 1 },
+        })
+    }
+
+    /// Allocate a new [`TlsArea`] containing the initial thread-local storage
+    /// of every loaded object, and map it into the given address space so that
+    /// it ends before `end_page`.
+    ///
+    /// Returns `None` if no loaded object has thread-local storage. Objects
+    /// that are loaded afterwards won't have any storage in the area.
+    pub fn allocate_tls_area(
+        &self,
+        name: &str,
+        address_space: &AddressSpace,
+        end_page: Page,
+    ) -> Result<Option<TlsArea>, LoaderError> {
+        let (layout, sections) = {
+            let tls = self.tls.lock();
+            if tls.layout.is_empty() {
+                return Ok(None);
+            }
+
+            (tls.layout, tls.sections.clone())
+        };
+
+        // The thread pointer points to the end of the block, and the first word there
+        // points to itself.
+        let block_size = layout.block_size();
+        let area_size = layout.area_size();
+        let mut mapping = KernelMapping::new(
+            format!("{name}.tls"),
+            area_size,
+            self.final_mapping_flags(false, true),
+        );
+        let pages = PageRange::from_end_size(end_page, area_size);
+        let thread_pointer = pages.start.base_addr() + block_size;
+
+        mapping.as_slice_mut(0, block_size).fill(0);
+        mapping
+            .as_slice_mut(block_size, size_of::<u64>())
+            .copy_from_slice(&(thread_pointer.to_raw() as u64).to_ne_bytes());
+        for section in sections.iter().filter_map(Weak::upgrade) {
+            if section.kind != SectionKind::TlsData {
+                continue;
+            }
+            let data = section
+                .mapping
+                .lock()
+                .as_slice_mut(section.mapping_offset, section.size)
+                .to_vec();
+            let offset = layout
+                .area_offset(section.addr.to_raw())
+                .ok_or("TLS section is outside of the static TLS block")?;
+            mapping
+                .as_slice_mut(offset, section.size)
+                .copy_from_slice(&data);
+        }
+
+        mapping
+            .map_into(address_space, pages, mapping.flags)
+            .map_err(|_| "failed to map TLS area")?;
+
+        Ok(Some(TlsArea {
+            mapping,
+            thread_pointer,
+        }))
+    }
+
+    /// Unload an object from the given namespace, removing its sections and
+    /// unmapping its memory.
+    ///
+    /// This fails if any other loaded object depends on it. The caller must
+    /// make sure that no running process still uses the object, since the
+    /// address spaces it was mapped into aren't tracked. Any thread-local
+    /// storage the object had isn't reclaimed.
+    pub fn unload_object_in(&self, namespace: &str, name: &str) -> Result<(), LoaderError> {
+        let object = self
+            .namespaces
+            .lock()
+            .get(namespace)
+            .and_then(|namespace| namespace.objects.get(name))
+            .cloned()
+            .ok_or("object isn't loaded")?;
+
+        self.unload_object_impl(name, object)
+    }
+
+    /// Replace a loaded object with a newly loaded one, relocating every
+    /// section of another object that depends on the old object against the
+    /// new object's section with the same name, then unloading the old object.
+    ///
+    /// The new object can have the same name as the old one, to reload it.
+    /// Fails if the new object is missing a section that's depended on, in
+    /// which case both objects are left loaded. The old object's data isn't
+    /// copied to the new one.
+    pub fn swap_object(
+        &self,
+        old_name: &str,
+        new_name: &str,
+        address_space: &AddressSpace,
+        start_page: Page,
+    ) -> Result<Arc<Mutex<LoadedObject>>, LoaderError> {
+        let old_object = self
+            .get_object(old_name)
+            .and_then(|object| object.upgrade())
+            .ok_or("object isn't loaded")?;
+
+        // The new object shouldn't be deduplicated against the old object's COMDAT
+        // groups, since they're about to be unloaded.
+        let old_comdat_groups = self.take_comdat_groups(&Arc::downgrade(&old_object));
+        let new_object = match self.load_object(new_name, address_space, start_page) {
+            Ok(object) => object,
+            Err(error) => {
+                let mut namespaces = self.namespaces.lock();
+                for (namespace, signature, group) in old_comdat_groups {
+                    namespaces
+                        .entry(namespace)
+                        .or_default()
+                        .comdat_groups
+                        .entry(signature)
+                        .or_insert(group);
+                }
+                return Err(error);
+            }
+        };
+
+        let mut replacements = Vec::new();
+        {
+            let old_lock = old_object.lock();
+            let new_lock = new_object.lock();
+            for old_section in old_lock.sections.values() {
+                let dependents = self.dependents_of(old_section);
+                if dependents.is_empty() {
+                    continue;
+                }
+                let new_section = new_lock
+                    .sections
+                    .values()
+                    .find(|section| {
+                        section.kind == old_section.kind && section.name == old_section.name
+                    })
+                    .ok_or_else(|| {
+                        error!(
+                            "`{new_name}` has no replacement for `{}`, which is depended on",
+                            old_section.name,
+                        );
+                        "replacement object is missing a section that's depended on"
+                    })?;
+                replacements.push((Arc::clone(old_section), Arc::clone(new_section), dependents));
+            }
+        }
+
+        let old_owner = Arc::downgrade(&old_object);
+        let new_owner = Arc::downgrade(&new_object);
+        let mut veneers = Veneers::empty();
+        for (old_section, new_section, dependents) in replacements {
+            for dependent in dependents {
+                {
+                    let mut dependencies = dependent.dependencies.lock();
+                    let mut mapping = dependent.mapping.lock();
+                    let target_slice =
+                        mapping.as_slice_mut(0, dependent.mapping_offset + dependent.size);
+                    for dependency in dependencies
+                        .iter_mut()
+                        .filter(|dependency| Arc::ptr_eq(&dependency.source, &old_section))
+                    {
+                        write_relocation(
+                            dependency.relocation,
+                            target_slice,
+                            dependent.mapping_offset + dependency.relocation.offset,
+                            new_section.addr + dependency.source_offset,
+                            &mut veneers,
+                        )?;
+                        dependency.source = Arc::clone(&new_section);
+                    }
+                }
+                new_section
+                    .dependents
+                    .lock()
+                    .push(Arc::downgrade(&dependent));
+
+                if let Some(owner) = dependent.owner.upgrade() {
+                    let mut owner = owner.lock();
+                    owner
+                        .dependencies
+                        .retain(|dependency| !dependency.ptr_eq(&old_owner));
+                    if !owner
+                        .dependencies
+                        .iter()
+                        .any(|dependency| dependency.ptr_eq(&new_owner))
+                    {
+                        owner.dependencies.push(new_owner.clone());
+                    }
+                }
+            }
+        }
+
+        self.unload_object_impl(old_name, old_object)?;
+
+        Ok(new_object)
+    }
+
+    /// Remove the COMDAT groups that an object loaded from every namespace,
+    /// returning them along with the namespace they were in.
+    fn take_comdat_groups(
+        &self,
+        owner: &Weak<Mutex<LoadedObject>>,
+    ) -> Vec<(Arc<str>, Arc<str>, ComdatGroup)> {
+        let mut taken = Vec::new();
+        for (name, namespace) in self.namespaces.lock().iter_mut() {
+            let signatures = namespace
+                .comdat_groups
+                .iter()
+                .filter(|(_signature, group)| group.owner.ptr_eq(owner))
+                .map(|(signature, _group)| Arc::clone(signature))
+                .collect::<Vec<_>>();
+            for signature in signatures {
+                let group = namespace.comdat_groups.remove(&signature).unwrap();
+                taken.push((Arc::clone(name), signature, group));
+            }
+        }
+
+        taken
+    }
+
+    /// Resolve the function that the [lazy stub](Self::set_lazy_binding) with
+    /// the given ID stands in for, loading its object if needed, and patch the
+    /// stub and its call sites to branch to the function directly.
+    ///
+    /// `address_space` is the address space of the process that called the
+    /// stub. Returns the address of the function.
+    pub fn resolve_lazy_stub(
+        &self,
+        id: usize,
+        address_space: &AddressSpace,
+    ) -> Result<VirtualAddress, LoaderError> {
+        let (symbol, object, stub_mapping, stub_offset, sites) = {
+            let stubs = self.lazy_stubs.lock();
+            let stub = stubs.get(id).ok_or("no lazy stub has that ID")?;
+            if let Some(addr) = stub.resolved {
+                return Ok(addr);
+            }
+
+            (
+                stub.symbol.clone(),
+                stub.object
+                    .upgrade()
+                    .ok_or("lazy stub's object was unloaded")?,
+                stub.mapping
+                    .upgrade()
+                    .ok_or("lazy stub's object was unloaded")?,
+                stub.offset,
+                stub.sites.clone(),
+            )
+        };
+
+        let section = {
+            let mut object = object.lock();
+            debug!("Lazily binding `{symbol}` for `{}`", object.name);
+            let section = self
+                .get_or_load_section(
+                    &symbol,
+                    &object,
+                    &AddressSpace::new("lazy_bind", None),
+                    // The actual value of this address doesn't matter.
+                    &mut Page::containing_addr(VirtualAddress::new(0x3333_0000_0000)),
+                )?
+                .upgrade()
+                .ok_or("couldn't upgrade lazily bound section")?;
+
+            if !object
+                .dependencies
+                .iter()
+                .any(|dependency| dependency.ptr_eq(&section.owner))
+            {
+                object.dependencies.push(section.owner.clone());
+                if AUTO_MAP_DEPENDENCIES {
+                    map_dependency(&mut object, &section, address_space, &mut BTreeSet::new());
+                }
+            }
+
+            section
+        };
+
+        let mut veneers = Veneers::empty();
+        for (site, relocation) in sites {
+            let Some(site) = site.upgrade() else {
+                continue;
+            };
+            {
+                let mut mapping = site.mapping.lock();
+                let target_slice = mapping.as_slice_mut(0, site.mapping_offset + site.size);
+                write_relocation(
+                    relocation,
+                    target_slice,
+                    site.mapping_offset + relocation.offset,
+                    section.addr,
+                    &mut veneers,
+                )?;
+            }
+            add_section_dependency(&section, &site, 0, relocation);
+        }
+
+        // Calls that were already on their way to the stub jump straight to the
+        // function.
+        stub_mapping
+            .lock()
+            .as_slice_mut(stub_offset, STUB_SIZE)
+            .copy_from_slice(&lazy_stub::resolved_code(section.addr.to_raw() as u64));
+        self.lazy_stubs.lock()[id].resolved = Some(section.addr);
+
+        Ok(section.addr)
+    }
+
+    /// Get the sections of other objects whose relocations refer to the given
+    /// section.
+    pub fn dependents_of(&self, section: &LoadedSection) -> Vec<Arc<LoadedSection>> {
+        let mut dependents = section.dependents.lock();
+        dependents.retain(|dependent| dependent.strong_count() > 0);
+
+        dependents.iter().filter_map(Weak::upgrade).collect()
+    }
+
+    fn unload_object_impl(
+        &self,
+        name: &str,
+        object: Arc<Mutex<LoadedObject>>,
+    ) -> Result<(), LoaderError> {
+        let owner = Arc::downgrade(&object);
+
+        let namespaces = self.namespaces.lock();
+        for (other_name, other) in namespaces
+            .values()
+            .flat_map(|namespace| namespace.objects.iter())
+        {
+            if Arc::ptr_eq(other, &object) {
+                continue;
+            }
+            if other
+                .lock()
+                .dependencies
+                .iter()
+                .any(|dependency| dependency.ptr_eq(&owner))
+            {
+                error!("Can't unload `{name}` because `{other_name}` depends on it");
+                return Err(LoaderError::Other(
+                    "object is still depended on by another object",
+                ));
+            }
+        }
+
+        self.remove_object(namespaces, name, object, true);
+
+        Ok(())
+    }
+
+    /// Remove an object's sections from every namespace and unmap its memory,
+    /// running its finalizers first if `finalize` is set.
+    ///
+    /// The caller must check that no other object depends on it, while holding
+    /// the same lock of the namespaces.
+    fn remove_object(
+        &self,
+        mut namespaces: spin_mutex::Guard<'_, Namespaces>,
+        name: &str,
+        object: Arc<Mutex<LoadedObject>>,
+        finalize: bool,
+    ) {
+        let owner = Arc::downgrade(&object);
+        let namespace = object.lock().namespace.clone();
+
+        info!("Unloading `{name}` from `{namespace}`...");
+        // When swapping objects with the same name, this is already the new object.
+        if let Some(objects) = namespaces
+            .get_mut(&namespace)
+            .map(|namespace| &mut namespace.objects)
+            && objects
+                .get(name)
+                .is_some_and(|loaded| Arc::ptr_eq(loaded, &object))
+        {
+            objects.remove(name);
+        }
+
+        // This also removes aliases to the object's sections, and any sections that
+        // were already dropped.
+        let is_owned_elsewhere = |section: &Weak<LoadedSection>| {
+            section
+                .upgrade()
+                .is_some_and(|section| !section.owner.ptr_eq(&owner))
+        };
+        for namespace in namespaces.values_mut() {
+            namespace
+                .sections
+                .retain(|_name, section| is_owned_elsewhere(section));
+            namespace
+                .comdat_groups
+                .retain(|_signature, group| !group.owner.ptr_eq(&owner));
+        }
+        drop(namespaces);
+        // The object can't be found anymore, but it's still mapped.
+        if finalize {
+            run_array_functions(&object, true);
+        }
+        self.sections_by_addr
+            .lock()
+            .retain(|_range, section| is_owned_elsewhere(section));
+        self.tls.lock().sections.retain(is_owned_elsewhere);
+
+        let mut object = object.lock();
+        for section in object.sections.values() {
+            if section.kind == SectionKind::EhFrame {
+                unwind::deregister_frames(section.addr);
+            }
+        }
+        object.sections.clear();
+        for mapping in [
+            object.executable_mapping.take(),
+            object.read_only_mapping.take(),
+            object.read_write_mapping.take(),
+            object.veneer_mapping.take(),
+            object.lazy_stub_mapping.take(),
+            object.image_mapping.take(),
+        ]
+        .into_iter()
+        .flatten()
+        {
+            match Arc::try_unwrap(mapping) {
+                Ok(mapping) => mapping.into_inner().unmap(),
+                Err(mapping) => {
+                    let mapping = mapping.lock();
+                    warn!(
+                        "Leaking mapping `{}` of unloaded object `{name}`, it's still referenced",
+                        mapping.name,
+                    );
+                    self.leaked_bytes.fetch_add(mapping.size, Ordering::Relaxed);
+                }
+            }
+        }
+    }
+
+    fn load_object_impl(
+        &self,
+        namespace: &str,
+        object_name: &str,
+        object_bytes: &[u8],
+        address_space: &AddressSpace,
+        start_page: &mut Page,
+    ) -> Result<Arc<Mutex<LoadedObject>>, LoaderError> {
+        if is_shared_object(object_bytes) {
+            let object = self.load_shared_object(
+                namespace,
+                object_name,
+                object_bytes,
+                address_space,
+                start_page,
+            )?;
+            run_array_functions(&object, false);
+
+            return Ok(object);
+        }
+
+        let mut mappings = BTreeSet::new();
+        let (object, elf_file) = self.load_unrelocated_object(
+            namespace,
             object_name,
             object_bytes,
             address_space,
             start_page,
             &mut mappings,
         )?;
-        self.add_sections(object.lock().sections.values());
-        self.objects
+        self.relocate_object(&elf_file, &object, address_space, start_page, &mut mappings)?;
+        run_array_functions(&object, false);
+
+        Ok(object)
+    }
+
+    /// Load an object's sections and make them visible in the namespace, but
+    /// don't relocate them yet.
+    ///
+    /// This only depends on the object itself, unlike [relocating
+    /// it](Self::relocate_object), which can load other objects.
+    fn load_unrelocated_object<'obj>(
+        &self,
+        namespace: &str,
+        object_name: &'obj str,
+        object_bytes: &'obj [u8],
+        address_space: &AddressSpace,
+        start_page: &mut Page,
+        mappings: &mut BTreeSet<VirtualAddress>,
+    ) -> Result<(Arc<Mutex<LoadedObject>>, ElfFile<'obj>), LoaderError> {
+        let (object, elf_file) = self.load_object_sections(namespace, object_name, object_bytes)?;
+        self.register_unrelocated_object(
+            namespace,
+            object_name,
+            &object,
+            address_space,
+            start_page,
+            mappings,
+        );
+
+        Ok((object, elf_file))
+    }
+
+    /// Map an object's [loaded sections](Self::load_object_sections) into the
+    /// address space and make them visible in the namespace.
+    fn register_unrelocated_object(
+        &self,
+        namespace: &str,
+        object_name: &str,
+        object: &Arc<Mutex<LoadedObject>>,
+        address_space: &AddressSpace,
+        start_page: &mut Page,
+        mappings: &mut BTreeSet<VirtualAddress>,
+    ) {
+        self.map_object_sections(&object.lock(), address_space, start_page, mappings);
+        self.add_sections(namespace, object.lock().sections.values());
+        self.namespaces
+            .lock()
+            .entry(namespace.into())
+            .or_default()
+            .objects
+            .insert(object_name.into(), Arc::clone(object));
+    }
+
+    /// Relocate an object [loaded](Self::load_unrelocated_object) without
+    /// relocations, loading its dependencies as needed, then register its
+    /// unwinding information and protect its mappings.
+    fn relocate_object(
+        &self,
+        elf_file: &ElfFile,
+        object: &Arc<Mutex<LoadedObject>>,
+        address_space: &AddressSpace,
+        start_page: &mut Page,
+        mappings: &mut BTreeSet<VirtualAddress>,
+    ) -> Result<(), LoaderError> {
+        self.relocate_object_sections(elf_file, object, address_space, start_page, mappings)?;
+        register_object_frames(&object.lock())?;
+        self.protect_object(&object.lock())?;
+
+        Ok(())
+    }
+
+    /// Drop write permission from the object's executable mappings and execute
+    /// permission from its data mappings, now that it's been relocated.
+    ///
+    /// NOTE: The kernel can still write to protected mappings (`CR0.WP` is
+    ///       cleared in [`memory::init`](crate::memory::init)), which
+    ///       [`Loader::swap_object`] relies on to rewrite relocations.
+    /// Load a fully linked shared object (`ET_DYN`) into memory.
+    ///
+    /// Its `PT_LOAD` segments are copied into a single mapping to keep their
+    /// layout, the objects it names in `DT_NEEDED` entries are loaded into the
+    /// same namespace if they aren't already, and its dynamic relocations are
+    /// written against itself and the sections of other loaded objects. Every
+    /// symbol it exports becomes a section of its own, so that other objects
+    /// can link against it like any other object.
+    ///
+    /// Thread-local storage isn't supported in shared objects yet.
+    fn load_shared_object(
+        &self,
+        namespace: &str,
+        object_name: &str,
+        object_bytes: &[u8],
+        address_space: &AddressSpace,
+        start_page: &mut Page,
+    ) -> Result<Arc<Mutex<LoadedObject>>, LoaderError> {
+        let elf_file = ElfFile::new(object_bytes)?;
+        let mut segments = Vec::new();
+        for header in elf_file.program_iter() {
+            match header.get_type()? {
+                ProgramHeaderType::Load => segments.push(header),
+                ProgramHeaderType::Tls => {
+                    return Err(LoaderError::Other(
+                        "thread-local storage in shared objects isn't supported",
+                    ));
+                }
+                _ => {}
+            }
+        }
+        let image_start = segments
+            .iter()
+            .map(|segment| segment.virtual_addr)
+            .min()
+            .ok_or("shared object has no loadable segments")?
+            & !(PAGE_SIZE as u64 - 1);
+        let image_end = segments
+            .iter()
+            .map(|segment| segment.virtual_addr.checked_add(segment.mem_size))
+            .try_fold(image_start, |end, segment_end| {
+                segment_end.map(|addr| end.max(addr))
+            })
+            .ok_or("shared object segment ends past the address space")?;
+
+        let mut image_mapping = KernelMapping::new(
+            format!("{object_name}.image"),
+            (image_end - image_start) as usize,
+            PageTableFlags::PRESENT | PageTableFlags::WRITABLE | PageTableFlags::USER_ACCESSIBLE,
+        );
+        for segment in &segments {
+            if segment.file_size > segment.mem_size {
+                return Err(LoaderError::Other(
+                    "shared object segment is larger in the file than in memory",
+                ));
+            }
+            let data = segment.raw_data(&elf_file)?;
+            let slice = image_mapping.as_slice_mut(
+                (segment.virtual_addr - image_start) as usize,
+                segment.mem_size as usize,
+            );
+            slice[..data.len()].copy_from_slice(data);
+            slice[data.len()..].fill(0);
+        }
+
+        // Pages shared by segments get the permissions of all of them.
+        let mut page_permissions = vec![(false, false); image_mapping.pages.len()];
+        for segment in &segments {
+            let first_page = (segment.virtual_addr - image_start) as usize / PAGE_SIZE;
+            let end_page = ((segment.virtual_addr + segment.mem_size - image_start) as usize)
+                .div_ceil(PAGE_SIZE);
+            for (executable, writable) in &mut page_permissions[first_page..end_page] {
+                *executable |= segment.is_execute();
+                *writable |= segment.is_write();
+            }
+        }
+        let mut image_segments: Vec<LoadedSegment> = Vec::new();
+        for (page, (executable, writable)) in page_permissions.into_iter().enumerate() {
+            let flags = if executable && writable {
+                warn!("`{object_name}` has a page that's both writable and executable");
+                PageTableFlags::PRESENT | PageTableFlags::WRITABLE | PageTableFlags::USER_ACCESSIBLE
+            } else {
+                self.final_mapping_flags(executable, writable)
+            };
+            match image_segments.last_mut() {
+                Some(segment) if segment.flags == flags => segment.size += PAGE_SIZE,
+                _ => image_segments.push(LoadedSegment {
+                    offset: page * PAGE_SIZE,
+                    size: PAGE_SIZE,
+                    flags,
+                }),
+            }
+        }
+
+        let pages = PageRange::from_start_len(*start_page, image_mapping.pages.len());
+        image_mapping
+            .map_into(address_space, pages, self.final_mapping_flags(false, false))
+            .map_err(|_| "failed to map shared object image")?;
+        for segment in &image_segments {
+            address_space
+                .set_flags(
+                    PageRange::from_start_len(
+                        pages.start + segment.offset / PAGE_SIZE,
+                        segment.size / PAGE_SIZE,
+                    ),
+                    segment.flags,
+                )
+                .map_err(|_| "failed to protect shared object segments")?;
+        }
+        *start_page = pages.end;
+
+        let image_addr = image_mapping.addr;
+        let image_mapping = Arc::new(Mutex::new(image_mapping));
+        let image_offset = |addr: u64| (addr - image_start) as usize;
+
+        let mut dependencies = Vec::new();
+        for entry in elf_file.get_dynamic_entries()? {
+            if entry.get_tag() != DT_NEEDED {
+                continue;
+            }
+            let needed = elf_file.get_dynamic_string(entry.get_value())?;
+            let loaded = self.find_in_namespace(namespace, |namespace| {
+                namespace.objects.get(needed).cloned()
+            });
+            let dependency = match loaded {
+                Some(dependency) => dependency,
+                None => {
+                    trace!("Loading `{needed}` as a dependency of `{object_name}`");
+                    let bytes = global_object_provider()
+                        .read_object(needed)
+                        .map_err(LoaderError::Io)?;
+                    self.load_object_impl(namespace, needed, &bytes, address_space, start_page)?
+                }
+            };
+            dependencies.push(Arc::downgrade(&dependency));
+        }
+
+        let object = Arc::new(Mutex::new(LoadedObject {
+            name: object_name.into(),
+            namespace: namespace.into(),
+            path: object_path(object_name).into(),
+            sections: HashMap::new(),
+            global_sections: BTreeSet::new(),
+            data_sections: BTreeSet::new(),
+            tls_sections: BTreeSet::new(),
+            dependencies,
+            executable_mapping: None,
+            read_only_mapping: None,
+            read_write_mapping: None,
+            veneer_mapping: None,
+            lazy_stub_mapping: None,
+            text_relocations: BTreeSet::new(),
+            unresolved_weak_symbols: Vec::new(),
+            deduplicated_sections: HashMap::new(),
+            init_array_sections: Vec::new(),
+            fini_array_sections: Vec::new(),
+            image_mapping: Some(Arc::clone(&image_mapping)),
+            image_segments,
+        }));
+
+        let symbols = match elf_file
+            .section_iter()
+            .find(|section| section.get_type() == Ok(SectionHeaderType::DynSym))
+            .map(|section| section.get_data(&elf_file))
+        {
+            Some(Ok(SectionData::DynSymbolTable(symbols))) => symbols,
+            _ => {
+                return Err(LoaderError::Other(
+                    "shared object has no dynamic symbol table",
+                ));
+            }
+        };
+        let new_section = |name: &str, kind, global, weak, addr: u64, size| {
+            Arc::new(LoadedSection {
+                name: name.into(),
+                kind,
+                global,
+                weak,
+                size,
+                addr: image_addr + image_offset(addr),
+                mapping: Arc::clone(&image_mapping),
+                mapping_offset: image_offset(addr),
+                owner: Arc::downgrade(&object),
+                dependencies: Mutex::new(Vec::new()),
+                dependents: Mutex::new(Vec::new()),
+            })
+        };
+
+        // Exported symbols are keyed by their index into the dynamic symbol table,
+        // and the object's other sections come after them.
+        let mut sections = HashMap::new();
+        let mut global_sections = BTreeSet::new();
+        for (index, symbol) in symbols.iter().enumerate() {
+            if !symbol.is_global() || symbol.is_undefined() || symbol.shndx() == SHN_ABS {
+                continue;
+            }
+            let is_writable = segments.iter().any(|segment| {
+                segment.is_write()
+                    && (segment.virtual_addr..segment.virtual_addr + segment.mem_size)
+                        .contains(&symbol.value())
+            });
+            let kind = match symbol.get_type() {
+                Ok(SymbolType::Func) => SectionKind::Text,
+                Ok(SymbolType::Object) if is_writable => SectionKind::Data,
+                Ok(SymbolType::Object) => SectionKind::Rodata,
+                _ => continue,
+            };
+            let name = rustc_demangle::demangle(elf_file.get_dynamic_string(symbol.name().into())?)
+                .to_string();
+            sections.insert(
+                index,
+                new_section(
+                    &name,
+                    kind,
+                    true,
+                    symbol.is_weak(),
+                    symbol.value(),
+                    symbol.size() as usize,
+                ),
+            );
+            global_sections.insert(index);
+        }
+        let mut next_index = symbols.len();
+        if let Some(eh_frame) = elf_file.find_section_by_name(".eh_frame") {
+            let kind = SectionKind::EhFrame;
+            sections.insert(
+                next_index,
+                new_section(
+                    kind.name(),
+                    kind,
+                    false,
+                    false,
+                    eh_frame.address(),
+                    eh_frame.size() as usize,
+                ),
+            );
+            next_index += 1;
+        }
+        let mut init_array_sections = Vec::new();
+        let mut fini_array_sections = Vec::new();
+        for (name, addr_tag, size_tag, array_sections) in [
+            (
+                ".init_array",
+                DT_INIT_ARRAY,
+                DT_INIT_ARRAYSZ,
+                &mut init_array_sections,
+            ),
+            (
+                ".fini_array",
+                DT_FINI_ARRAY,
+                DT_FINI_ARRAYSZ,
+                &mut fini_array_sections,
+            ),
+        ] {
+            if let (Some(addr), Some(size)) = (
+                elf_file.get_dynamic_value(addr_tag)?,
+                elf_file.get_dynamic_value(size_tag)?,
+            ) {
+                sections.insert(
+                    next_index,
+                    new_section(name, SectionKind::Data, false, false, addr, size as usize),
+                );
+                array_sections.push(next_index);
+                next_index += 1;
+            }
+        }
+
+        {
+            let mut object_lock = object.lock();
+            object_lock.sections = sections;
+            object_lock.global_sections = global_sections;
+            object_lock.init_array_sections = init_array_sections;
+            object_lock.fini_array_sections = fini_array_sections;
+        }
+        self.add_sections(namespace, object.lock().sections.values());
+        self.namespaces
             .lock()
+            .entry(namespace.into())
+            .or_default()
+            .objects
             .insert(object_name.into(), Arc::clone(&object));
-        self.relocate_object_sections(
-            &elf_file,
-            &object,
-            address_space,
-            start_page,
-            &mut mappings,
-        )?;
+
+        let owner = Arc::downgrade(&object);
+        let mut object_lock = object.lock();
+        let mut image_lock = image_mapping.lock();
+        let image_size = image_lock.size();
+        let image = image_lock.as_slice_mut(0, image_size);
+        for rela_entry in elf_file.get_dynamic_relocations()?.into_iter().flatten() {
+            let source_addr = match DynamicRelocation::of(rela_entry.get_type())? {
+                DynamicRelocation::None => continue,
+                // The image's first page is at `image_start`, not zero.
+                DynamicRelocation::Relative => {
+                    VirtualAddress::new(image_addr.to_raw() - image_start as usize)
+                }
+                DynamicRelocation::Symbol => {
+                    let symbol = symbols
+                        .get(rela_entry.get_symbol_table_index() as usize)
+                        .ok_or("dynamic relocation refers to a missing symbol")?;
+                    if symbol.shndx() == SHN_ABS {
+                        VirtualAddress::new(symbol.value() as usize)
+                    } else if !symbol.is_undefined() {
+                        // Like `-Bsymbolic`, the object's own definitions are never preempted.
+                        image_addr + image_offset(symbol.value())
+                    } else {
+                        let name = rustc_demangle::demangle(
+                            elf_file.get_dynamic_string(symbol.name().into())?,
+                        )
+                        .to_string();
+                        // Like a static linker, weak references don't cause objects to be
+                        // loaded, they only bind to sections that already are.
+                        let section = if symbol.is_weak() {
+                            self.find_section(namespace, &name)
+                                .ok_or(LoaderError::Other("weak symbol isn't loaded"))
+                        } else {
+                            self.get_or_load_section(&name, &object_lock, address_space, start_page)
+                        }
+                        .and_then(|section| {
+                            section
+                                .upgrade()
+                                .ok_or(LoaderError::Other("dynamic symbol was unloaded"))
+                        });
+                        match section {
+                            Ok(section) => {
+                                if !section.owner.ptr_eq(&owner)
+                                    && !object_lock
+                                        .dependencies
+                                        .iter()
+                                        .any(|dependency| dependency.ptr_eq(&section.owner))
+                                {
+                                    object_lock.dependencies.push(section.owner.clone());
+                                }
+                                section.addr
+                            }
+                            Err(_) if symbol.is_weak() => {
+                                // Undefined weak symbols resolve to zero.
+                                if !object_lock
+                                    .unresolved_weak_symbols
+                                    .iter()
+                                    .any(|symbol| **symbol == *name)
+                                {
+                                    object_lock.unresolved_weak_symbols.push(name.into());
+                                }
+                                VirtualAddress::new(0)
+                            }
+                            Err(error) => {
+                                error!(
+                                    "Couldn't resolve dynamic symbol `{name}` for \
+                                    `{object_name}`: {error}",
+                                );
+                                return Err(error);
+                            }
+                        }
+                    }
+                }
+            };
+
+            let relocation = Relocation {
+                kind: DynamicRelocation::ABSOLUTE,
+                ..Relocation::from(rela_entry)
+            };
+            write_relocation(
+                relocation,
+                image,
+                image_offset(relocation.offset as u64),
+                source_addr,
+                &mut Veneers::empty(),
+            )?;
+        }
+        drop(image_lock);
+
+        register_object_frames(&object_lock)?;
+        let mut image_lock = image_mapping.lock();
+        for segment in &object_lock.image_segments {
+            image_lock
+                .set_range_flags(segment.offset, segment.size, segment.flags)
+                .map_err(|_| "failed to protect shared object segments")?;
+        }
+        drop(image_lock);
+        drop(object_lock);
 
         Ok(object)
     }
 
-    fn load_object_sections<'obj>(
+    /// Map the mappings of an object's [loaded
+    /// sections](Self::load_object_sections) into the given address space,
+    /// starting at `start_page`.
+    fn map_object_sections(
         &self,
-        object_name: &'obj str,
-        object_bytes: &'obj [u8],
+        object: &LoadedObject,
         address_space: &AddressSpace,
         start_page: &mut Page,
         mappings: &mut BTreeSet<VirtualAddress>,
-    ) -> Result<(Arc<Mutex<LoadedObject>>, ElfFile<'obj>), &'static str> {
+    ) {
+        for (mapping, executable, writable) in [
+            (&object.executable_mapping, true, false),
+            (&object.read_only_mapping, false, false),
+            (&object.read_write_mapping, false, true),
+        ] {
+            if let Some(mapping) = mapping {
+                let mapping = mapping.lock();
+                let pages = PageRange::from_start_len(*start_page, mapping.pages.len());
+                mappings.insert(mapping.addr);
+                mapping
+                    .map_into(
+                        address_space,
+                        pages,
+                        self.final_mapping_flags(executable, writable),
+                    )
+                    .unwrap();
+                *start_page = pages.end;
+            }
+        }
+    }
+
+    fn protect_object(&self, object: &LoadedObject) -> Result<(), LoaderError> {
+        for (mapping, executable, writable) in [
+            (&object.executable_mapping, true, false),
+            (&object.read_only_mapping, false, false),
+            (&object.read_write_mapping, false, true),
+            (&object.veneer_mapping, true, false),
+            (&object.lazy_stub_mapping, true, false),
+        ] {
+            if let Some(mapping) = mapping {
+                mapping
+                    .lock()
+                    .set_flags(self.final_mapping_flags(executable, writable))
+                    .map_err(|_| "failed to protect object mappings")?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Load an object's sections into new kernel mappings, without mapping them
+    /// into any other address space (see [`Loader::map_object_sections`]) or
+    /// making them visible in the namespace.
+    ///
+    /// This only touches the loader's state behind its locks, so it can run
+    /// for several objects at once on different processors.
+    fn load_object_sections<'obj>(
+        &self,
+        namespace: &str,
+        object_name: &'obj str,
+        object_bytes: &'obj [u8],
+    ) -> Result<(Arc<Mutex<LoadedObject>>, ElfFile<'obj>), LoaderError> {
         let elf_file = ElfFile::new(object_bytes)?;
         if elf_file.header.get_type() != ObjectFileType::Relocatable {
-            return Err("not a relocatable ELF file");
+            return Err(LoaderError::Other("not a relocatable ELF file"));
         }
 
         let SectionMappings {
@@ -539,44 +2636,6 @@ impl Loader {
             read_write: read_write_mapping,
         } = allocate_section_mappings(object_name, &elf_file)?;
 
-        // Map loaded sections into the object's address space.
-        if let Some(mapping) = &executable_mapping {
-            let pages = PageRange::from_start_len(*start_page, mapping.pages.len());
-            mappings.insert(mapping.addr);
-            mapping
-                .map_into(
-                    address_space,
-                    pages,
-                    PageTableFlags::PRESENT | PageTableFlags::USER_ACCESSIBLE,
-                )
-                .unwrap();
-            *start_page = pages.end;
-        }
-        if let Some(mapping) = &read_only_mapping {
-            let pages = PageRange::from_start_len(*start_page, mapping.pages.len());
-            mappings.insert(mapping.addr);
-            mapping
-                .map_into(
-                    address_space,
-                    pages,
-                    PageTableFlags::PRESENT | PageTableFlags::USER_ACCESSIBLE,
-                )
-                .unwrap();
-            *start_page = pages.end;
-        }
-        if let Some(mapping) = &read_write_mapping {
-            let pages = PageRange::from_start_len(*start_page, mapping.pages.len());
-            mappings.insert(mapping.addr);
-            mapping
-                .map_into(
-                    address_space,
-                    pages,
-                    PageTableFlags::PRESENT | PageTableFlags::USER_ACCESSIBLE,
-                )
-                .unwrap();
-            *start_page = pages.end;
-        }
-
         let executable_mapping = executable_mapping.map(|mapping| Arc::new(Mutex::new(mapping)));
         let read_only_mapping = read_only_mapping.map(|mapping| Arc::new(Mutex::new(mapping)));
         let read_write_mapping = read_write_mapping.map(|mapping| Arc::new(Mutex::new(mapping)));
@@ -598,6 +2657,8 @@ impl Loader {
 
         let object = Arc::new(Mutex::new(LoadedObject {
             name: rustc_demangle::demangle(object_name).to_string().into(),
+            namespace: namespace.into(),
+            path: object_path(object_name).into(),
             sections: HashMap::new(),
             global_sections: BTreeSet::new(),
             data_sections: BTreeSet::new(),
@@ -606,28 +2667,76 @@ impl Loader {
             executable_mapping: executable_mapping.clone(),
             read_only_mapping: read_only_mapping.clone(),
             read_write_mapping: read_write_mapping.clone(),
+            veneer_mapping: None,
+            lazy_stub_mapping: None,
+            text_relocations: BTreeSet::new(),
+            unresolved_weak_symbols: Vec::new(),
+            deduplicated_sections: HashMap::new(),
+            init_array_sections: Vec::new(),
+            fini_array_sections: Vec::new(),
+            image_mapping: None,
+            image_segments: Vec::new(),
         }));
 
         let mut loaded_sections: HashMap<usize, Arc<LoadedSection>> = HashMap::new();
         let mut data_sections: BTreeSet<usize> = BTreeSet::new();
         let mut tls_sections: BTreeSet<usize> = BTreeSet::new();
-        let global_sections: BTreeSet<usize> = {
+        let mut init_array_sections: Vec<(u32, usize)> = Vec::new();
+        let mut fini_array_sections: Vec<(u32, usize)> = Vec::new();
+        let (global_sections, weak_sections) = {
             let symbol_table = elf_file.get_symbol_table()?;
             let mut globals: BTreeSet<usize> = BTreeSet::new();
+            let mut strong: BTreeSet<usize> = BTreeSet::new();
             for entry in symbol_table.iter() {
-                if entry.get_binding() == Ok(SymbolBinding::Global) {
+                if entry.is_global() && !entry.is_undefined() {
                     match entry.get_type() {
                         Ok(SymbolType::Func | SymbolType::Object | SymbolType::Tls) => {
                             globals.insert(entry.shndx() as usize);
+                            if !entry.is_weak() {
+                                strong.insert(entry.shndx() as usize);
+                            }
                         }
                         _ => continue,
                     }
                 }
             }
+            let weak: BTreeSet<usize> = globals.difference(&strong).copied().collect();
 
-            globals
+            (globals, weak)
         };
 
+        // Generic instantiations (and the like) are emitted into COMDAT groups by every
+        // object that uses them. Only the first copy of a group is loaded, and the
+        // others are replaced by it.
+        //
+        // NOTE: Deduplicated `.text` sections were already copied into the executable
+        //       mapping above, and are just left unused.
+        let mut deduplicated_sections = HashMap::new();
+        let mut new_comdat_groups = Vec::new();
+        for (signature, members) in comdat_groups(&elf_file)? {
+            let loaded_sections = self.find_in_namespace(namespace, |namespace| {
+                namespace
+                    .comdat_groups
+                    .get(signature)
+                    .filter(|group| group.is_loaded())
+                    .map(|group| group.sections.clone())
+            });
+            let Some(loaded_sections) = loaded_sections else {
+                new_comdat_groups.push((signature, members));
+                continue;
+            };
+
+            for index in members {
+                let index = *index as usize;
+                let name = elf_file
+                    .get_section_header(index as u16)?
+                    .get_name(&elf_file)?;
+                if let Some(section) = loaded_sections.get(name) {
+                    deduplicated_sections.insert(index, section.clone());
+                }
+            }
+        }
+
         let mut rodata_offset = 0;
         let mut data_offset = 0;
 
@@ -638,6 +2747,9 @@ impl Loader {
             if section_flags & SHF_ALLOC == 0 {
                 continue;
             }
+            if deduplicated_sections.contains_key(&section_index) {
+                continue;
+            }
 
             // If the current section is zero-sized, it's a reference to the next section.
             // So, we just use the next section's information (size, align, etc.) with the
@@ -655,7 +2767,9 @@ impl Loader {
                         }
                     }
                     _ => {
-                        return Err("couldn't get the section following a zero-sized section");
+                        return Err(LoaderError::Other(
+                            "couldn't get the section following a zero-sized section",
+                        ));
                     }
                 }
             } else {
@@ -678,13 +2792,13 @@ impl Loader {
                         match $sec_name {
                             ".text" | ".rodata" | ".data" | ".bss" => continue,
                             _ => {
-                                return Err(concat!(
+                                return Err(LoaderError::Other(concat!(
                                     "failed to get the ",
                                     $prefix,
                                     " section's name after '",
                                     $prefix,
                                     "'"
-                                ));
+                                )));
                             }
                         }
                     }
@@ -722,9 +2836,12 @@ impl Loader {
                         size: section_size,
                         addr: section_addr,
                         global: is_global,
+                        weak: weak_sections.contains(&section_index),
                         mapping: Arc::clone(&executable_mapping),
                         mapping_offset: text_offset,
                         owner: Arc::downgrade(&object),
+                        dependencies: Mutex::new(Vec::new()),
+                        dependents: Mutex::new(Vec::new()),
                     }),
                 );
             }
@@ -751,33 +2868,97 @@ impl Loader {
                     match section.get_data(&elf_file) {
                         Ok(SectionData::Undefined(sec_data)) => slice.copy_from_slice(sec_data),
                         _ => {
-                            return Err("couldn't get data for `.tdata` section");
+                            return Err(LoaderError::Other(
+                                "couldn't get data for `.tdata` section",
+                            ));
                         }
                     };
 
                     (rodata_offset, SectionKind::TlsData)
                 };
 
+                let mut tls = self.tls.lock();
+                let tls_offset = tls.reserve(section_size, section_align)?;
                 let tls_section = Arc::new(LoadedSection {
                     name: rustc_demangle::demangle(name).to_string().into(),
                     kind,
                     size: section_size,
-                    addr: VirtualAddress::new(0), // See below.
+                    addr: VirtualAddress::new(tls_offset),
                     global: global_sections.contains(&section_index),
+                    weak: weak_sections.contains(&section_index),
                     mapping: Arc::clone(&read_only_mapping),
                     mapping_offset,
                     owner: Arc::downgrade(&object),
+                    dependencies: Mutex::new(Vec::new()),
+                    dependents: Mutex::new(Vec::new()),
                 });
-
-                // This should initialize a TLS area and set the section's address.
-                if true {
-                    return Err("TODO: TLS section initialization");
-                }
+                tls.sections.push(Arc::downgrade(&tls_section));
 
                 loaded_sections.insert(section_index, tls_section);
                 tls_sections.insert(section_index);
 
-                rodata_offset += section_size.next_multiple_of(section_align);
+                if !is_bss {
+                    rodata_offset += section_size.next_multiple_of(section_align);
+                }
+            }
+            // .init_array/.fini_array
+            else if let Some((is_init, priority)) = section_name
+                .strip_prefix(".init_array")
+                .map(|suffix| (true, suffix))
+                .or_else(|| {
+                    section_name
+                        .strip_prefix(".fini_array")
+                        .map(|suffix| (false, suffix))
+                })
+            {
+                let Some(read_write_mapping) = &read_write_mapping else {
+                    continue;
+                };
+                let mut read_write_map_lock = read_write_mapping.lock();
+
+                assert!(data_offset < read_write_map_lock.size());
+                let section_addr = read_write_map_lock.addr + data_offset;
+
+                let slice = read_write_map_lock.as_slice_mut(data_offset, section_size);
+                match section.get_data(&elf_file) {
+                    Ok(SectionData::Undefined(sec_data)) => slice.copy_from_slice(sec_data),
+                    Ok(SectionData::Empty) => slice.fill(0),
+                    _ => {
+                        return Err(LoaderError::Other(
+                            "couldn't get data for `.init_array` or `.fini_array` section",
+                        ));
+                    }
+                }
+
+                loaded_sections.insert(
+                    section_index,
+                    Arc::new(LoadedSection {
+                        name: section_name.into(),
+                        kind: SectionKind::Data,
+                        size: section_size,
+                        addr: section_addr,
+                        global: false,
+                        weak: false,
+                        mapping: Arc::clone(&read_write_mapping),
+                        mapping_offset: data_offset,
+                        owner: Arc::downgrade(&object),
+                        dependencies: Mutex::new(Vec::new()),
+                        dependents: Mutex::new(Vec::new()),
+                    }),
+                );
+                // Sections without a priority (`.init_array.<priority>`) run after every
+                // section with one, like a linker orders them.
+                let priority = priority
+                    .strip_prefix('.')
+                    .and_then(|priority| priority.parse().ok())
+                    .unwrap_or(u32::MAX);
+                if is_init {
+                    init_array_sections.push((priority, section_index));
+                } else {
+                    fini_array_sections.push((priority, section_index));
+                }
+
+                data_offset += section_size.next_multiple_of(section_align);
             }
             // .data/.bss
             else if is_write {
@@ -804,7 +2985,7 @@ impl Loader {
                     Ok(SectionData::Undefined(sec_data)) => slice.copy_from_slice(sec_data),
                     Ok(SectionData::Empty) => slice.fill(0),
                     _ => {
-                        return Err("couldn't get data for `.data` section");
+                        return Err(LoaderError::Other("couldn't get data for `.data` section"));
                     }
                 }
 
@@ -820,9 +3001,12 @@ impl Loader {
                         size: section_size,
                         addr: section_addr,
                         global: global_sections.contains(&section_index),
+                        weak: weak_sections.contains(&section_index),
                         mapping: Arc::clone(&read_write_mapping),
                         mapping_offset: data_offset,
                         owner: Arc::downgrade(&object),
+                        dependencies: Mutex::new(Vec::new()),
+                        dependents: Mutex::new(Vec::new()),
                     }),
                 );
                 data_sections.insert(section_index);
@@ -846,7 +3030,9 @@ impl Loader {
                     Ok(SectionData::Undefined(sec_data)) => slice.copy_from_slice(sec_data),
                     Ok(SectionData::Empty) => slice.fill(0),
                     _ => {
-                        return Err("couldn't get data for `.rodata` section");
+                        return Err(LoaderError::Other(
+                            "couldn't get data for `.rodata` section",
+                        ));
                     }
                 }
 
@@ -858,9 +3044,12 @@ impl Loader {
                         size: section_size,
                         addr: section_addr,
                         global: global_sections.contains(&section_index),
+                        weak: weak_sections.contains(&section_index),
                         mapping: Arc::clone(&read_only_mapping),
                         mapping_offset: rodata_offset,
                         owner: Arc::downgrade(&object),
+                        dependencies: Mutex::new(Vec::new()),
+                        dependents: Mutex::new(Vec::new()),
                     }),
                 );
 
@@ -886,7 +3075,9 @@ impl Loader {
                     Ok(SectionData::Undefined(sec_data)) => slice.copy_from_slice(sec_data),
                     Ok(SectionData::Empty) => slice.fill(0),
                     _ => {
-                        return Err("couldn't get data for `.lrodata` section");
+                        return Err(LoaderError::Other(
+                            "couldn't get data for `.lrodata` section",
+                        ));
                     }
                 }
 
@@ -898,9 +3089,12 @@ impl Loader {
                         size: section_size,
                         addr: section_addr,
                         global: global_sections.contains(&section_index),
+                        weak: weak_sections.contains(&section_index),
                         mapping: Arc::clone(&read_only_mapping),
                         mapping_offset: rodata_offset,
                         owner: Arc::downgrade(&object),
+                        dependencies: Mutex::new(Vec::new()),
+                        dependents: Mutex::new(Vec::new()),
                     }),
                 );
 
@@ -921,7 +3115,9 @@ impl Loader {
                     Ok(SectionData::Undefined(sec_data)) => slice.copy_from_slice(sec_data),
                     Ok(SectionData::Empty) => slice.fill(0),
                     _ => {
-                        return Err("couldn't get data for `.gcc_except_table` section");
+                        return Err(LoaderError::Other(
+                            "couldn't get data for `.gcc_except_table` section",
+                        ));
                     }
                 }
 
@@ -934,9 +3130,12 @@ impl Loader {
                         size: section_size,
                         addr: section_addr,
                         global: false,
+                        weak: false,
                         mapping: Arc::clone(&read_only_mapping),
                         mapping_offset: rodata_offset,
                         owner: Arc::downgrade(&object),
+                        dependencies: Mutex::new(Vec::new()),
+                        dependents: Mutex::new(Vec::new()),
                     }),
                 );
 
@@ -957,7 +3156,9 @@ impl Loader {
                     Ok(SectionData::Undefined(sec_data)) => slice.copy_from_slice(sec_data),
                     Ok(SectionData::Empty) => slice.fill(0),
                     _ => {
-                        return Err("couldn't get data for `.eh_frame` section");
+                        return Err(LoaderError::Other(
+                            "couldn't get data for `.eh_frame` section",
+                        ));
                     }
                 }
 
@@ -970,9 +3171,12 @@ impl Loader {
                         size: section_size,
                         addr: section_addr,
                         global: false,
+                        weak: false,
                         mapping: Arc::clone(&read_only_mapping),
                         mapping_offset: rodata_offset,
                         owner: Arc::downgrade(&object),
+                        dependencies: Mutex::new(Vec::new()),
+                        dependents: Mutex::new(Vec::new()),
                     }),
                 );
 
@@ -981,7 +3185,41 @@ impl Loader {
             // Unhandled section.
             else {
                 error!("Encountered unhandled section: `{section_name}`");
-                return Err("encountered unhandled section");
+                return Err(LoaderError::Other("encountered unhandled section"));
+            }
+        }
+
+        if !new_comdat_groups.is_empty() {
+            let mut namespaces = self.namespaces.lock();
+            let comdat_groups = &mut namespaces
+                .entry(namespace.into())
+                .or_default()
+                .comdat_groups;
+            for (signature, members) in new_comdat_groups {
+                if comdat_groups
+                    .get(signature)
+                    .is_some_and(|group| group.is_loaded())
+                {
+                    continue;
+                }
+
+                let mut sections = HashMap::default();
+                for index in members {
+                    let index = *index as usize;
+                    if let Some(section) = loaded_sections.get(&index) {
+                        let name = elf_file
+                            .get_section_header(index as u16)?
+                            .get_name(&elf_file)?;
+                        sections.insert(name.into(), Arc::downgrade(section));
+                    }
+                }
+                comdat_groups.insert(
+                    signature.into(),
+                    ComdatGroup {
+                        owner: Arc::downgrade(&object),
+                        sections,
+                    },
+                );
             }
         }
 
@@ -991,6 +3229,17 @@ impl Loader {
             object_lock.global_sections = global_sections;
             object_lock.data_sections = data_sections;
             object_lock.tls_sections = tls_sections;
+            object_lock.deduplicated_sections = deduplicated_sections;
+            init_array_sections.sort();
+            object_lock.init_array_sections = init_array_sections
+                .into_iter()
+                .map(|(_priority, index)| index)
+                .collect();
+            fini_array_sections.sort();
+            object_lock.fini_array_sections = fini_array_sections
+                .into_iter()
+                .map(|(_priority, index)| index)
+                .collect();
         }
 
         Ok((object, elf_file))
@@ -1003,26 +3252,68 @@ impl Loader {
         address_space: &AddressSpace,
         start_page: &mut Page,
         mappings: &mut BTreeSet<VirtualAddress>,
-    ) -> Result<(), &'static str> {
+    ) -> Result<(), LoaderError> {
+        let owner = Arc::downgrade(object);
         let mut object = object.lock();
         let symbol_table = elf_file.get_symbol_table()?;
 
+        // Veneers are mapped right after the object's other mappings, so that they're
+        // as close as possible to the branches that need them.
+        let mut veneers = Veneers::new(&object.name, elf_file)?;
+        if let Some(mapping) = veneers.mapping() {
+            let mapping_lock = mapping.lock();
+            let pages = PageRange::from_start_len(*start_page, mapping_lock.pages.len());
+            mappings.insert(mapping_lock.addr);
+            mapping_lock
+                .map_into(address_space, pages, self.final_mapping_flags(true, false))
+                .unwrap();
+            *start_page = pages.end;
+            object.veneer_mapping = Some(Arc::clone(mapping));
+        }
+        let mut lazy_stubs = if self.lazy_binding.load(Ordering::Relaxed) {
+            LazyStubs::new(&object.name, elf_file)?
+        } else {
+            LazyStubs::empty()
+        };
+        if let Some(mapping) = lazy_stubs.mapping() {
+            let mapping_lock = mapping.lock();
+            let pages = PageRange::from_start_len(*start_page, mapping_lock.pages.len());
+            mappings.insert(mapping_lock.addr);
+            mapping_lock
+                .map_into(address_space, pages, self.final_mapping_flags(true, false))
+                .unwrap();
+            *start_page = pages.end;
+            object.lazy_stub_mapping = Some(Arc::clone(mapping));
+        }
+
         for section in elf_file.section_iter().filter(|section| {
             section.get_type() == Ok(SectionHeaderType::Rela) && section.size() != 0
         }) {
             let rela_array = match section.get_data(elf_file) {
                 Ok(SectionData::Rela(rela_arr)) => rela_arr,
                 _ => {
-                    return Err("found `rela` section that wasn't able to be parsed");
+                    return Err(LoaderError::Other(
+                        "found `rela` section that wasn't able to be parsed",
+                    ));
                 }
             };
 
             let target_section_index = section.info() as usize;
+            // The loaded copy of a deduplicated section was already relocated.
+            if object
+                .deduplicated_sections
+                .contains_key(&target_section_index)
+            {
+                continue;
+            }
             let target_section = object
                 .sections
                 .get(&target_section_index)
                 .cloned()
                 .ok_or("target section was not loaded for `rela` section")?;
+            if target_section.kind == SectionKind::Text {
+                object.text_relocations.insert(target_section_index);
+            }
 
             {
                 let mut target_section_mapping = target_section.mapping.lock();
@@ -1035,7 +3326,16 @@ impl Loader {
                     let source_value = source_entry.value() as usize;
 
                     let source_section = match object.sections.get(&source_index) {
+                        // A global section elsewhere preempts this object's weak definition.
+                        Some(section) if section.weak => self
+                            .find_section(&object.namespace, &section.name)
+                            .and_then(|section| section.upgrade())
+                            .unwrap_or_else(|| section.clone()),
                         Some(section) => section.clone(),
+                        None if object.deduplicated_sections.contains_key(&source_index) => object
+                            .deduplicated_sections[&source_index]
+                            .upgrade()
+                            .ok_or("deduplicated section was unloaded")?,
                         None => {
                             let name = source_entry
                                 .get_name(&elf_file)
@@ -1049,15 +3349,72 @@ impl Loader {
 
                             let demangled_name = rustc_demangle::demangle(name).to_string();
 
-                            let section = match self.get_or_load_section(
-                                &demangled_name,
-                                &object,
-                                address_space,
-                                start_page,
-                            ) {
+                            if lazy_stubs.mapping().is_some()
+                                && LazyStubs::is_call(rela_entry.get_type())
+                                && !source_entry.is_weak()
+                                && self
+                                    .find_section(&object.namespace, &demangled_name)
+                                    .is_none()
+                            {
+                                let relocation = Relocation::from(rela_entry);
+                                let (stub_id, stub_addr) = lazy_stubs.get_or_write(
+                                    &self.lazy_stubs,
+                                    rela_entry.get_symbol_table_index() as usize,
+                                    &demangled_name,
+                                    &owner,
+                                )?;
+                                write_relocation(
+                                    relocation,
+                                    target_slice,
+                                    target_section.mapping_offset + relocation.offset,
+                                    stub_addr,
+                                    &mut veneers,
+                                )?;
+                                self.lazy_stubs.lock()[stub_id]
+                                    .sites
+                                    .push((Arc::downgrade(&target_section), relocation));
+                                continue;
+                            }
+
+                            // Like a static linker, weak references don't cause objects to be
+                            // loaded, they only bind to sections that already are.
+                            let section = if source_entry.is_weak() {
+                                self.find_section(&object.namespace, &demangled_name)
+                                    .ok_or_else(|| LoaderError::MissingSymbol {
+                                        symbol: demangled_name.as_str().into(),
+                                        object: object.name.clone(),
+                                    })
+                            } else {
+                                self.get_or_load_section(
+                                    &demangled_name,
+                                    &object,
+                                    address_space,
+                                    start_page,
+                                )
+                            };
+                            let section = match section {
                                 Ok(section) => section.upgrade().ok_or(
                                     "couldn't upgrade section reference for relocation entry",
                                 ),
+                                Err(_) if source_entry.is_weak() => {
+                                    // Undefined weak symbols resolve to zero.
+                                    if !object
+                                        .unresolved_weak_symbols
+                                        .iter()
+                                        .any(|symbol| **symbol == *demangled_name)
+                                    {
+                                        object.unresolved_weak_symbols.push(demangled_name.into());
+                                    }
+                                    write_relocation(
+                                        Relocation::from(rela_entry),
+                                        target_slice,
+                                        target_section.mapping_offset
+                                            + rela_entry.get_offset() as usize,
+                                        VirtualAddress::new(0),
+                                        &mut veneers,
+                                    )?;
+                                    continue;
+                                }
                                 Err(error) => {
                                     // HACK: For now, fully relocating libcore isn't entirely
                                     //       possible because many of the math symbols just aren't
@@ -1081,25 +3438,42 @@ impl Loader {
                                 }
                             }?;
 
-                            // At this point, we know `section` is some external dependency (i.e.
-                            // `section.owner` != `object`).
-                            if AUTO_MAP_DEPENDENCIES {
-                                map_dependency(&mut object, &section, address_space, mappings);
-                            }
-
                             section
                         }
                     };
 
-                    let target_offset =
-                        target_section.mapping_offset + rela_entry.get_offset() as usize;
+                    // Sections that weren't loaded with this object are external dependencies.
+                    if !source_section.owner.ptr_eq(&owner)
+                        && !object
+                            .dependencies
+                            .iter()
+                            .any(|dependency| dependency.ptr_eq(&source_section.owner))
+                    {
+                        object.dependencies.push(source_section.owner.clone());
+                        if AUTO_MAP_DEPENDENCIES {
+                            map_dependency(&mut object, &source_section, address_space, mappings);
+                        }
+                    }
+
+                    let relocation = Relocation::from(rela_entry);
+                    let target_offset = target_section.mapping_offset + relocation.offset;
 
                     write_relocation(
-                        rela_entry,
+                        relocation,
                         target_slice,
                         target_offset,
                         source_section.addr + source_value,
+                        &mut veneers,
                     )?;
+
+                    if !source_section.owner.ptr_eq(&target_section.owner) {
+                        add_section_dependency(
+                            &source_section,
+                            &target_section,
+                            source_value,
+                            relocation,
+                        );
+                    }
                 }
             }
         }
@@ -1107,26 +3481,41 @@ impl Loader {
         Ok(())
     }
 
-    fn add_sections<'a, I>(&self, sections: I) -> usize
+    fn add_sections<'a, I>(&self, namespace: &str, sections: I) -> usize
     where
         I: IntoIterator<Item = &'a Arc<LoadedSection>>,
     {
-        let mut map = self.sections.lock();
+        let mut namespaces = self.namespaces.lock();
+        let map = &mut namespaces.entry(namespace.into()).or_default().sections;
         let mut range_map = self.sections_by_addr.lock();
         let mut added_count = 0;
         for new_section in sections.into_iter() {
-            range_map.insert(
-                (new_section.addr, new_section.size),
-                Arc::downgrade(new_section),
-            );
+            // TLS sections don't have a single address, see `LoadedSection::addr`.
+            if !matches!(new_section.kind, SectionKind::TlsData | SectionKind::TlsBss) {
+                range_map.insert(
+                    (new_section.addr, new_section.size),
+                    Arc::downgrade(new_section),
+                );
+            }
             if new_section.global {
-                if let Some(old_section) =
-                    map.insert(new_section.name.clone(), Arc::downgrade(new_section))
-                {
-                    let old_section = old_section.upgrade().unwrap();
+                let old_section = map.get(&new_section.name).and_then(Weak::upgrade);
+                // Weak definitions never replace loaded sections, but global ones replace
+                // weak definitions.
+                if new_section.weak && old_section.is_some() {
+                    continue;
+                }
+                map.insert(new_section.name.clone(), Arc::downgrade(new_section));
+                if let Some(old_section) = old_section {
                     debug!(
-                        "Moved `{}` from {:x} to {:x}",
-                        old_section.name, old_section.addr, new_section.addr,
+                        "{} `{}` from {:x} to {:x}",
+                        if old_section.weak {
+                            "Preempted"
+                        } else {
+                            "Moved"
+                        },
+                        old_section.name,
+                        old_section.addr,
+                        new_section.addr,
                     );
                 } else {
                     added_count += 1;
@@ -1138,6 +3527,28 @@ impl Loader {
     }
 }
 
+/// Record that a relocation in `target` refers to `source`, which belongs to
+/// another object.
+fn add_section_dependency(
+    source: &Arc<LoadedSection>,
+    target: &Arc<LoadedSection>,
+    source_offset: usize,
+    relocation: Relocation,
+) {
+    let mut dependents = source.dependents.lock();
+    if !dependents
+        .iter()
+        .any(|dependent| dependent.as_ptr() == Arc::as_ptr(target))
+    {
+        dependents.push(Arc::downgrade(target));
+    }
+    target.dependencies.lock().push(SectionDependency {
+        source: Arc::clone(source),
+        source_offset,
+        relocation,
+    });
+}
+
 /// Map the section's dependencies into the object's address space.
 fn map_dependency(
     object: &mut LoadedObject,
@@ -1145,15 +3556,178 @@ fn map_dependency(
     address_space: &AddressSpace,
     mappings: &mut BTreeSet<VirtualAddress>,
 ) {
-    object.dependencies.push(section.owner.clone());
-
     let owner = section
         .owner
         .upgrade()
         .expect("should be able to get the source section owner");
     let mut owner_lock = owner.lock();
 
-    map_dependency_sections(object, &mut owner_lock, address_space, mappings);
+    map_dependency_sections(object, &mut owner_lock, address_space, mappings);
+}
+
+/// The COMDAT section groups in an ELF file, as the name of each group's
+/// signature symbol and the indices of its member sections.
+fn comdat_groups<'a>(elf_file: &'a ElfFile) -> Result<Vec<(&'a str, &'a [u32])>, LoaderError> {
+    const GRP_COMDAT: u32 = 0x1;
+
+    let mut groups = Vec::new();
+    for section in elf_file
+        .section_iter()
+        .filter(|section| section.get_type() == Ok(SectionHeaderType::Group))
+    {
+        let Ok(SectionData::Group { flags, indices }) = section.get_data(elf_file) else {
+            return Err(LoaderError::Other("couldn't get data for section group"));
+        };
+        if flags & GRP_COMDAT == 0 {
+            continue;
+        }
+
+        let signature = elf_file
+            .get_symbol_table()?
+            .get(section.info() as usize)
+            .ok_or("section group's signature is out of bounds")?;
+        // Section symbols don't have names of their own.
+        let name = match signature.get_name(elf_file)? {
+            "" => elf_file
+                .get_section_header(signature.shndx())?
+                .get_name(elf_file)?,
+            name => name,
+        };
+        groups.push((name, indices));
+    }
+
+    Ok(groups)
+}
+
+/// Register the object's `.eh_frame` with the [unwinder](unwind), now that
+/// the addresses in it have been relocated.
+/// Call the functions in an object's `.init_array` sections in order, or the
+/// ones in its `.fini_array` sections in reverse order.
+///
+/// NOTE: These are called by the kernel, on the current stack, since objects
+///       aren't loaded on behalf of any one process.
+fn run_array_functions(object: &Mutex<LoadedObject>, fini: bool) {
+    let (name, mut functions) = {
+        let object = object.lock();
+        let sections = if fini {
+            &object.fini_array_sections
+        } else {
+            &object.init_array_sections
+        };
+        let mut functions = Vec::new();
+        for section in sections
+            .iter()
+            .filter_map(|index| object.sections.get(index))
+        {
+            let mut mapping = section.mapping.lock();
+            let slice = mapping.as_slice_mut(section.mapping_offset, section.size);
+            functions.extend(
+                slice
+                    .chunks_exact(size_of::<usize>())
+                    .map(|entry| usize::from_ne_bytes(entry.try_into().unwrap()))
+                    // Some toolchains terminate these arrays with zero or `-1`.
+                    .filter(|addr| *addr != 0 && *addr != usize::MAX),
+            );
+        }
+
+        (object.name.clone(), functions)
+    };
+    if functions.is_empty() {
+        return;
+    }
+    if fini {
+        functions.reverse();
+    }
+
+    debug!(
+        "Running {} {} for `{name}`...",
+        functions.len(),
+        if fini {
+            "finalizer(s)"
+        } else {
+            "initializer(s)"
+        },
+    );
+    for addr in functions {
+        // SAFETY: The object was relocated, so its arrays hold the addresses of its
+        //         (or its dependencies') initializers and finalizers.
+        let function: extern "C" fn() = unsafe { core::mem::transmute(addr) };
+        function();
+    }
+}
+
+/// Whether an object is a fully linked shared object (`ET_DYN`), see
+/// [`Loader::load_shared_object`].
+fn is_shared_object(object_bytes: &[u8]) -> bool {
+    ElfFile::new(object_bytes)
+        .is_ok_and(|elf_file| elf_file.header.get_type() == ObjectFileType::SharedObject)
+}
+
+/// What a shared object's dynamic relocation is written with, see
+/// [`Loader::load_shared_object`].
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum DynamicRelocation {
+    /// Nothing is written.
+    None,
+    /// The address the shared object was loaded at, plus the addend.
+    Relative,
+    /// The address of the relocation's symbol, plus the addend.
+    Symbol,
+}
+
+#[cfg(target_arch = "x86_64")]
+impl DynamicRelocation {
+    /// The relocation type that dynamic relocations are written as.
+    const ABSOLUTE: u32 = 1; // R_X86_64_64
+
+    fn of(kind: u32) -> Result<Self, LoaderError> {
+        const R_X86_64_NONE: u32 = 0;
+        const R_X86_64_64: u32 = 1;
+        const R_X86_64_GLOB_DAT: u32 = 6;
+        const R_X86_64_JUMP_SLOT: u32 = 7;
+        const R_X86_64_RELATIVE: u32 = 8;
+
+        match kind {
+            R_X86_64_NONE => Ok(Self::None),
+            R_X86_64_RELATIVE => Ok(Self::Relative),
+            R_X86_64_64 | R_X86_64_GLOB_DAT | R_X86_64_JUMP_SLOT => Ok(Self::Symbol),
+            other => Err(LoaderError::UnsupportedRelocation(other)),
+        }
+    }
+}
+
+#[cfg(target_arch = "aarch64")]
+impl DynamicRelocation {
+    /// The relocation type that dynamic relocations are written as.
+    const ABSOLUTE: u32 = aarch64::R_AARCH64_ABS64;
+
+    fn of(kind: u32) -> Result<Self, LoaderError> {
+        const R_AARCH64_NONE: u32 = 0;
+        const R_AARCH64_GLOB_DAT: u32 = 1025;
+        const R_AARCH64_JUMP_SLOT: u32 = 1026;
+        const R_AARCH64_RELATIVE: u32 = 1027;
+
+        match kind {
+            R_AARCH64_NONE => Ok(Self::None),
+            R_AARCH64_RELATIVE => Ok(Self::Relative),
+            aarch64::R_AARCH64_ABS64 | R_AARCH64_GLOB_DAT | R_AARCH64_JUMP_SLOT => Ok(Self::Symbol),
+            other => Err(LoaderError::UnsupportedRelocation(other)),
+        }
+    }
+}
+
+fn register_object_frames(object: &LoadedObject) -> Result<(), LoaderError> {
+    for section in object.sections.values() {
+        if section.kind != SectionKind::EhFrame {
+            continue;
+        }
+        let mut mapping = section.mapping.lock();
+        let eh_frame = mapping.as_slice_mut(section.mapping_offset, section.size);
+        let count = unwind::register_frames(section.addr, eh_frame)?;
+        trace!("Registered {count} frame(s) for `{}`", object.name);
+    }
+
+    Ok(())
 }
 
 fn map_dependency_sections(
@@ -1196,6 +3770,42 @@ fn map_dependency_sections(
             mappings.insert(rw_lock.addr);
         }
     }
+    if let Some(veneer_mapping) = dependency.veneer_mapping.as_ref() {
+        let veneer_lock = veneer_mapping.lock();
+        if !mappings.contains(&veneer_lock.addr) {
+            let pages = veneer_lock.pages;
+            let flags = veneer_lock.flags;
+            _ = veneer_lock.map_into(address_space, pages, flags);
+            mappings.insert(veneer_lock.addr);
+        }
+    }
+    if let Some(lazy_stub_mapping) = dependency.lazy_stub_mapping.as_ref() {
+        let lazy_stub_lock = lazy_stub_mapping.lock();
+        if !mappings.contains(&lazy_stub_lock.addr) {
+            let pages = lazy_stub_lock.pages;
+            let flags = lazy_stub_lock.flags;
+            _ = lazy_stub_lock.map_into(address_space, pages, flags);
+            mappings.insert(lazy_stub_lock.addr);
+        }
+    }
+    if let Some(image_mapping) = dependency.image_mapping.as_ref() {
+        let image_lock = image_mapping.lock();
+        if !mappings.contains(&image_lock.addr) {
+            let pages = image_lock.pages;
+            let flags = image_lock.flags;
+            _ = image_lock.map_into(address_space, pages, flags);
+            for segment in &dependency.image_segments {
+                _ = address_space.set_flags(
+                    PageRange::from_start_len(
+                        pages.start + segment.offset / PAGE_SIZE,
+                        segment.size / PAGE_SIZE,
+                    ),
+                    segment.flags,
+                );
+            }
+            mappings.insert(image_lock.addr);
+        }
+    }
 
     for secondary_dep in dependency.dependencies.iter() {
         map_dependency_sections(
@@ -1207,33 +3817,55 @@ fn map_dependency_sections(
     }
 }
 
+/// Branches on x86_64 can reach anywhere, so no veneers are needed.
+#[cfg(target_arch = "x86_64")]
+struct Veneers;
+
+#[cfg(target_arch = "x86_64")]
+impl Veneers {
+    fn new(_object_name: &str, _elf_file: &ElfFile) -> Result<Self, LoaderError> {
+        Ok(Self)
+    }
+
+    fn empty() -> Self {
+        Self
+    }
+
+    fn mapping(&self) -> Option<&Arc<Mutex<KernelMapping>>> {
+        None
+    }
+}
+
 #[cfg(target_arch = "x86_64")]
 fn write_relocation(
-    relocation_entry: &elf::Rela,
+    relocation: Relocation,
     target_slice: &mut [u8],
     target_offset: usize,
     source_addr: VirtualAddress,
-) -> Result<(), &'static str> {
+    _veneers: &mut Veneers,
+) -> Result<(), LoaderError> {
     // https://docs.rs/goblin/latest/src/goblin/elf/constants_relocation.rs.html
     const R_X86_64_64: u32 = 1;
     const R_X86_64_PC32: u32 = 2;
     const R_X86_64_PLT32: u32 = 4;
     const R_X86_64_32: u32 = 10;
     const R_X86_64_32S: u32 = 11;
+    const R_X86_64_TPOFF64: u32 = 18;
+    const R_X86_64_TPOFF32: u32 = 23;
     const R_X86_64_PC64: u32 = 24;
 
     // trace!(
     //     "REL({}): {source_addr:#x} | {:#p}, {target_offset:#x}",
-    //     relocation_entry.get_type(),
+    //     relocation.kind,
     //     target_slice.as_ptr(),
     // );
 
     let source_addr = source_addr.to_raw() as u64;
-    match relocation_entry.get_type() {
+    match relocation.kind {
         R_X86_64_32 | R_X86_64_32S => {
             let target_range = target_offset..(target_offset + size_of::<u32>());
             let target_ref = &mut target_slice[target_range];
-            let source_value = source_addr.wrapping_add(relocation_entry.get_addend()) as u32;
+            let source_value = source_addr.wrapping_add(relocation.addend) as u32;
 
             target_ref.copy_from_slice(&source_value.to_ne_bytes());
         }
@@ -1241,7 +3873,7 @@ fn write_relocation(
             let target_range = target_offset..(target_offset + size_of::<u32>());
             let target_ref = &mut target_slice[target_range];
             let source_value = source_addr
-                .wrapping_add(relocation_entry.get_addend())
+                .wrapping_add(relocation.addend)
                 .wrapping_sub(target_ref.as_ptr() as usize as u64)
                 as u32;
 
@@ -1250,7 +3882,7 @@ fn write_relocation(
         R_X86_64_64 => {
             let target_range = target_offset..(target_offset + size_of::<u64>());
             let target_ref = &mut target_slice[target_range];
-            let source_value = source_addr.wrapping_add(relocation_entry.get_addend());
+            let source_value = source_addr.wrapping_add(relocation.addend);
 
             target_ref.copy_from_slice(&source_value.to_ne_bytes());
         }
@@ -1258,16 +3890,255 @@ fn write_relocation(
             let target_range = target_offset..(target_offset + size_of::<u64>());
             let target_ref = &mut target_slice[target_range];
             let source_val = source_addr
-                .wrapping_add(relocation_entry.get_addend())
+                .wrapping_add(relocation.addend)
                 .wrapping_sub(target_ref.as_ptr() as usize as u64);
 
             target_ref.copy_from_slice(&source_val.to_ne_bytes());
         }
+        // The source is a TLS section, so its address is its offset into the static TLS
+        // block, which ends at the thread pointer.
+        R_X86_64_TPOFF32 => {
+            let target_range = target_offset..(target_offset + size_of::<u32>());
+            let target_ref = &mut target_slice[target_range];
+            let source_value = static_tls::thread_pointer_offset(
+                source_addr.wrapping_add(relocation.addend),
+                STATIC_TLS_LIMIT,
+            ) as u32;
+
+            target_ref.copy_from_slice(&source_value.to_ne_bytes());
+        }
+        R_X86_64_TPOFF64 => {
+            let target_range = target_offset..(target_offset + size_of::<u64>());
+            let target_ref = &mut target_slice[target_range];
+            let source_value = static_tls::thread_pointer_offset(
+                source_addr.wrapping_add(relocation.addend),
+                STATIC_TLS_LIMIT,
+            );
+
+            target_ref.copy_from_slice(&source_value.to_ne_bytes());
+        }
+
+        other => return Err(LoaderError::UnsupportedRelocation(other)),
+    }
+
+    Ok(())
+}
+
+/// Stubs that branch to targets too far away for a `B` or `BL` instruction to
+/// reach (more than 128 MiB), with one mapping per object.
+#[cfg(target_arch = "aarch64")]
+struct Veneers {
+    mapping: Option<Arc<Mutex<KernelMapping>>>,
+    used: usize,
+    /// The address of the veneer for each branch target.
+    targets: BTreeMap<u64, VirtualAddress>,
+}
+
+#[cfg(target_arch = "aarch64")]
+impl Veneers {
+    /// `LDR x16, #8; BR x16`, followed by the target address.
+    const SIZE: usize = 16;
+
+    /// Allocate enough space for every branch relocation in the object to need
+    /// its own veneer.
+    fn new(object_name: &str, elf_file: &ElfFile) -> Result<Self, LoaderError> {
+        let mut branch_count = 0;
+        for section in elf_file.section_iter().filter(|section| {
+            section.get_type() == Ok(SectionHeaderType::Rela) && section.size() != 0
+        }) {
+            let Ok(SectionData::Rela(rela_array)) = section.get_data(elf_file) else {
+                return Err(LoaderError::Other(
+                    "found `rela` section that wasn't able to be parsed",
+                ));
+            };
+            branch_count += rela_array
+                .iter()
+                .filter(|rela_entry| {
+                    matches!(
+                        rela_entry.get_type(),
+                        aarch64::R_AARCH64_JUMP26 | aarch64::R_AARCH64_CALL26
+                    )
+                })
+                .count();
+        }
+
+        Ok(Self {
+            mapping: (branch_count > 0).then(|| {
+                Arc::new(Mutex::new(KernelMapping::new(
+                    format!("{object_name}.veneers"),
+                    branch_count * Self::SIZE,
+                    PageTableFlags::PRESENT
+                        | PageTableFlags::WRITABLE
+                        | PageTableFlags::USER_ACCESSIBLE,
+                )))
+            }),
+            used: 0,
+            targets: BTreeMap::new(),
+        })
+    }
+
+    /// No veneers, for when relocations are written again, so branches that
+    /// are out of range fail.
+    fn empty() -> Self {
+        Self {
+            mapping: None,
+            used: 0,
+            targets: BTreeMap::new(),
+        }
+    }
+
+    fn mapping(&self) -> Option<&Arc<Mutex<KernelMapping>>> {
+        self.mapping.as_ref()
+    }
+
+    /// Get the address of a veneer that branches to the target, writing a new
+    /// one if there isn't one already.
+    fn get_or_write(&mut self, target: u64) -> Result<VirtualAddress, LoaderError> {
+        const LDR_X16_8: u32 = 0x5800_0050;
+        const BR_X16: u32 = 0xd61f_0200;
+
+        if let Some(addr) = self.targets.get(&target) {
+            return Ok(*addr);
+        }
+        let mut mapping = self
+            .mapping
+            .as_ref()
+            .ok_or("no veneers were allocated")?
+            .lock();
+        if self.used + Self::SIZE > mapping.size() {
+            return Err(LoaderError::SectionOverflow("ran out of space for veneers"));
+        }
 
-        other => {
-            error!("Unsupported relocation type: {other}");
-            return Err("unsupported relocation type");
+        let slice = mapping.as_slice_mut(self.used, Self::SIZE);
+        slice[0..4].copy_from_slice(&LDR_X16_8.to_le_bytes());
+        slice[4..8].copy_from_slice(&BR_X16.to_le_bytes());
+        slice[8..16].copy_from_slice(&target.to_ne_bytes());
+        let addr = mapping.addr + self.used;
+        self.used += Self::SIZE;
+        self.targets.insert(target, addr);
+
+        Ok(addr)
+    }
+}
+
+#[cfg(target_arch = "aarch64")]
+mod aarch64 {
+    // https://github.com/ARM-software/abi-aa/blob/main/aaelf64/aaelf64.rst#relocation
+    pub const R_AARCH64_ABS64: u32 = 257;
+    pub const R_AARCH64_PREL64: u32 = 260;
+    pub const R_AARCH64_PREL32: u32 = 261;
+    pub const R_AARCH64_ADR_PREL_PG_HI21: u32 = 275;
+    pub const R_AARCH64_ADD_ABS_LO12_NC: u32 = 277;
+    pub const R_AARCH64_LDST8_ABS_LO12_NC: u32 = 278;
+    pub const R_AARCH64_JUMP26: u32 = 282;
+    pub const R_AARCH64_CALL26: u32 = 283;
+    pub const R_AARCH64_LDST16_ABS_LO12_NC: u32 = 284;
+    pub const R_AARCH64_LDST32_ABS_LO12_NC: u32 = 285;
+    pub const R_AARCH64_LDST64_ABS_LO12_NC: u32 = 286;
+    pub const R_AARCH64_LDST128_ABS_LO12_NC: u32 = 299;
+
+    /// Replace the bits of the instruction covered by `mask` with `value`.
+    pub fn patch_instruction(target: &mut [u8], mask: u32, value: u32) {
+        let instruction = u32::from_le_bytes(target.try_into().unwrap());
+        let instruction = (instruction & !mask) | (value & mask);
+        target.copy_from_slice(&instruction.to_le_bytes());
+    }
+
+    /// Whether a `B` or `BL` instruction can branch by this offset.
+    pub fn branch_in_range(offset: i64) -> bool {
+        offset % 4 == 0 && (-(1 << 27)..(1 << 27)).contains(&offset)
+    }
+}
+
+#[cfg(target_arch = "aarch64")]
+fn write_relocation(
+    relocation: Relocation,
+    target_slice: &mut [u8],
+    target_offset: usize,
+    source_addr: VirtualAddress,
+    veneers: &mut Veneers,
+) -> Result<(), LoaderError> {
+    use aarch64::*;
+
+    let source_value = (source_addr.to_raw() as u64).wrapping_add(relocation.addend);
+    let place = target_slice[target_offset..].as_ptr() as usize as u64;
+    match relocation.kind {
+        R_AARCH64_ABS64 => {
+            let target_range = target_offset..(target_offset + size_of::<u64>());
+            target_slice[target_range].copy_from_slice(&source_value.to_ne_bytes());
+        }
+        R_AARCH64_PREL64 => {
+            let target_range = target_offset..(target_offset + size_of::<u64>());
+            let source_value = source_value.wrapping_sub(place);
+
+            target_slice[target_range].copy_from_slice(&source_value.to_ne_bytes());
+        }
+        R_AARCH64_PREL32 => {
+            let target_range = target_offset..(target_offset + size_of::<u32>());
+            let source_value =
+                i32::try_from(source_value.wrapping_sub(place) as i64).map_err(|_| {
+                    LoaderError::SectionOverflow("`R_AARCH64_PREL32` relocation is out of range")
+                })?;
+
+            target_slice[target_range].copy_from_slice(&source_value.to_ne_bytes());
+        }
+        R_AARCH64_JUMP26 | R_AARCH64_CALL26 => {
+            let target_range = target_offset..(target_offset + size_of::<u32>());
+            let mut offset = source_value.wrapping_sub(place) as i64;
+            if !branch_in_range(offset) {
+                let veneer = veneers.get_or_write(source_value)?;
+                offset = (veneer.to_raw() as u64).wrapping_sub(place) as i64;
+                if !branch_in_range(offset) {
+                    return Err(LoaderError::SectionOverflow(
+                        "veneer is out of range of its branch",
+                    ));
+                }
+            }
+
+            patch_instruction(
+                &mut target_slice[target_range],
+                0x03ff_ffff,
+                (offset >> 2) as u32,
+            );
+        }
+        R_AARCH64_ADR_PREL_PG_HI21 => {
+            let target_range = target_offset..(target_offset + size_of::<u32>());
+            let page_offset = ((source_value & !0xfff) as i64 - (place & !0xfff) as i64) >> 12;
+            if !(-(1 << 20)..(1 << 20)).contains(&page_offset) {
+                return Err(LoaderError::SectionOverflow(
+                    "`R_AARCH64_ADR_PREL_PG_HI21` relocation is out of range",
+                ));
+            }
+
+            // The low 2 bits go in `immlo` (29..31), and the rest in `immhi` (5..24).
+            let page_offset = page_offset as u32;
+            patch_instruction(
+                &mut target_slice[target_range],
+                (0b11 << 29) | (0x7_ffff << 5),
+                ((page_offset & 0b11) << 29) | (((page_offset >> 2) & 0x7_ffff) << 5),
+            );
+        }
+        kind @ (R_AARCH64_ADD_ABS_LO12_NC
+        | R_AARCH64_LDST8_ABS_LO12_NC
+        | R_AARCH64_LDST16_ABS_LO12_NC
+        | R_AARCH64_LDST32_ABS_LO12_NC
+        | R_AARCH64_LDST64_ABS_LO12_NC
+        | R_AARCH64_LDST128_ABS_LO12_NC) => {
+            let target_range = target_offset..(target_offset + size_of::<u32>());
+            // Loads and stores scale the offset by the size of the access.
+            let shift = match kind {
+                R_AARCH64_LDST16_ABS_LO12_NC => 1,
+                R_AARCH64_LDST32_ABS_LO12_NC => 2,
+                R_AARCH64_LDST64_ABS_LO12_NC => 3,
+                R_AARCH64_LDST128_ABS_LO12_NC => 4,
+                _ => 0,
+            };
+            let imm12 = ((source_value & 0xfff) >> shift) as u32;
+
+            patch_instruction(&mut target_slice[target_range], 0xfff << 10, imm12 << 10);
         }
+
+        other => return Err(LoaderError::UnsupportedRelocation(other)),
     }
 
     Ok(())
@@ -1275,11 +4146,146 @@ fn write_relocation(
 
 
 
+
+/// A call target that hasn't been resolved yet, see
+/// [`Loader::set_lazy_binding`].
+#[derive(Debug)]
+struct LazyStub {
+    /// The demangled name of the function.
+    symbol: Arc<str>,
+    /// The object that calls the function.
+    object: Weak<Mutex<LoadedObject>>,
+    mapping: Weak<Mutex<KernelMapping>>,
+    /// The offset of the stub into its mapping.
+    offset: usize,
+    /// The sections that call the function, with the relocations that point
+    /// those calls at the stub.
+    sites: Vec<(Weak<LoadedSection>, Relocation)>,
+    /// The address of the function, once it's been resolved.
+    resolved: Option<VirtualAddress>,
+}
+
+/// The [lazy stubs](LazyStub) of an object being relocated, with one mapping
+/// per object. Their code is written by the `lazy_stub` crate.
+struct LazyStubs {
+    mapping: Option<Arc<Mutex<KernelMapping>>>,
+    used: usize,
+    /// The ID and address of the stub for each symbol table index.
+    symbols: BTreeMap<usize, (usize, VirtualAddress)>,
+}
+
+impl LazyStubs {
+    /// Allocate a stub for every undefined function the object calls.
+    fn new(object_name: &str, elf_file: &ElfFile) -> Result<Self, LoaderError> {
+        let symbol_table = elf_file.get_symbol_table()?;
+        let mut symbols = BTreeSet::new();
+        for section in elf_file.section_iter().filter(|section| {
+            section.get_type() == Ok(SectionHeaderType::Rela) && section.size() != 0
+        }) {
+            let Ok(SectionData::Rela(rela_array)) = section.get_data(elf_file) else {
+                return Err(LoaderError::Other(
+                    "found `rela` section that wasn't able to be parsed",
+                ));
+            };
+            symbols.extend(
+                rela_array
+                    .iter()
+                    .filter(|rela_entry| Self::is_call(rela_entry.get_type()))
+                    .map(|rela_entry| rela_entry.get_symbol_table_index() as usize)
+                    .filter(|index| symbol_table[*index].shndx() == 0),
+            );
+        }
+
+        Ok(Self {
+            mapping: (!symbols.is_empty()).then(|| {
+                Arc::new(Mutex::new(KernelMapping::new(
+                    format!("{object_name}.lazy"),
+                    symbols.len() * STUB_SIZE,
+                    PageTableFlags::PRESENT
+                        | PageTableFlags::WRITABLE
+                        | PageTableFlags::USER_ACCESSIBLE,
+                )))
+            }),
+            used: 0,
+            symbols: BTreeMap::new(),
+        })
+    }
+
+    /// No stubs, so every call is bound when the object is relocated.
+    fn empty() -> Self {
+        Self {
+            mapping: None,
+            used: 0,
+            symbols: BTreeMap::new(),
+        }
+    }
+
+    fn mapping(&self) -> Option<&Arc<Mutex<KernelMapping>>> {
+        self.mapping.as_ref()
+    }
+
+    /// Whether a relocation of the given type is a direct call that can be
+    /// bound lazily.
+    fn is_call(kind: u32) -> bool {
+        const R_X86_64_PLT32: u32 = 4;
+
+        cfg!(target_arch = "x86_64") && kind == R_X86_64_PLT32
+    }
+
+    /// Get the ID and address of the stub for the function at the given symbol
+    /// table index, writing a new one if there isn't one already.
+    fn get_or_write(
+        &mut self,
+        stubs: &Mutex<Vec<LazyStub>>,
+        symbol_index: usize,
+        symbol: &str,
+        object: &Weak<Mutex<LoadedObject>>,
+    ) -> Result<(usize, VirtualAddress), LoaderError> {
+        if let Some(stub) = self.symbols.get(&symbol_index) {
+            return Ok(*stub);
+        }
+        let mapping = self
+            .mapping
+            .as_ref()
+            .ok_or("no lazy stubs were allocated")?;
+        let mut mapping_lock = mapping.lock();
+        if self.used + STUB_SIZE > mapping_lock.size() {
+            return Err(LoaderError::SectionOverflow(
+                "ran out of space for lazy stubs",
+            ));
+        }
+
+        let mut stubs = stubs.lock();
+        let id = stubs.len();
+        let code = lazy_stub::unresolved_code(id, LAZY_BIND_INTERRUPT_NUMBER)
+            .ok_or(LoaderError::SectionOverflow("ran out of lazy stub IDs"))?;
+        mapping_lock
+            .as_slice_mut(self.used, STUB_SIZE)
+            .copy_from_slice(&code);
+        let addr = mapping_lock.addr + self.used;
+
+        stubs.push(LazyStub {
+            symbol: symbol.into(),
+            object: object.clone(),
+            mapping: Arc::downgrade(mapping),
+            offset: self.used,
+            sites: Vec::new(),
+            resolved: None,
+        });
+        self.used += STUB_SIZE;
+        self.symbols.insert(symbol_index, (id, addr));
+
+        Ok((id, addr))
+    }
+}
+
+
+
 // TODO: This needs to be thoroughly tested.
 fn allocate_section_mappings(
     object_name: &str,
     elf_file: &ElfFile,
-) -> Result<SectionMappings, &'static str> {
+) -> Result<SectionMappings, LoaderError> {
     let (executable_len, read_only_len, read_write_len): (usize, usize, usize) = {
         let mut executable_len = 0;
         let mut read_only_len = 0;
@@ -1362,6 +4368,414 @@ struct SectionMappings {
     read_write: Option<KernelMapping>,
 }
 
+/// The static TLS block shared by every loaded object.
+#[derive(Debug)]
+struct StaticTls {
+    layout: StaticTlsLayout,
+    /// Every TLS section, used to initialize new [`TlsArea`]s.
+    sections: Vec<Weak<LoadedSection>>,
+}
+
+impl StaticTls {
+    const fn new() -> Self {
+        Self {
+            layout: StaticTlsLayout::new(STATIC_TLS_LIMIT),
+            sections: Vec::new(),
+        }
+    }
+
+    /// Reserve space for a section, returning its offset into the block.
+    fn reserve(&mut self, size: usize, align: usize) -> Result<usize, LoaderError> {
+        self.layout
+            .reserve(size, align)
+            .ok_or(LoaderError::SectionOverflow("static TLS block is full"))
+    }
+}
+
+/// A thread's copy of the static TLS block, created with
+/// [`Loader::allocate_tls_area`].
+#[derive(Debug)]
+pub struct TlsArea {
+    mapping: KernelMapping,
+    /// The address (in the thread's address space) that the `%fs` base should
+    /// be set to.
+    pub thread_pointer: VirtualAddress,
+}
+
+impl TlsArea {
+    /// The size of this area in bytes, including the word at the thread
+    /// pointer.
+    pub fn size(&self) -> usize {
+        self.mapping.size()
+    }
+}
+
+
+
+/// Get the path an object with the given name is read from.
+pub fn object_path(name: &str) -> String {
+    if name.starts_with("/") {
+        name.to_string()
+    } else if name.ends_with(')') {
+        // Archive members are named `archive(member)`, see `archive_file_names`.
+        format!("{OBJECT_SEARCH_PATH}{name}")
+    } else if name.ends_with(".so") {
+        // Shared objects are named after their files, like `DT_NEEDED` entries.
+        format!("{OBJECT_SEARCH_PATH}{name}")
+    } else {
+        format!("{OBJECT_SEARCH_PATH}{name}.o")
+    }
+}
+
+/// The names of the archives within the [`OBJECT_SEARCH_PATH`] that a crate's
+/// dependencies are loaded from when it doesn't have an object file, in the
+/// order they're searched.
+///
+/// Only the members needed to resolve a symbol are loaded, and each one is
+/// named `archive(member)`.
+pub fn archive_file_names(crate_name: &str) -> [String; 2] {
+    [format!("{crate_name}.rlib"), format!("lib{crate_name}.a")]
+}
+
+fn is_in_search_path(path: &str) -> bool {
+    path.strip_prefix(OBJECT_SEARCH_PATH)
+        .is_some_and(|name| !name.contains('/'))
+}
+
+
+
+/// The report produced by [`Loader::audit`].
+#[derive(Debug, Default)]
+pub struct LoaderAudit {
+    /// Every mapping of every loaded object, sorted by address.
+    pub mappings: Vec<MappingAudit>,
+    /// Executable sections that relocations were written into, sorted by
+    /// address.
+    pub text_relocations: Vec<SectionAudit>,
+    /// Weak symbols that were left unresolved.
+    pub unresolved_weak_symbols: Vec<SymbolAudit>,
+    /// Objects that were read from outside of [`OBJECT_SEARCH_PATH`].
+    pub objects_outside_search_path: Vec<ObjectAudit>,
+}
+
+impl LoaderAudit {
+    pub fn writable_executable_mappings(&self) -> impl Iterator<Item = &MappingAudit> {
+        self.mappings
+            .iter()
+            .filter(|mapping| mapping.is_writable_executable())
+    }
+}
+
+impl fmt::Display for LoaderAudit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "--- LOADER AUDIT ---")?;
+        writeln!(
+            f,
+            "mappings ({}, {} writable and executable):",
+            self.mappings.len(),
+            self.writable_executable_mappings().count(),
+        )?;
+        for mapping in &self.mappings {
+            writeln!(
+                f,
+                "    {:#x} | {:>10} | {} | {:?}",
+                mapping.addr,
+                mapping.size,
+                if mapping.is_writable_executable() {
+                    "W+X"
+                } else {
+                    "   "
+                },
+                mapping.flags,
+            )?;
+            writeln!(f, "        {} ({})", mapping.object, mapping.name)?;
+        }
+        writeln!(
+            f,
+            "relocated executable sections ({}):",
+            self.text_relocations.len(),
+        )?;
+        for section in &self.text_relocations {
+            writeln!(
+                f,
+                "    {:#x} | {} | {}",
+                section.addr,
+                section.object,
+                &section.section[..section.section.len().min(50)],
+            )?;
+        }
+        writeln!(
+            f,
+            "unresolved weak symbols ({}):",
+            self.unresolved_weak_symbols.len(),
+        )?;
+        for symbol in &self.unresolved_weak_symbols {
+            writeln!(f, "    {} | {}", symbol.object, symbol.symbol)?;
+        }
+        writeln!(
+            f,
+            "objects outside `{OBJECT_SEARCH_PATH}` ({}):",
+            self.objects_outside_search_path.len(),
+        )?;
+        for object in &self.objects_outside_search_path {
+            writeln!(f, "    {} | {}", object.object, object.path)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// How long each phase of [loading a batch](Loader::load_objects_in) of
+/// objects took.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct BatchTimings {
+    /// Reading the objects from the file system.
+    pub read_time: Duration,
+    /// Loading every object's sections, which only depends on the object
+    /// itself.
+    pub sections_time: Duration,
+    /// Relocating every object, and loading their dependencies.
+    pub relocation_time: Duration,
+    /// The number of processors that the sections were loaded on.
+    pub processors: usize,
+}
+
+impl fmt::Display for BatchTimings {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{:?} (reading: {:?}, sections: {:?} on {} processor(s), relocation: {:?})",
+            self.read_time + self.sections_time + self.relocation_time,
+            self.read_time,
+            self.sections_time,
+            self.processors,
+            self.relocation_time,
+        )
+    }
+}
+
+/// The report produced by [`Loader::stats`].
+#[derive(Debug, Default)]
+pub struct LoaderStats {
+    /// The usage of each loaded object, sorted by namespace and name.
+    pub objects: Vec<ObjectStats>,
+    /// The combined usage of every loaded object.
+    pub total: UsageStats,
+    /// The number of [lazy stubs](Loader::set_lazy_binding) that have been
+    /// written. They're never freed, even when their object is unloaded.
+    pub lazy_stub_count: usize,
+    /// The number of bytes of mappings that were still referenced when their
+    /// object was unloaded, so they were never freed.
+    pub leaked_bytes: usize,
+}
+
+impl fmt::Display for LoaderStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "--- LOADER STATS ---")?;
+        writeln!(
+            f,
+            "    {:>10} | {:>10} | {:>10} | {:>8} | {:>8} | {:>8} | object",
+            "text", "rodata", "data", "sections", "resolved", "missing",
+        )?;
+        let mut write_usage = |usage: &UsageStats, name: fmt::Arguments| {
+            writeln!(
+                f,
+                "    {:>10} | {:>10} | {:>10} | {:>8} | {:>8} | {:>8} | {name}",
+                usage.text_bytes,
+                usage.rodata_bytes,
+                usage.data_bytes,
+                usage.section_count,
+                usage.resolved_symbols,
+                usage.unresolved_weak_symbols + usage.lazy_symbols,
+            )
+        };
+        for object in &self.objects {
+            write_usage(
+                &object.usage,
+                format_args!(
+                    "{}::{}",
+                    object.namespace,
+                    &object.name[..object.name.len().min(40)],
+                ),
+            )?;
+        }
+        write_usage(
+            &self.total,
+            format_args!("total ({} objects)", self.objects.len()),
+        )?;
+        writeln!(f, "lazy stubs: {}", self.lazy_stub_count)?;
+        writeln!(f, "leaked bytes: {}", self.leaked_bytes)?;
+
+        Ok(())
+    }
+}
+
+/// A [`LoadedObject`] reported by [`Loader::stats`].
+#[derive(Debug)]
+pub struct ObjectStats {
+    pub namespace: Arc<str>,
+    pub name: Arc<str>,
+    pub usage: UsageStats,
+}
+
+/// The memory and symbols used by one or more objects, see
+/// [`Loader::stats`].
+#[derive(Clone, Copy, Debug, Default)]
+pub struct UsageStats {
+    /// The bytes of executable mappings, including veneers and lazy stubs.
+    pub text_bytes: usize,
+    /// The bytes of read-only mappings.
+    pub rodata_bytes: usize,
+    /// The bytes of writable mappings.
+    pub data_bytes: usize,
+    pub section_count: usize,
+    pub global_section_count: usize,
+    /// The number of sections of other objects that relocations were written
+    /// against, plus the lazily bound functions that have been resolved.
+    ///
+    /// The relocations of shared objects aren't tracked per section, so
+    /// aren't counted.
+    pub resolved_symbols: usize,
+    /// The number of lazily bound functions that haven't been called yet.
+    pub lazy_symbols: usize,
+    /// The number of weak symbols that were left unresolved.
+    pub unresolved_weak_symbols: usize,
+}
+
+impl core::ops::AddAssign for UsageStats {
+    fn add_assign(&mut self, other: Self) {
+        self.text_bytes += other.text_bytes;
+        self.rodata_bytes += other.rodata_bytes;
+        self.data_bytes += other.data_bytes;
+        self.section_count += other.section_count;
+        self.global_section_count += other.global_section_count;
+        self.resolved_symbols += other.resolved_symbols;
+        self.lazy_symbols += other.lazy_symbols;
+        self.unresolved_weak_symbols += other.unresolved_weak_symbols;
+    }
+}
+
+/// A [`KernelMapping`] reported by [`Loader::audit`].
+#[derive(Debug)]
+pub struct MappingAudit {
+    /// The name of the object that owns this mapping.
+    pub object: Arc<str>,
+    pub name: String,
+    pub addr: VirtualAddress,
+    pub size: usize,
+    pub flags: PageTableFlags,
+}
+
+impl MappingAudit {
+    pub fn is_writable_executable(&self) -> bool {
+        self.flags.get(PageTableFlags::WRITABLE) && !self.flags.get(PageTableFlags::NO_EXECUTE)
+    }
+}
+
+/// A [`LoadedSection`] reported by [`Loader::audit`].
+#[derive(Debug)]
+pub struct SectionAudit {
+    /// The name of the object that owns this section.
+    pub object: Arc<str>,
+    pub section: Arc<str>,
+    pub addr: VirtualAddress,
+}
+
+/// A symbol reported by [`Loader::audit`].
+#[derive(Debug)]
+pub struct SymbolAudit {
+    /// The name of the object that references this symbol.
+    pub object: Arc<str>,
+    pub symbol: Arc<str>,
+}
+
+/// A [`LoadedObject`] reported by [`Loader::audit`].
+#[derive(Debug)]
+pub struct ObjectAudit {
+    pub object: Arc<str>,
+    pub path: Arc<str>,
+}
+
+/// The report produced by [`Loader::verify_object`].
+#[derive(Debug, Default)]
+pub struct ObjectVerification {
+    /// Every symbol the object refers to without defining it.
+    pub undefined_symbols: Vec<UndefinedSymbol>,
+    /// The relocation types the object uses that aren't supported on this
+    /// architecture.
+    pub unsupported_relocations: BTreeSet<u32>,
+}
+
+impl ObjectVerification {
+    /// The error that loading the object would fail with, if any.
+    pub fn error(&self, object_name: &str) -> Option<LoaderError> {
+        if let Some(symbol) = self
+            .undefined_symbols
+            .iter()
+            .find(|symbol| !symbol.weak && symbol.resolution == SymbolResolution::Missing)
+        {
+            return Some(LoaderError::MissingSymbol {
+                symbol: symbol.name.clone(),
+                object: object_name.into(),
+            });
+        }
+
+        self.unsupported_relocations
+            .first()
+            .map(|kind| LoaderError::UnsupportedRelocation(*kind))
+    }
+}
+
+impl fmt::Display for ObjectVerification {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "--- OBJECT VERIFICATION ---")?;
+        writeln!(f, "undefined symbols ({}):", self.undefined_symbols.len(),)?;
+        for symbol in &self.undefined_symbols {
+            let resolution = match &symbol.resolution {
+                SymbolResolution::Loaded => "loaded",
+                SymbolResolution::Load(object) => object,
+                SymbolResolution::Missing if symbol.weak => "missing (resolves to zero)",
+                SymbolResolution::Missing => "MISSING",
+            };
+            writeln!(
+                f,
+                "    {} | {resolution} | {}",
+                if symbol.weak { "weak" } else { "    " },
+                &symbol.name[..symbol.name.len().min(50)],
+            )?;
+        }
+        writeln!(
+            f,
+            "unsupported relocation types ({}): {:?}",
+            self.unsupported_relocations.len(),
+            self.unsupported_relocations,
+        )?;
+
+        Ok(())
+    }
+}
+
+/// A symbol reported by [`Loader::verify_object`].
+#[derive(Debug)]
+pub struct UndefinedSymbol {
+    /// The demangled name of the symbol.
+    pub name: Arc<str>,
+    pub weak: bool,
+    pub resolution: SymbolResolution,
+}
+
+/// How an [`UndefinedSymbol`] would be resolved.
+#[derive(Debug, PartialEq, Eq)]
+pub enum SymbolResolution {
+    /// A loaded section already defines the symbol.
+    Loaded,
+    /// Loading the named object should define the symbol.
+    Load(String),
+    /// No object that defines the symbol could be found. This is only an
+    /// error for symbols that aren't weak.
+    Missing,
+}
+
 
 
 pub fn crate_names_in_symbol(symbol_name: &str) -> Vec<&str> {
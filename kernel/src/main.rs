@@ -17,7 +17,9 @@ mod loader;
 mod memory;
 mod scheduler;
 mod serial;
+mod smp;
 mod tsc;
+mod unwind;
 mod vfat;
 mod window_manager;
 
@@ -106,6 +108,8 @@ pub extern "sysv64" fn main(boot_info: &'static BootInfo) -> ! {
     let dur = pit_start.elapsed();
     debug!("`pit::sleep(1ms)`\t\t: {dur:?}");
 
+    smp::init();
+
     ata::init(boot_info);
 
     unsafe {
@@ -188,10 +192,14 @@ impl KernelStack {
 pub trait FileSystem {
     fn list(&self, dir_path: &str) -> Result<Vec<String>, &'static str>;
     fn read(&mut self, path: &str) -> Result<Vec<u8>, &'static str>;
+    fn entry(&self, path: &str) -> Result<DirectoryEntry, &'static str>;
 }
 
+#[derive(Clone, Debug)]
 pub struct DirectoryEntry {
     pub index: usize,
     pub name: String,
     pub size: usize,
+    /// When the file was last modified, in seconds since the Unix epoch.
+    pub modified: u64,
 }
@@ -18,7 +18,10 @@ use {
     spin_mutex::Mutex,
     x86_64::{
         instructions::interrupts::without_interrupts,
-        registers::control::{Cr0, Cr0Flags, Cr3, Cr3Flags},
+        registers::{
+            control::{Cr0, Cr0Flags, Cr3, Cr3Flags},
+            model_specific::{Efer, EferFlags},
+        },
     },
 };
 
@@ -53,6 +56,12 @@ pub fn init(boot_info: &BootInfo) {
         }
     }
 
+    // Allow pages to be marked as non-executable (see
+    // `PageTableFlags::NO_EXECUTE`).
+    unsafe {
+        Efer::update(|flags| flags.insert(EferFlags::NO_EXECUTE_ENABLE));
+    }
+
     let free_frames = {
         let mut frame_allocator = FRAME_ALLOCATOR.lock();
         frame_allocator.init(&boot_info.memory_map);
@@ -245,6 +254,48 @@ impl KernelMapping {
         unsafe { ((self.addr + offset).to_raw() as *mut T).as_mut().unwrap() }
     }
 
+    /// Change the flags of this mapping within the kernel's [`AddressSpace`].
+    pub fn set_flags(&mut self, flags: PageTableFlags) -> Result<(), MappingError> {
+        kernel_address_space().set_flags(self.pages, flags)?;
+        self.flags = flags;
+
+        Ok(())
+    }
+
+    /// Change the flags of the pages covering `offset..offset + len` within
+    /// the kernel's [`AddressSpace`], leaving the rest of this mapping as it
+    /// is.
+    ///
+    /// Unlike [`set_flags`](Self::set_flags), this doesn't change
+    /// [`self.flags`](Self::flags).
+    pub fn set_range_flags(
+        &mut self,
+        offset: usize,
+        len: usize,
+        flags: PageTableFlags,
+    ) -> Result<(), MappingError> {
+        assert!(
+            offset + len <= self.size(),
+            "Requested offset and length would overflow kernel mapping",
+        );
+
+        let first_page = offset / PAGE_SIZE;
+        let end_page = (offset + len).div_ceil(PAGE_SIZE);
+        kernel_address_space().set_flags(
+            PageRange::from_start_len(self.pages.start + first_page, end_page - first_page),
+            flags,
+        )
+    }
+
+    /// Unmap this mapping from the kernel's [`AddressSpace`], freeing its
+    /// frames.
+    ///
+    /// Any other address space it was [mapped into](Self::map_into) must no
+    /// longer be in use.
+    pub fn unmap(self) {
+        kernel_address_space().unmap_pages(self.pages);
+    }
+
     /// Make this mapping available within the given [`AddressSpace`].
     ///
     /// ## Arguments
@@ -570,11 +621,29 @@ impl AddressSpace {
             .push((name.into(), pages));
     }
 
-    pub fn set_flags(
-        &mut self,
-        pages: PageRange,
-        flags: PageTableFlags,
-    ) -> Result<(), MappingError> {
+    /// Unmap the pages, freeing the frames they were mapped to if this address
+    /// space allocated them.
+    pub fn unmap_pages(&self, pages: PageRange) {
+        let mut frame_allocator = self.frame_allocator.lock();
+        let mut page_table = self.page_table.lock();
+
+        for page in pages {
+            if let Ok((frame, flush)) = page_table.unmap(page) {
+                flush.flush();
+                frame_allocator.deallocate_frame(frame);
+            }
+        }
+
+        if let Some(mappings) = TRACKER
+            .lock()
+            .spaces
+            .get_mut(&(self.name.clone(), self.frame))
+        {
+            mappings.retain(|(_, mapped_pages)| *mapped_pages != pages);
+        }
+    }
+
+    pub fn set_flags(&self, pages: PageRange, flags: PageTableFlags) -> Result<(), MappingError> {
         let mut frame_allocator = self.frame_allocator.lock();
         let mut page_table = self.page_table.lock();
 
@@ -618,6 +687,28 @@ impl AddressSpace {
     pub fn translate_page(&self, page: Page) -> Option<Frame> {
         self.page_table.lock().translate_page(page).ok()
     }
+
+    /// Whether every byte of `addr..addr + len` is mapped in this address space
+    /// and accessible from user mode, such as memory that a user process claims
+    /// to have passed to the kernel.
+    pub fn is_user_accessible(&self, addr: VirtualAddress, len: usize) -> bool {
+        let Some(last) = addr.to_raw().checked_add(len.saturating_sub(1)) else {
+            return false;
+        };
+        let page_table = self.page_table.lock();
+        let first = Page::containing_addr(addr).number();
+        let last = Page::containing_addr(VirtualAddress::new(last)).number();
+
+        (first..=last).all(|number| {
+            page_table
+                .translate(Page::new(number).base_addr())
+                .is_ok_and(|translation| {
+                    translation
+                        .flags
+                        .get(PageTableFlags::PRESENT | PageTableFlags::USER_ACCESSIBLE)
+                })
+        })
+    }
 }
 
 impl Drop for AddressSpace {
@@ -648,6 +739,21 @@ impl DynFrameAllocator for FrameAllocatorProxy {
     }
 }
 
+impl FrameAllocatorProxy {
+    /// Return a frame that this proxy allocated to the global
+    /// [`FrameAllocator`]. Frames that it didn't allocate are ignored.
+    fn deallocate_frame(&mut self, frame: Frame) {
+        let Some(index) = self.allocated_frames.iter().position(|f| *f == frame) else {
+            return;
+        };
+        self.allocated_frames.swap_remove(index);
+
+        without_interrupts(|| {
+            let _ = FRAME_ALLOCATOR.lock().deallocate(frame); // Ignore errors.
+        });
+    }
+}
+
 impl Drop for FrameAllocatorProxy {
     fn drop(&mut self) {
         without_interrupts(|| {
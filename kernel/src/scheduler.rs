@@ -5,23 +5,25 @@
 use {
     crate::{
         KERNEL_STACK, KERNEL_STACK_SIZE, gdt,
-        loader::global_loader,
+        loader::{DEFAULT_NAMESPACE, LoadedObject, TlsArea, global_loader},
         memory::{AddressSpace, kernel_address_space},
     },
     alloc::{
         collections::{btree_map::BTreeMap, vec_deque::VecDeque},
         string::String,
+        sync::Arc,
     },
     core::{
         arch::asm,
         fmt,
         sync::atomic::{AtomicU64, Ordering},
     },
-    log::{info, warn},
+    log::{debug, error, info, warn},
     memory_types::{PAGE_SIZE, Page, PageRange, PageTableFlags, VirtualAddress},
     spin_mutex::Mutex,
     x86_64::{
-        instructions::interrupts::without_interrupts, registers::rflags::RFlags,
+        instructions::interrupts::without_interrupts,
+        registers::{model_specific::FsBase, rflags::RFlags},
         structures::idt::InterruptStackFrameValue,
     },
 };
@@ -139,6 +141,8 @@ impl Scheduler {
                 ),
             }),
             allow_io: true,
+            tls: None,
+            object: None,
         });
     }
 
@@ -179,6 +183,12 @@ impl Scheduler {
             let process = self.next_ready();
             process.address_space.enter();
             crate::gdt::set_user_io_allowed(process.allow_io);
+            FsBase::write(x86_64::VirtAddr::new(
+                process
+                    .tls
+                    .as_ref()
+                    .map_or(0, |tls| tls.thread_pointer.to_raw() as u64),
+            ));
             self.current = Some(process);
         }
 
@@ -265,6 +275,8 @@ impl Scheduler {
             address_space,
             context: Some(context),
             allow_io: true,
+            tls: None,
+            object: None,
         };
 
         info!("Running {process}");
@@ -295,8 +307,11 @@ impl Scheduler {
         let user_code_addr = VirtualAddress::new(USER_CODE_ADDR);
         let user_code_page = Page::containing_addr(user_code_addr);
 
-        let _object = global_loader()
-            .load_object(&name, &address_space, user_code_page)
+        // Each program gets its own namespace named after it, so that the symbols it
+        // exports can't collide with another program's.
+        global_loader().create_namespace(&name, &[DEFAULT_NAMESPACE]);
+        let object = global_loader()
+            .load_object_in(&name, &name, &address_space, user_code_page)
             .unwrap();
 
         global_loader().dump_info();
@@ -316,6 +331,22 @@ impl Scheduler {
             PageTableFlags::PRESENT | PageTableFlags::WRITABLE | PageTableFlags::USER_ACCESSIBLE,
         );
 
+        // The TLS area goes right below the stack.
+        let tls = global_loader()
+            .allocate_tls_area(
+                &format!("{name}.{id}"),
+                &address_space,
+                Page::containing_addr(stack_top_addr) - stack_size.div_ceil(PAGE_SIZE),
+            )
+            .unwrap();
+        if let Some(tls) = &tls {
+            debug!(
+                "Allocated {} bytes of TLS at {:#x}",
+                tls.size(),
+                tls.thread_pointer,
+            );
+        }
+
         let context = ExecutionContext {
             registers: CpuRegisters::EMPTY,
             frame: InterruptStackFrameValue::new(
@@ -335,6 +366,8 @@ impl Scheduler {
             address_space,
             context: Some(context),
             allow_io,
+            tls,
+            object: Some(object),
         };
 
         info!("Running {process}");
@@ -387,26 +420,112 @@ pub fn schedule() -> ! {
 
 pub const DEFER_INTERRUPT_NUMBER: u8 = 0x40; // TODO: Choose a less arbitrary number.
 pub const EXIT_INTERRUPT_NUMBER: u8 = 0x41;
+/// Raised by the stubs of lazily bound calls, see
+/// [`Loader::set_lazy_binding`](crate::loader::Loader::set_lazy_binding).
+pub const LAZY_BIND_INTERRUPT_NUMBER: u8 = 0x42;
 
 define_interrupt_handler_with_context!(defer_interrupt_handler {
     with_scheduler(|scheduler| scheduler.preempt_current());
 });
 
 define_interrupt_handler_with_context!(exit_interrupt_handler {
+    exit_current();
+
+    // Immediately after this block is finished, `Scheduler::schedule_next` is
+    // called to set the next execution context.
+});
+
+define_interrupt_handler_with_context!(lazy_bind_interrupt_handler {
+    // NOTE: Resolving a stub can read files and load objects, so the process is
+    //       taken out of the scheduler while it happens instead of holding the
+    //       scheduler's lock throughout, the same way `exit_current` does.
+    let mut process = with_scheduler(|scheduler| scheduler.current.take())
+        .expect("current process should exist");
+    let resolved = resolve_lazy_stub_for(&mut process);
+    with_scheduler(|scheduler| scheduler.current = Some(process));
+
+    if !resolved {
+        exit_current();
+    }
+});
+
+/// Resolve the lazy stub that the process was interrupted in, continuing it
+/// in the resolved function. Returns `false` if the process should be killed.
+fn resolve_lazy_stub_for(process: &mut Process) -> bool {
+    // The stub that raised this interrupt pushed its ID on top of the return
+    // address of the call to it (see the `lazy_stub` crate). Once the function
+    // is resolved, the process continues in the function as if it had been
+    // called directly.
+    let stack_pointer = process
+        .context
+        .as_ref()
+        .expect("current process should have a context")
+        .frame
+        .stack_pointer;
+    // Any user process can raise this interrupt, not just the stubs, so its
+    // stack pointer can't be trusted to point at anything.
+    if !process.address_space.is_user_accessible(
+        VirtualAddress::new(stack_pointer.as_u64() as usize),
+        size_of::<u64>(),
+    ) {
+        error!("{process} raised a lazy binding interrupt with an invalid stack pointer");
+        return false;
+    }
+    let pushed = unsafe { stack_pointer.as_ptr::<u64>().read_unaligned() };
+    let Some(stub_id) = lazy_stub::pushed_id(pushed) else {
+        error!("{process} raised a lazy binding interrupt without a stub ID ({pushed:#x})");
+        return false;
+    };
+
+    match global_loader().resolve_lazy_stub(stub_id, &process.address_space) {
+        Ok(addr) => {
+            let resume = lazy_stub::resume(stack_pointer.as_u64(), addr.to_raw() as u64);
+            let context = process
+                .context
+                .as_mut()
+                .expect("current process should have a context");
+            context.frame.instruction_pointer = x86_64::VirtAddr::new(resume.instruction_pointer);
+            context.frame.stack_pointer = x86_64::VirtAddr::new(resume.stack_pointer);
+            true
+        }
+        Err(error) => {
+            error!("Failed to resolve lazy stub #{stub_id} for {process}: {error}");
+            false
+        }
+    }
+}
+
+/// Drop the current process, unloading its object.
+fn exit_current() {
     // Exiting the current process is as simple as dropping it. The process
     // will no longer exist within the run queue, and its allocated frames will
     // be deallocated when the address space is dropped.
 
     kernel_address_space().enter(); // ???: Is this necessary?
     if let Some(mut process) = with_scheduler(|scheduler| scheduler.current.take()) {
-        info!("Exiting {process} with context: {:?}", process.context.take());
+        info!(
+            "Exiting {process} with context: {:?}",
+            process.context.take()
+        );
+
+        // Unload the process's object, unless it's been loaded again since.
+        let name = core::mem::take(&mut process.name);
+        let object = process.object.take();
+        drop(process);
+        if let Some(object) = object
+            && global_loader()
+                .get_object_in(&name, &name)
+                .is_some_and(|loaded| loaded.as_ptr() == Arc::as_ptr(&object))
+        {
+            drop(object);
+            if let Err(error) = global_loader().unload_object_in(&name, &name) {
+                warn!("Failed to unload `{name}`: {error}");
+            }
+        }
     } else {
         unreachable!()
     }
-
-    // Immediately after this block is finished, `Scheduler::schedule_next` is
-    // called to set the next execution context.
-});
+}
 
 /// Defer execution to the scheduler.
 pub fn defer() {
@@ -442,6 +561,10 @@ struct Process {
     context: Option<ExecutionContext>,
     /// Whether the process is allowed to perform I/O instructions.
     allow_io: bool,
+    /// The process's thread-local storage, if any loaded object has some.
+    tls: Option<TlsArea>,
+    /// The object that user processes were loaded from.
+    object: Option<Arc<Mutex<LoadedObject>>>,
 }
 
 impl fmt::Display for Process {
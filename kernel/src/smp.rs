@@ -0,0 +1,358 @@
+//! # Symmetric Multiprocessing (SMP)
+//!
+//! The application processors (APs) are started once at boot, and then only
+//! run the items of [parallel jobs](for_each_parallel) for the bootstrap
+//! processor. They never run processes or take interrupts.
+
+use {
+    crate::{acpi, apic, gdt, idt, memory::kernel_address_space},
+    alloc::vec::Vec,
+    core::{
+        arch::global_asm,
+        hint::spin_loop,
+        mem::offset_of,
+        sync::atomic::{AtomicUsize, Ordering},
+        time::Duration,
+    },
+    log::{info, warn},
+    memory_types::{PAGE_SIZE, Page, PageRange, PageTableFlags, VirtualAddress},
+    spin_mutex::Mutex,
+    x86_64::registers::{
+        control::{Cr0, Cr3, Cr4, Cr4Flags},
+        model_specific::{Efer, EferFlags},
+    },
+};
+
+
+
+/// The identity mapped address that the [trampoline](ap_trampoline_start) is
+/// copied to. APs start in real mode at `vector << 12`, so it has to be below
+/// 1 MiB and page aligned.
+const TRAMPOLINE_ADDR: usize = 0x8000;
+const TRAMPOLINE_VECTOR: u8 = (TRAMPOLINE_ADDR >> 12) as u8;
+/// More APs than this are left halted.
+const MAX_APPLICATION_PROCESSORS: usize = 15;
+/// Whether [`init`] starts the APs at all. They can't recover from faults or
+/// panics (see [`for_each_parallel`]), so they're left halted until starting
+/// them has been validated under QEMU.
+const START_APPLICATION_PROCESSORS: bool = false;
+const AP_STACK_SIZE: usize = 16 * PAGE_SIZE;
+
+/// The number of APs that have finished starting up.
+static ONLINE: AtomicUsize = AtomicUsize::new(0);
+static JOB: Mutex<Option<Job>> = Mutex::new(None);
+
+unsafe extern "C" {
+    static ap_trampoline_start: u8;
+    static ap_trampoline_data: u8;
+    static ap_trampoline_end: u8;
+}
+
+/// The values that the BSP fills in at the end of the trampoline before
+/// starting the APs.
+#[repr(C)]
+struct TrampolineData {
+    cr0: u64,
+    /// Must be below 4 GiB, since it's loaded before long mode is entered.
+    cr3: u64,
+    cr4: u64,
+    efer: u64,
+    /// The address of [`ap_main`].
+    entry: u64,
+    /// The index of the next AP to start, which decides its stack.
+    next_index: u32,
+    stack_count: u32,
+    stack_tops: [u64; MAX_APPLICATION_PROCESSORS],
+}
+
+// NOTE: APs start in real mode, so this goes straight to long mode with the
+//       BSP's page tables and control registers, then calls `ap_main` on the
+//       stack for its index. It's only ever run from its copy at
+//       `TRAMPOLINE_ADDR`, so every address is relative to that.
+global_asm!(
+    ".pushsection .rodata.ap_trampoline, \"a\"",
+    ".global ap_trampoline_start",
+    ".global ap_trampoline_data",
+    ".global ap_trampoline_end",
+    ".code16",
+    "ap_trampoline_start:",
+    "    cli",
+    "    cld",
+    "    xor ax, ax",
+    "    mov ds, ax",
+    "    lgdt [ap_trampoline_gdt_pointer_addr]",
+    "    mov eax, dword ptr [ap_trampoline_data_addr + {cr4}]",
+    "    mov cr4, eax",
+    "    mov eax, dword ptr [ap_trampoline_data_addr + {cr3}]",
+    "    mov cr3, eax",
+    "    mov ecx, 0xC0000080",
+    "    mov eax, dword ptr [ap_trampoline_data_addr + {efer}]",
+    "    xor edx, edx",
+    "    wrmsr",
+    "    mov eax, dword ptr [ap_trampoline_data_addr + {cr0}]",
+    "    mov cr0, eax",
+    // A far jump to the 64-bit code segment, with a 32-bit offset.
+    "    .byte 0x66, 0xEA",
+    "    .long {base} + ap_trampoline_long_mode - ap_trampoline_start",
+    "    .word 0x08",
+    ".code64",
+    "ap_trampoline_long_mode:",
+    "    mov ax, 0x10",
+    "    mov ds, ax",
+    "    mov es, ax",
+    "    mov ss, ax",
+    "    mov fs, ax",
+    "    mov gs, ax",
+    "    mov eax, 1",
+    "    lock xadd dword ptr [ap_trampoline_data_addr + {next_index}], eax",
+    "    cmp eax, dword ptr [ap_trampoline_data_addr + {stack_count}]",
+    "    jae ap_trampoline_halt",
+    "    mov rsp, qword ptr [ap_trampoline_data_addr + {stack_tops} + rax * 8]",
+    "    mov edi, eax",
+    "    mov rax, qword ptr [ap_trampoline_data_addr + {entry}]",
+    "    call rax",
+    "ap_trampoline_halt:",
+    "    cli",
+    "    hlt",
+    "    jmp ap_trampoline_halt",
+    ".align 8",
+    "ap_trampoline_gdt:",
+    "    .quad 0",
+    "    .quad 0x00AF9A000000FFFF",
+    "    .quad 0x00CF92000000FFFF",
+    "ap_trampoline_gdt_pointer:",
+    "    .word 23",
+    "    .long {base} + ap_trampoline_gdt - ap_trampoline_start",
+    ".align 8",
+    "ap_trampoline_data:",
+    "    .zero {data_size}",
+    "ap_trampoline_end:",
+    // Memory operands can't refer to more than one symbol.
+    ".set ap_trampoline_data_addr, {base} + ap_trampoline_data - ap_trampoline_start",
+    ".set ap_trampoline_gdt_pointer_addr, {base} + ap_trampoline_gdt_pointer - ap_trampoline_start",
+    ".popsection",
+    base = const TRAMPOLINE_ADDR,
+    cr0 = const offset_of!(TrampolineData, cr0),
+    cr3 = const offset_of!(TrampolineData, cr3),
+    cr4 = const offset_of!(TrampolineData, cr4),
+    efer = const offset_of!(TrampolineData, efer),
+    entry = const offset_of!(TrampolineData, entry),
+    next_index = const offset_of!(TrampolineData, next_index),
+    stack_count = const offset_of!(TrampolineData, stack_count),
+    stack_tops = const offset_of!(TrampolineData, stack_tops),
+    data_size = const size_of::<TrampolineData>(),
+);
+
+/// Start the application processors, and wait for them to come online.
+pub fn init() {
+    if !START_APPLICATION_PROCESSORS {
+        info!("Application processors are disabled, leaving them halted");
+        return;
+    }
+
+    let count = acpi::application_processor_count().min(MAX_APPLICATION_PROCESSORS);
+    if count == 0 {
+        info!("No application processors to start");
+        return;
+    }
+
+    let (cr3, _) = Cr3::read_raw();
+    let trampoline_page = Page::containing_addr(VirtualAddress::new(TRAMPOLINE_ADDR));
+    if cr3.start_address().as_u64() >= 1 << 32
+        || kernel_address_space()
+            .translate_page(trampoline_page)
+            .is_none_or(|frame| frame.base_addr().to_raw() != TRAMPOLINE_ADDR)
+    {
+        warn!("The AP trampoline can't be reached, skipping SMP initialization...");
+        return;
+    }
+
+    info!("Starting {count} application processor(s)...");
+
+    // The firmware may have left the trampoline's page non-executable.
+    kernel_address_space()
+        .set_flags(
+            PageRange::from_start_len(trampoline_page, 1),
+            PageTableFlags::PRESENT | PageTableFlags::WRITABLE,
+        )
+        .expect("trampoline page should be mapped");
+
+    let data = TrampolineData {
+        cr0: Cr0::read_raw(),
+        cr3: cr3.start_address().as_u64(),
+        // PCIDs can only be enabled in long mode.
+        cr4: (Cr4::read() - Cr4Flags::PCID).bits(),
+        efer: (Efer::read() - EferFlags::LONG_MODE_ACTIVE).bits(),
+        entry: ap_main as *const () as usize as u64,
+        next_index: 0,
+        stack_count: count as u32,
+        stack_tops: core::array::from_fn(|index| {
+            if index >= count {
+                return 0;
+            }
+            let stack = vec![0u8; AP_STACK_SIZE].leak();
+            // The System V ABI requires 16 byte stack alignment.
+            ((stack.as_ptr().addr() + AP_STACK_SIZE) & !0xF) as u64
+        }),
+    };
+    unsafe {
+        let start = &raw const ap_trampoline_start;
+        let len = (&raw const ap_trampoline_end).addr() - start.addr();
+        let data_offset = (&raw const ap_trampoline_data).addr() - start.addr();
+        core::ptr::copy_nonoverlapping(start, TRAMPOLINE_ADDR as *mut u8, len);
+        ((TRAMPOLINE_ADDR + data_offset) as *mut TrampolineData).write(data);
+    }
+
+    // The INIT-SIPI-SIPI sequence, see the Intel SDM (volume 3, section 9.4.4).
+    apic::send_init_ipi_all();
+    busy_wait(Duration::from_millis(10));
+    for _ in 0..2 {
+        apic::send_startup_ipi_all(TRAMPOLINE_VECTOR);
+        busy_wait(Duration::from_micros(200));
+    }
+
+    let start = time::now();
+    while ONLINE.load(Ordering::Acquire) < count && start.elapsed() < Duration::from_millis(100) {
+        spin_loop();
+    }
+    let online = ONLINE.load(Ordering::Acquire);
+    if online < count {
+        warn!("Only {online} of {count} application processor(s) came online");
+    } else {
+        info!("{online} application processor(s) online");
+    }
+}
+
+/// The number of processors that [parallel jobs](for_each_parallel) are
+/// spread over, including the bootstrap processor.
+pub fn processor_count() -> usize {
+    ONLINE.load(Ordering::Acquire) + 1
+}
+
+fn busy_wait(duration: Duration) {
+    let start = time::now();
+    while start.elapsed() < duration {
+        spin_loop();
+    }
+}
+
+extern "sysv64" fn ap_main(_index: u32) -> ! {
+    gdt::init_application_processor();
+    idt::load();
+    ONLINE.fetch_add(1, Ordering::Release);
+
+    loop {
+        if !run_next_item() {
+            spin_loop();
+        }
+    }
+}
+
+
+
+/// Work that's split between every online processor, see
+/// [`for_each_parallel`].
+struct Job {
+    /// Runs the item with the given index. This is only valid until every
+    /// item has finished.
+    run: *const (dyn Fn(usize) + Sync),
+    len: usize,
+    /// The index of the next item to run.
+    next: usize,
+    finished: usize,
+}
+
+// SAFETY: `Job::run` is `Sync`, and only called while it's valid.
+unsafe impl Send for Job {}
+
+/// Call `f` with every index in `0..len`, spreading the calls over every
+/// online processor (including this one), and return once they've all
+/// finished.
+///
+/// NOTE: The APs can't take interrupts (they have no TSS for the interrupt
+///       stacks), so `f` mustn't fault. Any TLB entries they cache for pages
+///       that are later remapped or protected aren't flushed either, which is
+///       fine as long as they never touch those pages again. If `f` panics on
+///       an AP, this never returns.
+pub fn for_each_parallel(len: usize, f: impl Fn(usize) + Sync) {
+    let run: *const (dyn Fn(usize) + Sync + '_) = &f;
+    // SAFETY: Other processors only call `run` between taking an item and
+    //         finishing it, and this doesn't return until every item has
+    //         finished.
+    let run = unsafe {
+        core::mem::transmute::<
+            *const (dyn Fn(usize) + Sync + '_),
+            *const (dyn Fn(usize) + Sync + 'static),
+        >(run)
+    };
+    {
+        let mut job = JOB.lock();
+        assert!(job.is_none(), "parallel jobs can't be nested");
+        *job = Some(Job {
+            run,
+            len,
+            next: 0,
+            finished: 0,
+        });
+    }
+
+    while run_next_item() {}
+    // Wait for the items that other processors are still running.
+    loop {
+        let mut job = JOB.lock();
+        if job.as_ref().is_some_and(|job| job.finished == job.len) {
+            *job = None;
+            break;
+        }
+        drop(job);
+        spin_loop();
+    }
+}
+
+/// Call `f` with each item, in parallel if `parallel` is set (see
+/// [`for_each_parallel`]), returning the results in the same order.
+pub fn map_parallel<T: Sync, R: Send>(
+    items: &[T],
+    parallel: bool,
+    f: impl Fn(&T) -> R + Sync,
+) -> Vec<R> {
+    if !parallel {
+        return items.iter().map(f).collect();
+    }
+
+    let results = items.iter().map(|_| Mutex::new(None)).collect::<Vec<_>>();
+    for_each_parallel(items.len(), |index| {
+        *results[index].lock() = Some(f(&items[index]));
+    });
+
+    results
+        .into_iter()
+        .map(|result| {
+            result
+                .into_inner()
+                .expect("every item should have finished")
+        })
+        .collect()
+}
+
+/// Run the next item of the current job, returning `false` if there isn't
+/// one.
+fn run_next_item() -> bool {
+    let (run, index) = {
+        let mut job = JOB.lock();
+        let Some(job) = job.as_mut().filter(|job| job.next < job.len) else {
+            return false;
+        };
+        job.next += 1;
+        (job.run, job.next - 1)
+    };
+
+    unsafe { (*run)(index) };
+
+    JOB.lock()
+        .as_mut()
+        .expect("job should exist until every item has finished")
+        .finished += 1;
+
+    true
+}
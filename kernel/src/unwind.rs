@@ -0,0 +1,304 @@
+//! # Unwinding Information
+//!
+//! The [loader](crate::loader) registers the `.eh_frame` section of every
+//! object it loads here, so that an unwinder can find the frame description
+//! entry (FDE) for an instruction in any loaded object. This is the same role
+//! that `__register_frame` and `_Unwind_Find_FDE` play for `libgcc`.
+//!
+//! See the [LSB specification] for the format of `.eh_frame`.
+//!
+//! [LSB specification]: https://refspecs.linuxfoundation.org/LSB_5.0.0/LSB-Core-generic/LSB-Core-generic/ehframechpt.html
+
+use {
+    alloc::{collections::btree_map::BTreeMap, vec::Vec},
+    memory_types::VirtualAddress,
+    spin_mutex::Mutex,
+};
+
+
+
+/// Every registered [`FrameInfo`], keyed by the first instruction it covers.
+static FRAMES: Mutex<BTreeMap<VirtualAddress, FrameInfo>> = Mutex::new(BTreeMap::new());
+
+/// The unwinding information for a range of instructions.
+#[derive(Clone, Copy, Debug)]
+pub struct FrameInfo {
+    /// The first instruction covered by this entry.
+    pub start: VirtualAddress,
+    /// The end of the instructions covered by this entry (exclusive).
+    pub end: VirtualAddress,
+    /// The `.eh_frame` section this entry was registered with.
+    pub eh_frame: VirtualAddress,
+    /// The frame description entry (FDE) itself.
+    pub fde: VirtualAddress,
+    /// The language-specific data area (LSDA) for this entry, which lives in
+    /// its object's `.gcc_except_table`.
+    pub lsda: Option<VirtualAddress>,
+}
+
+/// Register every frame description entry in a relocated `.eh_frame` section
+/// that has been loaded at `addr`.
+///
+/// Returns the number of entries that were registered.
+pub fn register_frames(addr: VirtualAddress, eh_frame: &[u8]) -> Result<usize, &'static str> {
+    let frames = parse_frames(addr, eh_frame)?;
+    let count = frames.len();
+
+    let mut registered = FRAMES.lock();
+    for frame in frames {
+        registered.insert(frame.start, frame);
+    }
+
+    Ok(count)
+}
+
+/// Remove every entry that was [registered](register_frames) with the
+/// `.eh_frame` section at `addr`.
+pub fn deregister_frames(addr: VirtualAddress) {
+    FRAMES.lock().retain(|_start, frame| frame.eh_frame != addr);
+}
+
+/// Find the unwinding information for the instruction at `addr`.
+pub fn find_frame(addr: VirtualAddress) -> Option<FrameInfo> {
+    FRAMES
+        .lock()
+        .range(..=addr)
+        .next_back()
+        .map(|(_start, frame)| *frame)
+        .filter(|frame| addr < frame.end)
+}
+
+
+
+// Pointer encodings (`DW_EH_PE_*`).
+const ENCODING_ABSOLUTE: u8 = 0x00;
+const ENCODING_ULEB128: u8 = 0x01;
+const ENCODING_UDATA2: u8 = 0x02;
+const ENCODING_UDATA4: u8 = 0x03;
+const ENCODING_UDATA8: u8 = 0x04;
+const ENCODING_SLEB128: u8 = 0x09;
+const ENCODING_SDATA2: u8 = 0x0a;
+const ENCODING_SDATA4: u8 = 0x0b;
+const ENCODING_SDATA8: u8 = 0x0c;
+const ENCODING_PC_RELATIVE: u8 = 0x10;
+const ENCODING_INDIRECT: u8 = 0x80;
+const ENCODING_OMIT: u8 = 0xff;
+
+/// The parts of a common information entry (CIE) that are needed to parse the
+/// FDEs that refer to it.
+#[derive(Clone, Copy)]
+struct CommonInfo {
+    pointer_encoding: u8,
+    lsda_encoding: u8,
+    has_augmentation_data: bool,
+}
+
+fn parse_frames(addr: VirtualAddress, eh_frame: &[u8]) -> Result<Vec<FrameInfo>, &'static str> {
+    let mut reader = Reader {
+        data: eh_frame,
+        base: addr.to_raw(),
+        offset: 0,
+    };
+    let mut cies = BTreeMap::new();
+    let mut frames = Vec::new();
+
+    while reader.offset + size_of::<u32>() <= eh_frame.len() {
+        let record_offset = reader.offset;
+        let length = match reader.u32()? {
+            // A zero length terminates the section.
+            0 => break,
+            u32::MAX => reader.u64()? as usize,
+            length => length as usize,
+        };
+        let record_end = reader
+            .offset
+            .checked_add(length)
+            .filter(|end| *end <= eh_frame.len())
+            .ok_or("`.eh_frame` record is out of bounds")?;
+
+        let id_offset = reader.offset;
+        let id = reader.u32()? as usize;
+        if id == 0 {
+            cies.insert(record_offset, parse_common_info(&mut reader)?);
+        } else {
+            // The ID of an FDE is the distance back to its CIE.
+            let cie = id_offset
+                .checked_sub(id)
+                .and_then(|offset| cies.get(&offset))
+                .ok_or("`.eh_frame` FDE refers to an unknown CIE")?;
+            let start = reader.pointer(cie.pointer_encoding)?;
+            let len = reader.pointer(cie.pointer_encoding & 0x0f)?;
+
+            let mut lsda = None;
+            if cie.has_augmentation_data {
+                let augmentation_len = reader.uleb128()? as usize;
+                let augmentation_end = reader.offset + augmentation_len;
+                if cie.lsda_encoding != ENCODING_OMIT {
+                    lsda = Some(VirtualAddress::new(reader.pointer(cie.lsda_encoding)?));
+                }
+                reader.offset = augmentation_end;
+            }
+
+            // Entries for code that was discarded have an empty range.
+            if len != 0 {
+                frames.push(FrameInfo {
+                    start: VirtualAddress::new(start),
+                    end: VirtualAddress::new(start + len),
+                    eh_frame: addr,
+                    fde: addr + record_offset,
+                    lsda,
+                });
+            }
+        }
+
+        reader.offset = record_end;
+    }
+
+    Ok(frames)
+}
+
+fn parse_common_info(reader: &mut Reader) -> Result<CommonInfo, &'static str> {
+    let version = reader.u8()?;
+    let augmentation = reader.c_str()?;
+    if augmentation.starts_with(b"eh") {
+        reader.u64()?;
+    }
+    let _code_alignment = reader.uleb128()?;
+    let _data_alignment = reader.sleb128()?;
+    let _return_address_register = if version == 1 {
+        reader.u8()? as u64
+    } else {
+        reader.uleb128()?
+    };
+
+    let mut info = CommonInfo {
+        pointer_encoding: ENCODING_ABSOLUTE,
+        lsda_encoding: ENCODING_OMIT,
+        has_augmentation_data: false,
+    };
+    if augmentation.first() == Some(&b'z') {
+        info.has_augmentation_data = true;
+        let augmentation_len = reader.uleb128()? as usize;
+        let augmentation_end = reader.offset + augmentation_len;
+        for code in &augmentation[1..] {
+            match code {
+                b'R' => info.pointer_encoding = reader.u8()?,
+                b'L' => info.lsda_encoding = reader.u8()?,
+                b'P' => {
+                    // Only the personality routine's pointer is indirect, and it isn't needed.
+                    let encoding = reader.u8()?;
+                    reader.pointer(encoding & !ENCODING_INDIRECT)?;
+                }
+                // The rest can be skipped with the augmentation length.
+                _ => break,
+            }
+        }
+        reader.offset = augmentation_end;
+    }
+
+    Ok(info)
+}
+
+struct Reader<'a> {
+    data: &'a [u8],
+    /// The address of the start of `data`, for PC-relative pointers.
+    base: usize,
+    offset: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn bytes<const N: usize>(&mut self) -> Result<[u8; N], &'static str> {
+        let bytes = self
+            .data
+            .get(self.offset..self.offset + N)
+            .ok_or("unexpected end of `.eh_frame`")?;
+        self.offset += N;
+
+        Ok(bytes.try_into().unwrap())
+    }
+
+    fn u8(&mut self) -> Result<u8, &'static str> {
+        self.bytes().map(u8::from_ne_bytes)
+    }
+
+    fn u16(&mut self) -> Result<u16, &'static str> {
+        self.bytes().map(u16::from_ne_bytes)
+    }
+
+    fn u32(&mut self) -> Result<u32, &'static str> {
+        self.bytes().map(u32::from_ne_bytes)
+    }
+
+    fn u64(&mut self) -> Result<u64, &'static str> {
+        self.bytes().map(u64::from_ne_bytes)
+    }
+
+    fn uleb128(&mut self) -> Result<u64, &'static str> {
+        let mut value = 0;
+        let mut shift = 0;
+        loop {
+            let byte = self.u8()?;
+            if shift < u64::BITS {
+                value |= ((byte & 0x7f) as u64) << shift;
+            }
+            shift += 7;
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+    }
+
+    fn sleb128(&mut self) -> Result<i64, &'static str> {
+        let mut value = 0;
+        let mut shift = 0;
+        loop {
+            let byte = self.u8()?;
+            if shift < i64::BITS {
+                value |= ((byte & 0x7f) as i64) << shift;
+            }
+            shift += 7;
+            if byte & 0x80 == 0 {
+                if shift < i64::BITS && byte & 0x40 != 0 {
+                    value |= -1 << shift;
+                }
+                return Ok(value);
+            }
+        }
+    }
+
+    fn c_str(&mut self) -> Result<&'a [u8], &'static str> {
+        let rest = &self.data[self.offset..];
+        let len = rest
+            .iter()
+            .position(|byte| *byte == 0)
+            .ok_or("unterminated string in `.eh_frame`")?;
+        self.offset += len + 1;
+
+        Ok(&rest[..len])
+    }
+
+    /// Read a pointer with the given `DW_EH_PE_*` encoding.
+    fn pointer(&mut self, encoding: u8) -> Result<usize, &'static str> {
+        if encoding & ENCODING_INDIRECT != 0 {
+            return Err("unsupported pointer encoding in `.eh_frame`");
+        }
+
+        let addr = self.base + self.offset;
+        let value = match encoding & 0x0f {
+            ENCODING_ABSOLUTE | ENCODING_UDATA8 | ENCODING_SDATA8 => self.u64()? as usize,
+            ENCODING_ULEB128 => self.uleb128()? as usize,
+            ENCODING_UDATA2 => self.u16()? as usize,
+            ENCODING_UDATA4 => self.u32()? as usize,
+            ENCODING_SLEB128 => self.sleb128()? as usize,
+            ENCODING_SDATA2 => self.u16()? as i16 as usize,
+            ENCODING_SDATA4 => self.u32()? as i32 as usize,
+            _ => return Err("unsupported pointer encoding in `.eh_frame`"),
+        };
+
+        match encoding & 0x70 {
+            0 => Ok(value),
+            ENCODING_PC_RELATIVE => Ok(addr.wrapping_add(value)),
+            _ => Err("unsupported pointer encoding in `.eh_frame`"),
+        }
+    }
+}
@@ -314,6 +314,7 @@ impl VfatFileSystem {
                         index: entry.cluster_index(),
                         name: file_name,
                         size: entry.size(),
+                        modified: entry.modified(),
                     };
 
                     debug!("/{} ({} bytes) @ {}", entry.name, entry.size, entry.index);
@@ -355,6 +356,10 @@ impl FileSystem for VfatFileSystem {
             dir_entry.size,
         )
     }
+
+    fn entry(&self, path: &str) -> Result<DirectoryEntry, &'static str> {
+        self.cache.get(path).cloned().ok_or("no such file")
+    }
 }
 
 #[repr(C)]
@@ -415,6 +420,33 @@ impl VfatDirectoryEntry {
         u32::from_le_bytes([bytes[28], bytes[29], bytes[30], bytes[31]]) as usize
     }
 
+    /// The last modification time, in seconds since the Unix epoch.
+    ///
+    /// FAT timestamps have no time zone and a 2 second resolution.
+    // https://en.wikipedia.org/wiki/Design_of_the_FAT_file_system#Directory_entry
+    pub const fn modified(&self) -> u64 {
+        let bytes = self.raw();
+        let time = u16::from_le_bytes([bytes[22], bytes[23]]) as u64;
+        let date = u16::from_le_bytes([bytes[24], bytes[25]]) as u64;
+
+        let year = 1980 + (date >> 9);
+        let month = (date >> 5) & 0xf;
+        let day = date & 0x1f;
+        let seconds = (time >> 11) * 3600 + ((time >> 5) & 0x3f) * 60 + (time & 0x1f) * 2;
+
+        // Count the days since 1970-01-01, with years starting in March so that
+        // leap days come last.
+        let (year, month) = if month <= 2 {
+            (year - 1, month + 9)
+        } else {
+            (year, month - 3)
+        };
+        let day_of_year = (153 * month + 2) / 5 + day.saturating_sub(1);
+        let days = year * 365 + year / 4 - year / 100 + year / 400 + day_of_year - 719_468;
+
+        days * 86_400 + seconds
+    }
+
     // https://wiki.osdev.org/FAT#Long_File_Names
     // https://en.wikipedia.org/wiki/Design_of_the_FAT_file_system#VFAT_long_file_names
     pub fn long_file_name(&self) -> Option<String> {
@@ -0,0 +1,160 @@
+//! # Screen Capture
+//!
+//! Screenshots and recordings of everything the shell draws, for debugging
+//! rendering issues on real hardware. Each output's framebuffer is read back
+//! (see `glReadPixels`) after it's drawn, and copied into an image of the whole
+//! desktop, which is written as a PNG once every output has been drawn.
+//!
+//! A recording writes every frame to its own numbered file in a directory
+//! (`frame-00000.png`, `frame-00001.png`, and so on), which can be turned into
+//! a video with most video tools.
+
+use std::path::PathBuf;
+
+use {
+    abi::{Rgba, raster::Image},
+    egui::Rect,
+    log::{info, warn},
+};
+
+use crate::output::Output;
+
+
+
+#[derive(Default)]
+pub struct Capture {
+    /// Where to write the next frame, see [`Capture::screenshot`].
+    screenshot: Option<PathBuf>,
+    recording: Option<Recording>,
+    /// The frame being captured, which is finished once every output has been
+    /// drawn.
+    frame: Option<Frame>,
+}
+
+struct Recording {
+    dir: PathBuf,
+    /// The number of the next frame.
+    frame: u32,
+}
+
+struct Frame {
+    image: Image,
+    /// Where the image's top left corner is on the desktop.
+    origin: egui::Pos2,
+    /// The outputs that have been copied into the image.
+    captured: Vec<String>,
+}
+
+impl Capture {
+    /// Write the next frame to a file.
+    pub fn screenshot(&mut self, path: PathBuf) {
+        self.screenshot = Some(path);
+    }
+
+    /// Write every frame to a directory until [`Capture::stop_recording`] is
+    /// called, creating the directory if it doesn't exist.
+    pub fn start_recording(&mut self, dir: PathBuf) -> std::io::Result<()> {
+        std::fs::create_dir_all(&dir)?;
+        self.recording = Some(Recording { dir, frame: 0 });
+        Ok(())
+    }
+
+    /// Stop recording, returning the number of frames recorded.
+    pub fn stop_recording(&mut self) -> Option<u32> {
+        let recording = self.recording.take()?;
+        if self.screenshot.is_none() {
+            self.frame = None;
+        }
+
+        Some(recording.frame)
+    }
+
+    pub fn is_recording(&self) -> bool {
+        self.recording.is_some()
+    }
+
+    /// Whether an output's framebuffer should be read back after it's drawn.
+    pub fn wants(&self, output: &str) -> bool {
+        (self.screenshot.is_some() || self.recording.is_some())
+            && !self
+                .frame
+                .as_ref()
+                .is_some_and(|frame| frame.captured.iter().any(|name| name == output))
+    }
+
+    /// Copy an output's framebuffer into the frame being captured.
+    ///
+    /// The pixels are RGBA, with rows from the bottom up (as OpenGL reads
+    /// them).
+    pub fn add_output(&mut self, desktop_rect: Rect, output: &Output, pixels: &[u8]) {
+        let frame = self.frame.get_or_insert_with(|| Frame {
+            image: Image::new(
+                desktop_rect.width() as u32,
+                desktop_rect.height() as u32,
+                Rgba::BLACK,
+            ),
+            origin: desktop_rect.min,
+            captured: Vec::new(),
+        });
+
+        let width = output.width() as usize;
+        let x = (output.position.x - frame.origin.x).max(0.0) as usize;
+        let y = (output.position.y - frame.origin.y).max(0.0) as usize;
+        let image_width = frame.image.width() as usize;
+        let image_height = frame.image.height() as usize;
+        let copied_width = width.min(image_width.saturating_sub(x));
+        let image = frame.image.pixels_mut();
+        for (row, src) in pixels.chunks_exact(width * 4).rev().enumerate() {
+            if y + row >= image_height {
+                break;
+            }
+            let start = (y + row) * image_width + x;
+            for (dst, src) in image[start..start + copied_width]
+                .iter_mut()
+                .zip(src.chunks_exact(4))
+            {
+                // The framebuffer's alpha isn't shown, so it's meaningless.
+                *dst = Rgba::rgb(src[0], src[1], src[2]);
+            }
+        }
+        frame.captured.push(output.name.clone());
+    }
+
+    /// Write the frame being captured once every output has been copied into
+    /// it.
+    pub fn finish_frame(&mut self, outputs: &[Output]) {
+        let complete = self.frame.as_ref().is_some_and(|frame| {
+            outputs
+                .iter()
+                .all(|output| frame.captured.contains(&output.name))
+        });
+        if !complete {
+            return;
+        }
+        let image = self.frame.take().unwrap().image;
+
+        if let Some(path) = self.screenshot.take() {
+            info!("Saving a screenshot to '{}'", path.display());
+            write_png(path, image.clone());
+        }
+        if let Some(recording) = &mut self.recording {
+            write_png(
+                recording
+                    .dir
+                    .join(format!("frame-{:05}.png", recording.frame)),
+                image,
+            );
+            recording.frame += 1;
+        }
+    }
+}
+
+/// Encode and write an image in the background, so that capturing doesn't
+/// hold up the next frame.
+fn write_png(path: PathBuf, image: Image) {
+    std::thread::spawn(move || {
+        if let Err(error) = std::fs::write(&path, image.encode_png()) {
+            warn!("Failed to write '{}': {error}", path.display());
+        }
+    });
+}
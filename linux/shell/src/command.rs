@@ -0,0 +1,792 @@
+//! # Command Line
+//!
+//! The commands that can be run from the shell's command line, and the host
+//! side of [`abi::command`].
+
+use std::{collections::BTreeMap, ffi::CString, sync::Mutex};
+
+use {
+    abi::{
+        Capability,
+        command::{
+            ArgKind, ArgSpec, Args, Command, CommandSpec, RegisterCommandError, is_valid_name,
+            split_words,
+        },
+    },
+    drm::control::Device as _,
+    log::{LevelFilter, info},
+};
+
+use crate::{
+    Shell,
+    system::{self, Caller},
+};
+
+
+
+/// Commands registered by applications, see [`abi::command::register`].
+static REGISTRY: Mutex<BTreeMap<&'static str, Entry>> = Mutex::new(BTreeMap::new());
+
+const BUILTINS: &[Entry] = &[
+    Entry {
+        spec: CommandSpec {
+            name: "cd",
+            summary: "Change the current directory",
+            args: &[ArgSpec::required("path", ArgKind::Path, "")],
+            complete: Some(complete_directories),
+        },
+        handler: Handler::Builtin(cd),
+    },
+    Entry {
+        spec: CommandSpec {
+            name: "env",
+            summary: "Print an environment variable, or all of them",
+            args: &[ArgSpec::optional("name", ArgKind::Text, "")],
+            complete: Some(complete_env_vars),
+        },
+        handler: Handler::Builtin(env),
+    },
+    Entry {
+        spec: CommandSpec {
+            name: "browser",
+            summary: "Set the program used to open URLs",
+            args: &[ArgSpec::optional(
+                "program",
+                ArgKind::Path,
+                "Leave empty to stop opening URLs",
+            )],
+            complete: Some(complete_paths),
+        },
+        handler: Handler::Builtin(browser),
+    },
+    Entry {
+        spec: CommandSpec {
+            name: "watch",
+            summary: "Recompile a program whenever its source file changes",
+            args: &[ArgSpec::optional(
+                "source",
+                ArgKind::Path,
+                "Leave empty to list the watched programs",
+            )],
+            complete: Some(complete_paths),
+        },
+        handler: Handler::Builtin(watch),
+    },
+    Entry {
+        spec: CommandSpec {
+            name: "output",
+            summary: "List the outputs, or an output's modes, or set its mode or position",
+            args: &[
+                ArgSpec::optional("name", ArgKind::Text, "Leave empty to list the outputs"),
+                ArgSpec::optional(
+                    "setting",
+                    ArgKind::Text,
+                    "`mode` or `position`, leave empty to list the modes",
+                ),
+                ArgSpec::optional("value", ArgKind::Text, "WIDTHxHEIGHT[@REFRESH] or X,Y"),
+            ],
+            complete: None,
+        },
+        handler: Handler::Builtin(output),
+    },
+    Entry {
+        spec: CommandSpec {
+            name: "input",
+            summary: "List the input settings, or change one of them",
+            args: &[
+                ArgSpec::optional("setting", ArgKind::Text, "Leave empty to list the settings"),
+                ArgSpec::optional("value", ArgKind::Text, ""),
+            ],
+            complete: None,
+        },
+        handler: Handler::Builtin(input),
+    },
+    Entry {
+        spec: CommandSpec {
+            name: "layout",
+            summary: "List the keyboard layouts, or switch to one of them",
+            args: &[ArgSpec::optional(
+                "layout",
+                ArgKind::Text,
+                "A built-in layout or a keymap file, leave empty to list the layouts",
+            )],
+            complete: None,
+        },
+        handler: Handler::Builtin(layout),
+    },
+    Entry {
+        spec: CommandSpec {
+            name: "screenshot",
+            summary: "Save the next frame drawn as a PNG",
+            args: &[ArgSpec::optional(
+                "path",
+                ArgKind::Path,
+                "Leave empty to save it as `screenshot-<time>.png` in the current directory",
+            )],
+            complete: Some(complete_paths),
+        },
+        handler: Handler::Builtin(screenshot),
+    },
+    Entry {
+        spec: CommandSpec {
+            name: "record",
+            summary: "Save every frame drawn as a PNG in a directory, or stop recording",
+            args: &[ArgSpec::required(
+                "directory",
+                ArgKind::Path,
+                "`stop` to stop recording",
+            )],
+            complete: Some(complete_directories),
+        },
+        handler: Handler::Builtin(record),
+    },
+    Entry {
+        spec: CommandSpec {
+            name: "run",
+            summary: "Show an application in the central panel, or stop showing it",
+            args: &[ArgSpec::required(
+                "app",
+                ArgKind::Path,
+                "An application's shared object, or `close`",
+            )],
+            complete: Some(complete_paths),
+        },
+        handler: Handler::Builtin(run_app),
+    },
+    Entry {
+        spec: CommandSpec {
+            name: "preview",
+            summary: "Draw a saved render over the shell, or stop drawing it",
+            args: &[ArgSpec::required(
+                "render",
+                ArgKind::Path,
+                "A render stream saved by `--headless --binary`, or `close`",
+            )],
+            complete: Some(complete_paths),
+        },
+        handler: Handler::Builtin(preview),
+    },
+    Entry {
+        spec: CommandSpec {
+            name: "perf",
+            summary: "Print frame timing statistics, or show them on screen",
+            args: &[ArgSpec::optional(
+                "action",
+                ArgKind::Text,
+                "`hud` to show or hide them, `reset` to clear them, leave empty to print them",
+            )],
+            complete: None,
+        },
+        handler: Handler::Builtin(perf),
+    },
+    Entry {
+        spec: CommandSpec {
+            name: "log",
+            summary: "Print the log levels, or change them, or show the log viewer",
+            args: &[
+                ArgSpec::optional(
+                    "action",
+                    ArgKind::Text,
+                    "`level`, `view`, `print` or `clear`, leave empty to print the levels",
+                ),
+                ArgSpec::optional(
+                    "level",
+                    ArgKind::Text,
+                    "LEVEL or TARGET=LEVEL, where LEVEL can be `default` for a target",
+                ),
+            ],
+            complete: None,
+        },
+        handler: Handler::Builtin(log),
+    },
+    Entry {
+        spec: CommandSpec {
+            name: "sym",
+            summary: "Print the address of one of the shell's symbols",
+            args: &[ArgSpec::required("symbol", ArgKind::Text, "")],
+            complete: None,
+        },
+        handler: Handler::Builtin(sym),
+    },
+    Entry {
+        spec: CommandSpec {
+            name: "help",
+            summary: "List the available commands, or describe one of them",
+            args: &[ArgSpec::optional("command", ArgKind::Text, "")],
+            complete: Some(complete_command_names),
+        },
+        handler: Handler::Builtin(help),
+    },
+    Entry {
+        spec: CommandSpec {
+            name: "exit",
+            summary: "Exit the shell",
+            args: &[],
+            complete: None,
+        },
+        handler: Handler::Builtin(exit),
+    },
+];
+
+#[derive(Clone, Copy)]
+struct Entry {
+    spec: CommandSpec,
+    handler: Handler,
+}
+
+#[derive(Clone, Copy)]
+enum Handler {
+    Builtin(fn(&mut Shell, &Args) -> Result<(), String>),
+    App {
+        /// Who registered the command, which it's run on behalf of.
+        caller: Caller,
+        run: fn(&Args) -> Result<String, String>,
+    },
+}
+
+fn find(name: &str) -> Option<Entry> {
+    BUILTINS
+        .iter()
+        .find(|entry| entry.spec.name == name)
+        .copied()
+        .or_else(|| REGISTRY.lock().unwrap().get(name).copied())
+}
+
+fn command_names() -> Vec<&'static str> {
+    BUILTINS
+        .iter()
+        .map(|entry| entry.spec.name)
+        .chain(REGISTRY.lock().unwrap().keys().copied())
+        .collect()
+}
+
+/// Run a line entered into the command line. Lines that don't start with a
+/// known command are run as programs.
+pub fn run(shell: &mut Shell, line: &str) {
+    let words = split_words(line);
+    let Some((name, arg_words)) = words.split_first() else {
+        return;
+    };
+
+    let Some(entry) = find(name) else {
+        spawn(name, arg_words);
+        return;
+    };
+    let args = match entry.spec.parse(arg_words) {
+        Ok(args) => args,
+        Err(error) => {
+            println!("{name}: {error}\nusage: {}", entry.spec.usage());
+            return;
+        }
+    };
+
+    match entry.handler {
+        Handler::Builtin(run) => {
+            if let Err(error) = system::with_caller(Caller::Shell, || run(shell, &args)) {
+                println!("{name}: {error}");
+            }
+        }
+        Handler::App { caller, run } => {
+            let result = system::with_caller(caller, || run(&args));
+
+            match result {
+                Ok(output) if output.is_empty() => {}
+                Ok(output) => println!("{output}"),
+                Err(error) => println!("{name}: {error}"),
+            }
+        }
+    }
+}
+
+fn spawn(program: &str, args: &[&str]) {
+    match std::process::Command::new(program).args(args).output() {
+        Ok(output) => {
+            println!("{}", String::from_utf8_lossy(&output.stdout));
+            println!("{}", String::from_utf8_lossy(&output.stderr));
+        }
+        Err(error) => {
+            println!("{error}");
+        }
+    }
+}
+
+/// Get the possible completions of the last word in the line.
+pub fn complete(line: &str) -> Vec<String> {
+    let mut words = split_words(line);
+    // A trailing space starts a new word.
+    if line.is_empty() || line.ends_with(char::is_whitespace) {
+        words.push("");
+    }
+    let Some((partial, previous)) = words.split_last() else {
+        return Vec::new();
+    };
+
+    let mut candidates = match previous.first() {
+        None => complete_command_names(0, partial),
+        Some(name) => match find(name) {
+            Some(entry) => entry
+                .spec
+                .complete
+                .map_or_else(Vec::new, |complete| complete(previous.len() - 1, partial)),
+            None => complete_paths(0, partial),
+        },
+    };
+    candidates.sort();
+    candidates.dedup();
+
+    candidates
+}
+
+/// Complete the last word in the line as far as possible, printing the
+/// candidates if there's more than one.
+pub fn complete_line(line: &mut String) {
+    let candidates = complete(line);
+    let partial_len = line.rsplit(char::is_whitespace).next().unwrap_or("").len();
+    let word_start = line.len() - partial_len;
+
+    match candidates.as_slice() {
+        [] => {}
+        [candidate] => {
+            line.replace_range(word_start.., candidate);
+            if !candidate.ends_with('/') {
+                line.push(' ');
+            }
+        }
+        [first, rest @ ..] => {
+            let prefix_len = rest.iter().fold(first.len(), |len, candidate| {
+                let common_len = first
+                    .chars()
+                    .zip(candidate.chars())
+                    .take_while(|(a, b)| a == b)
+                    .map(|(ch, _)| ch.len_utf8())
+                    .sum::<usize>();
+                len.min(common_len)
+            });
+            if prefix_len > partial_len {
+                line.replace_range(word_start.., &first[..prefix_len]);
+            }
+            println!("{}", candidates.join("  "));
+        }
+    }
+}
+
+fn complete_command_names(_arg_index: usize, partial: &str) -> Vec<String> {
+    command_names()
+        .into_iter()
+        .filter(|name| name.starts_with(partial))
+        .map(String::from)
+        .collect()
+}
+
+fn complete_env_vars(_arg_index: usize, partial: &str) -> Vec<String> {
+    std::env::vars()
+        .map(|(name, _)| name)
+        .filter(|name| name.starts_with(partial))
+        .collect()
+}
+
+fn complete_paths(_arg_index: usize, partial: &str) -> Vec<String> {
+    path_candidates(partial, false)
+}
+
+fn complete_directories(_arg_index: usize, partial: &str) -> Vec<String> {
+    path_candidates(partial, true)
+}
+
+fn path_candidates(partial: &str, directories_only: bool) -> Vec<String> {
+    let (dir, file_prefix) = match partial.rsplit_once('/') {
+        Some((dir, file_prefix)) => (format!("{dir}/"), file_prefix),
+        None => (String::new(), partial),
+    };
+    let Ok(entries) = std::fs::read_dir(if dir.is_empty() { "." } else { &dir }) else {
+        return Vec::new();
+    };
+
+    entries
+        .filter_map(|entry| {
+            let entry = entry.ok()?;
+            let name = entry.file_name().into_string().ok()?;
+            let is_dir = entry.file_type().is_ok_and(|file_type| file_type.is_dir());
+            (name.starts_with(file_prefix) && (is_dir || !directories_only)).then(|| {
+                if is_dir {
+                    format!("{dir}{name}/")
+                } else {
+                    format!("{dir}{name}")
+                }
+            })
+        })
+        .collect()
+}
+
+
+
+fn cd(shell: &mut Shell, args: &Args) -> Result<(), String> {
+    std::env::set_current_dir(args.text("path").unwrap_or_default())
+        .map_err(|error| error.to_string())?;
+    shell.current_dir = std::env::current_dir()
+        .unwrap()
+        .to_str()
+        .unwrap()
+        .to_string();
+
+    Ok(())
+}
+
+fn env(_shell: &mut Shell, args: &Args) -> Result<(), String> {
+    match args.text("name") {
+        Some(name) => {
+            println!(
+                "{}",
+                std::env::var(name).map_err(|error| error.to_string())?
+            );
+        }
+        None => {
+            for (name, value) in std::env::vars() {
+                println!("{name} = {value}");
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn browser(_shell: &mut Shell, args: &Args) -> Result<(), String> {
+    system::set_url_handler(args.text("program").map(String::from));
+
+    Ok(())
+}
+
+fn watch(shell: &mut Shell, args: &Args) -> Result<(), String> {
+    let Some(source) = args.text("source") else {
+        for (source, output) in &shell.watched_programs {
+            println!("    {} -> {output}", source.to_string_lossy());
+        }
+        return Ok(());
+    };
+
+    let source = std::fs::canonicalize(source).map_err(|error| error.to_string())?;
+    let output = source.with_extension("so");
+    let source = CString::new(source.into_os_string().into_encoded_bytes())
+        .map_err(|error| error.to_string())?;
+    shell
+        .watcher
+        .watch_file(source.as_c_str())
+        .map_err(|error| error.description().to_string())?;
+
+    info!("Watching '{}' for changes...", source.to_string_lossy());
+    shell
+        .watched_programs
+        .insert(source, output.to_string_lossy().into_owned());
+
+    Ok(())
+}
+
+fn output(shell: &mut Shell, args: &Args) -> Result<(), String> {
+    let Some(name) = args.text("name") else {
+        for output in &shell.outputs {
+            let rect = output.rect();
+            println!(
+                "    {}: {}x{}@{} at {},{}",
+                output.name,
+                output.width(),
+                output.height(),
+                output.mode.vrefresh(),
+                rect.min.x,
+                rect.min.y,
+            );
+        }
+        return Ok(());
+    };
+    let output = shell
+        .outputs
+        .iter()
+        .find(|output| output.name == name)
+        .ok_or_else(|| format!("unknown output '{name}'"))?;
+
+    match (args.text("setting"), args.text("value")) {
+        (None, _) => {
+            let conn_info = shell
+                .gpu
+                .get_connector(output.conn, false)
+                .map_err(|error| error.to_string())?;
+            for mode in conn_info.modes() {
+                let current = if *mode == output.mode {
+                    " (current)"
+                } else {
+                    ""
+                };
+                println!(
+                    "    {}x{}@{}{current}",
+                    mode.size().0,
+                    mode.size().1,
+                    mode.vrefresh(),
+                );
+            }
+            return Ok(());
+        }
+        (Some("mode"), Some(value)) => {
+            crate::output::ModeRequest::parse(value)
+                .ok_or_else(|| format!("invalid mode '{value}'"))?;
+        }
+        (Some("position"), Some(value)) => {
+            let valid = value.split_once(',').is_some_and(|(x, y)| {
+                x.trim().parse::<f32>().is_ok() && y.trim().parse::<f32>().is_ok()
+            });
+            if !valid {
+                return Err(format!("invalid position '{value}'"));
+            }
+        }
+        (Some(setting @ ("mode" | "position")), None) => {
+            return Err(format!("missing value for '{setting}'"));
+        }
+        (Some(setting), _) => return Err(format!("unknown setting '{setting}'")),
+    }
+
+    let setting = args.text("setting").unwrap_or_default();
+    let value = args.text("value").unwrap_or_default();
+    system::set_shell_setting(&format!("output.{name}.{setting}"), value);
+    shell.sync_outputs().map_err(|error| error.to_string())
+}
+
+fn input(shell: &mut Shell, args: &Args) -> Result<(), String> {
+    let mut config = shell.input_config.lock().unwrap();
+    let Some(setting) = args.text("setting") else {
+        let crate::input::InputConfig {
+            acceleration,
+            speed,
+            natural_scrolling,
+            tap_to_click,
+            repeat_delay,
+            repeat_rate,
+        } = *config;
+        println!("    acceleration: {acceleration:?}");
+        println!("    speed: {speed}");
+        println!("    natural_scrolling: {natural_scrolling}");
+        println!("    tap_to_click: {tap_to_click}");
+        println!("    repeat_delay: {}", repeat_delay.as_millis());
+        println!("    repeat_rate: {repeat_rate}");
+        return Ok(());
+    };
+    let Some(value) = args.text("value") else {
+        return match crate::input::InputConfig::SETTINGS
+            .iter()
+            .find(|(name, _)| *name == setting)
+        {
+            Some((_, values)) => Err(format!("missing value for '{setting}', expected {values}")),
+            None => Err(format!("unknown setting '{setting}'")),
+        };
+    };
+
+    config.set(setting, value)?;
+    system::set_shell_setting(&format!("input.{setting}"), value);
+    Ok(())
+}
+
+fn layout(shell: &mut Shell, args: &Args) -> Result<(), String> {
+    let Some(name) = args.text("layout") else {
+        let current = shell.keymap.lock().unwrap();
+        for (layout, source) in crate::keymap::LAYOUTS {
+            // Each layout starts with a comment describing it.
+            let description = source.lines().next().unwrap_or_default();
+            let marker = if *layout == current.name() { "*" } else { " " };
+            println!(
+                "  {marker} {layout:<4} {}",
+                description.trim_start_matches("# ")
+            );
+        }
+        if current.name().contains('/') {
+            println!("  * {}", current.name());
+        }
+        return Ok(());
+    };
+
+    *shell.keymap.lock().unwrap() = crate::keymap::Keymap::named(name)?;
+    system::set_shell_setting("keyboard.layout", name);
+    Ok(())
+}
+
+fn screenshot(shell: &mut Shell, args: &Args) -> Result<(), String> {
+    let path = match args.text("path") {
+        Some(path) => path.to_string(),
+        None => {
+            let time = std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .unwrap_or_default();
+            format!("screenshot-{}.png", time.as_secs())
+        }
+    };
+    shell.capture.screenshot(path.into());
+    Ok(())
+}
+
+fn record(shell: &mut Shell, args: &Args) -> Result<(), String> {
+    let dir = args.text("directory").unwrap_or_default();
+    if dir == "stop" {
+        let frames = shell
+            .capture
+            .stop_recording()
+            .ok_or_else(|| "not recording".to_string())?;
+        println!("Recorded {frames} frames");
+        return Ok(());
+    }
+    if shell.capture.is_recording() {
+        return Err("already recording, run `record stop` first".to_string());
+    }
+
+    shell
+        .capture
+        .start_recording(dir.into())
+        .map_err(|error| format!("'{dir}': {error}"))
+}
+
+fn run_app(shell: &mut Shell, args: &Args) -> Result<(), String> {
+    let path = args.text("app").unwrap_or_default();
+    // The old application is closed even if the new one fails to load.
+    shell.program = None;
+    if path == "close" {
+        return Ok(());
+    }
+
+    let program = crate::program::Program::load(path.as_ref(), &shell.config)
+        .map_err(|error| format!("'{path}': {error}"))?;
+    shell.program = Some(program);
+    Ok(())
+}
+
+fn preview(shell: &mut Shell, args: &Args) -> Result<(), String> {
+    let path = args.text("render").unwrap_or_default();
+    if path == "close" {
+        shell.preview = None;
+        return Ok(());
+    }
+
+    let bytes = std::fs::read(path).map_err(|error| format!("'{path}': {error}"))?;
+    let render = abi::Render::decode(&bytes).map_err(|error| format!("'{path}': {error}"))?;
+    shell.preview = Some(render);
+    Ok(())
+}
+
+fn perf(shell: &mut Shell, args: &Args) -> Result<(), String> {
+    match args.text("action") {
+        None => print!("{}", shell.perf.report()),
+        Some("hud") => {
+            let hud = !shell.perf.hud();
+            shell.perf.set_hud(hud);
+        }
+        Some("reset") => shell.perf.reset(),
+        Some(action) => return Err(format!("unknown action '{action}'")),
+    }
+
+    Ok(())
+}
+
+fn log(shell: &mut Shell, args: &Args) -> Result<(), String> {
+    match args.text("action") {
+        None => {
+            let filters = crate::log::filters();
+            println!("default   {}", filters.default_level);
+            for (target, level) in &filters.targets {
+                println!("{target:<9} {level}");
+            }
+            let printing = if crate::log::printing() { "on" } else { "off" };
+            println!("printing  {printing}");
+        }
+        Some("level") => {
+            let directive = args
+                .text("level")
+                .ok_or_else(|| "expected a level".to_string())?;
+            let parse_level = |level: &str| {
+                level
+                    .parse::<LevelFilter>()
+                    .map_err(|_| format!("invalid level '{level}'"))
+            };
+            match directive.split_once('=') {
+                Some((target, "default")) => {
+                    crate::log::update_filters(|filters| filters.set(target, None));
+                }
+                Some((target, level)) => {
+                    let level = parse_level(level)?;
+                    crate::log::update_filters(|filters| filters.set(target, Some(level)));
+                }
+                None => {
+                    let level = parse_level(directive)?;
+                    crate::log::update_filters(|filters| filters.default_level = level);
+                }
+            }
+        }
+        Some("view") => shell.log_viewer.open = !shell.log_viewer.open,
+        Some("print") => crate::log::set_printing(!crate::log::printing()),
+        Some("clear") => crate::log::clear(),
+        Some(action) => return Err(format!("unknown action '{action}'")),
+    }
+
+    Ok(())
+}
+
+fn sym(shell: &mut Shell, args: &Args) -> Result<(), String> {
+    let name = args.text("symbol").unwrap_or_default();
+    // The type doesn't matter in this case (we're just printing debug info).
+    match shell.this_obj.get_untyped(name) {
+        Some(ptr) => {
+            println!("{ptr:?}");
+            Ok(())
+        }
+        None => Err(format!("symbol '{name}' not found")),
+    }
+}
+
+fn help(_shell: &mut Shell, args: &Args) -> Result<(), String> {
+    if let Some(name) = args.text("command") {
+        let entry = find(name).ok_or_else(|| format!("unknown command '{name}'"))?;
+        println!("{}", entry.spec.help());
+        return Ok(());
+    }
+
+    let entries = BUILTINS
+        .iter()
+        .copied()
+        .chain(REGISTRY.lock().unwrap().values().copied())
+        .collect::<Vec<_>>();
+    for entry in entries {
+        println!("    {:<24} {}", entry.spec.usage(), entry.spec.summary);
+    }
+    println!("Anything else is run as a program.");
+
+    Ok(())
+}
+
+fn exit(shell: &mut Shell, _args: &Args) -> Result<(), String> {
+    shell.session.restore_vt();
+    std::process::exit(0);
+}
+
+
+
+abi::declare! {
+    mod command {
+        fn register(command: &'static Command) -> Result<(), RegisterCommandError> {
+            let Some(caller) = system::current_caller()
+                .filter(|caller| caller.has_capability(Capability::RegisterCommands))
+            else {
+                return Err(RegisterCommandError::NotPermitted);
+            };
+            let name = command.spec.name;
+            if !is_valid_name(name) {
+                return Err(RegisterCommandError::InvalidName);
+            }
+
+            let mut registry = REGISTRY.lock().unwrap();
+            if registry.contains_key(name) || BUILTINS.iter().any(|entry| entry.spec.name == name) {
+                return Err(RegisterCommandError::AlreadyExists);
+            }
+
+            registry.insert(name, Entry {
+                spec: command.spec,
+                handler: Handler::App { caller, run: command.run },
+            });
+            info!("Registered command `{name}` for {}", caller.name());
+
+            Ok(())
+        }
+    }
+}
@@ -1,10 +1,19 @@
 //! # Compiler
 
+use std::path::Path;
+
 use anyhow::Result;
 
 
 
-pub fn run(content: &str, input_filename: &str, output_filename: &str) -> Result<()> {
+/// Compile a program into a shared library, with `libabi.rlib` (and the
+/// standard library) from `library_dir`.
+pub fn run(
+    content: &str,
+    input_filename: &str,
+    output_filename: &str,
+    library_dir: &Path,
+) -> Result<()> {
     let result = std::panic::catch_unwind(|| {
         let config = interface::Config {
             opts: session::config::Options {
@@ -15,7 +24,7 @@ pub fn run(content: &str, input_filename: &str, output_filename: &str) -> Result
                         session::config::ExternEntry {
                             location: session::config::ExternLocation::ExactPaths(
                                 [session::utils::CanonicalizedPath::new(
-                                    "/lib/libabi.rlib".into(),
+                                    library_dir.join("libabi.rlib"),
                                 )]
                                 .into(),
                             ),
@@ -29,7 +38,7 @@ pub fn run(content: &str, input_filename: &str, output_filename: &str) -> Result
                 ),
                 search_paths: vec![session::search_paths::SearchPath::new(
                     session::search_paths::PathKind::All,
-                    "/lib".into(),
+                    library_dir.to_path_buf(),
                 )],
                 incremental: None, // TODO: Use incremental compilation.
                 output_types: session::config::OutputTypes::new(&[(
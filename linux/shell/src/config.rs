@@ -0,0 +1,268 @@
+//! # Configuration File
+//!
+//! The shell reads its configuration from `~/.config/shell/config.ini` at
+//! startup, and again whenever the file changes. Anything the file leaves out
+//! (or gets wrong, which is logged) keeps its default.
+//!
+//! ```text
+//! [cursor]
+//! theme = /usr/share/cursors/default
+//!
+//! [compiler]
+//! library_dir = /lib
+//!
+//! # Defaults for the `input` command's settings.
+//! [input]
+//! repeat_delay = 400
+//! repeat_rate = 40
+//!
+//! [gestures]
+//! multi_click_timeout = 400
+//! multi_click_distance = 4
+//! drag_threshold = 4
+//!
+//! # The shell's own interface.
+//! [colors]
+//! panel = #1e1e22
+//! hyperlink = #a3a3cc
+//!
+//! # The theme that views are drawn with. Tokens have one color for both
+//! # modes, or a light and a dark color separated by a comma.
+//! [theme]
+//! mode = dark
+//! accent = #3d5afe,#8c9eff
+//! ```
+//!
+//! Settings changed with shell commands (see
+//! [`shell_setting`](crate::system::shell_setting)) take precedence over the
+//! configuration file.
+
+use std::{
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use {
+    abi::{
+        GestureSettings, Rgba, View,
+        theme::{ColorMode, TokenColor},
+    },
+    log::warn,
+};
+
+use crate::input::InputConfig;
+
+
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Config {
+    /// The directory that cursor images are loaded from, set by `cursor.theme`.
+    pub cursor_theme: PathBuf,
+    /// The directory that `libabi.rlib` (and the standard library) are found
+    /// in when compiling programs, set by `compiler.library_dir`.
+    pub library_dir: PathBuf,
+    /// The input settings used until they're changed with the `input`
+    /// command, set in the `[input]` section.
+    pub input: InputConfig,
+    /// Set in the `[gestures]` section.
+    pub gestures: GestureSettings,
+    /// Set in the `[colors]` section.
+    pub colors: Colors,
+    /// The color mode views start in, set by `theme.mode`.
+    pub color_mode: ColorMode,
+    /// Tokens defined in the `[theme]` section, which replace the default
+    /// theme's tokens of the same name.
+    pub theme_tokens: Vec<(String, TokenColor)>,
+}
+
+/// The colors of the shell's own interface.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Colors {
+    pub panel: Rgba,
+    pub window: Rgba,
+    pub text_edit: Rgba,
+    pub hyperlink: Rgba,
+    pub text: Rgba,
+    pub text_hovered: Rgba,
+    pub text_active: Rgba,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            cursor_theme: PathBuf::from("/usr/share/cursors/default"),
+            library_dir: PathBuf::from("/lib"),
+            input: InputConfig::default(),
+            gestures: GestureSettings::default(),
+            colors: Colors::default(),
+            color_mode: ColorMode::default(),
+            theme_tokens: Vec::new(),
+        }
+    }
+}
+
+impl Default for Colors {
+    fn default() -> Self {
+        Self {
+            panel: Rgba::rgb(0x1e, 0x1e, 0x22),
+            window: Rgba::rgb(0x2b, 0x2b, 0x31),
+            text_edit: Rgba::rgb(0x2b, 0x2b, 0x31),
+            hyperlink: Rgba::rgb(0xa3, 0xa3, 0xcc),
+            text: Rgba::rgb(0x97, 0x97, 0xaa),
+            text_hovered: Rgba::rgb(0xa3, 0xa3, 0xbb),
+            text_active: Rgba::rgb(0xb7, 0xb7, 0xcc),
+        }
+    }
+}
+
+impl Config {
+    /// The configuration file in the user's home directory, if they have one.
+    pub fn path() -> Option<PathBuf> {
+        let home = std::env::var_os("HOME")?;
+
+        Some(PathBuf::from(home).join(".config/shell/config.ini"))
+    }
+
+    /// Load the user's configuration file, see [`Config::path`].
+    pub fn load() -> Self {
+        Self::path().map_or_else(Self::default, |path| Self::read(&path))
+    }
+
+    /// Load a configuration file, logging anything that's wrong with it. A
+    /// missing file is treated as empty.
+    pub fn read(path: &Path) -> Self {
+        let source = match std::fs::read_to_string(path) {
+            Ok(source) => source,
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => String::new(),
+            Err(error) => {
+                warn!("Failed to read '{}': {error}", path.display());
+                String::new()
+            }
+        };
+
+        let (config, errors) = Self::parse(&source);
+        for error in errors {
+            warn!("Invalid configuration in '{}': {error}", path.display());
+        }
+
+        config
+    }
+
+    /// Parse a configuration file, along with an error for each line that
+    /// couldn't be used.
+    pub fn parse(source: &str) -> (Self, Vec<String>) {
+        let mut config = Self::default();
+        let mut errors = Vec::new();
+        let mut section = String::new();
+        for (index, line) in source.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with(['#', ';']) {
+                continue;
+            }
+            if let Some(name) = line
+                .strip_prefix('[')
+                .and_then(|line| line.strip_suffix(']'))
+            {
+                section = name.trim().to_string();
+                continue;
+            }
+            let result = match line.split_once('=') {
+                Some((key, value)) => config.set(&section, key.trim(), value.trim()),
+                None => Err("expected `key = value`".to_string()),
+            };
+            if let Err(error) = result {
+                errors.push(format!("line {}: {error}", index + 1));
+            }
+        }
+
+        (config, errors)
+    }
+
+    fn set(&mut self, section: &str, key: &str, value: &str) -> Result<(), String> {
+        let invalid = || format!("invalid value for '{section}.{key}': '{value}'");
+        match (section, key) {
+            ("cursor", "theme") => self.cursor_theme = PathBuf::from(value),
+            ("compiler", "library_dir") => self.library_dir = PathBuf::from(value),
+            ("input", setting) => self.input.set(setting, value)?,
+            ("gestures", "multi_click_timeout") => {
+                let millis = value.parse().map_err(|_| invalid())?;
+                self.gestures.multi_click_timeout = Duration::from_millis(millis);
+            }
+            ("gestures", "multi_click_distance") => {
+                self.gestures.multi_click_distance = parse_distance(value).ok_or_else(invalid)?;
+            }
+            ("gestures", "drag_threshold") => {
+                self.gestures.drag_threshold = parse_distance(value).ok_or_else(invalid)?;
+            }
+            ("colors", name) => {
+                let color = parse_color(value).ok_or_else(invalid)?;
+                *self.colors.get_mut(name)? = color;
+            }
+            ("theme", "mode") => {
+                self.color_mode = match value {
+                    "light" => ColorMode::Light,
+                    "dark" => ColorMode::Dark,
+                    _ => return Err(invalid()),
+                };
+            }
+            ("theme", token) => {
+                let color = match value.split_once(',') {
+                    Some((light, dark)) => TokenColor::new(
+                        parse_color(light.trim()).ok_or_else(invalid)?,
+                        parse_color(dark.trim()).ok_or_else(invalid)?,
+                    ),
+                    None => TokenColor::both(parse_color(value).ok_or_else(invalid)?),
+                };
+                self.theme_tokens.push((token.to_string(), color));
+            }
+            _ => return Err(format!("unknown setting '{section}.{key}'")),
+        }
+
+        Ok(())
+    }
+
+    /// Apply the configured gesture settings, color mode and tokens to a view.
+    pub fn apply_to_view(&self, view: &mut View) {
+        view.set_gesture_settings(self.gestures);
+        view.update_theme(|theme| {
+            theme.set_mode(self.color_mode);
+            for (name, color) in &self.theme_tokens {
+                theme.define(name.clone(), *color);
+            }
+        });
+    }
+}
+
+impl Colors {
+    fn get_mut(&mut self, name: &str) -> Result<&mut Rgba, String> {
+        Ok(match name {
+            "panel" => &mut self.panel,
+            "window" => &mut self.window,
+            "text_edit" => &mut self.text_edit,
+            "hyperlink" => &mut self.hyperlink,
+            "text" => &mut self.text,
+            "text_hovered" => &mut self.text_hovered,
+            "text_active" => &mut self.text_active,
+            _ => return Err(format!("unknown color '{name}'")),
+        })
+    }
+}
+
+fn parse_distance(value: &str) -> Option<f32> {
+    value
+        .parse::<f32>()
+        .ok()
+        .filter(|distance| distance.is_finite() && *distance >= 0.0)
+}
+
+/// Parse a color written as `#rrggbb` or `#rrggbbaa`.
+fn parse_color(value: &str) -> Option<Rgba> {
+    let hex = value.strip_prefix('#')?;
+    if !matches!(hex.len(), 6 | 8) || !hex.is_ascii() {
+        return None;
+    }
+    let channel = |index: usize| u8::from_str_radix(hex.get(index..index + 2)?, 16).ok();
+    let alpha = if hex.len() == 8 { channel(6)? } else { u8::MAX };
+
+    Some(Rgba::new(channel(0)?, channel(2)?, channel(4)?, alpha))
+}
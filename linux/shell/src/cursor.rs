@@ -44,9 +44,15 @@ impl CursorData {
         Ok(Self { icons })
     }
 
-    pub fn get_image(&self, scale: u32, millis: u32) -> Image {
-        let size = 24 * scale;
-        frame(millis, size, &self.icons)
+    /// The index of the animation frame that's shown `millis` into the
+    /// cursor's animation at the `scale`, see [`frame`](Self::frame).
+    pub fn frame_index(&self, scale: u32, millis: u32) -> usize {
+        frame_index(millis, 24 * scale, &self.icons)
+    }
+
+    /// The cursor's image for the animation frame at the `scale`.
+    pub fn frame(&self, scale: u32, index: usize) -> &Image {
+        nearest_images(24 * scale, &self.icons).nth(index).unwrap()
     }
 }
 
@@ -62,7 +68,7 @@ fn nearest_images(size: u32, images: &[Image]) -> impl Iterator<Item = &Image> {
     })
 }
 
-fn frame(mut millis: u32, size: u32, images: &[Image]) -> Image {
+fn frame_index(mut millis: u32, size: u32, images: &[Image]) -> usize {
     let total = nearest_images(size, images).fold(0, |acc, image| acc + image.delay);
 
     if total == 0 {
@@ -71,9 +77,9 @@ fn frame(mut millis: u32, size: u32, images: &[Image]) -> Image {
         millis %= total;
     }
 
-    for img in nearest_images(size, images) {
+    for (index, img) in nearest_images(size, images).enumerate() {
         if millis <= img.delay {
-            return img.clone();
+            return index;
         }
         millis -= img.delay;
     }
@@ -0,0 +1,476 @@
+//! # Input Devices
+//!
+//! Every evdev device is read through a single [`InputDevices`] event source,
+//! which keeps its own [`EventPoll`] of the devices so that they can be added
+//! and removed while the input thread's event loop is running. Devices are
+//! added when the kernel announces them with a uevent (see [`UeventSocket`]),
+//! and removed once they're unplugged.
+//!
+//! Each device is classified by what it can do (see [`DeviceKind`]), which
+//! decides how the input thread translates its events.
+//!
+//! Only devices on the shell's seat are used, and only while its session is
+//! active (see [`Session`]). Devices are revoked when the session is
+//! deactivated, and opened again when it's reactivated.
+
+use std::{
+    os::fd::AsRawFd as _,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use {
+    anyhow::Result,
+    egui::{Vec2, vec2},
+    linux_uapi::{
+        constants,
+        epoll::{Event, EventPoll},
+        file::File,
+        traits::AsFile as _,
+        uevent::UeventSocket,
+    },
+    log::{debug, info, warn},
+};
+
+use crate::{EventResponse, EventSource, seat::Session};
+
+
+
+/// The key that the uevent socket is added to the device poll with, which is
+/// never a device's ID.
+const UEVENT_KEY: u64 = u64::MAX;
+/// The key that the session's [`Session::changed`] file is added to the device
+/// poll with.
+const SESSION_KEY: u64 = u64::MAX - 1;
+
+/// The maximum number of device events handled each time the poll wakes up.
+const MAX_EVENTS_PER_POLL: usize = 16;
+
+/// The width assumed for touchpads that don't report their resolution.
+const FALLBACK_TOUCHPAD_WIDTH_MM: f32 = 100.0;
+
+/// What an input device is, based on the events it supports.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum DeviceKind {
+    Keyboard,
+    /// A mouse, trackball, or pointing stick, which reports relative motion.
+    Mouse,
+    /// A touchpad, which reports where fingers are on it.
+    Touchpad,
+    /// A touchscreen that tracks each finger touching it (see `ABS_MT_SLOT`),
+    /// whose positions map onto the whole desktop.
+    Touchscreen,
+    /// A single-touch touchscreen, graphics tablet, or virtual machine's
+    /// pointer, whose positions map onto the whole desktop.
+    Absolute,
+    /// Anything else, such as a power button or lid switch.
+    Other,
+}
+
+impl DeviceKind {
+    pub fn classify(device: &evdev::Device) -> Self {
+        let has_key = |key| {
+            device
+                .supported_keys()
+                .is_some_and(|keys| keys.contains(key))
+        };
+        let has_abs = |axis| {
+            device
+                .supported_absolute_axes()
+                .is_some_and(|axes| axes.contains(axis))
+        };
+        let has_rel = |axis| {
+            device
+                .supported_relative_axes()
+                .is_some_and(|axes| axes.contains(axis))
+        };
+        let props = device.properties();
+
+        if has_abs(evdev::AbsoluteAxisCode::ABS_X) && has_abs(evdev::AbsoluteAxisCode::ABS_Y) {
+            let touchpad = props.contains(evdev::PropType::POINTER)
+                || props.contains(evdev::PropType::BUTTONPAD)
+                || (has_key(evdev::KeyCode::BTN_TOOL_FINGER)
+                    && !has_key(evdev::KeyCode::BTN_TOOL_PEN)
+                    && !props.contains(evdev::PropType::DIRECT));
+            return if touchpad {
+                Self::Touchpad
+            } else if props.contains(evdev::PropType::DIRECT)
+                && has_abs(evdev::AbsoluteAxisCode::ABS_MT_SLOT)
+            {
+                Self::Touchscreen
+            } else {
+                Self::Absolute
+            };
+        }
+        if has_rel(evdev::RelativeAxisCode::REL_X) && has_rel(evdev::RelativeAxisCode::REL_Y) {
+            return Self::Mouse;
+        }
+        if has_key(evdev::KeyCode::KEY_A) && has_key(evdev::KeyCode::KEY_ENTER) {
+            return Self::Keyboard;
+        }
+
+        Self::Other
+    }
+}
+
+/// What the input thread needs to know about a device to translate its events.
+#[derive(Clone, Copy, Debug)]
+pub struct DeviceInfo {
+    /// The device's ID, which is reused once it's removed.
+    pub id: usize,
+    pub kind: DeviceKind,
+    /// The smallest values of the X and Y axes, for absolute devices.
+    pub abs_min: Vec2,
+    /// The largest values of the X and Y axes, for absolute devices.
+    pub abs_max: Vec2,
+    /// The X and Y axes' units per millimeter, for absolute devices.
+    pub resolution: Vec2,
+}
+
+impl DeviceInfo {
+    fn new(id: usize, device: &evdev::Device) -> Self {
+        let kind = DeviceKind::classify(device);
+        let abs_info = device
+            .get_absinfo()
+            .map(|info| info.collect::<Vec<_>>())
+            .unwrap_or_default();
+        let axis = |axis| {
+            abs_info
+                .iter()
+                .find(|(code, _)| *code == axis)
+                .map(|(_, info)| *info)
+        };
+        let (x, y) = (
+            axis(evdev::AbsoluteAxisCode::ABS_X),
+            axis(evdev::AbsoluteAxisCode::ABS_Y),
+        );
+        let range = |get: fn(&evdev::AbsInfo) -> i32| {
+            vec2(
+                x.as_ref().map_or(0, get) as f32,
+                y.as_ref().map_or(0, get) as f32,
+            )
+        };
+        let abs_min = range(evdev::AbsInfo::minimum);
+        let abs_max = range(evdev::AbsInfo::maximum);
+        let mut resolution = range(evdev::AbsInfo::resolution);
+        if resolution.x <= 0.0 || resolution.y <= 0.0 {
+            let units_per_mm = (abs_max.x - abs_min.x) / FALLBACK_TOUCHPAD_WIDTH_MM;
+            resolution = Vec2::splat(units_per_mm.max(1.0));
+        }
+
+        Self {
+            id,
+            kind,
+            abs_min,
+            abs_max,
+            resolution,
+        }
+    }
+}
+
+/// Something that happened to one of the [`InputDevices`].
+#[derive(Debug)]
+pub enum DeviceEvent {
+    Input(DeviceInfo, evdev::InputEvent),
+    /// The device was unplugged or revoked, so anything it was holding down
+    /// should be released.
+    Removed(DeviceInfo),
+}
+
+struct Device {
+    path: PathBuf,
+    device: evdev::Device,
+    info: DeviceInfo,
+}
+
+/// Every input device, which are added and removed as they're plugged in and
+/// unplugged.
+pub struct InputDevices {
+    poll: EventPoll,
+    /// The socket that hotplug events are read from, or `None` if it couldn't
+    /// be opened, in which case only the devices present at startup are
+    /// used.
+    uevents: Option<UeventSocket>,
+    /// The devices, indexed by their IDs.
+    devices: Vec<Option<Device>>,
+    event_buffer: Vec<Event>,
+    session: Arc<Session>,
+}
+
+impl InputDevices {
+    /// Open every input device on the session's seat that's currently plugged
+    /// in, and start listening for more.
+    pub fn open(session: Arc<Session>) -> Result<Self> {
+        let poll = EventPoll::create()?;
+        poll.add(
+            &session.changed().as_file(),
+            Event::new(SESSION_KEY, true, false),
+        )?;
+        let uevents = match UeventSocket::open() {
+            Ok(socket) => {
+                poll.add(&socket.as_file(), Event::new(UEVENT_KEY, true, false))?;
+                Some(socket)
+            }
+            Err(error) => {
+                warn!("Failed to listen for input devices being plugged in: {error:?}");
+                None
+            }
+        };
+
+        let mut devices = Self {
+            poll,
+            uevents,
+            devices: Vec::new(),
+            event_buffer: Vec::with_capacity(MAX_EVENTS_PER_POLL),
+            session,
+        };
+        devices.add_present();
+
+        Ok(devices)
+    }
+
+    /// Add every device that's currently plugged in.
+    fn add_present(&mut self) {
+        for (path, device) in evdev::enumerate() {
+            if let Err(error) = self.add(path, device) {
+                warn!("Failed to add input device: {error}");
+            }
+        }
+    }
+
+    fn add(&mut self, path: PathBuf, device: evdev::Device) -> Result<()> {
+        // Devices present at startup can be announced again while enumerating.
+        if self.find(&path).is_some() {
+            return Ok(());
+        }
+        // Devices plugged in while the session is inactive are added once it's
+        // reactivated.
+        if !self.session.is_active() {
+            return Ok(());
+        }
+        if !self.session.owns(&path) {
+            debug!(target: "dev", "Ignoring {}, which is on another seat", path.display());
+            return Ok(());
+        }
+        device.set_nonblocking(true)?;
+
+        let id = self
+            .devices
+            .iter()
+            .position(Option::is_none)
+            .unwrap_or(self.devices.len());
+        let info = DeviceInfo::new(id, &device);
+        self.poll.add(
+            &unsafe { File::from_raw(device.as_raw_fd()) },
+            Event::new(id as u64, true, false),
+        )?;
+
+        info!(
+            "Added input device {} ({}) as {:?}",
+            path.display(),
+            device.name().unwrap_or("Unnamed Device"),
+            info.kind,
+        );
+        debug!(
+            target: "dev",
+            "{}\n\
+            \t.physical_path: {}\n\
+            \t.properties: {:?}\n\
+            \t.supported_events: {:?}\n\
+            \t.supported_keys: {:?}\n\
+            \t.supported_absolute_axes: {:?}\n\
+            \t.supported_relative_axes: {:?}\n\
+            \t.info: {:?}",
+            path.display(),
+            device.physical_path().unwrap_or("NONE"),
+            device.properties(),
+            device.supported_events(),
+            device.supported_keys(),
+            device.supported_absolute_axes(),
+            device.supported_relative_axes(),
+            info,
+        );
+
+        let device = Device { path, device, info };
+        if id == self.devices.len() {
+            self.devices.push(Some(device));
+        } else {
+            self.devices[id] = Some(device);
+        }
+
+        Ok(())
+    }
+
+    fn remove(&mut self, id: usize) -> Option<DeviceInfo> {
+        let device = self.devices.get_mut(id)?.take()?;
+        // The device's file may already be gone, which removes it from the poll.
+        let _ = self
+            .poll
+            .remove(&unsafe { File::from_raw(device.device.as_raw_fd()) });
+        info!("Removed input device {}", device.path.display());
+
+        Some(device.info)
+    }
+
+    /// Revoke every device when the session is deactivated, so that nothing can
+    /// be read from them until it's reactivated, when they're opened again.
+    fn handle_session_change<D, F>(&mut self, data: &mut D, callback: &mut F) -> Result<()>
+    where
+        F: FnMut(&mut D, DeviceEvent) -> Result<()>,
+    {
+        self.session.changed().take()?;
+        if self.session.is_active() {
+            info!("Session activated, opening input devices...");
+            self.add_present();
+            return Ok(());
+        }
+
+        info!("Session deactivated, revoking input devices...");
+        for id in 0..self.devices.len() {
+            let Some(device) = &self.devices[id] else {
+                continue;
+            };
+            // https://www.kernel.org/doc/html/latest/input/input.html
+            let fd = device.device.as_raw_fd();
+            if unsafe { libc::ioctl(fd, constants::EVIOCREVOKE, 0) } == -1 {
+                warn!(
+                    "Failed to revoke {}: {}",
+                    device.path.display(),
+                    std::io::Error::last_os_error(),
+                );
+            }
+            if let Some(info) = self.remove(id) {
+                callback(data, DeviceEvent::Removed(info))?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn find(&self, path: &Path) -> Option<usize> {
+        self.devices
+            .iter()
+            .position(|device| device.as_ref().is_some_and(|device| device.path == path))
+    }
+
+    /// Add and remove devices for every uevent waiting on the socket.
+    fn handle_uevents<D, F>(&mut self, data: &mut D, callback: &mut F) -> Result<()>
+    where
+        F: FnMut(&mut D, DeviceEvent) -> Result<()>,
+    {
+        let Some(socket) = &self.uevents else {
+            return Ok(());
+        };
+        let mut uevents = Vec::new();
+        loop {
+            match socket.read() {
+                Ok(Some(uevent)) => uevents.push(uevent),
+                Ok(None) => break,
+                Err(error) => {
+                    warn!("Failed to read uevent: {error:?}");
+                    break;
+                }
+            }
+        }
+
+        for uevent in uevents {
+            // Only event devices can be read with evdev, rather than the
+            // `inputN` devices they belong to or legacy `mouseN` devices.
+            let Some(devname) = uevent.property("DEVNAME") else {
+                continue;
+            };
+            if uevent.subsystem() != Some("input") || !devname.starts_with("input/event") {
+                continue;
+            }
+            let path = Path::new("/dev").join(devname);
+            match uevent.action.as_str() {
+                "add" => match evdev::Device::open(&path) {
+                    Ok(device) => {
+                        if let Err(error) = self.add(path, device) {
+                            warn!("Failed to add input device: {error}");
+                        }
+                    }
+                    Err(error) => warn!("Failed to open {}: {error}", path.display()),
+                },
+                "remove" => {
+                    if let Some(info) = self.find(&path).and_then(|id| self.remove(id)) {
+                        callback(data, DeviceEvent::Removed(info))?;
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl<D> EventSource<D> for InputDevices {
+    type Event = DeviceEvent;
+
+    fn init(&mut self, poll: &EventPoll, key: u64) -> Result<()> {
+        poll.add(&self.poll.as_file(), Event::new(key, true, false))?;
+        Ok(())
+    }
+
+    fn handle_event<F>(
+        &mut self,
+        data: &mut D,
+        event: Event,
+        mut callback: F,
+    ) -> Result<EventResponse>
+    where
+        F: FnMut(&mut D, DeviceEvent) -> Result<()>,
+    {
+        if !event.readable() {
+            return Ok(EventResponse::Continue);
+        }
+
+        self.event_buffer.clear();
+        self.poll.wait(&mut self.event_buffer, 0)?;
+        for index in 0..self.event_buffer.len() {
+            let key = self.event_buffer[index].data();
+            if key == UEVENT_KEY {
+                self.handle_uevents(data, &mut callback)?;
+                continue;
+            }
+            if key == SESSION_KEY {
+                self.handle_session_change(data, &mut callback)?;
+                continue;
+            }
+
+            let id = key as usize;
+            let Some(Some(device)) = self.devices.get_mut(id) else {
+                continue;
+            };
+            let info = device.info;
+            let unplugged = match device.device.fetch_events() {
+                Ok(events) => {
+                    for event in events {
+                        callback(data, DeviceEvent::Input(info, event))?;
+                    }
+                    false
+                }
+                Err(error) if error.kind() == std::io::ErrorKind::WouldBlock => false,
+                // Unplugged devices fail with `ENODEV`, sometimes before their
+                // uevent arrives.
+                Err(error) => {
+                    if error.raw_os_error() != Some(libc::ENODEV) {
+                        warn!("Failed to read input device: {error}");
+                    }
+                    true
+                }
+            };
+            if unplugged && let Some(info) = self.remove(id) {
+                callback(data, DeviceEvent::Removed(info))?;
+            }
+        }
+
+        Ok(EventResponse::Continue)
+    }
+
+    fn cleanup(&mut self, poll: &EventPoll) -> Result<()> {
+        poll.remove(&self.poll.as_file())?;
+        Ok(())
+    }
+}
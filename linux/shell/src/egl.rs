@@ -4,11 +4,17 @@ use std::sync::Arc;
 
 use anyhow::Result;
 
+use abi::Rgba;
+
+use crate::{config::Colors, render::ViewRenderer};
+
 
 
 pub struct Renderer {
     pub painter: egui_glow::Painter,
     pub egui_context: egui::Context,
+    /// Draws programs' renders, see [`render`](crate::render).
+    pub views: ViewRenderer,
     pub gl: Arc<glow::Context>,
 }
 
@@ -22,6 +28,7 @@ impl Renderer {
         });
 
         let painter = egui_glow::Painter::new(Arc::clone(&gl), "", None, true)?;
+        let views = ViewRenderer::new(Arc::clone(&gl))?;
 
         {
             let mut fonts = egui::FontDefinitions::default();
@@ -47,34 +54,39 @@ impl Renderer {
             egui_context.set_fonts(fonts);
         }
 
-        egui_context.style_mut(|s| {
-            s.visuals.interact_cursor = Some(egui::CursorIcon::PointingHand);
-
-            s.visuals.button_frame = false;
-            s.visuals.collapsing_header_frame = false;
-
-            s.visuals.panel_fill = egui::Color32::from_rgb(0x1e, 0x1e, 0x22);
-            s.visuals.window_fill = egui::Color32::from_rgb(0x2b, 0x2b, 0x31);
-            s.visuals.text_edit_bg_color = Some(egui::Color32::from_rgb(0x2b, 0x2b, 0x31));
-
-            s.visuals.hyperlink_color = egui::Color32::from_rgb(0xa3, 0xa3, 0xcc);
-
-            s.visuals.widgets.inactive.fg_stroke =
-                egui::Stroke::new(1.0, egui::Color32::from_rgb(0x97, 0x97, 0xaa));
-            s.visuals.widgets.hovered.fg_stroke =
-                egui::Stroke::new(1.0, egui::Color32::from_rgb(0xa3, 0xa3, 0xbb));
-            s.visuals.widgets.active.fg_stroke =
-                egui::Stroke::new(1.0, egui::Color32::from_rgb(0xb7, 0xb7, 0xcc));
-        });
-
         Ok(Self {
             painter,
             egui_context,
+            views,
             gl,
         })
     }
 }
 
+/// Style the shell's interface with the configured colors, see
+/// [`config`](crate::config).
+pub fn apply_style(egui_context: &egui::Context, colors: &Colors) {
+    let color =
+        |color: Rgba| egui::Color32::from_rgba_unmultiplied(color.r, color.g, color.b, color.a);
+
+    egui_context.style_mut(|s| {
+        s.visuals.interact_cursor = Some(egui::CursorIcon::PointingHand);
+
+        s.visuals.button_frame = false;
+        s.visuals.collapsing_header_frame = false;
+
+        s.visuals.panel_fill = color(colors.panel);
+        s.visuals.window_fill = color(colors.window);
+        s.visuals.text_edit_bg_color = Some(color(colors.text_edit));
+
+        s.visuals.hyperlink_color = color(colors.hyperlink);
+
+        s.visuals.widgets.inactive.fg_stroke = egui::Stroke::new(1.0, color(colors.text));
+        s.visuals.widgets.hovered.fg_stroke = egui::Stroke::new(1.0, color(colors.text_hovered));
+        s.visuals.widgets.active.fg_stroke = egui::Stroke::new(1.0, color(colors.text_active));
+    });
+}
+
 pub use egui_phosphor::regular::ICONS as ALL_ICONS;
 
 pub mod icons {
@@ -0,0 +1,685 @@
+//! # Headless Mode
+//!
+//! Runs an application without a display for a fixed number of frames, feeding
+//! it scripted input and saving each frame's [`Render`] to disk. This exercises
+//! the same loading and ABI path as the shell, so it can be used for end-to-end
+//! tests on machines without a GPU.
+//!
+//! ```text
+//! shell --headless <app.so> [--frames N] [--size WxH] [--script PATH] [--out DIR] [--binary]
+//!     [--images] [--config PATH] [--record PATH]
+//! ```
+//!
+//! Views that the application opens with [`abi::system::open_view`] are
+//! rendered too, to `frame-NNNN-<view>.render`, but scripted input only goes to
+//! the main view.
+//!
+//! Frames are written in the text format by default, or as render streams (see
+//! [`abi::render_stream`]) with `--binary`, which can be printed as text with:
+//!
+//! ```text
+//! shell --decode-render <frame.bin>
+//! ```
+//!
+//! Scripted input is sent to the main view through an input channel (see
+//! [`linux_uapi::channel`]) alongside a [`DriverInput`], and each render comes
+//! back through another a page at a time, the same way they would with a
+//! driver, so that what's written is what a host would draw.
+//!
+//! The main view's control messages (see [`abi::DriverControl`]) are logged,
+//! and the run stops early if the application asks for its view to be closed.
+//!
+//! Views use the default gesture settings and theme, unless a configuration
+//! file (see [`config`](crate::config)) is given with `--config`. The user's
+//! own configuration isn't used, so that runs are the same on every machine.
+//!
+//! With `--images`, each frame is also drawn with the software rasterizer (see
+//! [`abi::raster`]) to `frame-NNNN.pam`, for comparing against golden images.
+//!
+//! With `--record`, every event and pass that the main view executes is
+//! recorded (see [`abi::View::start_recording`]) and written to the given path,
+//! one step per line with the elements that it changed below it, to find the
+//! event that caused a bad layout or a missing render. Only the last
+//! [`abi::MAX_RECORDED_FRAMES`] steps are kept.
+//!
+//! Scripts have one event per line, prefixed with the frame it happens before.
+//! Empty lines and anything after a `#` are ignored.
+//!
+//! ```text
+//! 0 move 10 10
+//! 1 down 10 10 primary
+//! 2 up primary
+//! 3 scroll 0 -1
+//! 4 key a
+//! 5 key ctrl+shift+enter
+//! 6 resize 640 480
+//! 7 touch 1 start 100 100
+//! 8 touch 1 move 120 100
+//! 9 touch 1 end 120 100
+//! 10 zoom 1.5 100 100
+//! 11 key backspace 3  # Held for 3 key repeats.
+//! ```
+
+use std::{
+    fmt::Write as _,
+    path::PathBuf,
+    time::{Duration, Instant},
+};
+
+use {
+    abi::{
+        Aabb2D, AppViews, DriverControl, DriverHandshake, DriverInput, DriverInputEvent,
+        FontFamily, FontStyle, Fonts, Key, KeyboardEvent, LineHeight, MAIN_VIEW, Modifiers,
+        PointerButton, PointerEvent, Recording, Render, RenderReceiver, RenderSender, Rgba,
+        ScrollDelta, TextAlignment, TextParams, TextSpan, TextWrapMode, TouchPhase, Xy,
+        raster::Image,
+    },
+    anyhow::{Context as _, Result, anyhow, bail},
+    linux_uapi::channel,
+    log::{debug, info, warn},
+};
+
+use crate::{
+    config::Config,
+    program::{self, LocalChannel},
+    system::{self, ViewRequest},
+};
+
+
+
+const DEFAULT_FRAME_COUNT: u32 = 1;
+/// The time between frames, so that animations progress the same way on every
+/// run.
+const FRAME_INTERVAL: Duration = Duration::from_nanos(1_000_000_000 / 60);
+const DEFAULT_WINDOW_SIZE: Xy<f32> = Xy::new(800.0, 600.0);
+/// The number of scripted events that can be sent in one frame.
+const SCRIPT_EVENT_CAPACITY: u32 = 128;
+
+pub struct HeadlessOptions {
+    pub app_path: PathBuf,
+    pub frame_count: u32,
+    pub window_size: Xy<f32>,
+    pub script_path: Option<PathBuf>,
+    pub out_dir: PathBuf,
+    /// Whether frames are written as binary render streams.
+    pub binary: bool,
+    /// Whether frames are also rasterized to images.
+    pub images: bool,
+    pub config_path: Option<PathBuf>,
+    /// Where the main view's recording is written, if anywhere.
+    pub record_path: Option<PathBuf>,
+}
+
+impl HeadlessOptions {
+    /// Parse the command line arguments (without the program name), returning
+    /// `None` if `--headless` wasn't given.
+    pub fn parse(args: impl IntoIterator<Item = String>) -> Result<Option<Self>> {
+        let args = args.into_iter().collect::<Vec<_>>();
+        if !args.iter().any(|arg| arg == "--headless") {
+            return Ok(None);
+        }
+        let mut args = args.into_iter();
+        let mut app_path = None;
+        let mut frame_count = DEFAULT_FRAME_COUNT;
+        let mut window_size = DEFAULT_WINDOW_SIZE;
+        let mut script_path = None;
+        let mut out_dir = PathBuf::from("frames");
+        let mut binary = false;
+        let mut images = false;
+        let mut config_path = None;
+        let mut record_path = None;
+
+        while let Some(arg) = args.next() {
+            let mut value = || {
+                args.next()
+                    .ok_or_else(|| anyhow!("missing value for `{arg}`"))
+            };
+            match arg.as_str() {
+                "--headless" => app_path = Some(PathBuf::from(value()?)),
+                "--frames" => frame_count = value()?.parse().context("invalid frame count")?,
+                "--size" => {
+                    let size = value()?;
+                    let (width, height) = size
+                        .split_once('x')
+                        .ok_or_else(|| anyhow!("expected a size like 800x600, got `{size}`"))?;
+                    window_size = Xy::new(width.parse()?, height.parse()?);
+                }
+                "--script" => script_path = Some(PathBuf::from(value()?)),
+                "--out" => out_dir = PathBuf::from(value()?),
+                "--binary" => binary = true,
+                "--images" => images = true,
+                "--config" => config_path = Some(PathBuf::from(value()?)),
+                "--record" => record_path = Some(PathBuf::from(value()?)),
+                _ => bail!("unknown argument `{arg}`"),
+            }
+        }
+
+        Ok(app_path.map(|app_path| Self {
+            app_path,
+            frame_count,
+            window_size,
+            script_path,
+            out_dir,
+            binary,
+            images,
+            config_path,
+            record_path,
+        }))
+    }
+}
+
+/// Run the application, writing each frame to `frame-NNNN.render` (or
+/// `frame-NNNN.bin`, and `frame-NNNN.pam`) in the output directory.
+pub fn run(options: HeadlessOptions) -> Result<()> {
+    let script = match &options.script_path {
+        Some(path) => {
+            let source = std::fs::read_to_string(path)
+                .with_context(|| format!("failed to read script `{}`", path.display()))?;
+            parse_script(&source)?
+        }
+        None => Vec::new(),
+    };
+
+    let (app_obj, manifest) = program::open_app(&options.app_path)?;
+
+    info!(
+        "Running {} headlessly for {} frames...",
+        manifest.name, options.frame_count,
+    );
+
+    std::fs::create_dir_all(&options.out_dir)?;
+    let config = options
+        .config_path
+        .as_deref()
+        .map_or_else(Config::default, Config::read);
+
+    system::set_current_app(Some(manifest));
+    let mut views = AppViews::new(manifest, || Box::new(MonospaceFonts));
+    let main_view = views.open(MAIN_VIEW, options.window_size)?;
+    if let Some(view) = views.get_mut(main_view) {
+        config.apply_to_view(view);
+        if options.record_path.is_some() {
+            view.start_recording();
+        }
+    }
+    let mut driver_input = Box::new(DriverInput::new(Aabb2D::new(
+        0.0,
+        0.0,
+        options.window_size.x,
+        options.window_size.y,
+    )));
+    driver_input.id = main_view.0;
+    let handshake = DriverHandshake {
+        requested_event_capacity: SCRIPT_EVENT_CAPACITY,
+    };
+    let (_event_channel, mut event_sender, event_receiver) =
+        LocalChannel::new(program::negotiate_event_capacity(handshake)?)?;
+    let (_render_channel, page_sender, page_receiver) =
+        LocalChannel::new(program::RENDER_PAGE_CAPACITY)?;
+    let mut render = Render::default();
+    let mut render_sender = RenderSender::new();
+    let mut render_receiver = RenderReceiver::new();
+    let mut output = String::new();
+    let mut binary_output = Vec::new();
+    let start = Instant::now();
+
+    let mut frame_count = options.frame_count;
+    for frame in 0..options.frame_count {
+        for (_, event) in script
+            .iter()
+            .filter(|(event_frame, _)| *event_frame == frame)
+        {
+            event.send_to(&mut event_sender)?;
+        }
+        event_sender.flush()?;
+        views.handle_input(&mut driver_input, program::receive_events(&event_receiver));
+        for request in system::take_view_requests() {
+            match request {
+                ViewRequest::Open(name) => match views.open(&name, options.window_size) {
+                    Ok(id) => {
+                        if let Some(view) = views.get_mut(id) {
+                            config.apply_to_view(view);
+                        }
+                    }
+                    Err(error) => warn!("Failed to open view: {error}"),
+                },
+                ViewRequest::Close(name) => {
+                    if let Some(id) = views.find(&name) {
+                        views.close(id);
+                    }
+                }
+            }
+        }
+
+        for id in views.ids().collect::<Vec<_>>() {
+            let suffix = match views.name(id) {
+                Some(name) if id != main_view => format!("-{name}"),
+                _ => String::new(),
+            };
+            let Some(view) = views.get_mut(id) else {
+                continue;
+            };
+            render.clear();
+            view.process_updates();
+            view.begin_frame(start + FRAME_INTERVAL * frame);
+            view.render(&mut render);
+            view.end_frame();
+            for event in view.poll_events() {
+                debug!("Frame {frame}{suffix}: {event:?}");
+            }
+
+            render_sender.start(&mut render);
+            while !program::receive_render_pages(&mut render_receiver, &page_receiver) {
+                program::send_render_pages(&mut render_sender, &page_sender)?;
+            }
+            let render = render_receiver.render();
+
+            if options.binary {
+                binary_output.clear();
+                render.encode(&mut binary_output);
+                std::fs::write(
+                    options
+                        .out_dir
+                        .join(format!("frame-{frame:04}{suffix}.bin")),
+                    &binary_output,
+                )?;
+            } else {
+                output.clear();
+                write!(output, "{render}")?;
+                std::fs::write(
+                    options
+                        .out_dir
+                        .join(format!("frame-{frame:04}{suffix}.render")),
+                    &output,
+                )?;
+            }
+            if options.images {
+                let size = view.window_size();
+                let mut image = Image::new(size.x.ceil() as u32, size.y.ceil() as u32, Rgba::BLACK);
+                image.draw_render(render);
+                std::fs::write(
+                    options
+                        .out_dir
+                        .join(format!("frame-{frame:04}{suffix}.pam")),
+                    image.encode_pam(),
+                )?;
+            }
+        }
+
+        views.write_controls(&mut driver_input);
+        let mut close_requested = false;
+        for control in driver_input.drain_controls() {
+            match control {
+                DriverControl::SetCursorIcon(icon) => debug!("Frame {frame}: cursor icon {icon:?}"),
+                DriverControl::SetTitle(title) => info!("Frame {frame}: title {title:?}"),
+                DriverControl::RequestClose => close_requested = true,
+                // Every frame is rendered anyway.
+                DriverControl::RequestRedraw => {}
+            }
+        }
+        if close_requested {
+            info!("{} closed its main view after frame {frame}", manifest.name);
+            frame_count = frame + 1;
+            break;
+        }
+    }
+    system::set_current_app(None);
+
+    if let Some(path) = &options.record_path
+        && let Some(recording) = views
+            .get_mut(main_view)
+            .and_then(|view| view.stop_recording())
+    {
+        output.clear();
+        write_recording(&recording, &mut output)?;
+        std::fs::write(path, &output)
+            .with_context(|| format!("failed to write recording `{}`", path.display()))?;
+        info!(
+            "Wrote {} recorded steps to {}",
+            recording.len(),
+            path.display()
+        );
+    }
+
+    info!(
+        "Wrote {} frames to {}",
+        frame_count,
+        options.out_dir.display(),
+    );
+
+    // The views' elements live in the application's object, so they must be
+    // dropped first.
+    drop(views);
+    drop(app_obj);
+
+    Ok(())
+}
+
+
+
+/// Write each step of a recording on its own line, from the oldest to the
+/// newest, followed by the elements that it changed.
+fn write_recording(recording: &Recording, output: &mut String) -> std::fmt::Result {
+    let mut cursor = recording.cursor();
+    if !cursor.seek(0) {
+        return Ok(());
+    }
+    loop {
+        if let Some(frame) = cursor.frame() {
+            writeln!(output, "{} {:?}", frame.sequence, frame.kind)?;
+        }
+        for (_, element) in cursor.changes() {
+            writeln!(output, "    {element}")?;
+        }
+        if !cursor.step_forward() {
+            return Ok(());
+        }
+    }
+}
+
+/// Print the binary render stream at the path after `--decode-render` in the
+/// text format, returning `false` if `--decode-render` wasn't given.
+pub fn decode_render(args: impl IntoIterator<Item = String>) -> Result<bool> {
+    let mut args = args.into_iter();
+    if !args.any(|arg| arg == "--decode-render") {
+        return Ok(false);
+    }
+    let path = args
+        .next()
+        .ok_or_else(|| anyhow!("missing value for `--decode-render`"))?;
+
+    let bytes = std::fs::read(&path).with_context(|| format!("failed to read render `{path}`"))?;
+    let render = Render::decode(&bytes).with_context(|| format!("failed to decode `{path}`"))?;
+    print!("{render}");
+
+    Ok(true)
+}
+
+
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum ScriptEvent {
+    Pointer(PointerEvent),
+    /// A key is pressed, held for some number of key repeats, and then
+    /// released.
+    Key(Key, Modifiers, usize),
+    Resize(Xy<f32>),
+}
+
+impl ScriptEvent {
+    fn send_to(self, sender: &mut channel::Sender<DriverInputEvent>) -> linux_uapi::Result<()> {
+        let mut push = |event| -> linux_uapi::Result<()> {
+            if !program::send_event(sender, event)? {
+                warn!("Too many scripted events in one frame, missed: {event:?}");
+            }
+            Ok(())
+        };
+        match self {
+            Self::Pointer(event) => push(DriverInputEvent::Pointer(event)),
+            Self::Key(key, modifiers, repeats) => {
+                for index in 0..=repeats {
+                    push(DriverInputEvent::Keyboard(KeyboardEvent::Down {
+                        key,
+                        modifiers,
+                        repeat: index != 0,
+                    }))?;
+                }
+                push(DriverInputEvent::Keyboard(KeyboardEvent::Up {
+                    key,
+                    modifiers,
+                }))
+            }
+            Self::Resize(size) => push(DriverInputEvent::WindowResize(Aabb2D::new(
+                0.0, 0.0, size.x, size.y,
+            ))),
+        }
+    }
+}
+
+fn parse_script(source: &str) -> Result<Vec<(u32, ScriptEvent)>> {
+    let mut events = Vec::new();
+    for (index, line) in source.lines().enumerate() {
+        let line = line.split('#').next().unwrap_or_default();
+        let words = line.split_whitespace().collect::<Vec<_>>();
+        if words.is_empty() {
+            continue;
+        }
+        let event =
+            parse_script_line(&words).with_context(|| format!("script line {}", index + 1))?;
+        events.push(event);
+    }
+
+    Ok(events)
+}
+
+fn parse_script_line(words: &[&str]) -> Result<(u32, ScriptEvent)> {
+    let float = |index: usize| -> Result<f32> {
+        let word = words
+            .get(index)
+            .ok_or_else(|| anyhow!("too few arguments"))?;
+        word.parse()
+            .with_context(|| format!("invalid number `{word}`"))
+    };
+    let button = |index: usize| -> Result<PointerButton> {
+        Ok(match words.get(index).copied().unwrap_or("primary") {
+            "primary" => PointerButton::Primary,
+            "secondary" => PointerButton::Secondary,
+            "auxiliary" => PointerButton::Auxiliary,
+            "back" => PointerButton::Back,
+            "forward" => PointerButton::Forward,
+            other => bail!("unknown button `{other}`"),
+        })
+    };
+
+    let frame = words[0]
+        .parse()
+        .with_context(|| format!("invalid frame `{}`", words[0]))?;
+    let event = match words.get(1).copied() {
+        Some("move") => ScriptEvent::Pointer(PointerEvent::Move {
+            position: Xy::new(float(2)?, float(3)?),
+            modifiers: Modifiers::NONE,
+        }),
+        Some("down") => ScriptEvent::Pointer(PointerEvent::Down {
+            button: button(4)?,
+            position: Xy::new(float(2)?, float(3)?),
+            modifiers: Modifiers::NONE,
+        }),
+        Some("up") => ScriptEvent::Pointer(PointerEvent::Up {
+            button: button(2)?,
+            modifiers: Modifiers::NONE,
+        }),
+        Some("scroll") => ScriptEvent::Pointer(PointerEvent::Scroll {
+            delta: ScrollDelta::Lines(Xy::new(float(2)?, float(3)?)),
+            modifiers: Modifiers::NONE,
+        }),
+        Some("touch") => ScriptEvent::Pointer(PointerEvent::Touch {
+            id: words
+                .get(2)
+                .ok_or_else(|| anyhow!("missing touch ID"))?
+                .parse()
+                .context("invalid touch ID")?,
+            phase: match words.get(3).copied() {
+                Some("start") => TouchPhase::Start,
+                Some("move") => TouchPhase::Move,
+                Some("end") => TouchPhase::End,
+                Some("cancel") => TouchPhase::Cancel,
+                Some(other) => bail!("unknown touch phase `{other}`"),
+                None => bail!("missing touch phase"),
+            },
+            position: Xy::new(float(4)?, float(5)?),
+            modifiers: Modifiers::NONE,
+        }),
+        Some("zoom") => ScriptEvent::Pointer(PointerEvent::Zoom {
+            factor: float(2)?,
+            position: Xy::new(float(3)?, float(4)?),
+            modifiers: Modifiers::NONE,
+        }),
+        Some("key") => {
+            let name = words.get(2).ok_or_else(|| anyhow!("missing key"))?;
+            let (key, modifiers) = parse_key(name)?;
+            let repeats = match words.get(3) {
+                Some(repeats) => repeats.parse().context("invalid repeat count")?,
+                None => 0,
+            };
+            ScriptEvent::Key(key, modifiers, repeats)
+        }
+        Some("resize") => ScriptEvent::Resize(Xy::new(float(2)?, float(3)?)),
+        Some(other) => bail!("unknown event `{other}`"),
+        None => bail!("missing event"),
+    };
+
+    Ok((frame, event))
+}
+
+/// Parse a key name, optionally prefixed with modifiers such as `ctrl+shift+`.
+fn parse_key(name: &str) -> Result<(Key, Modifiers)> {
+    let mut name = name;
+    let mut modifiers = Modifiers::NONE;
+    while let Some((prefix, rest)) = name.split_once('+')
+        && !rest.is_empty()
+    {
+        match prefix {
+            "ctrl" => modifiers.ctrl = true,
+            "shift" => modifiers.shift = true,
+            "alt" => modifiers.alt = true,
+            "super" | "meta" => modifiers.meta = true,
+            _ => bail!("unknown modifier `{prefix}`"),
+        }
+        name = rest;
+    }
+
+    let key = match name {
+        "space" => Key::Space,
+        "tab" => Key::Tab,
+        "enter" => Key::Enter,
+        "backspace" => Key::Backspace,
+        "delete" => Key::Delete,
+        "escape" => Key::Escape,
+        "up" => Key::ArrowUp,
+        "down" => Key::ArrowDown,
+        "left" => Key::ArrowLeft,
+        "right" => Key::ArrowRight,
+        "pageup" => Key::PageUp,
+        "pagedown" => Key::PageDown,
+        _ => {
+            let mut chars = name.chars();
+            match (chars.next(), chars.next()) {
+                (Some(ch), None) => Key::Char(ch),
+                _ => bail!("unknown key `{name}`"),
+            }
+        }
+    };
+
+    Ok((key, modifiers))
+}
+
+
+
+/// Approximate text metrics, so that layouts are deterministic without loading
+/// any fonts.
+struct MonospaceFonts;
+
+impl MonospaceFonts {
+    /// The advance of every character, relative to the font size.
+    const ADVANCE: f32 = 0.6;
+
+    /// The most characters that fit on a row, for the given advance and
+    /// wrapping.
+    fn max_chars(char_advance: f32, max_advance: Option<f32>, wrap_mode: TextWrapMode) -> usize {
+        match (max_advance, wrap_mode) {
+            (Some(max_advance), TextWrapMode::Wrap) => {
+                ((max_advance / char_advance).floor() as usize).max(1)
+            }
+            _ => usize::MAX,
+        }
+    }
+
+    /// The byte offset that each row of text starts at, after wrapping, along
+    /// with the offsets of its characters (and the end of the row).
+    fn rows(params: &TextParams<'_>) -> Vec<Vec<usize>> {
+        let char_advance = params.font_size * Self::ADVANCE;
+        let max_chars = Self::max_chars(char_advance, params.max_advance, params.wrap_mode);
+
+        let mut rows = Vec::new();
+        let mut line_start = 0;
+        for line in params.text.split('\n') {
+            let mut offsets = line
+                .char_indices()
+                .map(|(index, _)| line_start + index)
+                .collect::<Vec<_>>();
+            offsets.push(line_start + line.len());
+            while offsets.len() > max_chars.saturating_add(1) {
+                let rest = offsets.split_off(max_chars);
+                let mut row = offsets;
+                row.push(rest[0]);
+                rows.push(row);
+                offsets = rest;
+            }
+            rows.push(offsets);
+            line_start += line.len() + 1;
+        }
+
+        rows
+    }
+}
+
+impl Fonts for MonospaceFonts {
+    fn measure_text(
+        &mut self,
+        _id: u64,
+        text: &str,
+        // NOTE: Every style has the same advance in a monospace font.
+        _spans: &[TextSpan],
+        max_advance: Option<f32>,
+        font_size: f32,
+        line_height: LineHeight,
+        // NOTE: Every family is treated as monospace, so that layouts don't depend on
+        //       which fonts are installed.
+        _font_family: FontFamily,
+        _font_style: FontStyle,
+        _alignment: TextAlignment,
+        wrap_mode: TextWrapMode,
+    ) -> Xy<f32> {
+        let char_advance = font_size * Self::ADVANCE;
+        let line_height = line_height.resolve(font_size);
+        let max_chars = Self::max_chars(char_advance, max_advance, wrap_mode);
+
+        let mut width = 0usize;
+        let mut line_count = 0usize;
+        for line in text.split('\n') {
+            let len = line.chars().count();
+            width = width.max(len.min(max_chars));
+            line_count += len.div_ceil(max_chars).max(1);
+        }
+
+        Xy::new(width as f32 * char_advance, line_count as f32 * line_height)
+    }
+
+    fn cursor_position(&mut self, _id: u64, params: TextParams<'_>, offset: usize) -> Xy<f32> {
+        let char_advance = params.font_size * Self::ADVANCE;
+        let line_height = params.line_height.resolve(params.font_size);
+        let rows = Self::rows(&params);
+
+        // NOTE: An offset at the end of a wrapped row is placed at the start of the
+        //       next one, like the character after it.
+        let row_index = rows.iter().rposition(|row| row[0] <= offset).unwrap_or(0);
+        let column = rows[row_index]
+            .iter()
+            .take_while(|char_offset| **char_offset < offset)
+            .count();
+
+        Xy::new(column as f32 * char_advance, row_index as f32 * line_height)
+    }
+
+    fn hit_test_text(&mut self, _id: u64, params: TextParams<'_>, position: Xy<f32>) -> usize {
+        let char_advance = params.font_size * Self::ADVANCE;
+        let line_height = params.line_height.resolve(params.font_size);
+        let rows = Self::rows(&params);
+
+        let row_index = ((position.y / line_height).max(0.0) as usize).min(rows.len() - 1);
+        let row = &rows[row_index];
+        let column = ((position.x / char_advance).round().max(0.0) as usize).min(row.len() - 1);
+
+        row[column]
+    }
+}
@@ -1,65 +1,1065 @@
 //! # Input Handling
+//!
+//! Input devices are read on a dedicated thread, which translates their events
+//! and sends them to the render loop through an [`InputQueue`]. On outputs that
+//! use the legacy modesetting interface, the hardware cursor is moved from the
+//! input thread as well, so it stays responsive even when a frame takes a long
+//! time to render. Outputs that use atomic commits move it with each frame.
+//!
+//! After each batch of input events, the input thread notifies an [`EventFile`]
+//! so that the render loop wakes up without waiting for its next frame.
+//!
+//! Devices are read as [`InputDevices`], so they can be plugged in at any time.
+//! Mouse motion is accelerated, and touchpads move the pointer, scroll with two
+//! fingers, zoom when pinched, and click when tapped, as set in the shell's
+//! `input.*` settings (see [`InputConfig`]).
+//!
+//! Each finger on a multi-touch touchscreen is sent as a touch (see
+//! [`TouchSlots`]), which egui uses for its own multi-touch gestures, and the
+//! first finger also drives the pointer with the primary button held.
+//!
+//! Pressing `Ctrl+Alt+F1` through `Ctrl+Alt+F12` switches virtual terminals,
+//! since the kernel doesn't read the keyboard while the shell is running (see
+//! [`seat`](crate::seat)).
+//!
+//! Held keys are repeated with a timer, as set by `input.repeat_delay` and
+//! `input.repeat_rate`, rather than with the kernel's own key repeat.
+//!
+//! Keys type whatever the current [`Keymap`](crate::keymap::Keymap) says they
+//! do, including accents from its dead keys.
 
-
-use std::os::fd::AsRawFd as _;
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::{Duration, SystemTime},
+};
 
 use {
     anyhow::Result,
-    linux_uapi::{
-        epoll::{Event, EventPoll},
-        file::File,
-    },
+    crossbeam_queue::ArrayQueue,
+    egui::{Pos2, Rect, Vec2, pos2, vec2},
+    linux_uapi::epoll::{EventFile, TimerFile},
+    log::{error, warn},
 };
 
-use crate::{EventResponse, EventSource, Shell};
+use crate::{
+    EventLoop, GraphicsCard, TimerSource,
+    devices::{DeviceEvent, DeviceInfo, DeviceKind, InputDevices},
+    evdev_keycode_to_egui_key,
+    keymap::{DeadKey, SharedKeymap, Symbol},
+    seat::Session,
+    system,
+};
+
+
+
+/// The maximum number of input messages that can be waiting for the render
+/// loop.
+const INPUT_QUEUE_CAPACITY: usize = 1024;
+
+/// The queue from the input thread (the only producer) to the render loop (the
+/// only consumer).
+pub type InputQueue = ArrayQueue<InputMessage>;
+
+#[derive(Debug)]
+pub enum InputMessage {
+    Event(egui::Event),
+    /// The keyboard modifiers changed.
+    Modifiers(egui::Modifiers),
+}
+
+/// The area of the desktop shown by an output, whose CRTC the hardware cursor
+/// is moved on while the pointer is inside it.
+#[derive(Clone, Copy, Debug)]
+pub struct CursorRegion {
+    pub crtc: drm::control::crtc::Handle,
+    pub rect: Rect,
+    /// Whether the output uses the legacy interface, and so has its cursor
+    /// moved by the input thread. Outputs that use atomic commits move their
+    /// cursor with each frame instead.
+    pub legacy: bool,
+}
+
+/// The cursor regions of every output, which the shell replaces when its
+/// outputs change.
+pub type CursorRegions = Mutex<Vec<CursorRegion>>;
 
+/// The input configuration, which the shell replaces when its settings change.
+pub type SharedInputConfig = Mutex<InputConfig>;
 
+/// Pointer movements faster than this (in device units per millisecond) are
+/// accelerated by [`AccelProfile::Adaptive`].
+const ACCEL_THRESHOLD: f32 = 0.4;
+/// How much faster the pointer moves for each unit per millisecond above
+/// [`ACCEL_THRESHOLD`].
+const ACCEL_INCLINE: f32 = 1.1;
+const MAX_ACCEL: f32 = 3.0;
 
-pub struct InputSource {
-    device: evdev::Device,
+/// How far the pointer moves for each millimeter a finger moves on a touchpad,
+/// before acceleration.
+const TOUCHPAD_PIXELS_PER_MM: f32 = 8.0;
+/// The longest a touch can last to count as a tap.
+const TAP_TIMEOUT: Duration = Duration::from_millis(180);
+/// The furthest a finger can move during a touch for it to count as a tap.
+const TAP_MAX_TRAVEL_MM: f32 = 3.0;
+/// The most key repeats that are sent at once, when the input thread falls
+/// behind the repeat timer.
+const MAX_KEY_REPEATS: u64 = 4;
+
+/// How pointer movements are scaled, see [`InputConfig::accelerate`].
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum AccelProfile {
+    /// Movements are scaled by a constant factor.
+    Flat,
+    /// Fast movements go further than slow ones, so the pointer can cross the
+    /// desktop quickly while staying precise.
+    Adaptive,
 }
 
-impl InputSource {
-    pub fn new(device: evdev::Device) -> Result<Self> {
-        device.set_nonblocking(true)?;
-        Ok(Self { device })
+/// How pointing devices behave, from the shell's `input.*` settings.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct InputConfig {
+    /// Set by `input.acceleration`, as `flat` or `adaptive`.
+    pub acceleration: AccelProfile,
+    /// How fast the pointer moves, from -1 (slowest) to 1 (fastest), set by
+    /// `input.speed`.
+    pub speed: f32,
+    /// Whether scrolling moves the content rather than the view, like dragging
+    /// it on a touchscreen, set by `input.natural_scrolling`.
+    pub natural_scrolling: bool,
+    /// Whether tapping a touchpad clicks, set by `input.tap_to_click`.
+    pub tap_to_click: bool,
+    /// How long a key is held before it starts repeating, set by
+    /// `input.repeat_delay` in milliseconds.
+    pub repeat_delay: Duration,
+    /// How many times a held key repeats each second, or zero if keys don't
+    /// repeat, set by `input.repeat_rate`.
+    pub repeat_rate: u32,
+}
+
+impl Default for InputConfig {
+    fn default() -> Self {
+        Self {
+            acceleration: AccelProfile::Adaptive,
+            speed: 0.0,
+            natural_scrolling: false,
+            tap_to_click: true,
+            repeat_delay: Duration::from_millis(500),
+            repeat_rate: 30,
+        }
     }
 }
 
-impl EventSource<Shell> for InputSource {
-    type Event = evdev::InputEvent;
+impl InputConfig {
+    /// Every setting, with a description of its values.
+    pub const SETTINGS: &[(&str, &str)] = &[
+        ("acceleration", "`flat` or `adaptive`"),
+        ("speed", "from -1 to 1"),
+        ("natural_scrolling", "`true` or `false`"),
+        ("tap_to_click", "`true` or `false`"),
+        ("repeat_delay", "in milliseconds"),
+        ("repeat_rate", "per second, or 0 to disable"),
+    ];
 
-    fn init(&mut self, poll: &EventPoll, key: u64) -> Result<()> {
-        poll.add(
-            &unsafe { File::from_raw(self.device.as_raw_fd()) },
-            Event::new(key, true, false),
-        )?;
+    /// Load the settings over the defaults from the configuration file (see
+    /// [`config`](crate::config)), ignoring (and logging) invalid settings.
+    pub fn load(defaults: Self) -> Self {
+        let mut config = defaults;
+        for (setting, _) in Self::SETTINGS {
+            let Some(value) = system::shell_setting(&format!("input.{setting}")) else {
+                continue;
+            };
+            if let Err(error) = config.set(setting, &value) {
+                warn!("Invalid input setting: {error}");
+            }
+        }
+
+        config
+    }
+
+    /// Change one of the [`SETTINGS`](Self::SETTINGS).
+    pub fn set(&mut self, setting: &str, value: &str) -> Result<(), String> {
+        let invalid = || format!("invalid value for '{setting}': '{value}'");
+        match setting {
+            "acceleration" => {
+                self.acceleration = match value.trim() {
+                    "flat" => AccelProfile::Flat,
+                    "adaptive" => AccelProfile::Adaptive,
+                    _ => return Err(invalid()),
+                };
+            }
+            "speed" => {
+                self.speed = value
+                    .trim()
+                    .parse::<f32>()
+                    .ok()
+                    .filter(|speed| (-1.0..=1.0).contains(speed))
+                    .ok_or_else(invalid)?;
+            }
+            "natural_scrolling" => {
+                self.natural_scrolling = value.trim().parse().map_err(|_| invalid())?;
+            }
+            "tap_to_click" => {
+                self.tap_to_click = value.trim().parse().map_err(|_| invalid())?;
+            }
+            "repeat_delay" => {
+                let millis = value.trim().parse().map_err(|_| invalid())?;
+                self.repeat_delay = Duration::from_millis(millis);
+            }
+            "repeat_rate" => {
+                self.repeat_rate = value
+                    .trim()
+                    .parse()
+                    .ok()
+                    .filter(|rate| *rate <= 1000)
+                    .ok_or_else(invalid)?;
+            }
+            _ => return Err(format!("unknown setting '{setting}'")),
+        }
 
         Ok(())
     }
 
-    fn handle_event<F>(
-        &mut self,
-        shell: &mut Shell,
-        event: Event,
-        mut callback: F,
-    ) -> Result<EventResponse>
-    where
-        F: FnMut(&mut Shell, evdev::InputEvent) -> Result<()>,
-    {
-        if !event.readable() {
-            return Ok(EventResponse::Continue);
+    /// Scale a pointer movement (in device units) that took `elapsed`.
+    pub fn accelerate(&self, delta: Vec2, elapsed: Duration) -> Vec2 {
+        let base = 1.0 + self.speed * 0.75;
+        let factor = match self.acceleration {
+            AccelProfile::Flat => 1.0,
+            AccelProfile::Adaptive => {
+                // Devices report at most once a millisecond, so anything
+                // shorter is from movements queued up together.
+                let millis = (elapsed.as_secs_f32() * 1000.0).max(1.0);
+                let velocity = delta.length() / millis;
+                (1.0 + (velocity - ACCEL_THRESHOLD).max(0.0) * ACCEL_INCLINE).min(MAX_ACCEL)
+            }
+        };
+
+        delta * base * factor
+    }
+
+    /// The scroll delta for a movement of the wheel or fingers, where positive
+    /// values move the view down or right.
+    fn scroll_delta(&self, movement: Vec2) -> Vec2 {
+        // Egui's deltas move the content, which is the opposite of the view.
+        if self.natural_scrolling {
+            movement
+        } else {
+            -movement
         }
+    }
+}
 
-        for event in self.device.fetch_events()? {
-            callback(shell, event)?;
+/// The fingers a multi-touch device is tracking, from its `ABS_MT_*` events.
+#[derive(Debug, Default)]
+struct TouchSlots {
+    /// The slot that `ABS_MT_*` events currently apply to.
+    current: usize,
+    slots: Vec<TouchSlot>,
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+struct TouchSlot {
+    /// The kernel's ID for the finger in this slot, or `None` if the slot is
+    /// empty.
+    tracking_id: Option<i32>,
+    /// The finger's position, in device units.
+    position: Vec2,
+    /// Whether the slot changed since the last report.
+    changed: bool,
+    /// The ID the finger's touch was started with, until it ends.
+    touch_id: Option<u64>,
+}
+
+impl TouchSlots {
+    /// Devices track at most 10 fingers, but report slots up to their maximum.
+    const MAX_SLOTS: usize = 16;
+
+    /// Update the slots with an absolute axis event, returning whether it was
+    /// for one of them.
+    fn handle(&mut self, axis: evdev::AbsoluteAxisCode, value: i32) -> bool {
+        if axis == evdev::AbsoluteAxisCode::ABS_MT_SLOT {
+            self.current = (value.max(0) as usize).min(Self::MAX_SLOTS - 1);
+            return true;
+        }
+        if self.slots.len() <= self.current {
+            self.slots.resize(self.current + 1, TouchSlot::default());
         }
+        let slot = &mut self.slots[self.current];
+        match axis {
+            evdev::AbsoluteAxisCode::ABS_MT_TRACKING_ID => {
+                slot.tracking_id = (value >= 0).then_some(value);
+            }
+            evdev::AbsoluteAxisCode::ABS_MT_POSITION_X => slot.position.x = value as f32,
+            evdev::AbsoluteAxisCode::ABS_MT_POSITION_Y => slot.position.y = value as f32,
+            _ => return false,
+        }
+        slot.changed = true;
+
+        true
+    }
+
+    /// The point between the first two fingers, and the distance between them,
+    /// in device units.
+    fn pinch(&self) -> Option<(Vec2, f32)> {
+        let mut fingers = self
+            .slots
+            .iter()
+            .filter(|slot| slot.tracking_id.is_some())
+            .map(|slot| slot.position);
+        let (a, b) = (fingers.next()?, fingers.next()?);
 
-        Ok(EventResponse::Continue)
+        Some(((a + b) / 2.0, (b - a).length()))
     }
+}
+
+/// A multi-touch touchscreen's state.
+#[derive(Debug, Default)]
+struct Touchscreen {
+    slots: TouchSlots,
+    /// The slot whose finger drives the pointer, if any.
+    pointer_slot: Option<usize>,
+}
+
+/// A touchpad's state, which is updated with each of its events and acted on at
+/// the end of each report (see `SYN_REPORT`).
+#[derive(Debug, Default)]
+struct Touchpad {
+    /// Whether a finger is touching, from `BTN_TOUCH`.
+    touching: bool,
+    /// How many fingers are touching, from `BTN_TOOL_*`.
+    fingers: u8,
+    /// The position reported so far, in device units.
+    position: Vec2,
+    /// The position and number of fingers at the end of the last report, if
+    /// anything was touching then.
+    last: Option<(Vec2, u8)>,
+    /// The current touch, if any.
+    touch: Option<Touch>,
+    slots: TouchSlots,
+    /// The distance between two fingers at the end of the last report, if two
+    /// fingers were touching then.
+    last_pinch: Option<f32>,
+}
+
+#[derive(Debug)]
+struct Touch {
+    start: SystemTime,
+    /// How far the fingers have moved since the touch started, in millimeters.
+    travel: f32,
+    /// The most fingers that have touched at once during the touch.
+    max_fingers: u8,
+}
+
+/// The virtual terminal that a function key switches to with `Ctrl+Alt`.
+fn vt_number(code: evdev::KeyCode) -> Option<u16> {
+    const KEYS: [evdev::KeyCode; 12] = [
+        evdev::KeyCode::KEY_F1,
+        evdev::KeyCode::KEY_F2,
+        evdev::KeyCode::KEY_F3,
+        evdev::KeyCode::KEY_F4,
+        evdev::KeyCode::KEY_F5,
+        evdev::KeyCode::KEY_F6,
+        evdev::KeyCode::KEY_F7,
+        evdev::KeyCode::KEY_F8,
+        evdev::KeyCode::KEY_F9,
+        evdev::KeyCode::KEY_F10,
+        evdev::KeyCode::KEY_F11,
+        evdev::KeyCode::KEY_F12,
+    ];
+
+    KEYS.iter()
+        .position(|key| *key == code)
+        .map(|index| index as u16 + 1)
+}
+
+fn desktop_rect(regions: &[CursorRegion]) -> Rect {
+    regions
+        .iter()
+        .map(|region| region.rect)
+        .reduce(|a, b| a.union(b))
+        .unwrap_or(Rect::ZERO)
+}
+
+/// Start reading every input device on a new thread, returning the queue that
+/// its messages are sent through.
+///
+/// The pointer moves across the whole desktop covered by the given regions,
+/// with the hardware cursor shown on the region it's in. The `wakeup` file is
+/// notified whenever new messages are waiting in the queue.
+pub(crate) fn spawn_input_thread(
+    gpu: GraphicsCard,
+    regions: Arc<CursorRegions>,
+    config: Arc<SharedInputConfig>,
+    keymap: Arc<SharedKeymap>,
+    session: Arc<Session>,
+    wakeup: Arc<EventFile>,
+) -> Result<Arc<InputQueue>> {
+    let queue = Arc::new(ArrayQueue::new(INPUT_QUEUE_CAPACITY));
+    let mut thread = InputThread {
+        gpu,
+        regions,
+        config,
+        keymap,
+        session,
+        cursor_region: 0,
+        queue: Arc::clone(&queue),
+        wakeup,
+        sent_messages: false,
+        mouse_pos: pos2(0.0, 0.0),
+        key_modifiers: egui::Modifiers::NONE,
+        altgr: false,
+        dead_key: None,
+        repeat_timer: Arc::new(TimerFile::create()?),
+        repeating: None,
+        motion: Vec2::ZERO,
+        last_motion: None,
+        touchpads: HashMap::new(),
+        touchscreens: HashMap::new(),
+    };
+
+    std::thread::Builder::new()
+        .name("input".to_string())
+        .spawn(move || {
+            if let Err(error) = run_input_thread(&mut thread) {
+                error!("Input thread stopped: {error}");
+            }
+        })?;
+
+    Ok(queue)
+}
 
-    fn cleanup(&mut self, poll: &EventPoll) -> Result<()> {
-        poll.remove(&unsafe { File::from_raw(self.device.as_raw_fd()) })?;
+/// Read every input device until the event loop stops.
+fn run_input_thread(thread: &mut InputThread) -> Result<()> {
+    let mut event_loop = EventLoop::new()?;
+    let devices = InputDevices::open(Arc::clone(&thread.session))?;
+    event_loop.add_source(devices, |thread: &mut InputThread, event| {
+        match event {
+            DeviceEvent::Input(info, input_event) => {
+                thread.handle_input_event(info, input_event);
+            }
+            DeviceEvent::Removed(info) => {
+                thread.touchpads.remove(&info.id);
+                thread.cancel_touches(info);
+                // Keys held on a keyboard are never released once it's gone.
+                if info.kind == DeviceKind::Keyboard {
+                    thread.stop_key_repeat();
+                    thread.altgr = false;
+                    thread.set_modifiers(egui::Modifiers::NONE);
+                }
+            }
+        }
         Ok(())
+    })?;
+    event_loop.add_source(
+        TimerSource(Arc::clone(&thread.repeat_timer)),
+        |thread: &mut InputThread, expirations| {
+            thread.repeat_key(expirations);
+            Ok(())
+        },
+    )?;
+
+    event_loop.run(thread, -1, |thread| thread.flush())
+}
+
+struct InputThread {
+    gpu: GraphicsCard,
+    regions: Arc<CursorRegions>,
+    /// The index of the region the hardware cursor is shown on.
+    cursor_region: usize,
+    queue: Arc<InputQueue>,
+    wakeup: Arc<EventFile>,
+    /// Whether any messages were sent since the last [`InputThread::flush`].
+    sent_messages: bool,
+    config: Arc<SharedInputConfig>,
+    keymap: Arc<SharedKeymap>,
+    session: Arc<Session>,
+    mouse_pos: Pos2,
+    key_modifiers: egui::Modifiers,
+    /// Whether AltGr is held, see
+    /// [`Keymap::has_altgr`](crate::keymap::Keymap::has_altgr).
+    altgr: bool,
+    /// The dead key that was pressed last, whose accent is added to the next
+    /// character typed.
+    dead_key: Option<DeadKey>,
+    /// Expires each time the held key should repeat.
+    repeat_timer: Arc<TimerFile>,
+    /// The key being held, which is repeated by the `repeat_timer`.
+    repeating: Option<evdev::KeyCode>,
+    /// Relative motion reported since the last `SYN_REPORT`.
+    motion: Vec2,
+    /// When relative motion was last reported, for acceleration.
+    last_motion: Option<SystemTime>,
+    /// The state of each touchpad, by device ID.
+    touchpads: HashMap<usize, Touchpad>,
+    /// The state of each multi-touch touchscreen, by device ID.
+    touchscreens: HashMap<usize, Touchscreen>,
+}
+
+impl InputThread {
+    fn send(&mut self, message: InputMessage) {
+        if let Err(message) = self.queue.push(message) {
+            warn!("Input queue exceeded its capacity, missed 1 message: {message:?}");
+        } else {
+            self.sent_messages = true;
+        }
+    }
+
+    /// Wake up the render loop if any messages were sent since this was last
+    /// called.
+    fn flush(&mut self) {
+        if !core::mem::take(&mut self.sent_messages) {
+            return;
+        }
+        if let Err(error) = self.wakeup.notify() {
+            warn!("Failed to wake up the render loop: {error:?}");
+        }
+    }
+
+    /// The area covered by every region, which the pointer is kept inside.
+    fn desktop(&self) -> Rect {
+        desktop_rect(&self.regions.lock().unwrap())
+    }
+
+    fn move_pointer(&mut self, pos: Pos2, delta: Option<Vec2>) {
+        let regions = self.regions.lock().unwrap().clone();
+        let pos = desktop_rect(&regions).clamp(pos);
+        self.mouse_pos = pos;
+        self.send(InputMessage::Event(egui::Event::PointerMoved(pos)));
+        if let Some(delta) = delta {
+            self.send(InputMessage::Event(egui::Event::MouseMoved(delta)));
+        }
+
+        // Outputs don't have to cover the whole desktop, so the cursor stays on
+        // the last output it was on while it's between them.
+        let Some(index) = regions
+            .iter()
+            .position(|region| region.rect.contains(pos))
+            .or((self.cursor_region < regions.len()).then_some(self.cursor_region))
+        else {
+            return;
+        };
+        if index != self.cursor_region
+            && let Some(previous) = regions.get(self.cursor_region)
+            && previous.legacy
+        {
+            // Hide the cursor on the output it left by moving it off the edge.
+            let hidden = previous.rect.size();
+            #[allow(deprecated)]
+            if let Err(error) = drm::control::Device::move_cursor(
+                &self.gpu,
+                previous.crtc,
+                (hidden.x as _, hidden.y as _),
+            ) {
+                warn!("Failed to move cursor: {error}");
+            }
+        }
+        self.cursor_region = index;
+
+        let region = regions[index];
+        if !region.legacy {
+            return;
+        }
+        let local = pos - region.rect.min;
+        #[allow(deprecated)]
+        if let Err(error) =
+            drm::control::Device::move_cursor(&self.gpu, region.crtc, (local.x as _, local.y as _))
+        {
+            warn!("Failed to move cursor: {error}");
+        }
+    }
+
+    fn press_button(&mut self, button: egui::PointerButton, pressed: bool) {
+        self.send(InputMessage::Event(egui::Event::PointerButton {
+            pos: self.mouse_pos,
+            button,
+            pressed,
+            modifiers: self.key_modifiers,
+        }));
+    }
+
+    fn set_modifiers(&mut self, modifiers: egui::Modifiers) {
+        self.key_modifiers = modifiers;
+        self.send(InputMessage::Modifiers(modifiers));
+    }
+
+    /// Move the pointer by the relative motion reported since the last
+    /// `SYN_REPORT`, which was reported at `time`.
+    fn flush_motion(&mut self, time: SystemTime) {
+        let motion = std::mem::take(&mut self.motion);
+        if motion == Vec2::ZERO {
+            return;
+        }
+        let elapsed = self
+            .last_motion
+            .replace(time)
+            .and_then(|last| time.duration_since(last).ok())
+            .unwrap_or(Duration::from_secs(1));
+
+        let delta = self.config.lock().unwrap().accelerate(motion, elapsed);
+        self.move_pointer(self.mouse_pos + delta, Some(delta));
+    }
+
+    fn scroll(&mut self, unit: egui::MouseWheelUnit, movement: Vec2) {
+        let delta = self.config.lock().unwrap().scroll_delta(movement);
+        self.send(InputMessage::Event(egui::Event::MouseWheel {
+            unit,
+            delta,
+            modifiers: self.key_modifiers,
+        }));
+    }
+
+    /// Track where a touchpad's fingers are, returning whether the event was
+    /// handled. Other events, such as a clickpad's buttons, are handled like
+    /// any other device's.
+    fn handle_touchpad_event(&mut self, info: DeviceInfo, input_event: evdev::InputEvent) -> bool {
+        let touchpad = self.touchpads.entry(info.id).or_default();
+        let value = input_event.value();
+        match input_event.event_type() {
+            evdev::EventType::ABSOLUTE => match evdev::AbsoluteAxisCode(input_event.code()) {
+                evdev::AbsoluteAxisCode::ABS_X => touchpad.position.x = value as f32,
+                evdev::AbsoluteAxisCode::ABS_Y => touchpad.position.y = value as f32,
+                // Each finger's position is only used for pinching.
+                axis => {
+                    touchpad.slots.handle(axis, value);
+                }
+            },
+            evdev::EventType::KEY => {
+                let fingers = match evdev::KeyCode(input_event.code()) {
+                    evdev::KeyCode::BTN_TOUCH => {
+                        touchpad.touching = value != 0;
+                        return true;
+                    }
+                    evdev::KeyCode::BTN_TOOL_FINGER => 1,
+                    evdev::KeyCode::BTN_TOOL_DOUBLETAP => 2,
+                    evdev::KeyCode::BTN_TOOL_TRIPLETAP => 3,
+                    evdev::KeyCode::BTN_TOOL_QUADTAP => 4,
+                    _ => return false,
+                };
+                // The tool for the old number of fingers can be released after
+                // the new one is pressed.
+                if value != 0 {
+                    touchpad.fingers = fingers;
+                } else if touchpad.fingers == fingers {
+                    touchpad.fingers = 0;
+                }
+            }
+            evdev::EventType::SYNCHRONIZATION => {
+                self.touchpad_report(info, input_event.timestamp());
+                return false;
+            }
+            _ => return false,
+        }
+
+        true
+    }
+
+    /// Act on everything a touchpad reported since its last `SYN_REPORT`.
+    fn touchpad_report(&mut self, info: DeviceInfo, time: SystemTime) {
+        let config = *self.config.lock().unwrap();
+        let Some(touchpad) = self.touchpads.get_mut(&info.id) else {
+            return;
+        };
+
+        if !touchpad.touching || touchpad.fingers == 0 {
+            touchpad.last = None;
+            touchpad.last_pinch = None;
+            let Some(touch) = touchpad.touch.take() else {
+                return;
+            };
+            let tapped = config.tap_to_click
+                && touch.travel < TAP_MAX_TRAVEL_MM
+                && time
+                    .duration_since(touch.start)
+                    .is_ok_and(|duration| duration < TAP_TIMEOUT);
+            let button = match touch.max_fingers {
+                1 => egui::PointerButton::Primary,
+                2 => egui::PointerButton::Secondary,
+                3 => egui::PointerButton::Middle,
+                _ => return,
+            };
+            if tapped {
+                self.press_button(button, true);
+                self.press_button(button, false);
+            }
+            return;
+        }
+
+        let touch = touchpad.touch.get_or_insert(Touch {
+            start: time,
+            travel: 0.0,
+            max_fingers: 0,
+        });
+        touch.max_fingers = touch.max_fingers.max(touchpad.fingers);
+        // The reported position jumps when fingers are added or lifted, so it's
+        // only tracked while the same fingers are touching.
+        let last = touchpad.last.replace((touchpad.position, touchpad.fingers));
+        let pinch = touchpad.slots.pinch().filter(|_| touchpad.fingers == 2);
+        let last_pinch = std::mem::replace(
+            &mut touchpad.last_pinch,
+            pinch.map(|(_, distance)| distance),
+        );
+        let Some((last_position, _)) = last.filter(|(_, fingers)| *fingers == touchpad.fingers)
+        else {
+            return;
+        };
+        let millimeters = (touchpad.position - last_position) / info.resolution;
+        touch.travel += millimeters.length();
+        let movement = millimeters * TOUCHPAD_PIXELS_PER_MM;
+
+        match touchpad.fingers {
+            // Moved (and accelerated) like a mouse at the end of the report.
+            1 => self.motion += movement,
+            2 => {
+                self.scroll(egui::MouseWheelUnit::Point, movement);
+                if let (Some(last_distance), Some((_, distance))) = (last_pinch, pinch)
+                    && last_distance > 0.0
+                    && distance > 0.0
+                    && distance != last_distance
+                {
+                    self.send(InputMessage::Event(egui::Event::Zoom(
+                        distance / last_distance,
+                    )));
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Send a key press or release, typing whatever the key types if it's
+    /// pressed.
+    fn press_key(&mut self, code: evdev::KeyCode, pressed: bool, repeat: bool) {
+        let modifiers = self.key_modifiers;
+        let (symbol, key) = {
+            let keymap = self.keymap.lock().unwrap();
+            (
+                keymap.symbol(code, modifiers.shift, self.altgr),
+                keymap.key(code),
+            )
+        };
+        if pressed && let Some(symbol) = symbol {
+            self.type_symbol(symbol);
+        }
+        if let Some(key) = key {
+            self.send(InputMessage::Event(egui::Event::Key {
+                key,
+                physical_key: evdev_keycode_to_egui_key(code),
+                pressed,
+                repeat,
+                modifiers,
+            }));
+        }
+    }
+
+    /// Start repeating a key that was just pressed, after the configured delay.
+    fn start_key_repeat(&mut self, code: evdev::KeyCode) {
+        let config = *self.config.lock().unwrap();
+        if config.repeat_rate == 0 {
+            return;
+        }
+        self.repeating = Some(code);
+        let interval = Duration::from_secs(1) / config.repeat_rate;
+        if let Err(error) = self.repeat_timer.set(config.repeat_delay, Some(interval)) {
+            warn!("Failed to start key repeat: {error:?}");
+        }
+    }
+
+    fn stop_key_repeat(&mut self) {
+        self.repeating = None;
+        if let Err(error) = self.repeat_timer.disarm() {
+            warn!("Failed to stop key repeat: {error:?}");
+        }
+    }
+
+    /// Press the held key again for each time the repeat timer expired.
+    fn repeat_key(&mut self, expirations: u64) {
+        let Some(code) = self.repeating else {
+            return;
+        };
+        for _ in 0..expirations.min(MAX_KEY_REPEATS) {
+            self.press_key(code, true, true);
+        }
+    }
+
+    /// Type what a key's symbol says, or wait for the next one if it's a dead
+    /// key.
+    fn type_symbol(&mut self, symbol: Symbol) {
+        let text = match (self.dead_key.take(), symbol) {
+            (None, Symbol::Char(ch)) => ch.to_string(),
+            (None, Symbol::Dead(dead_key)) => {
+                self.dead_key = Some(dead_key);
+                return;
+            }
+            (Some(dead_key), symbol) => dead_key.apply(symbol),
+        };
+        self.send(InputMessage::Event(egui::Event::Text(text)));
+    }
+
+    /// Track where a touchscreen's fingers are, returning whether the event was
+    /// handled.
+    fn handle_touchscreen_event(
+        &mut self,
+        info: DeviceInfo,
+        input_event: evdev::InputEvent,
+    ) -> bool {
+        let touchscreen = self.touchscreens.entry(info.id).or_default();
+        match input_event.event_type() {
+            // The first finger's position is reported with `ABS_X` and `ABS_Y`
+            // too, which are ignored in favor of its slot.
+            evdev::EventType::ABSOLUTE => {
+                touchscreen.slots.handle(
+                    evdev::AbsoluteAxisCode(input_event.code()),
+                    input_event.value(),
+                );
+                true
+            }
+            // Touching is implied by the slots.
+            evdev::EventType::KEY => {
+                evdev::KeyCode(input_event.code()) == evdev::KeyCode::BTN_TOUCH
+            }
+            evdev::EventType::SYNCHRONIZATION => {
+                if evdev::SynchronizationCode(input_event.code())
+                    == evdev::SynchronizationCode::SYN_REPORT
+                {
+                    self.touchscreen_report(info);
+                }
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// The desktop position of a touchscreen position in device units.
+    fn touch_position(&self, info: DeviceInfo, position: Vec2) -> Pos2 {
+        let fraction = |abs: f32, min: f32, max: f32| {
+            if max > min {
+                (abs - min) / (max - min)
+            } else {
+                0.0
+            }
+        };
+        let desktop = self.desktop();
+
+        desktop.min
+            + desktop.size()
+                * vec2(
+                    fraction(position.x, info.abs_min.x, info.abs_max.x),
+                    fraction(position.y, info.abs_min.y, info.abs_max.y),
+                )
+    }
+
+    /// Send a touch for every finger that changed since the touchscreen's last
+    /// `SYN_REPORT`.
+    fn touchscreen_report(&mut self, info: DeviceInfo) {
+        let Some(touchscreen) = self.touchscreens.get_mut(&info.id) else {
+            return;
+        };
+        let mut touches = Vec::new();
+        for (index, slot) in touchscreen.slots.slots.iter_mut().enumerate() {
+            if !std::mem::take(&mut slot.changed) {
+                continue;
+            }
+            let (id, phase) = match (slot.tracking_id, slot.touch_id) {
+                (Some(tracking_id), None) => {
+                    let id = tracking_id as u64;
+                    slot.touch_id = Some(id);
+                    (id, egui::TouchPhase::Start)
+                }
+                (Some(_), Some(id)) => (id, egui::TouchPhase::Move),
+                (None, Some(id)) => {
+                    slot.touch_id = None;
+                    (id, egui::TouchPhase::End)
+                }
+                (None, None) => continue,
+            };
+            touches.push((index, id, phase, slot.position));
+        }
+
+        for (index, id, phase, position) in touches {
+            self.send_touch(info, index, id, phase, position);
+        }
+    }
+
+    /// Send a touch to egui, and move the pointer with the first finger to
+    /// touch while no other finger is.
+    fn send_touch(
+        &mut self,
+        info: DeviceInfo,
+        slot: usize,
+        id: u64,
+        phase: egui::TouchPhase,
+        position: Vec2,
+    ) {
+        let pos = self.touch_position(info, position);
+        self.send(InputMessage::Event(egui::Event::Touch {
+            device_id: egui::TouchDeviceId(info.id as u64),
+            id: egui::TouchId(id),
+            phase,
+            pos,
+            force: None,
+        }));
+
+        let Some(touchscreen) = self.touchscreens.get_mut(&info.id) else {
+            return;
+        };
+        let touching = touchscreen
+            .slots
+            .slots
+            .iter()
+            .filter(|slot| slot.touch_id.is_some())
+            .count();
+        let pointer_slot = touchscreen.pointer_slot;
+        match phase {
+            egui::TouchPhase::Start if touching == 1 => {
+                touchscreen.pointer_slot = Some(slot);
+                self.move_pointer(pos, None);
+                self.press_button(egui::PointerButton::Primary, true);
+            }
+            // The pointer lets go once a second finger touches, so multi-touch
+            // gestures don't drag whatever is under it.
+            egui::TouchPhase::Start => {
+                if touchscreen.pointer_slot.take().is_some() {
+                    self.press_button(egui::PointerButton::Primary, false);
+                }
+            }
+            egui::TouchPhase::Move if pointer_slot == Some(slot) => {
+                self.move_pointer(pos, None);
+            }
+            egui::TouchPhase::End | egui::TouchPhase::Cancel if pointer_slot == Some(slot) => {
+                touchscreen.pointer_slot = None;
+                self.move_pointer(pos, None);
+                self.press_button(egui::PointerButton::Primary, false);
+            }
+            _ => {}
+        }
+    }
+
+    /// Cancel every touch on a touchscreen that was unplugged.
+    fn cancel_touches(&mut self, info: DeviceInfo) {
+        let Some(touchscreen) = self.touchscreens.get(&info.id) else {
+            return;
+        };
+        let touches = touchscreen
+            .slots
+            .slots
+            .iter()
+            .enumerate()
+            .filter_map(|(index, slot)| Some((index, slot.touch_id?, slot.position)))
+            .collect::<Vec<_>>();
+        for (index, id, position) in touches {
+            self.send_touch(info, index, id, egui::TouchPhase::Cancel, position);
+        }
+        self.touchscreens.remove(&info.id);
+    }
+
+    fn handle_input_event(&mut self, info: DeviceInfo, input_event: evdev::InputEvent) {
+        if info.kind == DeviceKind::Touchpad && self.handle_touchpad_event(info, input_event) {
+            return;
+        }
+        if info.kind == DeviceKind::Touchscreen && self.handle_touchscreen_event(info, input_event)
+        {
+            return;
+        }
+
+        match input_event.event_type() {
+            evdev::EventType::SYNCHRONIZATION => {
+                if evdev::SynchronizationCode(input_event.code())
+                    == evdev::SynchronizationCode::SYN_REPORT
+                {
+                    self.flush_motion(input_event.timestamp());
+                }
+            }
+            evdev::EventType::ABSOLUTE => {
+                let abs = input_event.value() as f32;
+                let fraction = |min: f32, max: f32| {
+                    if max > min {
+                        (abs - min) / (max - min)
+                    } else {
+                        0.0
+                    }
+                };
+                match evdev::AbsoluteAxisCode(input_event.code()) {
+                    evdev::AbsoluteAxisCode::ABS_X => {
+                        let desktop = self.desktop();
+                        let x = desktop.min.x
+                            + desktop.width() * fraction(info.abs_min.x, info.abs_max.x);
+                        self.move_pointer(pos2(x, self.mouse_pos.y), None);
+                    }
+                    evdev::AbsoluteAxisCode::ABS_Y => {
+                        let desktop = self.desktop();
+                        let y = desktop.min.y
+                            + desktop.height() * fraction(info.abs_min.y, info.abs_max.y);
+                        self.move_pointer(pos2(self.mouse_pos.x, y), None);
+                    }
+                    _ => {}
+                }
+            }
+            evdev::EventType::RELATIVE => {
+                let movement = input_event.value() as f32;
+                match evdev::RelativeAxisCode(input_event.code()) {
+                    evdev::RelativeAxisCode::REL_X => self.motion.x += movement,
+                    evdev::RelativeAxisCode::REL_Y => self.motion.y += movement,
+                    // The wheel reports positive values when it's rolled away,
+                    // which scrolls up.
+                    evdev::RelativeAxisCode::REL_WHEEL => {
+                        self.scroll(egui::MouseWheelUnit::Line, vec2(0.0, -movement));
+                    }
+                    evdev::RelativeAxisCode::REL_HWHEEL => {
+                        self.scroll(egui::MouseWheelUnit::Line, vec2(movement, 0.0));
+                    }
+                    _ => {}
+                }
+            }
+            evdev::EventType::KEY => {
+                // The kernel's key repeats are ignored in favor of the shell's.
+                if input_event.value() == 2 {
+                    return;
+                }
+                let pressed = input_event.value() == 1;
+                let mut modifiers = self.key_modifiers;
+                match evdev::KeyCode(input_event.code()) {
+                    evdev::KeyCode::BTN_LEFT => {
+                        self.press_button(egui::PointerButton::Primary, pressed);
+                    }
+                    evdev::KeyCode::BTN_RIGHT => {
+                        self.press_button(egui::PointerButton::Secondary, pressed);
+                    }
+
+                    evdev::KeyCode::KEY_LEFTCTRL | evdev::KeyCode::KEY_RIGHTCTRL => {
+                        modifiers.ctrl = pressed;
+                        modifiers.command = pressed;
+                        self.set_modifiers(modifiers);
+                    }
+                    evdev::KeyCode::KEY_LEFTSHIFT | evdev::KeyCode::KEY_RIGHTSHIFT => {
+                        modifiers.shift = pressed;
+                        self.set_modifiers(modifiers);
+                    }
+                    evdev::KeyCode::KEY_RIGHTALT if self.keymap.lock().unwrap().has_altgr() => {
+                        self.altgr = pressed;
+                    }
+                    evdev::KeyCode::KEY_LEFTALT | evdev::KeyCode::KEY_RIGHTALT => {
+                        modifiers.alt = pressed;
+                        self.set_modifiers(modifiers);
+                    }
+
+                    other => {
+                        if pressed
+                            && modifiers.ctrl
+                            && modifiers.alt
+                            && let Some(number) = vt_number(other)
+                        {
+                            self.session.switch_vt(number);
+                            return;
+                        }
+                        self.press_key(other, pressed, false);
+                        if pressed {
+                            self.start_key_repeat(other);
+                        } else if self.repeating == Some(other) {
+                            self.stop_key_repeat();
+                        }
+                    }
+                }
+            }
+            _ => {}
+        }
     }
 }
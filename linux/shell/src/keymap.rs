@@ -0,0 +1,261 @@
+//! # Keyboard Layouts
+//!
+//! A [`Keymap`] maps each key to the characters it types, so that keyboards
+//! with layouts other than US English type what's printed on their keys. The
+//! layout is set by the shell's `keyboard.layout` setting, and can be changed
+//! at any time with the `layout` command.
+//!
+//! Keymaps are written in a minimal format, with one key on each line:
+//!
+//! ```text
+//! # German
+//!
+//! include us
+//!
+//! KEY_Q           q       Q       @
+//! KEY_EQUAL       dead_acute dead_grave
+//! ```
+//!
+//! Each line names an evdev key, followed by what it types on its own, with
+//! Shift, with AltGr, and with both. Later levels can be left out. Each symbol
+//! is either a single character, `U+` followed by a character's hex code,
+//! `space`, `none`, or a dead key (see [`DeadKey`]), which changes the next
+//! character typed instead of typing one itself. Lines starting with `#` are
+//! comments, and `include` copies one of the built-in [`LAYOUTS`] before the
+//! lines after it change any of its keys.
+
+use std::{collections::HashMap, sync::Mutex};
+
+use log::warn;
+
+use crate::{evdev_keycode_to_egui_key, system};
+
+
+
+/// The layouts built into the shell, by name.
+pub const LAYOUTS: &[(&str, &str)] = &[
+    ("us", include_str!("../../../res/keymaps/us.keymap")),
+    ("gb", include_str!("../../../res/keymaps/gb.keymap")),
+    ("de", include_str!("../../../res/keymaps/de.keymap")),
+    ("fr", include_str!("../../../res/keymaps/fr.keymap")),
+];
+
+/// The layout that's used when none is set, or the one that is can't be
+/// loaded.
+const DEFAULT_LAYOUT: &str = "us";
+
+/// The current keymap, which the shell replaces when its layout changes.
+pub type SharedKeymap = Mutex<Keymap>;
+
+#[derive(Clone, Debug)]
+pub struct Keymap {
+    /// The name the keymap was loaded with, see [`Keymap::named`].
+    name: String,
+    /// The symbols typed by each key (by evdev code), for each combination of
+    /// Shift and AltGr (see [`Keymap::symbol`]).
+    keys: HashMap<u16, [Option<Symbol>; 4]>,
+}
+
+/// What a key types.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Symbol {
+    Char(char),
+    Dead(DeadKey),
+}
+
+/// An accent that's combined with the next character typed.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum DeadKey {
+    /// `dead_grave`, as in "à".
+    Grave,
+    /// `dead_acute`, as in "á".
+    Acute,
+    /// `dead_circumflex`, as in "â".
+    Circumflex,
+    /// `dead_tilde`, as in "ã".
+    Tilde,
+    /// `dead_diaeresis`, as in "ä".
+    Diaeresis,
+    /// `dead_cedilla`, as in "ç".
+    Cedilla,
+    /// `dead_ring`, as in "å".
+    Ring,
+}
+
+impl Keymap {
+    /// Load the keymap set by `keyboard.layout`, falling back to the default
+    /// (and logging why) if it can't be loaded.
+    pub fn load() -> Self {
+        let layout = system::shell_setting("keyboard.layout");
+        match Self::named(layout.as_deref().unwrap_or(DEFAULT_LAYOUT)) {
+            Ok(keymap) => keymap,
+            Err(error) => {
+                warn!("Invalid keyboard layout: {error}");
+                Self::named(DEFAULT_LAYOUT).unwrap()
+            }
+        }
+    }
+
+    /// Load one of the built-in [`LAYOUTS`], or a keymap file if the name is a
+    /// path.
+    pub fn named(name: &str) -> Result<Self, String> {
+        let source = if name.contains('/') {
+            std::fs::read_to_string(name).map_err(|error| format!("'{name}': {error}"))?
+        } else {
+            builtin(name)?.to_string()
+        };
+        let mut keymap = Self {
+            name: name.to_string(),
+            keys: HashMap::new(),
+        };
+        keymap
+            .parse(&source)
+            .map_err(|error| format!("'{name}': {error}"))?;
+
+        Ok(keymap)
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Whether any key types something with AltGr, in which case the right Alt
+    /// key acts as AltGr instead of Alt.
+    pub fn has_altgr(&self) -> bool {
+        self.keys
+            .values()
+            .any(|levels| levels[2].is_some() || levels[3].is_some())
+    }
+
+    /// What a key types with the given modifiers.
+    pub fn symbol(&self, code: evdev::KeyCode, shift: bool, altgr: bool) -> Option<Symbol> {
+        self.keys.get(&code.code())?[shift as usize + 2 * altgr as usize]
+    }
+
+    /// The key that a key acts as for shortcuts, which is the one printed on
+    /// it, or its US English equivalent if that isn't one egui knows about.
+    pub fn key(&self, code: evdev::KeyCode) -> Option<egui::Key> {
+        match self.symbol(code, false, false) {
+            Some(Symbol::Char(ch)) => {
+                egui::Key::from_name(&ch.to_string()).or_else(|| evdev_keycode_to_egui_key(code))
+            }
+            _ => evdev_keycode_to_egui_key(code),
+        }
+    }
+
+    fn parse(&mut self, source: &str) -> Result<(), String> {
+        for (index, line) in source.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let error = |message: String| format!("line {}: {message}", index + 1);
+            let mut words = line.split_whitespace();
+            let first = words.next().unwrap();
+            if first == "include" {
+                let name = words
+                    .next()
+                    .ok_or_else(|| error("missing layout to include".to_string()))?;
+                self.parse(builtin(name).map_err(error)?)?;
+                continue;
+            }
+
+            let code = first
+                .parse::<evdev::KeyCode>()
+                .map_err(|_| error(format!("unknown key '{first}'")))?;
+            let mut levels = [None; 4];
+            for (level, word) in words.enumerate() {
+                *levels
+                    .get_mut(level)
+                    .ok_or_else(|| error(format!("too many symbols for '{first}'")))? =
+                    parse_symbol(word).map_err(error)?;
+            }
+            self.keys.insert(code.code(), levels);
+        }
+
+        Ok(())
+    }
+}
+
+fn builtin(name: &str) -> Result<&'static str, String> {
+    LAYOUTS
+        .iter()
+        .find(|(layout, _)| *layout == name)
+        .map(|(_, source)| *source)
+        .ok_or_else(|| format!("unknown layout '{name}'"))
+}
+
+fn parse_symbol(word: &str) -> Result<Option<Symbol>, String> {
+    let dead = match word {
+        "none" => return Ok(None),
+        "space" => return Ok(Some(Symbol::Char(' '))),
+        "dead_grave" => DeadKey::Grave,
+        "dead_acute" => DeadKey::Acute,
+        "dead_circumflex" => DeadKey::Circumflex,
+        "dead_tilde" => DeadKey::Tilde,
+        "dead_diaeresis" => DeadKey::Diaeresis,
+        "dead_cedilla" => DeadKey::Cedilla,
+        "dead_ring" => DeadKey::Ring,
+        _ => {
+            if let Some(hex) = word.strip_prefix("U+") {
+                return u32::from_str_radix(hex, 16)
+                    .ok()
+                    .and_then(char::from_u32)
+                    .map(|ch| Some(Symbol::Char(ch)))
+                    .ok_or_else(|| format!("invalid character code '{word}'"));
+            }
+            let mut chars = word.chars();
+            return match (chars.next(), chars.next()) {
+                (Some(ch), None) => Ok(Some(Symbol::Char(ch))),
+                _ => Err(format!("unknown symbol '{word}'")),
+            };
+        }
+    };
+
+    Ok(Some(Symbol::Dead(dead)))
+}
+
+impl DeadKey {
+    /// The accent on its own, which is typed when the dead key is followed by a
+    /// space or itself.
+    pub fn spacing(self) -> char {
+        match self {
+            Self::Grave => '`',
+            Self::Acute => '´',
+            Self::Circumflex => '^',
+            Self::Tilde => '~',
+            Self::Diaeresis => '¨',
+            Self::Cedilla => '¸',
+            Self::Ring => '°',
+        }
+    }
+
+    /// The character with this accent, if there is one.
+    pub fn compose(self, base: char) -> Option<char> {
+        let (bases, composed) = match self {
+            Self::Grave => ("aeiouAEIOU", "àèìòùÀÈÌÒÙ"),
+            Self::Acute => ("aeiouycnszAEIOUYCNSZ", "áéíóúýćńśźÁÉÍÓÚÝĆŃŚŹ"),
+            Self::Circumflex => ("aeiouAEIOU", "âêîôûÂÊÎÔÛ"),
+            Self::Tilde => ("anoANO", "ãñõÃÑÕ"),
+            Self::Diaeresis => ("aeiouyAEIOUY", "äëïöüÿÄËÏÖÜŸ"),
+            Self::Cedilla => ("cCsS", "çÇşŞ"),
+            Self::Ring => ("auAU", "åůÅŮ"),
+        };
+        let index = bases.chars().position(|ch| ch == base)?;
+
+        composed.chars().nth(index)
+    }
+
+    /// What's typed when the dead key is followed by `next`.
+    pub fn apply(self, next: Symbol) -> String {
+        match next {
+            Symbol::Char(' ') => self.spacing().to_string(),
+            Symbol::Char(ch) => match self.compose(ch) {
+                Some(composed) => composed.to_string(),
+                None => format!("{}{ch}", self.spacing()),
+            },
+            Symbol::Dead(dead) if dead == self => self.spacing().to_string(),
+            Symbol::Dead(dead) => format!("{}{}", self.spacing(), dead.spacing()),
+        }
+    }
+}
@@ -1,7 +1,70 @@
 //! # Logging
+//!
+//! Everything that's logged (by the shell, or by applications through
+//! `shell::debug` and friends) is printed, and kept in a buffer of the most
+//! recent entries that can be inspected in the log viewer (see the `log`
+//! command). Each target's level can be changed while the shell is running,
+//! and is saved to the `log.levels` setting.
 
-use anyhow::Result;
-use log::{Level, LevelFilter, Log};
+use std::{
+    collections::VecDeque,
+    fmt,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use {
+    anyhow::Result,
+    log::{Level, LevelFilter, Log},
+};
+
+use crate::system;
+
+
+
+/// The number of entries that are kept, see [`entries`].
+pub const BUFFER_CAPACITY: usize = 2000;
+
+static STATE: Mutex<State> = Mutex::new(State {
+    filters: Filters {
+        default_level: LevelFilter::Trace,
+        targets: Vec::new(),
+    },
+    entries: VecDeque::new(),
+    printing: true,
+    start: None,
+});
+
+struct State {
+    filters: Filters,
+    /// The most recent entries, oldest first.
+    entries: VecDeque<Entry>,
+    /// Whether entries are printed as well as kept, see [`set_printing`].
+    printing: bool,
+    /// When the logger was initialized, which entries are timed from.
+    start: Option<Instant>,
+}
+
+#[derive(Clone, Debug)]
+pub struct Entry {
+    /// The time since the shell started.
+    pub time: Duration,
+    pub level: Level,
+    pub target: String,
+    pub message: String,
+}
+
+/// The level that each target is logged at.
+///
+/// Written like `info,abi=debug,shell::input=off`, where the bare level is the
+/// default for targets that aren't listed.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Filters {
+    pub default_level: LevelFilter,
+    /// Ordered from the longest target to the shortest, so that the most
+    /// specific target that an entry's target starts with is used.
+    pub targets: Vec<(String, LevelFilter)>,
+}
 
 pub struct Logger {
     pub use_stderr: bool,
@@ -39,21 +102,23 @@ impl Logger {
             .unwrap_or(self.default_level)
     }
 
-    pub fn init(self) -> Result<()> {
+    pub fn init(mut self) -> Result<()> {
         log::set_max_level(self.max_level());
+        {
+            let mut state = STATE.lock().unwrap();
+            state.filters = Filters {
+                default_level: self.default_level,
+                targets: std::mem::take(&mut self.targets),
+            };
+            state.start = Some(Instant::now());
+        }
         Ok(log::set_boxed_logger(Box::new(self))?)
     }
 }
 
 impl Log for Logger {
     fn enabled(&self, metadata: &log::Metadata) -> bool {
-        &metadata.level().to_level_filter()
-            <= self
-                .targets
-                .iter()
-                .find(|(name, _level)| metadata.target().starts_with(name))
-                .map(|(_name, level)| level)
-                .unwrap_or(&self.default_level)
+        metadata.level() <= STATE.lock().unwrap().filters.level(metadata.target())
     }
 
     fn log(&self, record: &log::Record) {
@@ -61,6 +126,27 @@ impl Log for Logger {
             return;
         }
 
+        // NOTE: The message is formatted before the buffer is locked, in case
+        //       formatting it logs something else.
+        let message = record.args().to_string();
+        let printing = {
+            let mut state = STATE.lock().unwrap();
+            if state.entries.len() == BUFFER_CAPACITY {
+                state.entries.pop_front();
+            }
+            let entry = Entry {
+                time: state.start.map(|start| start.elapsed()).unwrap_or_default(),
+                level: record.level(),
+                target: record.target().to_string(),
+                message: message.clone(),
+            };
+            state.entries.push_back(entry);
+            state.printing
+        };
+        if !printing {
+            return;
+        }
+
         let level_color_code = match record.level() {
             Level::Error => 31, // ANSI SGR "red"
             Level::Warn => 33,  // ANSI SGR "yellow"
@@ -85,7 +171,7 @@ impl Log for Logger {
             } else {
                 0 // ANSI SGR "reset"
             },
-            record.args(),
+            message,
         );
 
         if self.use_stderr {
@@ -97,3 +183,231 @@ impl Log for Logger {
 
     fn flush(&self) {}
 }
+
+impl Filters {
+    /// The level that entries with the given target are logged at.
+    pub fn level(&self, target: &str) -> LevelFilter {
+        self.targets
+            .iter()
+            .find(|(name, _level)| target.starts_with(name.as_str()))
+            .map_or(self.default_level, |(_name, level)| *level)
+    }
+
+    /// The most verbose level that any target is logged at.
+    pub fn max_level(&self) -> LevelFilter {
+        self.targets
+            .iter()
+            .map(|(_name, level)| *level)
+            .fold(self.default_level, Ord::max)
+    }
+
+    /// Set the level of a target, or go back to the default level if `level`
+    /// is `None`.
+    pub fn set(&mut self, target: &str, level: Option<LevelFilter>) {
+        self.targets.retain(|(name, _level)| name != target);
+        if let Some(level) = level {
+            self.targets.push((target.to_string(), level));
+            self.targets
+                .sort_by_key(|(name, _level)| name.len().wrapping_neg());
+        }
+    }
+
+    pub fn parse(source: &str) -> Option<Self> {
+        let mut filters = Filters {
+            default_level: LevelFilter::Trace,
+            targets: Vec::new(),
+        };
+        for directive in source.split(',').map(str::trim) {
+            if directive.is_empty() {
+                continue;
+            }
+            match directive.split_once('=') {
+                Some((target, level)) => {
+                    filters.set(target.trim(), Some(level.trim().parse().ok()?))
+                }
+                None => filters.default_level = directive.parse().ok()?,
+            }
+        }
+
+        Some(filters)
+    }
+}
+
+impl fmt::Display for Filters {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.default_level.as_str().to_lowercase())?;
+        for (target, level) in &self.targets {
+            write!(f, ",{target}={}", level.as_str().to_lowercase())?;
+        }
+
+        Ok(())
+    }
+}
+
+
+
+/// Load the levels and whether entries are printed from the `log.levels` and
+/// `log.print` settings.
+pub fn load_settings() {
+    let filters = system::shell_setting("log.levels").and_then(|source| {
+        let filters = Filters::parse(&source);
+        if filters.is_none() {
+            log::warn!("Invalid log levels '{source}'");
+        }
+        filters
+    });
+    let printing = system::shell_setting("log.print").is_none_or(|value| value != "off");
+
+    let mut state = STATE.lock().unwrap();
+    if let Some(filters) = filters {
+        log::set_max_level(filters.max_level());
+        state.filters = filters;
+    }
+    state.printing = printing;
+}
+
+pub fn filters() -> Filters {
+    STATE.lock().unwrap().filters.clone()
+}
+
+/// Change the level that each target is logged at, saving it to `log.levels`.
+pub fn update_filters(f: impl FnOnce(&mut Filters)) {
+    let source = {
+        let mut state = STATE.lock().unwrap();
+        f(&mut state.filters);
+        log::set_max_level(state.filters.max_level());
+        state.filters.to_string()
+    };
+    system::set_shell_setting("log.levels", &source);
+}
+
+/// A copy of the entries in the buffer, oldest first.
+pub fn entries() -> Vec<Entry> {
+    STATE.lock().unwrap().entries.iter().cloned().collect()
+}
+
+pub fn clear() {
+    STATE.lock().unwrap().entries.clear();
+}
+
+pub fn printing() -> bool {
+    STATE.lock().unwrap().printing
+}
+
+/// Set whether entries are printed to the terminal as well as being kept,
+/// saving it to `log.print`. Entries are still kept when they aren't printed.
+pub fn set_printing(printing: bool) {
+    STATE.lock().unwrap().printing = printing;
+    system::set_shell_setting("log.print", if printing { "on" } else { "off" });
+}
+
+
+
+/// The filters of the log viewer, which only shows entries at or above a level
+/// whose target contains some text.
+pub struct Viewer {
+    pub open: bool,
+    level: LevelFilter,
+    target: String,
+}
+
+impl Default for Viewer {
+    fn default() -> Self {
+        Self {
+            open: false,
+            level: LevelFilter::Trace,
+            target: String::new(),
+        }
+    }
+}
+
+/// Show the log viewer in a window, if it's open.
+pub fn show_viewer(ctx: &egui::Context, viewer: &mut Viewer) {
+    let Viewer {
+        open,
+        level,
+        target,
+    } = viewer;
+    egui::Window::new("Log")
+        .open(open)
+        .default_size([640.0, 320.0])
+        .show(ctx, |ui| {
+            ui.horizontal(|ui| {
+                egui::ComboBox::from_id_salt("log-level")
+                    .selected_text(level.as_str())
+                    .show_ui(ui, |ui| {
+                        for option in LevelFilter::iter() {
+                            ui.selectable_value(level, option, option.as_str());
+                        }
+                    });
+                ui.add(
+                    egui::TextEdit::singleline(target)
+                        .hint_text("Target")
+                        .desired_width(160.0),
+                );
+                if ui.button("Clear").clicked() {
+                    clear();
+                }
+            });
+            ui.separator();
+
+            let entries = entries()
+                .into_iter()
+                .filter(|entry| entry.level <= *level && entry.target.contains(target.as_str()))
+                .collect::<Vec<_>>();
+            let row_height = ui.text_style_height(&egui::TextStyle::Monospace);
+            egui::ScrollArea::vertical()
+                .auto_shrink([false, false])
+                .stick_to_bottom(true)
+                .show_rows(ui, row_height, entries.len(), |ui, rows| {
+                    for entry in &entries[rows] {
+                        let color = match entry.level {
+                            Level::Error => egui::Color32::from_rgb(0xe0, 0x50, 0x50),
+                            Level::Warn => egui::Color32::from_rgb(0xe0, 0xc0, 0x50),
+                            Level::Info => ui.visuals().text_color(),
+                            Level::Debug => egui::Color32::from_rgb(0x70, 0x90, 0xe0),
+                            Level::Trace => ui.visuals().weak_text_color(),
+                        };
+                        let text = format!(
+                            "{:>9.3} {:<5} [{}] {}",
+                            entry.time.as_secs_f32(),
+                            entry.level.as_str(),
+                            entry.target,
+                            entry.message,
+                        );
+                        ui.label(egui::RichText::new(text).monospace().color(color));
+                    }
+                });
+        });
+}
+
+
+
+fn log_app(level: Level, text: &str) {
+    let target = system::current_app().map_or("shell", |manifest| manifest.name);
+    log::log!(target: target, level, "{text}");
+}
+
+abi::declare! {
+    mod shell {
+        fn error(text: &str) {
+            log_app(Level::Error, text)
+        }
+
+        fn warn(text: &str) {
+            log_app(Level::Warn, text)
+        }
+
+        fn info(text: &str) {
+            log_app(Level::Info, text)
+        }
+
+        fn debug(text: &str) {
+            log_app(Level::Debug, text)
+        }
+
+        fn trace(text: &str) {
+            log_app(Level::Trace, text)
+        }
+    }
+}
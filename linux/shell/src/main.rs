@@ -9,22 +9,34 @@ extern crate rustc_session as session;
 extern crate rustc_span as span;
 extern crate rustc_target;
 
+pub mod capture;
+pub mod command;
 pub mod compiler;
+pub mod config;
 pub mod cursor;
+pub mod devices;
 pub mod egl;
+pub mod headless;
 pub mod input;
+pub mod keymap;
 pub mod log;
+pub mod output;
+pub mod perf;
+pub mod program;
+pub mod render;
+pub mod seat;
+pub mod system;
 
 use std::{
-    collections::HashMap,
-    ffi::OsString,
+    collections::{BTreeMap, HashMap},
+    ffi::CString,
     io::{BufRead as _, Read as _, Write as _},
     num::NonZeroU32,
     os::fd::AsRawFd as _,
+    path::PathBuf,
     ptr::NonNull,
-    str::FromStr as _,
     sync::Arc,
-    time::Instant,
+    time::{Duration, Instant},
 };
 
 use {
@@ -32,7 +44,7 @@ use {
     abi::*,
     anyhow::{Context as _, Result, bail},
     drm::{Device, control::Device as ControlDevice},
-    egui::{Pos2, Rect, pos2, vec2},
+    egui::Pos2,
     gbm::AsRaw as _,
     glow::HasContext as _,
     glutin::{
@@ -42,13 +54,18 @@ use {
         surface::GlSurface as _,
     },
     linux_uapi::{
-        epoll::{Event, EventPoll},
+        epoll::{Event, EventFile, EventPoll, Interest, TimerFile, Trigger},
         file::File,
         object::Object,
+        proc::{WaitStatus, wait_for_children_once},
+        signal::{Signal, SignalFile, SignalInfo, SignalMask},
+        traits::AsFile as _,
+        uevent::{Uevent, UeventSocket},
+        watch::Watcher,
     },
 };
 
-use crate::cursor::CursorData;
+use crate::{cursor::CursorData, output::Output};
 
 
 
@@ -57,15 +74,49 @@ fn main() -> Result<()> {
 
     log::Logger::default().init()?;
 
-    run_abi_tests().context("failed to run ABI tests")?;
+    if let Some(options) = headless::HeadlessOptions::parse(std::env::args().skip(1))? {
+        return headless::run(options);
+    }
+    if headless::decode_render(std::env::args().skip(1))? {
+        return Ok(());
+    }
+
+    let shell_config = config::Config::load();
+
+    run_abi_tests(&shell_config.library_dir).context("failed to run ABI tests")?;
+
+    if let Some(home) = std::env::var_os("HOME") {
+        let settings_path = PathBuf::from(home).join(".config/shell/settings");
+        if let Err(error) = system::load_settings(settings_path) {
+            warn!("Failed to load settings: {error}");
+        }
+    }
+    log::load_settings();
 
     info!("Starting shell...");
 
+    // Handled signals are read from the event loop, so they must be blocked
+    // before any threads are spawned for the mask to be inherited by all of
+    // them.
+    let handled_signals = SignalMask::from_signals(&[
+        Signal::CHLD,
+        Signal::TERM,
+        Signal::INT,
+        seat::RELEASE_SIGNAL,
+        seat::ACQUIRE_SIGNAL,
+    ]);
+    handled_signals.block()?;
+
+    let session = Arc::new(seat::Session::open()?);
+    let card_path = session.find_card()?;
+    let card_path = card_path.to_str().context("invalid graphics card path")?;
+
     std::thread::sleep(std::time::Duration::from_secs(1));
 
     let egui_context = egui::Context::default();
+    egl::apply_style(&egui_context, &shell_config.colors);
 
-    let gpu = GraphicsCard::open("/dev/dri/card0")?;
+    let gpu = GraphicsCard::open(card_path)?;
 
     let display = unsafe {
         glutin::api::egl::display::Display::new(raw_window_handle::RawDisplayHandle::Gbm(
@@ -100,20 +151,6 @@ fn main() -> Result<()> {
     })
     .expect("no available GL configs");
 
-    let context_attributes = glutin::context::ContextAttributesBuilder::new().build(None);
-    let fallback_context_attributes = glutin::context::ContextAttributesBuilder::new()
-        .with_context_api(glutin::context::ContextApi::Gles(None))
-        .build(None);
-    let context = unsafe {
-        display
-            .create_context(&config, &context_attributes)
-            .unwrap_or_else(|_| {
-                display
-                    .create_context(&config, &fallback_context_attributes)
-                    .expect("failed to create context")
-            })
-    };
-
     trace!(target: "gpu", "Setting DRM client capabilities...");
 
     gpu.set_client_capability(drm::ClientCapability::UniversalPlanes, true)
@@ -125,8 +162,8 @@ fn main() -> Result<()> {
 
     trace!(target: "gpu", "Preparing outputs...");
 
-    let output = match gpu.prepare_output(&config, context, egui_context.clone()) {
-        Ok(output) => output,
+    let outputs = match gpu.prepare_outputs(&display, &config, egui_context.clone()) {
+        Ok(outputs) => outputs,
         Err(error) => {
             bail!(
                 "\x1b[31mERROR\x1b[0m \x1b[2m(shell)\x1b[0m: \
@@ -135,75 +172,7 @@ fn main() -> Result<()> {
         }
     };
 
-    let cursor_width = gpu
-        .get_driver_capability(drm::DriverCapability::CursorWidth)
-        .unwrap_or(64);
-    let cursor_height = gpu
-        .get_driver_capability(drm::DriverCapability::CursorHeight)
-        .unwrap_or(64);
-    let cursor_hotspot;
-    let mut cursor_data = HashMap::new();
-    #[allow(deprecated)]
-    let cursor_buffer = {
-        let data = cursor_data
-            .entry(CursorIcon::Default)
-            .or_insert_with(|| CursorData::load_or_fallback("/usr/share/cursors/default/default"))
-            .get_image(1, startup_time.elapsed().as_millis() as _);
-
-        let mut buffer: gbm::BufferObject<()> = gpu.create_buffer_object(
-            cursor_width as _,
-            cursor_height as _,
-            gbm::Format::Argb8888,
-            gbm::BufferObjectFlags::CURSOR | gbm::BufferObjectFlags::WRITE,
-        )?;
-
-        println!(
-            "IMAGE: {}x{}, {}",
-            data.width,
-            data.height,
-            data.pixels_rgba.len()
-        );
-
-        buffer.map_mut(0, 0, data.width, data.height, |map| {
-            map.buffer_mut()
-                .chunks_exact_mut(cursor_width as usize * 4)
-                .zip(data.pixels_rgba.chunks_exact(data.width as usize * 4))
-                .for_each(|(dst, src)| dst[..src.len()].copy_from_slice(src));
-        })?;
-
-        cursor_hotspot = (data.xhot as _, data.yhot as _);
-
-        if gpu
-            .set_cursor2(output.crtc, Some(&buffer), cursor_hotspot)
-            .is_err()
-        {
-            gpu.set_cursor(output.crtc, Some(&buffer))?;
-        }
-
-        buffer
-    };
-
-    // let cursor_plane = gpu.plane_handles()?.iter()
-    //     .find_map(|plane| {
-    //         let info = gpu.get_plane(*plane).ok()?;
-    //         let prop = gpu.get_properties(*plane).ok()?.iter().find_map(|prop| {
-    //             let info = gpu.get_property(*prop.0).ok()?;
-    //             (info.name() == c"type").then_some({
-    //                 let value_type = info.value_type();
-    //                 let drm::control::property::Value::Enum(value)
-    //                     = value_type.convert_value(*prop.1)
-    //                 else {
-    //                     return None;
-    //                 };
-    //                 value?.value()
-    //             })
-    //         })?;
-
-    //         (prop == drm::control::PlaneType::Cursor as u64&& info.crtc() ==
-    // Some(output.crtc))             .then_some(info)
-    //     })
-    //     .expect("failed to find cursor plane")
-    //     .handle();
+    let cursor_data = HashMap::new();
 
     let this_obj = unsafe { Object::open_this().expect("should be able to open shell binary") };
 
@@ -218,7 +187,15 @@ fn main() -> Result<()> {
     let mut event_loop = EventLoop::new()?;
 
     event_loop.add_source(gpu.clone(), |shell, drm_event| {
-        if let drm::control::Event::PageFlip(_event) = drm_event {
+        if let drm::control::Event::PageFlip(event) = drm_event {
+            if let Some(output) = shell
+                .outputs
+                .iter_mut()
+                .find(|output| output.crtc == event.crtc)
+            {
+                output.flip_pending = false;
+                shell.perf.flipped(&output.name, event.frame);
+            }
             shell.render()?;
         } else {
             trace!("Unknown DRM event occurred");
@@ -227,207 +204,108 @@ fn main() -> Result<()> {
         Ok(())
     })?;
 
-    for (path, device) in evdev::enumerate() {
-        let name = device.name().unwrap_or("Unnamed Device").to_string();
+    // The event loop below only wakes up for events, so frames are scheduled
+    // with a timer instead of polling for them.
+    let frame_timer = TimerFile::create()?;
+    frame_timer.set(FRAME_INTERVAL, Some(FRAME_INTERVAL))?;
+    event_loop.add_source(
+        TimerSource(Arc::new(frame_timer)),
+        |_shell, _expirations| Ok(()),
+    )?;
 
-        let abs_info = device.get_absinfo().map(|info| info.collect::<Vec<_>>());
+    event_loop.add_source(
+        SignalSource(SignalFile::open_non_blocking(&handled_signals)?),
+        |shell: &mut Shell, info| {
+            match info.signal() {
+                Ok(seat::RELEASE_SIGNAL) => shell.deactivate_session(),
+                Ok(seat::ACQUIRE_SIGNAL) => shell.activate_session()?,
+                Ok(Signal::TERM | Signal::INT) => {
+                    shell.session.restore_vt();
+                    handle_signal(info);
+                }
+                _ => handle_signal(info),
+            }
+            Ok(())
+        },
+    )?;
 
+    let mut watcher = Watcher::new(WATCH_DEBOUNCE)?;
+    // The configuration is reloaded whenever it changes, see
+    // `Shell::handle_file_changes`.
+    let config_path = config::Config::path()
+        .and_then(|path| CString::new(path.into_os_string().into_encoded_bytes()).ok());
+    if let Some(path) = &config_path
+        && let Err(error) = watcher.watch_file(path.as_c_str())
+    {
         debug!(
-            target: "dev",
-            "{}\n\
-            \t.name: {}\n\
-            \t.physical_path: {}\n\
-            \t.properties: {:?}\n\
-            \t.misc_properties: {:?}\n\
-            \t.supported_events: {:?}\n\
-            \t.supported_keys: {:?}\n\
-            \t.supported_absolute_axes: {:?}\n\
-            \t.supported_relative_axes: {:?}\n\
-            \t.abs_info: {:?}",
-            path.display(),
-            &name,
-            device.physical_path().unwrap_or("NONE"),
-            device.properties(),
-            device.misc_properties(),
-            device.supported_events(),
-            device.supported_keys(),
-            device.supported_absolute_axes(),
-            device.supported_relative_axes(),
-            &abs_info,
+            "Not watching '{}' for changes: {}",
+            path.to_string_lossy(),
+            error.description(),
         );
+    }
+    event_loop.add_source(WatcherSource(watcher.as_file()), |_shell, ()| Ok(()))?;
 
-        let max_abs_x = abs_info
-            .as_ref()
-            .map(|vals| {
-                vals.iter()
-                    .find(|val| val.0 == evdev::AbsoluteAxisCode::ABS_X)
-                    .map(|val| val.1.maximum())
-                    .unwrap_or(0)
-            })
-            .unwrap_or(0) as f32;
-        let max_abs_y = abs_info
-            .as_ref()
-            .map(|vals| {
-                vals.iter()
-                    .find(|val| val.0 == evdev::AbsoluteAxisCode::ABS_Y)
-                    .map(|val| val.1.maximum())
-                    .unwrap_or(0)
-            })
-            .unwrap_or(0) as f32;
-
-        event_loop.add_source(
-            input::InputSource::new(device)?,
-            move |shell, input_event| {
-                match input_event.event_type() {
-                    evdev::EventType::ABSOLUTE => {
-                        match evdev::AbsoluteAxisCode(input_event.code()) {
-                            evdev::AbsoluteAxisCode::ABS_X => {
-                                let abs_x = input_event.value() as f32;
-                                if abs_x == 0.0 {
-                                    shell.input_state.mouse_pos.x = 0.0;
-                                } else {
-                                    shell.input_state.mouse_pos.x =
-                                        shell.output.width() as f32 / (max_abs_x / abs_x);
-                                }
-                                shell
-                                    .input_state
-                                    .events
-                                    .push(egui::Event::PointerMoved(shell.input_state.mouse_pos));
-                            }
-                            evdev::AbsoluteAxisCode::ABS_Y => {
-                                let abs_y = input_event.value() as f32;
-                                if abs_y == 0.0 {
-                                    shell.input_state.mouse_pos.y = 0.0;
-                                } else {
-                                    shell.input_state.mouse_pos.y =
-                                        shell.output.height() as f32 / (max_abs_y / abs_y);
-                                }
-                                shell
-                                    .input_state
-                                    .events
-                                    .push(egui::Event::PointerMoved(shell.input_state.mouse_pos));
-                            }
-                            _ => {}
-                        }
-                    }
-                    evdev::EventType::RELATIVE => {
-                        match evdev::RelativeAxisCode(input_event.code()) {
-                            evdev::RelativeAxisCode::REL_X => {
-                                let movement = input_event.value() as f32;
-                                shell.input_state.mouse_pos.x += movement;
-                                shell
-                                    .input_state
-                                    .events
-                                    .push(egui::Event::PointerMoved(shell.input_state.mouse_pos));
-                                shell
-                                    .input_state
-                                    .events
-                                    .push(egui::Event::MouseMoved(vec2(movement, 0.0)));
-                            }
-                            evdev::RelativeAxisCode::REL_Y => {
-                                let movement = input_event.value() as f32;
-                                shell.input_state.mouse_pos.y += movement;
-                                shell
-                                    .input_state
-                                    .events
-                                    .push(egui::Event::PointerMoved(shell.input_state.mouse_pos));
-                                shell
-                                    .input_state
-                                    .events
-                                    .push(egui::Event::MouseMoved(vec2(0.0, movement)));
-                            }
-                            evdev::RelativeAxisCode::REL_WHEEL => {
-                                shell.input_state.events.push(egui::Event::MouseWheel {
-                                    unit: egui::MouseWheelUnit::Line,
-                                    delta: vec2(0.0, input_event.value() as f32),
-                                    modifiers: shell.input_state.key_modifiers,
-                                });
-                            }
-                            _ => {}
-                        }
-                    }
-                    evdev::EventType::KEY => match evdev::KeyCode(input_event.code()) {
-                        evdev::KeyCode::BTN_LEFT => {
-                            shell.input_state.events.push(egui::Event::PointerButton {
-                                pos: shell.input_state.mouse_pos,
-                                button: egui::PointerButton::Primary,
-                                pressed: input_event.value() == 1,
-                                modifiers: shell.input_state.key_modifiers,
-                            });
-                        }
-                        evdev::KeyCode::BTN_RIGHT => {
-                            shell.input_state.events.push(egui::Event::PointerButton {
-                                pos: shell.input_state.mouse_pos,
-                                button: egui::PointerButton::Secondary,
-                                pressed: input_event.value() == 1,
-                                modifiers: shell.input_state.key_modifiers,
-                            });
-                        }
+    let input_wakeup = Arc::new(EventFile::create()?);
+    event_loop.add_source(Wakeup(Arc::clone(&input_wakeup)), |_shell, _count| Ok(()))?;
 
-                        evdev::KeyCode::KEY_LEFTCTRL | evdev::KeyCode::KEY_RIGHTCTRL => {
-                            shell.input_state.key_modifiers.ctrl = input_event.value() == 1;
-                            shell.input_state.key_modifiers.command = input_event.value() == 1;
-                        }
-                        evdev::KeyCode::KEY_LEFTSHIFT | evdev::KeyCode::KEY_RIGHTSHIFT => {
-                            shell.input_state.key_modifiers.shift = input_event.value() == 1;
-                        }
-                        evdev::KeyCode::KEY_LEFTALT | evdev::KeyCode::KEY_RIGHTALT => {
-                            shell.input_state.key_modifiers.alt = input_event.value() == 1;
-                        }
+    event_loop.add_source(HotplugSource(UeventSocket::open()?), |shell, event| {
+        if event.is_drm_hotplug() {
+            info!("Displays changed, updating outputs...");
+            shell.sync_outputs()?;
+        }
 
-                        other => {
-                            let pressed = input_event.value() == 1;
-                            if pressed {
-                                let shift = shell.input_state.key_modifiers.shift;
-                                if let Some(ch) = evdev_keycode_to_char(other, shift) {
-                                    shell
-                                        .input_state
-                                        .events
-                                        .push(egui::Event::Text(ch.to_string()));
-                                }
-                            }
-                            if let Some(key) = evdev_keycode_to_egui_key(other) {
-                                shell.input_state.events.push(egui::Event::Key {
-                                    key,
-                                    physical_key: Some(key),
-                                    pressed,
-                                    repeat: false,
-                                    modifiers: shell.input_state.key_modifiers,
-                                });
-                            }
-                        }
-                    },
-                    _ => {}
-                }
+        Ok(())
+    })?;
 
-                Ok(())
-            },
-        )?;
-    }
+    let cursor_regions = Arc::new(input::CursorRegions::new(output::cursor_regions(&outputs)));
+    let input_config = Arc::new(input::SharedInputConfig::new(input::InputConfig::load(
+        shell_config.input,
+    )));
+    let keymap = Arc::new(keymap::SharedKeymap::new(keymap::Keymap::load()));
+    let input_queue = input::spawn_input_thread(
+        gpu.clone(),
+        Arc::clone(&cursor_regions),
+        Arc::clone(&input_config),
+        Arc::clone(&keymap),
+        Arc::clone(&session),
+        input_wakeup,
+    )?;
 
-    gpu.debug_info("/dev/dri/card0");
+    gpu.debug_info(card_path);
 
     let mut shell = Shell {
         startup_time,
         gpu: gpu.clone(),
-        output,
+        display,
+        gl_config: config,
+        outputs,
+        cursor_regions,
+        input_config,
+        keymap,
+        session,
         current_dir: std::env::current_dir()
             .unwrap()
             .to_str()
             .unwrap()
             .to_string(),
+        this_obj,
+        input_queue,
         input_state: InputState {
-            mouse_pos: pos2(0.0, 0.0),
             events: Vec::with_capacity(2),
             key_modifiers: egui::Modifiers::NONE,
         },
         input_buffer: String::new(),
-        cursor_width,
-        cursor_hotspot,
-        cursor_icon: CursorIcon::Default,
         cursor_data,
-        cursor_buffer,
         egui_context,
+        watcher,
+        watched_programs: BTreeMap::new(),
+        capture: capture::Capture::default(),
+        preview: None,
+        program: None,
+        perf: perf::FrameStats::load(),
+        log_viewer: log::Viewer::default(),
+        config: shell_config,
+        config_path,
     };
 
     shell.render()?;
@@ -436,8 +314,10 @@ fn main() -> Result<()> {
 
     std::io::stdout().flush().unwrap();
 
-    event_loop.run(&mut shell, 0, |shell| {
+    event_loop.run(&mut shell, -1, |shell| {
         shell.render().unwrap();
+        system::open_pending_urls();
+        shell.handle_file_changes();
 
         if stdin.lock().read(&mut []).is_err() {
             return;
@@ -449,133 +329,19 @@ fn main() -> Result<()> {
             return;
         };
 
-        let line = line.trim().to_string();
-
-        if line.is_empty() {
-            return;
-        }
-
-        let args = line.split(' ').collect::<Vec<_>>();
-        let args_os: Vec<OsString> = args
-            .iter()
-            .map(|item| OsString::from_str(item).unwrap())
-            .collect();
+        let line = line.trim_end_matches(['\n', '\r']);
 
-        match args[0] {
-            "cd" => {
-                if let Err(error) = std::env::set_current_dir(args[1]) {
-                    println!("{error}");
-                } else {
-                    shell.current_dir = std::env::current_dir()
-                        .unwrap()
-                        .to_str()
-                        .unwrap()
-                        .to_string();
-                }
-            }
-            "env" => {
-                if args.len() == 1 {
-                    for (name, value) in std::env::vars() {
-                        println!("{name} = {value}");
-                    }
-                } else {
-                    match std::env::var(args[1]) {
-                        Ok(value) => {
-                            println!("{value}")
-                        }
-                        Err(error) => {
-                            println!("{error}");
-                        }
-                    }
-                }
-            }
-            "exit" => {
-                std::process::exit(0);
-            }
-            "sym" => {
-                // The type doesn't matter in this case (we're just printing debug info).
-                match this_obj.get_untyped(args[1]) {
-                    Some(ptr) => {
-                        println!("{ptr:?}")
-                    }
-                    None => {
-                        println!("Symbol '{}' not found", args[1]);
-                    }
-                }
-            }
-            // "clear" => 'handle_clear: {
-            //     if args.len() >= 4 {
-            //         let Ok(r) = u8::from_str_radix(args[1], 10) else {
-            //             println!("Invalid red channel: {}", args[1]);
-            //             break 'handle_clear;
-            //         };
-            //         let Ok(g) = u8::from_str_radix(args[2], 10) else {
-            //             println!("Invalid green channel: {}", args[2]);
-            //             break 'handle_clear;
-            //         };
-            //         let Ok(b) = u8::from_str_radix(args[3], 10) else {
-            //             println!("Invalid blue channel: {}", args[3]);
-            //             break 'handle_clear;
-            //         };
-            //         let a = {
-            //             if args.len() == 5 {
-            //                 let Ok(a) = u8::from_str_radix(args[4], 10) else {
-            //                     println!("Invalid alpha channel: {}", args[4]);
-            //                     break 'handle_clear;
-            //                 };
-            //                 a
-            //             } else {
-            //                 255
-            //             }
-            //         };
-
-            //         clear_color = [b, g, r, a];
-
-            //         'map_outputs: for output in &mut outputs {
-            //             let Ok(mut map) = gpu.map_dumb_buffer(&mut output.db) else {
-            //                 println!(
-            //                     "\x1b[31mERROR\x1b[0m \x1b[2m(shell.clear)\x1b[0m: \
-            //                     Failed to map output buffer",
-            //                 );
-            //                 continue 'map_outputs;
-            //             };
-            //             for pixel in map.chunks_exact_mut(4) {
-            //                 pixel[0] = clear_color[0];
-            //                 pixel[1] = clear_color[1];
-            //                 pixel[2] = clear_color[2];
-            //                 pixel[3] = clear_color[3];
-            //             }
-            //             if let Err(error) = gpu.set_crtc(
-            //                 output.crtc,
-            //                 Some(output.fb),
-            //                 (0, 0),
-            //                 &[output.conn],
-            //                 Some(output.mode),
-            //             ) {
-            //                 println!(
-            //                     "\x1b[31mERROR\x1b[0m \x1b[2m(shell.clear)\x1b[0m: \
-            //                     Failed to set CRTC: {error}",
-            //                 );
-            //             }
-            //         }
-            //     } else {
-            //         println!("Invalid arguments");
-            //     }
-            // }
-            _ => {
-                match std::process::Command::new(args[0])
-                    .args(&args_os[1..])
-                    .output()
-                {
-                    Ok(output) => {
-                        println!("{}", String::from_utf8(output.stdout).unwrap());
-                        println!("{}", String::from_utf8(output.stderr).unwrap());
-                    }
-                    Err(error) => {
-                        println!("{error}");
-                    }
-                }
+        // Lines ending with a tab are completed rather than run.
+        if let Some(partial) = line.strip_suffix('\t') {
+            let mut partial = partial.trim_start().to_string();
+            command::complete_line(&mut partial);
+            println!("{partial}");
+        } else {
+            let line = line.trim();
+            if line.is_empty() {
+                return;
             }
+            command::run(shell, line);
         }
 
         print!("\x1b[2m {} }} \x1b[0m", &shell.current_dir);
@@ -590,36 +356,214 @@ pub struct Shell {
     startup_time: Instant,
     gpu: GraphicsCard,
     current_dir: String,
-    output: Output,
+    this_obj: Object,
+    display: glutin::api::egl::display::Display,
+    gl_config: glutin::api::egl::config::Config,
+    /// Every connected display, see [`output`].
+    outputs: Vec<Output>,
+    /// Shared with the input thread, see [`Shell::sync_outputs`].
+    cursor_regions: Arc<input::CursorRegions>,
+    input_config: Arc<input::SharedInputConfig>,
+    /// Shared with the input thread, see [`keymap`].
+    keymap: Arc<keymap::SharedKeymap>,
+    /// Shared with the input thread, see [`seat`].
+    session: Arc<seat::Session>,
+    input_queue: Arc<input::InputQueue>,
     input_state: InputState,
     input_buffer: String,
-    cursor_width: u64,
-    cursor_hotspot: (i32, i32),
-    cursor_icon: CursorIcon,
     cursor_data: HashMap<CursorIcon, CursorData>,
-    cursor_buffer: gbm::BufferObject<()>,
     egui_context: egui::Context,
+    watcher: Watcher,
+    /// The source files of programs that are recompiled when they change, and
+    /// the files they're compiled to, see the `watch` command.
+    watched_programs: BTreeMap<CString, String>,
+    /// Screenshots and recordings, see [`capture`].
+    capture: capture::Capture,
+    /// A saved render that's drawn over the interface, see the `preview`
+    /// command.
+    preview: Option<Render>,
+    /// The application shown in the central panel, see the `run` command.
+    program: Option<program::Program>,
+    /// Frame timing, see [`perf`].
+    perf: perf::FrameStats,
+    /// The log viewer's window, see the `log` command.
+    log_viewer: log::Viewer,
+    /// See [`config`].
+    config: config::Config,
+    /// The configuration file, which is reloaded when it changes.
+    config_path: Option<CString>,
 }
 
 impl Shell {
+    /// Reload the configuration if it has changed, and recompile every watched
+    /// program whose source file has stopped changing.
+    fn handle_file_changes(&mut self) {
+        for source in self.watcher.take_changes() {
+            if self.config_path.as_ref() == Some(&source) {
+                self.reload_config();
+                continue;
+            }
+            let Some(output) = self.watched_programs.get(&source) else {
+                continue;
+            };
+            let source = source.to_string_lossy();
+
+            // The file may have been removed (or replaced) rather than changed.
+            let content = match std::fs::read_to_string(&*source) {
+                Ok(content) => content,
+                Err(error) => {
+                    warn!("Failed to read '{source}': {error}");
+                    continue;
+                }
+            };
+
+            info!("'{source}' changed, recompiling...");
+            match compiler::run(&content, &source, output, &self.config.library_dir) {
+                Ok(()) => info!("Compiled '{output}'"),
+                Err(error) => warn!("Failed to compile '{source}': {error}"),
+            }
+        }
+    }
+
+    /// Apply any changes to the configuration file, see [`config`].
+    fn reload_config(&mut self) {
+        let config = config::Config::load();
+        if config == self.config {
+            return;
+        }
+        info!("Configuration changed, reloading...");
+
+        egl::apply_style(&self.egui_context, &config.colors);
+        if config.cursor_theme != self.config.cursor_theme {
+            self.cursor_data.clear();
+        }
+        *self.input_config.lock().unwrap() = input::InputConfig::load(config.input);
+        self.config = config;
+    }
+
+    /// Bring the outputs up to date with the connected displays and the modes
+    /// and positions set for them (see [`output`]), such as after a display is
+    /// plugged in or an output's mode is changed with the `output` command.
+    ///
+    /// Outputs whose mode has changed are prepared again from scratch.
+    fn sync_outputs(&mut self) -> Result<()> {
+        let connectors = self.gpu.connected_connectors()?;
+        // Flips that are still pending on a CRTC carry over to the next output
+        // using it, which can't present anything until they complete.
+        let mut busy_crtcs = Vec::new();
+        let mut index = 0;
+        while index < self.outputs.len() {
+            let output = &mut self.outputs[index];
+            let config = output::OutputConfig::load(&output.name);
+            let keep = connectors
+                .iter()
+                .find(|conn_info| conn_info.handle() == output.conn)
+                .is_some_and(|conn_info| {
+                    output::choose_mode(conn_info.modes(), config.mode) == Some(output.mode)
+                });
+            if keep {
+                output.configured_position = config.position;
+                index += 1;
+                continue;
+            }
+
+            let output = self.outputs.remove(index);
+            info!("Removing output {}", output.name);
+            if output.flip_pending {
+                busy_crtcs.push(output.crtc);
+            }
+            if let Err(error) = self.gpu.set_crtc(output.crtc, None, (0, 0), &[], None) {
+                warn!("Failed to disable output {}: {error}", output.name);
+            }
+            if let Some(blob) = output.mode_blob {
+                let _ = self.gpu.destroy_property_blob(blob);
+            }
+        }
+
+        for conn_info in connectors {
+            if self
+                .outputs
+                .iter()
+                .any(|output| output.conn == conn_info.handle())
+            {
+                continue;
+            }
+            let used_crtcs = self
+                .outputs
+                .iter()
+                .map(|output| output.crtc)
+                .collect::<Vec<_>>();
+            let used_planes = output::used_planes(&self.outputs);
+            if let Some(mut output) = self.gpu.prepare_output(
+                &conn_info,
+                &used_crtcs,
+                &used_planes,
+                &self.display,
+                &self.gl_config,
+                self.egui_context.clone(),
+            )? {
+                output.flip_pending = busy_crtcs.contains(&output.crtc);
+                self.outputs.push(output);
+            }
+        }
+
+        if self.outputs.is_empty() {
+            warn!("No outputs left to render to");
+        }
+        output::arrange(&mut self.outputs);
+        *self.cursor_regions.lock().unwrap() = output::cursor_regions(&self.outputs);
+
+        Ok(())
+    }
+
+    /// Stop using the seat's displays before the kernel switches to another VT,
+    /// see [`seat`].
+    fn deactivate_session(&mut self) {
+        info!("Switching away from the shell's VT...");
+        self.session.set_active(false);
+        self.perf.pause();
+        if let Err(error) = self.gpu.release_master_lock() {
+            warn!("Failed to drop DRM master: {error}");
+        }
+        self.session.release_vt();
+    }
+
+    /// Take the seat's displays back after the kernel switched back to the
+    /// shell's VT, setting every output up again since whatever ran in the
+    /// meantime may have changed them.
+    fn activate_session(&mut self) -> Result<()> {
+        info!("Switched back to the shell's VT");
+        self.session.acquire_vt();
+        if let Err(error) = self.gpu.acquire_master_lock() {
+            warn!("Failed to become DRM master: {error}");
+        }
+        for output in &mut self.outputs {
+            output.reset();
+        }
+        self.session.set_active(true);
+        self.sync_outputs()?;
+        self.render()
+    }
+
     fn render(&mut self) -> Result<()> {
-        self.output
-            .context
-            .make_current(&self.output.surface)
-            .unwrap();
-
-        #[allow(deprecated)]
-        self.gpu.move_cursor(
-            self.output.crtc,
-            (
-                self.input_state.mouse_pos.x as _,
-                self.input_state.mouse_pos.y as _,
-            ),
-        )?;
+        // Another VT is using the displays.
+        if !self.session.is_active() {
+            return Ok(());
+        }
+        let frame_start = Instant::now();
+
+        while let Some(message) = self.input_queue.pop() {
+            match message {
+                input::InputMessage::Event(event) => self.input_state.events.push(event),
+                input::InputMessage::Modifiers(modifiers) => {
+                    self.input_state.key_modifiers = modifiers;
+                }
+            }
+        }
 
-        let (width, height) = self.output.mode.size();
-        let size = vec2(width as _, height as _);
-        let rect = Rect::from_min_size(Pos2::ZERO, size);
+        // The interface spans every output.
+        let rect = output::desktop_rect(&self.outputs);
+        let size = rect.size();
         let raw_input = egui::RawInput {
             viewport_id: egui::ViewportId::ROOT,
             viewports: std::iter::once((
@@ -651,6 +595,7 @@ impl Shell {
             system_theme: Some(egui::Theme::Dark),
             safe_area_insets: None,
         };
+        let mut submitted_line: Option<String> = None;
         let full_output = self.egui_context.run(raw_input, |ctx| {
             egui::TopBottomPanel::top("menubar")
                 .show_separator_line(false)
@@ -694,6 +639,16 @@ impl Shell {
                         });
                 });
             egui::CentralPanel::default().show(ctx, |ui| {
+                if let Some(program) = &mut self.program {
+                    egui::CentralPanel::default()
+                        .frame(egui::Frame::menu(&ctx.style()))
+                        .show_inside(ui, |ui| {
+                            ui.heading(program.title());
+                            ui.separator();
+                            program.show(ui);
+                        });
+                    return;
+                }
                 egui::CentralPanel::default()
                     .frame(egui::Frame::menu(&ctx.style()))
                     .show_inside(ui, |ui| {
@@ -705,125 +660,135 @@ impl Shell {
 
                                 let resp = ui.add(
                                     egui::TextEdit::singleline(&mut self.input_buffer)
-                                        .hint_text("Enter a command..."),
+                                        .hint_text("Enter a command...")
+                                        .lock_focus(true),
                                 );
+                                if resp.has_focus() && ui.input(|i| i.key_pressed(egui::Key::Tab)) {
+                                    command::complete_line(&mut self.input_buffer);
+                                }
                                 if resp.lost_focus()
                                     && ui.input(|i| i.key_pressed(egui::Key::Enter))
                                 {
-                                    let line: String = self.input_buffer.drain(..).collect();
-                                    println!("{}", line);
+                                    submitted_line = Some(self.input_buffer.drain(..).collect());
                                 }
                             });
                     });
             });
+            if self.perf.hud() {
+                perf::show_hud(ctx, &self.perf);
+            }
+            log::show_viewer(ctx, &mut self.log_viewer);
         });
+        if let Some(line) = submitted_line {
+            command::run(self, &line);
+        }
+        if let Some(program) = &mut self.program
+            && !program.update(frame_start)
+        {
+            self.program = None;
+        }
+
         let clipped_primitives = self
-            .output
-            .renderer
             .egui_context
             .tessellate(full_output.shapes, full_output.pixels_per_point);
 
-        unsafe {
-            self.output.renderer.gl.clear_color(0.1, 0.1, 0.1, 1.0);
-            self.output.renderer.painter.paint_and_update_textures(
-                [width as _, height as _],
-                full_output.pixels_per_point,
-                &clipped_primitives,
-                &full_output.textures_delta,
-            );
-            self.output.renderer.gl.finish();
-        }
+        // Each output draws the cursor's current animation frame when it
+        // presents, if it isn't already showing it.
+        let cursor_icon = cursor::egui_to_abi_cursor_icon(full_output.platform_output.cursor_icon);
+        let cursor_theme = &self.config.cursor_theme;
+        let cursor_data = self.cursor_data.entry(cursor_icon).or_insert_with(|| {
+            CursorData::load_or_fallback(&cursor_theme.join(cursor_icon.name()).to_string_lossy())
+        });
+        let index = cursor_data.frame_index(1, self.startup_time.elapsed().as_millis() as _);
+        let cursor = output::CursorFrame {
+            icon: cursor_icon,
+            index,
+            image: cursor_data.frame(1, index),
+            position: self.egui_context.pointer_latest_pos(),
+        };
+        let mut drawn_outputs = 0;
+        for output in &mut self.outputs {
+            output
+                .pending_textures
+                .append(full_output.textures_delta.clone());
+            // Outputs still waiting for their last frame to be shown are left
+            // until their flip completes, which renders again.
+            if output.flip_pending {
+                continue;
+            }
 
-        let next_icon = cursor::egui_to_abi_cursor_icon(full_output.platform_output.cursor_icon);
-        if self.cursor_icon != next_icon {
-            self.cursor_icon = next_icon;
-
-            let data = self
-                .cursor_data
-                .entry(self.cursor_icon)
-                .or_insert_with(|| {
-                    CursorData::load_or_fallback(&format!(
-                        "/usr/share/cursors/default/{}",
-                        self.cursor_icon.name(),
-                    ))
-                })
-                .get_image(1, self.startup_time.elapsed().as_millis() as _);
-
-            self.cursor_buffer
-                .map_mut(0, 0, data.width, data.height, |map| {
-                    map.buffer_mut()
-                        .chunks_exact_mut(self.cursor_width as usize * 4)
-                        .zip(data.pixels_rgba.chunks_exact(data.width as usize * 4))
-                        .for_each(|(dst, src)| dst[..src.len()].copy_from_slice(src));
-                })?;
-
-            self.cursor_hotspot = (data.xhot as _, data.yhot as _);
-
-            #[allow(deprecated)]
-            if self
-                .gpu
-                .set_cursor2(
-                    self.output.crtc,
-                    Some(&self.cursor_buffer),
-                    self.cursor_hotspot,
-                )
-                .is_err()
-            {
-                self.gpu
-                    .set_cursor(self.output.crtc, Some(&self.cursor_buffer))?;
+            output.context.make_current(&output.surface).unwrap();
+
+            let textures_delta = std::mem::take(&mut output.pending_textures);
+            unsafe {
+                output.renderer.gl.clear_color(0.1, 0.1, 0.1, 1.0);
+                output.renderer.painter.paint_and_update_textures(
+                    [output.width() as _, output.height() as _],
+                    full_output.pixels_per_point,
+                    &output::primitives_for_output(&clipped_primitives, output.position),
+                    &textures_delta,
+                );
+                if let Some(program) = &self.program {
+                    let screen_size = Xy::new(output.width() as _, output.height() as _);
+                    let output_position = Xy::new(output.position.x, output.position.y);
+                    program.draw(&mut output.renderer.views, screen_size, output_position);
+                }
+                if let Some(preview) = &self.preview {
+                    output.renderer.views.draw(
+                        preview,
+                        render::RenderTarget {
+                            screen_size: Xy::new(output.width() as _, output.height() as _),
+                            offset: Xy::new(
+                                rect.min.x - output.position.x,
+                                rect.min.y - output.position.y,
+                            ),
+                            scale: 1.0,
+                            clip: None,
+                        },
+                    );
+                }
+                output.renderer.gl.finish();
+            }
+            if self.capture.wants(&output.name) {
+                let mut pixels = vec![0; output.width() as usize * output.height() as usize * 4];
+                unsafe {
+                    output.renderer.gl.read_pixels(
+                        0,
+                        0,
+                        output.width() as _,
+                        output.height() as _,
+                        glow::RGBA,
+                        glow::UNSIGNED_BYTE,
+                        glow::PixelPackData::Slice(Some(&mut pixels)),
+                    );
+                }
+                self.capture.add_output(rect, output, &pixels);
             }
-        }
 
-        self.output
-            .surface
-            .swap_buffers(&self.output.context)
-            .unwrap();
+            output.surface.swap_buffers(&output.context).unwrap();
 
-        let bo = unsafe { self.output.bo.lock_front_buffer().unwrap() };
-        let fb = if let Some(handle) = &self.output.fb {
-            *handle
-        } else {
-            let fb = self.gpu.add_framebuffer(&bo, 24, 32).unwrap();
-            self.output.fb = Some(fb);
-            fb
-        };
-        if !self.output.crtc_set {
-            self.output.crtc_set = true;
-
-            self.gpu.set_crtc(
-                self.output.crtc,
-                Some(fb),
-                (0, 0),
-                &[self.output.conn],
-                Some(self.output.mode),
-            )?;
-            self.gpu.page_flip(
-                self.output.crtc,
-                fb,
-                drm::control::PageFlipFlags::empty(),
-                None,
-            )?;
-        } else {
-            self.gpu.page_flip(
-                self.output.crtc,
-                fb,
-                drm::control::PageFlipFlags::empty(),
-                None,
-            )?;
+            let bo = unsafe { output.bo.lock_front_buffer().unwrap() };
+            let fb = if let Some(handle) = &output.fb {
+                *handle
+            } else {
+                let fb = self.gpu.add_framebuffer(&bo, 24, 32).unwrap();
+                output.fb = Some(fb);
+                fb
+            };
+            output.present(&self.gpu, fb, &cursor)?;
+            self.perf.presented(&output.name);
+            drawn_outputs += 1;
         }
-
-        #[allow(deprecated)]
-        if self
-            .gpu
-            .set_cursor2(
-                self.output.crtc,
-                Some(&self.cursor_buffer),
-                self.cursor_hotspot,
-            )
-            .is_err()
-        {
-            self.gpu
-                .set_cursor(self.output.crtc, Some(&self.cursor_buffer))?;
+        self.capture.finish_frame(&self.outputs);
+        if drawn_outputs > 0 {
+            if let Some(program) = &self.program {
+                self.perf
+                    .record_render(program.name(), program.render_len());
+            }
+            if let Some(preview) = &self.preview {
+                self.perf.record_render("preview", preview.commands.len());
+            }
+            self.perf.record_frame(frame_start.elapsed());
         }
 
         Ok(())
@@ -831,101 +796,10 @@ impl Shell {
 }
 
 struct InputState {
-    mouse_pos: Pos2,
     events: Vec<egui::Event>,
     key_modifiers: egui::Modifiers,
 }
 
-fn evdev_keycode_to_char(code: evdev::KeyCode, shift: bool) -> Option<char> {
-    use evdev::KeyCode;
-
-    Some(match code {
-        KeyCode::KEY_0 if !shift => '0',
-        KeyCode::KEY_1 if !shift => '1',
-        KeyCode::KEY_2 if !shift => '2',
-        KeyCode::KEY_3 if !shift => '3',
-        KeyCode::KEY_4 if !shift => '4',
-        KeyCode::KEY_5 if !shift => '5',
-        KeyCode::KEY_6 if !shift => '6',
-        KeyCode::KEY_7 if !shift => '7',
-        KeyCode::KEY_8 if !shift => '8',
-        KeyCode::KEY_9 if !shift => '9',
-
-        KeyCode::KEY_0 if shift => ')',
-        KeyCode::KEY_1 if shift => '!',
-        KeyCode::KEY_2 if shift => '@',
-        KeyCode::KEY_3 if shift => '#',
-        KeyCode::KEY_4 if shift => '$',
-        KeyCode::KEY_5 if shift => '%',
-        KeyCode::KEY_6 if shift => '^',
-        KeyCode::KEY_7 if shift => '&',
-        KeyCode::KEY_8 if shift => '*',
-        KeyCode::KEY_9 if shift => '(',
-
-        KeyCode::KEY_GRAVE if !shift => '`',
-        KeyCode::KEY_GRAVE if shift => '~',
-        KeyCode::KEY_BACKSLASH if !shift => '\\',
-        KeyCode::KEY_BACKSLASH if shift => '|',
-        KeyCode::KEY_MINUS if !shift => '-',
-        KeyCode::KEY_MINUS if shift => '_',
-        KeyCode::KEY_EQUAL if !shift => '=',
-        KeyCode::KEY_EQUAL if shift => '+',
-        KeyCode::KEY_LEFTBRACE if !shift => '[',
-        KeyCode::KEY_LEFTBRACE if shift => '{',
-        KeyCode::KEY_RIGHTBRACE if !shift => ']',
-        KeyCode::KEY_RIGHTBRACE if shift => '}',
-        KeyCode::KEY_SEMICOLON if !shift => ';',
-        KeyCode::KEY_SEMICOLON if shift => ':',
-        KeyCode::KEY_APOSTROPHE if !shift => '\'',
-        KeyCode::KEY_APOSTROPHE if shift => '\"',
-        KeyCode::KEY_COMMA if !shift => ',',
-        KeyCode::KEY_COMMA if shift => '<',
-        KeyCode::KEY_DOT if !shift => '.',
-        KeyCode::KEY_DOT if shift => '>',
-        KeyCode::KEY_SLASH if !shift => '/',
-        KeyCode::KEY_SLASH if shift => '?',
-
-        KeyCode::KEY_SPACE => ' ',
-
-        other => {
-            let letter = match other {
-                KeyCode::KEY_A => 'a',
-                KeyCode::KEY_B => 'b',
-                KeyCode::KEY_C => 'c',
-                KeyCode::KEY_D => 'd',
-                KeyCode::KEY_E => 'e',
-                KeyCode::KEY_F => 'f',
-                KeyCode::KEY_G => 'g',
-                KeyCode::KEY_H => 'h',
-                KeyCode::KEY_I => 'i',
-                KeyCode::KEY_J => 'j',
-                KeyCode::KEY_K => 'k',
-                KeyCode::KEY_L => 'l',
-                KeyCode::KEY_M => 'm',
-                KeyCode::KEY_N => 'n',
-                KeyCode::KEY_O => 'o',
-                KeyCode::KEY_P => 'p',
-                KeyCode::KEY_Q => 'q',
-                KeyCode::KEY_R => 'r',
-                KeyCode::KEY_S => 's',
-                KeyCode::KEY_T => 't',
-                KeyCode::KEY_U => 'u',
-                KeyCode::KEY_V => 'v',
-                KeyCode::KEY_W => 'w',
-                KeyCode::KEY_X => 'x',
-                KeyCode::KEY_Y => 'y',
-                KeyCode::KEY_Z => 'z',
-                _ => None?,
-            };
-            if shift {
-                letter.to_ascii_uppercase()
-            } else {
-                letter
-            }
-        }
-    })
-}
-
 fn evdev_keycode_to_egui_key(code: evdev::KeyCode) -> Option<egui::Key> {
     use {egui::Key, evdev::KeyCode};
     Some(match code {
@@ -1150,6 +1024,233 @@ pub enum EventResponse {
     RemoveSource,
 }
 
+/// An event source for a [`SignalFile`], whose events are the signals it
+/// received.
+pub struct SignalSource(SignalFile);
+
+impl<D> EventSource<D> for SignalSource {
+    type Event = SignalInfo;
+
+    fn init(&mut self, poll: &EventPoll, key: u64) -> Result<()> {
+        poll.add(&self.0.as_file(), Event::new(key, true, false))?;
+
+        Ok(())
+    }
+
+    fn handle_event<F>(
+        &mut self,
+        data: &mut D,
+        _event: Event,
+        mut callback: F,
+    ) -> Result<EventResponse>
+    where
+        F: FnMut(&mut D, Self::Event) -> Result<()>,
+    {
+        while let Some(info) = self.0.read()? {
+            callback(data, info)?;
+        }
+
+        Ok(EventResponse::Continue)
+    }
+
+    fn cleanup(&mut self, poll: &EventPoll) -> Result<()> {
+        poll.remove(&self.0.as_file())?;
+
+        Ok(())
+    }
+}
+
+fn handle_signal(info: SignalInfo) {
+    match info.signal() {
+        Ok(Signal::CHLD) => {
+            // Signals of the same kind are merged while pending, so every child that
+            // changed state has to be reaped here.
+            while let Ok(status) = wait_for_children_once() {
+                match status {
+                    WaitStatus::Running => break,
+                    WaitStatus::Exited { proc, code } => {
+                        debug!("Child process {} exited with code {code}", proc.id());
+                    }
+                    WaitStatus::Signaled { proc, sig, .. } => {
+                        warn!("Child process {} was killed by {}", proc.id(), sig.as_str());
+                    }
+                    WaitStatus::Stopped { .. } | WaitStatus::Continued { .. } => {}
+                }
+            }
+        }
+        Ok(sig @ (Signal::TERM | Signal::INT)) => {
+            info!("Received {}, exiting...", sig.as_str());
+            std::io::stdout().flush().unwrap();
+            std::process::exit(0);
+        }
+        _ => trace!("Ignoring signal: {info:?}"),
+    }
+}
+
+/// How long a watched source file has to stop changing before it's recompiled.
+const WATCH_DEBOUNCE: Duration = Duration::from_millis(250);
+
+/// An event source for the shell's [`Watcher`], which has no events of its own.
+/// Changes are taken from [`Shell::watcher`] once they're debounced.
+pub struct WatcherSource(File);
+
+impl EventSource<Shell> for WatcherSource {
+    type Event = ();
+
+    fn init(&mut self, poll: &EventPoll, key: u64) -> Result<()> {
+        poll.add(&self.0, Event::new(key, true, false))?;
+
+        Ok(())
+    }
+
+    fn handle_event<F>(
+        &mut self,
+        data: &mut Shell,
+        _event: Event,
+        mut callback: F,
+    ) -> Result<EventResponse>
+    where
+        F: FnMut(&mut Shell, Self::Event) -> Result<()>,
+    {
+        data.watcher.process()?;
+        callback(data, ())?;
+
+        Ok(EventResponse::Continue)
+    }
+
+    fn cleanup(&mut self, poll: &EventPoll) -> Result<()> {
+        poll.remove(&self.0)?;
+
+        Ok(())
+    }
+}
+
+/// An event source for kernel device events, such as displays being plugged in.
+pub struct HotplugSource(UeventSocket);
+
+impl<D> EventSource<D> for HotplugSource {
+    type Event = Uevent;
+
+    fn init(&mut self, poll: &EventPoll, key: u64) -> Result<()> {
+        poll.add(
+            &self.0.as_file(),
+            Event::with_interest(key, Interest::READABLE, Trigger::Edge),
+        )?;
+
+        Ok(())
+    }
+
+    fn handle_event<F>(
+        &mut self,
+        data: &mut D,
+        _event: Event,
+        mut callback: F,
+    ) -> Result<EventResponse>
+    where
+        F: FnMut(&mut D, Self::Event) -> Result<()>,
+    {
+        // Edge-triggered, so every waiting event has to be read.
+        while let Some(event) = self.0.read()? {
+            callback(data, event)?;
+        }
+
+        Ok(EventResponse::Continue)
+    }
+
+    fn cleanup(&mut self, poll: &EventPoll) -> Result<()> {
+        poll.remove(&self.0.as_file())?;
+
+        Ok(())
+    }
+}
+
+/// The time between animation frames of the shell's render loop.
+const FRAME_INTERVAL: Duration = Duration::from_micros(16_667);
+
+/// An event source for a timer, whose events are the number of times it
+/// expired. The timer is shared so that it can be set again by whatever owns
+/// the event loop's data.
+pub struct TimerSource(Arc<TimerFile>);
+
+impl<D> EventSource<D> for TimerSource {
+    type Event = u64;
+
+    fn init(&mut self, poll: &EventPoll, key: u64) -> Result<()> {
+        poll.add(
+            &self.0.as_file(),
+            Event::with_interest(key, Interest::READABLE, Trigger::Edge),
+        )?;
+
+        Ok(())
+    }
+
+    fn handle_event<F>(
+        &mut self,
+        data: &mut D,
+        _event: Event,
+        mut callback: F,
+    ) -> Result<EventResponse>
+    where
+        F: FnMut(&mut D, Self::Event) -> Result<()>,
+    {
+        // Edge-triggered, so the timer has to be drained before it wakes the
+        // loop up again.
+        let expirations = self.0.expirations()?;
+        if expirations != 0 {
+            callback(data, expirations)?;
+        }
+
+        Ok(EventResponse::Continue)
+    }
+
+    fn cleanup(&mut self, poll: &EventPoll) -> Result<()> {
+        self.0.disarm()?;
+        poll.remove(&self.0.as_file())?;
+
+        Ok(())
+    }
+}
+
+/// An event source for an [`EventFile`] notified by other threads, whose events
+/// are the number of notifications.
+pub struct Wakeup(Arc<EventFile>);
+
+impl<D> EventSource<D> for Wakeup {
+    type Event = u64;
+
+    fn init(&mut self, poll: &EventPoll, key: u64) -> Result<()> {
+        poll.add(
+            &self.0.as_file(),
+            Event::with_interest(key, Interest::READABLE, Trigger::Edge),
+        )?;
+
+        Ok(())
+    }
+
+    fn handle_event<F>(
+        &mut self,
+        data: &mut D,
+        _event: Event,
+        mut callback: F,
+    ) -> Result<EventResponse>
+    where
+        F: FnMut(&mut D, Self::Event) -> Result<()>,
+    {
+        let count = self.0.take()?;
+        if count != 0 {
+            callback(data, count)?;
+        }
+
+        Ok(EventResponse::Continue)
+    }
+
+    fn cleanup(&mut self, poll: &EventPoll) -> Result<()> {
+        poll.remove(&self.0.as_file())?;
+
+        Ok(())
+    }
+}
+
 
 
 #[derive(Clone, Debug)]
@@ -1183,6 +1284,18 @@ impl GraphicsCard {
         )?)))
     }
 
+    /// The size of cursor buffers the driver supports.
+    fn cursor_size(&self) -> (u32, u32) {
+        let width = self
+            .get_driver_capability(drm::DriverCapability::CursorWidth)
+            .unwrap_or(64);
+        let height = self
+            .get_driver_capability(drm::DriverCapability::CursorHeight)
+            .unwrap_or(64);
+
+        (width as _, height as _)
+    }
+
     fn debug_info(&self, path: &str) {
         let name = path
             .rsplit_once('/')
@@ -1380,84 +1493,179 @@ impl GraphicsCard {
         }
     }
 
-    fn prepare_output(
+    /// Prepare an [`Output`] for every connected connector that a CRTC can be
+    /// found for, arranged into a single desktop (see [`output`]).
+    fn prepare_outputs(
         &self,
+        display: &glutin::api::egl::display::Display,
         config: &glutin::api::egl::config::Config,
-        context: glutin::api::egl::context::NotCurrentContext,
         egui_context: egui::Context,
-    ) -> Result<Output> {
-        let resources = self.resource_handles()?;
+    ) -> Result<Vec<Output>> {
+        let mut outputs = Vec::new();
+        for conn_info in self.connected_connectors()? {
+            let used_crtcs = outputs
+                .iter()
+                .map(|output: &Output| output.crtc)
+                .collect::<Vec<_>>();
+            let used_planes = output::used_planes(&outputs);
+            if let Some(output) = self.prepare_output(
+                &conn_info,
+                &used_crtcs,
+                &used_planes,
+                display,
+                config,
+                egui_context.clone(),
+            )? {
+                outputs.push(output);
+            }
+        }
+
+        if outputs.is_empty() {
+            bail!("no valid outputs found");
+        }
+        output::arrange(&mut outputs);
 
-        for conn in resources.connectors().iter().copied() {
+        Ok(outputs)
+    }
+
+    /// Every connector with a display connected to it.
+    fn connected_connectors(&self) -> Result<Vec<drm::control::connector::Info>> {
+        let mut connectors = Vec::new();
+        for conn in self.resource_handles()?.connectors().iter().copied() {
             let conn_info = self.get_connector(conn, true)?;
+            if conn_info.state() == drm::control::connector::State::Connected {
+                connectors.push(conn_info);
+            }
+        }
 
-            let Some(enc) = conn_info.current_encoder() else {
-                continue;
-            };
+        Ok(connectors)
+    }
 
+    /// Prepare an [`Output`] for the connector with its configured mode (see
+    /// [`output::OutputConfig`]) on a CRTC that isn't in `used_crtcs`,
+    /// returning `None` if there's no such CRTC or the connector has no modes.
+    ///
+    /// The output uses atomic commits if planes that aren't in `used_planes`
+    /// can be found for it (see [`output::OutputPlanes`]).
+    fn prepare_output(
+        &self,
+        conn_info: &drm::control::connector::Info,
+        used_crtcs: &[drm::control::crtc::Handle],
+        used_planes: &[drm::control::plane::Handle],
+        display: &glutin::api::egl::display::Display,
+        config: &glutin::api::egl::config::Config,
+        egui_context: egui::Context,
+    ) -> Result<Option<Output>> {
+        let resources = self.resource_handles()?;
+        let conn = conn_info.handle();
+        let name = format!(
+            "{}-{}",
+            conn_info.interface().as_str(),
+            conn_info.interface_id(),
+        );
+
+        // Prefer the CRTC the connector is already using, so that the mode
+        // is set without a flicker where possible.
+        let mut crtcs = Vec::new();
+        for enc in conn_info
+            .current_encoder()
+            .into_iter()
+            .chain(conn_info.encoders().iter().copied())
+        {
             let enc_info = self.get_encoder(enc)?;
+            crtcs.extend(enc_info.crtc());
+            crtcs.extend(resources.filter_crtcs(enc_info.possible_crtcs()));
+        }
+        let Some(crtc) = crtcs.into_iter().find(|crtc| !used_crtcs.contains(crtc)) else {
+            warn!("No CRTC available for output {name}");
+            return Ok(None);
+        };
 
-            let Some(crtc) = enc_info.crtc() else {
-                continue;
-            };
+        let output_config = output::OutputConfig::load(&name);
+        let Some(mode) = output::choose_mode(conn_info.modes(), output_config.mode) else {
+            warn!("No modes available for output {name}");
+            return Ok(None);
+        };
+        if output_config
+            .mode
+            .is_some_and(|request| mode.size() != (request.width, request.height))
+        {
+            warn!("Output {name} doesn't support its configured mode");
+        }
 
-            let Some(mode) = conn_info.modes().iter().find(|mode| {
-                mode.mode_type()
-                    .contains(drm::control::ModeTypeFlags::PREFERRED)
-            }) else {
-                continue;
-            };
+        let bo = self.create_surface(
+            mode.size().0 as _,
+            mode.size().1 as _,
+            gbm::Format::Argb8888,
+            gbm::BufferObjectFlags::SCANOUT | gbm::BufferObjectFlags::RENDERING,
+        )?;
 
-            let bo = self.create_surface(
-                mode.size().0 as _,
-                mode.size().1 as _,
-                gbm::Format::Argb8888,
-                gbm::BufferObjectFlags::SCANOUT | gbm::BufferObjectFlags::RENDERING,
-            )?;
-
-            let surface = unsafe {
-                context
-                    .display()
-                    .create_window_surface(
-                        &config,
-                        &glutin::surface::SurfaceAttributesBuilder::<
-                            glutin::surface::WindowSurface
-                        >::new()
-                            .build(
-                                raw_window_handle::RawWindowHandle::Gbm(
-                                    raw_window_handle::GbmWindowHandle::new(
-                                        NonNull::new(bo.as_raw() as *mut _).unwrap()
-                                    ),
+        let surface = unsafe {
+            display
+                .create_window_surface(
+                    config,
+                    &glutin::surface::SurfaceAttributesBuilder::<
+                        glutin::surface::WindowSurface
+                    >::new()
+                        .build(
+                            raw_window_handle::RawWindowHandle::Gbm(
+                                raw_window_handle::GbmWindowHandle::new(
+                                    NonNull::new(bo.as_raw() as *mut _).unwrap()
                                 ),
-                                NonZeroU32::new(mode.size().0 as _).unwrap(),
-                                NonZeroU32::new(mode.size().1 as _).unwrap(),
-                            ))
-                    .unwrap()
-            };
+                            ),
+                            NonZeroU32::new(mode.size().0 as _).unwrap(),
+                            NonZeroU32::new(mode.size().1 as _).unwrap(),
+                        ))
+                .unwrap()
+        };
 
-            let context = context.make_current(&surface)?;
-
-            surface.set_swap_interval(
-                &context,
-                glutin::surface::SwapInterval::Wait(NonZeroU32::MIN),
-            )?;
-
-            let renderer = egl::Renderer::new(&context.display(), egui_context)?;
-
-            return Ok(Output {
-                bo,
-                fb: None,
-                conn,
-                crtc,
-                mode: *mode,
-                renderer,
-                surface,
-                context,
-                crtc_set: false,
-            });
+        let context = create_context(display, config).make_current(&surface)?;
+
+        surface.set_swap_interval(
+            &context,
+            glutin::surface::SwapInterval::Wait(NonZeroU32::MIN),
+        )?;
+
+        let renderer = egl::Renderer::new(&context.display(), egui_context)?;
+
+        let planes = output::OutputPlanes::find(self, conn, crtc, used_planes);
+        if planes.is_none() {
+            info!("Output {name} doesn't support atomic commits, using legacy modesetting");
         }
+        let cursor = output::OutputCursor::new(
+            self,
+            self.cursor_size(),
+            planes
+                .as_ref()
+                .is_some_and(|planes| planes.cursor.is_some()),
+        )?;
 
-        bail!("no valid outputs found")
+        info!(
+            "Found output {name} ({}x{}@{})",
+            mode.size().0,
+            mode.size().1,
+            mode.vrefresh(),
+        );
+
+        Ok(Some(Output {
+            name,
+            bo,
+            fb: None,
+            conn,
+            crtc,
+            mode,
+            position: Pos2::ZERO,
+            configured_position: output_config.position,
+            renderer,
+            surface,
+            context,
+            crtc_set: false,
+            planes,
+            mode_blob: None,
+            cursor,
+            flip_pending: false,
+            pending_textures: egui::TexturesDelta::default(),
+        }))
     }
 }
 
@@ -1498,39 +1706,40 @@ impl EventSource<Shell> for GraphicsCard {
 
 
 
-struct Output {
-    bo: gbm::Surface<drm::control::framebuffer::Handle>,
-    fb: Option<drm::control::framebuffer::Handle>,
-    conn: drm::control::connector::Handle,
-    crtc: drm::control::crtc::Handle,
-    mode: drm::control::Mode,
-    renderer: egl::Renderer,
-    surface: glutin::api::egl::surface::Surface<glutin::surface::WindowSurface>,
-    context: glutin::api::egl::context::PossiblyCurrentContext,
-    crtc_set: bool,
-}
-
-impl Output {
-    pub fn width(&self) -> u16 {
-        self.mode.size().0
-    }
+/// Create a GL context, falling back to GLES if GL isn't available. Each output
+/// has its own context, since contexts can only be current on one surface.
+fn create_context(
+    display: &glutin::api::egl::display::Display,
+    config: &glutin::api::egl::config::Config,
+) -> glutin::api::egl::context::NotCurrentContext {
+    let context_attributes = glutin::context::ContextAttributesBuilder::new().build(None);
+    let fallback_context_attributes = glutin::context::ContextAttributesBuilder::new()
+        .with_context_api(glutin::context::ContextApi::Gles(None))
+        .build(None);
 
-    pub fn height(&self) -> u16 {
-        self.mode.size().1
+    unsafe {
+        display
+            .create_context(config, &context_attributes)
+            .unwrap_or_else(|_| {
+                display
+                    .create_context(config, &fallback_context_attributes)
+                    .expect("failed to create context")
+            })
     }
 }
 
 
 
-fn run_abi_tests() -> Result<()> {
+fn run_abi_tests(library_dir: &std::path::Path) -> Result<()> {
     use std::{any::TypeId, mem::transmute};
 
     info!("Compiling ABI tests...");
 
     compiler::run(
-        &std::fs::read_to_string("/lib/abi_tests.rs")?,
+        &std::fs::read_to_string(library_dir.join("abi_tests.rs"))?,
         "abi_tests.rs",
         "abi_tests.so",
+        library_dir,
     )?;
 
     info!("Running ABI tests...");
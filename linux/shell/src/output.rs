@@ -0,0 +1,661 @@
+//! # Outputs
+//!
+//! Every connected display is driven as its own [`Output`], with its own CRTC,
+//! GL surface, and renderer. The outputs are arranged into a single desktop
+//! that the shell's interface spans, side by side from left to right unless
+//! their positions are set in the shell's settings, which can also choose
+//! their modes:
+//!
+//! ```text
+//! shell/output.HDMI-A-1.mode=1920x1080@60
+//! shell/output.HDMI-A-1.position=1920,0
+//! ```
+//!
+//! Outputs are named after their connectors, as listed by the `output`
+//! command, which can also change these settings while the shell is running.
+//! Outputs are added and removed as displays are plugged in and unplugged.
+//!
+//! Frames are presented with atomic commits (see [`OutputPlanes`]), which set
+//! the mode, flip the primary plane, and move the cursor plane all at once, so
+//! the cursor never tears. Outputs whose driver doesn't support atomic
+//! modesetting fall back to the legacy interface, where the cursor is moved
+//! from the input thread instead.
+//!
+//! Each output has its own cursor buffer (see [`OutputCursor`]), which is only
+//! drawn to when the output shows a different cursor icon or animation frame.
+
+use std::collections::HashMap;
+
+use {
+    drm::control::{
+        AtomicCommitFlags, Device as _, PlaneType, ResourceHandle, atomic::AtomicModeReq,
+        framebuffer, plane, property,
+    },
+    egui::{
+        Pos2, Rect,
+        epaint::{ClippedPrimitive, Primitive},
+        pos2, vec2,
+    },
+    log::warn,
+};
+
+use {abi::CursorIcon, xcursor::parser::Image};
+
+use crate::{GraphicsCard, egl, input::CursorRegion, system};
+
+
+
+pub struct Output {
+    /// The connector's name, such as `HDMI-A-1`.
+    pub name: String,
+    pub bo: gbm::Surface<drm::control::framebuffer::Handle>,
+    pub fb: Option<drm::control::framebuffer::Handle>,
+    pub conn: drm::control::connector::Handle,
+    pub crtc: drm::control::crtc::Handle,
+    pub mode: drm::control::Mode,
+    /// The output's top left corner on the desktop, see [`arrange`].
+    pub position: Pos2,
+    /// The position set in the shell's settings when the output was prepared.
+    pub configured_position: Option<Pos2>,
+    pub renderer: egl::Renderer,
+    pub surface: glutin::api::egl::surface::Surface<glutin::surface::WindowSurface>,
+    pub context: glutin::api::egl::context::PossiblyCurrentContext,
+    pub crtc_set: bool,
+    /// The planes used for atomic commits, or `None` if the output uses the
+    /// legacy interface.
+    pub planes: Option<OutputPlanes>,
+    /// The blob holding the output's mode, once it has been set with an atomic
+    /// commit.
+    pub mode_blob: Option<u64>,
+    pub cursor: OutputCursor,
+    /// Whether a page flip has been queued that hasn't completed yet, in which
+    /// case nothing else can be presented.
+    pub flip_pending: bool,
+    /// Texture changes from frames that were skipped while a flip was pending,
+    /// which are applied with the next frame this output presents.
+    pub pending_textures: egui::TexturesDelta,
+}
+
+impl Output {
+    pub fn width(&self) -> u16 {
+        self.mode.size().0
+    }
+
+    pub fn height(&self) -> u16 {
+        self.mode.size().1
+    }
+
+    /// The output's area of the desktop.
+    pub fn rect(&self) -> Rect {
+        Rect::from_min_size(
+            self.position,
+            vec2(self.width() as f32, self.height() as f32),
+        )
+    }
+
+    /// Forget what the output has presented, so that its next frame sets its
+    /// mode and cursor again, such as after another program used the graphics
+    /// card.
+    pub fn reset(&mut self) {
+        self.crtc_set = false;
+        self.flip_pending = false;
+        self.cursor.shown = None;
+    }
+
+    /// Queue the framebuffer to be shown at the next vblank, along with the
+    /// cursor, setting the output's mode first if it hasn't been set yet.
+    ///
+    /// A page flip event is sent once the frame is shown, until which the
+    /// output is [`flip_pending`](Self::flip_pending).
+    pub(crate) fn present(
+        &mut self,
+        gpu: &GraphicsCard,
+        fb: framebuffer::Handle,
+        cursor: &CursorFrame<'_>,
+    ) -> anyhow::Result<()> {
+        let first_frame = !self.crtc_set;
+        self.crtc_set = true;
+        let cursor_changed = self.cursor.update(cursor)?;
+        if let Some(planes) = &self.planes {
+            if let Some(mode_blob) = self.commit(gpu, planes, fb, cursor.position, first_frame)? {
+                self.mode_blob = Some(mode_blob);
+            }
+            self.flip_pending = true;
+            return Ok(());
+        }
+
+        if first_frame {
+            gpu.set_crtc(self.crtc, Some(fb), (0, 0), &[self.conn], Some(self.mode))?;
+        }
+        gpu.page_flip(self.crtc, fb, drm::control::PageFlipFlags::EVENT, None)?;
+        self.flip_pending = true;
+
+        if cursor_changed {
+            #[allow(deprecated)]
+            if gpu
+                .set_cursor2(self.crtc, Some(&self.cursor.buffer), self.cursor.hotspot)
+                .is_err()
+            {
+                gpu.set_cursor(self.crtc, Some(&self.cursor.buffer))?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Present the frame with an atomic commit, returning the mode's blob if
+    /// the mode was set.
+    fn commit(
+        &self,
+        gpu: &GraphicsCard,
+        planes: &OutputPlanes,
+        fb: framebuffer::Handle,
+        cursor_position: Option<Pos2>,
+        first_frame: bool,
+    ) -> anyhow::Result<Option<u64>> {
+        let mut req = AtomicModeReq::new();
+        let mut flags = AtomicCommitFlags::PAGE_FLIP_EVENT | AtomicCommitFlags::NONBLOCK;
+        let mut mode_blob = None;
+        if first_frame {
+            let blob = gpu.create_property_blob(&self.mode)?;
+            if let property::Value::Blob(id) = blob {
+                mode_blob = Some(id);
+            }
+            req.add_property(
+                self.conn,
+                planes.conn_props["CRTC_ID"],
+                property::Value::CRTC(Some(self.crtc)),
+            );
+            req.add_property(
+                self.crtc,
+                planes.crtc_props["ACTIVE"],
+                property::Value::Boolean(true),
+            );
+            req.add_property(self.crtc, planes.crtc_props["MODE_ID"], blob);
+            flags |= AtomicCommitFlags::ALLOW_MODESET;
+        }
+
+        let (width, height) = (self.width() as u32, self.height() as u32);
+        place_plane(
+            &mut req,
+            planes.primary,
+            &planes.primary_props,
+            Some((self.crtc, fb)),
+            (width, height),
+            (0, 0),
+            (width, height),
+        );
+        // The whole output is painted again every frame.
+        let damage = match planes.primary_props.get("FB_DAMAGE_CLIPS") {
+            Some(prop) => {
+                let blob = gpu.create_property_blob(&DamageRect {
+                    x1: 0,
+                    y1: 0,
+                    x2: width as i32,
+                    y2: height as i32,
+                })?;
+                req.add_property(planes.primary, *prop, blob);
+                Some(blob)
+            }
+            None => None,
+        };
+
+        if let Some((cursor_plane, cursor_props)) = &planes.cursor {
+            let position = cursor_position
+                .filter(|position| self.rect().contains(*position))
+                .map(|position| position - self.position);
+            let (hotspot_x, hotspot_y) = self.cursor.hotspot;
+            match (position, self.cursor.fb) {
+                (Some(position), Some(cursor_fb)) => {
+                    place_plane(
+                        &mut req,
+                        *cursor_plane,
+                        cursor_props,
+                        Some((self.crtc, cursor_fb)),
+                        self.cursor.size,
+                        (position.x as i32 - hotspot_x, position.y as i32 - hotspot_y),
+                        self.cursor.size,
+                    );
+                    // Virtual machines draw the cursor themselves, and need to
+                    // know where its hotspot is to do so.
+                    if let (Some(x), Some(y)) =
+                        (cursor_props.get("HOTSPOT_X"), cursor_props.get("HOTSPOT_Y"))
+                    {
+                        req.add_property(
+                            *cursor_plane,
+                            *x,
+                            property::Value::SignedRange(hotspot_x as i64),
+                        );
+                        req.add_property(
+                            *cursor_plane,
+                            *y,
+                            property::Value::SignedRange(hotspot_y as i64),
+                        );
+                    }
+                }
+                _ => place_plane(
+                    &mut req,
+                    *cursor_plane,
+                    cursor_props,
+                    None,
+                    (0, 0),
+                    (0, 0),
+                    (0, 0),
+                ),
+            }
+        }
+
+        let result = gpu.atomic_commit(flags, req);
+        if let Some(property::Value::Blob(blob)) = damage {
+            // The commit keeps its own reference to the blob.
+            let _ = gpu.destroy_property_blob(blob);
+        }
+        if let Err(error) = result {
+            if let Some(blob) = mode_blob {
+                let _ = gpu.destroy_property_blob(blob);
+            }
+            return Err(error.into());
+        }
+
+        Ok(mode_blob)
+    }
+}
+
+/// A rectangle of a framebuffer that has changed, see `FB_DAMAGE_CLIPS`.
+#[repr(C)]
+struct DamageRect {
+    x1: i32,
+    y1: i32,
+    x2: i32,
+    y2: i32,
+}
+
+/// The cursor's current image, as presented with each frame.
+pub struct CursorFrame<'a> {
+    pub icon: CursorIcon,
+    /// The image's animation frame, see [`CursorData::frame_index`].
+    ///
+    /// [`CursorData::frame_index`]: crate::cursor::CursorData::frame_index
+    pub index: usize,
+    pub image: &'a Image,
+    /// The pointer's position on the desktop.
+    pub position: Option<Pos2>,
+}
+
+/// An output's hardware cursor.
+pub struct OutputCursor {
+    buffer: gbm::BufferObject<()>,
+    /// The buffer's framebuffer, for outputs with a cursor plane.
+    fb: Option<framebuffer::Handle>,
+    size: (u32, u32),
+    hotspot: (i32, i32),
+    /// The icon and animation frame in the buffer, if anything has been drawn
+    /// to it yet.
+    shown: Option<(CursorIcon, usize)>,
+}
+
+impl OutputCursor {
+    /// Create a cursor buffer of the `size` the driver supports, with a
+    /// framebuffer if it's going to be shown on a cursor plane.
+    pub(crate) fn new(gpu: &GraphicsCard, size: (u32, u32), plane: bool) -> anyhow::Result<Self> {
+        let buffer = gpu.create_buffer_object(
+            size.0,
+            size.1,
+            gbm::Format::Argb8888,
+            gbm::BufferObjectFlags::CURSOR | gbm::BufferObjectFlags::WRITE,
+        )?;
+        let fb = if plane {
+            Some(gpu.add_framebuffer(&buffer, 32, 32)?)
+        } else {
+            None
+        };
+
+        Ok(Self {
+            buffer,
+            fb,
+            size,
+            hotspot: (0, 0),
+            shown: None,
+        })
+    }
+
+    /// Draw the frame's image into the buffer unless it's already there,
+    /// returning whether it was drawn.
+    fn update(&mut self, frame: &CursorFrame<'_>) -> anyhow::Result<bool> {
+        if self.shown == Some((frame.icon, frame.index)) {
+            return Ok(false);
+        }
+
+        let image = frame.image;
+        self.buffer.map_mut(0, 0, self.size.0, self.size.1, |map| {
+            let stride = map.stride() as usize;
+            let buffer = map.buffer_mut();
+            // Clear whatever the last image left outside of this one.
+            buffer.fill(0);
+            buffer
+                .chunks_exact_mut(stride)
+                .zip(image.pixels_rgba.chunks_exact(image.width as usize * 4))
+                .for_each(|(dst, src)| {
+                    let len = src.len().min(dst.len());
+                    dst[..len].copy_from_slice(&src[..len]);
+                });
+        })?;
+        self.hotspot = (image.xhot as _, image.yhot as _);
+        self.shown = Some((frame.icon, frame.index));
+
+        Ok(true)
+    }
+}
+
+/// The names and handles of an object's properties.
+pub type PropertyMap = HashMap<String, property::Handle>;
+
+/// The planes and properties an output is driven with using atomic commits.
+pub struct OutputPlanes {
+    pub primary: plane::Handle,
+    pub cursor: Option<(plane::Handle, PropertyMap)>,
+    conn_props: PropertyMap,
+    crtc_props: PropertyMap,
+    primary_props: PropertyMap,
+}
+
+impl OutputPlanes {
+    const CONNECTOR_PROPS: &[&str] = &["CRTC_ID"];
+    const CRTC_PROPS: &[&str] = &["ACTIVE", "MODE_ID"];
+    const PLANE_PROPS: &[&str] = &[
+        "FB_ID", "CRTC_ID", "SRC_X", "SRC_Y", "SRC_W", "SRC_H", "CRTC_X", "CRTC_Y", "CRTC_W",
+        "CRTC_H",
+    ];
+
+    /// Find a primary plane (and a cursor plane, if there is one) for the CRTC
+    /// that isn't in `used_planes`, returning `None` if there isn't one or any
+    /// of the properties needed for atomic commits are missing.
+    pub(crate) fn find(
+        gpu: &GraphicsCard,
+        conn: drm::control::connector::Handle,
+        crtc: drm::control::crtc::Handle,
+        used_planes: &[plane::Handle],
+    ) -> Option<Self> {
+        let resources = gpu.resource_handles().ok()?;
+        let mut primary = None;
+        let mut cursor = None;
+        for plane in gpu.plane_handles().ok()? {
+            if used_planes.contains(&plane) {
+                continue;
+            }
+            let Ok(info) = gpu.get_plane(plane) else {
+                continue;
+            };
+            if !resources
+                .filter_crtcs(info.possible_crtcs())
+                .contains(&crtc)
+            {
+                continue;
+            }
+            match plane_type(gpu, plane) {
+                Some(PlaneType::Primary) if primary.is_none() => primary = Some(plane),
+                Some(PlaneType::Cursor) if cursor.is_none() => cursor = Some(plane),
+                _ => {}
+            }
+        }
+
+        let primary = primary?;
+        let primary_props = properties(gpu, primary, Self::PLANE_PROPS)?;
+        Some(Self {
+            primary,
+            cursor: cursor
+                .and_then(|cursor| Some((cursor, properties(gpu, cursor, Self::PLANE_PROPS)?))),
+            conn_props: properties(gpu, conn, Self::CONNECTOR_PROPS)?,
+            crtc_props: properties(gpu, crtc, Self::CRTC_PROPS)?,
+            primary_props,
+        })
+    }
+
+    /// Every plane used by the output.
+    pub fn handles(&self) -> impl Iterator<Item = plane::Handle> + '_ {
+        std::iter::once(self.primary).chain(self.cursor.as_ref().map(|(cursor, _)| *cursor))
+    }
+}
+
+/// The object's properties, returning `None` if any of the `required` ones are
+/// missing.
+fn properties(
+    gpu: &GraphicsCard,
+    handle: impl ResourceHandle,
+    required: &[&str],
+) -> Option<PropertyMap> {
+    let props = gpu
+        .get_properties(handle)
+        .ok()?
+        .as_hashmap(gpu)
+        .ok()?
+        .into_iter()
+        .map(|(name, info)| (name, info.handle()))
+        .collect::<PropertyMap>();
+
+    required
+        .iter()
+        .all(|name| props.contains_key(*name))
+        .then_some(props)
+}
+
+fn plane_type(gpu: &GraphicsCard, plane: plane::Handle) -> Option<PlaneType> {
+    let props = gpu.get_properties(plane).ok()?;
+    let (handles, values) = props.as_props_and_values();
+    let value = handles.iter().zip(values).find_map(|(handle, value)| {
+        let info = gpu.get_property(*handle).ok()?;
+        (info.name().to_bytes() == b"type").then_some(*value)
+    })?;
+
+    [PlaneType::Primary, PlaneType::Cursor, PlaneType::Overlay]
+        .into_iter()
+        .find(|plane_type| *plane_type as u64 == value)
+}
+
+/// Show the framebuffer on the CRTC with the plane, or disable it with `None`.
+///
+/// Source coordinates are in 16.16 fixed point.
+fn place_plane(
+    req: &mut AtomicModeReq,
+    plane: plane::Handle,
+    props: &PropertyMap,
+    target: Option<(drm::control::crtc::Handle, framebuffer::Handle)>,
+    src_size: (u32, u32),
+    position: (i32, i32),
+    size: (u32, u32),
+) {
+    let (crtc, fb) = target.unzip();
+    req.add_property(plane, props["FB_ID"], property::Value::Framebuffer(fb));
+    req.add_property(plane, props["CRTC_ID"], property::Value::CRTC(crtc));
+    req.add_property(plane, props["SRC_X"], property::Value::UnsignedRange(0));
+    req.add_property(plane, props["SRC_Y"], property::Value::UnsignedRange(0));
+    req.add_property(
+        plane,
+        props["SRC_W"],
+        property::Value::UnsignedRange((src_size.0 as u64) << 16),
+    );
+    req.add_property(
+        plane,
+        props["SRC_H"],
+        property::Value::UnsignedRange((src_size.1 as u64) << 16),
+    );
+    req.add_property(
+        plane,
+        props["CRTC_X"],
+        property::Value::SignedRange(position.0 as i64),
+    );
+    req.add_property(
+        plane,
+        props["CRTC_Y"],
+        property::Value::SignedRange(position.1 as i64),
+    );
+    req.add_property(
+        plane,
+        props["CRTC_W"],
+        property::Value::UnsignedRange(size.0 as u64),
+    );
+    req.add_property(
+        plane,
+        props["CRTC_H"],
+        property::Value::UnsignedRange(size.1 as u64),
+    );
+}
+
+/// An output's mode and position, as set in the shell's settings.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct OutputConfig {
+    pub mode: Option<ModeRequest>,
+    pub position: Option<Pos2>,
+}
+
+impl OutputConfig {
+    /// Load the configuration for the output with the given name, ignoring (and
+    /// logging) invalid settings.
+    pub fn load(name: &str) -> Self {
+        let mut config = Self::default();
+        if let Some(mode) = system::shell_setting(&format!("output.{name}.mode")) {
+            config.mode = ModeRequest::parse(&mode);
+            if config.mode.is_none() {
+                warn!("Invalid mode for output {name}: '{mode}'");
+            }
+        }
+        if let Some(position) = system::shell_setting(&format!("output.{name}.position")) {
+            config.position = position
+                .split_once(',')
+                .and_then(|(x, y)| Some(pos2(x.trim().parse().ok()?, y.trim().parse().ok()?)));
+            if config.position.is_none() {
+                warn!("Invalid position for output {name}: '{position}'");
+            }
+        }
+
+        config
+    }
+}
+
+/// A mode requested for an output, written as `WIDTHxHEIGHT` or
+/// `WIDTHxHEIGHT@REFRESH`.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct ModeRequest {
+    pub width: u16,
+    pub height: u16,
+    pub refresh: Option<u32>,
+}
+
+impl ModeRequest {
+    pub fn parse(value: &str) -> Option<Self> {
+        let (size, refresh) = match value.trim().split_once('@') {
+            Some((size, refresh)) => (size, Some(refresh.parse().ok()?)),
+            None => (value.trim(), None),
+        };
+        let (width, height) = size.split_once('x')?;
+
+        Some(Self {
+            width: width.parse().ok()?,
+            height: height.parse().ok()?,
+            refresh,
+        })
+    }
+
+    fn matches(&self, mode: &drm::control::Mode) -> bool {
+        mode.size() == (self.width, self.height)
+            && self
+                .refresh
+                .is_none_or(|refresh| mode.vrefresh() == refresh)
+    }
+}
+
+/// Choose the requested mode if the connector supports it, falling back to its
+/// preferred mode, and then to its first.
+pub fn choose_mode(
+    modes: &[drm::control::Mode],
+    request: Option<ModeRequest>,
+) -> Option<drm::control::Mode> {
+    request
+        .and_then(|request| modes.iter().find(|mode| request.matches(mode)))
+        .or_else(|| {
+            modes.iter().find(|mode| {
+                mode.mode_type()
+                    .contains(drm::control::ModeTypeFlags::PREFERRED)
+            })
+        })
+        .or_else(|| modes.first())
+        .copied()
+}
+
+/// Position every output at its configured position, placing those without
+/// one to the right of all the others.
+pub fn arrange(outputs: &mut [Output]) {
+    let mut right = outputs
+        .iter()
+        .filter_map(|output| {
+            output
+                .configured_position
+                .map(|position| position.x + output.width() as f32)
+        })
+        .fold(0.0, f32::max);
+
+    for output in outputs {
+        output.position = output.configured_position.unwrap_or_else(|| {
+            let position = pos2(right, 0.0);
+            right += output.width() as f32;
+            position
+        });
+    }
+}
+
+/// The area covered by every output.
+pub fn desktop_rect(outputs: &[Output]) -> Rect {
+    outputs
+        .iter()
+        .map(Output::rect)
+        .reduce(|a, b| a.union(b))
+        .unwrap_or(Rect::ZERO)
+}
+
+/// Every plane used by the outputs, which can't be used by another output.
+pub fn used_planes(outputs: &[Output]) -> Vec<plane::Handle> {
+    outputs
+        .iter()
+        .filter_map(|output| output.planes.as_ref())
+        .flat_map(OutputPlanes::handles)
+        .collect()
+}
+
+/// The areas of the desktop that the hardware cursor is shown on each output.
+pub fn cursor_regions(outputs: &[Output]) -> Vec<CursorRegion> {
+    outputs
+        .iter()
+        .map(|output| CursorRegion {
+            crtc: output.crtc,
+            rect: output.rect(),
+            legacy: output.planes.is_none(),
+        })
+        .collect()
+}
+
+/// Move the primitives from desktop coordinates to those of an output at the
+/// given position.
+pub fn primitives_for_output(
+    primitives: &[ClippedPrimitive],
+    position: Pos2,
+) -> Vec<ClippedPrimitive> {
+    let offset = -position.to_vec2();
+    primitives
+        .iter()
+        .map(|clipped| ClippedPrimitive {
+            clip_rect: clipped.clip_rect.translate(offset),
+            primitive: match &clipped.primitive {
+                Primitive::Mesh(mesh) => {
+                    let mut mesh = mesh.clone();
+                    mesh.translate(offset);
+                    Primitive::Mesh(mesh)
+                }
+                Primitive::Callback(callback) => {
+                    let mut callback = callback.clone();
+                    callback.rect = callback.rect.translate(offset);
+                    Primitive::Callback(callback)
+                }
+            },
+        })
+        .collect()
+}
@@ -0,0 +1,253 @@
+//! # Performance Statistics
+//!
+//! Timing for the shell's recent frames, for diagnosing jank on real hardware:
+//! how long each frame took to draw on the CPU, how long each output took to
+//! show it once it was presented (its flip latency), and how many vblanks
+//! passed without a new frame. The number of commands in each program's last
+//! render is tracked too, since long renders are the usual cause of slow
+//! frames.
+//!
+//! The statistics are printed by the `perf` command, which can also show them
+//! in a corner of the screen (see the `perf.hud` setting).
+
+use std::{
+    collections::{BTreeMap, HashMap, VecDeque},
+    fmt::Write as _,
+    time::{Duration, Instant},
+};
+
+use crate::{FRAME_INTERVAL, system};
+
+
+
+/// The number of frames that statistics are kept for.
+pub const HISTORY_LEN: usize = 240;
+
+#[derive(Default)]
+pub struct FrameStats {
+    /// The most recent frames, oldest first.
+    frames: VecDeque<FrameSample>,
+    frame_count: u64,
+    missed_vblanks: u64,
+    outputs: HashMap<String, OutputTiming>,
+    /// The number of commands in each program's renders, by name.
+    programs: BTreeMap<String, ProgramStats>,
+    hud: bool,
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct FrameSample {
+    /// How long the frame took to draw and present, before the GPU shows it.
+    pub cpu_time: Duration,
+    /// The longest time between presenting the frame on an output and it being
+    /// shown, or `None` if it hasn't been shown yet.
+    pub flip_latency: Option<Duration>,
+}
+
+#[derive(Default)]
+struct OutputTiming {
+    /// When the output's last frame was presented, until it's shown.
+    presented: Option<Instant>,
+    /// The vblank that the output's last frame was shown at.
+    last_vblank: Option<u32>,
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct ProgramStats {
+    pub renders: u64,
+    pub last_commands: usize,
+    pub max_commands: usize,
+}
+
+/// The statistics of [`FrameStats::frames`].
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Summary {
+    pub frames: usize,
+    pub average_cpu_time: Duration,
+    pub max_cpu_time: Duration,
+    pub average_flip_latency: Option<Duration>,
+    pub max_flip_latency: Option<Duration>,
+}
+
+impl FrameStats {
+    /// Create an empty history, showing the HUD if `perf.hud` is set.
+    pub fn load() -> Self {
+        Self {
+            hud: system::shell_setting("perf.hud").is_some_and(|value| value == "on"),
+            ..Self::default()
+        }
+    }
+
+    pub fn hud(&self) -> bool {
+        self.hud
+    }
+
+    /// Show or hide the HUD, saving it to `perf.hud`.
+    pub fn set_hud(&mut self, hud: bool) {
+        self.hud = hud;
+        system::set_shell_setting("perf.hud", if hud { "on" } else { "off" });
+    }
+
+    /// Forget every frame and program.
+    pub fn reset(&mut self) {
+        *self = Self {
+            hud: self.hud,
+            ..Self::default()
+        };
+    }
+
+    pub fn frames(&self) -> impl Iterator<Item = &FrameSample> {
+        self.frames.iter()
+    }
+
+    /// Record a frame that was drawn to at least one output.
+    pub fn record_frame(&mut self, cpu_time: Duration) {
+        if self.frames.len() == HISTORY_LEN {
+            self.frames.pop_front();
+        }
+        self.frames.push_back(FrameSample {
+            cpu_time,
+            flip_latency: None,
+        });
+        self.frame_count += 1;
+    }
+
+    /// Record the number of commands in a program's render.
+    pub fn record_render(&mut self, program: &str, commands: usize) {
+        let stats = self.programs.entry(program.to_string()).or_default();
+        stats.renders += 1;
+        stats.last_commands = commands;
+        stats.max_commands = stats.max_commands.max(commands);
+    }
+
+    /// Note that an output was given a frame to show.
+    pub fn presented(&mut self, output: &str) {
+        self.outputs
+            .entry(output.to_string())
+            .or_default()
+            .presented = Some(Instant::now());
+    }
+
+    /// Note that an output showed its last frame at the given vblank.
+    ///
+    /// The shell draws a new frame as soon as the last one is shown, so any
+    /// vblanks in between were missed.
+    pub fn flipped(&mut self, output: &str, vblank: u32) {
+        let timing = self.outputs.entry(output.to_string()).or_default();
+        if let Some(last) = timing.last_vblank.replace(vblank) {
+            self.missed_vblanks += vblank.wrapping_sub(last).saturating_sub(1) as u64;
+        }
+        let Some(presented) = timing.presented.take() else {
+            return;
+        };
+        let latency = presented.elapsed();
+        if let Some(frame) = self.frames.back_mut() {
+            frame.flip_latency = Some(frame.flip_latency.unwrap_or_default().max(latency));
+        }
+    }
+
+    /// Forget when each output last showed a frame, such as while another VT
+    /// was using them, so that the time in between isn't counted as missed
+    /// vblanks.
+    pub fn pause(&mut self) {
+        self.outputs.clear();
+    }
+
+    pub fn summary(&self) -> Summary {
+        let frames = self.frames.len();
+        if frames == 0 {
+            return Summary::default();
+        }
+        let cpu_times = self.frames.iter().map(|frame| frame.cpu_time);
+        let latencies = self
+            .frames
+            .iter()
+            .filter_map(|frame| frame.flip_latency)
+            .collect::<Vec<_>>();
+
+        Summary {
+            frames,
+            average_cpu_time: cpu_times.clone().sum::<Duration>() / frames as u32,
+            max_cpu_time: cpu_times.max().unwrap_or_default(),
+            average_flip_latency: (!latencies.is_empty())
+                .then(|| latencies.iter().sum::<Duration>() / latencies.len() as u32),
+            max_flip_latency: latencies.into_iter().max(),
+        }
+    }
+
+    /// The statistics as text, with one on each line.
+    pub fn report(&self) -> String {
+        let summary = self.summary();
+        let mut report = String::new();
+        let _ = writeln!(
+            report,
+            "frames    {} ({} recent)",
+            self.frame_count, summary.frames,
+        );
+        let _ = writeln!(
+            report,
+            "cpu       {} avg, {} max",
+            millis(summary.average_cpu_time),
+            millis(summary.max_cpu_time),
+        );
+        let _ = match (summary.average_flip_latency, summary.max_flip_latency) {
+            (Some(average), Some(max)) => writeln!(
+                report,
+                "flip      {} avg, {} max",
+                millis(average),
+                millis(max),
+            ),
+            _ => writeln!(report, "flip      -"),
+        };
+        let _ = writeln!(report, "missed    {} vblanks", self.missed_vblanks);
+        for (program, stats) in &self.programs {
+            let _ = writeln!(
+                report,
+                "{program:<9} {} commands ({} max, {} renders)",
+                stats.last_commands, stats.max_commands, stats.renders,
+            );
+        }
+
+        report
+    }
+}
+
+/// Show the statistics in the bottom right corner of the screen, above a graph
+/// of each recent frame's CPU time (where frames that took longer than the
+/// frame interval are red).
+pub fn show_hud(ctx: &egui::Context, stats: &FrameStats) {
+    const GRAPH_HEIGHT: f32 = 40.0;
+
+    egui::Area::new(egui::Id::new("perf-hud"))
+        .anchor(egui::Align2::RIGHT_BOTTOM, [-8.0, -8.0])
+        .interactable(false)
+        .show(ctx, |ui| {
+            egui::Frame::popup(ui.style()).show(ui, |ui| {
+                ui.label(egui::RichText::new(stats.report()).monospace().small());
+
+                let (rect, _) = ui.allocate_exact_size(
+                    egui::vec2(HISTORY_LEN as f32, GRAPH_HEIGHT),
+                    egui::Sense::hover(),
+                );
+                let painter = ui.painter_at(rect);
+                for (index, frame) in stats.frames().enumerate() {
+                    let fraction = frame.cpu_time.as_secs_f32() / FRAME_INTERVAL.as_secs_f32();
+                    let color = if fraction > 1.0 {
+                        egui::Color32::from_rgb(0xe0, 0x50, 0x50)
+                    } else {
+                        egui::Color32::from_rgb(0x70, 0xc0, 0x70)
+                    };
+                    let x = rect.left() + index as f32;
+                    let top = rect.bottom() - fraction.min(1.0) * GRAPH_HEIGHT;
+                    painter.line_segment(
+                        [egui::pos2(x, rect.bottom()), egui::pos2(x, top)],
+                        egui::Stroke::new(1.0, color),
+                    );
+                }
+            });
+        });
+}
+
+fn millis(duration: Duration) -> String {
+    format!("{:.2}ms", duration.as_secs_f64() * 1000.0)
+}
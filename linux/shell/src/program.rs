@@ -0,0 +1,583 @@
+//! # Running Programs
+//!
+//! An application started with the `run` command is shown in the shell's
+//! central panel. Its main view is drawn with the native
+//! [`ViewRenderer`](crate::render::ViewRenderer) rather than through egui, and
+//! its text is measured with [`GlyphFonts`], so that it looks the same as it
+//! would in a driver.
+//!
+//! Input over the panel is sent to the view through an input channel (see
+//! [`linux_uapi::channel`]) alongside a [`DriverInput`], and its renders come
+//! back through another, the same way they would with a driver. The input
+//! channel's capacity is agreed on with a [`DriverHandshake`] first, see
+//! [`negotiate_event_capacity`]. Only the main view is shown, so views that the
+//! application opens with [`abi::system::open_view`] are ignored.
+
+use std::{
+    path::Path,
+    time::{Duration, Instant},
+};
+
+use {
+    abi::{
+        Aabb2D, AppViews, DriverControl, DriverHandshake, DriverInput, DriverInputEvent, Key,
+        KeyboardEvent, MAIN_VIEW, Manifest, Modifiers, PointerButton, PointerEvent, Render,
+        RenderFrame, RenderReceiver, RenderSender, ScrollDelta, ViewId, Xy,
+    },
+    anyhow::{Result, anyhow, bail},
+    linux_uapi::{channel, object::Object, shm::Mailbox},
+    log::{debug, info, warn},
+};
+
+use crate::{
+    config::Config,
+    render::{GlyphFonts, RenderTarget, ViewRenderer},
+    system::{self, Caller},
+};
+
+
+
+/// The number of input events that the main view asks to be able to queue
+/// between frames.
+const EVENT_CAPACITY: u32 = 256;
+/// How long the shell waits for a driver's [`DriverHandshake`].
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(1);
+/// The number of render pages that can be sent before the shell takes them.
+pub const RENDER_PAGE_CAPACITY: u32 = 4;
+
+/// Open the application at `path`, and check that it was built for the shell's
+/// ABI version.
+pub fn open_app(path: &Path) -> Result<(Object, &'static Manifest)> {
+    let app_obj = unsafe { Object::open(path.as_os_str().as_encoded_bytes()) }
+        .map_err(|error| anyhow!("failed to open application: {error:?}"))?;
+    let manifest: &'static Manifest = *app_obj
+        .get::<_, &'static Manifest>("__MANIFEST")
+        .ok_or_else(|| anyhow!("application has no manifest"))?;
+    if manifest.abi_version != abi::VERSION {
+        bail!(
+            "{} was built for ABI version {}, but the shell uses {}",
+            manifest.name,
+            manifest.abi_version,
+            abi::VERSION,
+        );
+    }
+
+    Ok((app_obj, manifest))
+}
+
+/// The memory of a channel whose ends are both in this process, so that it
+/// doesn't need to be shared. It must outlive both ends.
+pub struct LocalChannel {
+    /// Aligned for the channel's header.
+    _memory: Box<[u64]>,
+}
+
+impl LocalChannel {
+    /// Create a channel of `capacity` messages, which must be a power of two.
+    pub fn new<T>(capacity: u32) -> Result<(Self, channel::Sender<T>, channel::Receiver<T>)> {
+        let mut memory =
+            vec![0u64; channel::required_size::<T>(capacity).div_ceil(size_of::<u64>())]
+                .into_boxed_slice();
+        let base = memory.as_mut_ptr() as *mut u8;
+        unsafe {
+            channel::init::<T>(base, capacity)?;
+            Ok((
+                Self { _memory: memory },
+                channel::Sender::from_existing(base)?,
+                channel::Receiver::from_existing(base)?,
+            ))
+        }
+    }
+}
+
+/// Pass a driver's handshake to its host through a [`Mailbox`], the same way
+/// a driver in another process would, returning the capacity of the input
+/// channel that the host agrees to.
+pub fn negotiate_event_capacity(handshake: DriverHandshake) -> Result<u32> {
+    let mut memory =
+        vec![0u64; Mailbox::<DriverHandshake>::SIZE.div_ceil(size_of::<u64>())].into_boxed_slice();
+    let base = memory.as_mut_ptr() as *mut u8;
+    let (host, driver) = unsafe {
+        (
+            Mailbox::<DriverHandshake>::new(base)?,
+            Mailbox::<DriverHandshake>::from_existing(base)?,
+        )
+    };
+
+    driver.offer(handshake)?;
+    let handshake = host.take(HANDSHAKE_TIMEOUT)?;
+
+    Ok(handshake.negotiated_event_capacity())
+}
+
+/// Send an input event, coalescing pointer moves while the driver is falling
+/// behind. Returns `false` if the event was discarded because the channel was
+/// full.
+pub fn send_event(
+    sender: &mut channel::Sender<DriverInputEvent>,
+    event: DriverInputEvent,
+) -> linux_uapi::Result<bool> {
+    sender.send_coalesced(event, DriverInputEvent::coalesces)
+}
+
+/// Receive every input event that has been sent, like a driver would, setting
+/// back pressure while the channel was more than half full.
+pub fn receive_events(
+    receiver: &channel::Receiver<DriverInputEvent>,
+) -> impl Iterator<Item = DriverInputEvent> + '_ {
+    receiver.set_back_pressure(receiver.len() * 2 > receiver.capacity());
+    receiver.drain()
+}
+
+/// Send the pages of the render that `render_sender` was started with while
+/// there's room for them, returning `true` once every page has been sent.
+pub fn send_render_pages(
+    render_sender: &mut RenderSender,
+    pages: &channel::Sender<RenderFrame>,
+) -> linux_uapi::Result<bool> {
+    let mut frame = RenderFrame::default();
+    while pages.len() < pages.capacity() {
+        if !render_sender.write_page(&mut frame) {
+            return Ok(true);
+        }
+        // NOTE: This never blocks, since there's room for the page and this is
+        //       the only sender.
+        pages.send(core::mem::take(&mut frame))?;
+    }
+
+    Ok(!render_sender.is_sending())
+}
+
+/// Take every render page that has been sent, returning `true` if a render
+/// was completely received.
+pub fn receive_render_pages(
+    render_receiver: &mut RenderReceiver,
+    pages: &channel::Receiver<RenderFrame>,
+) -> bool {
+    pages.drain().fold(false, |received, frame| {
+        render_receiver.receive(&frame) || received
+    })
+}
+
+
+
+/// An application shown in the shell, see the [module docs](self).
+pub struct Program {
+    manifest: &'static Manifest,
+    /// The views' elements live in the application's object, so this is
+    /// dropped before the object.
+    driver: Driver,
+    /// The shell's end of the input channel.
+    events: channel::Sender<DriverInputEvent>,
+    /// The shell's end of the render channel.
+    render_pages: channel::Receiver<RenderFrame>,
+    /// Holds the main view's latest render, which is drawn every frame until
+    /// the view renders again.
+    render_receiver: RenderReceiver,
+    /// Where the main view is shown, in the interface's coordinates.
+    rect: Aabb2D,
+    /// The title the application last gave its main view.
+    title: Option<String>,
+    _channels: [LocalChannel; 2],
+    _app_obj: Object,
+}
+
+/// The main view's side of a [`Program`], which only has what it would have in
+/// a driver: its [`DriverInput`] and its ends of the channels.
+struct Driver {
+    views: AppViews,
+    main_view: ViewId,
+    input: Box<DriverInput>,
+    events: channel::Receiver<DriverInputEvent>,
+    render: Render,
+    render_sender: RenderSender,
+    render_pages: channel::Sender<RenderFrame>,
+}
+
+impl Driver {
+    /// Handle the input that was sent, and render the main view if it has
+    /// changed since the last vsync.
+    fn update(&mut self, manifest: &Manifest, now: Instant) {
+        let Self {
+            views,
+            main_view,
+            input,
+            events,
+            render,
+            render_sender,
+            render_pages,
+        } = self;
+
+        views.handle_input(input, receive_events(events));
+        for request in system::take_view_requests() {
+            debug!(
+                "{} can't open other views in the shell: {request:?}",
+                manifest.name
+            );
+        }
+
+        if views.should_render(input)
+            && let Some(view) = views.get_mut(*main_view)
+        {
+            render.clear();
+            view.process_updates();
+            view.begin_frame(now);
+            view.render(render);
+            view.end_frame();
+            for event in view.poll_events() {
+                debug!("{}: {event:?}", manifest.name);
+            }
+            input.present();
+            render_sender.start(render);
+        }
+        if let Err(error) = send_render_pages(render_sender, render_pages) {
+            warn!("Failed to send {}'s render: {error}", manifest.name);
+        }
+
+        views.write_controls(input);
+    }
+}
+
+impl Program {
+    pub fn load(path: &Path, config: &Config) -> Result<Self> {
+        let (app_obj, manifest) = open_app(path)?;
+        info!("Running {}...", manifest.name);
+
+        // The fonts are built in, so if they load once they always do.
+        GlyphFonts::new()?;
+        let mut views = AppViews::new(manifest, || {
+            Box::new(GlyphFonts::new().expect("built-in fonts should load"))
+        });
+        // The view is resized to fit the panel once it's shown.
+        let size = Xy::new(1.0, 1.0);
+        let main_view = system::with_caller(Caller::App(manifest), || views.open(MAIN_VIEW, size))?;
+        if let Some(view) = views.get_mut(main_view) {
+            config.apply_to_view(view);
+        }
+        let mut input = Box::new(DriverInput::new(Aabb2D::new(0.0, 0.0, size.x, size.y)));
+        input.id = main_view.0;
+
+        let handshake = DriverHandshake {
+            requested_event_capacity: EVENT_CAPACITY,
+        };
+        let (event_channel, event_sender, event_receiver) =
+            LocalChannel::new(negotiate_event_capacity(handshake)?)?;
+        let (render_channel, page_sender, page_receiver) = LocalChannel::new(RENDER_PAGE_CAPACITY)?;
+
+        Ok(Self {
+            manifest,
+            driver: Driver {
+                views,
+                main_view,
+                input,
+                events: event_receiver,
+                render: Render::default(),
+                render_sender: RenderSender::new(),
+                render_pages: page_sender,
+            },
+            events: event_sender,
+            render_pages: page_receiver,
+            render_receiver: RenderReceiver::new(),
+            rect: Aabb2D::new(0.0, 0.0, size.x, size.y),
+            title: None,
+            _channels: [event_channel, render_channel],
+            _app_obj: app_obj,
+        })
+    }
+
+    pub fn name(&self) -> &'static str {
+        self.manifest.name
+    }
+
+    /// The main view's title, or the application's name if it hasn't set one.
+    pub fn title(&self) -> &str {
+        self.title.as_deref().unwrap_or(self.manifest.name)
+    }
+
+    /// Take up the rest of the UI with the main view, and send it the input
+    /// over it.
+    ///
+    /// Keyboard input is only sent once the view has been clicked, which
+    /// gives it the keyboard focus.
+    pub fn show(&mut self, ui: &mut egui::Ui) {
+        let (rect, response) = ui.allocate_exact_size(ui.available_size(), egui::Sense::click());
+        if response.clicked() {
+            response.request_focus();
+        }
+        // Keys that move the focus between widgets go to the view instead.
+        ui.memory_mut(|memory| {
+            memory.set_focus_lock_filter(
+                response.id,
+                egui::EventFilter {
+                    tab: true,
+                    horizontal_arrows: true,
+                    vertical_arrows: true,
+                    escape: true,
+                },
+            );
+        });
+
+        let rect = Aabb2D::new(rect.min.x, rect.min.y, rect.max.x, rect.max.y);
+        if rect.size() != self.rect.size() {
+            self.send(DriverInputEvent::WindowResize(Aabb2D::new(
+                0.0,
+                0.0,
+                rect.size().x,
+                rect.size().y,
+            )));
+        }
+        self.rect = rect;
+
+        let focused = response.has_focus();
+        let hovered = response.contains_pointer();
+        let (events, modifiers) = ui.input(|input| (input.events.clone(), input.modifiers));
+        for event in &events {
+            self.send_egui_event(event, egui_to_abi_modifiers(modifiers), focused, hovered);
+        }
+    }
+
+    fn send(&mut self, event: DriverInputEvent) {
+        match send_event(&mut self.events, event) {
+            Ok(true) => {}
+            Ok(false) => debug!(
+                "Too many input events for {}, missed: {event:?}",
+                self.title()
+            ),
+            Err(error) => warn!("Failed to send input to {}: {error}", self.title()),
+        }
+    }
+
+    fn send_egui_event(
+        &mut self,
+        event: &egui::Event,
+        modifiers: Modifiers,
+        focused: bool,
+        hovered: bool,
+    ) {
+        let rect = self.rect;
+        let local = |pos: egui::Pos2| Xy::new(pos.x - rect.min.x, pos.y - rect.min.y);
+        let contains = |pos: egui::Pos2| {
+            let position = local(pos);
+            let size = rect.size();
+            position.x >= 0.0 && position.y >= 0.0 && position.x < size.x && position.y < size.y
+        };
+
+        match event {
+            egui::Event::PointerMoved(pos) if contains(*pos) => {
+                self.send(DriverInputEvent::Pointer(PointerEvent::Move {
+                    position: local(*pos),
+                    modifiers,
+                }));
+            }
+            egui::Event::PointerButton {
+                pos,
+                button,
+                pressed,
+                modifiers,
+            } => {
+                let Some(button) = egui_to_abi_pointer_button(*button) else {
+                    return;
+                };
+                let modifiers = egui_to_abi_modifiers(*modifiers);
+                if !pressed {
+                    // Buttons are released even outside of the view, so that it
+                    // never thinks they're still held.
+                    self.send(DriverInputEvent::Pointer(PointerEvent::Up {
+                        button,
+                        modifiers,
+                    }));
+                } else if contains(*pos) {
+                    self.send(DriverInputEvent::Pointer(PointerEvent::Down {
+                        button,
+                        position: local(*pos),
+                        modifiers,
+                    }));
+                }
+            }
+            egui::Event::MouseWheel {
+                unit,
+                delta,
+                modifiers,
+                ..
+            } if hovered => {
+                let delta = match unit {
+                    egui::MouseWheelUnit::Point => ScrollDelta::Pixels(Xy::new(delta.x, delta.y)),
+                    egui::MouseWheelUnit::Line => ScrollDelta::Lines(Xy::new(delta.x, delta.y)),
+                    egui::MouseWheelUnit::Page => ScrollDelta::Pixels(Xy::new(
+                        delta.x * rect.size().x,
+                        delta.y * rect.size().y,
+                    )),
+                };
+                self.send(DriverInputEvent::Pointer(PointerEvent::Scroll {
+                    delta,
+                    modifiers: egui_to_abi_modifiers(*modifiers),
+                }));
+            }
+            egui::Event::Key {
+                key,
+                pressed,
+                repeat,
+                modifiers,
+                ..
+            } if focused => {
+                let modifiers = egui_to_abi_modifiers(*modifiers);
+                // NOTE: Printable keys are sent with their text, unless they're part
+                //       of a shortcut, which doesn't produce any.
+                let shortcut = modifiers.ctrl || modifiers.alt || modifiers.meta;
+                let Some(key) = egui_to_abi_key(*key, shortcut) else {
+                    return;
+                };
+                self.send(DriverInputEvent::Keyboard(if *pressed {
+                    KeyboardEvent::Down {
+                        key,
+                        modifiers,
+                        repeat: *repeat,
+                    }
+                } else {
+                    KeyboardEvent::Up { key, modifiers }
+                }));
+            }
+            egui::Event::Text(text) if focused => {
+                for ch in text.chars().filter(|ch| !ch.is_control() && *ch != ' ') {
+                    let key = Key::Char(ch);
+                    self.send(DriverInputEvent::Keyboard(KeyboardEvent::Down {
+                        key,
+                        modifiers,
+                        repeat: false,
+                    }));
+                    self.send(DriverInputEvent::Keyboard(KeyboardEvent::Up {
+                        key,
+                        modifiers,
+                    }));
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Hand the input that was sent to the main view over to it, and take its
+    /// render if it has changed since the last vsync, which the shell has once
+    /// per frame.
+    ///
+    /// Returns `false` once the main view has been closed, or the application
+    /// asks for it to be.
+    pub fn update(&mut self, now: Instant) -> bool {
+        let Self {
+            manifest,
+            driver,
+            events,
+            render_pages,
+            render_receiver,
+            title,
+            ..
+        } = self;
+
+        // Pointer moves may have been held back while the view was busy.
+        if let Err(error) = events.flush() {
+            warn!("Failed to send input to {}: {error}", manifest.name);
+        }
+        driver.input.signal_vsync();
+        system::with_caller(Caller::App(manifest), || driver.update(manifest, now));
+        receive_render_pages(render_receiver, render_pages);
+
+        let mut open = true;
+        for control in driver.input.drain_controls() {
+            match control {
+                DriverControl::SetTitle(new_title) => {
+                    *title = Some(new_title.as_str().to_string());
+                }
+                DriverControl::RequestClose => open = false,
+                // The shell renders every frame anyway, and draws its own
+                // cursor.
+                DriverControl::RequestRedraw | DriverControl::SetCursorIcon(_) => {}
+            }
+        }
+
+        open
+    }
+
+    /// Draw the main view's latest render where it was last shown, on an
+    /// output at the given position in the interface.
+    pub fn draw(
+        &self,
+        renderer: &mut ViewRenderer,
+        screen_size: Xy<f32>,
+        output_position: Xy<f32>,
+    ) {
+        let to_output =
+            |point: Xy<f32>| Xy::new(point.x - output_position.x, point.y - output_position.y);
+        let (min, max) = (to_output(self.rect.min), to_output(self.rect.max));
+        renderer.draw(
+            self.render_receiver.render(),
+            RenderTarget {
+                screen_size,
+                offset: min,
+                scale: 1.0,
+                clip: Some(Aabb2D::new(min.x, min.y, max.x, max.y)),
+            },
+        );
+    }
+
+    /// The number of commands in the main view's latest render.
+    pub fn render_len(&self) -> usize {
+        self.render_receiver.render().commands.len()
+    }
+}
+
+impl Drop for Program {
+    fn drop(&mut self) {
+        info!("Stopped running {}", self.manifest.name);
+    }
+}
+
+
+
+fn egui_to_abi_modifiers(modifiers: egui::Modifiers) -> Modifiers {
+    Modifiers {
+        ctrl: modifiers.ctrl,
+        shift: modifiers.shift,
+        alt: modifiers.alt,
+        meta: modifiers.mac_cmd,
+    }
+}
+
+fn egui_to_abi_pointer_button(button: egui::PointerButton) -> Option<PointerButton> {
+    Some(match button {
+        egui::PointerButton::Primary => PointerButton::Primary,
+        egui::PointerButton::Secondary => PointerButton::Secondary,
+        egui::PointerButton::Middle => PointerButton::Auxiliary,
+        egui::PointerButton::Extra1 => PointerButton::Back,
+        egui::PointerButton::Extra2 => PointerButton::Forward,
+    })
+}
+
+/// Convert a key that isn't sent as text, which printable keys only are when
+/// they're part of a shortcut.
+fn egui_to_abi_key(key: egui::Key, shortcut: bool) -> Option<Key> {
+    use egui::Key as EguiKey;
+    Some(match key {
+        EguiKey::Space => Key::Space,
+        EguiKey::Tab => Key::Tab,
+        EguiKey::Enter => Key::Enter,
+        EguiKey::Backspace => Key::Backspace,
+        EguiKey::Delete => Key::Delete,
+        EguiKey::Escape => Key::Escape,
+        EguiKey::ArrowUp => Key::ArrowUp,
+        EguiKey::ArrowDown => Key::ArrowDown,
+        EguiKey::ArrowLeft => Key::ArrowLeft,
+        EguiKey::ArrowRight => Key::ArrowRight,
+        EguiKey::PageUp => Key::PageUp,
+        EguiKey::PageDown => Key::PageDown,
+        _ if shortcut => {
+            let name = key.name();
+            let mut chars = name.chars();
+            match (chars.next(), chars.next()) {
+                (Some(ch), None) if ch.is_ascii_alphanumeric() => {
+                    Key::Char(ch.to_ascii_lowercase())
+                }
+                _ => return None,
+            }
+        }
+        _ => return None,
+    })
+}
@@ -0,0 +1,896 @@
+//! # Native View Renderer
+//!
+//! Draws the [`Render`]s that programs produce with OpenGL directly, rather
+//! than converting them to egui shapes first. Everything is batched into
+//! triangles in a single vertex buffer, textured with a glyph atlas that each
+//! character is rasterized into (see [`ab_glyph`]) the first time it's drawn at
+//! each size.
+//!
+//! Commands are interpreted like the software rasterizer does (see
+//! [`abi::raster`]), so renders look the same on the GPU as in golden images,
+//! apart from their text. Hosts place each render with a [`RenderTarget`],
+//! which moves and scales it, and clips it to an area of the screen.
+//!
+//! Programs measure their text with [`GlyphFonts`], which uses the same fonts
+//! and wrapping as the renderer, so that text fits where it was laid out.
+
+use std::{collections::HashMap, sync::Arc};
+
+use {
+    ab_glyph::{Font as _, FontRef, PxScale, ScaleFont as _},
+    abi::{
+        Aabb2D, FontFamily, FontStyle, Fonts, LineHeight, PathSegment, Render, RenderCommand, Rgba,
+        TextAlignment, TextDecoration, TextSpan, TextWrapMode, Transform2D, Xy,
+    },
+    anyhow::{Result, anyhow},
+    glow::HasContext as _,
+};
+
+
+
+/// The width and height of the glyph atlas.
+const ATLAS_SIZE: u32 = 1024;
+/// The space left between glyphs in the atlas, so that they don't bleed into
+/// each other.
+const ATLAS_PADDING: u32 = 1;
+/// The number of sizes that glyphs are rasterized at between each whole pixel.
+const GLYPH_SIZE_STEPS: f32 = 4.0;
+
+const VERTEX_SHADER: &str = "
+uniform vec2 u_screen_size;
+attribute vec2 a_position;
+attribute vec2 a_uv;
+attribute vec4 a_color;
+varying vec2 v_uv;
+varying vec4 v_color;
+
+void main() {
+    gl_Position = vec4(
+        2.0 * a_position.x / u_screen_size.x - 1.0,
+        1.0 - 2.0 * a_position.y / u_screen_size.y,
+        0.0,
+        1.0
+    );
+    v_uv = a_uv;
+    v_color = a_color;
+}
+";
+
+const FRAGMENT_SHADER: &str = "
+uniform sampler2D u_atlas;
+varying vec2 v_uv;
+varying vec4 v_color;
+
+void main() {
+    gl_FragColor = v_color * texture2D(u_atlas, v_uv);
+}
+";
+
+/// Where a render is drawn on the screen.
+#[derive(Clone, Copy, Debug)]
+pub struct RenderTarget {
+    /// The size of the framebuffer being drawn to, in pixels.
+    pub screen_size: Xy<f32>,
+    /// Where the render's origin is on the screen.
+    pub offset: Xy<f32>,
+    /// The number of pixels in each of the render's units.
+    pub scale: f32,
+    /// The area of the screen that can be drawn to, or `None` for all of it.
+    pub clip: Option<Aabb2D>,
+}
+
+impl RenderTarget {
+    fn screen_point(&self, point: Xy<f32>) -> Xy<f32> {
+        Xy::new(
+            self.offset.x + point.x * self.scale,
+            self.offset.y + point.y * self.scale,
+        )
+    }
+
+    fn screen_rect(&self, rect: Aabb2D) -> Aabb2D {
+        let (min, max) = (self.screen_point(rect.min), self.screen_point(rect.max));
+        Aabb2D::new(min.x, min.y, max.x, max.y)
+    }
+
+    /// The pixels that can be drawn to.
+    fn clip_rect(&self) -> Aabb2D {
+        let screen = Aabb2D::new(0.0, 0.0, self.screen_size.x, self.screen_size.y);
+        let Some(clip) = self.clip else {
+            return screen;
+        };
+
+        Aabb2D::new(
+            clip.min.x.max(0.0),
+            clip.min.y.max(0.0),
+            clip.max.x.min(screen.max.x),
+            clip.max.y.min(screen.max.y),
+        )
+    }
+}
+
+pub struct ViewRenderer {
+    gl: Arc<glow::Context>,
+    program: glow::Program,
+    screen_size_location: Option<glow::UniformLocation>,
+    atlas_location: Option<glow::UniformLocation>,
+    /// `None` on OpenGL ES 2, which doesn't have vertex array objects.
+    vertex_array: Option<glow::VertexArray>,
+    vertex_buffer: glow::Buffer,
+    atlas: GlyphAtlas,
+    /// The triangles that haven't been drawn yet.
+    vertices: Vec<Vertex>,
+}
+
+#[derive(Clone, Copy)]
+#[repr(C)]
+struct Vertex {
+    position: [f32; 2],
+    uv: [f32; 2],
+    color: [u8; 4],
+}
+
+impl ViewRenderer {
+    /// Create a renderer for the current context.
+    pub fn new(gl: Arc<glow::Context>) -> Result<Self> {
+        let version = gl.version();
+        let header = if version.is_embedded {
+            "#version 100\nprecision mediump float;\n"
+        } else {
+            "#version 120\n"
+        };
+        let has_vertex_arrays = !version.is_embedded || version.major >= 3;
+
+        unsafe {
+            let program = gl.create_program().map_err(|error| anyhow!(error))?;
+            let mut shaders = Vec::new();
+            for (kind, source) in [
+                (glow::VERTEX_SHADER, VERTEX_SHADER),
+                (glow::FRAGMENT_SHADER, FRAGMENT_SHADER),
+            ] {
+                let shader = gl.create_shader(kind).map_err(|error| anyhow!(error))?;
+                gl.shader_source(shader, &format!("{header}{source}"));
+                gl.compile_shader(shader);
+                if !gl.get_shader_compile_status(shader) {
+                    return Err(anyhow!(
+                        "failed to compile view shader: {}",
+                        gl.get_shader_info_log(shader),
+                    ));
+                }
+                gl.attach_shader(program, shader);
+                shaders.push(shader);
+            }
+            for (index, name) in ["a_position", "a_uv", "a_color"].into_iter().enumerate() {
+                gl.bind_attrib_location(program, index as u32, name);
+            }
+            gl.link_program(program);
+            for shader in shaders {
+                gl.detach_shader(program, shader);
+                gl.delete_shader(shader);
+            }
+            if !gl.get_program_link_status(program) {
+                return Err(anyhow!(
+                    "failed to link view shaders: {}",
+                    gl.get_program_info_log(program),
+                ));
+            }
+
+            let vertex_array = if has_vertex_arrays {
+                Some(gl.create_vertex_array().map_err(|error| anyhow!(error))?)
+            } else {
+                None
+            };
+
+            Ok(Self {
+                screen_size_location: gl.get_uniform_location(program, "u_screen_size"),
+                atlas_location: gl.get_uniform_location(program, "u_atlas"),
+                program,
+                vertex_array,
+                vertex_buffer: gl.create_buffer().map_err(|error| anyhow!(error))?,
+                atlas: GlyphAtlas::new(&gl)?,
+                vertices: Vec::new(),
+                gl,
+            })
+        }
+    }
+
+    /// Draw a render over whatever's in the current framebuffer.
+    pub fn draw(&mut self, render: &Render, target: RenderTarget) {
+        let clip = target.clip_rect();
+        if clip.min.x >= clip.max.x || clip.min.y >= clip.max.y {
+            return;
+        }
+
+        let gl = Arc::clone(&self.gl);
+        unsafe {
+            gl.viewport(0, 0, target.screen_size.x as _, target.screen_size.y as _);
+            gl.disable(glow::CULL_FACE);
+            gl.disable(glow::DEPTH_TEST);
+            gl.enable(glow::BLEND);
+            gl.blend_equation(glow::FUNC_ADD);
+            gl.blend_func_separate(
+                glow::SRC_ALPHA,
+                glow::ONE_MINUS_SRC_ALPHA,
+                glow::ONE,
+                glow::ONE_MINUS_SRC_ALPHA,
+            );
+            // NOTE: Scissor rectangles start from the bottom left.
+            gl.enable(glow::SCISSOR_TEST);
+            gl.scissor(
+                clip.min.x as _,
+                (target.screen_size.y - clip.max.y) as _,
+                (clip.max.x - clip.min.x) as _,
+                (clip.max.y - clip.min.y) as _,
+            );
+
+            gl.use_program(Some(self.program));
+            gl.uniform_2_f32(
+                self.screen_size_location.as_ref(),
+                target.screen_size.x,
+                target.screen_size.y,
+            );
+            gl.uniform_1_i32(self.atlas_location.as_ref(), 0);
+            gl.active_texture(glow::TEXTURE0);
+            gl.bind_texture(glow::TEXTURE_2D, Some(self.atlas.texture));
+            gl.bind_vertex_array(self.vertex_array);
+            gl.bind_buffer(glow::ARRAY_BUFFER, Some(self.vertex_buffer));
+            let stride = size_of::<Vertex>() as i32;
+            gl.enable_vertex_attrib_array(0);
+            gl.vertex_attrib_pointer_f32(0, 2, glow::FLOAT, false, stride, 0);
+            gl.enable_vertex_attrib_array(1);
+            gl.vertex_attrib_pointer_f32(1, 2, glow::FLOAT, false, stride, 8);
+            gl.enable_vertex_attrib_array(2);
+            gl.vertex_attrib_pointer_f32(2, 4, glow::UNSIGNED_BYTE, true, stride, 16);
+        }
+
+        let mut state = DrawState::default();
+        for command in &render.commands {
+            match command {
+                RenderCommand::DrawChar(ch) => {
+                    self.draw_char(&mut state, &target, *ch);
+                    continue;
+                }
+                RenderCommand::DrawQuad => self.draw_quad(&state, &target),
+                RenderCommand::SetBounds(bounds) => state.bounds = *bounds,
+                RenderCommand::SetForegroundColor(color) => {
+                    state.foreground_color = *color;
+                    continue;
+                }
+                RenderCommand::SetBackgroundColor(color) => state.background_color = *color,
+                RenderCommand::SetBorderColor(color) => state.border_color = *color,
+                RenderCommand::SetBorderWidth(width) => state.border_width = *width,
+                RenderCommand::SetFontSize(size) => state.font_size = *size,
+                RenderCommand::SetOpacity(opacity) => state.opacity = opacity.clamp(0.0, 1.0),
+                RenderCommand::SetTransform(transform) => state.transform = *transform,
+                RenderCommand::SetFontFamily(family) => {
+                    state.monospace = *family == FontFamily::Monospace;
+                    continue;
+                }
+                RenderCommand::SetTextDecoration(decoration) => {
+                    state.text_decoration = *decoration;
+                    continue;
+                }
+                // The built-in fonts only have one weight and style.
+                RenderCommand::SetFontWeight(_) | RenderCommand::SetFontStyle(_) => continue,
+                RenderCommand::BeginPath => state.path.clear(),
+                RenderCommand::PathSegment(segment) => state.path.push(*segment),
+                RenderCommand::FillPath => self.fill_path(&state, &target, clip),
+                RenderCommand::StrokePath => self.stroke_path(&state, &target),
+            }
+            // NOTE: Style changes happen in the middle of styled text, so only the
+            //       other commands end it.
+            state.pen = None;
+        }
+        self.flush();
+
+        unsafe {
+            gl.bind_vertex_array(None);
+            gl.disable(glow::SCISSOR_TEST);
+        }
+    }
+
+    /// Draw the triangles that have been batched so far.
+    fn flush(&mut self) {
+        if self.vertices.is_empty() {
+            return;
+        }
+        unsafe {
+            let bytes = std::slice::from_raw_parts(
+                self.vertices.as_ptr().cast::<u8>(),
+                self.vertices.len() * size_of::<Vertex>(),
+            );
+            self.gl
+                .buffer_data_u8_slice(glow::ARRAY_BUFFER, bytes, glow::STREAM_DRAW);
+            self.gl
+                .draw_arrays(glow::TRIANGLES, 0, self.vertices.len() as _);
+        }
+        self.vertices.clear();
+    }
+
+    fn draw_quad(&mut self, state: &DrawState, target: &RenderTarget) {
+        let bounds = state.bounds;
+        self.fill_transformed_rect(
+            state,
+            target,
+            bounds,
+            state.with_opacity(state.background_color),
+        );
+
+        let width = state
+            .border_width
+            .min(bounds.size().x / 2.0)
+            .min(bounds.size().y / 2.0);
+        if width <= 0.0 {
+            return;
+        }
+        let Aabb2D { min, max } = bounds;
+        let color = state.with_opacity(state.border_color);
+        for side in [
+            Aabb2D::new(min.x, min.y, max.x, min.y + width),
+            Aabb2D::new(min.x, max.y - width, max.x, max.y),
+            Aabb2D::new(min.x, min.y + width, min.x + width, max.y - width),
+            Aabb2D::new(max.x - width, min.y + width, max.x, max.y - width),
+        ] {
+            self.fill_transformed_rect(state, target, side, color);
+        }
+    }
+
+    fn draw_char(&mut self, state: &mut DrawState, target: &RenderTarget, ch: char) {
+        let size = state.font_size;
+        let bounds = state.bounds;
+        let mut pen = state.pen.unwrap_or(bounds.position());
+        if ch == '\n' {
+            state.pen = Some(Xy::new(bounds.min.x, pen.y + size));
+            return;
+        }
+
+        let font = self.atlas.font(state.monospace);
+        let scale = px_scale(font, size * target.scale);
+        let scaled = font.as_scaled(scale);
+        let advance = scaled.h_advance(font.glyph_id(ch)) / target.scale;
+        let ascent = scaled.ascent() / target.scale;
+        if pen.x + advance > bounds.max.x && pen.x > bounds.min.x {
+            pen = Xy::new(bounds.min.x, pen.y + size);
+        }
+        state.pen = Some(Xy::new(pen.x + advance, pen.y));
+
+        let color = state.with_opacity(state.foreground_color);
+        if !ch.is_whitespace() {
+            let key = GlyphKey {
+                ch,
+                monospace: state.monospace,
+                size: (size * target.scale * GLYPH_SIZE_STEPS).round() as u32,
+            };
+            let glyph = match self.atlas.glyph(&self.gl, key) {
+                Ok(glyph) => glyph,
+                Err(AtlasFull) => {
+                    // Everything already batched uses the old glyphs.
+                    self.flush();
+                    self.atlas.clear();
+                    self.atlas.glyph(&self.gl, key).ok().flatten()
+                }
+            };
+            if let Some(glyph) = glyph {
+                let origin = Xy::new(pen.x, pen.y + ascent);
+                if state.transform.is_axis_aligned() {
+                    let origin = target.screen_point(state.transform * origin);
+                    let min = Xy::new(
+                        origin.x.round() + glyph.offset.x,
+                        origin.y.round() + glyph.offset.y,
+                    );
+                    self.push_rect(
+                        Aabb2D::new(min.x, min.y, min.x + glyph.size.x, min.y + glyph.size.y),
+                        glyph.uv,
+                        color,
+                    );
+                } else {
+                    // NOTE: Rotated glyphs can't be aligned to the pixel grid, so they're
+                    //       sampled from the atlas wherever they land.
+                    let min = Xy::new(
+                        origin.x + glyph.offset.x / target.scale,
+                        origin.y + glyph.offset.y / target.scale,
+                    );
+                    let rect = Aabb2D::new(
+                        min.x,
+                        min.y,
+                        min.x + glyph.size.x / target.scale,
+                        min.y + glyph.size.y / target.scale,
+                    );
+                    let uv = glyph.uv;
+                    self.push_quad(
+                        corners(rect).map(|point| target.screen_point(state.transform * point)),
+                        [
+                            uv.min,
+                            Xy::new(uv.max.x, uv.min.y),
+                            uv.max,
+                            Xy::new(uv.min.x, uv.max.y),
+                        ],
+                        color,
+                    );
+                }
+            }
+        }
+
+        let thickness = (size / 16.0).max(1.0);
+        if state.text_decoration.underline {
+            let y = pen.y + size * 0.9;
+            let line = Aabb2D::new(pen.x, y, pen.x + advance, y + thickness);
+            self.fill_transformed_rect(state, target, line, color);
+        }
+        if state.text_decoration.strikethrough {
+            let y = pen.y + size * 0.5;
+            let line = Aabb2D::new(pen.x, y, pen.x + advance, y + thickness);
+            self.fill_transformed_rect(state, target, line, color);
+        }
+    }
+
+    /// Fill the area enclosed by the path with the non-zero winding rule, as a
+    /// rectangle for each span of pixels that's inside it on each row.
+    fn fill_path(&mut self, state: &DrawState, target: &RenderTarget, clip: Aabb2D) {
+        let polygons = abi::raster::flatten_path(&state.path)
+            .into_iter()
+            .map(|(points, _)| {
+                points
+                    .into_iter()
+                    .map(|point| target.screen_point(state.transform * point))
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+        let (min_y, max_y) = polygons
+            .iter()
+            .flatten()
+            .fold((f32::INFINITY, f32::NEG_INFINITY), |(min, max), point| {
+                (min.min(point.y), max.max(point.y))
+            });
+        let color = state.with_opacity(state.background_color);
+
+        let mut crossings = Vec::new();
+        let first_row = min_y.max(clip.min.y).floor() as i32;
+        let last_row = max_y.min(clip.max.y).ceil() as i32;
+        for y in first_row..last_row {
+            let center_y = y as f32 + 0.5;
+            crossings.clear();
+            for polygon in &polygons {
+                for (index, from) in polygon.iter().enumerate() {
+                    let to = polygon[(index + 1) % polygon.len()];
+                    let winding = match (from.y <= center_y, to.y <= center_y) {
+                        (true, false) => 1,
+                        (false, true) => -1,
+                        _ => continue,
+                    };
+                    let t = (center_y - from.y) / (to.y - from.y);
+                    crossings.push((from.x + (to.x - from.x) * t, winding));
+                }
+            }
+            crossings.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+            let mut winding = 0;
+            for pair in crossings.windows(2) {
+                winding += pair[0].1;
+                if winding != 0 && pair[0].0 < pair[1].0 {
+                    let span = Aabb2D::new(pair[0].0, y as f32, pair[1].0, y as f32 + 1.0);
+                    self.fill_rect(span, color);
+                }
+            }
+        }
+    }
+
+    /// Strokes are drawn as a rectangle along each line, so wide strokes have
+    /// gaps at sharp corners.
+    fn stroke_path(&mut self, state: &DrawState, target: &RenderTarget) {
+        let half_width = state.border_width / 2.0;
+        if half_width <= 0.0 {
+            return;
+        }
+        let color = state.with_opacity(state.border_color);
+        let white_uv = self.atlas.white_uv();
+
+        for (mut points, closed) in abi::raster::flatten_path(&state.path) {
+            if closed && let Some(first) = points.first() {
+                points.push(*first);
+            }
+            for line in points.windows(2) {
+                let (from, to) = (line[0], line[1]);
+                let direction = to - from;
+                let length = direction.length_squared().sqrt();
+                if length <= f32::EPSILON {
+                    continue;
+                }
+                let normal = Xy::new(
+                    -direction.y / length * half_width,
+                    direction.x / length * half_width,
+                );
+                self.push_quad(
+                    [from + normal, to + normal, to - normal, from - normal]
+                        .map(|point| target.screen_point(state.transform * point)),
+                    [white_uv; 4],
+                    color,
+                );
+            }
+        }
+    }
+
+    /// Fill a rectangle in the space of the render commands, which is only a
+    /// rectangle on the screen if it isn't rotated or skewed.
+    fn fill_transformed_rect(
+        &mut self,
+        state: &DrawState,
+        target: &RenderTarget,
+        rect: Aabb2D,
+        color: Rgba,
+    ) {
+        if state.transform.is_axis_aligned() {
+            self.fill_rect(
+                target.screen_rect(state.transform.transform_area(rect)),
+                color,
+            );
+        } else if color.a != 0 && rect.min.x < rect.max.x && rect.min.y < rect.max.y {
+            self.push_quad(
+                corners(rect).map(|point| target.screen_point(state.transform * point)),
+                [self.atlas.white_uv(); 4],
+                color,
+            );
+        }
+    }
+
+    fn fill_rect(&mut self, rect: Aabb2D, color: Rgba) {
+        if color.a == 0 {
+            return;
+        }
+        let uv = self.atlas.white_uv();
+        self.push_rect(rect, Aabb2D::new(uv.x, uv.y, uv.x, uv.y), color);
+    }
+
+    fn push_rect(&mut self, rect: Aabb2D, uv: Aabb2D, color: Rgba) {
+        if rect.min.x >= rect.max.x || rect.min.y >= rect.max.y {
+            return;
+        }
+        let color = [color.r, color.g, color.b, color.a];
+        let corner = |x: f32, y: f32, u: f32, v: f32| Vertex {
+            position: [x, y],
+            uv: [u, v],
+            color,
+        };
+        let top_left = corner(rect.min.x, rect.min.y, uv.min.x, uv.min.y);
+        let top_right = corner(rect.max.x, rect.min.y, uv.max.x, uv.min.y);
+        let bottom_right = corner(rect.max.x, rect.max.y, uv.max.x, uv.max.y);
+        let bottom_left = corner(rect.min.x, rect.max.y, uv.min.x, uv.max.y);
+        self.vertices.extend_from_slice(&[
+            top_left,
+            top_right,
+            bottom_right,
+            top_left,
+            bottom_right,
+            bottom_left,
+        ]);
+    }
+
+    /// Push a quadrilateral with the given texture coordinates at each corner.
+    fn push_quad(&mut self, corners: [Xy<f32>; 4], uvs: [Xy<f32>; 4], color: Rgba) {
+        let color = [color.r, color.g, color.b, color.a];
+        let vertex = |point: Xy<f32>, uv: Xy<f32>| Vertex {
+            position: [point.x, point.y],
+            uv: [uv.x, uv.y],
+            color,
+        };
+        let [a, b, c, d] = [0, 1, 2, 3].map(|index| vertex(corners[index], uvs[index]));
+        self.vertices.extend_from_slice(&[a, b, c, a, c, d]);
+    }
+}
+
+/// The state that render commands set, starting with the same values as a
+/// [`RenderPass`](abi::RenderPass).
+struct DrawState {
+    bounds: Aabb2D,
+    font_size: f32,
+    foreground_color: Rgba,
+    background_color: Rgba,
+    border_color: Rgba,
+    border_width: f32,
+    opacity: f32,
+    transform: Transform2D,
+    monospace: bool,
+    text_decoration: TextDecoration,
+    /// Where the next character is drawn, which is reset to the top left of the
+    /// bounds whenever anything other than text is drawn.
+    pen: Option<Xy<f32>>,
+    path: Vec<PathSegment>,
+}
+
+impl Default for DrawState {
+    fn default() -> Self {
+        Self {
+            bounds: Aabb2D::ZERO,
+            font_size: 16.0,
+            foreground_color: Rgba::WHITE,
+            background_color: Rgba::BLACK,
+            border_color: Rgba::NONE,
+            border_width: 0.0,
+            opacity: 1.0,
+            transform: Transform2D::IDENTITY,
+            monospace: false,
+            text_decoration: TextDecoration::NONE,
+            pen: None,
+            path: Vec::new(),
+        }
+    }
+}
+
+impl DrawState {
+    fn with_opacity(&self, color: Rgba) -> Rgba {
+        Rgba {
+            a: (color.a as f32 * self.opacity).round() as u8,
+            ..color
+        }
+    }
+}
+
+/// The corners of a rectangle, clockwise from the top left.
+fn corners(rect: Aabb2D) -> [Xy<f32>; 4] {
+    let Aabb2D { min, max } = rect;
+    [min, Xy::new(max.x, min.y), max, Xy::new(min.x, max.y)]
+}
+
+
+
+/// A texture of every glyph that's been drawn, packed into rows.
+///
+/// The top left pixel is always white, so that shapes can be drawn with the
+/// same texture as text.
+struct GlyphAtlas {
+    texture: glow::Texture,
+    proportional: FontRef<'static>,
+    monospace: FontRef<'static>,
+    /// Each glyph that's been rasterized, or `None` for glyphs that have no
+    /// outline (such as spaces).
+    glyphs: HashMap<GlyphKey, Option<AtlasGlyph>>,
+    /// Where the next glyph goes, unless it doesn't fit in the current row.
+    cursor: (u32, u32),
+    row_height: u32,
+}
+
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+struct GlyphKey {
+    ch: char,
+    monospace: bool,
+    /// The font size in pixels, in steps of [`GLYPH_SIZE_STEPS`].
+    size: u32,
+}
+
+#[derive(Clone, Copy, Debug)]
+struct AtlasGlyph {
+    /// Where the glyph is in the atlas, as texture coordinates.
+    uv: Aabb2D,
+    /// Where the glyph's top left corner is drawn, relative to where the
+    /// baseline starts.
+    offset: Xy<f32>,
+    size: Xy<f32>,
+}
+
+/// The atlas has no room for another glyph until it's cleared.
+struct AtlasFull;
+
+impl GlyphAtlas {
+    fn new(gl: &glow::Context) -> Result<Self> {
+        let mut pixels = vec![0; (ATLAS_SIZE * ATLAS_SIZE * 4) as usize];
+        pixels[..4].fill(u8::MAX);
+
+        let texture = unsafe {
+            let texture = gl.create_texture().map_err(|error| anyhow!(error))?;
+            gl.bind_texture(glow::TEXTURE_2D, Some(texture));
+            for parameter in [glow::TEXTURE_MIN_FILTER, glow::TEXTURE_MAG_FILTER] {
+                gl.tex_parameter_i32(glow::TEXTURE_2D, parameter, glow::NEAREST as _);
+            }
+            for parameter in [glow::TEXTURE_WRAP_S, glow::TEXTURE_WRAP_T] {
+                gl.tex_parameter_i32(glow::TEXTURE_2D, parameter, glow::CLAMP_TO_EDGE as _);
+            }
+            gl.tex_image_2d(
+                glow::TEXTURE_2D,
+                0,
+                glow::RGBA as _,
+                ATLAS_SIZE as _,
+                ATLAS_SIZE as _,
+                0,
+                glow::RGBA,
+                glow::UNSIGNED_BYTE,
+                glow::PixelUnpackData::Slice(Some(&pixels)),
+            );
+            texture
+        };
+
+        Ok(Self {
+            texture,
+            proportional: built_in_font(false)?,
+            monospace: built_in_font(true)?,
+            glyphs: HashMap::new(),
+            cursor: (1 + ATLAS_PADDING, 0),
+            row_height: 1,
+        })
+    }
+
+    fn font(&self, monospace: bool) -> &FontRef<'static> {
+        if monospace {
+            &self.monospace
+        } else {
+            &self.proportional
+        }
+    }
+
+    /// The texture coordinates of the white pixel.
+    fn white_uv(&self) -> Xy<f32> {
+        Xy::new(0.5 / ATLAS_SIZE as f32, 0.5 / ATLAS_SIZE as f32)
+    }
+
+    /// Forget every glyph, so that their space can be reused.
+    fn clear(&mut self) {
+        self.glyphs.clear();
+        self.cursor = (1 + ATLAS_PADDING, 0);
+        self.row_height = 1;
+    }
+
+    /// Find a glyph in the atlas, rasterizing it if it isn't there yet.
+    fn glyph(
+        &mut self,
+        gl: &glow::Context,
+        key: GlyphKey,
+    ) -> Result<Option<AtlasGlyph>, AtlasFull> {
+        if let Some(glyph) = self.glyphs.get(&key) {
+            return Ok(*glyph);
+        }
+
+        let font = self.font(key.monospace);
+        let scale = px_scale(font, key.size as f32 / GLYPH_SIZE_STEPS);
+        let Some(outline) = font.outline_glyph(font.glyph_id(key.ch).with_scale(scale)) else {
+            self.glyphs.insert(key, None);
+            return Ok(None);
+        };
+        let bounds = outline.px_bounds();
+        let (width, height) = (bounds.width() as u32, bounds.height() as u32);
+        if width == 0 || height == 0 {
+            self.glyphs.insert(key, None);
+            return Ok(None);
+        }
+
+        let (x, y) = self.allocate(width, height)?;
+        let mut pixels = vec![u8::MAX; (width * height * 4) as usize];
+        outline.draw(|x, y, coverage| {
+            pixels[((y * width + x) * 4 + 3) as usize] = (coverage.clamp(0.0, 1.0) * 255.0) as u8;
+        });
+        unsafe {
+            gl.bind_texture(glow::TEXTURE_2D, Some(self.texture));
+            gl.pixel_store_i32(glow::UNPACK_ALIGNMENT, 4);
+            gl.tex_sub_image_2d(
+                glow::TEXTURE_2D,
+                0,
+                x as _,
+                y as _,
+                width as _,
+                height as _,
+                glow::RGBA,
+                glow::UNSIGNED_BYTE,
+                glow::PixelUnpackData::Slice(Some(&pixels)),
+            );
+        }
+
+        let texel = 1.0 / ATLAS_SIZE as f32;
+        let glyph = AtlasGlyph {
+            uv: Aabb2D::new(
+                x as f32 * texel,
+                y as f32 * texel,
+                (x + width) as f32 * texel,
+                (y + height) as f32 * texel,
+            ),
+            offset: Xy::new(bounds.min.x, bounds.min.y),
+            size: Xy::new(width as f32, height as f32),
+        };
+        self.glyphs.insert(key, Some(glyph));
+
+        Ok(Some(glyph))
+    }
+
+    /// Find space for a glyph, starting a new row if it doesn't fit in the
+    /// current one.
+    fn allocate(&mut self, width: u32, height: u32) -> Result<(u32, u32), AtlasFull> {
+        if self.cursor.0 + width > ATLAS_SIZE {
+            self.cursor = (0, self.cursor.1 + self.row_height + ATLAS_PADDING);
+            self.row_height = 0;
+        }
+        if self.cursor.0 + width > ATLAS_SIZE || self.cursor.1 + height > ATLAS_SIZE {
+            return Err(AtlasFull);
+        }
+
+        let position = self.cursor;
+        self.cursor.0 += width + ATLAS_PADDING;
+        self.row_height = self.row_height.max(height);
+
+        Ok(position)
+    }
+}
+
+fn built_in_font(monospace: bool) -> Result<FontRef<'static>> {
+    let data = if monospace {
+        epaint_default_fonts::HACK_REGULAR
+    } else {
+        epaint_default_fonts::UBUNTU_LIGHT
+    };
+
+    Ok(FontRef::try_from_slice(data)?)
+}
+
+/// The scale that draws a font at a size in pixels, which `ab_glyph` measures
+/// from its ascent to its descent rather than by its em square.
+fn px_scale(font: &FontRef<'_>, size: f32) -> PxScale {
+    let units_per_em = font.units_per_em().unwrap_or(1.0);
+
+    PxScale::from(size * font.height_unscaled() / units_per_em)
+}
+
+
+
+/// Measures text with the fonts that [`ViewRenderer`] draws it with.
+///
+/// Only the font family of the whole text is used, and every line is as tall
+/// as its line height.
+pub struct GlyphFonts {
+    proportional: FontRef<'static>,
+    monospace: FontRef<'static>,
+}
+
+impl GlyphFonts {
+    pub fn new() -> Result<Self> {
+        Ok(Self {
+            proportional: built_in_font(false)?,
+            monospace: built_in_font(true)?,
+        })
+    }
+}
+
+impl Fonts for GlyphFonts {
+    fn measure_text(
+        &mut self,
+        _id: u64,
+        text: &str,
+        // NOTE: The built-in fonts only have one weight and style.
+        _spans: &[TextSpan],
+        max_advance: Option<f32>,
+        font_size: f32,
+        line_height: LineHeight,
+        font_family: FontFamily,
+        _font_style: FontStyle,
+        _alignment: TextAlignment,
+        wrap_mode: TextWrapMode,
+    ) -> Xy<f32> {
+        let font = if font_family == FontFamily::Monospace {
+            &self.monospace
+        } else {
+            &self.proportional
+        };
+        let scaled = font.as_scaled(px_scale(font, font_size));
+        let max_advance = match (max_advance, wrap_mode) {
+            (Some(max_advance), TextWrapMode::Wrap) => max_advance,
+            _ => f32::INFINITY,
+        };
+
+        // Lines are wrapped before the first character that doesn't fit, like
+        // `ViewRenderer::draw_char` does.
+        let mut width = 0.0f32;
+        let mut row_count = 0;
+        for line in text.split('\n') {
+            let mut row_width = 0.0;
+            row_count += 1;
+            // Arabic letters are drawn in their joining forms, which have their own
+            // advances.
+            for (_, ch) in abi::bidi::shaped_char_indices(line) {
+                let advance = scaled.h_advance(font.glyph_id(ch));
+                if row_width + advance > max_advance && row_width > 0.0 {
+                    width = width.max(row_width);
+                    row_width = 0.0;
+                    row_count += 1;
+                }
+                row_width += advance;
+            }
+            width = width.max(row_width);
+        }
+
+        Xy::new(width, row_count as f32 * line_height.resolve(font_size))
+    }
+}
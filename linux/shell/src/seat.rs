@@ -0,0 +1,188 @@
+//! # Seats and Sessions
+//!
+//! A seat is a set of displays and input devices used together, named by each
+//! device's `ID_SEAT` property in udev's database (or `seat0` if it has none).
+//! The shell only uses devices on the seat named by `XDG_SEAT` (or `seat0`), so
+//! that one shell can run on each seat.
+//!
+//! The shell's [`Session`] is only active while its virtual terminal is shown.
+//! Before the kernel switches to another VT, the shell stops rendering, drops
+//! DRM master so that whatever runs there can use the displays, and revokes its
+//! input devices (see `EVIOCREVOKE`) so that nothing typed there is read in the
+//! background. Everything is taken back once the shell's VT is switched back
+//! to. Seats other than `seat0` have no VTs, so their sessions are always
+//! active.
+
+use std::{
+    os::unix::fs::MetadataExt as _,
+    path::{Path, PathBuf},
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+use {
+    anyhow::{Result, anyhow},
+    linux_uapi::{Signal, epoll::EventFile, vt::VirtualTerminal},
+    log::{info, warn},
+};
+
+
+
+/// The seat that devices without an `ID_SEAT` belong to, which is the only one
+/// with VTs.
+const DEFAULT_SEAT: &str = "seat0";
+
+/// The signal the kernel sends before switching away from the shell's VT.
+pub const RELEASE_SIGNAL: Signal = Signal::USR1;
+/// The signal the kernel sends after switching back to the shell's VT.
+pub const ACQUIRE_SIGNAL: Signal = Signal::USR2;
+
+/// The shell's use of its seat, which is shared with the input thread.
+pub struct Session {
+    seat: String,
+    /// The VT the shell runs on, or `None` if it isn't running on one.
+    vt: Option<VirtualTerminal>,
+    active: AtomicBool,
+    /// Notified whenever the session is activated or deactivated, to wake up
+    /// the input thread.
+    changed: EventFile,
+}
+
+impl Session {
+    /// Start a session on the seat named by `XDG_SEAT`, taking over the current
+    /// VT if it's `seat0`.
+    ///
+    /// Failing to take over the VT only means that switching VTs won't work,
+    /// such as when the shell isn't started from one.
+    pub fn open() -> Result<Self> {
+        let seat = std::env::var("XDG_SEAT").unwrap_or_else(|_| DEFAULT_SEAT.to_string());
+        let vt = if seat == DEFAULT_SEAT {
+            let vt = VirtualTerminal::open_active().and_then(|mut vt| {
+                vt.take_over(RELEASE_SIGNAL, ACQUIRE_SIGNAL)?;
+                Ok(vt)
+            });
+            match vt {
+                Ok(vt) => {
+                    info!("Running on {seat}, VT {}", vt.number());
+                    Some(vt)
+                }
+                Err(error) => {
+                    warn!("Failed to take over the VT, switching VTs won't work: {error:?}");
+                    None
+                }
+            }
+        } else {
+            info!("Running on {seat}");
+            None
+        };
+
+        Ok(Self {
+            seat,
+            vt,
+            active: AtomicBool::new(true),
+            changed: EventFile::create()?,
+        })
+    }
+
+    /// Whether the shell's VT is being shown, in which case it can use its
+    /// seat's devices.
+    pub fn is_active(&self) -> bool {
+        self.active.load(Ordering::Acquire)
+    }
+
+    pub fn changed(&self) -> &EventFile {
+        &self.changed
+    }
+
+    pub(crate) fn set_active(&self, active: bool) {
+        self.active.store(active, Ordering::Release);
+        if let Err(error) = self.changed.notify() {
+            warn!("Failed to notify the input thread of a VT switch: {error:?}");
+        }
+    }
+
+    /// Whether a device file belongs to the session's seat.
+    pub fn owns(&self, path: &Path) -> bool {
+        device_seat(path) == self.seat
+    }
+
+    /// The first graphics card on the session's seat.
+    pub fn find_card(&self) -> Result<PathBuf> {
+        let mut cards = std::fs::read_dir("/dev/dri")?
+            .flatten()
+            .map(|entry| entry.path())
+            .filter(|path| {
+                path.file_name()
+                    .and_then(|name| name.to_str())
+                    .is_some_and(|name| name.starts_with("card"))
+            })
+            .collect::<Vec<_>>();
+        cards.sort();
+
+        cards
+            .into_iter()
+            .find(|path| self.owns(path))
+            .ok_or_else(|| anyhow!("no graphics card on {}", self.seat))
+    }
+
+    /// Switch to another VT, which the kernel asks the shell to release its
+    /// own VT for first.
+    pub fn switch_vt(&self, number: u16) {
+        let Some(vt) = &self.vt else {
+            return;
+        };
+        if number != vt.number()
+            && let Err(error) = vt.activate(number)
+        {
+            warn!("Failed to switch to VT {number}: {error:?}");
+        }
+    }
+
+    /// Let the kernel switch away from the shell's VT, once the session has
+    /// been deactivated.
+    pub fn release_vt(&self) {
+        if let Some(vt) = &self.vt
+            && let Err(error) = vt.allow_release()
+        {
+            warn!("Failed to release the VT: {error:?}");
+        }
+    }
+
+    /// Take the shell's VT back after the kernel switched to it.
+    pub fn acquire_vt(&self) {
+        if let Some(vt) = &self.vt
+            && let Err(error) = vt.acknowledge_acquire()
+        {
+            warn!("Failed to acquire the VT: {error:?}");
+        }
+    }
+
+    /// Give the VT back to the kernel before the shell exits, so that its
+    /// console works again.
+    pub fn restore_vt(&self) {
+        if let Some(vt) = &self.vt
+            && let Err(error) = vt.restore()
+        {
+            warn!("Failed to restore the VT: {error:?}");
+        }
+    }
+}
+
+/// The seat a device file belongs to, from udev's database (which is keyed by
+/// device number). Only devices on seats other than `seat0` have an
+/// `ID_SEAT`.
+fn device_seat(path: &Path) -> String {
+    let seat = std::fs::metadata(path).ok().and_then(|metadata| {
+        let rdev = metadata.rdev();
+        let data = format!(
+            "/run/udev/data/c{}:{}",
+            libc::major(rdev),
+            libc::minor(rdev),
+        );
+        std::fs::read_to_string(data)
+            .ok()?
+            .lines()
+            .find_map(|line| line.strip_prefix("E:ID_SEAT=").map(str::to_string))
+    });
+
+    seat.unwrap_or_else(|| DEFAULT_SEAT.to_string())
+}
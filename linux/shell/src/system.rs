@@ -0,0 +1,289 @@
+//! # System Services
+//!
+//! The host side of [`abi::system`].
+
+use std::{collections::BTreeMap, path::PathBuf, sync::Mutex};
+
+use {
+    abi::{
+        Capability, Manifest,
+        system::{OpenUrlError, OpenViewError, is_valid_setting_key},
+    },
+    log::{info, warn},
+};
+
+
+
+static STATE: Mutex<SystemState> = Mutex::new(SystemState {
+    caller: None,
+    url_handler: None,
+    pending_urls: Vec::new(),
+    settings: BTreeMap::new(),
+    settings_path: None,
+    view_requests: Vec::new(),
+});
+
+struct SystemState {
+    /// Who system calls are currently being made on behalf of, or `None` if no
+    /// one is (such as while an object is being loaded), in which case every
+    /// capability is denied.
+    caller: Option<Caller>,
+    /// The program used to open URLs.
+    url_handler: Option<String>,
+    /// URLs waiting to be opened by [`open_pending_urls`].
+    pending_urls: Vec<String>,
+    /// Application settings, keyed by `"{app name}/{key}"`.
+    settings: BTreeMap<String, String>,
+    /// The file that settings are persisted to.
+    settings_path: Option<PathBuf>,
+    /// Views opened or closed by the current application since the last
+    /// [`take_view_requests`].
+    view_requests: Vec<ViewRequest>,
+}
+
+/// Who a system call is being made on behalf of, which decides the
+/// capabilities it has.
+#[derive(Clone, Copy, Debug)]
+pub enum Caller {
+    /// The shell itself, which has every capability.
+    Shell,
+    /// An application, which only has the capabilities declared in its
+    /// manifest.
+    App(&'static Manifest),
+}
+
+impl Caller {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Shell => "shell",
+            Self::App(manifest) => manifest.name,
+        }
+    }
+
+    pub fn has_capability(&self, capability: Capability) -> bool {
+        match self {
+            Self::Shell => true,
+            Self::App(manifest) => manifest.capabilities.contains(&capability),
+        }
+    }
+}
+
+/// A request from an application to open or close one of its views.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum ViewRequest {
+    Open(String),
+    Close(String),
+}
+
+/// Set the application whose capabilities are checked by system calls made from
+/// now on. With `None`, every capability is denied until another caller is set.
+pub fn set_current_app(manifest: Option<&'static Manifest>) {
+    STATE.lock().unwrap().caller = manifest.map(Caller::App);
+}
+
+/// Run `f` with system calls made on behalf of the given caller, restoring the
+/// previous caller afterwards.
+pub fn with_caller<T>(caller: Caller, f: impl FnOnce() -> T) -> T {
+    let previous = STATE.lock().unwrap().caller.replace(caller);
+    let result = f();
+    STATE.lock().unwrap().caller = previous;
+
+    result
+}
+
+/// Who system calls are currently being made on behalf of, see
+/// [`with_caller`].
+pub fn current_caller() -> Option<Caller> {
+    STATE.lock().unwrap().caller
+}
+
+/// The application currently being run, see [`set_current_app`].
+pub fn current_app() -> Option<&'static Manifest> {
+    match current_caller()? {
+        Caller::Shell => None,
+        Caller::App(manifest) => Some(manifest),
+    }
+}
+
+/// Register the program that URLs are routed to. It is called with the URL as
+/// its only argument.
+pub fn set_url_handler(program: Option<String>) {
+    STATE.lock().unwrap().url_handler = program;
+}
+
+/// Launch the URL handler for every URL that has been requested since the last
+/// call.
+pub fn open_pending_urls() {
+    let (handler, urls) = {
+        let mut state = STATE.lock().unwrap();
+        let urls = std::mem::take(&mut state.pending_urls);
+        (state.url_handler.clone(), urls)
+    };
+    let Some(handler) = handler else {
+        return;
+    };
+
+    for url in urls {
+        info!("Opening '{url}' with '{handler}'...");
+        if let Err(error) = std::process::Command::new(&handler).arg(&url).spawn() {
+            warn!("Failed to open '{url}' with '{handler}': {error}");
+        }
+    }
+}
+
+/// The views that the current application has asked to open or close since the
+/// last call.
+pub fn take_view_requests() -> Vec<ViewRequest> {
+    std::mem::take(&mut STATE.lock().unwrap().view_requests)
+}
+
+/// Load application settings from the given file, which is also where they're
+/// saved to from now on. A missing file is treated as empty.
+pub fn load_settings(path: PathBuf) -> std::io::Result<()> {
+    let mut state = STATE.lock().unwrap();
+    state.settings_path = Some(path.clone());
+
+    let contents = match std::fs::read_to_string(&path) {
+        Ok(contents) => contents,
+        Err(error) if error.kind() == std::io::ErrorKind::NotFound => return Ok(()),
+        Err(error) => return Err(error),
+    };
+    state.settings = contents
+        .lines()
+        .filter_map(|line| line.split_once('='))
+        .filter(|(key, _)| key.contains('/'))
+        .map(|(key, value)| (key.to_string(), value.to_string()))
+        .collect();
+
+    Ok(())
+}
+
+/// The shell's own setting with the given key, regardless of which application
+/// is currently being run.
+pub fn shell_setting(key: &str) -> Option<String> {
+    STATE
+        .lock()
+        .unwrap()
+        .settings
+        .get(&format!("shell/{key}"))
+        .cloned()
+}
+
+/// Set one of the shell's own settings, see [`shell_setting`].
+pub fn set_shell_setting(key: &str, value: &str) {
+    if !is_valid_setting_key(key) {
+        warn!("Ignoring invalid shell setting key {key:?}");
+        return;
+    }
+    let mut state = STATE.lock().unwrap();
+    state
+        .settings
+        .insert(format!("shell/{key}"), setting_value(value));
+
+    if let Err(error) = save_settings(&state) {
+        warn!("Failed to save settings: {error}");
+    }
+}
+
+fn save_settings(state: &SystemState) -> std::io::Result<()> {
+    let Some(path) = &state.settings_path else {
+        return Ok(());
+    };
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+
+    let contents = state
+        .settings
+        .iter()
+        .map(|(key, value)| format!("{key}={value}\n"))
+        .collect::<String>();
+    std::fs::write(path, contents)
+}
+
+/// The key that the calling application's setting is stored under, or `None`
+/// if there's no application to store it for or the key is invalid.
+fn setting_key(state: &SystemState, key: &str) -> Option<String> {
+    // The shell's own settings go through `shell_setting` instead, so that
+    // nothing outside an application can write to the shell's namespace.
+    let Some(Caller::App(manifest)) = state.caller else {
+        return None;
+    };
+    is_valid_setting_key(key).then(|| format!("{}/{key}", manifest.name))
+}
+
+/// Line breaks would split the value over multiple entries of the settings
+/// file.
+fn setting_value(value: &str) -> String {
+    value.replace(['\n', '\r'], " ")
+}
+
+fn has_capability(state: &SystemState, capability: Capability) -> bool {
+    state
+        .caller
+        .is_some_and(|caller| caller.has_capability(capability))
+}
+
+abi::declare! {
+    mod system {
+        fn open_url(url: &str) -> Result<(), OpenUrlError> {
+            let mut state = STATE.lock().unwrap();
+
+            if !has_capability(&state, Capability::OpenUrl) {
+                return Err(OpenUrlError::NotPermitted);
+            }
+            if state.url_handler.is_none() {
+                return Err(OpenUrlError::NoHandler);
+            }
+
+            // The handler is launched later from the event loop so that applications aren't
+            // blocked on process creation.
+            state.pending_urls.push(url.to_string());
+
+            Ok(())
+        }
+
+        fn get_setting(key: &str) -> Option<String> {
+            let state = STATE.lock().unwrap();
+
+            state.settings.get(&setting_key(&state, key)?).cloned()
+        }
+
+        fn set_setting(key: &str, value: &str) {
+            let mut state = STATE.lock().unwrap();
+
+            let Some(key) = setting_key(&state, key) else {
+                warn!("Ignoring setting {key:?} without a valid key and calling application");
+                return;
+            };
+            state.settings.insert(key, setting_value(value));
+
+            if let Err(error) = save_settings(&state) {
+                warn!("Failed to save settings: {error}");
+            }
+        }
+
+        fn open_view(name: &str) -> Result<(), OpenViewError> {
+            let mut state = STATE.lock().unwrap();
+
+            // The shell itself has no views to open.
+            let Some(Caller::App(manifest)) = state.caller else {
+                return Err(OpenViewError::NotSupported);
+            };
+            if manifest.view_init(name).is_none() {
+                return Err(OpenViewError::UnknownView);
+            }
+            state.view_requests.push(ViewRequest::Open(name.to_string()));
+
+            Ok(())
+        }
+
+        fn close_view(name: &str) {
+            let mut state = STATE.lock().unwrap();
+
+            if let Some(Caller::App(_)) = state.caller {
+                state.view_requests.push(ViewRequest::Close(name.to_string()));
+            }
+        }
+    }
+}
@@ -0,0 +1,644 @@
+//! # Shared Memory Channels
+//!
+//! A [`Sender`] and [`Receiver`] pair for passing messages between two
+//! processes through a ring buffer in shared memory. Each channel has exactly
+//! one sender and one receiver, and either end can block on the other with a
+//! futex rather than spinning.
+//!
+//! Messages are numbered with wrapping sequence numbers, which are also the
+//! positions of the ring buffer's head (the next message to send) and tail (the
+//! next message to receive). The capacity is a power of two, so that a
+//! message's slot stays the same when its sequence number wraps around.
+//!
+//! A receiver that's falling behind can set [back
+//! pressure](Receiver::set_back_pressure), which makes the sender coalesce the
+//! messages that can be (see [`Sender::send_coalesced`]) rather than filling
+//! the channel with them.
+
+use core::{
+    cell::UnsafeCell,
+    marker::PhantomData,
+    mem::MaybeUninit,
+    sync::atomic::{AtomicU32, AtomicU64, Ordering},
+    time::Duration,
+};
+
+use crate::{Error, Result};
+
+
+
+#[repr(C)]
+struct Header {
+    /// The sequence number of the next message to be sent. Only written by the
+    /// sender.
+    head: AtomicU32,
+    /// The sequence number of the next message to be received. Only written by
+    /// the receiver.
+    tail: AtomicU32,
+    /// Set when the receiver is (about to be) waiting for a message.
+    receiver_waiting: AtomicU32,
+    /// Set when the sender is (about to be) waiting for room in the channel.
+    sender_waiting: AtomicU32,
+    /// The futex the receiver waits on, which is changed to wake it up.
+    receiver_wakeups: AtomicU32,
+    /// The futex the sender waits on, which is changed to wake it up.
+    sender_wakeups: AtomicU32,
+    /// Set once either end is closed.
+    closed: AtomicU32,
+    /// Set by the receiver while it's falling behind, see
+    /// [`Receiver::set_back_pressure`].
+    back_pressure: AtomicU32,
+    capacity: u32,
+    /// The size of a message, so that both ends can check that they agree on
+    /// the message type.
+    message_size: u32,
+    /// The number of messages that were discarded because the channel was full,
+    /// see [`Sender::send_or_discard`].
+    overflow_count: AtomicU64,
+}
+
+/// The size of the header, padded so that messages are aligned.
+const fn header_size<T>() -> usize {
+    size_of::<Header>().next_multiple_of(align_of::<T>())
+}
+
+/// Get the size of the memory region needed for a channel of `capacity`
+/// messages of type `T`.
+pub const fn required_size<T>(capacity: u32) -> usize {
+    header_size::<T>() + size_of::<T>() * capacity as usize
+}
+
+/// Initialize a new channel of `capacity` messages at the given `base`
+/// pointer, which can then be opened with [`Sender::from_existing`] and
+/// [`Receiver::from_existing`].
+///
+/// Returns [`Error::INVAL`] if `capacity` isn't a power of two.
+///
+/// # Safety
+///
+/// The provided pointer **MUST** be aligned for both `T` and [`AtomicU64`], and
+/// point to a memory region at least as large as [`required_size`]. `T` must
+/// not contain any pointers, since the other process can't use them.
+pub unsafe fn init<T>(base: *mut u8, capacity: u32) -> Result<()> {
+    if !capacity.is_power_of_two() || size_of::<T>() > u32::MAX as usize {
+        return Err(Error::INVAL);
+    }
+
+    unsafe {
+        (base as *mut Header).write(Header {
+            head: AtomicU32::new(0),
+            tail: AtomicU32::new(0),
+            receiver_waiting: AtomicU32::new(0),
+            sender_waiting: AtomicU32::new(0),
+            receiver_wakeups: AtomicU32::new(0),
+            sender_wakeups: AtomicU32::new(0),
+            closed: AtomicU32::new(0),
+            back_pressure: AtomicU32::new(0),
+            capacity,
+            message_size: size_of::<T>() as u32,
+            overflow_count: AtomicU64::new(0),
+        });
+    }
+
+    Ok(())
+}
+
+/// The parts of a channel shared by both of its ends.
+struct Ring<T> {
+    header: *const Header,
+    slots: *const UnsafeCell<MaybeUninit<T>>,
+    _message: PhantomData<T>,
+}
+
+unsafe impl<T: Send> Send for Ring<T> {}
+
+impl<T> Ring<T> {
+    unsafe fn from_existing(base: *mut u8) -> Result<Self> {
+        let header = base as *const Header;
+        let header_ref = unsafe { &*header };
+        if !header_ref.capacity.is_power_of_two()
+            || header_ref.message_size as usize != size_of::<T>()
+        {
+            return Err(Error::INVAL);
+        }
+
+        Ok(Self {
+            header,
+            slots: unsafe { base.add(header_size::<T>()) } as *const _,
+            _message: PhantomData,
+        })
+    }
+
+    fn header(&self) -> &Header {
+        // SAFETY: The header was checked to be initialized when the ring was opened.
+        unsafe { &*self.header }
+    }
+
+    fn slot(&self, sequence: u32) -> *mut MaybeUninit<T> {
+        let index = sequence & (self.header().capacity - 1);
+        unsafe { (*self.slots.add(index as usize)).get() }
+    }
+
+    fn len(&self) -> u32 {
+        let header = self.header();
+        header
+            .head
+            .load(Ordering::Acquire)
+            .wrapping_sub(header.tail.load(Ordering::Acquire))
+    }
+
+    fn is_closed(&self) -> bool {
+        self.header().closed.load(Ordering::Acquire) != 0
+    }
+
+    fn close(&self) {
+        let header = self.header();
+        header.closed.store(1, Ordering::SeqCst);
+        // Wake up the other end, since what it's waiting for will never happen.
+        wake(&header.receiver_wakeups);
+        wake(&header.sender_wakeups);
+    }
+}
+
+
+
+/// The sending end of a channel, see the [module docs](self).
+pub struct Sender<T> {
+    ring: Ring<T>,
+    /// The latest message that was coalesced rather than sent, see
+    /// [`Sender::send_coalesced`].
+    held: Option<T>,
+}
+
+impl<T> Sender<T> {
+    /// Open the sending end of a channel created with [`init`].
+    ///
+    /// # Safety
+    ///
+    /// The provided pointer **MUST** point to an initialized channel, and there
+    /// must be no other senders for it.
+    pub unsafe fn from_existing(base: *mut u8) -> Result<Self> {
+        Ok(Self {
+            ring: unsafe { Ring::from_existing(base)? },
+            held: None,
+        })
+    }
+
+    /// Send a message if there is room for it, returning it otherwise.
+    ///
+    /// Returns [`Error::PIPE`] if the channel was closed.
+    pub fn try_send(&self, message: T) -> Result<core::result::Result<(), T>> {
+        if self.ring.is_closed() {
+            return Err(Error::PIPE);
+        }
+
+        let header = self.ring.header();
+        let head = header.head.load(Ordering::Relaxed);
+        let tail = header.tail.load(Ordering::Acquire);
+        if head.wrapping_sub(tail) >= header.capacity {
+            return Ok(Err(message));
+        }
+
+        // SAFETY: The receiver doesn't read this slot until the head is moved past it.
+        unsafe { self.ring.slot(head).write(MaybeUninit::new(message)) };
+        header.head.store(head.wrapping_add(1), Ordering::SeqCst);
+
+        if header.receiver_waiting.load(Ordering::SeqCst) != 0 {
+            wake(&header.receiver_wakeups);
+        }
+
+        Ok(Ok(()))
+    }
+
+    /// Send a message, blocking until there is room for it.
+    ///
+    /// Returns [`Error::PIPE`] if the channel was closed.
+    pub fn send(&self, mut message: T) -> Result<()> {
+        let header = self.ring.header();
+        loop {
+            message = match self.try_send(message)? {
+                Ok(()) => return Ok(()),
+                Err(message) => message,
+            };
+
+            header.sender_waiting.store(1, Ordering::SeqCst);
+            let wakeups = header.sender_wakeups.load(Ordering::SeqCst);
+            // Check again in case the receiver took a message before seeing that this
+            // is waiting.
+            if self.ring.len() >= header.capacity && !self.ring.is_closed() {
+                futex_wait(&header.sender_wakeups, wakeups, None)?;
+            }
+            header.sender_waiting.store(0, Ordering::SeqCst);
+        }
+    }
+
+    /// Send a message if there is room for it, otherwise discard it and
+    /// increment the [overflow count](Self::overflow_count). Returns whether
+    /// the message was sent.
+    ///
+    /// Returns [`Error::PIPE`] if the channel was closed.
+    pub fn send_or_discard(&self, message: T) -> Result<bool> {
+        match self.try_send(message)? {
+            Ok(()) => Ok(true),
+            Err(_message) => {
+                self.ring
+                    .header()
+                    .overflow_count
+                    .fetch_add(1, Ordering::Relaxed);
+                Ok(false)
+            }
+        }
+    }
+
+    /// Send a message like [`send_or_discard`](Self::send_or_discard), except
+    /// that a message for which `coalesces` returns `true` is held back while
+    /// the receiver has set [back pressure](Self::back_pressure) or the channel
+    /// is full, and replaced by the next one that coalesces. Returns whether
+    /// the message was sent or held.
+    ///
+    /// The held message is sent before any message that doesn't coalesce, or
+    /// by [`flush`](Self::flush), which should be called after each batch of
+    /// messages.
+    ///
+    /// Returns [`Error::PIPE`] if the channel was closed.
+    pub fn send_coalesced(&mut self, message: T, coalesces: impl Fn(&T) -> bool) -> Result<bool> {
+        if self.ring.is_closed() {
+            return Err(Error::PIPE);
+        }
+
+        if coalesces(&message) {
+            if self.held.is_some()
+                || self.back_pressure()
+                || self.ring.len() >= self.ring.header().capacity
+            {
+                self.held = Some(message);
+                return Ok(true);
+            }
+        } else {
+            self.flush()?;
+        }
+
+        self.send_or_discard(message)
+    }
+
+    /// Send the message held back by [`send_coalesced`](Self::send_coalesced),
+    /// if there is one, discarding it if there's no room. Returns `false` if it
+    /// was discarded.
+    ///
+    /// Returns [`Error::PIPE`] if the channel was closed.
+    pub fn flush(&mut self) -> Result<bool> {
+        match self.held.take() {
+            Some(message) => self.send_or_discard(message),
+            None => Ok(true),
+        }
+    }
+
+    /// Whether the receiver has asked for messages to be coalesced, see
+    /// [`Receiver::set_back_pressure`].
+    pub fn back_pressure(&self) -> bool {
+        self.ring.header().back_pressure.load(Ordering::Relaxed) != 0
+    }
+
+    /// The sequence number of the next message to be sent, which is also the
+    /// number of messages sent so far (wrapping around).
+    pub fn sequence(&self) -> u32 {
+        self.ring.header().head.load(Ordering::Relaxed)
+    }
+
+    /// The number of messages that were discarded because the channel was
+    /// full.
+    pub fn overflow_count(&self) -> u64 {
+        self.ring.header().overflow_count.load(Ordering::Relaxed)
+    }
+
+    /// The number of messages waiting to be received.
+    pub fn len(&self) -> usize {
+        self.ring.len() as usize
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.ring.header().capacity as usize
+    }
+
+    /// Close the channel, waking up the receiver if it's waiting. Messages
+    /// that were already sent can still be received.
+    pub fn close(&self) {
+        self.ring.close();
+    }
+}
+
+
+
+/// The receiving end of a channel, see the [module docs](self).
+///
+/// NOTE: Messages left in the channel are never dropped, since they live in
+///       shared memory.
+pub struct Receiver<T> {
+    ring: Ring<T>,
+}
+
+impl<T> Receiver<T> {
+    /// Open the receiving end of a channel created with [`init`].
+    ///
+    /// # Safety
+    ///
+    /// The provided pointer **MUST** point to an initialized channel, and there
+    /// must be no other receivers for it.
+    pub unsafe fn from_existing(base: *mut u8) -> Result<Self> {
+        Ok(Self {
+            ring: unsafe { Ring::from_existing(base)? },
+        })
+    }
+
+    /// Receive the next message, if one has been sent.
+    pub fn try_recv(&self) -> Option<T> {
+        let header = self.ring.header();
+        let tail = header.tail.load(Ordering::Relaxed);
+        let head = header.head.load(Ordering::Acquire);
+        if head == tail {
+            return None;
+        }
+
+        // SAFETY: The sender wrote this slot before moving the head past it, and won't
+        //         write it again until the tail is moved past it.
+        let message = unsafe { self.ring.slot(tail).read().assume_init() };
+        header.tail.store(tail.wrapping_add(1), Ordering::SeqCst);
+
+        if header.sender_waiting.load(Ordering::SeqCst) != 0 {
+            wake(&header.sender_wakeups);
+        }
+
+        Some(message)
+    }
+
+    /// Receive the next message, blocking until one is sent or the timeout
+    /// expires (if there is one).
+    ///
+    /// Returns [`Error::TIMEDOUT`] if the timeout expired, or [`Error::PIPE`]
+    /// if the channel was closed and every message was received.
+    pub fn recv(&self, timeout: Option<Duration>) -> Result<T> {
+        let header = self.ring.header();
+        loop {
+            if let Some(message) = self.try_recv() {
+                return Ok(message);
+            }
+            if self.ring.is_closed() {
+                return Err(Error::PIPE);
+            }
+
+            header.receiver_waiting.store(1, Ordering::SeqCst);
+            let wakeups = header.receiver_wakeups.load(Ordering::SeqCst);
+            // Check again in case the sender sent a message before seeing that this is
+            // waiting.
+            let result = if self.ring.len() == 0 && !self.ring.is_closed() {
+                futex_wait(&header.receiver_wakeups, wakeups, timeout)
+            } else {
+                Ok(())
+            };
+            header.receiver_waiting.store(0, Ordering::SeqCst);
+            result?;
+        }
+    }
+
+    /// Receive every message that has been sent.
+    pub fn drain(&self) -> impl Iterator<Item = T> + '_ {
+        core::iter::from_fn(|| self.try_recv())
+    }
+
+    /// Ask the sender to coalesce the messages that can be while this is
+    /// falling behind, see [`Sender::send_coalesced`].
+    pub fn set_back_pressure(&self, back_pressure: bool) {
+        self.ring
+            .header()
+            .back_pressure
+            .store(back_pressure as u32, Ordering::Relaxed);
+    }
+
+    /// The sequence number of the next message to be received, which is also
+    /// the number of messages received so far (wrapping around).
+    pub fn sequence(&self) -> u32 {
+        self.ring.header().tail.load(Ordering::Relaxed)
+    }
+
+    /// The number of messages that the sender discarded because the channel
+    /// was full.
+    pub fn overflow_count(&self) -> u64 {
+        self.ring.header().overflow_count.load(Ordering::Relaxed)
+    }
+
+    /// The number of messages waiting to be received.
+    pub fn len(&self) -> usize {
+        self.ring.len() as usize
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.ring.header().capacity as usize
+    }
+
+    /// Close the channel, waking up the sender if it's waiting.
+    pub fn close(&self) {
+        self.ring.close();
+    }
+}
+
+
+
+/// Block until `futex` is woken up, if it still holds `expected`.
+///
+/// Spurious wakeups (and interruptions) are reported as success, since callers
+/// check their condition again anyway.
+// https://www.man7.org/linux/man-pages/man2/futex.2.html
+fn futex_wait(futex: &AtomicU32, expected: u32, timeout: Option<Duration>) -> Result<()> {
+    let timeout = timeout.map(|timeout| libc::timespec {
+        tv_sec: timeout.as_secs().min(libc::time_t::MAX as u64) as _,
+        tv_nsec: timeout.subsec_nanos() as _,
+    });
+    // NOTE: This isn't `FUTEX_PRIVATE_FLAG`, since the other end is in another
+    //       process.
+    let res = unsafe {
+        libc::syscall(
+            libc::SYS_futex,
+            futex.as_ptr(),
+            libc::FUTEX_WAIT,
+            expected,
+            timeout
+                .as_ref()
+                .map_or(core::ptr::null(), |timeout| timeout as *const _),
+        )
+    };
+    if res == -1 {
+        match Error::latest() {
+            Error::AGAIN | Error::INTR => Ok(()),
+            error => Err(error),
+        }
+    } else {
+        Ok(())
+    }
+}
+
+/// Change `futex` and wake up everything waiting on it, so that anything about
+/// to wait on it doesn't.
+fn wake(futex: &AtomicU32) {
+    futex.fetch_add(1, Ordering::SeqCst);
+    let _ = unsafe { libc::syscall(libc::SYS_futex, futex.as_ptr(), libc::FUTEX_WAKE, i32::MAX) };
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    extern crate std;
+
+    use std::{prelude::v1::*, thread};
+
+    /// Memory for a channel of `capacity` `u32`s, aligned for the header.
+    fn channel_memory(capacity: u32) -> Box<[u64]> {
+        vec![0u64; required_size::<u32>(capacity).div_ceil(size_of::<u64>())].into_boxed_slice()
+    }
+
+    fn open(memory: &mut [u64], capacity: u32) -> (Sender<u32>, Receiver<u32>) {
+        let base = memory.as_mut_ptr() as *mut u8;
+        unsafe {
+            init::<u32>(base, capacity).unwrap();
+            (
+                Sender::from_existing(base).unwrap(),
+                Receiver::from_existing(base).unwrap(),
+            )
+        }
+    }
+
+    #[test]
+    fn capacity_must_be_power_of_two() {
+        let mut memory = channel_memory(3);
+        let base = memory.as_mut_ptr() as *mut u8;
+        assert_eq!(unsafe { init::<u32>(base, 0) }, Err(Error::INVAL));
+        assert_eq!(unsafe { init::<u32>(base, 3) }, Err(Error::INVAL));
+        assert_eq!(unsafe { init::<u32>(base, 2) }, Ok(()));
+        // Both ends check the message size too.
+        assert!(unsafe { Receiver::<u64>::from_existing(base) }.is_err());
+    }
+
+    #[test]
+    fn sequence_wraparound() {
+        let mut memory = channel_memory(4);
+        let (sender, receiver) = open(&mut memory, 4);
+        let header = sender.ring.header();
+        header.head.store(u32::MAX - 2, Ordering::Relaxed);
+        header.tail.store(u32::MAX - 2, Ordering::Relaxed);
+
+        for round in 0..3 {
+            for index in 0..4 {
+                assert_eq!(sender.try_send(round * 4 + index), Ok(Ok(())));
+            }
+            assert_eq!(sender.len(), 4);
+            assert_eq!(sender.try_send(100), Ok(Err(100)));
+            assert_eq!(sender.send_or_discard(100), Ok(false));
+            assert_eq!(
+                receiver.drain().collect::<Vec<_>>(),
+                (round * 4..round * 4 + 4).collect::<Vec<_>>(),
+            );
+            assert!(receiver.is_empty());
+        }
+        assert_eq!(sender.sequence(), (u32::MAX - 2).wrapping_add(12));
+        assert_eq!(receiver.sequence(), sender.sequence());
+        assert_eq!(receiver.overflow_count(), 3);
+    }
+
+    #[test]
+    fn coalescing_and_back_pressure() {
+        let mut memory = channel_memory(2);
+        let (mut sender, receiver) = open(&mut memory, 2);
+        // Messages from 100 up stand in for pointer moves.
+        let coalesces = |message: &u32| *message >= 100;
+
+        assert_eq!(sender.send_coalesced(0, coalesces), Ok(true));
+        assert_eq!(sender.send_coalesced(101, coalesces), Ok(true));
+        // Full, so the moves are held and merged.
+        assert_eq!(sender.send_coalesced(102, coalesces), Ok(true));
+        assert_eq!(sender.send_coalesced(103, coalesces), Ok(true));
+        assert_eq!(sender.overflow_count(), 0);
+        // The held move goes first, so there's no room for anything after it.
+        assert_eq!(sender.send_coalesced(1, coalesces), Ok(false));
+        assert_eq!(sender.overflow_count(), 2);
+        assert_eq!(receiver.drain().collect::<Vec<_>>(), [0, 101]);
+
+        receiver.set_back_pressure(true);
+        assert!(sender.back_pressure());
+        assert_eq!(sender.send_coalesced(104, coalesces), Ok(true));
+        assert_eq!(sender.send_coalesced(105, coalesces), Ok(true));
+        assert!(receiver.is_empty());
+        assert_eq!(sender.flush(), Ok(true));
+        assert_eq!(sender.flush(), Ok(true));
+        assert_eq!(receiver.drain().collect::<Vec<_>>(), [105]);
+
+        receiver.set_back_pressure(false);
+        assert_eq!(sender.send_coalesced(106, coalesces), Ok(true));
+        assert_eq!(sender.send_coalesced(107, coalesces), Ok(true));
+        assert_eq!(receiver.drain().collect::<Vec<_>>(), [106, 107]);
+
+        receiver.close();
+        assert_eq!(sender.send_coalesced(108, coalesces), Err(Error::PIPE));
+    }
+
+    #[test]
+    fn blocked_receiver_wakes() {
+        let mut memory = channel_memory(2);
+        let (sender, receiver) = open(&mut memory, 2);
+
+        let receiving = thread::spawn(move || {
+            let first = receiver.recv(None);
+            let second = receiver.recv(None);
+            (first, second)
+        });
+        thread::sleep(Duration::from_millis(20));
+        sender.send(1).unwrap();
+        thread::sleep(Duration::from_millis(20));
+        sender.close();
+
+        assert_eq!(receiving.join().unwrap(), (Ok(1), Err(Error::PIPE)));
+        assert_eq!(sender.try_send(2), Err(Error::PIPE));
+    }
+
+    #[test]
+    fn blocked_sender_wakes() {
+        let mut memory = channel_memory(1);
+        let (sender, receiver) = open(&mut memory, 1);
+        sender.send(1).unwrap();
+
+        let sending = thread::spawn(move || {
+            let first = sender.send(2);
+            let second = sender.send(3);
+            (first, second)
+        });
+        // Receiving the first message makes room for the second, and the third
+        // waits until the channel is closed.
+        thread::sleep(Duration::from_millis(20));
+        assert_eq!(receiver.try_recv(), Some(1));
+        thread::sleep(Duration::from_millis(20));
+        receiver.close();
+
+        assert_eq!(sending.join().unwrap(), (Ok(()), Err(Error::PIPE)));
+        assert_eq!(receiver.try_recv(), Some(2));
+        assert_eq!(receiver.recv(None), Err(Error::PIPE));
+    }
+
+    #[test]
+    fn receive_timeout() {
+        let mut memory = channel_memory(2);
+        let (_sender, receiver) = open(&mut memory, 2);
+        assert_eq!(
+            receiver.recv(Some(Duration::from_millis(10))),
+            Err(Error::TIMEDOUT),
+        );
+    }
+}
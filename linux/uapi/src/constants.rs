@@ -2,7 +2,7 @@
 
 #![allow(non_snake_case, overflowing_literals)]
 
-use core::ffi::{c_int, c_ulong, c_void};
+use core::ffi::{c_int, c_uint, c_ulong, c_void};
 
 pub const STDIN_FILENO: c_int = 0;
 pub const STDOUT_FILENO: c_int = 1;
@@ -242,6 +242,41 @@ pub const EPOLLWAKEUP: c_int = 0x20000000;
 pub const EPOLLONESHOT: c_int = 0x40000000;
 pub const EPOLLET: c_int = 0x80000000;
 
+pub const CLOCK_REALTIME: c_int = 0;
+pub const CLOCK_MONOTONIC: c_int = 1;
+
+pub const TFD_NONBLOCK: c_int = O_NONBLOCK;
+pub const TFD_CLOEXEC: c_int = O_CLOEXEC;
+
+pub const EFD_SEMAPHORE: c_int = 0x1;
+pub const EFD_NONBLOCK: c_int = O_NONBLOCK;
+pub const EFD_CLOEXEC: c_int = O_CLOEXEC;
+
+pub const IN_NONBLOCK: c_int = O_NONBLOCK;
+pub const IN_CLOEXEC: c_int = O_CLOEXEC;
+
+pub const IN_ACCESS: c_uint = 0x00000001;
+pub const IN_MODIFY: c_uint = 0x00000002;
+pub const IN_ATTRIB: c_uint = 0x00000004;
+pub const IN_CLOSE_WRITE: c_uint = 0x00000008;
+pub const IN_CLOSE_NOWRITE: c_uint = 0x00000010;
+pub const IN_OPEN: c_uint = 0x00000020;
+pub const IN_MOVED_FROM: c_uint = 0x00000040;
+pub const IN_MOVED_TO: c_uint = 0x00000080;
+pub const IN_CREATE: c_uint = 0x00000100;
+pub const IN_DELETE: c_uint = 0x00000200;
+pub const IN_DELETE_SELF: c_uint = 0x00000400;
+pub const IN_MOVE_SELF: c_uint = 0x00000800;
+pub const IN_UNMOUNT: c_uint = 0x00002000;
+pub const IN_Q_OVERFLOW: c_uint = 0x00004000;
+pub const IN_IGNORED: c_uint = 0x00008000;
+pub const IN_ONLYDIR: c_uint = 0x01000000;
+pub const IN_DONT_FOLLOW: c_uint = 0x02000000;
+pub const IN_EXCL_UNLINK: c_uint = 0x04000000;
+pub const IN_MASK_ADD: c_uint = 0x20000000;
+pub const IN_ISDIR: c_uint = 0x40000000;
+pub const IN_ONESHOT: c_uint = 0x80000000;
+
 pub const WNOHANG: c_int = 0x00000001;
 pub const WUNTRACED: c_int = 0x00000002;
 pub const WSTOPPED: c_int = WUNTRACED;
@@ -342,6 +377,22 @@ pub const TIOCGPTLCK: c_ulong = 0x80045439;
 pub const TIOCGEXCL: c_ulong = 0x80045440;
 pub const TIOCGPTPEER: c_ulong = 0x5441;
 
+pub const KDSETMODE: c_ulong = 0x4B3A;
+pub const KDGKBMODE: c_ulong = 0x4B44;
+pub const KDSKBMODE: c_ulong = 0x4B45;
+pub const KD_TEXT: c_int = 0;
+pub const KD_GRAPHICS: c_int = 1;
+pub const K_OFF: c_int = 4;
+pub const VT_SETMODE: c_ulong = 0x5602;
+pub const VT_GETSTATE: c_ulong = 0x5603;
+pub const VT_RELDISP: c_ulong = 0x5605;
+pub const VT_ACTIVATE: c_ulong = 0x5606;
+pub const VT_AUTO: i8 = 0;
+pub const VT_PROCESS: i8 = 1;
+pub const VT_ACKACQ: c_int = 2;
+
+pub const EVIOCREVOKE: c_ulong = 0x40044591;
+
 pub const S_IFIFO: u32 = 0o1_0000;
 pub const S_IFCHR: u32 = 0o2_0000;
 pub const S_IFBLK: u32 = 0o6_0000;
@@ -372,3 +423,17 @@ pub const SFD_CLOEXEC: c_int = 0x80000;
 
 pub const PTHREAD_PROCESS_PRIVATE: c_int = 0;
 pub const PTHREAD_PROCESS_SHARED: c_int = 1;
+pub const PTHREAD_MUTEX_STALLED: c_int = 0;
+pub const PTHREAD_MUTEX_ROBUST: c_int = 1;
+pub const PTHREAD_PRIO_NONE: c_int = 0;
+pub const PTHREAD_PRIO_INHERIT: c_int = 1;
+pub const PTHREAD_PRIO_PROTECT: c_int = 2;
+
+pub const AF_NETLINK: c_int = 16;
+pub const SOCK_DGRAM: c_int = 2;
+pub const SOCK_NONBLOCK: c_int = O_NONBLOCK;
+pub const SOCK_CLOEXEC: c_int = O_CLOEXEC;
+pub const NETLINK_KOBJECT_UEVENT: c_int = 15;
+/// The netlink multicast group that the kernel sends uevents to (as opposed to
+/// udev, which rebroadcasts them to its own group).
+pub const UEVENT_KERNEL_GROUP: u32 = 1;
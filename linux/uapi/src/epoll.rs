@@ -1,5 +1,7 @@
 //! # Polling Interfaces
 
+use core::time::Duration;
+
 use alloc::vec::Vec;
 
 use crate::{Error, Result, constants, file::File, traits::AsFile};
@@ -29,16 +31,20 @@ impl EventPoll {
     }
 
     // https://www.man7.org/linux/man-pages/man2/epoll_ctl.2.html
-    pub fn add(&self, fd: &File, mut ev: Event) -> Result<()> {
+    pub fn add(&self, fd: &File, ev: Event) -> Result<()> {
+        self.control(constants::EPOLL_CTL_ADD, fd, ev)
+    }
+
+    /// Change the event registered for a file, which also re-arms
+    /// [one-shot](Trigger::OneShot) registrations.
+    // https://www.man7.org/linux/man-pages/man2/epoll_ctl.2.html
+    pub fn modify(&self, fd: &File, ev: Event) -> Result<()> {
+        self.control(constants::EPOLL_CTL_MOD, fd, ev)
+    }
+
+    fn control(&self, op: i32, fd: &File, mut ev: Event) -> Result<()> {
         let ptr = &mut ev as *mut Event;
-        let res = unsafe {
-            libc::epoll_ctl(
-                self.fd,
-                constants::EPOLL_CTL_ADD,
-                fd.fd,
-                ptr as *mut libc::epoll_event,
-            )
-        };
+        let res = unsafe { libc::epoll_ctl(self.fd, op, fd.fd, ptr as *mut libc::epoll_event) };
         if res == -1 {
             Err(Error::latest())
         } else {
@@ -78,6 +84,19 @@ impl EventPoll {
             Ok(new_events_count)
         }
     }
+
+    /// Like [`EventPoll::wait`], but waits forever if `timeout` is `None`.
+    ///
+    /// The timeout is rounded up to the next millisecond, so that this never
+    /// returns before it expires.
+    pub fn wait_for(&self, events: &mut Vec<Event>, timeout: Option<Duration>) -> Result<usize> {
+        let timeout_ms = match timeout {
+            Some(timeout) => timeout.as_nanos().div_ceil(1_000_000).min(i32::MAX as u128) as i32,
+            None => -1,
+        };
+
+        self.wait(events, timeout_ms)
+    }
 }
 
 
@@ -91,17 +110,27 @@ pub struct Event {
 
 impl Event {
     pub fn new(data: u64, readable: bool, writable: bool) -> Self {
-        let mut flags = 0;
+        let mut interest = Interest::NONE;
         if readable {
-            flags |= constants::EPOLLIN | constants::EPOLLPRI;
+            interest = interest | Interest::READABLE | Interest::PRIORITY;
         }
         if writable {
-            flags |= constants::EPOLLOUT;
+            interest = interest | Interest::WRITABLE;
         }
 
+        Self::with_interest(data, interest, Trigger::Level)
+    }
+
+    pub fn with_interest(data: u64, interest: Interest, trigger: Trigger) -> Self {
+        let trigger = match trigger {
+            Trigger::Level => 0,
+            Trigger::Edge => constants::EPOLLET,
+            Trigger::OneShot => constants::EPOLLONESHOT,
+        };
+
         Self {
             raw: libc::epoll_event {
-                events: flags as _,
+                events: (interest.0 | trigger) as _,
                 u64: data,
             },
         }
@@ -111,6 +140,12 @@ impl Event {
         self.raw.u64
     }
 
+    /// Get what the file became ready for, for an event returned by
+    /// [`EventPoll::wait`].
+    pub fn readiness(&self) -> Readiness {
+        Readiness(self.raw.events as i32)
+    }
+
     pub fn readable(&self) -> bool {
         self.raw.events & (constants::EPOLLIN | constants::EPOLLPRI) as u32 != 0
     }
@@ -119,3 +154,203 @@ impl Event {
         self.raw.events & constants::EPOLLOUT as u32 != 0
     }
 }
+
+/// What to wait for a file to become ready for, see [`Event::with_interest`].
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct Interest(i32);
+
+impl Interest {
+    pub const NONE: Self = Self(0);
+    /// The file can be read from.
+    pub const READABLE: Self = Self(constants::EPOLLIN);
+    /// The file can be written to.
+    pub const WRITABLE: Self = Self(constants::EPOLLOUT);
+    /// The file has exceptional data, such as out-of-band data on a socket.
+    pub const PRIORITY: Self = Self(constants::EPOLLPRI);
+    /// The other end of a socket stopped writing.
+    pub const READ_HANGUP: Self = Self(constants::EPOLLRDHUP);
+
+    pub const fn contains(self, other: Self) -> bool {
+        self.0 & other.0 == other.0
+    }
+}
+
+impl core::ops::BitOr for Interest {
+    type Output = Self;
+
+    fn bitor(self, rhs: Self) -> Self::Output {
+        Self(self.0 | rhs.0)
+    }
+}
+
+/// When a registered file produces events, see [`Event::with_interest`].
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum Trigger {
+    /// Whenever the file is ready.
+    #[default]
+    Level,
+    /// Only when the file becomes ready again, so it has to be drained (until
+    /// it would block) before it produces another event.
+    Edge,
+    /// Only the first time the file is ready, until it's
+    /// [re-armed](EventPoll::modify).
+    OneShot,
+}
+
+/// What a file became ready for, see [`Event::readiness`].
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct Readiness(i32);
+
+impl Readiness {
+    pub const fn is_readable(self) -> bool {
+        self.0 & constants::EPOLLIN != 0
+    }
+
+    pub const fn is_writable(self) -> bool {
+        self.0 & constants::EPOLLOUT != 0
+    }
+
+    pub const fn is_priority(self) -> bool {
+        self.0 & constants::EPOLLPRI != 0
+    }
+
+    /// Whether the other end of the file stopped writing (or was closed).
+    pub const fn is_hangup(self) -> bool {
+        self.0 & (constants::EPOLLHUP | constants::EPOLLRDHUP) != 0
+    }
+
+    pub const fn is_error(self) -> bool {
+        self.0 & constants::EPOLLERR != 0
+    }
+}
+
+
+
+/// A timer that makes its file readable each time it expires, so that it can
+/// wake up an [`EventPoll`].
+// https://www.man7.org/linux/man-pages/man2/timerfd_create.2.html
+pub struct TimerFile {
+    fd: i32,
+}
+
+impl AsFile for TimerFile {
+    fn as_file(&self) -> File {
+        File { fd: self.fd }
+    }
+}
+
+impl TimerFile {
+    /// Create a non-blocking, disarmed timer on the monotonic clock.
+    pub fn create() -> Result<Self> {
+        let res = unsafe {
+            libc::timerfd_create(
+                constants::CLOCK_MONOTONIC,
+                constants::TFD_NONBLOCK | constants::TFD_CLOEXEC,
+            )
+        };
+        if res == -1 {
+            Err(Error::latest())
+        } else {
+            Ok(Self { fd: res })
+        }
+    }
+
+    /// Arm the timer to expire after `initial`, then every `interval` if
+    /// there is one.
+    ///
+    /// An `initial` delay of zero expires as soon as possible, rather than
+    /// disarming the timer like `timerfd_settime` would.
+    // https://www.man7.org/linux/man-pages/man2/timerfd_settime.2.html
+    pub fn set(&self, initial: Duration, interval: Option<Duration>) -> Result<()> {
+        self.set_raw(
+            initial.max(Duration::from_nanos(1)),
+            interval.unwrap_or(Duration::ZERO),
+        )
+    }
+
+    /// Stop the timer from expiring until it's [set](Self::set) again.
+    pub fn disarm(&self) -> Result<()> {
+        self.set_raw(Duration::ZERO, Duration::ZERO)
+    }
+
+    fn set_raw(&self, initial: Duration, interval: Duration) -> Result<()> {
+        let timespec = |duration: Duration| libc::timespec {
+            tv_sec: duration.as_secs() as _,
+            tv_nsec: duration.subsec_nanos() as _,
+        };
+        let new_value = libc::itimerspec {
+            it_interval: timespec(interval),
+            it_value: timespec(initial),
+        };
+        let res = unsafe { libc::timerfd_settime(self.fd, 0, &new_value, core::ptr::null_mut()) };
+        if res == -1 {
+            Err(Error::latest())
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Get the number of times the timer expired since this was last called,
+    /// which is zero if it hasn't.
+    pub fn expirations(&self) -> Result<u64> {
+        read_counter(self.fd)
+    }
+}
+
+
+
+/// A counter that makes its file readable while it's nonzero, so that other
+/// threads can wake up an [`EventPoll`].
+// https://www.man7.org/linux/man-pages/man2/eventfd.2.html
+pub struct EventFile {
+    fd: i32,
+}
+
+impl AsFile for EventFile {
+    fn as_file(&self) -> File {
+        File { fd: self.fd }
+    }
+}
+
+impl EventFile {
+    /// Create a non-blocking event file with a counter of zero.
+    pub fn create() -> Result<Self> {
+        let res = unsafe { libc::eventfd(0, constants::EFD_NONBLOCK | constants::EFD_CLOEXEC) };
+        if res == -1 {
+            Err(Error::latest())
+        } else {
+            Ok(Self { fd: res })
+        }
+    }
+
+    /// Add one to the counter, waking up anything waiting for this file.
+    pub fn notify(&self) -> Result<()> {
+        let res = unsafe { libc::eventfd_write(self.fd, 1) };
+        if res == -1 {
+            Err(Error::latest())
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Reset the counter to zero, returning what it was.
+    pub fn take(&self) -> Result<u64> {
+        read_counter(self.fd)
+    }
+}
+
+/// Read the 8-byte counter of a non-blocking timer or event file, which is
+/// zero if it would block.
+fn read_counter(fd: i32) -> Result<u64> {
+    let mut counter = [0; size_of::<u64>()];
+    let len = counter.len();
+    let res = crate::raw::read(fd, &mut counter, len);
+    if res == -1 {
+        match Error::latest() {
+            Error::AGAIN => Ok(0),
+            error => Err(error),
+        }
+    } else {
+        Ok(u64::from_ne_bytes(counter))
+    }
+}
@@ -5,6 +5,7 @@
 extern crate alloc;
 
 pub mod c_str;
+pub mod channel;
 pub mod constants;
 pub mod epoll;
 mod error;
@@ -16,6 +17,9 @@ pub mod raw;
 pub mod shm;
 pub mod signal;
 pub mod traits;
+pub mod uevent;
+pub mod vt;
+pub mod watch;
 
 pub use {error::Error, signal::Signal};
 
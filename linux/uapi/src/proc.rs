@@ -48,6 +48,10 @@ impl Process {
             Some(ProcessGroup { id })
         }
     }
+
+    pub fn id(&self) -> i32 {
+        self.id
+    }
 }
 
 
@@ -1,6 +1,10 @@
 //! # Shared Memory
 
-use core::ops::{Deref, DerefMut};
+use core::{
+    mem::MaybeUninit,
+    ops::{Deref, DerefMut},
+    time::Duration,
+};
 
 use crate::{Error, Result, c_str::AsCStr, constants};
 
@@ -127,6 +131,20 @@ impl SharedMemory {
 
 
 /// A mutual exclusion primitive useful for protecting shared memory.
+///
+/// The mutex is robust, so a process dying while holding it doesn't block
+/// every other process forever. Instead, the next process to lock it is told
+/// that the owner died (see [`MutexGuard::owner_died`]), and has to make the
+/// data consistent again before unlocking it.
+///
+/// It also uses priority inheritance, so a low priority process holding the
+/// lock (like a driver) is boosted while a higher priority process (like the
+/// shell) waits for it.
+///
+/// NOTE: These are glibc's robust PI mutexes, which are implemented with
+///       `FUTEX_LOCK_PI` and the thread's robust list. The robust list can't be
+///       registered separately, since glibc already registers one per thread.
+///       https://www.kernel.org/doc/html/latest/locking/robust-futexes.html
 pub struct Mutex<T: Sized> {
     ptr: *mut libc::pthread_mutex_t,
     data: core::cell::UnsafeCell<*mut T>,
@@ -163,7 +181,22 @@ impl<T: Sized> Mutex<T> {
             return Err(Error::from_raw(res));
         }
 
+        let res = unsafe {
+            libc::pthread_mutexattr_setrobust(&mut lock_attr, constants::PTHREAD_MUTEX_ROBUST)
+        };
+        if res != 0 {
+            return Err(Error::from_raw(res));
+        }
+
+        let res = unsafe {
+            libc::pthread_mutexattr_setprotocol(&mut lock_attr, constants::PTHREAD_PRIO_INHERIT)
+        };
+        if res != 0 {
+            return Err(Error::from_raw(res));
+        }
+
         let res = unsafe { libc::pthread_mutex_init(ptr, &lock_attr) };
+        let _ = unsafe { libc::pthread_mutexattr_destroy(&mut lock_attr) };
         if res != 0 {
             return Err(Error::from_raw(res));
         }
@@ -191,20 +224,60 @@ impl<T: Sized> Mutex<T> {
 
     /// Acquires a mutex, blocking the current thread until it is able to do so.
     ///
+    /// If the previous owner died while holding the mutex, the guard is still
+    /// returned, see [`MutexGuard::owner_died`]. Returns
+    /// [`Error::NOTRECOVERABLE`] if a guard like that was dropped without being
+    /// [marked consistent](MutexGuard::mark_consistent).
+    ///
     /// **Warning:** This will cause a deadlock if the current thread is already
     /// holding this mutex.
     pub fn lock(&self) -> Result<MutexGuard<'_, T>> {
         let res = unsafe { libc::pthread_mutex_lock(self.ptr) };
-        if res != 0 {
-            return Err(Error::from_raw(res));
+        self.guard_for(res)
+    }
+
+    /// Acquires a mutex if no one else is holding it, returning `None`
+    /// otherwise.
+    ///
+    /// See [`Mutex::lock`] for what happens if the previous owner died.
+    pub fn try_lock(&self) -> Result<Option<MutexGuard<'_, T>>> {
+        let res = unsafe { libc::pthread_mutex_trylock(self.ptr) };
+        if res == constants::EBUSY {
+            return Ok(None);
         }
 
-        Ok(MutexGuard { mutex: self })
+        self.guard_for(res).map(Some)
+    }
+
+    /// Acquires a mutex, blocking the current thread for at most `timeout`.
+    /// Returns [`Error::TIMEDOUT`] if the mutex couldn't be acquired in time.
+    ///
+    /// See [`Mutex::lock`] for what happens if the previous owner died.
+    pub fn lock_timeout(&self, timeout: Duration) -> Result<MutexGuard<'_, T>> {
+        // NOTE: Timed locks always use the realtime clock.
+        let deadline = deadline_after(libc::CLOCK_REALTIME, timeout);
+        let res = unsafe { libc::pthread_mutex_timedlock(self.ptr, &deadline) };
+        self.guard_for(res)
     }
 }
 
 // Private.
 impl<T: Sized> Mutex<T> {
+    /// Create the guard for the result of a `pthread_mutex_*lock` function.
+    fn guard_for(&self, res: i32) -> Result<MutexGuard<'_, T>> {
+        match res {
+            0 => Ok(MutexGuard {
+                mutex: self,
+                owner_died: false,
+            }),
+            constants::EOWNERDEAD => Ok(MutexGuard {
+                mutex: self,
+                owner_died: true,
+            }),
+            _ => Err(Error::from_raw(res)),
+        }
+    }
+
     fn unlock(&self) -> Result<()> {
         let res = unsafe { libc::pthread_mutex_unlock(self.ptr) };
         if res != 0 {
@@ -223,6 +296,36 @@ impl<T: Sized> Mutex<T> {
 
 pub struct MutexGuard<'lock, T: Sized> {
     mutex: &'lock Mutex<T>,
+    owner_died: bool,
+}
+
+impl<T: Sized> MutexGuard<'_, T> {
+    /// Whether the previous owner of the mutex died while holding it, which
+    /// means the data it protects may be inconsistent.
+    ///
+    /// The data should be repaired (or reset) and the mutex
+    /// [marked consistent](Self::mark_consistent) before this guard is
+    /// dropped, otherwise the mutex can never be locked again.
+    pub fn owner_died(&self) -> bool {
+        self.owner_died
+    }
+
+    /// Mark the mutex as consistent after its previous owner died, so that it
+    /// can be locked normally once this guard is dropped.
+    // https://www.man7.org/linux/man-pages/man3/pthread_mutex_consistent.3.html
+    pub fn mark_consistent(&mut self) -> Result<()> {
+        if !self.owner_died {
+            return Ok(());
+        }
+
+        let res = unsafe { libc::pthread_mutex_consistent(self.mutex.ptr) };
+        if res != 0 {
+            return Err(Error::from_raw(res));
+        }
+        self.owner_died = false;
+
+        Ok(())
+    }
 }
 
 impl<T: Sized> Drop for MutexGuard<'_, T> {
@@ -246,3 +349,359 @@ impl<T: Sized> DerefMut for MutexGuard<'_, T> {
         unsafe { self.mutex.get_inner() }
     }
 }
+
+
+
+/// A condition variable that can be shared between processes, for waiting
+/// until another process changes the data protected by a shared [`Mutex`].
+pub struct Condvar {
+    ptr: *mut libc::pthread_cond_t,
+}
+
+impl Condvar {
+    /// The size of the condition variable.
+    pub const SIZE: usize = size_of::<libc::pthread_cond_t>();
+
+    /// Creates a new shared condition variable at the given `base` pointer.
+    ///
+    /// # Safety
+    ///
+    /// The provided pointer **MUST** point to a memory region at least as large
+    /// as [`Self::SIZE`].
+    pub unsafe fn new(base: *mut u8) -> Result<Self> {
+        let ptr = base as *mut libc::pthread_cond_t;
+
+        let mut cond_attr = core::mem::MaybeUninit::uninit();
+
+        let res = unsafe { libc::pthread_condattr_init(cond_attr.as_mut_ptr()) };
+        if res != 0 {
+            return Err(Error::from_raw(res));
+        }
+
+        let mut cond_attr = unsafe { cond_attr.assume_init() };
+
+        let res = unsafe {
+            libc::pthread_condattr_setpshared(&mut cond_attr, constants::PTHREAD_PROCESS_SHARED)
+        };
+        if res != 0 {
+            return Err(Error::from_raw(res));
+        }
+
+        // Timeouts shouldn't be affected by changes to the system time.
+        let res = unsafe { libc::pthread_condattr_setclock(&mut cond_attr, libc::CLOCK_MONOTONIC) };
+        if res != 0 {
+            return Err(Error::from_raw(res));
+        }
+
+        let res = unsafe { libc::pthread_cond_init(ptr, &cond_attr) };
+        let _ = unsafe { libc::pthread_condattr_destroy(&mut cond_attr) };
+        if res != 0 {
+            return Err(Error::from_raw(res));
+        }
+
+        Ok(Self { ptr })
+    }
+
+    /// Opens an existing shared condition variable at the given `base`
+    /// pointer.
+    ///
+    /// # Safety
+    ///
+    /// The provided pointer **MUST** point to an already initialized condition
+    /// variable.
+    pub unsafe fn from_existing(base: *mut u8) -> Result<Self> {
+        Ok(Self {
+            ptr: base as *mut libc::pthread_cond_t,
+        })
+    }
+
+    /// Unlock the guard's mutex and block until this is notified, locking the
+    /// mutex again before returning.
+    ///
+    /// Like any condition variable, this can wake up spuriously, so the
+    /// condition being waited for should be checked again in a loop.
+    // https://www.man7.org/linux/man-pages/man3/pthread_cond_wait.3p.html
+    pub fn wait<'lock, T>(&self, guard: MutexGuard<'lock, T>) -> Result<MutexGuard<'lock, T>> {
+        let mutex = guard.mutex;
+        // The mutex is unlocked by the wait instead.
+        core::mem::forget(guard);
+
+        let res = unsafe { libc::pthread_cond_wait(self.ptr, mutex.ptr) };
+        mutex.guard_for(res)
+    }
+
+    /// Like [`Condvar::wait`], but only blocks for at most `timeout`. The
+    /// returned boolean is true if the timeout expired.
+    pub fn wait_timeout<'lock, T>(
+        &self,
+        guard: MutexGuard<'lock, T>,
+        timeout: Duration,
+    ) -> Result<(MutexGuard<'lock, T>, bool)> {
+        let mutex = guard.mutex;
+        core::mem::forget(guard);
+
+        let deadline = deadline_after(libc::CLOCK_MONOTONIC, timeout);
+        let res = unsafe { libc::pthread_cond_timedwait(self.ptr, mutex.ptr, &deadline) };
+        if res == constants::ETIMEDOUT {
+            mutex.guard_for(0).map(|guard| (guard, true))
+        } else {
+            mutex.guard_for(res).map(|guard| (guard, false))
+        }
+    }
+
+    /// Wake up one of the threads waiting on this, if there are any.
+    // https://www.man7.org/linux/man-pages/man3/pthread_cond_signal.3p.html
+    pub fn notify_one(&self) -> Result<()> {
+        let res = unsafe { libc::pthread_cond_signal(self.ptr) };
+        if res != 0 {
+            return Err(Error::from_raw(res));
+        }
+
+        Ok(())
+    }
+
+    /// Wake up every thread waiting on this.
+    // https://www.man7.org/linux/man-pages/man3/pthread_cond_broadcast.3p.html
+    pub fn notify_all(&self) -> Result<()> {
+        let res = unsafe { libc::pthread_cond_broadcast(self.ptr) };
+        if res != 0 {
+            return Err(Error::from_raw(res));
+        }
+
+        Ok(())
+    }
+}
+
+/// A single value passed between processes, such as the parameters a driver
+/// asks for before its host sets up their channels.
+///
+/// The value is protected by a robust [`Mutex`], so a process that dies while
+/// writing it leaves the mailbox empty rather than locked or half written, and
+/// [`Mailbox::take`] waits for it with a [`Condvar`].
+pub struct Mailbox<T: Copy> {
+    mutex: Mutex<MailboxSlot<T>>,
+    condvar: Condvar,
+}
+
+#[repr(C)]
+struct MailboxSlot<T> {
+    full: bool,
+    value: MaybeUninit<T>,
+}
+
+// Public.
+impl<T: Copy> Mailbox<T> {
+    /// The offset of the condition variable, after the mutex and its slot.
+    const CONDVAR_OFFSET: usize = (Mutex::<MailboxSlot<T>>::HEADER_SIZE
+        + size_of::<MailboxSlot<T>>())
+    .next_multiple_of(align_of::<libc::pthread_cond_t>());
+    /// The size of the mailbox.
+    pub const SIZE: usize = Self::CONDVAR_OFFSET + Condvar::SIZE;
+
+    /// Creates a new, empty mailbox at the given `base` pointer.
+    ///
+    /// Returns [`Error::INVAL`] if `T` needs to be aligned to more than 8
+    /// bytes.
+    ///
+    /// # Safety
+    ///
+    /// The provided pointer **MUST** be aligned to 8 bytes, and point to a
+    /// memory region at least as large as [`Self::SIZE`]. `T` must not contain
+    /// any pointers, since the other process can't use them.
+    pub unsafe fn new(base: *mut u8) -> Result<Self> {
+        if align_of::<T>() > align_of::<u64>() {
+            return Err(Error::INVAL);
+        }
+
+        let mailbox = Self {
+            mutex: unsafe { Mutex::new(base)? },
+            condvar: unsafe { Condvar::new(base.add(Self::CONDVAR_OFFSET))? },
+        };
+        let guard = mailbox.lock()?;
+        unsafe {
+            guard.write(MailboxSlot {
+                full: false,
+                value: MaybeUninit::uninit(),
+            })
+        };
+        drop(guard);
+
+        Ok(mailbox)
+    }
+
+    /// Opens an existing mailbox at the given `base` pointer.
+    ///
+    /// # Safety
+    ///
+    /// The provided pointer **MUST** point to a mailbox created with
+    /// [`Mailbox::new`].
+    pub unsafe fn from_existing(base: *mut u8) -> Result<Self> {
+        Ok(Self {
+            mutex: unsafe { Mutex::from_existing(base)? },
+            condvar: unsafe { Condvar::from_existing(base.add(Self::CONDVAR_OFFSET))? },
+        })
+    }
+
+    /// Put a value in the mailbox, replacing any that wasn't taken yet, and
+    /// wake up every process waiting for it.
+    pub fn offer(&self, value: T) -> Result<()> {
+        let guard = self.lock()?;
+        unsafe {
+            guard.write(MailboxSlot {
+                full: true,
+                value: MaybeUninit::new(value),
+            })
+        };
+        drop(guard);
+
+        self.condvar.notify_all()
+    }
+
+    /// Take the value out of the mailbox, blocking until one is offered or the
+    /// timeout expires. Returns [`Error::TIMEDOUT`] if the timeout expired.
+    pub fn take(&self, timeout: Duration) -> Result<T> {
+        let deadline = now(libc::CLOCK_MONOTONIC).saturating_add(timeout);
+        let mut guard = self.lock()?;
+        loop {
+            let slot = unsafe { &mut **guard };
+            if slot.full {
+                slot.full = false;
+                return Ok(unsafe { slot.value.assume_init() });
+            }
+
+            let remaining = deadline.saturating_sub(now(libc::CLOCK_MONOTONIC));
+            if remaining.is_zero() {
+                return Err(Error::TIMEDOUT);
+            }
+            let (next, _timed_out) = self.condvar.wait_timeout(guard, remaining)?;
+            guard = Self::recover(next)?;
+        }
+    }
+}
+
+// Private.
+impl<T: Copy> Mailbox<T> {
+    fn lock(&self) -> Result<MutexGuard<'_, MailboxSlot<T>>> {
+        Self::recover(self.mutex.lock()?)
+    }
+
+    /// Empty the mailbox if the last process to hold its lock died, since it
+    /// may have been halfway through writing the value.
+    fn recover(
+        mut guard: MutexGuard<'_, MailboxSlot<T>>,
+    ) -> Result<MutexGuard<'_, MailboxSlot<T>>> {
+        if guard.owner_died() {
+            unsafe { (**guard).full = false };
+            guard.mark_consistent()?;
+        }
+
+        Ok(guard)
+    }
+}
+
+
+
+/// Get the current time on the given clock.
+fn now(clock: libc::clockid_t) -> Duration {
+    let mut now = core::mem::MaybeUninit::uninit();
+    // NOTE: This can't fail for the clocks used here.
+    let _ = unsafe { libc::clock_gettime(clock, now.as_mut_ptr()) };
+    let now: libc::timespec = unsafe { now.assume_init() };
+
+    Duration::new(now.tv_sec as u64, now.tv_nsec as u32)
+}
+
+/// Get the absolute time on the given clock that is `timeout` from now.
+fn deadline_after(clock: libc::clockid_t, timeout: Duration) -> libc::timespec {
+    let deadline = now(clock).saturating_add(timeout);
+    libc::timespec {
+        tv_sec: deadline.as_secs().min(libc::time_t::MAX as u64) as _,
+        tv_nsec: deadline.subsec_nanos() as _,
+    }
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    extern crate std;
+
+    use std::{prelude::v1::*, thread};
+
+    /// Memory for a mailbox of `u32`s, aligned for its mutex.
+    fn mailbox_memory() -> Box<[u64]> {
+        vec![0u64; Mailbox::<u32>::SIZE.div_ceil(size_of::<u64>())].into_boxed_slice()
+    }
+
+    #[test]
+    fn mailbox_handoff() {
+        let mut memory = mailbox_memory();
+        let base = memory.as_mut_ptr() as usize;
+        let mailbox = unsafe { Mailbox::<u32>::new(base as *mut u8) }.unwrap();
+        assert_eq!(
+            mailbox.take(Duration::from_millis(10)),
+            Err(Error::TIMEDOUT)
+        );
+
+        // The receiving process waits for the value to be offered.
+        let taking = thread::spawn(move || {
+            let mailbox = unsafe { Mailbox::<u32>::from_existing(base as *mut u8) }.unwrap();
+            mailbox.take(Duration::from_secs(5))
+        });
+        thread::sleep(Duration::from_millis(20));
+        mailbox.offer(7).unwrap();
+        assert_eq!(taking.join().unwrap(), Ok(7));
+
+        // Only the latest value is kept, and it can only be taken once.
+        mailbox.offer(8).unwrap();
+        mailbox.offer(9).unwrap();
+        assert_eq!(mailbox.take(Duration::ZERO), Ok(9));
+        assert_eq!(mailbox.take(Duration::ZERO), Err(Error::TIMEDOUT));
+    }
+
+    #[test]
+    fn mailbox_owner_death() {
+        let mut memory = mailbox_memory();
+        let base = memory.as_mut_ptr() as usize;
+        let mailbox = unsafe { Mailbox::<u32>::new(base as *mut u8) }.unwrap();
+
+        // A process dies halfway through offering a value, while holding the lock.
+        thread::spawn(move || {
+            let mailbox = unsafe { Mailbox::<u32>::from_existing(base as *mut u8) }.unwrap();
+            let guard = mailbox.mutex.lock().unwrap();
+            unsafe {
+                (*guard).write(MailboxSlot {
+                    full: true,
+                    value: MaybeUninit::uninit(),
+                })
+            };
+            core::mem::forget(guard);
+        })
+        .join()
+        .unwrap();
+
+        // The next lock recovers the mutex, and throws away the partial value.
+        let mut guard = mailbox.mutex.try_lock().unwrap().unwrap();
+        assert!(guard.owner_died());
+        guard.mark_consistent().unwrap();
+        assert!(!guard.owner_died());
+        unsafe { (**guard).full = false };
+        drop(guard);
+        assert!(!mailbox.mutex.lock().unwrap().owner_died());
+
+        // The mailbox does the same by itself.
+        thread::spawn(move || {
+            let mailbox = unsafe { Mailbox::<u32>::from_existing(base as *mut u8) }.unwrap();
+            let mut guard = mailbox.mutex.lock().unwrap();
+            unsafe { (**guard).full = true };
+            core::mem::forget(guard);
+        })
+        .join()
+        .unwrap();
+        assert_eq!(mailbox.take(Duration::ZERO), Err(Error::TIMEDOUT));
+        mailbox.offer(3).unwrap();
+        assert_eq!(mailbox.take(Duration::ZERO), Ok(3));
+    }
+}
@@ -1,6 +1,11 @@
 //! # Signal Handling
 
-use crate::{Error, Result, constants, file::File, traits};
+use crate::{
+    Error, Result, constants,
+    file::File,
+    proc::{Process, WaitStatus},
+    traits,
+};
 
 
 
@@ -174,6 +179,21 @@ impl SignalMask {
         }
     }
 
+    /// Create a mask of only the given signals.
+    pub fn from_signals(signals: &[Signal]) -> Self {
+        let mut mask = Self::empty();
+        for sig in signals {
+            mask.add(*sig);
+        }
+
+        mask
+    }
+
+    // https://www.man7.org/linux/man-pages/man3/sigismember.3.html
+    pub fn contains(&self, sig: Signal) -> bool {
+        unsafe { libc::sigismember(&self.raw as *const libc::sigset_t, sig as i32) == 1 }
+    }
+
     // https://www.man7.org/linux/man-pages/man3/sigaddset.3.html
     pub fn add(&mut self, sig: Signal) {
         unsafe { libc::sigaddset(&mut self.raw as *mut libc::sigset_t, sig as i32) };
@@ -200,6 +220,32 @@ impl SignalMask {
         }
     }
 
+    // https://www.man7.org/linux/man-pages/man2/sigprocmask.2.html
+    pub fn unblock(&self) -> Result<()> {
+        let res =
+            unsafe { libc::sigprocmask(constants::SIG_UNBLOCK, &self.raw, core::ptr::null_mut()) };
+        if res == -1 {
+            Err(Error::latest())
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Get the signals that are currently blocked for the calling thread.
+    // https://www.man7.org/linux/man-pages/man2/sigprocmask.2.html
+    pub fn blocked() -> Result<Self> {
+        let mut set = core::mem::MaybeUninit::uninit();
+        let res =
+            unsafe { libc::sigprocmask(constants::SIG_BLOCK, core::ptr::null(), set.as_mut_ptr()) };
+        if res == -1 {
+            Err(Error::latest())
+        } else {
+            Ok(Self {
+                raw: unsafe { set.assume_init() },
+            })
+        }
+    }
+
     // https://www.man7.org/linux/man-pages/man3/sigwait.3.html
     pub fn wait(&self) -> Result<Signal> {
         let mut sig_ptr = core::mem::MaybeUninit::uninit();
@@ -309,4 +355,107 @@ impl SignalFile {
             Ok(Self { fd: res })
         }
     }
+
+    /// Read the next pending signal, if there is one.
+    ///
+    /// The signals must be [blocked](SignalMask::block), otherwise they're
+    /// handled normally instead of being queued for this file. Returns `None`
+    /// if this file is [non-blocking](Self::open_non_blocking) and no signals
+    /// are pending.
+    // https://www.man7.org/linux/man-pages/man2/signalfd.2.html#DESCRIPTION
+    pub fn read(&self) -> Result<Option<SignalInfo>> {
+        let mut info = core::mem::MaybeUninit::<libc::signalfd_siginfo>::uninit();
+        let res = unsafe {
+            libc::read(
+                self.fd,
+                info.as_mut_ptr().cast(),
+                size_of::<libc::signalfd_siginfo>(),
+            )
+        };
+        match res {
+            -1 => match Error::latest() {
+                Error::AGAIN => Ok(None),
+                error => Err(error),
+            },
+            // The kernel only ever returns whole structures.
+            _ => Ok(Some(SignalInfo {
+                raw: unsafe { info.assume_init() },
+            })),
+        }
+    }
+}
+
+/// Information about a signal read from a [`SignalFile`].
+#[derive(Clone, Copy)]
+#[repr(transparent)]
+// https://www.man7.org/linux/man-pages/man2/signalfd.2.html#DESCRIPTION
+pub struct SignalInfo {
+    raw: libc::signalfd_siginfo,
+}
+
+impl core::fmt::Debug for SignalInfo {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("SignalInfo")
+            .field("signal", &self.signal())
+            .field("code", &self.code())
+            .field("sender", &self.raw.ssi_pid)
+            .field("sender_user", &self.raw.ssi_uid)
+            .finish()
+    }
+}
+
+impl SignalInfo {
+    /// The signal that was received, which is [`Error::INVAL`] for real-time
+    /// signals.
+    pub fn signal(&self) -> Result<Signal> {
+        Signal::from_raw(self.raw.ssi_signo as i32)
+    }
+
+    /// The signal-specific reason it was sent (`si_code`).
+    pub fn code(&self) -> i32 {
+        self.raw.ssi_code
+    }
+
+    /// The process that sent the signal, or the child process that changed
+    /// state for [`Signal::CHLD`].
+    pub fn sender(&self) -> Process {
+        Process {
+            id: self.raw.ssi_pid as i32,
+        }
+    }
+
+    /// The real user ID of the process that sent the signal.
+    pub fn sender_user(&self) -> u32 {
+        self.raw.ssi_uid
+    }
+
+    /// The new state of the child process, if this is a [`Signal::CHLD`].
+    ///
+    /// NOTE: The kernel merges pending signals of the same kind, so this may
+    ///       only describe one of several children. The children still have to
+    ///       be waited on to be reaped.
+    // https://www.man7.org/linux/man-pages/man2/sigaction.2.html#DESCRIPTION
+    pub fn child_status(&self) -> Option<Result<WaitStatus>> {
+        if self.signal() != Ok(Signal::CHLD) {
+            return None;
+        }
+
+        let proc = self.sender();
+        let status = self.raw.ssi_status;
+        Some(match self.raw.ssi_code {
+            libc::CLD_EXITED => Ok(WaitStatus::Exited { proc, code: status }),
+            libc::CLD_KILLED | libc::CLD_DUMPED => {
+                Signal::from_raw(status).map(|sig| WaitStatus::Signaled {
+                    proc,
+                    sig,
+                    core_dumped: self.raw.ssi_code == libc::CLD_DUMPED,
+                })
+            }
+            libc::CLD_TRAPPED | libc::CLD_STOPPED => {
+                Signal::from_raw(status).map(|sig| WaitStatus::Stopped { proc, sig })
+            }
+            libc::CLD_CONTINUED => Ok(WaitStatus::Continued { proc }),
+            _ => Err(Error::INVAL),
+        })
+    }
 }
@@ -0,0 +1,132 @@
+//! # Device Events
+
+use alloc::{collections::BTreeMap, string::String};
+
+use crate::{Error, Result, constants, file::File, raw, traits::AsFile};
+
+
+
+/// An event from the kernel about a device, such as one being added or removed,
+/// or a display being connected to a graphics card.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct Uevent {
+    /// What happened to the device, such as `add`, `remove`, or `change`.
+    pub action: String,
+    /// The device's path in `/sys`, without the `/sys` prefix.
+    pub devpath: String,
+    pub properties: BTreeMap<String, String>,
+}
+
+impl Uevent {
+    /// Parse an event in the kernel's format, which is a header followed by
+    /// `KEY=VALUE` properties, each terminated by a null byte:
+    ///
+    /// ```text
+    /// change@/devices/pci0000:00/0000:00:02.0/drm/card0\0ACTION=change\0SUBSYSTEM=drm\0HOTPLUG=1\0
+    /// ```
+    pub fn parse(bytes: &[u8]) -> Option<Self> {
+        let mut fields = bytes
+            .split(|byte| *byte == 0)
+            .filter(|field| !field.is_empty())
+            .map(|field| core::str::from_utf8(field).ok());
+        let (action, devpath) = fields.next()??.split_once('@')?;
+
+        let mut event = Self {
+            action: action.into(),
+            devpath: devpath.into(),
+            properties: BTreeMap::new(),
+        };
+        for field in fields {
+            if let Some((key, value)) = field.and_then(|field| field.split_once('=')) {
+                event.properties.insert(key.into(), value.into());
+            }
+        }
+
+        Some(event)
+    }
+
+    pub fn property(&self, key: &str) -> Option<&str> {
+        self.properties.get(key).map(String::as_str)
+    }
+
+    /// The kind of device the event is for, such as `drm` or `input`.
+    pub fn subsystem(&self) -> Option<&str> {
+        self.property("SUBSYSTEM")
+    }
+
+    /// Whether the event is for a graphics card whose connectors have changed,
+    /// such as because a display was plugged in or unplugged.
+    pub fn is_drm_hotplug(&self) -> bool {
+        self.subsystem() == Some("drm") && self.property("HOTPLUG") == Some("1")
+    }
+}
+
+
+
+/// A non-blocking socket that receives every [`Uevent`] the kernel sends.
+// https://www.man7.org/linux/man-pages/man7/netlink.7.html
+pub struct UeventSocket {
+    fd: i32,
+}
+
+impl AsFile for UeventSocket {
+    fn as_file(&self) -> File {
+        File { fd: self.fd }
+    }
+}
+
+impl UeventSocket {
+    // https://www.man7.org/linux/man-pages/man2/socket.2.html
+    // https://www.man7.org/linux/man-pages/man2/bind.2.html
+    pub fn open() -> Result<Self> {
+        let fd = unsafe {
+            libc::socket(
+                constants::AF_NETLINK,
+                constants::SOCK_DGRAM | constants::SOCK_NONBLOCK | constants::SOCK_CLOEXEC,
+                constants::NETLINK_KOBJECT_UEVENT,
+            )
+        };
+        if fd == -1 {
+            return Err(Error::latest());
+        }
+
+        let mut addr: libc::sockaddr_nl = unsafe { core::mem::zeroed() };
+        addr.nl_family = constants::AF_NETLINK as _;
+        addr.nl_groups = constants::UEVENT_KERNEL_GROUP;
+        let res = unsafe {
+            libc::bind(
+                fd,
+                (&raw const addr).cast(),
+                size_of::<libc::sockaddr_nl>() as _,
+            )
+        };
+        if res == -1 {
+            let error = Error::latest();
+            raw::close(fd);
+            return Err(error);
+        }
+
+        Ok(Self { fd })
+    }
+
+    /// Read the next event, or `None` if there are none waiting.
+    ///
+    /// Messages that aren't in the kernel's format are skipped.
+    pub fn read(&self) -> Result<Option<Uevent>> {
+        // Events are limited to a few kilobytes by the kernel.
+        let mut buf = [0u8; 8192];
+        loop {
+            let len = buf.len();
+            let res = raw::read(self.fd, &mut buf, len);
+            if res == -1 {
+                return match Error::latest() {
+                    Error::AGAIN => Ok(None),
+                    error => Err(error),
+                };
+            }
+            if let Some(event) = Uevent::parse(&buf[..res as usize]) {
+                return Ok(Some(event));
+            }
+        }
+    }
+}
@@ -0,0 +1,147 @@
+//! # Virtual Terminals
+
+use alloc::format;
+
+use crate::{
+    Error, Result, Signal, constants,
+    file::{self, File},
+    traits::AsFile,
+};
+
+
+
+/// One of the kernel's virtual terminals (VTs), which are switched between with
+/// `Ctrl+Alt+F1` through `Ctrl+Alt+F12`.
+///
+/// A graphical program that [takes over](Self::take_over) its VT is asked
+/// before the kernel switches away from it, so that it can stop using the
+/// graphics card and input devices, and is told when it's switched back to.
+// https://www.man7.org/linux/man-pages/man2/ioctl_vt.2.html
+// https://www.man7.org/linux/man-pages/man2/ioctl_console.2.html
+pub struct VirtualTerminal {
+    fd: i32,
+    number: u16,
+    /// The keyboard mode to restore, see [`Self::restore`].
+    keyboard_mode: i32,
+}
+
+impl AsFile for VirtualTerminal {
+    fn as_file(&self) -> File {
+        File { fd: self.fd }
+    }
+}
+
+/// `struct vt_mode` from `<linux/vt.h>`.
+#[repr(C)]
+struct VtMode {
+    mode: i8,
+    waitv: i8,
+    relsig: i16,
+    acqsig: i16,
+    frsig: i16,
+}
+
+/// `struct vt_stat` from `<linux/vt.h>`.
+#[repr(C)]
+struct VtStat {
+    v_active: u16,
+    v_signal: u16,
+    v_state: u16,
+}
+
+impl VirtualTerminal {
+    /// Open the VT that's currently shown.
+    pub fn open_active() -> Result<Self> {
+        let flags = file::O_RDWR | file::O_CLOEXEC | file::O_NOCTTY;
+        let console = File::open("/dev/tty0", flags)?;
+        let mut state = VtStat {
+            v_active: 0,
+            v_signal: 0,
+            v_state: 0,
+        };
+        let res = unsafe { libc::ioctl(console.fd, constants::VT_GETSTATE, &raw mut state) };
+        let error = (res == -1).then(Error::latest);
+        console.close()?;
+        if let Some(error) = error {
+            return Err(error);
+        }
+
+        let number = state.v_active;
+        let terminal = File::open(&*format!("/dev/tty{number}"), flags)?;
+        Ok(Self {
+            fd: terminal.fd,
+            number,
+            keyboard_mode: constants::K_OFF,
+        })
+    }
+
+    /// The VT's number, such as 1 for `/dev/tty1`.
+    pub fn number(&self) -> u16 {
+        self.number
+    }
+
+    /// Stop the kernel from drawing the console or reading the keyboard on this
+    /// VT, and have it send `release` before switching away from it and
+    /// `acquire` after switching back to it.
+    ///
+    /// The kernel waits for the switch to be [allowed](Self::allow_release)
+    /// after sending `release`, and the new VT should be
+    /// [acknowledged](Self::acknowledge_acquire) after `acquire`.
+    pub fn take_over(&mut self, release: Signal, acquire: Signal) -> Result<()> {
+        let res =
+            unsafe { libc::ioctl(self.fd, constants::KDGKBMODE, &raw mut self.keyboard_mode) };
+        if res == -1 {
+            return Err(Error::latest());
+        }
+        self.ioctl(constants::KDSKBMODE, constants::K_OFF)?;
+        self.ioctl(constants::KDSETMODE, constants::KD_GRAPHICS)?;
+        self.set_mode(constants::VT_PROCESS, release as i16, acquire as i16)
+    }
+
+    /// Give the VT back to the kernel, undoing [`Self::take_over`].
+    pub fn restore(&self) -> Result<()> {
+        self.set_mode(constants::VT_AUTO, 0, 0)?;
+        self.ioctl(constants::KDSETMODE, constants::KD_TEXT)?;
+        self.ioctl(constants::KDSKBMODE, self.keyboard_mode)
+    }
+
+    /// Let the kernel switch away from the VT, after being asked to.
+    pub fn allow_release(&self) -> Result<()> {
+        self.ioctl(constants::VT_RELDISP, 1)
+    }
+
+    /// Tell the kernel that the VT has been taken back after switching to it.
+    pub fn acknowledge_acquire(&self) -> Result<()> {
+        self.ioctl(constants::VT_RELDISP, constants::VT_ACKACQ)
+    }
+
+    /// Switch to another VT, which happens once this one is released.
+    pub fn activate(&self, number: u16) -> Result<()> {
+        self.ioctl(constants::VT_ACTIVATE, number as i32)
+    }
+
+    fn set_mode(&self, mode: i8, relsig: i16, acqsig: i16) -> Result<()> {
+        let mode = VtMode {
+            mode,
+            waitv: 0,
+            relsig,
+            acqsig,
+            frsig: 0,
+        };
+        let res = unsafe { libc::ioctl(self.fd, constants::VT_SETMODE, &raw const mode) };
+        if res == -1 {
+            Err(Error::latest())
+        } else {
+            Ok(())
+        }
+    }
+
+    fn ioctl(&self, request: core::ffi::c_ulong, arg: i32) -> Result<()> {
+        let res = unsafe { libc::ioctl(self.fd, request, arg as core::ffi::c_ulong) };
+        if res == -1 {
+            Err(Error::latest())
+        } else {
+            Ok(())
+        }
+    }
+}
@@ -0,0 +1,435 @@
+//! # File Watching
+
+use core::{ffi::CStr, time::Duration};
+
+use alloc::{
+    borrow::ToOwned,
+    collections::{BTreeMap, BTreeSet},
+    ffi::CString,
+    vec::Vec,
+};
+
+use crate::{Error, Result, c_str::AsCStr, constants, file::File, raw, traits::AsFile};
+
+
+
+/// A set of filesystem events that can be watched for with an [`Inotify`].
+// https://www.man7.org/linux/man-pages/man7/inotify.7.html#DESCRIPTION
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct WatchMask(u32);
+
+impl WatchMask {
+    pub const NONE: Self = Self(0);
+    pub const ACCESS: Self = Self(constants::IN_ACCESS);
+    pub const MODIFY: Self = Self(constants::IN_MODIFY);
+    pub const ATTRIB: Self = Self(constants::IN_ATTRIB);
+    pub const CLOSE_WRITE: Self = Self(constants::IN_CLOSE_WRITE);
+    pub const CLOSE_NOWRITE: Self = Self(constants::IN_CLOSE_NOWRITE);
+    pub const OPEN: Self = Self(constants::IN_OPEN);
+    pub const MOVED_FROM: Self = Self(constants::IN_MOVED_FROM);
+    pub const MOVED_TO: Self = Self(constants::IN_MOVED_TO);
+    pub const CREATE: Self = Self(constants::IN_CREATE);
+    pub const DELETE: Self = Self(constants::IN_DELETE);
+    pub const DELETE_SELF: Self = Self(constants::IN_DELETE_SELF);
+    pub const MOVE_SELF: Self = Self(constants::IN_MOVE_SELF);
+    /// Only watch the path if it's a directory.
+    pub const ONLY_DIRECTORY: Self = Self(constants::IN_ONLYDIR);
+    /// Don't follow the path if it's a symbolic link.
+    pub const DONT_FOLLOW: Self = Self(constants::IN_DONT_FOLLOW);
+
+    /// Only set in events, for a watch that was removed.
+    pub const IGNORED: Self = Self(constants::IN_IGNORED);
+    /// Only set in events, for a file that is a directory.
+    pub const IS_DIRECTORY: Self = Self(constants::IN_ISDIR);
+    /// Only set in events, for when events were dropped because too many were
+    /// waiting to be read.
+    pub const QUEUE_OVERFLOW: Self = Self(constants::IN_Q_OVERFLOW);
+
+    /// Every event that changes the contents of a directory or its files.
+    ///
+    /// `MODIFY` is left out, since `CLOSE_WRITE` happens once the file is done
+    /// being written to.
+    pub const CHANGES: Self = Self(
+        constants::IN_CLOSE_WRITE
+            | constants::IN_MOVED_FROM
+            | constants::IN_MOVED_TO
+            | constants::IN_CREATE
+            | constants::IN_DELETE,
+    );
+
+    pub const fn contains(self, other: Self) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    pub const fn intersects(self, other: Self) -> bool {
+        self.0 & other.0 != 0
+    }
+}
+
+impl core::ops::BitOr for WatchMask {
+    type Output = Self;
+
+    fn bitor(self, rhs: Self) -> Self::Output {
+        Self(self.0 | rhs.0)
+    }
+}
+
+/// A watched path, see [`Inotify::add_watch`].
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct Watch(i32);
+
+/// A filesystem event read from an [`Inotify`].
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct WatchEvent {
+    pub watch: Watch,
+    pub mask: WatchMask,
+    /// Connects the `MOVED_FROM` and `MOVED_TO` events of a single rename.
+    pub cookie: u32,
+    /// The name of the file in the watched directory, if the event is for a
+    /// file in it rather than the watched path itself.
+    pub name: Option<CString>,
+}
+
+
+
+// https://www.man7.org/linux/man-pages/man7/inotify.7.html
+pub struct Inotify {
+    fd: i32,
+}
+
+impl AsFile for Inotify {
+    fn as_file(&self) -> File {
+        File { fd: self.fd }
+    }
+}
+
+impl Inotify {
+    /// Create a non-blocking inotify instance without any watches.
+    // https://www.man7.org/linux/man-pages/man2/inotify_init.2.html
+    pub fn create() -> Result<Self> {
+        let res = unsafe { libc::inotify_init1(constants::IN_NONBLOCK | constants::IN_CLOEXEC) };
+        if res == -1 {
+            Err(Error::latest())
+        } else {
+            Ok(Self { fd: res })
+        }
+    }
+
+    /// Start watching the given path for the given events.
+    ///
+    /// Watching the same path again replaces its mask and returns the same
+    /// [`Watch`].
+    // https://www.man7.org/linux/man-pages/man2/inotify_add_watch.2.html
+    pub fn add_watch<P: AsCStr + ?Sized>(&self, path: &P, mask: WatchMask) -> Result<Watch> {
+        let res = path
+            .map_cstr(|path| unsafe { libc::inotify_add_watch(self.fd, path.as_ptr(), mask.0) })?;
+        if res == -1 {
+            Err(Error::latest())
+        } else {
+            Ok(Watch(res))
+        }
+    }
+
+    // https://www.man7.org/linux/man-pages/man2/inotify_rm_watch.2.html
+    pub fn remove_watch(&self, watch: Watch) -> Result<()> {
+        let res = unsafe { libc::inotify_rm_watch(self.fd, watch.0) };
+        if res == -1 {
+            Err(Error::latest())
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Read every waiting event into `events`, returning how many were read.
+    pub fn read_events(&self, events: &mut Vec<WatchEvent>) -> Result<usize> {
+        const HEADER_SIZE: usize = size_of::<libc::inotify_event>();
+
+        // Large enough for at least one event with the longest possible name.
+        let mut buf = [0u8; 4096];
+        let mut count = 0;
+        loop {
+            let len = buf.len();
+            let res = raw::read(self.fd, &mut buf, len);
+            if res == -1 {
+                return match Error::latest() {
+                    Error::AGAIN => Ok(count),
+                    error => Err(error),
+                };
+            }
+
+            let mut offset = 0;
+            while offset + HEADER_SIZE <= res as usize {
+                // SAFETY: The kernel only ever returns whole events, and the header fits in
+                //         the buffer.
+                let header = unsafe {
+                    core::ptr::read_unaligned(
+                        buf.as_ptr().add(offset).cast::<libc::inotify_event>(),
+                    )
+                };
+                let name_start = offset + HEADER_SIZE;
+                let name_end = name_start + header.len as usize;
+
+                // The name is padded with nul bytes, and is missing for events on the
+                // watched path itself.
+                let name = CStr::from_bytes_until_nul(&buf[name_start..name_end])
+                    .ok()
+                    .filter(|name| !name.is_empty())
+                    .map(CStr::to_owned);
+
+                events.push(WatchEvent {
+                    watch: Watch(header.wd),
+                    mask: WatchMask(header.mask),
+                    cookie: header.cookie,
+                    name,
+                });
+                count += 1;
+                offset = name_end;
+            }
+        }
+    }
+}
+
+
+
+/// Watches files and directories for changes, reporting each changed path once
+/// it has stopped changing for a while.
+///
+/// Files are watched through their parent directory, so that editors that save
+/// by replacing the file (rather than writing to it) are handled correctly.
+///
+/// [`Watcher::process`] should be called when the watcher becomes readable
+/// (see [`EventPoll`](crate::epoll::EventPoll)), and [`Watcher::take_changes`]
+/// once [`Watcher::next_change_in`] has passed.
+pub struct Watcher {
+    inotify: Inotify,
+    targets: BTreeMap<Watch, Target>,
+    debounce: Duration,
+    /// Changed paths, and when they last changed.
+    pending: BTreeMap<CString, Duration>,
+    events: Vec<WatchEvent>,
+}
+
+struct Target {
+    path: CString,
+    /// The names of the watched files in the directory, or `None` if every
+    /// file is watched.
+    files: Option<BTreeSet<CString>>,
+    recursive: bool,
+}
+
+impl AsFile for Watcher {
+    fn as_file(&self) -> File {
+        self.inotify.as_file()
+    }
+}
+
+impl Watcher {
+    /// Create a watcher that reports changes once a path hasn't changed for
+    /// `debounce`.
+    pub fn new(debounce: Duration) -> Result<Self> {
+        Ok(Self {
+            inotify: Inotify::create()?,
+            targets: BTreeMap::new(),
+            debounce,
+            pending: BTreeMap::new(),
+            events: Vec::new(),
+        })
+    }
+
+    /// Watch a single file for changes, including it being created, deleted,
+    /// or replaced.
+    pub fn watch_file<P: AsCStr + ?Sized>(&mut self, path: &P) -> Result<()> {
+        let path = path.map_cstr(CStr::to_owned)?;
+        let bytes = path.to_bytes();
+        let (dir, name) = match bytes.iter().rposition(|byte| *byte == b'/') {
+            Some(0) => (&b"/"[..], &bytes[1..]),
+            Some(index) => (&bytes[..index], &bytes[index + 1..]),
+            None => (&b"."[..], bytes),
+        };
+        if name.is_empty() {
+            return Err(Error::ISDIR);
+        }
+        // SAFETY: Both are parts of a `CStr`, so neither contains a nul byte.
+        let (dir, name) = unsafe {
+            (
+                CString::from_vec_unchecked(dir.to_vec()),
+                CString::from_vec_unchecked(name.to_vec()),
+            )
+        };
+
+        let watch = self.inotify.add_watch(
+            dir.as_c_str(),
+            WatchMask::CHANGES | WatchMask::ONLY_DIRECTORY,
+        )?;
+        let target = self.targets.entry(watch).or_insert_with(|| Target {
+            path: dir,
+            files: Some(BTreeSet::new()),
+            recursive: false,
+        });
+        if let Some(files) = &mut target.files {
+            files.insert(name);
+        }
+
+        Ok(())
+    }
+
+    /// Watch every file in a directory for changes, including those in its
+    /// subdirectories if `recursive` is true.
+    ///
+    /// Subdirectories created after this is called are watched as well.
+    pub fn watch_directory<P: AsCStr + ?Sized>(&mut self, path: &P, recursive: bool) -> Result<()> {
+        let path = path.map_cstr(CStr::to_owned)?;
+
+        let watch = self.inotify.add_watch(
+            path.as_c_str(),
+            WatchMask::CHANGES | WatchMask::ONLY_DIRECTORY,
+        )?;
+        let target = self.targets.entry(watch).or_insert_with(|| Target {
+            path: path.clone(),
+            files: None,
+            recursive,
+        });
+        target.files = None;
+        target.recursive |= recursive;
+
+        if recursive {
+            for subdir in subdirectories(&path)? {
+                // The directory may have been removed since it was listed.
+                match self.watch_directory(subdir.as_c_str(), true) {
+                    Ok(()) | Err(Error::NOENT) => {}
+                    Err(error) => return Err(error),
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Stop watching everything.
+    pub fn clear(&mut self) -> Result<()> {
+        for watch in core::mem::take(&mut self.targets).into_keys() {
+            self.inotify.remove_watch(watch)?;
+        }
+        self.pending.clear();
+
+        Ok(())
+    }
+
+    /// Read every waiting event, recording the paths that changed.
+    pub fn process(&mut self) -> Result<()> {
+        self.inotify.read_events(&mut self.events)?;
+
+        let now = monotonic_now();
+        for event in core::mem::take(&mut self.events) {
+            if event.mask.contains(WatchMask::IGNORED) {
+                self.targets.remove(&event.watch);
+                continue;
+            }
+            let (Some(target), Some(name)) = (self.targets.get(&event.watch), event.name) else {
+                continue;
+            };
+            if target
+                .files
+                .as_ref()
+                .is_some_and(|files| !files.contains(&name))
+            {
+                continue;
+            }
+
+            let path = join_path(&target.path, &name);
+            if event.mask.contains(WatchMask::IS_DIRECTORY) {
+                if target.recursive
+                    && event
+                        .mask
+                        .intersects(WatchMask::CREATE | WatchMask::MOVED_TO)
+                {
+                    match self.watch_directory(path.as_c_str(), true) {
+                        Ok(()) | Err(Error::NOENT) => {}
+                        Err(error) => return Err(error),
+                    }
+                }
+            } else {
+                self.pending.insert(path, now);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Take every path that changed, but hasn't changed again for the debounce
+    /// duration.
+    pub fn take_changes(&mut self) -> Vec<CString> {
+        let now = monotonic_now();
+        let mut changes = Vec::new();
+        self.pending.retain(|path, changed_at| {
+            if now.saturating_sub(*changed_at) >= self.debounce {
+                changes.push(path.clone());
+                false
+            } else {
+                true
+            }
+        });
+
+        changes
+    }
+
+    /// Get the time until the next changed path is reported by
+    /// [`Watcher::take_changes`], if any paths have changed.
+    pub fn next_change_in(&self) -> Option<Duration> {
+        let now = monotonic_now();
+        self.pending
+            .values()
+            .map(|changed_at| (*changed_at + self.debounce).saturating_sub(now))
+            .min()
+    }
+}
+
+fn join_path(dir: &CStr, name: &CStr) -> CString {
+    let mut bytes = dir.to_bytes().to_vec();
+    if !bytes.ends_with(b"/") {
+        bytes.push(b'/');
+    }
+    bytes.extend_from_slice(name.to_bytes());
+
+    // SAFETY: Neither part contains a nul byte.
+    unsafe { CString::from_vec_unchecked(bytes) }
+}
+
+/// Get the paths of every directory in the given directory.
+// https://www.man7.org/linux/man-pages/man3/readdir.3.html
+fn subdirectories(path: &CStr) -> Result<Vec<CString>> {
+    let dir = unsafe { libc::opendir(path.as_ptr()) };
+    if dir.is_null() {
+        return Err(Error::latest());
+    }
+
+    let mut subdirs = Vec::new();
+    loop {
+        let entry = unsafe { libc::readdir(dir) };
+        if entry.is_null() {
+            break;
+        }
+        // NOTE: Filesystems that don't report entry types (`DT_UNKNOWN`) aren't
+        //       searched, which none of ours are.
+        if unsafe { (*entry).d_type } != libc::DT_DIR {
+            continue;
+        }
+        let name = unsafe { CStr::from_ptr((*entry).d_name.as_ptr()) };
+        if name == c"." || name == c".." {
+            continue;
+        }
+        subdirs.push(join_path(path, name));
+    }
+    unsafe { libc::closedir(dir) };
+
+    Ok(subdirs)
+}
+
+fn monotonic_now() -> Duration {
+    let mut time = core::mem::MaybeUninit::uninit();
+    // NOTE: This can't fail for the monotonic clock.
+    let _ = unsafe { libc::clock_gettime(constants::CLOCK_MONOTONIC, time.as_mut_ptr()) };
+    let time: libc::timespec = unsafe { time.assume_init() };
+
+    Duration::new(time.tv_sec as u64, time.tv_nsec as u32)
+}
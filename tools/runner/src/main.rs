@@ -1,13 +1,15 @@
 //! # Runner
 
 use {
-    anyhow::{Result, bail},
+    anyhow::{Error, Result, bail},
+    elf::{ElfFile, SHN_LORESERVE},
     std::{
         env::{current_dir, set_current_dir},
-        fs::{create_dir_all, read_dir},
+        fs::{create_dir_all, read, read_dir, write},
         path::{Path, PathBuf},
         process::{Command, ExitStatus, Stdio},
     },
+    symbol_index::{IndexedObject, SymbolIndex},
 };
 
 const OBJECT_SOURCES: &[(&str, &str)] = &[
@@ -118,6 +120,10 @@ fn main() -> Result<()> {
         }
     }
 
+    println!("Indexing symbols...");
+
+    write_symbol_index(&kernel_dir.join("esp"))?;
+
     println!("Building kernel...");
 
     let cargo_exit_status = Command::new("cargo")
@@ -198,6 +204,75 @@ fn build_crate_object(workspace_dir: &Path, dir: &str, name: &str) -> Result<Exi
         .status()?)
 }
 
+/// Write the [symbol index](symbol_index) the kernel's loader uses to find
+/// the object that defines a symbol, for every object and archive in `dir`.
+fn write_symbol_index(dir: &Path) -> Result<()> {
+    let mut index = SymbolIndex::default();
+
+    let mut paths = read_dir(dir)?
+        .map(|entry| Ok(entry?.path()))
+        .collect::<Result<Vec<_>>>()?;
+    paths.sort();
+    for path in paths {
+        let Some(file_name) = path.file_name().and_then(|name| name.to_str()) else {
+            continue;
+        };
+        let size = path.metadata()?.len() as usize;
+
+        if let Some(name) = file_name.strip_suffix(".o") {
+            let bytes = read(&path)?;
+            let object = index.add_object(IndexedObject {
+                name: name.to_string(),
+                file: file_name.to_string(),
+                size,
+            });
+            index_object_symbols(&mut index, object, &bytes)?;
+        } else if file_name.ends_with(".rlib") || file_name.ends_with(".a") {
+            let bytes = read(&path)?;
+            let archive = ar::Archive::new(&bytes).map_err(Error::msg)?;
+            for member in archive.members() {
+                let member = member.map_err(Error::msg)?;
+                if !member.name.ends_with(".o") {
+                    continue;
+                }
+                let object = index.add_object(IndexedObject {
+                    name: format!("{file_name}({})", member.name),
+                    file: file_name.to_string(),
+                    size,
+                });
+                index_object_symbols(&mut index, object, member.data)?;
+            }
+        }
+    }
+
+    println!(
+        "\tIndexed {} symbol(s) in {} object(s)",
+        index.symbol_count(),
+        index.objects().len(),
+    );
+
+    Ok(write(dir.join(symbol_index::FILE_NAME), index.to_string())?)
+}
+
+fn index_object_symbols(index: &mut SymbolIndex, object: usize, bytes: &[u8]) -> Result<()> {
+    let elf_file = ElfFile::new(bytes).map_err(Error::msg)?;
+    for entry in elf_file.get_symbol_table().map_err(Error::msg)? {
+        // Section indices from `SHN_LORESERVE` up have special meanings, like
+        // absolute and common symbols.
+        if !entry.is_global() || entry.is_undefined() || entry.shndx() >= SHN_LORESERVE {
+            continue;
+        }
+        let name = entry.get_name(&elf_file).map_err(Error::msg)?;
+        index.add_symbol(
+            object,
+            &rustc_demangle::demangle(name).to_string(),
+            entry.shndx() as usize,
+        );
+    }
+
+    Ok(())
+}
+
 fn crate_name_to_object_name(crate_name: &str) -> String {
     crate_name.replace('-', "_")
 }